@@ -0,0 +1,58 @@
+//! benchmarks of row extraction and end-to-end collection, using the mock rpc source
+
+use clap::Parser;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use cryo_cli::{parse_opts, Args};
+
+const DATATYPES: [&str; 4] = ["blocks", "transactions", "logs", "traces"];
+
+const N_BLOCKS: u64 = 100;
+
+fn args(datatype: &str, dir: &str) -> Args {
+    let blocks = format!("0:{}", N_BLOCKS);
+    let args =
+        ["cryo", datatype, "--mock-rpc", "-b", &blocks, "-o", dir, "--overwrite", "--no-verbose"];
+    Args::parse_from(args)
+}
+
+fn runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Runtime::new().expect("tokio runtime")
+}
+
+/// collection of rows into dataframes, without writing files
+fn row_extraction(c: &mut Criterion) {
+    let runtime = runtime();
+    let dir = std::env::temp_dir().join("cryo_benches").to_string_lossy().to_string();
+    let mut group = c.benchmark_group("row_extraction");
+    group.throughput(Throughput::Elements(N_BLOCKS));
+    for datatype in DATATYPES {
+        let (query, source, _sink) =
+            runtime.block_on(parse_opts(&args(datatype, &dir))).expect("parse options");
+        group.bench_function(BenchmarkId::from_parameter(datatype), |b| {
+            b.to_async(&runtime).iter(|| async {
+                cryo_freeze::collect(query.clone().into(), source.clone()).await.expect("collect")
+            })
+        });
+    }
+    group.finish();
+}
+
+/// parsing, collection, and writing of files, as run by the cli
+fn end_to_end(c: &mut Criterion) {
+    let runtime = runtime();
+    let dir = std::env::temp_dir().join("cryo_benches").to_string_lossy().to_string();
+    let mut group = c.benchmark_group("end_to_end");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(N_BLOCKS));
+    for datatype in DATATYPES {
+        group.bench_function(BenchmarkId::from_parameter(datatype), |b| {
+            b.to_async(&runtime)
+                .iter(|| async { cryo_cli::run(args(datatype, &dir)).await.expect("run") })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, row_extraction, end_to_end);
+criterion_main!(benches);
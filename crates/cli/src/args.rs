@@ -1,12 +1,13 @@
 use clap::Parser;
 use color_print::cstr;
+use serde::{Deserialize, Serialize};
 
 /// Command line arguments
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone, Serialize, Deserialize)]
 #[command(name = "cryo", author, version, about = get_about_str(), long_about = None, styles=get_styles(), after_help=get_after_str())]
 pub struct Args {
     /// datatype to collect
-    #[arg(required = true, help=get_datatype_help(), num_args(1..))]
+    #[arg(required_unless_present_any = ["config", "profile"], help=get_datatype_help(), num_args(1..))]
     pub datatype: Vec<String>,
 
     /// Block numbers, see syntax below
@@ -22,9 +23,38 @@ pub struct Args {
     )]
     pub txs: Option<Vec<String>>,
 
+    /// Collect blocks of this recent duration, e.g. 30m, 24h, or 7d,
+    /// found by searching block timestamps
+    #[arg(
+        long,
+        value_name = "DURATION",
+        help_heading = "Content Options",
+        verbatim_doc_comment,
+        conflicts_with_all = ["blocks", "txs", "chunks"]
+    )]
+    pub last: Option<String>,
+
+    /// Csv file of start_block,end_block rows to use as chunks,
+    /// instead of splitting --blocks into chunks
+    #[arg(
+        long,
+        value_name = "FILE",
+        help_heading = "Content Options",
+        verbatim_doc_comment,
+        conflicts_with_all = ["blocks", "txs", "align", "n_chunks", "partition_by"]
+    )]
+    pub chunks: Option<String>,
+
     /// Align block chunk boundaries to regular intervals,
-    /// e.g. (1000, 2000, 3000) instead of (1106, 2106, 3106)
-    #[arg(short, long, help_heading = "Content Options", verbatim_doc_comment)]
+    /// e.g. (1000, 2000, 3000) instead of (1106, 2106, 3106),
+    /// skipping partial chunks at the edges of the range
+    #[arg(
+        short,
+        long,
+        help_heading = "Content Options",
+        verbatim_doc_comment,
+        conflicts_with = "n_chunks"
+    )]
     pub align: bool,
 
     /// Reorg buffer, save blocks only when they are this old,
@@ -56,6 +86,15 @@ pub struct Args {
     #[arg(long, help_heading = "Content Options")]
     pub hex: bool,
 
+    /// Never output lossy float columns, values are exact decimal strings or integers
+    #[arg(long, help_heading = "Content Options")]
+    pub exact: bool,
+
+    /// Store uint32 columns as uint64, for chains whose block numbers or gas
+    /// values exceed the uint32 range
+    #[arg(long, verbatim_doc_comment, help_heading = "Content Options")]
+    pub wide_integers: bool,
+
     /// Columns(s) to sort by, `none` to disable sorting
     #[arg(short, long, num_args(0..), help_heading="Content Options")]
     pub sort: Option<Vec<String>>,
@@ -68,10 +107,114 @@ pub struct Args {
     #[arg(long, help_heading = "Source Options")]
     pub network_name: Option<String>,
 
+    /// Log each rpc request and truncated response to stderr or to PATH
+    #[arg(
+        long,
+        value_name = "PATH",
+        num_args(0..=1),
+        default_missing_value = "-",
+        help_heading = "Source Options"
+    )]
+    pub debug_rpc: Option<String>,
+
+    /// Record all rpc requests and responses into a fixture file
+    #[arg(long, value_name = "PATH", help_heading = "Source Options")]
+    pub record_rpc: Option<String>,
+
+    /// Serve rpc requests from a fixture file made with --record-rpc
+    #[arg(
+        long,
+        value_name = "PATH",
+        help_heading = "Source Options",
+        conflicts_with_all = ["rpc", "record_rpc"]
+    )]
+    pub replay_rpc: Option<String>,
+
+    /// Serve rpc requests from synthetic deterministic chain data
+    #[arg(long, help_heading = "Source Options", conflicts_with_all = ["rpc", "replay_rpc"])]
+    pub mock_rpc: bool,
+
+    /// Sourcify server used for the is_verified column [default: https://sourcify.dev/server]
+    #[arg(long, value_name = "URL", help_heading = "Source Options")]
+    pub sourcify_url: Option<String>,
+
+    /// Beacon node api used for consensus layer datasets
+    #[arg(long, value_name = "URL", help_heading = "Source Options")]
+    pub beacon_url: Option<String>,
+
+    /// Read the genesis dataset from a genesis.json instead of a state dump of block 0
+    #[arg(long, value_name = "FILE", help_heading = "Source Options")]
+    pub genesis_file: Option<String>,
+
+    /// Derive log-based datasets, e.g. erc20_transfers, from parquet logs in DIR instead of rpc
+    #[arg(long, value_name = "DIR", help_heading = "Source Options")]
+    pub logs_dir: Option<String>,
+
+    /// Threads reading --logs-dir files of each chunk [default: number of cpus]
+    #[arg(long, value_name = "N", help_heading = "Source Options")]
+    pub local_threads: Option<usize>,
+
+    /// Cap on bytes of --logs-dir files being read at once, e.g. 4GB
+    #[arg(long, value_name = "SIZE", help_heading = "Source Options")]
+    pub local_max_memory: Option<String>,
+
+    /// Tolerate nonstandard rpc responses of exotic evm chains, e.g. missing fields, numbers
+    /// instead of hex strings, or null logsBloom, instead of failing chunks
+    #[arg(long, help_heading = "Source Options")]
+    pub lenient: bool,
+
+    /// Apply known limits of a provider: eth_getLogs range, concurrency, ratelimit, unsupported
+    /// methods, and credit costs, one of alchemy, infura, quicknode, llamanodes, local-geth, or
+    /// local-reth, other options take precedence
+    #[arg(long, value_name = "NAME", help_heading = "Source Options")]
+    pub provider_profile: Option<String>,
+
+    /// Rpc dialect of the chain, one of evm, zksync, or starknet [default: evm]
+    #[arg(long, value_name = "VM", help_heading = "Source Options")]
+    pub vm: Option<String>,
+
     /// Ratelimit on requests per second
     #[arg(short('l'), long, value_name = "limit", help_heading = "Acquisition Options")]
     pub requests_per_second: Option<u32>,
 
+    /// Ratelimits of rpc methods, e.g. trace=5 debug_trace*=2 default=100,
+    /// default applies to methods without their own limit
+    #[arg(long, value_name = "METHOD=LIMIT", num_args(1..), verbatim_doc_comment, help_heading = "Acquisition Options")]
+    pub rps: Option<Vec<String>>,
+
+    /// Max requests sent at once under each ratelimit [default: the ratelimit]
+    #[arg(long, value_name = "N", help_heading = "Acquisition Options")]
+    pub rate_burst: Option<u32>,
+
+    /// Random delay of up to MS milliseconds added to rate limited requests
+    #[arg(long, value_name = "MS", help_heading = "Acquisition Options")]
+    pub rate_jitter: Option<u64>,
+
+    /// Share --requests-per-second with other cryo processes using the
+    /// same PATH [default PATH: a temp file named after the rpc url]
+    #[arg(long, value_name = "PATH", num_args(0..=1), default_missing_value = "", verbatim_doc_comment, help_heading = "Acquisition Options")]
+    pub shared_rate_limit: Option<String>,
+
+    /// Credits charged per request of rpc methods, e.g. eth_getLogs=75
+    /// trace=24 default=26, added to those of --provider-profile
+    #[arg(long, value_name = "METHOD=COST", num_args(1..), verbatim_doc_comment, help_heading = "Acquisition Options")]
+    pub credit_costs: Option<Vec<String>>,
+
+    /// Stop sending requests once this many credits are spent, failing
+    /// the remaining chunks so a later run can resume them
+    #[arg(long, value_name = "N", verbatim_doc_comment, help_heading = "Acquisition Options")]
+    pub max_credits: Option<u64>,
+
+    /// Do not add the requests and credits of this run to the usage
+    /// ledger shown by `cryo usage`
+    #[arg(long, verbatim_doc_comment, help_heading = "Acquisition Options")]
+    pub no_usage_ledger: bool,
+
+    /// Add the usage of runs to the usage ledger, set by the cryo binary so that library and
+    /// python runs do not write to the home directory
+    #[arg(skip)]
+    pub record_usage: bool,
+
     /// Global number of concurrent requests
     #[arg(long, value_name = "M", help_heading = "Acquisition Options")]
     pub max_concurrent_requests: Option<u64>,
@@ -80,14 +223,133 @@ pub struct Args {
     #[arg(long, value_name = "M", help_heading = "Acquisition Options")]
     pub max_concurrent_chunks: Option<u64>,
 
+    /// Pause requests after N consecutive rpc failures
+    #[arg(long, value_name = "N", help_heading = "Acquisition Options")]
+    pub circuit_breaker: Option<u32>,
+
+    /// Seconds to pause before probing a failing rpc [default: 10]
+    #[arg(long, value_name = "SECONDS", help_heading = "Acquisition Options")]
+    pub circuit_cooldown: Option<f64>,
+
+    /// Retries of rate limited requests, waiting for the Retry-After
+    /// header of responses or an exponential backoff [default: 5]
+    #[arg(long, value_name = "N", verbatim_doc_comment, help_heading = "Acquisition Options")]
+    pub max_retries: Option<u32>,
+
+    /// Longest Retry-After honored, requests told to wait longer fail,
+    /// 0 ignores the header and uses exponential backoff [default: 60]
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        verbatim_doc_comment,
+        help_heading = "Acquisition Options"
+    )]
+    pub max_retry_after: Option<f64>,
+
+    /// Abandon a chunk that takes longer than this to collect, logging it
+    /// to cryo_failed_chunks.jsonl and continuing with other chunks
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        verbatim_doc_comment,
+        help_heading = "Acquisition Options"
+    )]
+    pub chunk_timeout: Option<f64>,
+
+    /// Collect PERCENT of chunks a second time and report chunks whose
+    /// responses differ, e.g. from load-balanced nodes on different forks
+    #[arg(
+        long,
+        value_name = "PERCENT",
+        verbatim_doc_comment,
+        help_heading = "Acquisition Options"
+    )]
+    pub audit_determinism: Option<f64>,
+
+    /// When a chunk fails in an interactive terminal, ask whether to retry
+    /// it, skip it, abort the run, or switch to another rpc url
+    #[arg(long, verbatim_doc_comment, help_heading = "Acquisition Options")]
+    pub prompt_on_failure: bool,
+
+    /// After collecting the requested blocks, keep collecting new blocks
+    /// as they reach --reorg-buffer confirmations, until interrupted
+    #[arg(long, verbatim_doc_comment, help_heading = "Acquisition Options")]
+    pub follow: bool,
+
+    /// Seconds between polls of the chain head when following [default: 12, or 1 with --tip]
+    #[arg(long, value_name = "SECONDS", help_heading = "Acquisition Options")]
+    pub follow_interval: Option<f64>,
+
+    /// Concurrent requests for following, separate from the backfill [default: 10]
+    #[arg(long, value_name = "M", help_heading = "Acquisition Options")]
+    pub follow_max_concurrent_requests: Option<u64>,
+
+    /// Websocket rpc url for following the chain head with a newHeads
+    /// subscription, polling when it is not given or the subscription ends
+    #[arg(long, value_name = "URL", verbatim_doc_comment, help_heading = "Acquisition Options")]
+    pub follow_ws_url: Option<String>,
+
+    /// Alert when followed blocks fall more than N blocks behind the confirmed chain head
+    #[arg(long, value_name = "N", help_heading = "Acquisition Options")]
+    pub max_block_lag: Option<u64>,
+
+    /// Alert when the provider's chain head does not advance for this long
+    #[arg(long, value_name = "SECONDS", help_heading = "Acquisition Options")]
+    pub max_head_stall: Option<f64>,
+
+    /// Post follow alerts as json to this url, e.g. a chat webhook
+    #[arg(long, value_name = "URL", help_heading = "Acquisition Options")]
+    pub alert_webhook: Option<String>,
+
+    /// Stop following with a nonzero exit code at the first alert
+    #[arg(long, help_heading = "Acquisition Options")]
+    pub exit_on_alert: bool,
+
+    /// When following, record reorgs of the chain head observed between
+    /// polls in cryo_reorgs.jsonl of the output directory
+    #[arg(long, verbatim_doc_comment, help_heading = "Acquisition Options")]
+    pub record_reorgs: bool,
+
+    /// When following, collect each new block as its own chunk and write
+    /// its files as soon as it arrives, polling every second by default
+    #[arg(long, verbatim_doc_comment, help_heading = "Acquisition Options")]
+    pub tip: bool,
+
+    /// With --tip, collect blocks at the chain head without waiting for
+    /// --reorg-buffer confirmations, then recollect each block whose hash
+    /// changed by the time it is confirmed
+    #[arg(long, verbatim_doc_comment, help_heading = "Acquisition Options")]
+    pub preconfirmed: bool,
+
     /// Dry run, collect no data
     #[arg(short, long, help_heading = "Acquisition Options")]
     pub dry: bool,
 
+    /// Collect data but only count rows per chunk and dataset, writing no files
+    #[arg(long, help_heading = "Acquisition Options")]
+    pub count_only: bool,
+
+    /// Write a zero-row file per dataset with the schema of the output, collecting no data
+    #[arg(long, help_heading = "Acquisition Options")]
+    pub emit_empty_schema: bool,
+
     /// Run quietly without printing information to stdout
     #[arg(long)]
     pub no_verbose: bool,
 
+    /// Load options from a config file, see `cryo init`
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<String>,
+
+    /// Load datasets and options of a collection profile, one of mev,
+    /// accounting, or nft, see `cryo profiles`
+    #[arg(long, value_name = "NAME", verbatim_doc_comment)]
+    pub profile: Option<String>,
+
+    /// Print `cryo schema` as markdown reference tables
+    #[arg(long)]
+    pub markdown: bool,
+
     /// Number of blocks per file
     #[arg(short, long, default_value_t = 1000, help_heading = "Output Options")]
     pub chunk_size: u64,
@@ -96,6 +358,30 @@ pub struct Args {
     #[arg(long, help_heading = "Output Options")]
     pub n_chunks: Option<u64>,
 
+    /// How to split blocks into chunks, one of:
+    /// blocks (--chunk-size blocks per file), bytes:SIZE of block data
+    /// (e.g. bytes:256MB), txs:N transactions (e.g. txs:100000), or
+    /// time:DURATION aligned to block timestamps (e.g. time:1d)
+    #[arg(
+        long,
+        value_name = "STRATEGY",
+        help_heading = "Output Options",
+        verbatim_doc_comment,
+        conflicts_with_all = ["txs", "chunks"]
+    )]
+    pub chunk_strategy: Option<String>,
+
+    /// Resize chunks while collecting to hold about this many rows,
+    /// shrinking chunks in busy block ranges and growing them in sparse ones
+    #[arg(
+        long,
+        value_name = "ROWS",
+        help_heading = "Output Options",
+        verbatim_doc_comment,
+        conflicts_with_all = ["txs", "chunks", "align", "n_chunks"]
+    )]
+    pub adaptive_chunks: Option<u64>,
+
     /// Directory for output files
     #[arg(short, long, default_value = ".", help_heading = "Output Options")]
     pub output_dir: String,
@@ -116,6 +402,48 @@ pub struct Args {
     #[arg(long, help_heading = "Output Options")]
     pub json: bool,
 
+    /// Save as excel xlsx instead of parquet, for small extracts
+    #[arg(long, help_heading = "Output Options")]
+    pub xlsx: bool,
+
+    /// Save to a sqlite database in the output directory, with one table per dataset
+    #[arg(long, help_heading = "Output Options")]
+    pub sqlite: bool,
+
+    /// Store values that sqlite cannot hold exactly as REAL, instead of
+    /// failing their chunk, e.g. uint64 values above 2^63-1
+    #[arg(long, verbatim_doc_comment, help_heading = "Output Options")]
+    pub allow_lossy: bool,
+
+    /// Fail chunks with more than N rows when saving as xlsx
+    #[arg(long, value_name = "N", default_value_t = 100_000, help_heading = "Output Options")]
+    pub xlsx_max_rows: usize,
+
+    /// Field delimiter of csv files, a single character or tab
+    #[arg(long, value_name = "CHAR", default_value = ",", help_heading = "Output Options")]
+    pub csv_delimiter: String,
+
+    /// Quote csv fields when necessary or always
+    #[arg(
+        long,
+        value_name = "STYLE",
+        default_value = "necessary",
+        help_heading = "Output Options"
+    )]
+    pub csv_quote_style: String,
+
+    /// Do not write a header row to csv files
+    #[arg(long, help_heading = "Output Options")]
+    pub no_csv_header: bool,
+
+    /// Write strict RFC 4180 csv files, with CRLF row endings and comma delimiters
+    #[arg(long, help_heading = "Output Options")]
+    pub csv_rfc4180: bool,
+
+    /// Compress csv files with gzip, as .csv.gz
+    #[arg(long, help_heading = "Output Options")]
+    pub csv_gzip: bool,
+
     /// Number of rows per row group in parquet file
     #[arg(long, value_name = "GROUP_SIZE", help_heading = "Output Options")]
     pub row_group_size: Option<usize>,
@@ -132,10 +460,90 @@ pub struct Args {
     #[arg(long, help_heading="Output Options", value_name="NAME [#]", num_args(1..=2), default_value = "lz4")]
     pub compression: Vec<String>,
 
+    /// Dictionary encode address and topic columns in parquet files, requires --hex. these
+    /// columns are then stored as dictionary<int32, string>, read as categorical by polars
+    #[arg(long, help_heading = "Output Options")]
+    pub dictionary_encoding: bool,
+
+    /// Write wei and token amounts as parquet decimal128 with SCALE decimal places instead of
+    /// strings, values above 38 digits are null
+    #[arg(
+        long,
+        value_name = "SCALE",
+        num_args(0..=1),
+        default_missing_value = "0",
+        help_heading = "Output Options"
+    )]
+    pub decimal: Option<u32>,
+
+    /// Write traces as one row per transaction with a list of call structs, instead of one row
+    /// per call
+    #[arg(long, help_heading = "Output Options")]
+    pub nested_traces: bool,
+
+    /// Collapse proxy calls that only delegatecall their implementation into one trace, with
+    /// the implementation in a delegate_to column
+    #[arg(long, help_heading = "Output Options")]
+    pub collapse_delegatecalls: bool,
+
+    /// Split output into files per contract (logs and traces) or per utc day, MODE is contract
+    /// or day
+    #[arg(long, value_name = "MODE", help_heading = "Output Options")]
+    pub partition_by: Option<String>,
+
+    /// Maximum number of contract partitions per chunk, other contracts go to an "other" file
+    #[arg(long, value_name = "N", default_value_t = 100, help_heading = "Output Options")]
+    pub max_partitions: usize,
+
+    /// Abort when more than this many rows have been written
+    #[arg(long, value_name = "N", help_heading = "Output Options")]
+    pub max_output_rows: Option<u64>,
+
+    /// Abort when more than this many bytes have been written, e.g. 500MB or 20GB
+    #[arg(long, value_name = "SIZE", help_heading = "Output Options")]
+    pub max_output_bytes: Option<String>,
+
+    /// Pause while free disk space is below SIZE, e.g. 1GB
+    #[arg(long, value_name = "SIZE", help_heading = "Output Options")]
+    pub min_free_space: Option<String>,
+
+    /// Write data of the rpc's chain into a directory holding data of other chains
+    #[arg(long, help_heading = "Output Options")]
+    pub allow_mixed_chains: bool,
+
+    /// Write byte-identical files for identical inputs, sorting rows by all columns and setting
+    /// file and manifest times to SOURCE_DATE_EPOCH (or 0)
+    #[arg(long, help_heading = "Output Options")]
+    pub deterministic: bool,
+
+    /// Write a summary of each epoch of BLOCKS blocks (row counts, value sums, and active
+    /// addresses) to a rollups directory as soon as the epoch is collected [default: 100000]
+    #[arg(
+        long,
+        value_name = "BLOCKS",
+        num_args(0..=1),
+        default_missing_value = "100000",
+        help_heading = "Output Options"
+    )]
+    pub rollups: Option<u64>,
+
+    /// Write a json sidecar of each file to a stats directory, with block
+    /// range, null counts, min and max values, and distinct value estimates
+    #[arg(long, verbatim_doc_comment, help_heading = "Output Options")]
+    pub stats_sidecars: bool,
+
+    /// Sign the manifest of file checksums with this secret key file (or gpg key id)
+    #[arg(long, value_name = "KEY", help_heading = "Output Options")]
+    pub sign_key: Option<String>,
+
+    /// Signing tool for --sign-key, minisign or gpg
+    #[arg(long, value_name = "TOOL", default_value = "minisign", help_heading = "Output Options")]
+    pub sign_with: String,
+
     // /// [transactions] track gas used by each transaction
     // #[arg(long, help_heading = "Dataset-specific Options")]
     // pub gas_used: bool,
-    /// [logs] filter logs by contract address
+    /// [logs, traces, state diffs] filter by contract address
     #[arg(long, help_heading = "Dataset-specific Options")]
     pub contract: Option<String>,
 
@@ -155,7 +563,72 @@ pub struct Args {
     #[arg(long, help_heading = "Dataset-specific Options")]
     pub topic3: Option<String>,
 
-    /// [logs] Number of blocks per log request
+    /// [logs] Drop spam token Transfer logs, e.g. zero-value transfers to many recipients
+    #[arg(long, help_heading = "Dataset-specific Options")]
+    pub filter_spam: bool,
+
+    /// [logs] File of spam contract addresses whose Transfer logs are dropped, one per line
+    #[arg(
+        long,
+        value_name = "FILE",
+        requires = "filter_spam",
+        help_heading = "Dataset-specific Options"
+    )]
+    pub spam_contracts: Option<String>,
+
+    /// [logs] Distinct recipients of zero-value transfers within a chunk that mark a spam contract
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 1000,
+        help_heading = "Dataset-specific Options"
+    )]
+    pub spam_min_recipients: usize,
+
+    /// [logs] With --txs, drop logs of each receipt that do not match --contract and --topic
+    /// filters
+    #[arg(long, help_heading = "Dataset-specific Options")]
+    pub prune_receipt_logs: bool,
+
+    /// [token datasets] File of token addresses to collect, one per line
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with = "contract",
+        help_heading = "Dataset-specific Options"
+    )]
+    pub tokens: Option<String>,
+
+    /// [token datasets] File of token addresses to skip, one per line
+    #[arg(long, value_name = "FILE", help_heading = "Dataset-specific Options")]
+    pub exclude_tokens: Option<String>,
+
+    /// [address_lifespans] File of addresses to look up, one per line
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with = "contract",
+        help_heading = "Dataset-specific Options"
+    )]
+    pub addresses: Option<String>,
+
+    /// [raw_rpc] JSON-RPC method requested once per block, e.g. eth_feeHistory
+    #[arg(long, value_name = "METHOD", help_heading = "Dataset-specific Options")]
+    pub rpc_method: Option<String>,
+
+    /// [raw_rpc] JSON array of method params, {block} is replaced by the hex
+    /// block number [default: ["{block}"]]
+    #[arg(
+        long,
+        value_name = "JSON",
+        requires = "rpc_method",
+        verbatim_doc_comment,
+        help_heading = "Dataset-specific Options"
+    )]
+    pub rpc_params: Option<String>,
+
+    /// [logs] Number of blocks per log request [default: 1, or the
+    /// largest range of --provider-profile]
     #[arg(
         long,
         value_name = "BLOCKS",
@@ -203,6 +676,22 @@ fn get_after_str() -> &'static str {
 - can use a parquet file             <white><bold>--txs ./path/to/file.parquet[:COLUMN_NAME]</bold></white>
                                      (default column name is <white><bold>transaction_hash</bold></white>)
 - can use multiple parquet files     <white><bold>--txs ./path/to/ethereum__logs*.parquet</bold></white>
+
+<white><bold>Commands</bold></white>
+- create a config file interactively <white><bold>cryo init [CONFIG_PATH]</bold></white>
+- diagnose rpc, disk, and options    <white><bold>cryo doctor [OPTIONS]</bold></white>
+- list or print collection profiles  <white><bold>cryo profiles [NAME]</bold></white>
+- list or run example recipes        <white><bold>cryo examples [NAME | run [NAME ...]]</bold></white>
+- collect a contract's full history  <white><bold>cryo contract <<ADDRESS></bold></white>
+- describe dataset columns           <white><bold>cryo schema [DATATYPE ...] [--markdown]</bold></white>
+- summarize an output directory      <white><bold>cryo info [DIR]</bold></white>
+- export dbt or glue table metadata  <white><bold>cryo catalog <<dbt|glue> [DIR] [LOCATION]</bold></white>
+- show where an output file is from  <white><bold>cryo lineage <<FILE></bold></white>
+- verify checksums and signature     <white><bold>cryo verify [DIR] [PUBLIC_KEY]</bold></white>
+- package for torrent or ipfs        <white><bold>cryo publish <<torrent|car> [DIR] [OUTPUT]</bold></white>
+- download a published dataset       <white><bold>cryo fetch <<MANIFEST_URL> [DIR] [PUBLIC_KEY]</bold></white>
+- show rpc usage across runs         <white><bold>cryo usage [LEDGER]</bold></white>
+- generate shell completions         <white><bold>cryo completions <<SHELL></bold></white>
 "#
     )
 }
@@ -219,6 +708,24 @@ fn get_datatype_help() -> &'static str {
 - <white><bold>code_diffs</bold></white>
 - <white><bold>nonce_diffs</bold></white>
 - <white><bold>storage_diffs</bold></white>
-- <white><bold>vm_traces</bold></white>     (alias = <white><bold>opcode_traces</bold></white>)"#
+- <white><bold>vm_traces</bold></white>     (alias = <white><bold>opcode_traces</bold></white>)
+- <white><bold>erc20_transfers</bold></white> (= decoded Transfer logs, with optional decimal amounts)
+- <white><bold>erc20_approvals</bold></white> (= decoded Approval logs, with optional decimal amounts)
+- <white><bold>approvals_for_all</bold></white> (= decoded erc721 and erc1155 ApprovalForAll logs)
+- <white><bold>erc1155_transfers</bold></white> (= decoded TransferSingle and TransferBatch logs, one row per token id)
+- <white><bold>weth_wraps</bold></white> (= decoded WETH Deposit and Withdrawal logs)
+- <white><bold>bridge_transfers</bold></white> (= decoded op stack standard bridge deposits and withdrawals)
+- <white><bold>safe_transactions</bold></white> (= decoded Safe executions and execTransaction calldata)
+- <white><bold>beacon_deposits</bold></white> (= decoded deposit contract DepositEvent logs)
+- <white><bold>validators</bold></white> (= validator withdrawal credentials at the end of each chunk, requires --beacon-url)
+- <white><bold>zksync_transactions</bold></white> (= transactions with l1 batch fields, requires --vm zksync)
+- <white><bold>starknet_blocks</bold></white> (= starknet blocks, requires --vm starknet)
+- <white><bold>starknet_transactions</bold></white> (= starknet transactions, requires --vm starknet)
+- <white><bold>multicall_calls</bold></white> (= child calls of multicall transactions, expanded recursively)
+- <white><bold>raw_rpc</bold></white>       (= json responses of --rpc-method, requested once per block)
+- <white><bold>gas_percentiles</bold></white> (= per-block gas price and priority fee percentiles)
+- <white><bold>address_activity</bold></white> (= per-address transaction counts, value, and gas)
+- <white><bold>address_lifespans</bold></white> (= first and last sent transaction of --addresses)
+- <white><bold>genesis</bold></white>       (= accounts allocated at block 0, optionally from --genesis-file)"#
     )
 }
@@ -0,0 +1,290 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Read, Write},
+    path::Path,
+};
+
+use sha2::{Digest, Sha256};
+
+use cryo_freeze::ParseError;
+
+/// bytes of each leaf block, the default chunk size of `ipfs add`
+const CHUNK_SIZE: usize = 1 << 18;
+
+/// maximum links of each file node, the default width of `ipfs add` balanced trees
+const MAX_LINKS: usize = 174;
+
+/// multicodec of raw leaf blocks
+const RAW: u64 = 0x55;
+
+/// multicodec of unixfs file and directory nodes
+const DAG_PB: u64 = 0x70;
+
+/// multihash code of sha2-256
+const SHA2_256: u8 = 0x12;
+
+/// unixfs node types
+const UNIXFS_DIRECTORY: u64 = 1;
+const UNIXFS_FILE: u64 = 2;
+
+/// link to a block of a unixfs dag
+struct Link {
+    cid: Vec<u8>,
+    /// bytes of the linked block and all of its descendants
+    tsize: u64,
+    /// bytes of file content below the linked block
+    size: u64,
+}
+
+/// a generated content addressed archive
+pub(crate) struct Car {
+    /// cid of the root directory
+    pub(crate) root: String,
+    /// (filename, cid) of each file
+    pub(crate) files: Vec<(String, String)>,
+}
+
+/// write a CARv1 of a unixfs directory holding files of dir, importable with `ipfs dag import`
+///
+/// files are chunked into raw leaves under balanced dag-pb trees, matching
+/// `ipfs add --cid-version 1 --raw-leaves`
+pub(crate) fn write_car(dir: &str, files: &[String], output: &str) -> Result<Car, ParseError> {
+    let write_error =
+        |_e: std::io::Error| ParseError::ParseError(format!("could not write {}", output));
+
+    // blocks are written to a temporary body, since the header must name the root first
+    let body_path = format!("{}_tmp", output);
+    let mut body = BufWriter::new(File::create(&body_path).map_err(write_error)?);
+    let mut links = Vec::new();
+    for filename in files.iter() {
+        let link = add_file(&mut body, &Path::new(dir).join(filename))
+            .map_err(|_e| ParseError::ParseError(format!("could not add {}", filename)))?;
+        links.push((filename.clone(), link));
+    }
+    links.sort_by(|a, b| a.0.cmp(&b.0));
+    let named: Vec<(Option<&str>, &Link)> =
+        links.iter().map(|(name, link)| (Some(name.as_str()), link)).collect();
+    let directory = dag_pb_node(&named, &unixfs_data(UNIXFS_DIRECTORY, None));
+    let root = write_block(&mut body, DAG_PB, &directory).map_err(write_error)?;
+    body.flush().map_err(write_error)?;
+    drop(body);
+
+    let mut out = BufWriter::new(File::create(output).map_err(write_error)?);
+    let header = car_header(&root);
+    write_varint(&mut out, header.len() as u64).map_err(write_error)?;
+    out.write_all(&header).map_err(write_error)?;
+    let mut body = File::open(&body_path).map_err(write_error)?;
+    std::io::copy(&mut body, &mut out).map_err(write_error)?;
+    out.flush().map_err(write_error)?;
+    std::fs::remove_file(&body_path).map_err(write_error)?;
+
+    Ok(Car {
+        root: cid_string(&root),
+        files: links.iter().map(|(name, link)| (name.clone(), cid_string(&link.cid))).collect(),
+    })
+}
+
+/// write the blocks of a file, returning a link to its root block
+fn add_file<W: Write>(out: &mut W, path: &Path) -> std::io::Result<Link> {
+    let mut file = File::open(path)?;
+    let mut leaves = Vec::new();
+    loop {
+        let mut chunk = Vec::with_capacity(CHUNK_SIZE);
+        (&mut file).take(CHUNK_SIZE as u64).read_to_end(&mut chunk)?;
+        // an empty file is a single empty leaf
+        if chunk.is_empty() && !leaves.is_empty() {
+            break
+        }
+        let cid = write_block(out, RAW, &chunk)?;
+        let size = chunk.len() as u64;
+        leaves.push(Link { cid, tsize: size, size });
+        if chunk.len() < CHUNK_SIZE {
+            break
+        }
+    }
+
+    // group links into file nodes, layer by layer, until a single root remains
+    let mut layer = leaves;
+    while layer.len() > 1 {
+        let mut parents = Vec::new();
+        for children in layer.chunks(MAX_LINKS) {
+            let blocksizes: Vec<u64> = children.iter().map(|child| child.size).collect();
+            let size = blocksizes.iter().sum();
+            // file links have empty names, as written by go-ipfs
+            let links: Vec<(Option<&str>, &Link)> =
+                children.iter().map(|child| (Some(""), child)).collect();
+            let node = dag_pb_node(&links, &unixfs_file_data(size, &blocksizes));
+            let cid = write_block(out, DAG_PB, &node)?;
+            let tsize = node.len() as u64 + children.iter().map(|child| child.tsize).sum::<u64>();
+            parents.push(Link { cid, tsize, size });
+        }
+        layer = parents;
+    }
+    Ok(layer.remove(0))
+}
+
+/// write a block as `varint(len) | cid | data`, returning its cidv1
+fn write_block<W: Write>(out: &mut W, codec: u64, data: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut cid = Vec::new();
+    push_varint(&mut cid, 1);
+    push_varint(&mut cid, codec);
+    cid.push(SHA2_256);
+    cid.push(32);
+    cid.extend(Sha256::digest(data));
+    write_varint(out, (cid.len() + data.len()) as u64)?;
+    out.write_all(&cid)?;
+    out.write_all(data)?;
+    Ok(cid)
+}
+
+/// dag-cbor header `{"roots": [root], "version": 1}`
+fn car_header(root: &[u8]) -> Vec<u8> {
+    let mut header = vec![0xa2, 0x65];
+    header.extend(b"roots");
+    // array of one cid, tag 42 over bytes prefixed by the identity multibase
+    header.extend([0x81, 0xd8, 0x2a, 0x58, (root.len() + 1) as u8, 0x00]);
+    header.extend(root);
+    header.push(0x67);
+    header.extend(b"version");
+    header.push(0x01);
+    header
+}
+
+/// dag-pb node, links are serialized before data
+fn dag_pb_node(links: &[(Option<&str>, &Link)], data: &[u8]) -> Vec<u8> {
+    let mut node = Vec::new();
+    for (name, link) in links.iter() {
+        let mut pb_link = Vec::new();
+        push_bytes_field(&mut pb_link, 1, &link.cid);
+        if let Some(name) = name {
+            push_bytes_field(&mut pb_link, 2, name.as_bytes());
+        }
+        push_varint_field(&mut pb_link, 3, link.tsize);
+        push_bytes_field(&mut node, 2, &pb_link);
+    }
+    push_bytes_field(&mut node, 1, data);
+    node
+}
+
+fn unixfs_data(node_type: u64, filesize: Option<u64>) -> Vec<u8> {
+    let mut data = Vec::new();
+    push_varint_field(&mut data, 1, node_type);
+    if let Some(filesize) = filesize {
+        push_varint_field(&mut data, 3, filesize);
+    }
+    data
+}
+
+fn unixfs_file_data(filesize: u64, blocksizes: &[u64]) -> Vec<u8> {
+    let mut data = unixfs_data(UNIXFS_FILE, Some(filesize));
+    for blocksize in blocksizes.iter() {
+        push_varint_field(&mut data, 4, *blocksize);
+    }
+    data
+}
+
+fn push_bytes_field(out: &mut Vec<u8>, field: u64, bytes: &[u8]) {
+    push_varint(out, (field << 3) | 2);
+    push_varint(out, bytes.len() as u64);
+    out.extend(bytes);
+}
+
+fn push_varint_field(out: &mut Vec<u8>, field: u64, value: u64) {
+    push_varint(out, field << 3);
+    push_varint(out, value);
+}
+
+fn push_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn write_varint<W: Write>(out: &mut W, value: u64) -> std::io::Result<()> {
+    let mut bytes = Vec::new();
+    push_varint(&mut bytes, value);
+    out.write_all(&bytes)
+}
+
+/// base32 multibase string of a cid
+fn cid_string(cid: &[u8]) -> String {
+    format!("b{}", data_encoding::BASE32_NOPAD.encode(cid).to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// directory of test files, recreated empty
+    fn test_dir(name: &str, files: &[(&str, Vec<u8>)]) -> String {
+        let dir = std::env::temp_dir().join(format!("cryo_car_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        for (filename, contents) in files.iter() {
+            std::fs::write(dir.join(filename), contents).unwrap();
+        }
+        dir.to_string_lossy().into_owned()
+    }
+
+    fn car_of(name: &str, files: &[(&str, Vec<u8>)]) -> (Car, Vec<u8>) {
+        let dir = test_dir(name, files);
+        let filenames: Vec<String> =
+            files.iter().map(|(filename, _)| filename.to_string()).collect();
+        let output = format!("{}.car", dir);
+        let car = write_car(&dir, &filenames, &output).unwrap();
+        let bytes = std::fs::read(&output).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        std::fs::remove_file(&output).unwrap();
+        (car, bytes)
+    }
+
+    #[test]
+    fn empty_directory() {
+        let (car, bytes) = car_of("empty", &[]);
+        assert_eq!(car.root, "bafybeiczsscdsbs7ffqz55asqdf3smv6klcw3gofszvwlyarci47bgf354");
+
+        // header naming the root, then the single directory block
+        let mut expected = vec![0x3a];
+        expected.extend(car_header(&bytes[bytes.len() - 40..bytes.len() - 4]));
+        expected.extend([0x28]);
+        expected.extend(&bytes[bytes.len() - 40..bytes.len() - 4]);
+        expected.extend([0x0a, 0x02, 0x08, 0x01]);
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    fn raw_leaf_files() {
+        let files = [("hello.txt", b"hello world".to_vec()), ("empty", vec![])];
+        let (car, _) = car_of("raw", &files);
+        assert_eq!(
+            car.files,
+            vec![
+                (
+                    "empty".to_string(),
+                    "bafkreihdwdcefgh4dqkjv67uzcmw7ojee6xedzdetojuzjevtenxquvyku".to_string()
+                ),
+                (
+                    "hello.txt".to_string(),
+                    "bafkreifzjut3te2nhyekklss27nh3k72ysco7y32koao5eei66wof36n5e".to_string()
+                ),
+            ]
+        );
+        assert_eq!(car.root, "bafybeigrxszdx3bnrhyzgbs7jice6yjl76jbvbws4ofnunpr557mpdwd6y");
+    }
+
+    #[test]
+    fn chunked_file() {
+        let files = [("zeros", vec![0; 2 * CHUNK_SIZE + 1])];
+        let (car, _) = car_of("chunked", &files);
+        assert_eq!(
+            car.files,
+            vec![(
+                "zeros".to_string(),
+                "bafybeiccdyx3btkzo6gcp5rzteka62rjst3vvgrnd7kosz6rlpg2esghpe".to_string()
+            )]
+        );
+        assert_eq!(car.root, "bafybeifxrghxqjun3mznd2hjepquierpxpz3nigrwg6rcq4mv4wid7xr6u");
+    }
+}
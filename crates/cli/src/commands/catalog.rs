@@ -0,0 +1,134 @@
+use std::collections::BTreeMap;
+
+use serde_json::json;
+
+use cryo_freeze::ParseError;
+
+use super::output_dir::{scan_dir, DatasetInfo};
+
+/// name of the dbt source and glue database of exported tables
+const DATABASE_NAME: &str = "cryo";
+
+/// print catalog metadata of the parquet datasets of an output directory
+///
+/// tables are located at LOCATION, which defaults to DIR, e.g. an s3 prefix the directory is
+/// synced to
+pub(crate) fn run(command_args: &[String]) -> Result<(), ParseError> {
+    let usage = "usage: cryo catalog <dbt|glue> [DIR] [LOCATION]";
+    let (format, dir, location) = match command_args {
+        [format] => (format, ".", "."),
+        [format, dir] => (format, dir.as_str(), dir.as_str()),
+        [format, dir, location] => (format, dir.as_str(), location.as_str()),
+        _ => return Err(ParseError::ParseError(usage.to_string())),
+    };
+    let datasets: BTreeMap<String, DatasetInfo> = scan_dir(dir)?
+        .into_iter()
+        .filter(|(name, info)| {
+            let has_schema = !info.schemas.is_empty();
+            if !has_schema {
+                eprintln!("skipping {}, catalogs are only exported for parquet files", name);
+            }
+            has_schema
+        })
+        .collect();
+    let location = location.trim_end_matches('/');
+    let output = match format.as_str() {
+        "dbt" => dbt_sources(&datasets, location),
+        "glue" => glue_tables(&datasets, location)?,
+        _ => return Err(ParseError::ParseError(usage.to_string())),
+    };
+    println!("{}", output);
+    Ok(())
+}
+
+/// dbt sources yaml, with external table properties for dbt-external-tables
+fn dbt_sources(datasets: &BTreeMap<String, DatasetInfo>, location: &str) -> String {
+    let mut lines = vec![
+        "version: 2".to_string(),
+        "".to_string(),
+        "sources:".to_string(),
+        format!("  - name: {}", DATABASE_NAME),
+        "    tables:".to_string(),
+    ];
+    for (name, info) in datasets.iter() {
+        lines.push(format!("      - name: {}", name));
+        lines.push(format!("        description: \"{}\"", describe(name, info)));
+        lines.push("        external:".to_string());
+        lines.push(format!("          location: \"{}\"", location));
+        lines.push("          file_format: parquet".to_string());
+        lines.push(format!("          pattern: \".*__{}__.*\\\\.parquet\"", name));
+        lines.push("        columns:".to_string());
+        for (column, dtype) in columns(info).iter() {
+            lines.push(format!("          - name: {}", column));
+            lines.push(format!("            data_type: {}", sql_type(dtype)));
+        }
+    }
+    lines.join("\n")
+}
+
+/// glue table inputs, each usable with `aws glue create-table --cli-input-json`
+fn glue_tables(
+    datasets: &BTreeMap<String, DatasetInfo>,
+    location: &str,
+) -> Result<String, ParseError> {
+    let tables: Vec<serde_json::Value> = datasets
+        .iter()
+        .map(|(name, info)| {
+            let columns: Vec<serde_json::Value> = columns(info)
+                .iter()
+                .map(|(column, dtype)| json!({ "Name": column, "Type": sql_type(dtype) }))
+                .collect();
+            json!({
+                "DatabaseName": DATABASE_NAME,
+                "TableInput": {
+                    "Name": name,
+                    "Description": describe(name, info),
+                    "TableType": "EXTERNAL_TABLE",
+                    "Parameters": { "classification": "parquet" },
+                    "StorageDescriptor": {
+                        "Columns": columns,
+                        "Location": location,
+                        "InputFormat":
+                            "org.apache.hadoop.hive.ql.io.parquet.MapredParquetInputFormat",
+                        "OutputFormat":
+                            "org.apache.hadoop.hive.ql.io.parquet.MapredParquetOutputFormat",
+                        "SerdeInfo": {
+                            "SerializationLibrary":
+                                "org.apache.hadoop.hive.ql.io.parquet.serde.ParquetHiveSerDe"
+                        }
+                    }
+                }
+            })
+        })
+        .collect();
+    serde_json::to_string_pretty(&tables).map_err(|e| ParseError::ParseError(e.to_string()))
+}
+
+fn describe(name: &str, info: &DatasetInfo) -> String {
+    let chains: Vec<&str> = info.chains.iter().map(|chain| chain.as_str()).collect();
+    format!("cryo {} dataset, {} files of {}", name, info.n_files, chains.join(", "))
+}
+
+/// columns of the schema shared by the most files
+fn columns(info: &DatasetInfo) -> Vec<(String, String)> {
+    info.schemas
+        .iter()
+        .max_by_key(|(_, n_files)| **n_files)
+        .map(|(columns, _)| columns.clone())
+        .unwrap_or_default()
+}
+
+/// hive sql type of a polars dtype, as understood by both glue and common dbt adapters
+fn sql_type(dtype: &str) -> &'static str {
+    match dtype {
+        "u8" | "u16" | "i8" | "i16" | "i32" => "int",
+        "u32" | "u64" | "i64" => "bigint",
+        "f32" => "float",
+        "f64" => "double",
+        "bool" => "boolean",
+        "binary" => "binary",
+        dtype if dtype.starts_with("decimal") => "decimal(38,0)",
+        dtype if dtype.starts_with("datetime") => "timestamp",
+        _ => "string",
+    }
+}
@@ -0,0 +1,27 @@
+use std::str::FromStr;
+
+use clap::CommandFactory;
+use clap_complete::Shell;
+
+use cryo_freeze::ParseError;
+
+use crate::args::Args;
+
+/// print shell completion script for the given shell
+pub(crate) fn run(command_args: &[String]) -> Result<(), ParseError> {
+    let shell = match command_args {
+        [shell] => Shell::from_str(shell).map_err(|_e| {
+            ParseError::ParseError(format!(
+                "unknown shell {}, use one of bash, elvish, fish, powershell, zsh",
+                shell
+            ))
+        })?,
+        _ => {
+            return Err(ParseError::ParseError(
+                "usage: cryo completions <bash|elvish|fish|powershell|zsh>".to_string(),
+            ))
+        }
+    };
+    clap_complete::generate(shell, &mut Args::command(), "cryo", &mut std::io::stdout());
+    Ok(())
+}
@@ -0,0 +1,130 @@
+use std::{path::Path, time::Instant};
+
+use ethers::prelude::*;
+use serde_json::json;
+
+use cryo_freeze::{lookup, CollectError, ParseError};
+
+use crate::{
+    args::Args,
+    parse,
+    summaries::{print_bullet, print_header},
+};
+
+/// datasets collected over the lifetime of a contract, each scoped to it with --contract
+const CONTRACT_DATASETS: [&str; 3] = ["logs", "traces", "storage_diffs"];
+
+/// file of the output directory of a contract describing the contract
+const CONTRACT_METADATA_FILE: &str = "contract.json";
+
+/// collect the full history of a contract into one directory
+///
+/// finds the block that deployed the contract, then collects the logs it emitted, the traces
+/// that call, create, or are sent by it, and its storage diffs, from that block through the
+/// latest block unless --blocks is given. output goes to --output-dir/<ADDRESS>, next to a
+/// metadata file with the deployment block and code of the contract. datasets are collected in
+/// this process with the output and chunking options of the command line, sharing one source
+pub(crate) async fn run(args: &Args, command_args: &[String]) -> Result<(), ParseError> {
+    let address = match command_args {
+        [address] => address
+            .parse::<H160>()
+            .map_err(|_| ParseError::ParseError(format!("invalid address: {}", address)))?,
+        _ => return Err(ParseError::ParseError("usage: cryo contract <ADDRESS>".to_string())),
+    };
+    let address_str = format!("{:?}", address);
+    if parse::find_rpc_url(args).is_none() {
+        let message = "contract needs an rpc, pass --rpc <URL> or set ETH_RPC_URL";
+        return Err(ParseError::ParseError(message.to_string()))
+    }
+
+    // find lifetime of contract
+    let source = parse::parse_source(args).await?;
+    let latest_block = source
+        .latest_block_number()
+        .await
+        .map_err(|_e| ParseError::ParseError("Error retrieving latest block number".to_string()))?;
+    let deployment_block = lookup::deployment_block(address, 0, latest_block, &source.provider)
+        .await
+        .map_err(lookup_error)?
+        .ok_or_else(|| {
+            ParseError::ParseError(format!("{} has no code at block {}", address_str, latest_block))
+        })?;
+    let code =
+        lookup::code_at(address, latest_block, &source.provider).await.map_err(lookup_error)?;
+
+    // write metadata
+    let output_dir = Path::new(&args.output_dir).join(&address_str);
+    std::fs::create_dir_all(&output_dir)
+        .map_err(|_| ParseError::ParseError("could not create output directory".to_string()))?;
+    let metadata = json!({
+        "address": address_str,
+        "chain_id": source.chain_id,
+        "deployment_block": deployment_block,
+        "latest_block": latest_block,
+        "code_size": code.len(),
+        "code_hash": format!("{:?}", H256::from(ethers::utils::keccak256(&code))),
+    });
+    let metadata = serde_json::to_string_pretty(&metadata)
+        .map_err(|_| ParseError::ParseError("could not serialize contract metadata".to_string()))?;
+    std::fs::write(output_dir.join(CONTRACT_METADATA_FILE), metadata)
+        .map_err(|_| ParseError::ParseError("could not write contract metadata".to_string()))?;
+
+    // range ends are exclusive, so the range ends one past the latest block
+    let blocks = match &args.blocks {
+        Some(blocks) => blocks.clone(),
+        None if deployment_block == latest_block => vec![latest_block.to_string()],
+        None => vec![format!("{}:{}", deployment_block, latest_block + 1)],
+    };
+    if !args.no_verbose {
+        print_header(format!("contract {}", address_str));
+        print_bullet("deployment block", deployment_block.to_string());
+        print_bullet("blocks", blocks.join(" "));
+        print_bullet("output directory", output_dir.display().to_string());
+        println!();
+    }
+
+    // collect each dataset, scoped to the contract
+    let mut failed = Vec::new();
+    for datatype in CONTRACT_DATASETS.iter() {
+        let t_start = Instant::now();
+        let dataset_dir = output_dir.join(datatype);
+        std::fs::create_dir_all(&dataset_dir)
+            .map_err(|_| ParseError::ParseError("could not create output directory".to_string()))?;
+        let dataset_args = Args {
+            datatype: vec![datatype.to_string()],
+            contract: Some(address_str.clone()),
+            blocks: Some(blocks.clone()),
+            txs: None,
+            last: None,
+            chunks: None,
+            output_dir: dataset_dir.to_string_lossy().into_owned(),
+            ..args.clone()
+        };
+        let (query, sink) = parse::parse_query_and_sink(&dataset_args, &source).await?;
+        let result = cryo_freeze::freeze(&query, &source, &sink).await;
+        let elapsed = format!("{:.1}s", t_start.elapsed().as_secs_f64());
+        let message = match result {
+            Ok(summary) if summary.n_errored == 0 => format!("ok in {}", elapsed),
+            Ok(summary) => {
+                failed.push(*datatype);
+                format!("{} chunks failed after {}", summary.n_errored, elapsed)
+            }
+            Err(e) => {
+                failed.push(*datatype);
+                format!("failed after {}: {}", elapsed, e)
+            }
+        };
+        if !args.no_verbose {
+            print_bullet(*datatype, message);
+        }
+    }
+
+    match failed.is_empty() {
+        true => Ok(()),
+        false => Err(ParseError::ParseError(format!("datasets failed: {}", failed.join(", ")))),
+    }
+}
+
+fn lookup_error(e: CollectError) -> ParseError {
+    ParseError::ParseError(e.to_string())
+}
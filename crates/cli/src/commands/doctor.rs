@@ -0,0 +1,380 @@
+use std::{
+    path::Path,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use serde_json::{json, Value};
+
+use cryo_freeze::{available_space, lock_holder, ParseError};
+
+use crate::{
+    args::Args,
+    parse,
+    summaries::{print_bullet, print_header},
+};
+
+/// free space below which the output directory is reported, unless --min-free-space is given
+const DOCTOR_MIN_FREE_SPACE: u64 = 10_000_000_000;
+
+/// seconds of difference between the local clock and the provider's clock that are reported
+const MAX_CLOCK_SKEW: i64 = 5;
+
+/// seconds of age of the latest block that suggest the node is not synced
+const MAX_HEAD_AGE: u64 = 300;
+
+/// timeout of each diagnostic rpc request
+const DOCTOR_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// methods whose support decides which datasets can be collected
+const CAPABILITY_METHODS: [(&str, &str); 4] = [
+    ("eth_getLogs", "logs and decoded log datasets"),
+    ("eth_getBlockReceipts", "receipts fetched per block"),
+    ("trace_block", "traces"),
+    ("debug_traceBlockByNumber", "traces and diffs on nodes without trace_*"),
+];
+
+enum Status {
+    Ok,
+    Warn,
+    Fail,
+}
+
+/// result of one diagnostic, with an actionable fix unless it passed
+struct Check {
+    name: &'static str,
+    status: Status,
+    detail: String,
+    fix: Option<String>,
+}
+
+impl Check {
+    fn ok(name: &'static str, detail: String) -> Check {
+        Check { name, status: Status::Ok, detail, fix: None }
+    }
+
+    fn warn(name: &'static str, detail: String, fix: String) -> Check {
+        Check { name, status: Status::Warn, detail, fix: Some(fix) }
+    }
+
+    fn fail(name: &'static str, detail: String, fix: String) -> Check {
+        Check { name, status: Status::Fail, detail, fix: Some(fix) }
+    }
+}
+
+/// check rpc reachability and capabilities, archive depth, clock skew, the output directory, and
+/// the validity of options, printing a fix for each problem
+///
+/// fails if any check fails, so that scripts can gate on it, while warnings still succeed
+pub(crate) async fn run(args: &Args, command_args: &[String]) -> Result<(), ParseError> {
+    if !command_args.is_empty() {
+        return Err(ParseError::ParseError("usage: cryo doctor [OPTIONS]".to_string()))
+    }
+    let mut checks = Vec::new();
+
+    // rpc
+    match (args.mock_rpc || args.replay_rpc.is_some(), parse::find_rpc_url(args)) {
+        (true, _) => checks.push(Check::ok("rpc", "offline source, skipped".to_string())),
+        (false, None) => checks.push(Check::fail(
+            "rpc",
+            "no rpc url".to_string(),
+            "pass --rpc <URL> or set ETH_RPC_URL".to_string(),
+        )),
+        (false, Some(url)) => checks.extend(check_rpc(&url).await),
+    }
+
+    // options are only parsed with an rpc, since parsing them connects to it
+    if args.mock_rpc || args.replay_rpc.is_some() || parse::find_rpc_url(args).is_some() {
+        checks.push(check_options(args).await);
+    }
+
+    // output directory
+    checks.extend(check_output_dir(args));
+
+    print_header("cryo doctor");
+    let mut n_problems = 0;
+    for check in checks.iter() {
+        let status = match check.status {
+            Status::Ok => "ok",
+            Status::Warn => "warn",
+            Status::Fail => "fail",
+        };
+        print_bullet(check.name, format!("{}, {}", status, check.detail));
+        if let Some(fix) = &check.fix {
+            println!("    fix: {}", fix);
+            n_problems += 1;
+        }
+    }
+    println!();
+    match n_problems {
+        0 => println!("no problems found"),
+        1 => println!("1 problem found"),
+        n => println!("{} problems found", n),
+    }
+    match checks.iter().filter(|check| matches!(check.status, Status::Fail)).count() {
+        0 => Ok(()),
+        1 => Err(ParseError::ParseError("1 doctor check failed".to_string())),
+        n => Err(ParseError::ParseError(format!("{} doctor checks failed", n))),
+    }
+}
+
+/// json-rpc requests sent directly, so that failures are reported instead of retried
+struct DoctorRpc {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl DoctorRpc {
+    /// send a request, returning its result and the provider's clock from the date header
+    async fn call(&self, method: &str, params: Value) -> Result<(Value, Option<i64>), String> {
+        let body = json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": params});
+        let response = self
+            .client
+            .post(&self.url)
+            .header("content-type", "application/json")
+            .body(body.to_string())
+            .timeout(DOCTOR_TIMEOUT)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        let date = response
+            .headers()
+            .get("date")
+            .and_then(|date| date.to_str().ok())
+            .and_then(|date| chrono::DateTime::parse_from_rfc2822(date).ok())
+            .map(|date| date.timestamp());
+        let status = response.status();
+        let text = response.text().await.map_err(|e| e.to_string())?;
+        let response: Value = serde_json::from_str(&text)
+            .map_err(|_e| format!("http {}, not a json-rpc response", status))?;
+        match (response.get("result"), response.get("error")) {
+            (_, Some(error)) => Err(error
+                .get("message")
+                .and_then(Value::as_str)
+                .unwrap_or("unknown error")
+                .to_string()),
+            (Some(result), None) if !result.is_null() => Ok((result.clone(), date)),
+            _ => Err("empty result".to_string()),
+        }
+    }
+}
+
+fn parse_hex(value: &Value) -> Option<u64> {
+    u64::from_str_radix(value.as_str()?.trim_start_matches("0x"), 16).ok()
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+async fn check_rpc(url: &str) -> Vec<Check> {
+    let rpc = DoctorRpc { client: reqwest::Client::new(), url: url.to_string() };
+    let mut checks = Vec::new();
+
+    // reachability
+    let start = Instant::now();
+    let (chain_id, date) = match rpc.call("eth_chainId", json!([])).await {
+        Ok((chain_id, date)) => (parse_hex(&chain_id).unwrap_or_default(), date),
+        Err(e) => {
+            checks.push(Check::fail(
+                "rpc",
+                format!("{} is unreachable: {}", url, e),
+                "check the url, api key, and network access of --rpc or ETH_RPC_URL".to_string(),
+            ));
+            return checks
+        }
+    };
+    let latency = start.elapsed().as_millis();
+    checks.push(Check::ok("rpc", format!("chain id {}, {}ms per request", chain_id, latency)));
+
+    // clock skew
+    checks.push(match date {
+        Some(date) => {
+            let skew = now() as i64 - date;
+            match skew.abs() > MAX_CLOCK_SKEW {
+                true => Check::warn(
+                    "clock",
+                    format!("local clock is {}s off the provider's clock", skew),
+                    "sync the system clock, e.g. enable ntp, since --last, follow health \
+                    checks, and manifest times use it"
+                        .to_string(),
+                ),
+                false => Check::ok("clock", format!("within {}s of the provider", skew.abs())),
+            }
+        }
+        None => Check::ok("clock", "provider sent no date header, skipped".to_string()),
+    });
+
+    // chain head
+    let latest = match rpc.call("eth_blockNumber", json!([])).await.map(|(n, _)| parse_hex(&n)) {
+        Ok(Some(latest)) => latest,
+        Ok(None) | Err(_) => {
+            checks.push(Check::fail(
+                "head",
+                "eth_blockNumber failed".to_string(),
+                "use a provider that serves standard eth_* methods".to_string(),
+            ));
+            return checks
+        }
+    };
+    let latest_hex = format!("{:#x}", latest);
+    let timestamp = rpc
+        .call("eth_getBlockByNumber", json!([latest_hex, false]))
+        .await
+        .ok()
+        .and_then(|(block, _)| block.get("timestamp").and_then(parse_hex));
+    checks.push(match timestamp {
+        Some(timestamp) if now().saturating_sub(timestamp) > MAX_HEAD_AGE => Check::warn(
+            "head",
+            format!("latest block {} is {}s old", latest, now().saturating_sub(timestamp)),
+            "the node may still be syncing, wait for it or use another provider".to_string(),
+        ),
+        Some(_) => Check::ok("head", format!("latest block {}", latest)),
+        None => Check::warn(
+            "head",
+            format!("could not get block {}", latest),
+            "use a provider that serves eth_getBlockByNumber".to_string(),
+        ),
+    });
+
+    // capabilities
+    let mut supported = Vec::new();
+    let mut missing = Vec::new();
+    for (method, purpose) in CAPABILITY_METHODS.iter() {
+        let params = match *method {
+            "eth_getLogs" => json!([{"fromBlock": latest_hex, "toBlock": latest_hex}]),
+            "debug_traceBlockByNumber" => json!([latest_hex, {"tracer": "callTracer"}]),
+            _ => json!([latest_hex]),
+        };
+        match rpc.call(method, params).await {
+            Ok(_) => supported.push(*method),
+            Err(_) => missing.push((*method, *purpose)),
+        }
+    }
+    checks.push(match missing.is_empty() {
+        true => Check::ok("capabilities", format!("supports {}", supported.join(", "))),
+        false => {
+            let missing_methods: Vec<&str> = missing.iter().map(|(method, _)| *method).collect();
+            let purposes: Vec<&str> = missing.iter().map(|(_, purpose)| *purpose).collect();
+            Check::warn(
+                "capabilities",
+                format!("missing {}", missing_methods.join(", ")),
+                format!("use a provider serving these methods to collect {}", purposes.join("; ")),
+            )
+        }
+    });
+
+    // archive depth
+    checks.push(check_archive(&rpc, latest).await);
+    checks
+}
+
+/// find the earliest block whose state the node serves, by binary search over eth_getBalance
+async fn check_archive(rpc: &DoctorRpc, latest: u64) -> Check {
+    let has_state = |block: u64| async move {
+        let params = json!(["0x0000000000000000000000000000000000000000", format!("{:#x}", block)]);
+        rpc.call("eth_getBalance", params).await.is_ok()
+    };
+    if has_state(1).await {
+        return Check::ok("archive", "state of every block is available".to_string())
+    }
+    if !has_state(latest).await {
+        return Check::fail(
+            "archive",
+            "eth_getBalance fails at the latest block".to_string(),
+            "use a provider that serves state methods".to_string(),
+        )
+    }
+    let (mut lower, mut upper) = (1, latest);
+    while lower < upper {
+        let mid = lower + (upper - lower) / 2;
+        if has_state(mid).await {
+            upper = mid;
+        } else {
+            lower = mid + 1;
+        }
+    }
+    Check::warn(
+        "archive",
+        format!("state available from block {} ({} blocks deep)", lower, latest - lower),
+        format!(
+            "use an archive node for state datasets (diffs, balances, address_lifespans) of \
+            blocks before {}",
+            lower
+        ),
+    )
+}
+
+/// parse options as a collection would, using blocks if no datatype is given
+async fn check_options(args: &Args) -> Check {
+    let datatype: Vec<String> =
+        args.datatype.iter().filter(|datatype| *datatype != "doctor").cloned().collect();
+    let datatype = match datatype.is_empty() {
+        true => vec!["blocks".to_string()],
+        false => datatype,
+    };
+    let args = Args { datatype, ..args.clone() };
+    let sources = match (&args.config, &args.profile) {
+        (Some(config), Some(profile)) => format!("of {} and profile {}", config, profile),
+        (Some(config), None) => format!("of {}", config),
+        (None, Some(profile)) => format!("of profile {}", profile),
+        (None, None) => "given".to_string(),
+    };
+    match parse::parse_opts(&args).await {
+        Ok(_) => Check::ok("options", format!("options {} are valid", sources)),
+        Err(e) => Check::fail(
+            "options",
+            format!("options {} are invalid: {}", sources, e),
+            "correct the option named in the error on the command line or in the config"
+                .to_string(),
+        ),
+    }
+}
+
+fn check_output_dir(args: &Args) -> Vec<Check> {
+    let dir = &args.output_dir;
+    let mut checks = Vec::new();
+
+    // permissions
+    let probe = Path::new(dir).join(".cryo_doctor");
+    let writable = std::fs::create_dir_all(dir)
+        .and_then(|_| std::fs::write(&probe, b"cryo doctor"))
+        .and_then(|_| std::fs::remove_file(&probe));
+    checks.push(match writable {
+        Ok(()) => Check::ok("output dir", format!("{} is writable", dir)),
+        Err(e) => Check::fail(
+            "output dir",
+            format!("cannot write to {}: {}", dir, e),
+            "choose a writable --output-dir, or fix the permissions of the directory".to_string(),
+        ),
+    });
+
+    // lock
+    if let Some(holder) = lock_holder(dir) {
+        checks.push(Check::warn(
+            "lock",
+            format!("{} is locked by a cryo run ({})", dir, holder),
+            "wait for the other cryo run to finish".to_string(),
+        ));
+    }
+
+    // disk space
+    let min_free_space = args
+        .min_free_space
+        .as_deref()
+        .and_then(|size| parse::parse_bytes(size).ok())
+        .filter(|size| *size > 0)
+        .unwrap_or(DOCTOR_MIN_FREE_SPACE);
+    checks.push(match available_space(dir) {
+        Some(free) if free < min_free_space => Check::warn(
+            "disk",
+            format!("{:.1} GB free in {}", free as f64 / 1e9, dir),
+            format!(
+                "free up space or choose another --output-dir, at least {:.1} GB is recommended",
+                min_free_space as f64 / 1e9
+            ),
+        ),
+        Some(free) => Check::ok("disk", format!("{:.1} GB free", free as f64 / 1e9)),
+        None => Check::ok("disk", "free space unknown, skipped".to_string()),
+    });
+    checks
+}
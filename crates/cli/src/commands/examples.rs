@@ -0,0 +1,159 @@
+use std::{path::Path, process::Command, time::Instant};
+
+use cryo_freeze::ParseError;
+
+use crate::{
+    args::Args,
+    parse,
+    summaries::{print_bullet, print_header},
+};
+
+/// end-to-end extraction recipe, run as a sequence of cryo commands
+struct Recipe {
+    name: &'static str,
+    description: &'static str,
+    /// blocks collected unless --blocks is given
+    blocks: &'static str,
+    /// arguments of each cryo command, without blocks, rpc, or output directory
+    steps: &'static [&'static [&'static str]],
+    /// how the collected data is used
+    analysis: &'static str,
+}
+
+/// curated recipes, with contract addresses of ethereum mainnet
+const RECIPES: [Recipe; 3] = [
+    Recipe {
+        name: "holder-snapshot",
+        description: "USDC transfers with decimal amounts, for balance changes of holders",
+        blocks: "-1000:",
+        steps: &[&[
+            "erc20_transfers",
+            "--contract",
+            "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48",
+            "--include-columns",
+            "amount",
+        ]],
+        analysis: "sum amounts received minus amounts sent per address for balance changes",
+    },
+    Recipe {
+        name: "dex-volumes",
+        description: "Swap events of the Uniswap v3 USDC/WETH 0.05% pool",
+        blocks: "-1000:",
+        steps: &[&[
+            "logs",
+            "--contract",
+            "0x88e6a0c2ddd26feeb64f039a2c41296fcb3f5640",
+            "--event",
+            "0xc42079f94a6350d7e6235f29174924f928cc2ac818eb64fed8004e115fbcca67",
+        ]],
+        analysis: "decode amount0 and amount1 from data and sum their absolute values per block",
+    },
+    Recipe {
+        name: "gas-study",
+        description: "base fees, gas usage, and gas price percentiles of recent blocks",
+        blocks: "-1000:",
+        steps: &[&["blocks", "--include-columns", "gas_limit"], &["gas_percentiles"]],
+        analysis: "join on block_number to compare fee percentiles with block fullness",
+    },
+];
+
+/// list recipes, print the commands of a recipe, or run recipes against the rpc of --rpc
+///
+/// recipes run the cryo executable with the given --rpc and --blocks, writing into a directory
+/// per recipe under --output-dir, and fail if any of their commands fail
+pub(crate) fn run(args: &Args, command_args: &[String]) -> Result<(), ParseError> {
+    let usage = "usage: cryo examples [NAME | run [NAME ...]]";
+    match command_args {
+        [] => {
+            print_header("example recipes");
+            for recipe in RECIPES.iter() {
+                print_bullet(recipe.name, recipe.description);
+            }
+            println!(
+                "\nprint a recipe with `cryo examples <NAME>`, run recipes with `cryo examples \
+                run [NAME ...] --rpc <URL>`"
+            );
+            Ok(())
+        }
+        [command, names @ ..] if command == "run" => {
+            let recipes = match names {
+                [] => RECIPES.iter().collect(),
+                names => names.iter().map(|name| find_recipe(name)).collect::<Result<_, _>>()?,
+            };
+            run_recipes(args, recipes)
+        }
+        [name] => {
+            let recipe = find_recipe(name)?;
+            print_header(recipe.name);
+            println!("{}\n", recipe.description);
+            for step in recipe.steps.iter() {
+                println!("cryo {} -b {}", step.join(" "), recipe.blocks);
+            }
+            println!("\n{}", recipe.analysis);
+            Ok(())
+        }
+        _ => Err(ParseError::ParseError(usage.to_string())),
+    }
+}
+
+fn find_recipe(name: &str) -> Result<&'static Recipe, ParseError> {
+    match RECIPES.iter().find(|recipe| recipe.name == name) {
+        Some(recipe) => Ok(recipe),
+        None => {
+            let names: Vec<&str> = RECIPES.iter().map(|recipe| recipe.name).collect();
+            Err(ParseError::ParseError(format!(
+                "unknown recipe {}, use one of {}",
+                name,
+                names.join(", ")
+            )))
+        }
+    }
+}
+
+fn run_recipes(args: &Args, recipes: Vec<&Recipe>) -> Result<(), ParseError> {
+    let rpc_url = parse::find_rpc_url(args).ok_or_else(|| {
+        ParseError::ParseError("recipes need an rpc, pass --rpc <URL> or set ETH_RPC_URL".into())
+    })?;
+    let exe = std::env::current_exe()
+        .map_err(|_| ParseError::ParseError("could not locate cryo executable".to_string()))?;
+
+    let mut failed = Vec::new();
+    for recipe in recipes.iter() {
+        let output_dir = Path::new(&args.output_dir).join(recipe.name);
+        let blocks = match &args.blocks {
+            Some(blocks) => blocks.clone(),
+            None => vec![recipe.blocks.to_string()],
+        };
+        let t_start = Instant::now();
+        let mut succeeded = true;
+        for step in recipe.steps.iter() {
+            let status = Command::new(&exe)
+                .args(step.iter())
+                .args(blocks.iter().flat_map(|block| ["--blocks", block.as_str()]))
+                .arg("--rpc")
+                .arg(&rpc_url)
+                .arg("--output-dir")
+                .arg(&output_dir)
+                .arg("--no-verbose")
+                .status()
+                .map_err(|_| ParseError::ParseError(format!("could not run {}", recipe.name)))?;
+            if !status.success() {
+                succeeded = false;
+                break
+            }
+        }
+        let elapsed = format!("{:.1}s, {}", t_start.elapsed().as_secs_f64(), output_dir.display());
+        match succeeded {
+            true => print_bullet(recipe.name, format!("ok in {}", elapsed)),
+            false => {
+                print_bullet(recipe.name, format!("failed after {}", elapsed));
+                failed.push(recipe.name);
+            }
+        }
+    }
+
+    match failed.is_empty() {
+        true => Ok(()),
+        false => Err(ParseError::ParseError(format!("recipes failed: {}", failed.join(", ")))),
+    }
+}
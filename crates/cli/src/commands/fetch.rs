@@ -0,0 +1,277 @@
+use std::{
+    collections::HashSet,
+    io::Write,
+    path::{Component, Path, PathBuf},
+};
+
+use thousands::Separable;
+
+use cryo_freeze::{
+    sha256_file, signatures::signature_path, verify_manifest, CommitStatus, FileError, Manifest,
+    ManifestEntry, ManifestFile, OutputLock, ParseError, SignatureScheme, MANIFEST_NAME,
+    STAGING_DIR_NAME,
+};
+
+use crate::summaries::{print_bullet, print_header};
+
+/// gateway used for ipfs:// urls, unless IPFS_GATEWAY is set
+const DEFAULT_IPFS_GATEWAY: &str = "https://ipfs.io";
+
+/// download a published dataset into a local output directory
+///
+/// the url points to the manifest of the published directory, over http(s), s3 (public buckets),
+/// or ipfs. files are verified against the checksums of the manifest and committed to the local
+/// manifest, so later collection skips the fetched chunks and only collects the delta. chunks
+/// whose files already exist locally with different contents are kept as is
+///
+/// a signature published next to the manifest is verified with the public key before any file is
+/// downloaded, and signed manifests are refused without a public key. file names of the manifest
+/// must be plain names, so that no file is written outside of the directory
+pub(crate) async fn run(command_args: &[String]) -> Result<(), ParseError> {
+    let usage = "usage: cryo fetch <MANIFEST_URL> [DIR] [PUBLIC_KEY]";
+    let (url, dir, public_key) = match command_args {
+        [url] => (url.as_str(), ".", None),
+        [url, dir] => (url.as_str(), dir.as_str(), None),
+        [url, dir, public_key] => (url.as_str(), dir.as_str(), Some(public_key.as_str())),
+        _ => return Err(ParseError::ParseError(usage.to_string())),
+    };
+    let manifest_url = resolve_url(url)?;
+    let base_url = match manifest_url.rsplit_once('/') {
+        Some((base_url, _)) => base_url.to_string(),
+        None => return Err(ParseError::ParseError(format!("invalid manifest url: {}", url))),
+    };
+    std::fs::create_dir_all(dir)
+        .map_err(|_e| ParseError::ParseError(format!("could not create directory {}", dir)))?;
+
+    // lock output directory, so no collection runs while files are committed
+    let _lock = OutputLock::acquire(dir).map_err(|e| ParseError::ParseError(e.to_string()))?;
+    Manifest::clear_staging(dir);
+    let manifest = Manifest::open(dir)
+        .map_err(|_e| ParseError::ParseError(format!("could not read {}", MANIFEST_NAME)))?;
+
+    let client = reqwest::Client::new();
+    let text = client
+        .get(&manifest_url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| ParseError::ParseError(format!("could not download manifest: {}", e)))?
+        .text()
+        .await
+        .map_err(|e| ParseError::ParseError(format!("could not download manifest: {}", e)))?;
+    let scheme = verify_remote_signature(&client, &manifest_url, &text, public_key).await?;
+    let entries = current_entries(&text);
+    for file in entries.iter().flat_map(|entry| entry.files.iter()) {
+        check_filename(&file.filename)?;
+    }
+
+    print_header(format!("fetching {}", url));
+    if let Some(scheme) = scheme {
+        print_bullet("signature", format!("valid {:?} signature", scheme).to_lowercase());
+    }
+    print_bullet("chunks", entries.len().to_string());
+    let staging_dir = Path::new(dir).join(STAGING_DIR_NAME);
+    let (mut n_fetched, mut n_present, mut n_kept) = (0, 0, 0);
+    let mut n_bytes = 0;
+    for entry in entries.into_iter() {
+        let mut missing = Vec::new();
+        let mut conflict = false;
+        for file in entry.files.iter() {
+            let path = Path::new(dir).join(&file.filename);
+            match local_state(&path, file, &manifest) {
+                LocalState::Missing => missing.push(file),
+                LocalState::Identical => {}
+                LocalState::Different => conflict = true,
+            }
+        }
+        if conflict {
+            n_kept += 1;
+            continue
+        }
+        let chunk_committed =
+            entry.datasets.iter().all(|dataset| manifest.is_chunk_committed(dataset, &entry.chunk));
+        if missing.is_empty() && chunk_committed {
+            n_present += 1;
+            continue
+        }
+
+        // stage and verify files, then commit them like a collected chunk
+        std::fs::create_dir_all(&staging_dir)
+            .map_err(|_e| ParseError::ParseError("could not create staging dir".to_string()))?;
+        let mut staged: Vec<(PathBuf, PathBuf)> = Vec::new();
+        for file in missing.iter() {
+            let staged_path = staging_dir.join(&file.filename);
+            let file_url = format!("{}/{}", base_url, file.filename);
+            n_bytes += download(&client, &file_url, &staged_path).await?;
+            verify_file(&staged_path, file)?;
+            staged.push((staged_path, Path::new(dir).join(&file.filename)));
+        }
+        let manifest_error =
+            |_e: FileError| ParseError::ParseError(format!("could not write {}", MANIFEST_NAME));
+        let mut local_entry = ManifestEntry { status: CommitStatus::Pending, ..entry.clone() };
+        manifest.append(&local_entry).map_err(manifest_error)?;
+        for (staged_path, path) in staged.iter() {
+            std::fs::rename(staged_path, path)
+                .map_err(|_e| ParseError::ParseError(format!("could not move {:?}", path)))?;
+        }
+        local_entry.status = CommitStatus::Committed;
+        manifest.append(&local_entry).map_err(manifest_error)?;
+        n_fetched += 1;
+    }
+    Manifest::clear_staging(dir);
+
+    print_bullet("chunks fetched", n_fetched.to_string());
+    print_bullet("chunks already present", n_present.to_string());
+    if n_kept > 0 {
+        print_bullet("chunks kept local, contents differ", n_kept.to_string());
+    }
+    print_bullet("bytes downloaded", n_bytes.separate_with_commas());
+    Ok(())
+}
+
+/// translate s3:// and ipfs:// urls to https urls
+fn resolve_url(url: &str) -> Result<String, ParseError> {
+    if let Some(path) = url.strip_prefix("s3://") {
+        let (bucket, key) = path
+            .split_once('/')
+            .ok_or_else(|| ParseError::ParseError(format!("invalid s3 url: {}", url)))?;
+        Ok(format!("https://{}.s3.amazonaws.com/{}", bucket, key))
+    } else if let Some(path) = url.strip_prefix("ipfs://") {
+        let gateway =
+            std::env::var("IPFS_GATEWAY").unwrap_or_else(|_| DEFAULT_IPFS_GATEWAY.to_string());
+        Ok(format!("{}/ipfs/{}", gateway.trim_end_matches('/'), path))
+    } else if url.starts_with("http://") || url.starts_with("https://") {
+        Ok(url.to_string())
+    } else {
+        Err(ParseError::ParseError(format!("unsupported url: {}", url)))
+    }
+}
+
+/// verify the signature published next to a remote manifest, returning its scheme if signed
+///
+/// the manifest text and signature are checked in a scratch directory, which is removed afterwards
+async fn verify_remote_signature(
+    client: &reqwest::Client,
+    manifest_url: &str,
+    text: &str,
+    public_key: Option<&str>,
+) -> Result<Option<SignatureScheme>, ParseError> {
+    for scheme in [SignatureScheme::Minisign, SignatureScheme::Gpg] {
+        let signature_url = format!("{}.{}", manifest_url, scheme.extension());
+        let response = match client.get(&signature_url).send().await {
+            Ok(response) if response.status().is_success() => response,
+            _ => continue,
+        };
+        let signature = response
+            .bytes()
+            .await
+            .map_err(|e| ParseError::ParseError(format!("could not download signature: {}", e)))?;
+        let public_key = public_key.ok_or_else(|| {
+            ParseError::ParseError(
+                "manifest is signed, verify it with cryo fetch <MANIFEST_URL> <DIR> <PUBLIC_KEY>"
+                    .to_string(),
+            )
+        })?;
+        let scratch_dir = std::env::temp_dir().join(format!("cryo_fetch_{}", std::process::id()));
+        let scratch = scratch_dir.to_string_lossy().to_string();
+        let scratch_error =
+            |_e: std::io::Error| ParseError::ParseError("could not write scratch dir".to_string());
+        std::fs::create_dir_all(&scratch_dir).map_err(scratch_error)?;
+        let result = std::fs::write(scratch_dir.join(MANIFEST_NAME), text)
+            .and_then(|_| std::fs::write(signature_path(&scratch, scheme), &signature))
+            .map_err(scratch_error)
+            .and_then(|_| {
+                verify_manifest(&scratch, scheme, public_key)
+                    .map_err(|e| ParseError::ParseError(format!("invalid signature: {}", e)))
+            });
+        let _ = std::fs::remove_dir_all(&scratch_dir);
+        return result.map(|_| Some(scheme))
+    }
+    match public_key {
+        Some(_) => Err(ParseError::ParseError("no signature published with manifest".to_string())),
+        None => Ok(None),
+    }
+}
+
+/// reject file names that are not a plain name, e.g. ../file or /file
+fn check_filename(filename: &str) -> Result<(), ParseError> {
+    let mut components = Path::new(filename).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) => Ok(()),
+        _ => Err(ParseError::ParseError(format!("invalid file name in manifest: {}", filename))),
+    }
+}
+
+/// committed entries of a manifest that hold the latest version of at least one file
+fn current_entries(text: &str) -> Vec<ManifestEntry> {
+    let committed: Vec<ManifestEntry> = text
+        .lines()
+        .filter_map(|line| serde_json::from_str::<ManifestEntry>(line).ok())
+        .filter(|entry| entry.status == CommitStatus::Committed)
+        .collect();
+    let mut seen = HashSet::new();
+    let mut current = Vec::new();
+    for entry in committed.into_iter().rev() {
+        let is_current = entry.files.iter().any(|file| !seen.contains(&file.filename));
+        seen.extend(entry.files.iter().map(|file| file.filename.clone()));
+        if is_current {
+            current.push(entry);
+        }
+    }
+    current.reverse();
+    current
+}
+
+enum LocalState {
+    Missing,
+    Identical,
+    Different,
+}
+
+/// state of a local file, files left behind by an interrupted commit count as missing
+fn local_state(path: &Path, file: &ManifestFile, manifest: &Manifest) -> LocalState {
+    if !manifest.is_committed(&path.to_string_lossy()) {
+        return LocalState::Missing
+    }
+    match matches_manifest(path, file) {
+        true => LocalState::Identical,
+        false => LocalState::Different,
+    }
+}
+
+/// stream a url into a file, returning the number of bytes written
+async fn download(client: &reqwest::Client, url: &str, path: &Path) -> Result<u64, ParseError> {
+    let download_error =
+        |e: String| ParseError::ParseError(format!("could not fetch {}: {}", url, e));
+    let mut response = client
+        .get(url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| download_error(e.to_string()))?;
+    let mut out = std::fs::File::create(path).map_err(|e| download_error(e.to_string()))?;
+    let mut n_bytes = 0;
+    while let Some(chunk) = response.chunk().await.map_err(|e| download_error(e.to_string()))? {
+        out.write_all(&chunk).map_err(|e| download_error(e.to_string()))?;
+        n_bytes += chunk.len() as u64;
+    }
+    out.sync_all().map_err(|e| download_error(e.to_string()))?;
+    Ok(n_bytes)
+}
+
+/// check a downloaded file against its manifest checksum, or its size for older manifests
+fn verify_file(path: &Path, file: &ManifestFile) -> Result<(), ParseError> {
+    match matches_manifest(path, file) {
+        true => Ok(()),
+        false => Err(ParseError::ParseError(format!("checksum mismatch for {}", file.filename))),
+    }
+}
+
+fn matches_manifest(path: &Path, file: &ManifestFile) -> bool {
+    match &file.sha256 {
+        Some(expected) => {
+            sha256_file(&path.to_string_lossy()).map(|actual| &actual == expected).unwrap_or(false)
+        }
+        None => std::fs::metadata(path).map(|m| m.len() == file.n_bytes).unwrap_or(false),
+    }
+}
@@ -0,0 +1,77 @@
+use std::collections::BTreeSet;
+
+use thousands::Separable;
+
+use cryo_freeze::ParseError;
+
+use super::output_dir::{merge_ranges, scan_dir};
+use crate::summaries::{print_bullet, print_header};
+
+/// summarize the datasets of an output directory
+pub(crate) fn run(command_args: &[String]) -> Result<(), ParseError> {
+    let dir = match command_args {
+        [] => ".".to_string(),
+        [dir] => dir.clone(),
+        _ => return Err(ParseError::ParseError("usage: cryo info [DIR]".to_string())),
+    };
+    let datasets = scan_dir(&dir)?;
+    if datasets.is_empty() {
+        println!("no cryo files in {}", dir);
+        return Ok(());
+    }
+
+    let chains: BTreeSet<&str> =
+        datasets.values().flat_map(|info| info.chains.iter().map(|c| c.as_str())).collect();
+    print_header(format!("cryo output in {}", dir));
+    print_bullet("datasets", datasets.keys().cloned().collect::<Vec<_>>().join(", "));
+    print_bullet("chains", chains.into_iter().collect::<Vec<_>>().join(", "));
+    print_bullet("files", datasets.values().map(|info| info.n_files).sum::<u64>().to_string());
+    let n_rows: Option<u64> = datasets.values().map(|info| info.n_rows).sum();
+    print_bullet("rows", format_rows(n_rows));
+    let n_bytes: u64 = datasets.values().map(|info| info.n_bytes).sum();
+    print_bullet("bytes", n_bytes.separate_with_commas());
+
+    for (name, info) in datasets.iter() {
+        println!();
+        print_header(name);
+        print_bullet("chains", info.chains.iter().cloned().collect::<Vec<_>>().join(", "));
+        print_bullet("files", info.n_files.to_string());
+        let coverage = merge_ranges(info.ranges.clone());
+        let coverage: Vec<String> = coverage
+            .iter()
+            .map(|(start, end)| {
+                format!("{} to {}", start.separate_with_commas(), end.separate_with_commas())
+            })
+            .collect();
+        if !coverage.is_empty() {
+            print_bullet("block coverage", coverage.join(", "));
+        }
+        print_bullet("rows", format_rows(info.n_rows));
+        print_bullet("bytes", info.n_bytes.separate_with_commas());
+        let n_schemas = info.schemas.len();
+        match n_schemas {
+            0 => {}
+            1 => print_bullet("schema versions", "1"),
+            _ => {
+                print_bullet("schema versions", n_schemas.to_string());
+                for (i, (columns, n_files)) in info.schemas.iter().enumerate() {
+                    let key = format!("schema {} ({} files)", i + 1, n_files);
+                    let columns: Vec<String> = columns
+                        .iter()
+                        .map(|(name, dtype)| format!("{}: {}", name, dtype))
+                        .collect();
+                    print_bullet(key, columns.join(", "));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// row count, unknown when a csv or json file is missing from the manifest
+fn format_rows(n_rows: Option<u64>) -> String {
+    match n_rows {
+        Some(n_rows) => n_rows.separate_with_commas(),
+        None => "unknown".to_string(),
+    }
+}
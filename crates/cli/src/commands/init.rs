@@ -0,0 +1,85 @@
+use std::io::{BufRead, Write};
+
+use cryo_freeze::ParseError;
+
+/// interactively build a config file that can be used with `cryo --config <PATH>`
+pub(crate) fn run(command_args: &[String]) -> Result<(), ParseError> {
+    let default_path = match command_args {
+        [] => "cryo.toml".to_string(),
+        [path] => path.clone(),
+        _ => return Err(ParseError::ParseError("usage: cryo init [CONFIG_PATH]".to_string())),
+    };
+
+    println!("creating a cryo config, press enter to accept [defaults]\n");
+    let network_name = prompt("chain / network name (empty = detect from rpc)", "")?;
+    let datatypes = prompt("datasets to collect", "blocks transactions logs")?;
+    let blocks = prompt("block range", "0:latest")?;
+    let rpc = prompt("rpc url (empty = use ETH_RPC_URL)", "")?;
+    let output_dir = prompt("output directory", ".")?;
+    let format = prompt("output format (parquet, csv, json, xlsx, sqlite)", "parquet")?;
+    let requests_per_second = prompt("requests per second (empty = unlimited)", "")?;
+    let path = prompt("config path", &default_path)?;
+    if std::path::Path::new(&path).exists() {
+        return Err(ParseError::ParseError(format!(
+            "{} already exists, remove it or choose another config path",
+            path
+        )))
+    }
+
+    let mut table = toml::Table::new();
+    let datatypes = datatypes.split([',', ' ']).filter(|s| !s.is_empty());
+    table.insert("datatype".to_string(), to_array(datatypes));
+    table.insert("blocks".to_string(), to_array(blocks.split_whitespace()));
+    if !network_name.is_empty() {
+        table.insert("network_name".to_string(), toml::Value::String(network_name));
+    }
+    if !rpc.is_empty() {
+        table.insert("rpc".to_string(), toml::Value::String(rpc.clone()));
+    }
+    table.insert("output_dir".to_string(), toml::Value::String(output_dir));
+    match format.as_str() {
+        "parquet" => {}
+        "csv" | "json" | "xlsx" | "sqlite" => {
+            table.insert(format.clone(), toml::Value::Boolean(true));
+        }
+        _ => return Err(ParseError::ParseError(format!("invalid output format {}", format))),
+    }
+    if !requests_per_second.is_empty() {
+        let value = requests_per_second
+            .parse::<i64>()
+            .map_err(|_e| ParseError::ParseError("invalid requests per second".to_string()))?;
+        table.insert("requests_per_second".to_string(), toml::Value::Integer(value));
+    }
+
+    let mut contents = format!("# cryo config, use with `cryo --config {}`\n", path);
+    if rpc.is_empty() {
+        contents.push_str("# rpc url is read from the ETH_RPC_URL env var\n");
+    }
+    contents.push_str(&table.to_string());
+    std::fs::write(&path, contents)
+        .map_err(|e| ParseError::ParseError(format!("could not write config: {}", e)))?;
+    println!("\nconfig written to {}, run with `cryo --config {}`", path, path);
+    Ok(())
+}
+
+fn prompt(question: &str, default: &str) -> Result<String, ParseError> {
+    if default.is_empty() {
+        print!("{}: ", question);
+    } else {
+        print!("{} [{}]: ", question, default);
+    }
+    std::io::stdout().flush().map_err(|_e| ParseError::ParseError("stdout error".to_string()))?;
+    let mut answer = String::new();
+    std::io::stdin()
+        .lock()
+        .read_line(&mut answer)
+        .map_err(|_e| ParseError::ParseError("could not read input".to_string()))?;
+    match answer.trim() {
+        "" => Ok(default.to_string()),
+        answer => Ok(answer.to_string()),
+    }
+}
+
+fn to_array<'a>(values: impl Iterator<Item = &'a str>) -> toml::Value {
+    toml::Value::Array(values.map(|s| toml::Value::String(s.to_string())).collect())
+}
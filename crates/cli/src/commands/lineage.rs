@@ -0,0 +1,132 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::Path,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use chrono::{DateTime, Local};
+use thousands::Separable;
+
+use cryo_freeze::{Lineage, Manifest, ManifestEntry, ParseError, MANIFEST_NAME};
+
+use crate::summaries::{print_bullet, print_header};
+
+/// print where a file came from, using the lineage recorded in the manifest of its directory
+///
+/// shows the run that last wrote the file, earlier runs that wrote it, and the ancestry of runs
+/// that wrote to the directory before
+pub(crate) fn run(command_args: &[String]) -> Result<(), ParseError> {
+    let path = match command_args {
+        [path] => Path::new(path),
+        _ => return Err(ParseError::ParseError("usage: cryo lineage <FILE>".to_string())),
+    };
+    let filename = match path.file_name() {
+        Some(filename) => filename.to_string_lossy().to_string(),
+        None => return Err(ParseError::ParseError(format!("not a file: {}", path.display()))),
+    };
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_string_lossy().to_string(),
+        _ => ".".to_string(),
+    };
+    let entries = Manifest::read(&dir)
+        .map_err(|_e| ParseError::ParseError(format!("could not read {}", MANIFEST_NAME)))?;
+
+    let writes: Vec<&ManifestEntry> = entries
+        .iter()
+        .filter(|entry| entry.files.iter().any(|file| file.filename == filename))
+        .collect();
+    let latest = match writes.last() {
+        Some(latest) => latest,
+        None => {
+            println!("{} is not recorded in {} of {}", filename, MANIFEST_NAME, dir);
+            return Ok(())
+        }
+    };
+
+    print_header(&filename);
+    if let Some(file) = latest.files.iter().find(|file| file.filename == filename) {
+        print_bullet("dataset", &file.dataset);
+        print_bullet("rows", file.n_rows.separate_with_commas());
+        print_bullet("bytes", file.n_bytes.separate_with_commas());
+    }
+    print_bullet("chunk", &latest.chunk);
+    print_bullet("committed", format_timestamp(latest.timestamp));
+    match &latest.lineage {
+        Some(lineage) => print_lineage(lineage),
+        None => print_bullet("lineage", "not recorded, file was written by an older cryo version"),
+    }
+
+    if writes.len() > 1 {
+        println!();
+        print_header("changelog");
+        for entry in writes.iter().rev() {
+            print_bullet(format_timestamp(entry.timestamp), describe_run(entry.lineage.as_ref()));
+        }
+    }
+
+    // walk parent runs, each run described by the first chunk it committed
+    let runs: HashMap<&str, &ManifestEntry> = entries
+        .iter()
+        .rev()
+        .filter_map(|entry| entry.lineage.as_ref().map(|lineage| (lineage.run_id.as_str(), entry)))
+        .collect();
+    let mut ancestors = Vec::new();
+    let mut visited = BTreeSet::new();
+    let mut queue: Vec<&str> = latest
+        .lineage
+        .iter()
+        .flat_map(|lineage| lineage.parent_run_ids.iter().map(|id| id.as_str()))
+        .collect();
+    while let Some(run_id) = queue.pop() {
+        if !visited.insert(run_id) {
+            continue
+        }
+        match runs.get(run_id).and_then(|entry| entry.lineage.as_ref().map(|l| (entry, l))) {
+            Some((entry, lineage)) => {
+                ancestors.push((entry.timestamp, run_id, describe_run(Some(lineage))));
+                queue.extend(lineage.parent_run_ids.iter().map(|id| id.as_str()));
+            }
+            None => ancestors.push((0, run_id, "not recorded in manifest".to_string())),
+        }
+    }
+    if !ancestors.is_empty() {
+        ancestors.sort_by_key(|ancestor| std::cmp::Reverse(ancestor.0));
+        println!();
+        print_header("ancestor runs");
+        for (_, run_id, description) in ancestors.iter() {
+            print_bullet(run_id, description);
+        }
+    }
+    Ok(())
+}
+
+fn print_lineage(lineage: &Lineage) {
+    print_bullet("run id", &lineage.run_id);
+    let parents = match lineage.parent_run_ids.is_empty() {
+        true => "none".to_string(),
+        false => lineage.parent_run_ids.join(", "),
+    };
+    print_bullet("parent runs", parents);
+    print_bullet("cryo version", &lineage.cryo_version);
+    print_bullet("command", lineage.command.join(" "));
+    print_bullet("endpoint", &lineage.endpoint);
+    print_bullet("chain id", lineage.chain_id.to_string());
+}
+
+fn describe_run(lineage: Option<&Lineage>) -> String {
+    match lineage {
+        Some(lineage) => format!(
+            "run {}, cryo {}, {}: {}",
+            lineage.run_id,
+            lineage.cryo_version,
+            lineage.endpoint,
+            lineage.command.join(" ")
+        ),
+        None => "run without recorded lineage".to_string(),
+    }
+}
+
+fn format_timestamp(timestamp: u64) -> String {
+    let datetime: DateTime<Local> = (UNIX_EPOCH + Duration::from_secs(timestamp)).into();
+    datetime.format("%Y-%m-%d %H:%M:%S").to_string()
+}
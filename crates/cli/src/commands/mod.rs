@@ -0,0 +1,44 @@
+mod car;
+mod catalog;
+mod completions;
+mod contract;
+mod doctor;
+mod examples;
+mod fetch;
+mod info;
+mod init;
+mod lineage;
+mod output_dir;
+pub(crate) mod profiles;
+mod publish;
+mod schema;
+mod torrent;
+mod usage;
+mod verify;
+
+use cryo_freeze::ParseError;
+
+use crate::args::Args;
+
+/// run a command if one was given in place of datatypes, return None otherwise
+pub(crate) async fn run_command(args: &Args) -> Option<Result<(), ParseError>> {
+    let (command, command_args) = args.datatype.split_first()?;
+    let result = match command.as_str() {
+        "catalog" => catalog::run(command_args),
+        "completions" => completions::run(command_args),
+        "contract" => contract::run(args, command_args).await,
+        "doctor" => doctor::run(args, command_args).await,
+        "examples" => examples::run(args, command_args),
+        "fetch" => fetch::run(command_args).await,
+        "info" => info::run(command_args),
+        "init" => init::run(command_args),
+        "lineage" => lineage::run(command_args),
+        "profiles" => profiles::run(command_args),
+        "publish" => publish::run(command_args),
+        "schema" => schema::run(args, command_args),
+        "usage" => usage::run(command_args),
+        "verify" => verify::run(command_args),
+        _ => return None,
+    };
+    Some(result)
+}
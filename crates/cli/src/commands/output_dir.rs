@@ -0,0 +1,154 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    path::Path,
+};
+
+use polars::prelude::*;
+
+use cryo_freeze::{Manifest, ParseError, MANIFEST_NAME};
+
+/// file extensions written by cryo
+const EXTENSIONS: [&str; 3] = ["parquet", "csv", "json"];
+
+/// contents of the files of a dataset
+#[derive(Default)]
+pub(crate) struct DatasetInfo {
+    /// network names of files
+    pub(crate) chains: BTreeSet<String>,
+    pub(crate) n_files: u64,
+    /// None when a csv or json file is missing from the manifest
+    pub(crate) n_rows: Option<u64>,
+    pub(crate) n_bytes: u64,
+    /// block ranges of files of block chunks
+    pub(crate) ranges: Vec<(u64, u64)>,
+    /// (name, dtype) of each column of each distinct parquet schema, with number of files
+    pub(crate) schemas: BTreeMap<Vec<(String, String)>, u64>,
+}
+
+/// scan the cryo files of an output directory, keyed by dataset name
+pub(crate) fn scan_dir(dir: &str) -> Result<BTreeMap<String, DatasetInfo>, ParseError> {
+    let entries = Manifest::read(dir)
+        .map_err(|_e| ParseError::ParseError(format!("could not read {}", MANIFEST_NAME)))?;
+    let manifest_files: HashMap<String, (u64, u64)> = entries
+        .into_iter()
+        .flat_map(|entry| entry.files)
+        .map(|file| (file.filename, (file.n_rows, file.n_bytes)))
+        .collect();
+
+    let read_dir = std::fs::read_dir(dir)
+        .map_err(|_e| ParseError::ParseError(format!("could not read directory {}", dir)))?;
+    let mut datasets: BTreeMap<String, DatasetInfo> = BTreeMap::new();
+    for entry in read_dir.flatten() {
+        let path = entry.path();
+        let filename = match path.file_name() {
+            Some(filename) => filename.to_string_lossy().to_string(),
+            None => continue,
+        };
+        let (chain, dataset, range) = match parse_filename(&filename) {
+            Some(parsed) => parsed,
+            None => continue,
+        };
+        let info = datasets
+            .entry(dataset)
+            .or_insert_with(|| DatasetInfo { n_rows: Some(0), ..Default::default() });
+        info.chains.insert(chain);
+        info.n_files += 1;
+        if let Some(range) = range {
+            info.ranges.push(range);
+        }
+
+        let schema = match filename.ends_with(".parquet") {
+            true => read_parquet_schema(&path),
+            false => None,
+        };
+        let (n_rows, n_bytes) = match manifest_files.get(&filename) {
+            Some((n_rows, n_bytes)) => (Some(*n_rows), *n_bytes),
+            None => {
+                let n_bytes = entry.metadata().map(|m| m.len()).unwrap_or_default();
+                (schema.as_ref().map(|(_, n_rows)| *n_rows), n_bytes)
+            }
+        };
+        info.n_rows = info.n_rows.zip(n_rows).map(|(total, n)| total + n);
+        info.n_bytes += n_bytes;
+        if let Some((columns, _)) = schema {
+            *info.schemas.entry(columns).or_default() += 1;
+        }
+    }
+    Ok(datasets)
+}
+
+/// names of the shareable files of an output directory, in sorted order
+///
+/// includes cryo files that are not left behind by an interrupted commit, the manifest, and its
+/// signatures
+pub(crate) fn list_files(dir: &str) -> Result<Vec<String>, ParseError> {
+    let manifest = Manifest::open(dir)
+        .map_err(|_e| ParseError::ParseError(format!("could not read {}", MANIFEST_NAME)))?;
+    let read_dir = std::fs::read_dir(dir)
+        .map_err(|_e| ParseError::ParseError(format!("could not read directory {}", dir)))?;
+    let mut files = Vec::new();
+    for entry in read_dir.flatten() {
+        let path = entry.path();
+        let filename = match path.file_name() {
+            Some(filename) => filename.to_string_lossy().to_string(),
+            None => continue,
+        };
+        let is_manifest = filename == MANIFEST_NAME ||
+            filename.starts_with(format!("{}.", MANIFEST_NAME).as_str());
+        let is_data = parse_filename(&filename).is_some() &&
+            manifest.is_committed(path.to_string_lossy().as_ref());
+        if path.is_file() && (is_manifest || is_data) {
+            files.push(filename);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// chain, dataset, and block range of a data file
+type FileInfo = (String, String, Option<(u64, u64)>);
+
+/// parse `{chain}__{dataset}[__{partition}]__{start}_to_{end}[__{suffix}].{ext}`
+///
+/// range is None for files of transaction chunks
+fn parse_filename(filename: &str) -> Option<FileInfo> {
+    let (stem, extension) = filename.rsplit_once('.')?;
+    if !EXTENSIONS.contains(&extension) {
+        return None;
+    }
+    let pieces: Vec<&str> = stem.split("__").collect();
+    if pieces.len() < 3 {
+        return None;
+    }
+    let range = pieces[2..].iter().find_map(|piece| {
+        let (start, end) = piece.split_once("_to_")?;
+        Some((start.parse().ok()?, end.parse().ok()?))
+    });
+    Some((pieces[0].to_string(), pieces[1].to_string(), range))
+}
+
+/// columns and number of rows of a parquet file, from its metadata
+fn read_parquet_schema(path: &Path) -> Option<(Vec<(String, String)>, u64)> {
+    let file = std::fs::File::open(path).ok()?;
+    let mut reader = ParquetReader::new(file);
+    let schema = reader.schema().ok()?;
+    let n_rows = reader.num_rows().ok()?;
+    let columns =
+        schema.iter().map(|(name, dtype)| (name.to_string(), dtype.to_string())).collect();
+    Some((columns, n_rows as u64))
+}
+
+/// merge overlapping and adjacent block ranges
+pub(crate) fn merge_ranges(mut ranges: Vec<(u64, u64)>) -> Vec<(u64, u64)> {
+    ranges.sort_unstable();
+    let mut merged: Vec<(u64, u64)> = Vec::new();
+    for (start, end) in ranges.into_iter() {
+        match merged.last_mut() {
+            Some((_, last_end)) if start <= last_end.saturating_add(1) => {
+                *last_end = (*last_end).max(end)
+            }
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
@@ -0,0 +1,52 @@
+use cryo_freeze::ParseError;
+
+use crate::summaries::{print_bullet, print_header};
+
+/// collection profiles selectable with --profile, as embedded toml configs
+pub(crate) static COLLECTION_PROFILES: [(&str, &str); 3] = [
+    ("accounting", include_str!("../profiles/accounting.toml")),
+    ("mev", include_str!("../profiles/mev.toml")),
+    ("nft", include_str!("../profiles/nft.toml")),
+];
+
+/// list collection profiles, or print the toml of a profile to copy and customize
+pub(crate) fn run(command_args: &[String]) -> Result<(), ParseError> {
+    match command_args {
+        [] => {
+            print_header("collection profiles");
+            for (name, toml) in COLLECTION_PROFILES.iter() {
+                print_bullet(name, describe(toml));
+            }
+            println!(
+                "\nuse with `cryo --profile <NAME>`, print a profile with `cryo profiles <NAME>`"
+            );
+            Ok(())
+        }
+        [name] => {
+            print!("{}", profile_toml(name)?);
+            Ok(())
+        }
+        _ => Err(ParseError::ParseError("usage: cryo profiles [NAME]".to_string())),
+    }
+}
+
+/// toml of a collection profile
+pub(crate) fn profile_toml(name: &str) -> Result<&'static str, ParseError> {
+    match COLLECTION_PROFILES.iter().find(|(profile, _)| *profile == name) {
+        Some((_, toml)) => Ok(toml),
+        None => {
+            let names: Vec<&str> = COLLECTION_PROFILES.iter().map(|(name, _)| *name).collect();
+            Err(ParseError::ParseError(format!(
+                "unknown profile {}, use one of {}",
+                name,
+                names.join(", ")
+            )))
+        }
+    }
+}
+
+/// description in the first comment of a profile, e.g. `# mev: transactions, ...`
+fn describe(toml: &str) -> &str {
+    let first_line = toml.lines().next().unwrap_or_default().trim_start_matches('#').trim();
+    first_line.split_once(": ").map(|(_, description)| description).unwrap_or(first_line)
+}
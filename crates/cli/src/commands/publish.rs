@@ -0,0 +1,72 @@
+use std::path::Path;
+
+use thousands::Separable;
+
+use cryo_freeze::{ParseError, MANIFEST_NAME};
+
+use super::{car::write_car, output_dir::list_files, torrent::build_torrent};
+use crate::summaries::{print_bullet, print_header};
+
+/// package an output directory for sharing as a torrent or an IPFS CAR, printing content hashes
+///
+/// the package holds committed cryo files, the manifest of their checksums, and any manifest
+/// signatures, so downloaders can check files with `cryo verify`
+pub(crate) fn run(command_args: &[String]) -> Result<(), ParseError> {
+    let usage = "usage: cryo publish <torrent|car> [DIR] [OUTPUT]";
+    let (format, dir, output) = match command_args {
+        [format] => (format, ".", None),
+        [format, dir] => (format, dir.as_str(), None),
+        [format, dir, output] => (format, dir.as_str(), Some(output.clone())),
+        _ => return Err(ParseError::ParseError(usage.to_string())),
+    };
+    let extension = match format.as_str() {
+        "torrent" => "torrent",
+        "car" => "car",
+        _ => return Err(ParseError::ParseError(usage.to_string())),
+    };
+
+    // name the package after the output directory
+    let canonical = std::fs::canonicalize(dir)
+        .map_err(|_e| ParseError::ParseError(format!("could not read directory {}", dir)))?;
+    let name = canonical
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "cryo".to_string());
+    let output = output.unwrap_or_else(|| format!("{}.{}", name, extension));
+
+    let files = list_files(dir)?;
+    if !files.iter().any(|filename| filename == MANIFEST_NAME) {
+        eprintln!("warning: {} has no {}, files cannot be verified", dir, MANIFEST_NAME);
+    }
+    let n_bytes: u64 = files
+        .iter()
+        .filter_map(|filename| std::fs::metadata(Path::new(dir).join(filename)).ok())
+        .map(|metadata| metadata.len())
+        .sum();
+
+    print_header(format!("publishing {}", dir));
+    print_bullet("files", files.len().to_string());
+    print_bullet("bytes", n_bytes.separate_with_commas());
+    match extension {
+        "torrent" => {
+            let torrent = build_torrent(dir, &name, &files)?;
+            std::fs::write(&output, &torrent.bytes)
+                .map_err(|_e| ParseError::ParseError(format!("could not write {}", output)))?;
+            print_bullet("output", &output);
+            print_bullet("piece length", torrent.piece_length.separate_with_commas());
+            print_bullet("pieces", torrent.n_pieces.separate_with_commas());
+            print_bullet("info hash", &torrent.info_hash);
+            let magnet = format!("magnet:?xt=urn:btih:{}&dn={}", torrent.info_hash, name);
+            print_bullet("magnet", magnet);
+        }
+        _ => {
+            let car = write_car(dir, &files, &output)?;
+            print_bullet("output", &output);
+            print_bullet("root cid", &car.root);
+            for (filename, cid) in car.files.iter() {
+                print_bullet(filename, cid);
+            }
+        }
+    }
+    Ok(())
+}
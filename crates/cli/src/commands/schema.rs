@@ -0,0 +1,110 @@
+use cryo_freeze::{column_description, Dataset, Datatype, ParseError, Vm};
+
+use crate::{
+    args::Args,
+    parse,
+    summaries::{print_bullet, print_header},
+};
+
+/// print the columns of datasets, or all datasets, with their types and descriptions
+///
+/// with --markdown, print reference tables generated from the dataset definitions, so that
+/// documentation of columns cannot drift from the code
+pub(crate) fn run(args: &Args, command_args: &[String]) -> Result<(), ParseError> {
+    let mut datatypes = match command_args {
+        [] => Datatype::variants(),
+        names => parse::parse_datatypes(&names.to_vec())?,
+    };
+    datatypes.sort_by_key(|datatype| datatype.dataset().name());
+    datatypes.dedup();
+    match args.markdown {
+        true => print_markdown(&datatypes),
+        false => {
+            for (i, datatype) in datatypes.iter().enumerate() {
+                if i > 0 {
+                    println!();
+                }
+                print_columns(datatype.dataset().as_ref());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// default columns in default order, then other columns by name
+fn ordered_columns(dataset: &dyn Dataset) -> Vec<&'static str> {
+    let column_types = dataset.column_types();
+    let default_columns: Vec<&'static str> = dataset
+        .default_columns()
+        .into_iter()
+        .filter(|column| column_types.contains_key(column))
+        .collect();
+    let mut other_columns: Vec<&'static str> =
+        column_types.into_keys().filter(|column| !default_columns.contains(column)).collect();
+    other_columns.sort();
+    default_columns.into_iter().chain(other_columns).collect()
+}
+
+fn print_columns(dataset: &dyn Dataset) {
+    print_header(dataset.name());
+    let column_types = dataset.column_types();
+    let default_columns = dataset.default_columns();
+    let nullable_columns = dataset.nullable_columns();
+    for column in ordered_columns(dataset) {
+        let mut value = column_types[column].as_str().to_string();
+        if default_columns.contains(&column) {
+            value.push_str(", default");
+        }
+        if nullable_columns.contains(&column) {
+            value.push_str(", nullable");
+        }
+        if let Some(description) = column_description(dataset, column) {
+            value.push_str(&format!(", {}", description));
+        }
+        print_bullet(column, value);
+    }
+}
+
+fn print_markdown(datatypes: &[Datatype]) {
+    println!("# cryo datasets\n");
+    println!("Generated by `cryo schema --markdown`. Columns marked as default are collected");
+    println!("unless `--columns` or `--exclude-columns` say otherwise. Binary columns are written");
+    println!("as hex strings with `--hex` and in formats without a binary type.");
+    for datatype in datatypes.iter() {
+        let dataset = datatype.dataset();
+        let column_types = dataset.column_types();
+        let default_columns = dataset.default_columns();
+        let nullable_columns = dataset.nullable_columns();
+        println!("\n## {}\n", dataset.name());
+        if dataset.vm() != Vm::Evm {
+            println!("Requires `--vm {}`.\n", dataset.vm().name());
+        }
+        println!("Sorted by {}.\n", markdown_sort(&dataset.default_sort()));
+        println!("| column | type | default | nullable | description |");
+        println!("|-|-|-|-|-|");
+        for column in ordered_columns(dataset.as_ref()) {
+            println!(
+                "| `{}` | {} | {} | {} | {} |",
+                column,
+                column_types[column].as_str(),
+                yes_or_blank(default_columns.contains(&column)),
+                yes_or_blank(nullable_columns.contains(&column)),
+                column_description(dataset.as_ref(), column).unwrap_or_default(),
+            );
+        }
+    }
+}
+
+fn yes_or_blank(value: bool) -> &'static str {
+    match value {
+        true => "yes",
+        false => "",
+    }
+}
+
+fn markdown_sort(sort: &[String]) -> String {
+    match sort.is_empty() {
+        true => "collection order".to_string(),
+        false => sort.iter().map(|column| format!("`{}`", column)).collect::<Vec<_>>().join(", "),
+    }
+}
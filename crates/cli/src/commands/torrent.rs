@@ -0,0 +1,177 @@
+use std::{collections::BTreeMap, io::Read, path::Path};
+
+use sha1::{Digest, Sha1};
+
+use cryo_freeze::ParseError;
+
+/// smallest piece length of generated torrents
+const MIN_PIECE_LENGTH: u64 = 1 << 18;
+
+/// largest piece length of generated torrents
+const MAX_PIECE_LENGTH: u64 = 1 << 24;
+
+/// piece length is doubled until a torrent has at most this many pieces
+const TARGET_PIECES: u64 = 2000;
+
+/// bencoded value
+enum Bencode {
+    Int(u64),
+    Bytes(Vec<u8>),
+    List(Vec<Bencode>),
+    Dict(BTreeMap<&'static str, Bencode>),
+}
+
+impl Bencode {
+    fn str(value: &str) -> Bencode {
+        Bencode::Bytes(value.as_bytes().to_vec())
+    }
+
+    fn encode(&self, out: &mut Vec<u8>) {
+        match self {
+            Bencode::Int(value) => out.extend(format!("i{}e", value).as_bytes()),
+            Bencode::Bytes(bytes) => {
+                out.extend(format!("{}:", bytes.len()).as_bytes());
+                out.extend(bytes);
+            }
+            Bencode::List(items) => {
+                out.push(b'l');
+                items.iter().for_each(|item| item.encode(out));
+                out.push(b'e');
+            }
+            // keys are sorted by the BTreeMap, as bencode requires
+            Bencode::Dict(items) => {
+                out.push(b'd');
+                for (key, value) in items.iter() {
+                    Bencode::str(key).encode(out);
+                    value.encode(out);
+                }
+                out.push(b'e');
+            }
+        }
+    }
+}
+
+/// a generated torrent file
+pub(crate) struct Torrent {
+    /// hex sha-1 of the bencoded info dictionary
+    pub(crate) info_hash: String,
+    pub(crate) piece_length: u64,
+    pub(crate) n_pieces: u64,
+    /// bencoded torrent file
+    pub(crate) bytes: Vec<u8>,
+}
+
+/// build a trackerless multi-file torrent of files of dir, named after name
+pub(crate) fn build_torrent(
+    dir: &str,
+    name: &str,
+    files: &[String],
+) -> Result<Torrent, ParseError> {
+    let read_error =
+        |filename: &str| ParseError::ParseError(format!("could not read {}", filename));
+    let mut lengths = Vec::new();
+    for filename in files.iter() {
+        let metadata =
+            std::fs::metadata(Path::new(dir).join(filename)).map_err(|_e| read_error(filename))?;
+        lengths.push(metadata.len());
+    }
+    let total_length: u64 = lengths.iter().sum();
+    let mut piece_length = MIN_PIECE_LENGTH;
+    while total_length / piece_length > TARGET_PIECES && piece_length < MAX_PIECE_LENGTH {
+        piece_length *= 2;
+    }
+
+    // pieces span file boundaries, hashing files as one concatenated stream
+    let mut pieces = Vec::new();
+    let mut piece = Vec::with_capacity(piece_length as usize);
+    let mut buffer = vec![0; 1 << 16];
+    for filename in files.iter() {
+        let mut file = std::fs::File::open(Path::new(dir).join(filename))
+            .map_err(|_e| read_error(filename))?;
+        loop {
+            let n = file.read(&mut buffer).map_err(|_e| read_error(filename))?;
+            if n == 0 {
+                break
+            }
+            let mut remaining = &buffer[..n];
+            while !remaining.is_empty() {
+                let take = remaining.len().min(piece_length as usize - piece.len());
+                piece.extend_from_slice(&remaining[..take]);
+                remaining = &remaining[take..];
+                if piece.len() == piece_length as usize {
+                    pieces.extend(Sha1::digest(&piece));
+                    piece.clear();
+                }
+            }
+        }
+    }
+    if !piece.is_empty() {
+        pieces.extend(Sha1::digest(&piece));
+    }
+    let n_pieces = (pieces.len() / 20) as u64;
+
+    let file_list = files
+        .iter()
+        .zip(lengths.iter())
+        .map(|(filename, length)| {
+            Bencode::Dict(BTreeMap::from([
+                ("length", Bencode::Int(*length)),
+                ("path", Bencode::List(vec![Bencode::str(filename)])),
+            ]))
+        })
+        .collect();
+    let info = Bencode::Dict(BTreeMap::from([
+        ("files", Bencode::List(file_list)),
+        ("name", Bencode::str(name)),
+        ("piece length", Bencode::Int(piece_length)),
+        ("pieces", Bencode::Bytes(pieces)),
+    ]));
+    let mut info_bytes = Vec::new();
+    info.encode(&mut info_bytes);
+    let info_hash = hex::encode(Sha1::digest(&info_bytes));
+
+    let creation_date = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let torrent = Bencode::Dict(BTreeMap::from([
+        ("created by", Bencode::str(&format!("cryo {}", env!("CARGO_PKG_VERSION")))),
+        ("creation date", Bencode::Int(creation_date)),
+        ("info", info),
+    ]));
+    let mut bytes = Vec::new();
+    torrent.encode(&mut bytes);
+    Ok(Torrent { info_hash, piece_length, n_pieces, bytes })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bencode() {
+        let value = Bencode::Dict(BTreeMap::from([
+            ("b", Bencode::List(vec![Bencode::Int(0), Bencode::str("spam")])),
+            ("a", Bencode::Int(42)),
+        ]));
+        let mut bytes = Vec::new();
+        value.encode(&mut bytes);
+        assert_eq!(bytes, b"d1:ai42e1:bli0e4:spamee");
+    }
+
+    #[test]
+    fn info_hash() {
+        let dir = std::env::temp_dir().join(format!("cryo_torrent_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("hello.txt"), b"hello world").unwrap();
+        let torrent =
+            build_torrent(&dir.to_string_lossy(), "cryo", &["hello.txt".to_string()]).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // sha-1 of d5:filesld6:lengthi11e4:pathl9:hello.txteee4:name4:cryo
+        // 12:piece lengthi262144e6:pieces20:<sha-1 of "hello world">e
+        assert_eq!(torrent.info_hash, "acc083167f011fe9a5beea58491fdbe51b45b1ab");
+        assert_eq!(torrent.piece_length, 262144);
+        assert_eq!(torrent.n_pieces, 1);
+    }
+}
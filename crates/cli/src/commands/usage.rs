@@ -0,0 +1,59 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Local};
+use thousands::Separable;
+
+use cryo_freeze::{ParseError, UsageLedger, USAGE_LEDGER_ENV};
+
+use crate::summaries::{print_bullet, print_header};
+
+/// print requests and credits of each provider by month, summed over the runs in the ledger
+pub(crate) fn run(command_args: &[String]) -> Result<(), ParseError> {
+    let ledger = match command_args {
+        [] => UsageLedger::default_ledger().ok_or_else(|| {
+            ParseError::ParseError(format!("no home directory, set {}", USAGE_LEDGER_ENV))
+        })?,
+        [path] => UsageLedger::new(path),
+        _ => return Err(ParseError::ParseError("usage: cryo usage [LEDGER]".to_string())),
+    };
+    let entries = ledger.read().map_err(|e| ParseError::ParseError(e.to_string()))?;
+    if entries.is_empty() {
+        println!("no usage recorded in {}", ledger.path().display());
+        return Ok(())
+    }
+
+    // provider -> month -> (runs, requests, credits)
+    let mut usage: BTreeMap<&str, BTreeMap<String, (u64, u64, u64)>> = BTreeMap::new();
+    for entry in entries.iter() {
+        let time = std::time::UNIX_EPOCH + std::time::Duration::from_secs(entry.timestamp);
+        let month = DateTime::<Local>::from(time).format("%Y-%m").to_string();
+        let totals = usage.entry(entry.provider.as_str()).or_default().entry(month).or_default();
+        totals.0 += 1;
+        totals.1 += entry.n_requests;
+        totals.2 += entry.credits;
+    }
+
+    for (i, (provider, months)) in usage.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        print_header(provider);
+        for (month, (n_runs, n_requests, credits)) in months.iter().rev() {
+            print_bullet(month, format_usage(*n_runs, *n_requests, *credits));
+        }
+        let (n_runs, n_requests, credits) = months.values().fold((0, 0, 0), |total, month| {
+            (total.0 + month.0, total.1 + month.1, total.2 + month.2)
+        });
+        print_bullet("total", format_usage(n_runs, n_requests, credits));
+    }
+    Ok(())
+}
+
+fn format_usage(n_runs: u64, n_requests: u64, credits: u64) -> String {
+    format!(
+        "{} runs, {} requests, {} credits",
+        n_runs.separate_with_commas(),
+        n_requests.separate_with_commas(),
+        credits.separate_with_commas()
+    )
+}
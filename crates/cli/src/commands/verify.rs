@@ -0,0 +1,75 @@
+use std::{collections::BTreeMap, path::Path};
+
+use cryo_freeze::{
+    sha256_file, signatures::signature_path, verify_manifest, Manifest, ManifestFile, ParseError,
+    SignatureScheme, MANIFEST_NAME,
+};
+
+use crate::summaries::{print_bullet, print_header};
+
+/// verify the files of an output directory against the checksums of its manifest
+///
+/// with a public key, the manifest signature is verified first, using minisign or gpg depending
+/// on which signature file is present
+pub(crate) fn run(command_args: &[String]) -> Result<(), ParseError> {
+    let usage = "usage: cryo verify [DIR] [PUBLIC_KEY]";
+    let (dir, public_key) = match command_args {
+        [] => (".", None),
+        [dir] => (dir.as_str(), None),
+        [dir, public_key] => (dir.as_str(), Some(public_key.as_str())),
+        _ => return Err(ParseError::ParseError(usage.to_string())),
+    };
+
+    print_header(format!("verifying {}", dir));
+    if let Some(public_key) = public_key {
+        let scheme = [SignatureScheme::Minisign, SignatureScheme::Gpg]
+            .into_iter()
+            .find(|scheme| signature_path(dir, *scheme).exists())
+            .ok_or_else(|| {
+                ParseError::ParseError(format!("no signature of {} in {}", MANIFEST_NAME, dir))
+            })?;
+        verify_manifest(dir, scheme, public_key)
+            .map_err(|e| ParseError::ParseError(format!("invalid signature: {}", e)))?;
+        print_bullet("signature", format!("valid {:?} signature", scheme).to_lowercase());
+    }
+
+    // the latest commit of each file holds its current checksum
+    let entries = Manifest::read(dir)
+        .map_err(|_e| ParseError::ParseError(format!("could not read {}", MANIFEST_NAME)))?;
+    let files: BTreeMap<String, ManifestFile> = entries
+        .into_iter()
+        .flat_map(|entry| entry.files)
+        .map(|file| (file.filename.clone(), file))
+        .collect();
+
+    let mut n_valid = 0;
+    let mut n_unchecked = 0;
+    let mut invalid = Vec::new();
+    for (filename, file) in files.iter() {
+        let path = Path::new(dir).join(filename).to_string_lossy().to_string();
+        let expected = match &file.sha256 {
+            Some(expected) => expected,
+            None => {
+                n_unchecked += 1;
+                continue
+            }
+        };
+        match sha256_file(&path) {
+            Ok(actual) if &actual == expected => n_valid += 1,
+            Ok(_) => invalid.push(format!("{}: checksum mismatch", filename)),
+            Err(_) => invalid.push(format!("{}: missing", filename)),
+        }
+    }
+    print_bullet("valid files", n_valid.to_string());
+    if n_unchecked > 0 {
+        print_bullet("files without checksum", n_unchecked.to_string());
+    }
+    if !invalid.is_empty() {
+        print_bullet("invalid files", invalid.len().to_string());
+        for message in invalid.iter() {
+            println!("    {}", message);
+        }
+        return Err(ParseError::ParseError(format!("{} files failed verification", invalid.len())))
+    }
+    Ok(())
+}
@@ -0,0 +1,152 @@
+use std::collections::HashSet;
+
+use clap::{CommandFactory, FromArgMatches, Parser};
+
+use cryo_freeze::ParseError;
+
+use crate::{args::Args, commands::profiles::profile_toml};
+
+/// apply the options stored by `--config <PATH>` and `--profile <NAME>` to args
+///
+/// options set in args take precedence over options given in the config file, which take
+/// precedence over options of the profile. an option counts as set when its value differs from
+/// its default
+pub(crate) fn apply_config(args: Args) -> Result<Args, ParseError> {
+    let mut tables = Vec::new();
+    if let Some(path) = &args.config {
+        tables.push(read_config(path)?);
+    }
+    if let Some(name) = &args.profile {
+        tables.push(read_profile(name)?);
+    }
+    if tables.is_empty() {
+        return Ok(args)
+    }
+
+    let defaults = to_values(&default_args()?)?;
+    let set: serde_json::Map<String, serde_json::Value> = to_values(&args)?
+        .into_iter()
+        .filter(|(key, value)| defaults.get(key) != Some(value))
+        .collect();
+    let mut given: HashSet<String> = set.keys().map(|key| key.replace('_', "-")).collect();
+
+    // datatypes that were set replace the datatypes of the config file
+    let mut config_args = vec!["cryo".to_string()];
+    config_args.extend(args.datatype.iter().cloned());
+    for table in tables.iter() {
+        config_args.extend(table_to_args(table, &given)?);
+        given.extend(table.keys().map(|key| key.replace('_', "-")));
+    }
+    let configured = Args::try_parse_from(config_args).map_err(|e| {
+        let message = e.to_string();
+        let message = message.lines().next().unwrap_or_default().trim_start_matches("error: ");
+        ParseError::ParseError(format!("invalid config: {}", message))
+    })?;
+
+    let mut values = to_values(&configured)?;
+    values.extend(set);
+    serde_json::from_value(serde_json::Value::Object(values))
+        .map_err(|e| ParseError::ParseError(format!("could not apply config: {}", e)))
+}
+
+fn default_args() -> Result<Args, ParseError> {
+    let matches = Args::command().ignore_errors(true).get_matches_from(["cryo"]);
+    Args::from_arg_matches(&matches)
+        .map_err(|e| ParseError::ParseError(format!("could not apply config: {}", e)))
+}
+
+fn to_values(args: &Args) -> Result<serde_json::Map<String, serde_json::Value>, ParseError> {
+    match serde_json::to_value(args) {
+        Ok(serde_json::Value::Object(values)) => Ok(values),
+        _ => Err(ParseError::ParseError("could not apply config".to_string())),
+    }
+}
+
+fn read_profile(name: &str) -> Result<toml::Table, ParseError> {
+    profile_toml(name)?
+        .parse()
+        .map_err(|e| ParseError::ParseError(format!("could not parse profile {}: {}", name, e)))
+}
+
+/// read a toml config file
+pub(crate) fn read_config(path: &str) -> Result<toml::Table, ParseError> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| ParseError::ParseError(format!("could not read config {}: {}", path, e)))?;
+    contents
+        .parse()
+        .map_err(|e| ParseError::ParseError(format!("could not parse config {}: {}", path, e)))
+}
+
+/// convert config table into cli arguments, skipping options that were already given
+pub(crate) fn table_to_args(
+    table: &toml::Table,
+    given: &HashSet<String>,
+) -> Result<Vec<String>, ParseError> {
+    let mut positional = Vec::new();
+    let mut options = Vec::new();
+    for (key, value) in table.iter() {
+        let flag = key.replace('_', "-");
+        if given.contains(&flag) {
+            continue
+        }
+        let values = match value {
+            toml::Value::Boolean(true) => vec![],
+            toml::Value::Boolean(false) => continue,
+            toml::Value::Array(array) => {
+                array.iter().map(value_to_string).collect::<Result<Vec<_>, _>>()?
+            }
+            value => vec![value_to_string(value)?],
+        };
+        if key == "datatype" {
+            positional.extend(values);
+        } else if values.is_empty() {
+            options.push(format!("--{}", flag));
+        } else {
+            for value in values {
+                options.push(format!("--{}", flag));
+                options.push(value);
+            }
+        }
+    }
+    positional.extend(options);
+    Ok(positional)
+}
+
+fn value_to_string(value: &toml::Value) -> Result<String, ParseError> {
+    match value {
+        toml::Value::String(value) => Ok(value.clone()),
+        toml::Value::Integer(value) => Ok(value.to_string()),
+        toml::Value::Float(value) => Ok(value.to_string()),
+        toml::Value::Boolean(value) => Ok(value.to_string()),
+        _ => Err(ParseError::ParseError("unsupported value type in config".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apply_config_file() {
+        let path = std::env::temp_dir().join(format!("cryo_config_{}.toml", std::process::id()));
+        let contents = "datatype = [\"blocks\"]\nblocks = [\"0:100\"]\nchunk_size = 50\ncsv = true";
+        std::fs::write(&path, contents).unwrap();
+        let config = path.to_string_lossy().to_string();
+
+        let args = Args::parse_from(["cryo", "--config", &config]);
+        let applied = apply_config(args).unwrap();
+        assert_eq!(applied.datatype, vec!["blocks"]);
+        assert_eq!(applied.blocks, Some(vec!["0:100".to_string()]));
+        assert_eq!(applied.chunk_size, 50);
+        assert!(applied.csv);
+
+        // options that were set take precedence, and applying the config again changes nothing
+        let args = Args::parse_from(["cryo", "logs", "--config", &config, "--chunk-size", "20"]);
+        let applied = apply_config(apply_config(args).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(applied.datatype, vec!["logs"]);
+        assert_eq!(applied.blocks, Some(vec!["0:100".to_string()]));
+        assert_eq!(applied.chunk_size, 20);
+        assert!(applied.csv);
+    }
+}
@@ -8,6 +8,8 @@
 ))]
 
 mod args;
+mod commands;
+mod config;
 mod parse;
 mod run;
 mod summaries;
@@ -16,6 +18,10 @@ mod summaries;
 use eyre as _;
 use tokio as _;
 
+// used in benches but not lib.rs
+#[cfg(test)]
+use criterion as _;
+
 pub use args::Args;
 pub use parse::parse_opts;
 pub use run::run;
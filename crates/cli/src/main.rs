@@ -3,6 +3,8 @@
 use clap::Parser;
 
 mod args;
+mod commands;
+mod config;
 mod parse;
 mod run;
 mod summaries;
@@ -10,9 +12,17 @@ mod summaries;
 pub use args::Args;
 use eyre::Result;
 
+#[cfg(feature = "mimalloc")]
+#[global_allocator]
+static ALLOC: mimalloc::MiMalloc = mimalloc::MiMalloc;
+
+#[cfg(all(feature = "jemalloc", not(feature = "mimalloc"), not(target_env = "msvc")))]
+#[global_allocator]
+static ALLOC: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
+
 #[tokio::main]
 async fn main() -> Result<()> {
-    let args = Args::parse();
+    let args = Args { record_usage: true, ..Args::parse() };
     match run::run(args).await {
         Ok(Some(_freeze_summary)) => Ok(()),
         Ok(None) => Ok(()),
@@ -1,15 +1,33 @@
-use std::sync::Arc;
-
 use cryo_freeze::{FileOutput, MultiQuery, ParseError, Source};
 
-use crate::args::Args;
+use crate::{args::Args, config};
 
 use super::{file_output, query, source};
 
 /// parse options for running freeze
 pub async fn parse_opts(args: &Args) -> Result<(MultiQuery, Source, FileOutput), ParseError> {
+    let args = &config::apply_config(args.clone())?;
     let source = source::parse_source(args).await?;
-    let query = query::parse_query(args, Arc::clone(&source.provider)).await?;
-    let sink = file_output::parse_file_output(args, &source)?;
+    let (query, sink) = parse_query_and_sink(args, &source).await?;
     Ok((query, source, sink))
 }
+
+/// parse options for running freeze with a source shared across runs
+pub(crate) async fn parse_query_and_sink(
+    args: &Args,
+    source: &Source,
+) -> Result<(MultiQuery, FileOutput), ParseError> {
+    let query = query::parse_query(args, source).await?;
+    for datatype in query.schemas.keys() {
+        let vm = datatype.dataset().vm();
+        if !source.vm.supports(vm) {
+            return Err(ParseError::ParseError(format!(
+                "{} requires --vm {}",
+                datatype.dataset().name(),
+                vm.name()
+            )));
+        }
+    }
+    let sink = file_output::parse_file_output(args, source)?;
+    Ok((query, sink))
+}
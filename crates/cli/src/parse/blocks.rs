@@ -1,44 +1,135 @@
-use std::sync::Arc;
-
-use ethers::prelude::*;
-
-use cryo_freeze::{BlockChunk, Chunk, ChunkData, ParseError, Subchunk};
+use cryo_freeze::{
+    lookup::{block_timestamp, first_block_after},
+    AdaptiveChunks, BlockChunk, ByteTarget, Chunk, ChunkData, ChunkStrategy, CollectError,
+    FixedBlocks, ParseError, Source, TimestampAligned, TxCountTarget,
+};
 
+use super::file_output::parse_bytes;
 use crate::args::Args;
 
-pub(crate) async fn parse_blocks(
-    args: &Args,
-    provider: Arc<Provider<Http>>,
-) -> Result<Vec<Chunk>, ParseError> {
+const SECONDS_PER_DAY: u64 = 86400;
+
+pub(crate) async fn parse_blocks(args: &Args, source: &Source) -> Result<Vec<Chunk>, ParseError> {
     // parse inputs into BlockChunks
     let block_chunks = match &args.blocks {
-        Some(inputs) => parse_block_inputs(inputs, &provider).await?,
+        Some(inputs) => parse_block_inputs(inputs, source).await?,
         None => return Err(ParseError::ParseError("could not parse block inputs".to_string())),
     };
 
-    postprocess_block_chunks(block_chunks, args, provider).await
+    postprocess_block_chunks(block_chunks, args, source).await
 }
 
-async fn postprocess_block_chunks(
-    block_chunks: Vec<BlockChunk>,
+/// parse block range spanning the given duration before the latest block, e.g. --last 24h
+pub(crate) async fn parse_last_blocks(
     args: &Args,
-    provider: Arc<Provider<Http>>,
+    source: &Source,
 ) -> Result<Vec<Chunk>, ParseError> {
-    // align
-    let block_chunks = if args.align {
-        block_chunks.into_iter().filter_map(|x| x.align(args.chunk_size)).collect()
-    } else {
-        block_chunks
+    let duration = match &args.last {
+        Some(duration) => parse_duration(duration)?,
+        None => return Err(ParseError::ParseError("no duration given".to_string())),
     };
+    let latest_block = source
+        .latest_block_number()
+        .await
+        .map_err(|_e| ParseError::ParseError("Error retrieving latest block number".to_string()))?;
+    let latest_timestamp =
+        block_timestamp(latest_block, &source.provider).await.map_err(lookup_error)?;
+    let start_block = first_block_after(
+        latest_timestamp.saturating_sub(duration),
+        0,
+        latest_block,
+        &source.provider,
+    )
+    .await
+    .map_err(lookup_error)?;
 
-    // split block range into chunks
-    let block_chunks = match args.n_chunks {
-        Some(n_chunks) => block_chunks.subchunk_by_count(&n_chunks),
-        None => block_chunks.subchunk_by_size(&args.chunk_size),
+    // range ends are exclusive when subchunking, so extend by one to include latest block
+    let block_chunks = vec![BlockChunk::Range(start_block, latest_block + 1)];
+    postprocess_block_chunks(block_chunks, args, source).await
+}
+
+/// parse duration such as 90s, 30m, 24h, 7d, or 2w into seconds
+fn parse_duration(duration: &str) -> Result<u64, ParseError> {
+    let invalid = || {
+        ParseError::ParseError(format!(
+            "invalid duration: {}, use a number with unit s, m, h, d, or w, e.g. 24h",
+            duration
+        ))
+    };
+    let duration = duration.trim();
+    let split = duration.find(|c: char| !c.is_ascii_digit()).ok_or_else(invalid)?;
+    let (number, unit) = duration.split_at(split);
+    let multiplier = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => SECONDS_PER_DAY,
+        "w" => 7 * SECONDS_PER_DAY,
+        _ => return Err(invalid()),
     };
+    match number.parse::<u64>() {
+        Ok(number) if number > 0 => Ok(number * multiplier),
+        _ => Err(invalid()),
+    }
+}
+
+/// read explicit block chunks from csv file of start_block,end_block rows
+///
+/// bounds are inclusive, matching the ranges in output file names, and a header row is optional
+pub(crate) fn parse_chunk_file(args: &Args) -> Result<Vec<Chunk>, ParseError> {
+    let path = match &args.chunks {
+        Some(path) => path,
+        None => return Err(ParseError::ParseError("no chunk file given".to_string())),
+    };
+    let contents = std::fs::read_to_string(path).map_err(|e| {
+        ParseError::ParseError(format!("could not read chunk file {}: {}", path, e))
+    })?;
+    let mut chunks = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue
+        }
+        let invalid_row = || {
+            ParseError::ParseError(format!(
+                "invalid row {} in chunk file {}, expected start_block,end_block",
+                i + 1,
+                path
+            ))
+        };
+        let bounds: Vec<&str> = line.split(',').map(|bound| bound.trim()).collect();
+        let (start_block, end_block) = match bounds.as_slice() {
+            [start, end] => match (start.parse::<u64>(), end.parse::<u64>()) {
+                (Ok(start_block), Ok(end_block)) => (start_block, end_block),
+                // header row
+                _ if i == 0 => continue,
+                _ => return Err(invalid_row()),
+            },
+            _ => return Err(invalid_row()),
+        };
+        if end_block < start_block {
+            return Err(invalid_row())
+        }
+        chunks.push(Chunk::Block(BlockChunk::Range(start_block, end_block)));
+    }
+    if chunks.is_empty() {
+        return Err(ParseError::ParseError(format!("chunk file {} has no chunks", path)))
+    }
+    Ok(chunks)
+}
+
+async fn postprocess_block_chunks(
+    block_chunks: Vec<BlockChunk>,
+    args: &Args,
+    source: &Source,
+) -> Result<Vec<Chunk>, ParseError> {
+    // split block range into chunks
+    let strategy = parse_chunk_strategy(args)?;
+    let block_chunks =
+        strategy.split(block_chunks, &source.provider).await.map_err(lookup_error)?;
 
     // apply reorg buffer
-    let block_chunks = apply_reorg_buffer(block_chunks, args.reorg_buffer, &provider).await?;
+    let block_chunks = apply_reorg_buffer(block_chunks, args.reorg_buffer, source).await?;
 
     // put into Chunk enums
     let chunks: Vec<Chunk> = block_chunks.iter().map(|x| Chunk::Block(x.clone())).collect();
@@ -48,28 +139,28 @@ async fn postprocess_block_chunks(
 
 pub(crate) async fn get_default_block_chunks(
     args: &Args,
-    provider: Arc<Provider<Http>>,
+    source: &Source,
 ) -> Result<Vec<Chunk>, ParseError> {
-    let block_chunks = parse_block_inputs(&vec!["0:latest".to_string()], &provider).await?;
-    postprocess_block_chunks(block_chunks, args, provider).await
+    let block_chunks = parse_block_inputs(&vec!["0:latest".to_string()], source).await?;
+    postprocess_block_chunks(block_chunks, args, source).await
 }
 
 /// parse block numbers to freeze
 async fn parse_block_inputs(
     inputs: &Vec<String>,
-    provider: &Provider<Http>,
+    source: &Source,
 ) -> Result<Vec<BlockChunk>, ParseError> {
     match inputs.len() {
         1 => {
             let first_input = inputs.get(0).ok_or_else(|| {
                 ParseError::ParseError("Failed to get the first input".to_string())
             })?;
-            parse_block_token(first_input, true, provider).await.map(|x| vec![x])
+            parse_block_token(first_input, true, source).await.map(|x| vec![x])
         }
         _ => {
             let mut chunks = Vec::new();
             for input in inputs {
-                chunks.push(parse_block_token(input, false, provider).await?);
+                chunks.push(parse_block_token(input, false, source).await?);
             }
             Ok(chunks)
         }
@@ -85,20 +176,20 @@ enum RangePosition {
 async fn parse_block_token(
     s: &str,
     as_range: bool,
-    provider: &Provider<Http>,
+    source: &Source,
 ) -> Result<BlockChunk, ParseError> {
     let s = s.replace('_', "");
     let parts: Vec<&str> = s.split(':').collect();
     match parts.as_slice() {
         [block_ref] => {
-            let block = parse_block_number(block_ref, RangePosition::None, provider).await?;
+            let block = parse_block_number(block_ref, RangePosition::None, source).await?;
             Ok(BlockChunk::Numbers(vec![block]))
         }
         [first_ref, second_ref] => {
             let (start_block, end_block) = match (first_ref, second_ref) {
                 _ if first_ref.starts_with('-') => {
                     let end_block =
-                        parse_block_number(second_ref, RangePosition::Last, provider).await?;
+                        parse_block_number(second_ref, RangePosition::Last, source).await?;
                     let start_block = end_block
                         .checked_sub(first_ref[1..].parse::<u64>().map_err(|_e| {
                             ParseError::ParseError("start_block parse error".to_string())
@@ -110,7 +201,7 @@ async fn parse_block_token(
                 }
                 _ if second_ref.starts_with('+') => {
                     let start_block =
-                        parse_block_number(first_ref, RangePosition::First, provider).await?;
+                        parse_block_number(first_ref, RangePosition::First, source).await?;
                     let end_block = start_block
                         .checked_add(second_ref[1..].parse::<u64>().map_err(|_e| {
                             ParseError::ParseError("start_block parse error".to_string())
@@ -120,9 +211,9 @@ async fn parse_block_token(
                 }
                 _ => {
                     let start_block =
-                        parse_block_number(first_ref, RangePosition::First, provider).await?;
+                        parse_block_number(first_ref, RangePosition::First, source).await?;
                     let end_block =
-                        parse_block_number(second_ref, RangePosition::Last, provider).await?;
+                        parse_block_number(second_ref, RangePosition::Last, source).await?;
                     (start_block, end_block)
                 }
             };
@@ -146,18 +237,17 @@ async fn parse_block_token(
 async fn parse_block_number(
     block_ref: &str,
     range_position: RangePosition,
-    provider: &Provider<Http>,
+    source: &Source,
 ) -> Result<u64, ParseError> {
     match (block_ref, range_position) {
-        ("latest", _) => provider.get_block_number().await.map(|n| n.as_u64()).map_err(|_e| {
+        ("latest", _) => source.latest_block_number().await.map_err(|_e| {
             ParseError::ParseError("Error retrieving latest block number".to_string())
         }),
         ("", RangePosition::First) => Ok(0),
-        ("", RangePosition::Last) => {
-            provider.get_block_number().await.map(|n| n.as_u64()).map_err(|_e| {
-                ParseError::ParseError("Error retrieving last block number".to_string())
-            })
-        }
+        ("", RangePosition::Last) => source
+            .latest_block_number()
+            .await
+            .map_err(|_e| ParseError::ParseError("Error retrieving last block number".to_string())),
         ("", RangePosition::None) => Err(ParseError::ParseError("invalid input".to_string())),
         _ if block_ref.ends_with('B') | block_ref.ends_with('b') => {
             let s = &block_ref[..block_ref.len() - 1];
@@ -184,16 +274,91 @@ async fn parse_block_number(
     }
 }
 
+/// parse strategy for splitting blocks into chunks, e.g. blocks, bytes:256MB, txs:100000, or
+/// time:1d
+fn parse_chunk_strategy(args: &Args) -> Result<Box<dyn ChunkStrategy>, ParseError> {
+    let strategy = match (args.chunk_strategy.as_deref(), args.partition_by.as_deref()) {
+        (None, Some("day")) => return Ok(Box::new(TimestampAligned { interval: SECONDS_PER_DAY })),
+        (Some(_), Some("day")) => {
+            return Err(ParseError::ParseError(
+                "--partition-by day already splits chunks by day, omit --chunk-strategy"
+                    .to_string(),
+            ))
+        }
+        (Some(strategy), _) => strategy.trim(),
+        (None, _) => "blocks",
+    };
+    let (kind, value) = match strategy.split_once(':') {
+        Some((kind, value)) => (kind, Some(value.trim())),
+        None => (strategy, None),
+    };
+    if kind != "blocks" && (args.align || args.n_chunks.is_some()) {
+        return Err(ParseError::ParseError(
+            "--align and --n-chunks only apply to the blocks chunk strategy".to_string(),
+        ))
+    }
+    let invalid = || {
+        ParseError::ParseError(format!(
+            "invalid chunk strategy: {}, use blocks, bytes:SIZE, txs:N, or time:DURATION",
+            strategy
+        ))
+    };
+    match (kind, value) {
+        ("blocks", None) => Ok(Box::new(FixedBlocks {
+            chunk_size: args.chunk_size,
+            n_chunks: args.n_chunks,
+            align: args.align,
+        })),
+        ("bytes", Some(size)) => match parse_bytes(size)? {
+            0 => Err(invalid()),
+            target_bytes => Ok(Box::new(ByteTarget { target_bytes })),
+        },
+        ("txs", Some(n_txs)) => match n_txs.replace('_', "").parse::<u64>() {
+            Ok(target_txs) if target_txs > 0 => Ok(Box::new(TxCountTarget { target_txs })),
+            _ => Err(invalid()),
+        },
+        ("time", Some(duration)) => {
+            Ok(Box::new(TimestampAligned { interval: parse_duration(duration)? }))
+        }
+        _ => Err(invalid()),
+    }
+}
+
+/// parse resizing of chunks while collecting, which keeps chunks of block ranges contiguous
+pub(crate) fn parse_adaptive_chunks(args: &Args) -> Result<Option<AdaptiveChunks>, ParseError> {
+    let target_rows = match args.adaptive_chunks {
+        Some(0) => {
+            return Err(ParseError::ParseError("--adaptive-chunks must be positive".to_string()))
+        }
+        Some(target_rows) => target_rows,
+        None => return Ok(None),
+    };
+    let time_aligned = args.partition_by.as_deref() == Some("day") ||
+        args.chunk_strategy.as_deref().map(|s| s.trim().starts_with("time")).unwrap_or(false);
+    if time_aligned {
+        return Err(ParseError::ParseError(
+            "--adaptive-chunks would merge chunks across time boundaries, use it without time \
+            aligned chunks"
+                .to_string(),
+        ))
+    }
+    Ok(Some(AdaptiveChunks { target_rows }))
+}
+
+fn lookup_error(e: CollectError) -> ParseError {
+    ParseError::ParseError(e.to_string())
+}
+
 async fn apply_reorg_buffer(
     block_chunks: Vec<BlockChunk>,
     reorg_filter: u64,
-    provider: &Provider<Http>,
+    source: &Source,
 ) -> Result<Vec<BlockChunk>, ParseError> {
     match reorg_filter {
         0 => Ok(block_chunks),
         reorg_filter => {
-            let latest_block = match provider.get_block_number().await {
-                Ok(result) => result.as_u64(),
+            let latest_block = match source.latest_block_number().await {
+                Ok(result) => result,
                 Err(_e) => {
                     return Err(ParseError::ParseError("reorg buffer parse error".to_string()))
                 }
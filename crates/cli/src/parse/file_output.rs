@@ -1,9 +1,13 @@
-use std::fs;
+use std::{collections::BTreeSet, fs, io::IsTerminal};
 
 use polars::prelude::*;
 
-use cryo_freeze::{FileFormat, FileOutput, ParseError, Source};
+use cryo_freeze::{
+    CsvDialect, CsvQuoteStyle, FileFormat, FileOutput, Lineage, Manifest, ParseError, PartitionBy,
+    SignatureScheme, Signer, Source, MANIFEST_NAME, XLSX_MAX_ROWS,
+};
 
+use super::source::parse_endpoint;
 use crate::args::Args;
 
 pub(crate) fn parse_file_output(args: &Args, source: &Source) -> Result<FileOutput, ParseError> {
@@ -18,6 +22,7 @@ pub(crate) fn parse_file_output(args: &Args, source: &Source) -> Result<FileOutp
         Ok(_) => {}
         Err(e) => return Err(ParseError::ParseError(format!("Error creating directory: {}", e))),
     };
+    verify_chain_id(args, source.chain_id, &output_dir)?;
 
     let file_suffix = &args.file_suffix;
 
@@ -30,6 +35,47 @@ pub(crate) fn parse_file_output(args: &Args, source: &Source) -> Result<FileOutp
     );
 
     let format = parse_output_format(args)?;
+    if args.dictionary_encoding && !(args.hex && format == FileFormat::Parquet) {
+        return Err(ParseError::ParseError(
+            "--dictionary-encoding requires parquet output with --hex".to_string(),
+        ))
+    }
+    if args.decimal.is_some() && format != FileFormat::Parquet {
+        return Err(ParseError::ParseError("--decimal requires parquet output".to_string()))
+    }
+    if args.nested_traces && format != FileFormat::Parquet && format != FileFormat::Json {
+        return Err(ParseError::ParseError(
+            "--nested-traces requires parquet or json output".to_string(),
+        ))
+    }
+    if args.sqlite && args.partition_by.is_some() {
+        return Err(ParseError::ParseError(
+            "--partition-by cannot be combined with --sqlite".to_string(),
+        ))
+    }
+    if args.xlsx_max_rows > XLSX_MAX_ROWS {
+        return Err(ParseError::ParseError(format!(
+            "--xlsx-max-rows cannot exceed the xlsx limit of {} rows",
+            XLSX_MAX_ROWS
+        )))
+    }
+    if args.nested_traces && args.partition_by.is_some() {
+        return Err(ParseError::ParseError(
+            "--nested-traces cannot be combined with --partition-by".to_string(),
+        ))
+    }
+    let csv = parse_csv_dialect(args, &format)?;
+    if args.rollups == Some(0) {
+        return Err(ParseError::ParseError("--rollups must be positive".to_string()))
+    }
+    if args.stats_sidecars && args.sqlite {
+        return Err(ParseError::ParseError(
+            "--stats-sidecars cannot be combined with --sqlite".to_string(),
+        ))
+    }
+    if args.allow_lossy && !args.sqlite {
+        return Err(ParseError::ParseError("--allow-lossy requires --sqlite".to_string()))
+    }
     let file_prefix = parse_network_name(args, source.chain_id);
 
     let output = FileOutput {
@@ -40,36 +86,229 @@ pub(crate) fn parse_file_output(args: &Args, source: &Source) -> Result<FileOutp
         format,
         suffix: file_suffix.clone(),
         parquet_compression,
+        parquet_dictionary_encoding: args.dictionary_encoding,
         row_group_size,
+        max_output_rows: args.max_output_rows,
+        max_output_bytes: args.max_output_bytes.as_deref().map(parse_bytes).transpose()?,
+        prompt_on_output_limit: std::io::stdin().is_terminal(),
+        prompt_on_failure: args.prompt_on_failure && std::io::stdin().is_terminal(),
+        min_free_space: match args.min_free_space.as_deref().map(parse_bytes).transpose()? {
+            Some(0) | None => None,
+            min_free_space => min_free_space,
+        },
+        verbose: !args.no_verbose,
+        partition_by: parse_partition_by(args)?,
+        lineage: Some(Lineage::new(parse_endpoint(args), source.chain_id, parse_command_line())),
+        signer: parse_signer(args)?,
+        decimal_scale: args.decimal,
+        nested_traces: args.nested_traces,
+        deterministic: args.deterministic,
+        rollup_epoch: args.rollups,
+        csv,
+        xlsx_max_rows: args.xlsx_max_rows,
+        count_only: args.count_only,
+        allow_lossy: args.allow_lossy,
+        stats_sidecars: args.stats_sidecars,
     };
 
     Ok(output)
 }
 
+fn parse_csv_dialect(args: &Args, format: &FileFormat) -> Result<CsvDialect, ParseError> {
+    let dialect = CsvDialect {
+        delimiter: match args.csv_delimiter.as_str() {
+            "tab" | "\\t" => b'\t',
+            delimiter if delimiter.len() == 1 => delimiter.as_bytes()[0],
+            delimiter => {
+                return Err(ParseError::ParseError(format!("invalid csv delimiter: {}", delimiter)))
+            }
+        },
+        quote_style: match args.csv_quote_style.as_str() {
+            "necessary" => CsvQuoteStyle::Necessary,
+            "always" => CsvQuoteStyle::Always,
+            style => {
+                return Err(ParseError::ParseError(format!("invalid csv quote style: {}", style)))
+            }
+        },
+        header: !args.no_csv_header,
+        rfc4180: args.csv_rfc4180,
+        gzip: args.csv_gzip,
+    };
+    if dialect != CsvDialect::default() && format != &FileFormat::Csv {
+        return Err(ParseError::ParseError("csv options require --csv".to_string()))
+    }
+    if dialect.rfc4180 && dialect.delimiter != b',' {
+        return Err(ParseError::ParseError("--csv-rfc4180 requires comma delimiters".to_string()))
+    }
+    if [b'"', b'\n', b'\r'].contains(&dialect.delimiter) {
+        return Err(ParseError::ParseError(
+            "csv delimiter cannot be a quote or line break".to_string(),
+        ))
+    }
+    Ok(dialect)
+}
+
+fn parse_partition_by(args: &Args) -> Result<Option<PartitionBy>, ParseError> {
+    match args.partition_by.as_deref() {
+        None => Ok(None),
+        Some(_) if args.max_partitions == 0 => {
+            Err(ParseError::ParseError("--max-partitions must be at least 1".to_string()))
+        }
+        Some("contract") => Ok(Some(PartitionBy::Contract { max_contracts: args.max_partitions })),
+        // day partitions are cut when chunking the block range
+        Some("day") if args.n_chunks.is_some() || args.align => Err(ParseError::ParseError(
+            "--partition-by day cannot be combined with --n-chunks or --align".to_string(),
+        )),
+        Some("day") => Ok(None),
+        Some(mode) => Err(ParseError::ParseError(format!("invalid partition mode: {}", mode))),
+    }
+}
+
+fn parse_signer(args: &Args) -> Result<Option<Signer>, ParseError> {
+    let scheme = SignatureScheme::from_name(&args.sign_with).ok_or_else(|| {
+        ParseError::ParseError(format!("invalid signing tool: {}", args.sign_with))
+    })?;
+    Ok(args.sign_key.as_ref().map(|key| Signer { scheme, key: key.clone() }))
+}
+
+/// flags whose values may contain api keys or private paths
+const SECRET_FLAGS: [&str; 6] =
+    ["-r", "--rpc", "--beacon-url", "--alert-webhook", "--debug-rpc", "--follow-ws-url"];
+
+/// short secret flags, which also take their value attached, e.g. -rURL
+const SECRET_SHORT_FLAGS: [&str; 1] = ["-r"];
+
+/// command line of the current process, with the values of secret flags redacted
+fn parse_command_line() -> Vec<String> {
+    let mut command = Vec::new();
+    let mut redact_next = false;
+    for arg in std::env::args() {
+        let short_flag = SECRET_SHORT_FLAGS.iter().find(|flag| {
+            arg.starts_with(**flag) && arg.len() > flag.len() && !arg.starts_with("--")
+        });
+        let redacted = match (arg.split_once('='), short_flag) {
+            _ if redact_next => "<redacted>".to_string(),
+            (Some((flag, _)), _) if SECRET_FLAGS.contains(&flag) => format!("{}=<redacted>", flag),
+            (_, Some(flag)) => format!("{}<redacted>", flag),
+            _ => arg.clone(),
+        };
+        redact_next = !redact_next && SECRET_FLAGS.contains(&arg.as_str());
+        command.push(redacted);
+    }
+    command
+}
+
+/// parse a byte size such as 1000, 500MB, or 20GiB
+pub(crate) fn parse_bytes(size: &str) -> Result<u64, ParseError> {
+    let size = size.trim().to_uppercase();
+    let split = size.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(size.len());
+    let (number, unit) = size.split_at(split);
+    let multiplier: u64 = match unit.trim() {
+        "" | "B" => 1,
+        "K" | "KB" => 1_000,
+        "M" | "MB" => 1_000_000,
+        "G" | "GB" => 1_000_000_000,
+        "T" | "TB" => 1_000_000_000_000,
+        "KIB" => 1 << 10,
+        "MIB" => 1 << 20,
+        "GIB" => 1 << 30,
+        "TIB" => 1 << 40,
+        _ => return Err(ParseError::ParseError(format!("invalid byte size: {}", size))),
+    };
+    let number: f64 = number
+        .parse()
+        .map_err(|_e| ParseError::ParseError(format!("invalid byte size: {}", size)))?;
+    Ok((number * multiplier as f64) as u64)
+}
+
+/// names of networks used as file prefixes
+const NETWORK_NAMES: [(u64, &str); 7] = [
+    (1, "ethereum"),
+    (10, "optimism"),
+    (56, "binance"),
+    (137, "polygon"),
+    (42161, "arbitrum"),
+    (43114, "avalanche"),
+    (11155111, "sepolia"),
+];
+
 pub(crate) fn parse_network_name(args: &Args, chain_id: u64) -> String {
     match &args.network_name {
         Some(name) => name.clone(),
-        None => match chain_id {
-            1 => "ethereum".to_string(),
-            10 => "optimism".to_string(),
-            56 => "binance".to_string(),
-            137 => "polygon".to_string(),
-            42161 => "arbitrum".to_string(),
-            43114 => "avalanche".to_string(),
-            11155111 => "sepolia".to_string(),
-            chain_id => "network_".to_string() + chain_id.to_string().as_str(),
+        None => match NETWORK_NAMES.iter().find(|(id, _)| *id == chain_id) {
+            Some((_, name)) => name.to_string(),
+            None => "network_".to_string() + chain_id.to_string().as_str(),
         },
     }
 }
 
-pub(crate) fn parse_output_format(args: &Args) -> Result<FileFormat, ParseError> {
-    match (args.csv, args.json) {
-        (true, true) => {
-            Err(ParseError::ParseError("choose one of parquet, csv, or json".to_string()))
+/// chain id of a network name, for names of known networks and network_<chain_id>
+fn network_chain_id(name: &str) -> Option<u64> {
+    match NETWORK_NAMES.iter().find(|(_, network_name)| *network_name == name) {
+        Some((chain_id, _)) => Some(*chain_id),
+        None => name.strip_prefix("network_").and_then(|chain_id| chain_id.parse().ok()),
+    }
+}
+
+/// check that the rpc serves the chain of --network-name and of data already in the output
+/// directory, so chains are not mixed by accident
+fn verify_chain_id(args: &Args, chain_id: u64, output_dir: &str) -> Result<(), ParseError> {
+    if args.allow_mixed_chains {
+        return Ok(())
+    }
+    let name_chain_id = args.network_name.as_deref().and_then(network_chain_id);
+    if let Some(name_chain_id) = name_chain_id.filter(|name_chain_id| *name_chain_id != chain_id) {
+        return Err(ParseError::ParseError(format!(
+            "rpc serves chain id {}, but --network-name {} is chain id {}, use --allow-mixed-chains to collect anyway",
+            chain_id,
+            args.network_name.as_deref().unwrap_or_default(),
+            name_chain_id
+        )))
+    }
+
+    let mut chain_ids: BTreeSet<u64> = Manifest::read(output_dir)
+        .map_err(|_e| ParseError::ParseError(format!("could not read {}", MANIFEST_NAME)))?
+        .iter()
+        .filter_map(|entry| entry.lineage.as_ref().map(|lineage| lineage.chain_id))
+        .collect();
+    // files written without lineage are identified by the network name of their prefix
+    if let Ok(read_dir) = fs::read_dir(output_dir) {
+        for entry in read_dir.flatten() {
+            let filename = entry.file_name().to_string_lossy().to_string();
+            if let Some((prefix, _)) = filename.split_once("__") {
+                chain_ids.extend(network_chain_id(prefix));
+            }
         }
-        (true, _) => Ok(FileFormat::Csv),
-        (_, true) => Ok(FileFormat::Json),
-        (false, false) => Ok(FileFormat::Parquet),
+    }
+    match chain_ids.into_iter().find(|other| *other != chain_id) {
+        Some(other) => Err(ParseError::ParseError(format!(
+            "output directory holds data of chain id {}, but rpc serves chain id {}, use --allow-mixed-chains to collect anyway",
+            other, chain_id
+        ))),
+        None => Ok(()),
+    }
+}
+
+pub(crate) fn parse_output_format(args: &Args) -> Result<FileFormat, ParseError> {
+    match (args.csv, args.json, args.xlsx, args.sqlite) {
+        (true, false, false, false) => Ok(FileFormat::Csv),
+        (false, true, false, false) => Ok(FileFormat::Json),
+        #[cfg(feature = "xlsx")]
+        (false, false, true, false) => Ok(FileFormat::Xlsx),
+        #[cfg(not(feature = "xlsx"))]
+        (false, false, true, false) => Err(ParseError::ParseError(
+            "cryo was built without xlsx output, rebuild with the xlsx feature".to_string(),
+        )),
+        #[cfg(feature = "sqlite")]
+        (false, false, false, true) => Ok(FileFormat::Sqlite),
+        #[cfg(not(feature = "sqlite"))]
+        (false, false, false, true) => Err(ParseError::ParseError(
+            "cryo was built without sqlite output, rebuild with the sqlite feature".to_string(),
+        )),
+        (false, false, false, false) => Ok(FileFormat::Parquet),
+        _ => Err(ParseError::ParseError(
+            "choose one of parquet, csv, json, xlsx, or sqlite".to_string(),
+        )),
     }
 }
 
@@ -0,0 +1,102 @@
+use std::{sync::Arc, time::Duration};
+
+use cryo_freeze::{Chunk, ChunkData, Follow, MultiQuery, ParseError, Source};
+
+use crate::args::Args;
+
+const DEFAULT_FOLLOW_INTERVAL: f64 = 12.0;
+const DEFAULT_TIP_FOLLOW_INTERVAL: f64 = 1.0;
+const DEFAULT_FOLLOW_MAX_CONCURRENT_REQUESTS: u64 = 10;
+
+/// parse following of the chain head, which starts after the last block of the query
+pub(crate) fn parse_follow(
+    args: &Args,
+    query: &MultiQuery,
+    source: &Source,
+) -> Result<Option<Follow>, ParseError> {
+    let health_checked = args.max_block_lag.is_some() ||
+        args.max_head_stall.is_some() ||
+        args.alert_webhook.is_some() ||
+        args.exit_on_alert;
+    if !args.follow {
+        if health_checked {
+            return Err(ParseError::ParseError("follow alert options require --follow".to_string()))
+        }
+        if args.record_reorgs {
+            return Err(ParseError::ParseError("--record-reorgs requires --follow".to_string()))
+        }
+        if args.tip {
+            return Err(ParseError::ParseError("--tip requires --follow".to_string()))
+        }
+        if args.follow_ws_url.is_some() {
+            return Err(ParseError::ParseError("--follow-ws-url requires --follow".to_string()))
+        }
+        return Ok(None)
+    }
+
+    let mut last_block = None;
+    for chunk in query.chunks.iter() {
+        match chunk {
+            Chunk::Block(block_chunk) => last_block = last_block.max(block_chunk.max_value()),
+            _ => {
+                return Err(ParseError::ParseError(
+                    "--follow can only be used with block chunks".to_string(),
+                ))
+            }
+        }
+    }
+    let start_block = match last_block {
+        Some(last_block) => last_block + 1,
+        None => return Err(ParseError::ParseError("--follow requires --blocks".to_string())),
+    };
+
+    if args.preconfirmed && !args.tip {
+        return Err(ParseError::ParseError("--preconfirmed requires --tip".to_string()))
+    }
+    if args.preconfirmed && args.reorg_buffer == 0 {
+        return Err(ParseError::ParseError("--preconfirmed requires --reorg-buffer".to_string()))
+    }
+
+    let default_interval = match args.tip {
+        true => DEFAULT_TIP_FOLLOW_INTERVAL,
+        false => DEFAULT_FOLLOW_INTERVAL,
+    };
+    let interval = args.follow_interval.unwrap_or(default_interval);
+    if !interval.is_finite() || interval <= 0.0 {
+        return Err(ParseError::ParseError("--follow-interval must be positive".to_string()))
+    }
+    let max_concurrent_requests =
+        args.follow_max_concurrent_requests.unwrap_or(DEFAULT_FOLLOW_MAX_CONCURRENT_REQUESTS);
+    let semaphore = tokio::sync::Semaphore::new(max_concurrent_requests.max(1) as usize);
+    let source = Source { semaphore: Some(Arc::new(semaphore)), ..source.clone() };
+
+    // followed blocks always trail the head by the confirmations of --reorg-buffer
+    if args.max_block_lag.map(|lag| lag <= args.reorg_buffer).unwrap_or(false) {
+        return Err(ParseError::ParseError(
+            "--max-block-lag must be larger than --reorg-buffer".to_string(),
+        ))
+    }
+    let max_head_stall = match args.max_head_stall {
+        Some(stall) if !stall.is_finite() || stall <= 0.0 => {
+            return Err(ParseError::ParseError("--max-head-stall must be positive".to_string()))
+        }
+        Some(stall) => Some(Duration::from_secs_f64(stall)),
+        None => None,
+    };
+
+    Ok(Some(Follow {
+        start_block,
+        confirmations: args.reorg_buffer,
+        chunk_size: args.chunk_size,
+        poll_interval: Duration::from_secs_f64(interval),
+        ws_url: args.follow_ws_url.clone(),
+        source,
+        max_block_lag: args.max_block_lag,
+        max_head_stall,
+        alert_webhook: args.alert_webhook.clone(),
+        exit_on_alert: args.exit_on_alert,
+        record_reorgs: args.record_reorgs,
+        tip: args.tip,
+        preconfirmed: args.preconfirmed,
+    }))
+}
@@ -1,11 +1,17 @@
 mod args;
 mod blocks;
 mod file_output;
+mod follow;
+mod profile;
 mod query;
 mod source;
 mod transactions;
 
 pub use args::*;
+pub(crate) use file_output::parse_bytes;
+pub(crate) use follow::parse_follow;
+pub(crate) use query::parse_datatypes;
+pub(crate) use source::{find_rpc_url, parse_endpoint, parse_source};
 // use blocks::*;
 // use file_output::*;
 // use query::*;
@@ -0,0 +1,134 @@
+use cryo_freeze::ParseError;
+
+use crate::args::Args;
+
+/// known limits and quirks of an rpc provider, applied unless overridden by other options
+pub(crate) struct ProviderProfile {
+    /// name used with --provider-profile
+    pub(crate) name: &'static str,
+    /// largest block range of an eth_getLogs request
+    pub(crate) max_logs_range: Option<u64>,
+    /// requests sent at once, cryo sends each request separately instead of in json-rpc batches,
+    /// so this plays the role of the provider's batch size limit
+    pub(crate) max_concurrent_requests: Option<u64>,
+    /// requests per second of the provider's common plans
+    pub(crate) requests_per_second: Option<u32>,
+    /// methods the provider does not serve, as patterns of --rps, failed without a request
+    pub(crate) unsupported_methods: &'static [&'static str],
+    /// approximate credits of methods on the provider's pricing, as patterns of --rps
+    pub(crate) credit_costs: &'static [(&'static str, u64)],
+}
+
+/// profiles selectable with --provider-profile
+pub(crate) static PROVIDER_PROFILES: [ProviderProfile; 6] = [
+    ProviderProfile {
+        name: "alchemy",
+        max_logs_range: Some(2000),
+        max_concurrent_requests: Some(25),
+        requests_per_second: Some(25),
+        unsupported_methods: &["debug_dumpBlock"],
+        credit_costs: &[
+            ("default", 26),
+            ("eth_chainId", 0),
+            ("eth_blockNumber", 10),
+            ("eth_getBlockByNumber", 16),
+            ("eth_getTransactionReceipt", 15),
+            ("eth_getTransactionByHash", 17),
+            ("eth_getBalance", 19),
+            ("eth_getStorageAt", 17),
+            ("eth_getLogs", 75),
+            ("trace_block", 24),
+            ("trace_replayBlockTransactions", 2983),
+            ("debug_traceTransaction", 309),
+            ("debug_traceBlockByNumber", 497),
+        ],
+    },
+    ProviderProfile {
+        name: "infura",
+        max_logs_range: Some(10000),
+        max_concurrent_requests: Some(10),
+        requests_per_second: Some(10),
+        unsupported_methods: &[
+            "trace_replayBlockTransactions",
+            "trace_replayTransaction",
+            "debug_dumpBlock",
+        ],
+        credit_costs: &[
+            ("default", 80),
+            ("eth_chainId", 5),
+            ("eth_getLogs", 255),
+            ("trace", 300),
+            ("debug", 1000),
+        ],
+    },
+    ProviderProfile {
+        name: "quicknode",
+        max_logs_range: Some(10000),
+        max_concurrent_requests: Some(25),
+        requests_per_second: Some(25),
+        unsupported_methods: &["debug_dumpBlock"],
+        credit_costs: &[("default", 20), ("trace", 40), ("debug", 40)],
+    },
+    ProviderProfile {
+        name: "llamanodes",
+        max_logs_range: Some(10000),
+        max_concurrent_requests: Some(10),
+        requests_per_second: Some(10),
+        unsupported_methods: &["trace", "debug"],
+        credit_costs: &[],
+    },
+    ProviderProfile {
+        name: "local-geth",
+        max_logs_range: None,
+        max_concurrent_requests: Some(100),
+        requests_per_second: None,
+        unsupported_methods: &["trace"],
+        credit_costs: &[],
+    },
+    ProviderProfile {
+        name: "local-reth",
+        max_logs_range: None,
+        max_concurrent_requests: Some(100),
+        requests_per_second: None,
+        unsupported_methods: &["debug_dumpBlock"],
+        credit_costs: &[],
+    },
+];
+
+/// profile selected with --provider-profile, if any
+pub(crate) fn parse_provider_profile(
+    args: &Args,
+) -> Result<Option<&'static ProviderProfile>, ParseError> {
+    let name = match &args.provider_profile {
+        Some(name) => name,
+        None => return Ok(None),
+    };
+    match PROVIDER_PROFILES.iter().find(|profile| profile.name == name.as_str()) {
+        Some(profile) => Ok(Some(profile)),
+        None => {
+            let names: Vec<&str> = PROVIDER_PROFILES.iter().map(|profile| profile.name).collect();
+            Err(ParseError::ParseError(format!(
+                "invalid provider profile: {}, must be one of {}",
+                name,
+                names.join(", ")
+            )))
+        }
+    }
+}
+
+/// blocks per eth_getLogs request, the profile's largest range unless --inner-request-size is set
+pub(crate) fn parse_inner_request_size(
+    args: &Args,
+    profile: Option<&ProviderProfile>,
+) -> Result<u64, ParseError> {
+    match profile.and_then(|profile| profile.max_logs_range.map(|range| (profile.name, range))) {
+        Some((_, max_range)) if args.inner_request_size == 1 => Ok(max_range),
+        Some((name, max_range)) if args.inner_request_size > max_range => {
+            Err(ParseError::ParseError(format!(
+                "{} limits eth_getLogs to {} blocks, lower --inner-request-size",
+                name, max_range
+            )))
+        }
+        _ => Ok(args.inner_request_size),
+    }
+}
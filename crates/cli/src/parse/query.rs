@@ -1,21 +1,23 @@
-use std::{collections::HashMap, sync::Arc};
+use std::collections::{HashMap, HashSet};
 
 use ethers::prelude::*;
 use hex::FromHex;
 
-use cryo_freeze::{ColumnEncoding, Datatype, FileFormat, MultiQuery, ParseError, RowFilter, Table};
+use cryo_freeze::{
+    ColumnEncoding, Datatype, FileFormat, MultiQuery, ParseError, RowFilter, Source, SpamFilter,
+    Table,
+};
 
 use super::{blocks, file_output, transactions};
 use crate::args::Args;
 
-pub(crate) async fn parse_query(
-    args: &Args,
-    provider: Arc<Provider<Http>>,
-) -> Result<MultiQuery, ParseError> {
+pub(crate) async fn parse_query(args: &Args, source: &Source) -> Result<MultiQuery, ParseError> {
     let chunks = match (&args.blocks, &args.txs) {
-        (Some(_), None) => blocks::parse_blocks(args, provider).await?,
+        _ if args.chunks.is_some() => blocks::parse_chunk_file(args)?,
+        _ if args.last.is_some() => blocks::parse_last_blocks(args, source).await?,
+        (Some(_), None) => blocks::parse_blocks(args, source).await?,
         (None, Some(txs)) => transactions::parse_transactions(txs)?,
-        (None, None) => blocks::get_default_block_chunks(args, provider).await?,
+        (None, None) => blocks::get_default_block_chunks(args, source).await?,
         (Some(_), Some(_)) => {
             return Err(ParseError::ParseError("specify only one of --blocks or --txs".to_string()))
         }
@@ -32,15 +34,44 @@ pub(crate) async fn parse_query(
         parse_topic(&args.topic2),
         parse_topic(&args.topic3),
     ];
-    let row_filter = RowFilter { address: contract, topics };
+    let spam_filter = parse_spam_filter(args)?;
+    let row_filter = RowFilter {
+        address: contract,
+        topics,
+        spam_filter,
+        excluded_addresses: HashSet::new(),
+        collapse_delegatecalls: false,
+        prune_receipt_logs: args.prune_receipt_logs,
+    };
+    let token_filter = parse_token_filter(args, &row_filter)?;
+    let address_filter = parse_address_filter(args, &row_filter)?;
     let mut row_filters: HashMap<Datatype, RowFilter> = HashMap::new();
+    row_filters.insert(Datatype::AddressLifespans, address_filter);
+    row_filters.insert(Datatype::ApprovalsForAll, token_filter.clone());
+    row_filters.insert(Datatype::Erc1155Transfers, token_filter.clone());
+    row_filters.insert(Datatype::Erc20Approvals, token_filter.clone());
+    row_filters.insert(Datatype::Erc20Transfers, token_filter);
+    row_filters.insert(Datatype::BeaconDeposits, row_filter.clone());
+    row_filters.insert(Datatype::BridgeTransfers, row_filter.clone());
+    row_filters.insert(Datatype::SafeTransactions, row_filter.clone());
+    row_filters.insert(Datatype::WethWraps, row_filter.clone());
+    let contract_filter = RowFilter { address: row_filter.address.clone(), ..Default::default() };
+    for datatype in
+        [Datatype::BalanceDiffs, Datatype::CodeDiffs, Datatype::NonceDiffs, Datatype::StorageDiffs]
+    {
+        row_filters.insert(datatype, contract_filter.clone());
+    }
+    let trace_filter =
+        RowFilter { collapse_delegatecalls: args.collapse_delegatecalls, ..contract_filter };
+    row_filters.insert(Datatype::Traces, trace_filter);
     row_filters.insert(Datatype::Logs, row_filter);
 
-    let query = MultiQuery { schemas, chunks, row_filters };
+    let adaptive = blocks::parse_adaptive_chunks(args)?;
+    let query = MultiQuery { schemas, chunks, row_filters, adaptive };
     Ok(query)
 }
 
-fn parse_datatypes(raw_inputs: &Vec<String>) -> Result<Vec<Datatype>, ParseError> {
+pub(crate) fn parse_datatypes(raw_inputs: &Vec<String>) -> Result<Vec<Datatype>, ParseError> {
     let mut datatypes = Vec::new();
 
     for raw_input in raw_inputs {
@@ -53,18 +84,36 @@ fn parse_datatypes(raw_inputs: &Vec<String>) -> Result<Vec<Datatype>, ParseError
             }
             datatype => {
                 let datatype = match datatype {
+                    "address_activity" => Datatype::AddressActivity,
+                    "address_lifespans" => Datatype::AddressLifespans,
+                    "approvals_for_all" => Datatype::ApprovalsForAll,
                     "balance_diffs" => Datatype::BalanceDiffs,
+                    "beacon_deposits" => Datatype::BeaconDeposits,
                     "blocks" => Datatype::Blocks,
+                    "bridge_transfers" => Datatype::BridgeTransfers,
                     "code_diffs" => Datatype::CodeDiffs,
+                    "erc1155_transfers" => Datatype::Erc1155Transfers,
+                    "erc20_approvals" => Datatype::Erc20Approvals,
+                    "erc20_transfers" => Datatype::Erc20Transfers,
+                    "gas_percentiles" => Datatype::GasPercentiles,
+                    "genesis" => Datatype::Genesis,
                     "logs" => Datatype::Logs,
                     "events" => Datatype::Logs,
+                    "multicall_calls" => Datatype::MulticallCalls,
+                    "raw_rpc" => Datatype::RawRpc,
                     "nonce_diffs" => Datatype::NonceDiffs,
                     "storage_diffs" => Datatype::StorageDiffs,
+                    "safe_transactions" => Datatype::SafeTransactions,
+                    "starknet_blocks" => Datatype::StarknetBlocks,
+                    "starknet_transactions" => Datatype::StarknetTransactions,
                     "transactions" => Datatype::Transactions,
                     "txs" => Datatype::Transactions,
                     "traces" => Datatype::Traces,
+                    "validators" => Datatype::Validators,
                     "vm_traces" => Datatype::VmTraces,
                     "opcode_traces" => Datatype::VmTraces,
+                    "weth_wraps" => Datatype::WethWraps,
+                    "zksync_transactions" => Datatype::ZkSyncTransactions,
                     _ => {
                         return Err(ParseError::ParseError(format!("invalid datatype {}", datatype)))
                     }
@@ -79,19 +128,30 @@ fn parse_datatypes(raw_inputs: &Vec<String>) -> Result<Vec<Datatype>, ParseError
 fn parse_schemas(args: &Args) -> Result<HashMap<Datatype, Table>, ParseError> {
     let datatypes = parse_datatypes(&args.datatype)?;
     let output_format = file_output::parse_output_format(args)?;
-    let binary_column_format = match args.hex | (output_format != FileFormat::Parquet) {
-        true => ColumnEncoding::Hex,
-        false => ColumnEncoding::Binary,
-    };
+    // parquet and sqlite store binary columns natively
+    let binary_column_format =
+        match args.hex | (output_format != FileFormat::Parquet && !args.sqlite) {
+            true => ColumnEncoding::Hex,
+            false => ColumnEncoding::Binary,
+        };
 
     let sort = parse_sort(&args.sort, &datatypes)?;
     let schemas: Result<HashMap<Datatype, Table>, ParseError> = datatypes
         .iter()
         .map(|datatype| {
+            // collapsed traces are annotated with the implementation they delegate to
+            let include_columns = match datatype {
+                Datatype::Traces if args.collapse_delegatecalls => {
+                    let mut include_columns = args.include_columns.clone().unwrap_or_default();
+                    include_columns.push("delegate_to".to_string());
+                    Some(include_columns)
+                }
+                _ => args.include_columns.clone(),
+            };
             datatype
                 .table_schema(
                     &binary_column_format,
-                    &args.include_columns,
+                    &include_columns,
                     &args.exclude_columns,
                     &args.columns,
                     sort[datatype].clone(),
@@ -105,7 +165,42 @@ fn parse_schemas(args: &Args) -> Result<HashMap<Datatype, Table>, ParseError> {
                 })
         })
         .collect();
-    schemas
+    let mut schemas = match args.exact {
+        true => schemas.and_then(|schemas| drop_float_columns(schemas, args))?,
+        false => schemas?,
+    };
+    if args.wide_integers {
+        schemas.values_mut().for_each(|schema| schema.widen_integers());
+    }
+    if let Some(traces) = schemas.get(&Datatype::Traces) {
+        if args.nested_traces && !traces.has_column("transaction_hash") {
+            return Err(ParseError::ParseError(
+                "--nested-traces requires the transaction_hash column".to_string(),
+            ))
+        }
+    }
+    Ok(schemas)
+}
+
+/// remove float columns for --exact, failing if one was requested by name
+fn drop_float_columns(
+    mut schemas: HashMap<Datatype, Table>,
+    args: &Args,
+) -> Result<HashMap<Datatype, Table>, ParseError> {
+    let requested: Vec<&String> =
+        args.columns.iter().chain(args.include_columns.iter()).flatten().collect();
+    for schema in schemas.values_mut() {
+        for column in schema.drop_float_columns().iter() {
+            if requested.contains(&column) {
+                let exact = column.strip_suffix("_float").unwrap_or(column);
+                return Err(ParseError::ParseError(format!(
+                    "--exact excludes lossy float column {}, use {} instead",
+                    column, exact
+                )))
+            }
+        }
+    }
+    Ok(schemas)
 }
 
 fn parse_sort(
@@ -155,3 +250,63 @@ fn parse_topic(input: &Option<String>) -> Option<ValueOrArray<Option<H256>>> {
 
     value.map(|inner| ValueOrArray::Value(Some(inner)))
 }
+
+fn parse_spam_filter(args: &Args) -> Result<Option<SpamFilter>, ParseError> {
+    if !args.filter_spam {
+        return Ok(None)
+    }
+    let contracts = match &args.spam_contracts {
+        Some(path) => parse_address_file(path, "spam contract")?,
+        None => HashSet::new(),
+    };
+    Ok(Some(SpamFilter::new(contracts, args.spam_min_recipients)))
+}
+
+/// restrict token datasets to the tokens of --tokens and drop the tokens of --exclude-tokens
+fn parse_token_filter(args: &Args, row_filter: &RowFilter) -> Result<RowFilter, ParseError> {
+    let mut token_filter = row_filter.clone();
+    if let Some(path) = &args.tokens {
+        let mut tokens: Vec<H160> = parse_address_file(path, "token")?.into_iter().collect();
+        if tokens.is_empty() {
+            return Err(ParseError::ParseError(format!("no tokens listed in {}", path)))
+        }
+        tokens.sort();
+        token_filter.address = Some(ValueOrArray::Array(tokens));
+    }
+    if let Some(path) = &args.exclude_tokens {
+        token_filter.excluded_addresses = parse_address_file(path, "token")?;
+    }
+    Ok(token_filter)
+}
+
+/// look up the addresses of --addresses, or the address of --contract
+fn parse_address_filter(args: &Args, row_filter: &RowFilter) -> Result<RowFilter, ParseError> {
+    let mut address_filter = row_filter.clone();
+    if let Some(path) = &args.addresses {
+        let mut addresses: Vec<H160> = parse_address_file(path, "lookup")?.into_iter().collect();
+        if addresses.is_empty() {
+            return Err(ParseError::ParseError(format!("no addresses listed in {}", path)))
+        }
+        addresses.sort();
+        address_filter.address = Some(ValueOrArray::Array(addresses));
+    }
+    Ok(address_filter)
+}
+
+/// parse file of addresses, one per line, skipping blank lines and # comments
+fn parse_address_file(path: &str, kind: &str) -> Result<HashSet<H160>, ParseError> {
+    let contents = std::fs::read_to_string(path).map_err(|e| {
+        ParseError::ParseError(format!("could not read {} file {}: {}", kind, path, e))
+    })?;
+    let mut addresses = HashSet::new();
+    for line in contents.lines().map(|line| line.trim()) {
+        if line.is_empty() || line.starts_with('#') {
+            continue
+        }
+        let address = line
+            .parse::<H160>()
+            .map_err(|_e| ParseError::ParseError(format!("invalid {} address: {}", kind, line)))?;
+        addresses.insert(address);
+    }
+    Ok(addresses)
+}
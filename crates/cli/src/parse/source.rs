@@ -1,68 +1,337 @@
 use std::env;
 
 use ethers::prelude::*;
-use governor::{Quota, RateLimiter};
+use governor::{Jitter, Quota, RateLimiter};
 use polars::prelude::*;
-use std::num::NonZeroU32;
+use std::{num::NonZeroU32, time::Duration};
 
-use cryo_freeze::{ParseError, Source};
+use cryo_freeze::{
+    endpoint_fingerprint, starknet::get_starknet_chain_id, BeaconClient, GenesisAlloc, LocalLogs,
+    MockFetcher, ParseError, RawRpcRequest, RpcClient, RpcFixture, RpcLogger, RpcRecorder,
+    SharedRateLimiter, Source, SourcifyClient, TokenDecimals, Vm, DEFAULT_SOURCIFY_URL,
+};
 
+use super::{file_output::parse_bytes, profile};
 use crate::args::Args;
 
+/// maximum number of characters of each response printed by --debug-rpc
+const DEBUG_RPC_RESPONSE_LENGTH: usize = 500;
+
+/// concurrent metadata requests, reserved on top of --max-concurrent-requests
+const METADATA_PERMITS: usize = 4;
+
+/// seconds requests are paused, unless set by --circuit-cooldown
+const DEFAULT_CIRCUIT_COOLDOWN: f64 = 10.0;
+
+/// retries of rate limited requests, unless set by --max-retries
+const DEFAULT_MAX_RETRIES: u32 = 5;
+
+/// longest Retry-After wait honored, unless set by --max-retry-after
+const DEFAULT_MAX_RETRY_AFTER: f64 = 60.0;
+
+/// params of the raw_rpc dataset, unless set by --rpc-params
+const DEFAULT_RPC_PARAMS: &str = r#"["{block}"]"#;
+
 pub(crate) async fn parse_source(args: &Args) -> Result<Source, ParseError> {
+    let profile = profile::parse_provider_profile(args)?;
+    let vm = match &args.vm {
+        Some(name) => Vm::from_name(name)
+            .ok_or_else(|| ParseError::ParseError(format!("invalid vm: {}", name)))?,
+        None => Vm::Evm,
+    };
+
     // parse network info
-    let rpc_url = parse_rpc_url(args);
-    let provider = Provider::<Http>::try_from(rpc_url)
-        .map_err(|_e| ParseError::ParseError("could not connect to provider".to_string()))?;
-    let chain_id = provider
-        .get_chainid()
-        .await
-        .map_err(|_e| ParseError::ParseError("could not connect to provider".to_string()))?
-        .as_u64();
-
-    let rate_limiter = match args.requests_per_second {
-        Some(rate_limit) => match NonZeroU32::new(rate_limit) {
-            Some(value) => {
-                let quota = Quota::per_second(value);
-                Some(Arc::new(RateLimiter::direct(quota)))
+    let max_retries = args.max_retries.unwrap_or(DEFAULT_MAX_RETRIES);
+    let max_retry_after = args.max_retry_after.unwrap_or(DEFAULT_MAX_RETRY_AFTER);
+    if !max_retry_after.is_finite() || max_retry_after < 0.0 {
+        return Err(ParseError::ParseError("invalid --max-retry-after".to_string()))
+    }
+    let max_retry_after = Duration::from_secs_f64(max_retry_after);
+    let client = match &args.replay_rpc {
+        _ if args.mock_rpc => RpcClient::offline(MockFetcher::default()),
+        Some(path) => RpcClient::offline(
+            RpcFixture::load(path).map_err(|e| ParseError::ParseError(e.to_string()))?,
+        ),
+        None => RpcClient::new(&parse_rpc_url(args))
+            .and_then(|client| client.with_retries(max_retries, max_retry_after))
+            .map_err(|_e| ParseError::ParseError("could not connect to provider".to_string()))?
+            .with_stats(),
+    }
+    .with_coalescing();
+    let client = match args.circuit_breaker {
+        None | Some(0) => client,
+        Some(max_failures) => {
+            let cooldown = args.circuit_cooldown.unwrap_or(DEFAULT_CIRCUIT_COOLDOWN);
+            if !cooldown.is_finite() || cooldown < 0.0 {
+                return Err(ParseError::ParseError("invalid --circuit-cooldown".to_string()))
             }
-            _ => None,
-        },
+            client.with_circuit_breaker(max_failures, Duration::from_secs_f64(cooldown))
+        }
+    };
+    let rate_jitter = match args.rate_jitter {
+        Some(jitter) => Jitter::up_to(Duration::from_millis(jitter)),
+        None => Jitter::default(),
+    };
+    let client = match &args.rps {
+        Some(rps) => {
+            client.with_method_rate_limits(parse_method_rate_limits(rps, args)?, rate_jitter)
+        }
+        None => client,
+    };
+    let client = match parse_credit_costs(args, profile)? {
+        Some(costs) => client.with_credits(costs, args.max_credits),
+        None => client,
+    };
+    let client = match profile {
+        Some(profile) => client.with_unsupported_methods(
+            profile.unsupported_methods.iter().map(|method| method.to_string()).collect(),
+        ),
+        None => client,
+    };
+    let client = match &args.record_rpc {
+        Some(path) => client.with_recorder(
+            RpcRecorder::file(path).map_err(|e| ParseError::ParseError(e.to_string()))?,
+        ),
+        None => client,
+    };
+    let client = match args.lenient {
+        true => client.with_lenient(),
+        false => client,
+    };
+    let client = match &args.debug_rpc {
+        Some(path) if path == "-" => {
+            client.with_logger(RpcLogger::stderr(DEBUG_RPC_RESPONSE_LENGTH))
+        }
+        Some(path) => client.with_logger(
+            RpcLogger::file(path, DEBUG_RPC_RESPONSE_LENGTH)
+                .map_err(|e| ParseError::ParseError(e.to_string()))?,
+        ),
+        None => client,
+    };
+    let provider = Provider::new(client);
+
+    let requests_per_second =
+        args.requests_per_second.or(profile.and_then(|profile| profile.requests_per_second));
+    let rate_limiter = match requests_per_second {
+        Some(rate_limit) => {
+            parse_quota(rate_limit, args)?.map(|quota| Arc::new(RateLimiter::direct(quota)))
+        }
         None => None,
     };
 
+    let shared_rate_limiter = parse_shared_rate_limiter(args, requests_per_second)?;
+
     // process concurrency info
-    let max_concurrent_requests = args.max_concurrent_requests.unwrap_or(100);
+    let max_concurrent_requests = args
+        .max_concurrent_requests
+        .or(profile.and_then(|profile| profile.max_concurrent_requests))
+        .unwrap_or(100);
     let max_concurrent_chunks = args.max_concurrent_chunks.unwrap_or(3);
 
     let semaphore = tokio::sync::Semaphore::new(max_concurrent_requests as usize);
     let semaphore = Some(Arc::new(semaphore));
+    let metadata_semaphore = Some(Arc::new(tokio::sync::Semaphore::new(METADATA_PERMITS)));
+
+    // the chain id request counts against the request rate like any other metadata request
+    if let Some(limiter) = &rate_limiter {
+        limiter.until_ready_with_jitter(rate_jitter).await;
+    }
+    if let Some(limiter) = &shared_rate_limiter {
+        limiter.until_ready().await;
+    }
+    let chain_id = match vm {
+        Vm::Starknet => get_starknet_chain_id(&provider).await,
+        _ => provider.get_chainid().await.map(|chain_id| chain_id.as_u64()),
+    }
+    .map_err(|_e| ParseError::ParseError("could not connect to provider".to_string()))?;
 
     let output = Source {
         provider: Arc::new(provider),
         chain_id,
         semaphore,
+        metadata_semaphore,
         rate_limiter,
-        inner_request_size: args.inner_request_size,
+        shared_rate_limiter,
+        rate_jitter,
+        inner_request_size: profile::parse_inner_request_size(args, profile)?,
         max_concurrent_chunks,
+        sourcify: Arc::new(SourcifyClient::new(
+            args.sourcify_url.as_deref().unwrap_or(DEFAULT_SOURCIFY_URL),
+        )),
+        token_decimals: Arc::new(TokenDecimals::default()),
+        beacon: args.beacon_url.as_deref().map(|url| Arc::new(BeaconClient::new(url))),
+        vm,
+        local_logs: parse_local_logs(args)?,
+        genesis: match &args.genesis_file {
+            Some(path) => Some(Arc::new(GenesisAlloc::open(path)?)),
+            None => None,
+        },
+        raw_rpc: match &args.rpc_method {
+            Some(method) => {
+                let params = args.rpc_params.as_deref().unwrap_or(DEFAULT_RPC_PARAMS);
+                Some(RawRpcRequest::new(method, params)?)
+            }
+            None => None,
+        },
+        chunk_timeout: match args.chunk_timeout {
+            Some(timeout) if !timeout.is_finite() || timeout <= 0.0 => {
+                return Err(ParseError::ParseError("--chunk-timeout must be positive".to_string()))
+            }
+            Some(timeout) => Some(Duration::from_secs_f64(timeout)),
+            None => None,
+        },
+        audit_fraction: match args.audit_determinism {
+            Some(percent) if !(percent > 0.0 && percent <= 100.0) => {
+                let message = "--audit-determinism must be a percentage in (0, 100]";
+                return Err(ParseError::ParseError(message.to_string()))
+            }
+            Some(percent) => Some(percent / 100.0),
+            None => None,
+        },
     };
 
     Ok(output)
 }
 
+/// rate limit shared through a file, by default one file per rpc url
+fn parse_shared_rate_limiter(
+    args: &Args,
+    requests_per_second: Option<u32>,
+) -> Result<Option<Arc<SharedRateLimiter>>, ParseError> {
+    let path = match &args.shared_rate_limit {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+    let requests_per_second = match requests_per_second {
+        Some(requests_per_second) => requests_per_second,
+        None => {
+            return Err(ParseError::ParseError(
+                "--shared-rate-limit requires --requests-per-second".to_string(),
+            ))
+        }
+    };
+    let path = match path.as_str() {
+        "" => {
+            let name: String = endpoint_fingerprint(&parse_rpc_url(args))
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+                .collect();
+            env::temp_dir().join(format!("cryo_rate_limit_{}", name))
+        }
+        path => std::path::PathBuf::from(path),
+    };
+    let burst = args.rate_burst.unwrap_or(requests_per_second);
+    let limiter = SharedRateLimiter::open(&path, requests_per_second, burst)?;
+    Ok(Some(Arc::new(limiter)))
+}
+
+/// quota of a requests per second limit, with the burst of --rate-burst
+fn parse_quota(rate_limit: u32, args: &Args) -> Result<Option<Quota>, ParseError> {
+    let value = match NonZeroU32::new(rate_limit) {
+        Some(value) => value,
+        None => return Ok(None),
+    };
+    match args.rate_burst {
+        Some(burst) => {
+            let burst = NonZeroU32::new(burst).ok_or_else(|| {
+                ParseError::ParseError("--rate-burst must be positive".to_string())
+            })?;
+            Ok(Some(Quota::per_second(value).allow_burst(burst)))
+        }
+        None => Ok(Some(Quota::per_second(value))),
+    }
+}
+
+/// parse --rps limits of the form METHOD=LIMIT
+fn parse_method_rate_limits(
+    rps: &[String],
+    args: &Args,
+) -> Result<Vec<(String, cryo_freeze::RateLimiter)>, ParseError> {
+    let mut limits = Vec::new();
+    for item in rps.iter() {
+        let invalid = || ParseError::ParseError(format!("invalid --rps limit: {}", item));
+        let (pattern, rate_limit) = item.split_once('=').ok_or_else(invalid)?;
+        let rate_limit: u32 = rate_limit.parse().map_err(|_e| invalid())?;
+        let quota = parse_quota(rate_limit, args)?.ok_or_else(invalid)?;
+        limits.push((pattern.to_string(), RateLimiter::direct(quota)));
+    }
+    Ok(limits)
+}
+
+/// credit costs of --provider-profile and --credit-costs, if credits are tracked
+fn parse_credit_costs(
+    args: &Args,
+    profile: Option<&profile::ProviderProfile>,
+) -> Result<Option<Vec<(String, u64)>>, ParseError> {
+    let mut costs: Vec<(String, u64)> = profile
+        .map(|profile| profile.credit_costs.iter().map(|(p, c)| (p.to_string(), *c)).collect())
+        .unwrap_or_default();
+    for item in args.credit_costs.iter().flatten() {
+        let invalid = || ParseError::ParseError(format!("invalid --credit-costs cost: {}", item));
+        let (pattern, cost) = item.split_once('=').ok_or_else(invalid)?;
+        let cost: u64 = cost.parse().map_err(|_e| invalid())?;
+        costs.retain(|(other, _)| other != pattern);
+        costs.push((pattern.to_string(), cost));
+    }
+    match (costs.is_empty(), args.max_credits) {
+        (true, Some(_)) => Err(ParseError::ParseError(
+            "--max-credits requires --credit-costs or a --provider-profile with known costs"
+                .to_string(),
+        )),
+        (true, None) => Ok(None),
+        (false, _) => Ok(Some(costs)),
+    }
+}
+
+fn parse_local_logs(args: &Args) -> Result<Option<Arc<LocalLogs>>, ParseError> {
+    let dir = match &args.logs_dir {
+        Some(dir) => dir,
+        None if args.local_threads.is_some() || args.local_max_memory.is_some() => {
+            return Err(ParseError::ParseError(
+                "--local-threads and --local-max-memory require --logs-dir".to_string(),
+            ))
+        }
+        None => return Ok(None),
+    };
+    let local_logs = LocalLogs::open(dir)?;
+    let local_logs = match args.local_threads {
+        Some(threads) => local_logs.with_threads(threads),
+        None => local_logs,
+    };
+    let local_logs = match &args.local_max_memory {
+        Some(size) => local_logs.with_max_memory(parse_bytes(size)?),
+        None => local_logs,
+    };
+    Ok(Some(Arc::new(local_logs)))
+}
+
+/// fingerprint of the data source, recorded in the lineage of output files
+pub(crate) fn parse_endpoint(args: &Args) -> String {
+    match &args.replay_rpc {
+        _ if args.mock_rpc => "mock".to_string(),
+        Some(path) => format!("replay:{}", path),
+        None => endpoint_fingerprint(&parse_rpc_url(args)),
+    }
+}
+
 fn parse_rpc_url(args: &Args) -> String {
+    match find_rpc_url(args) {
+        Some(url) => url,
+        None => {
+            println!("must provide --rpc or set ETH_RPC_URL");
+            std::process::exit(0);
+        }
+    }
+}
+
+/// rpc url of --rpc or ETH_RPC_URL, none if neither is given
+pub(crate) fn find_rpc_url(args: &Args) -> Option<String> {
     let mut url = match &args.rpc {
         Some(url) => url.clone(),
-        _ => match env::var("ETH_RPC_URL") {
-            Ok(url) => url,
-            Err(_e) => {
-                println!("must provide --rpc or set ETH_RPC_URL");
-                std::process::exit(0);
-            }
-        },
+        _ => env::var("ETH_RPC_URL").ok()?,
     };
     if !url.starts_with("http") {
         url = "http://".to_string() + url.as_str();
     };
-    url
+    Some(url)
 }
@@ -1,16 +1,24 @@
 use std::time::SystemTime;
 
-use crate::{args, parse, summaries};
-use cryo_freeze::{FreezeError, FreezeSummary};
+use crate::{args, commands, config, parse, summaries};
+use cryo_freeze::{FreezeError, FreezeSummary, RpcClient, Source, UsageEntry, UsageLedger};
 
 /// run freeze for given Args
 pub async fn run(args: args::Args) -> Result<Option<FreezeSummary>, FreezeError> {
+    let args = config::apply_config(args)?;
+
+    // run command instead of collecting data
+    if let Some(result) = commands::run_command(&args).await {
+        return result.map(|_| None).map_err(FreezeError::ParseError)
+    }
+
     // parse inputs
     let t_start = SystemTime::now();
     let (query, source, sink) = match parse::parse_opts(&args).await {
         Ok(opts) => opts,
         Err(e) => return Err(e.into()),
     };
+    let follow = parse::parse_follow(&args, &query, &source)?;
     let t_parse_done = SystemTime::now();
 
     // print summary
@@ -26,11 +34,28 @@ pub async fn run(args: args::Args) -> Result<Option<FreezeSummary>, FreezeError>
         return Ok(None)
     };
 
+    // write empty files with the output schemas
+    if args.emit_empty_schema {
+        let paths = cryo_freeze::freeze_empty_schemas(&query, &sink)?;
+        if !args.no_verbose {
+            summaries::print_header("\n\nempty schema files");
+            for path in paths.iter() {
+                println!("{}", path);
+            }
+        }
+        return Ok(None)
+    }
+
     // collect data
     if !args.no_verbose {
         summaries::print_header("\n\ncollecting data");
     }
-    match cryo_freeze::freeze(&query, &source, &sink).await {
+    let result = match &follow {
+        Some(follow) => cryo_freeze::freeze_and_follow(&query, &source, &sink, follow).await,
+        None => cryo_freeze::freeze(&query, &source, &sink).await,
+    };
+    record_usage(&args, &source);
+    match result {
         Ok(freeze_summary) => {
             // print summary
             let t_data_done = SystemTime::now();
@@ -42,7 +67,9 @@ pub async fn run(args: args::Args) -> Result<Option<FreezeSummary>, FreezeError>
                     t_data_done,
                     &query,
                     &freeze_summary,
-                )
+                );
+                summaries::print_rpc_summary(&source);
+                summaries::print_local_logs_summary(&source);
             }
 
             // return summary
@@ -55,3 +82,21 @@ pub async fn run(args: args::Args) -> Result<Option<FreezeSummary>, FreezeError>
         }
     }
 }
+
+/// add the requests and credits of a cli run to the usage ledger, for runs against a live rpc
+fn record_usage(args: &args::Args, source: &Source) {
+    if !args.record_usage || args.no_usage_ledger || args.mock_rpc || args.replay_rpc.is_some() {
+        return
+    }
+    let client: &RpcClient = source.provider.as_ref().as_ref();
+    let (ledger, entry) = match (
+        UsageLedger::default_ledger(),
+        UsageEntry::from_client(&parse::parse_endpoint(args), client),
+    ) {
+        (Some(ledger), Some(entry)) => (ledger, entry),
+        _ => return,
+    };
+    if let Err(e) = ledger.record(&entry) {
+        println!("could not record usage in {}: {}", ledger.path().display(), e);
+    }
+}
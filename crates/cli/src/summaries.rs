@@ -1,19 +1,24 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use chrono::{DateTime, Local};
 use colored::Colorize;
 use std::time::SystemTime;
 use thousands::Separable;
 
+#[cfg(feature = "sqlite")]
+use cryo_freeze::{dataframes::sqlite_mapping, FileFormat};
 use cryo_freeze::{
-    BlockChunk, Chunk, ChunkData, Datatype, FileOutput, FreezeSummary, MultiQuery, Source, Table,
-    TransactionChunk,
+    BlockChunk, Chunk, ChunkData, CollectError, Datatype, ErrorKind, FileOutput, FreezeError,
+    FreezeSummary, MultiQuery, RpcClient, Source, Table, TransactionChunk,
 };
 
 const TITLE_R: u8 = 0;
 const TITLE_G: u8 = 225;
 const TITLE_B: u8 = 0;
 
+/// failed chunks listed for each error kind
+const MAX_ERROR_CHUNKS: usize = 5;
+
 pub(crate) fn print_header<A: AsRef<str>>(header: A) {
     let header_str = header.as_ref().white().bold();
     let underline = "─".repeat(header_str.len()).truecolor(TITLE_R, TITLE_G, TITLE_B);
@@ -21,7 +26,7 @@ pub(crate) fn print_header<A: AsRef<str>>(header: A) {
     println!("{}", underline);
 }
 
-fn print_bullet<A: AsRef<str>, B: AsRef<str>>(key: A, value: B) {
+pub(crate) fn print_bullet<A: AsRef<str>, B: AsRef<str>>(key: A, value: B) {
     let bullet_str = "- ".truecolor(TITLE_R, TITLE_G, TITLE_B);
     let key_str = key.as_ref().white().bold();
     let value_str = value.as_ref().truecolor(170, 170, 170);
@@ -44,7 +49,114 @@ pub(crate) fn print_cryo_summary(query: &MultiQuery, source: &Source, sink: &Fil
     };
     print_bullet("output format", sink.format.as_str());
     print_bullet("output dir", &sink.output_dir);
+    print_credit_estimate(query, source);
     print_schemas(&query.schemas);
+    #[cfg(feature = "sqlite")]
+    if sink.format == FileFormat::Sqlite {
+        print_sqlite_mapping(&query.schemas, sink);
+    }
+}
+
+/// print the sqlite type of each column, flagging columns whose values can lose precision
+#[cfg(feature = "sqlite")]
+fn print_sqlite_mapping(schemas: &HashMap<Datatype, Table>, sink: &FileOutput) {
+    let mut n_lossy = 0;
+    for (datatype, schema) in schemas.iter() {
+        println!();
+        println!();
+        print_header("sqlite mapping for ".to_string() + datatype.dataset().name());
+        for mapping in sqlite_mapping(schema) {
+            let mut value = format!("{} -> {}", mapping.column_type.as_str(), mapping.sql_type);
+            if let Some(lossy) = mapping.lossy {
+                value = format!("{} (lossy: {})", value, lossy);
+                n_lossy += 1;
+            }
+            print_bullet(mapping.column, value);
+        }
+    }
+    if n_lossy > 0 {
+        println!();
+        match sink.allow_lossy {
+            true => println!("lossy values are stored with --allow-lossy"),
+            false => println!("chunks with lossy values fail unless --allow-lossy is used"),
+        }
+    }
+}
+
+/// print credits of the main requests of each datatype over the block chunks of a query
+///
+/// the estimate counts one request per block, or per log request range, of each method
+fn print_credit_estimate(query: &MultiQuery, source: &Source) {
+    let client: &RpcClient = source.provider.as_ref().as_ref();
+    if client.credit_cost("default").is_none() {
+        return
+    }
+    let block_chunks: Vec<BlockChunk> = query
+        .chunks
+        .iter()
+        .filter_map(|x| match x {
+            Chunk::Block(chunk) => Some(chunk.clone()),
+            _ => None,
+        })
+        .collect();
+    let n_blocks = block_chunks.size();
+    let n_log_requests: u64 =
+        block_chunks.iter().map(|chunk| chunk.size().div_ceil(source.inner_request_size)).sum();
+
+    // datatypes sharing a method, e.g. the state diffs, share its requests
+    let mut requests: BTreeMap<&str, u64> = BTreeMap::new();
+    let mut unestimated = Vec::new();
+    for datatype in query.schemas.keys() {
+        let (method, n_requests) = match datatype {
+            Datatype::Blocks |
+            Datatype::Transactions |
+            Datatype::GasPercentiles |
+            Datatype::MulticallCalls => ("eth_getBlockByNumber", n_blocks),
+            Datatype::Logs |
+            Datatype::Erc20Transfers |
+            Datatype::Erc20Approvals |
+            Datatype::Erc1155Transfers |
+            Datatype::ApprovalsForAll |
+            Datatype::WethWraps |
+            Datatype::BridgeTransfers |
+            Datatype::SafeTransactions |
+            Datatype::BeaconDeposits => ("eth_getLogs", n_log_requests),
+            Datatype::Traces => ("trace_block", n_blocks),
+            Datatype::BalanceDiffs |
+            Datatype::CodeDiffs |
+            Datatype::NonceDiffs |
+            Datatype::StorageDiffs |
+            Datatype::VmTraces => ("trace_replayBlockTransactions", n_blocks),
+            Datatype::RawRpc => match &source.raw_rpc {
+                Some(request) => (request.method.as_str(), n_blocks),
+                None => continue,
+            },
+            _ => {
+                unestimated.push(datatype.dataset().name());
+                continue
+            }
+        };
+        let entry = requests.entry(method).or_default();
+        *entry = (*entry).max(n_requests);
+    }
+
+    print_header("\n\nestimated credits");
+    let mut total = 0;
+    for (method, n_requests) in requests.iter() {
+        let credits = n_requests * client.credit_cost(method).unwrap_or_default();
+        total += credits;
+        let n_requests = n_requests.separate_with_commas();
+        let value = format!("{} requests, {} credits", n_requests, credits.separate_with_commas());
+        print_bullet(method, value);
+    }
+    print_bullet("total", total.separate_with_commas());
+    if let Some(budget) = client.credit_budget() {
+        print_bullet("budget", budget.separate_with_commas());
+    }
+    if !unestimated.is_empty() {
+        unestimated.sort();
+        println!("requests of {} are not estimated", unestimated.join(", "));
+    }
 }
 
 fn print_block_chunks(query: &MultiQuery) {
@@ -140,7 +252,7 @@ pub(crate) fn print_cryo_conclusion(
         Ok(duration) => duration,
         Err(_e) => {
             println!("error computing system time, aborting");
-            return
+            return;
         }
     };
     let seconds = duration.as_secs();
@@ -162,8 +274,185 @@ pub(crate) fn print_cryo_conclusion(
         format!("{} / {}", freeze_summary.n_completed.separate_with_commas(), query.chunks.len()),
     );
 
+    if let Some(spam_filter) =
+        query.row_filters.get(&Datatype::Logs).and_then(|filter| filter.spam_filter.as_ref())
+    {
+        print_bullet("spam logs dropped", spam_filter.n_dropped().separate_with_commas());
+    }
+
     print_block_chunk_summary(query, freeze_summary, total_time);
     print_transaction_chunk_summary(query, freeze_summary, total_time);
+    print_error_summary(freeze_summary);
+    print_decision_summary(freeze_summary);
+    print_audit_summary(freeze_summary);
+    print_row_count_summary(freeze_summary);
+}
+
+/// print rows counted with --count-only, in total and per chunk of each dataset
+fn print_row_count_summary(freeze_summary: &FreezeSummary) {
+    if freeze_summary.row_counts.is_empty() {
+        return
+    }
+    print_header("\n\nrow counts");
+    let mut datatypes: Vec<&Datatype> = freeze_summary.row_counts.keys().collect();
+    datatypes.sort_by_key(|datatype| datatype.dataset().name());
+    for datatype in datatypes {
+        let counts = &freeze_summary.row_counts[datatype];
+        let n_rows: u64 = counts.values().sum();
+        let min = counts.values().min().copied().unwrap_or_default();
+        let max = counts.values().max().copied().unwrap_or_default();
+        let mean = n_rows as f64 / counts.len().max(1) as f64;
+        print_bullet(
+            datatype.dataset().name(),
+            format!(
+                "{} rows in {} chunks, per chunk min {} mean {} max {}",
+                n_rows.separate_with_commas(),
+                counts.len().separate_with_commas(),
+                min.separate_with_commas(),
+                format_float(mean),
+                max.separate_with_commas(),
+            ),
+        );
+        for (chunk, n_rows) in counts.iter() {
+            println!("    {}: {}", chunk, n_rows.separate_with_commas());
+        }
+    }
+}
+
+/// print decisions given at prompts about failed chunks
+fn print_decision_summary(freeze_summary: &FreezeSummary) {
+    if freeze_summary.decisions.is_empty() {
+        return
+    }
+    print_header("\n\nfailed chunk decisions");
+    for decision in freeze_summary.decisions.iter() {
+        match decision.rsplit_once(": ") {
+            Some((chunk, decision)) => print_bullet(chunk, decision),
+            None => print_bullet("chunk", decision),
+        }
+    }
+}
+
+/// print chunks whose responses differed when collected a second time
+fn print_audit_summary(freeze_summary: &FreezeSummary) {
+    if freeze_summary.n_audited == 0 {
+        return
+    }
+    print_header("\n\ndeterminism audit");
+    print_bullet(
+        "chunks audited",
+        format!(
+            "{}, {} responses differed",
+            freeze_summary.n_audited.separate_with_commas(),
+            freeze_summary.audit_mismatches.len().separate_with_commas()
+        ),
+    );
+    for mismatch in freeze_summary.audit_mismatches.iter() {
+        match mismatch.rsplit_once(": ") {
+            Some((chunk, difference)) => print_bullet(chunk, difference),
+            None => print_bullet("chunk", mismatch),
+        }
+    }
+}
+
+/// print failed chunks grouped by cause, and whether each cause is the provider's fault
+fn print_error_summary(freeze_summary: &FreezeSummary) {
+    if freeze_summary.errors.is_empty() {
+        return
+    }
+    let mut kinds: BTreeMap<ErrorKind, Vec<String>> = BTreeMap::new();
+    for error in freeze_summary.errors.iter() {
+        let chunk = match error {
+            FreezeError::CollectError(CollectError::ChunkFailed { dataset, chunk, .. }) => {
+                format!("{} {}", dataset, chunk.stub().unwrap_or_default())
+            }
+            _ => "unknown chunk".to_string(),
+        };
+        kinds.entry(error.kind()).or_default().push(chunk);
+    }
+
+    print_header("\n\nchunk errors");
+    for (kind, chunks) in kinds.iter() {
+        let fault = match kind.is_provider_fault() {
+            true => "provider",
+            false => "not provider",
+        };
+        let n_chunks = chunks.len().separate_with_commas();
+        print_bullet(kind.as_str(), format!("{} chunks ({}), {}", n_chunks, fault, kind.hint()));
+        let mut examples = chunks.iter().take(MAX_ERROR_CHUNKS).cloned().collect::<Vec<_>>();
+        if chunks.len() > MAX_ERROR_CHUNKS {
+            examples.push(format!("and {} more", chunks.len() - MAX_ERROR_CHUNKS));
+        }
+        println!("    {}", examples.join(", ").truecolor(170, 170, 170));
+    }
+}
+
+/// print requests and response bytes of each rpc method
+pub(crate) fn print_rpc_summary(source: &Source) {
+    let client: &RpcClient = source.provider.as_ref().as_ref();
+    let stats = client.stats();
+    if stats.is_empty() {
+        return
+    }
+    print_header("\n\nrpc responses");
+    for (method, method_stats) in stats.iter() {
+        print_bullet(
+            method,
+            format!(
+                "{} requests, {} bytes",
+                method_stats.n_requests.separate_with_commas(),
+                method_stats.n_bytes.separate_with_commas()
+            ),
+        );
+    }
+    if let Some(n_pauses) = client.circuit_pauses().filter(|n_pauses| *n_pauses > 0) {
+        print_bullet("circuit breaker pauses", n_pauses.separate_with_commas());
+    }
+
+    let credits = client.credits_spent();
+    if credits.is_empty() {
+        return
+    }
+    print_header("\n\nrpc credits");
+    for (method, spent) in credits.iter() {
+        print_bullet(method, spent.separate_with_commas());
+    }
+    let total: u64 = credits.iter().map(|(_, spent)| spent).sum();
+    match client.credit_budget() {
+        Some(budget) => print_bullet(
+            "total",
+            format!("{} of {} budget", total.separate_with_commas(), budget.separate_with_commas()),
+        ),
+        None => print_bullet("total", total.separate_with_commas()),
+    }
+}
+
+/// print files and row groups of --logs-dir skipped by parquet statistics
+pub(crate) fn print_local_logs_summary(source: &Source) {
+    let pruning = match &source.local_logs {
+        Some(local_logs) => local_logs.pruning(),
+        None => return,
+    };
+    if pruning.n_files == 0 {
+        return
+    }
+    print_header("\n\nlocal logs pruning");
+    print_bullet(
+        "files skipped",
+        format!(
+            "{} of {}",
+            pruning.n_files_pruned.separate_with_commas(),
+            pruning.n_files.separate_with_commas()
+        ),
+    );
+    print_bullet(
+        "row groups skipped",
+        format!(
+            "{} of {}",
+            pruning.n_row_groups_pruned.separate_with_commas(),
+            pruning.n_row_groups.separate_with_commas()
+        ),
+    );
 }
 
 fn print_block_chunk_summary(query: &MultiQuery, freeze_summary: &FreezeSummary, total_time: f64) {
@@ -0,0 +1,65 @@
+//! benchmarks of schema conversion, hex encoding, and file writing
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+use cryo_freeze::{
+    testing::{random_dataframe, roundtrip_dataframe},
+    ColumnEncoding, Datatype, FileFormat, ToVecHex,
+};
+
+const DATATYPES: [Datatype; 4] =
+    [Datatype::Blocks, Datatype::Transactions, Datatype::Logs, Datatype::Traces];
+
+const N_ROWS: usize = 10_000;
+
+fn schema_conversion(c: &mut Criterion) {
+    let mut group = c.benchmark_group("schema_conversion");
+    let all = Some(vec!["all".to_string()]);
+    for datatype in DATATYPES.iter() {
+        group.bench_function(datatype.dataset().name(), |b| {
+            b.iter(|| datatype.table_schema(&ColumnEncoding::Hex, &None, &None, &all, None))
+        });
+    }
+    group.finish();
+}
+
+fn hex_encoding(c: &mut Criterion) {
+    let mut group = c.benchmark_group("hex_encoding");
+    for n_bytes in [20, 32, 1024] {
+        let values: Vec<Vec<u8>> = (0..N_ROWS).map(|i| vec![i as u8; n_bytes]).collect();
+        group.throughput(Throughput::Bytes((N_ROWS * n_bytes) as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(n_bytes), &values, |b, values| {
+            b.iter(|| values.to_vec_hex())
+        });
+    }
+    group.finish();
+}
+
+fn file_writing(c: &mut Criterion) {
+    let dir = std::env::temp_dir().join("cryo_benches");
+    let dir = dir.to_string_lossy().to_string();
+    let all = Some(vec!["all".to_string()]);
+    let mut group = c.benchmark_group("file_roundtrip");
+    group.sample_size(10);
+    for format in [FileFormat::Parquet, FileFormat::Csv] {
+        let encoding = match format {
+            FileFormat::Parquet => ColumnEncoding::Binary,
+            _ => ColumnEncoding::Hex,
+        };
+        for datatype in DATATYPES.iter() {
+            let name = datatype.dataset().name();
+            let schema = datatype
+                .table_schema(&encoding, &None, &None, &all, None)
+                .expect("schema of built-in dataset");
+            let df = random_dataframe(&schema, N_ROWS, 0).expect("random dataframe");
+            let id = BenchmarkId::new(format.as_str(), name);
+            group.bench_function(id, |b| {
+                b.iter(|| roundtrip_dataframe(&df, name, &format, &dir).expect("roundtrip"))
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, schema_conversion, hex_encoding, file_writing);
+criterion_main!(benches);
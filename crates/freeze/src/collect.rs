@@ -1,6 +1,8 @@
 use std::collections::HashMap;
 
+use futures::Future;
 use polars::prelude::*;
+use tokio::runtime::Handle;
 
 use crate::types::{Chunk, CollectError, Datatype, MultiQuery, SingleQuery, Source};
 
@@ -8,7 +10,24 @@ use crate::types::{Chunk, CollectError, Datatype, MultiQuery, SingleQuery, Sourc
 pub async fn collect(query: SingleQuery, source: Source) -> Result<DataFrame, CollectError> {
     let chunk: Chunk = query.chunks.into();
     let filter = query.row_filter.as_ref();
-    query.datatype.dataset().collect_chunk(&chunk, &source, &query.schema, filter).await
+    let dataset = query.datatype.dataset();
+    dataset
+        .collect_chunk(&chunk, &source, &query.schema, filter)
+        .await
+        .map_err(|e| e.in_chunk(dataset.name(), &chunk))
+}
+
+/// collect data on the runtime of a handle, returning a future that any executor can await
+///
+/// collection needs a tokio runtime for its timers and http clients. applications that already
+/// run one, or that use another executor, pass a handle instead of entering a runtime for cryo
+pub fn collect_on(
+    handle: &Handle,
+    query: SingleQuery,
+    source: Source,
+) -> impl Future<Output = Result<DataFrame, CollectError>> + Send + 'static {
+    let task = handle.spawn(collect(query, source));
+    async move { task.await.map_err(CollectError::TaskFailed)? }
 }
 
 /// collect data and return as dataframe
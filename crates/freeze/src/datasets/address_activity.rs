@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+
+use ethers::prelude::*;
+use polars::prelude::*;
+use tokio::sync::mpsc;
+
+use super::{blocks, blocks_and_transactions};
+use crate::{
+    dataframes::SortableDataFrame,
+    types::{
+        conversions::{NarrowU32, ToVecHex},
+        AddressActivity, BlockChunk, CollectError, ColumnType, Dataset, Datatype, RowFilter,
+        Source, Table,
+    },
+    with_series, with_series_binary, with_series_u32,
+};
+
+#[async_trait::async_trait]
+impl Dataset for AddressActivity {
+    fn datatype(&self) -> Datatype {
+        Datatype::AddressActivity
+    }
+
+    fn name(&self) -> &'static str {
+        "address_activity"
+    }
+
+    fn column_types(&self) -> HashMap<&'static str, ColumnType> {
+        HashMap::from_iter(vec![
+            ("address", ColumnType::Binary),
+            ("n_sent", ColumnType::UInt32),
+            ("n_received", ColumnType::UInt32),
+            ("value_sent", ColumnType::Decimal128),
+            ("value_received", ColumnType::Decimal128),
+            ("gas_used", ColumnType::UInt64),
+            ("gas_spent", ColumnType::Decimal128),
+            ("first_block", ColumnType::UInt32),
+            ("last_block", ColumnType::UInt32),
+            ("chain_id", ColumnType::UInt64),
+        ])
+    }
+
+    fn default_columns(&self) -> Vec<&'static str> {
+        vec![
+            "address",
+            "n_sent",
+            "n_received",
+            "value_sent",
+            "value_received",
+            "first_block",
+            "last_block",
+        ]
+    }
+
+    fn default_sort(&self) -> Vec<String> {
+        vec!["address".to_string()]
+    }
+
+    async fn collect_block_chunk(
+        &self,
+        chunk: &BlockChunk,
+        source: &Source,
+        schema: &Table,
+        _filter: Option<&RowFilter>,
+    ) -> Result<DataFrame, CollectError> {
+        // gas used requires a receipt per transaction, so it is only fetched when requested
+        let include_gas_used = schema.has_column("gas_used") || schema.has_column("gas_spent");
+        let rx = blocks_and_transactions::fetch_blocks_and_transactions(
+            chunk,
+            source,
+            include_gas_used,
+            false,
+            blocks_and_transactions::RecipientLookups::default(),
+        )
+        .await;
+        address_activity_to_df(rx, schema, source.chain_id).await
+    }
+}
+
+#[derive(Default)]
+struct Activity {
+    n_sent: u32,
+    n_received: u32,
+    value_sent: U256,
+    value_received: U256,
+    gas_used: u64,
+    gas_spent: U256,
+    first_block: u64,
+    last_block: u64,
+}
+
+impl Activity {
+    fn see_block(&mut self, block_number: u64) {
+        if self.n_sent + self.n_received == 0 || block_number < self.first_block {
+            self.first_block = block_number;
+        }
+        self.last_block = self.last_block.max(block_number);
+    }
+}
+
+/// aggregate the transactions of a chunk by sending and receiving address
+async fn address_activity_to_df(
+    mut blocks: mpsc::Receiver<blocks::BlockTxGasTuple<Transaction>>,
+    schema: &Table,
+    chain_id: u64,
+) -> Result<DataFrame, CollectError> {
+    let mut activity: HashMap<H160, Activity> = HashMap::new();
+    while let Some(message) = blocks.recv().await {
+        let (block, receipts, _) = message?;
+        let block_number = match block.number {
+            Some(number) => number.as_u64(),
+            None => return Err(CollectError::CollectError("block number missing".to_string())),
+        };
+        for (i, tx) in block.transactions.iter().enumerate() {
+            let sender = activity.entry(tx.from).or_default();
+            sender.see_block(block_number);
+            sender.n_sent += 1;
+            sender.value_sent += tx.value;
+            if let Some(receipt) = receipts.as_ref().and_then(|receipts| receipts.get(i)) {
+                sender.gas_used += receipt.gas_used;
+                sender.gas_spent += U256::from(receipt.gas_used) * tx.gas_price.unwrap_or_default();
+            }
+            if let Some(to) = tx.to {
+                let receiver = activity.entry(to).or_default();
+                receiver.see_block(block_number);
+                receiver.n_received += 1;
+                receiver.value_received += tx.value;
+            }
+        }
+    }
+
+    let n_rows = activity.len();
+    let mut address: Vec<Vec<u8>> = Vec::with_capacity(n_rows);
+    let mut n_sent: Vec<u32> = Vec::with_capacity(n_rows);
+    let mut n_received: Vec<u32> = Vec::with_capacity(n_rows);
+    let mut value_sent: Vec<String> = Vec::with_capacity(n_rows);
+    let mut value_received: Vec<String> = Vec::with_capacity(n_rows);
+    let mut gas_used: Vec<u64> = Vec::with_capacity(n_rows);
+    let mut gas_spent: Vec<String> = Vec::with_capacity(n_rows);
+    let mut first_block: Vec<u64> = Vec::with_capacity(n_rows);
+    let mut last_block: Vec<u64> = Vec::with_capacity(n_rows);
+    for (addr, a) in activity.into_iter() {
+        address.push(addr.as_bytes().to_vec());
+        n_sent.push(a.n_sent);
+        n_received.push(a.n_received);
+        value_sent.push(a.value_sent.to_string());
+        value_received.push(a.value_received.to_string());
+        gas_used.push(a.gas_used);
+        gas_spent.push(a.gas_spent.to_string());
+        first_block.push(a.first_block);
+        last_block.push(a.last_block);
+    }
+
+    let mut cols = Vec::new();
+    with_series_binary!(cols, "address", address, schema);
+    with_series!(cols, "n_sent", n_sent, schema);
+    with_series!(cols, "n_received", n_received, schema);
+    with_series!(cols, "value_sent", value_sent, schema);
+    with_series!(cols, "value_received", value_received, schema);
+    with_series!(cols, "gas_used", gas_used, schema);
+    with_series!(cols, "gas_spent", gas_spent, schema);
+    with_series_u32!(cols, "first_block", first_block, schema);
+    with_series_u32!(cols, "last_block", last_block, schema);
+    if schema.has_column("chain_id") {
+        cols.push(Series::new("chain_id", vec![chain_id; n_rows]));
+    }
+
+    DataFrame::new(cols).map_err(CollectError::PolarsError).sort_by_schema(schema)
+}
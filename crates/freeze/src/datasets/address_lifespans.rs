@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+
+use ethers::prelude::*;
+use polars::prelude::*;
+
+use crate::{
+    dataframes::SortableDataFrame,
+    lookup,
+    types::{
+        conversions::ToVecHex, AddressLifespans, BlockChunk, ChunkData, CollectError, ColumnType,
+        Dataset, Datatype, RequestPriority, RowFilter, Source, Table,
+    },
+    with_series, with_series_binary,
+};
+
+#[async_trait::async_trait]
+impl Dataset for AddressLifespans {
+    fn datatype(&self) -> Datatype {
+        Datatype::AddressLifespans
+    }
+
+    fn name(&self) -> &'static str {
+        "address_lifespans"
+    }
+
+    fn column_types(&self) -> HashMap<&'static str, ColumnType> {
+        HashMap::from_iter(vec![
+            ("address", ColumnType::Binary),
+            ("first_block", ColumnType::UInt32),
+            ("last_block", ColumnType::UInt32),
+            ("n_sent", ColumnType::UInt64),
+            ("creation_block", ColumnType::UInt32),
+            ("chain_id", ColumnType::UInt64),
+        ])
+    }
+
+    fn default_columns(&self) -> Vec<&'static str> {
+        vec!["address", "first_block", "last_block", "n_sent"]
+    }
+
+    fn default_sort(&self) -> Vec<String> {
+        vec!["address".to_string()]
+    }
+
+    fn nullable_columns(&self) -> Vec<&'static str> {
+        vec!["first_block", "last_block", "creation_block"]
+    }
+
+    fn column_descriptions(&self) -> HashMap<&'static str, &'static str> {
+        HashMap::from_iter(vec![
+            ("first_block", "first block of the chunk in which the address sent a transaction"),
+            ("last_block", "last block of the chunk in which the address sent a transaction"),
+        ])
+    }
+
+    /// first and last block of each address sending a transaction within the chunk
+    ///
+    /// nonces only change when an address sends a transaction, so both blocks are found by
+    /// binary search over eth_getTransactionCount, which requires an archive node. addresses
+    /// that only receive transactions are not detected
+    async fn collect_block_chunk(
+        &self,
+        chunk: &BlockChunk,
+        source: &Source,
+        schema: &Table,
+        filter: Option<&RowFilter>,
+    ) -> Result<DataFrame, CollectError> {
+        let addresses: Vec<H160> = match filter.and_then(|filter| filter.address.as_ref()) {
+            Some(ValueOrArray::Value(address)) => vec![*address],
+            Some(ValueOrArray::Array(addresses)) => addresses.clone(),
+            None => {
+                return Err(CollectError::CollectError(
+                    "address_lifespans requires --addresses or --contract".to_string(),
+                ))
+            }
+        };
+        let (start, end) = match (chunk.min_value(), chunk.max_value()) {
+            (Some(start), Some(end)) => (start, end),
+            _ => return Err(CollectError::CollectError("empty block chunk".to_string())),
+        };
+        let include_creation = schema.has_column("creation_block");
+        let lifespans = futures::future::try_join_all(
+            addresses
+                .iter()
+                .map(|address| address_lifespan(*address, start, end, include_creation, source)),
+        )
+        .await?;
+
+        let mut address: Vec<Vec<u8>> = Vec::new();
+        let mut first_block: Vec<Option<u32>> = Vec::new();
+        let mut last_block: Vec<Option<u32>> = Vec::new();
+        let mut n_sent: Vec<u64> = Vec::new();
+        let mut creation_block: Vec<Option<u32>> = Vec::new();
+        for (lifespan_address, lifespan) in addresses.iter().zip(lifespans) {
+            if lifespan.n_sent == 0 && lifespan.creation_block.is_none() {
+                continue
+            }
+            address.push(lifespan_address.as_bytes().to_vec());
+            first_block.push(lifespan.first_block.map(|block| block as u32));
+            last_block.push(lifespan.last_block.map(|block| block as u32));
+            n_sent.push(lifespan.n_sent);
+            creation_block.push(lifespan.creation_block.map(|block| block as u32));
+        }
+
+        let n_rows = address.len();
+        let mut cols = Vec::new();
+        with_series_binary!(cols, "address", address, schema);
+        with_series!(cols, "first_block", first_block, schema);
+        with_series!(cols, "last_block", last_block, schema);
+        with_series!(cols, "n_sent", n_sent, schema);
+        with_series!(cols, "creation_block", creation_block, schema);
+        if schema.has_column("chain_id") {
+            cols.push(Series::new("chain_id", vec![source.chain_id; n_rows]));
+        }
+
+        DataFrame::new(cols).map_err(CollectError::PolarsError).sort_by_schema(schema)
+    }
+}
+
+#[derive(Default)]
+struct Lifespan {
+    first_block: Option<u64>,
+    last_block: Option<u64>,
+    n_sent: u64,
+    creation_block: Option<u64>,
+}
+
+async fn address_lifespan(
+    address: H160,
+    start: u64,
+    end: u64,
+    include_creation: bool,
+    source: &Source,
+) -> Result<Lifespan, CollectError> {
+    let mut lifespan = Lifespan::default();
+    let nonce_before = match start.checked_sub(1) {
+        Some(before) => nonce_at(address, before, source).await?,
+        None => 0,
+    };
+    let nonce_end = nonce_at(address, end, source).await?;
+    if nonce_end > nonce_before {
+        let first_block =
+            first_block_with_nonce(address, nonce_before + 1, start, end, source).await?;
+        lifespan.first_block = Some(first_block);
+        lifespan.last_block =
+            Some(first_block_with_nonce(address, nonce_end, first_block, end, source).await?);
+        lifespan.n_sent = nonce_end - nonce_before;
+    }
+    if include_creation {
+        lifespan.creation_block = creation_block(address, start, end, source).await?;
+    }
+    Ok(lifespan)
+}
+
+/// binary search for first block in [lower, upper] after which the nonce reaches nonce
+async fn first_block_with_nonce(
+    address: H160,
+    nonce: u64,
+    mut lower: u64,
+    mut upper: u64,
+    source: &Source,
+) -> Result<u64, CollectError> {
+    while lower < upper {
+        let mid = lower + (upper - lower) / 2;
+        if nonce_at(address, mid, source).await? >= nonce {
+            upper = mid;
+        } else {
+            lower = mid + 1;
+        }
+    }
+    Ok(lower)
+}
+
+/// binary search for the block in [start, end] that deployed code to address, if any
+async fn creation_block(
+    address: H160,
+    start: u64,
+    end: u64,
+    source: &Source,
+) -> Result<Option<u64>, CollectError> {
+    if !has_code(address, end, source).await? {
+        return Ok(None)
+    }
+    if let Some(before) = start.checked_sub(1) {
+        if has_code(address, before, source).await? {
+            return Ok(None)
+        }
+    }
+    let (mut lower, mut upper) = (start, end);
+    while lower < upper {
+        let mid = lower + (upper - lower) / 2;
+        if has_code(address, mid, source).await? {
+            upper = mid;
+        } else {
+            lower = mid + 1;
+        }
+    }
+    Ok(Some(lower))
+}
+
+async fn nonce_at(address: H160, block_number: u64, source: &Source) -> Result<u64, CollectError> {
+    let _permit = source.permit_request(RequestPriority::Bulk).await;
+    lookup::nonce_at(address, block_number, &source.provider).await
+}
+
+async fn has_code(address: H160, block_number: u64, source: &Source) -> Result<bool, CollectError> {
+    let _permit = source.permit_request(RequestPriority::Bulk).await;
+    source
+        .provider
+        .get_code(address, Some(block_number.into()))
+        .await
+        .map(|code| !code.is_empty())
+        .map_err(|e| CollectError::rpc("eth_getCode", e))
+}
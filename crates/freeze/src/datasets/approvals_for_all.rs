@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+
+use ethers::prelude::*;
+use polars::prelude::*;
+use tokio::sync::mpsc;
+
+use super::logs;
+use crate::{
+    dataframes::SortableDataFrame,
+    types::{
+        conversions::{checked_u64, NarrowU32, ToVecHex},
+        ApprovalsForAll, BlockChunk, CollectError, ColumnType, Dataset, Datatype, RowFilter,
+        Source, Table, TransactionChunk,
+    },
+    with_series, with_series_binary, with_series_u32,
+};
+
+/// topic0 of ApprovalForAll(address,address,bool), shared by erc721 and erc1155
+const APPROVAL_FOR_ALL_TOPIC: &str =
+    "0x17307eab39ab6107e8899845ad3d59bd9653f200f220920489ca2b5937696c31";
+
+#[async_trait::async_trait]
+impl Dataset for ApprovalsForAll {
+    fn datatype(&self) -> Datatype {
+        Datatype::ApprovalsForAll
+    }
+
+    fn name(&self) -> &'static str {
+        "approvals_for_all"
+    }
+
+    fn column_types(&self) -> HashMap<&'static str, ColumnType> {
+        HashMap::from_iter(vec![
+            ("block_number", ColumnType::UInt32),
+            ("transaction_index", ColumnType::UInt32),
+            ("log_index", ColumnType::UInt32),
+            ("transaction_hash", ColumnType::Binary),
+            ("contract_address", ColumnType::Binary),
+            ("owner_address", ColumnType::Binary),
+            ("operator_address", ColumnType::Binary),
+            ("approved", ColumnType::Boolean),
+            ("chain_id", ColumnType::UInt64),
+        ])
+    }
+
+    fn default_columns(&self) -> Vec<&'static str> {
+        vec![
+            "block_number",
+            "transaction_index",
+            "log_index",
+            "transaction_hash",
+            "contract_address",
+            "owner_address",
+            "operator_address",
+            "approved",
+        ]
+    }
+
+    fn default_sort(&self) -> Vec<String> {
+        vec!["block_number".to_string(), "log_index".to_string()]
+    }
+
+    async fn collect_block_chunk(
+        &self,
+        chunk: &BlockChunk,
+        source: &Source,
+        schema: &Table,
+        filter: Option<&RowFilter>,
+    ) -> Result<DataFrame, CollectError> {
+        let filter = logs::event_filter(filter, &[APPROVAL_FOR_ALL_TOPIC]);
+        let rx = logs::fetch_block_logs(chunk, source, Some(&filter)).await;
+        approvals_for_all_to_df(rx, &filter, schema, source.chain_id).await
+    }
+
+    async fn collect_transaction_chunk(
+        &self,
+        chunk: &TransactionChunk,
+        source: &Source,
+        schema: &Table,
+        filter: Option<&RowFilter>,
+    ) -> Result<DataFrame, CollectError> {
+        let filter = logs::event_filter(filter, &[APPROVAL_FOR_ALL_TOPIC]);
+        let rx = logs::fetch_transaction_logs(chunk, source, Some(&filter)).await;
+        approvals_for_all_to_df(rx, &filter, schema, source.chain_id).await
+    }
+}
+
+async fn approvals_for_all_to_df(
+    mut logs: mpsc::Receiver<Result<Vec<Log>, CollectError>>,
+    filter: &RowFilter,
+    schema: &Table,
+    chain_id: u64,
+) -> Result<DataFrame, CollectError> {
+    let topic: H256 = APPROVAL_FOR_ALL_TOPIC.parse().expect("valid approval for all topic");
+    let mut approvals: Vec<Log> = Vec::new();
+    while let Some(message) = logs.recv().await {
+        approvals.extend(message?.into_iter().filter(|log| {
+            log.removed != Some(true) &&
+                !filter.excluded_addresses.contains(&log.address) &&
+                log.topics.len() == 3 &&
+                log.topics[0] == topic &&
+                log.data.len() == 32
+        }));
+    }
+
+    let n_rows = approvals.len();
+    let mut block_number: Vec<u64> = Vec::with_capacity(n_rows);
+    let mut transaction_index: Vec<u64> = Vec::with_capacity(n_rows);
+    let mut log_index: Vec<u64> = Vec::with_capacity(n_rows);
+    let mut transaction_hash: Vec<Vec<u8>> = Vec::with_capacity(n_rows);
+    let mut contract_address: Vec<Vec<u8>> = Vec::with_capacity(n_rows);
+    let mut owner_address: Vec<Vec<u8>> = Vec::with_capacity(n_rows);
+    let mut operator_address: Vec<Vec<u8>> = Vec::with_capacity(n_rows);
+    let mut approved: Vec<bool> = Vec::with_capacity(n_rows);
+    for log in approvals.iter() {
+        block_number.push(log.block_number.map(|n| n.as_u64()).unwrap_or_default());
+        transaction_index.push(log.transaction_index.map(|i| i.as_u64()).unwrap_or_default());
+        log_index.push(checked_u64(log.log_index.unwrap_or_default(), "log_index")?);
+        transaction_hash.push(log.transaction_hash.unwrap_or_default().as_bytes().to_vec());
+        contract_address.push(log.address.as_bytes().to_vec());
+        owner_address.push(log.topics[1].as_bytes()[12..].to_vec());
+        operator_address.push(log.topics[2].as_bytes()[12..].to_vec());
+        approved.push(log.data.iter().any(|byte| *byte != 0));
+    }
+
+    let mut cols = Vec::new();
+    with_series_u32!(cols, "block_number", block_number, schema);
+    with_series_u32!(cols, "transaction_index", transaction_index, schema);
+    with_series_u32!(cols, "log_index", log_index, schema);
+    with_series_binary!(cols, "transaction_hash", transaction_hash, schema);
+    with_series_binary!(cols, "contract_address", contract_address, schema);
+    with_series_binary!(cols, "owner_address", owner_address, schema);
+    with_series_binary!(cols, "operator_address", operator_address, schema);
+    with_series!(cols, "approved", approved, schema);
+    if schema.has_column("chain_id") {
+        cols.push(Series::new("chain_id", vec![chain_id; n_rows]));
+    }
+
+    DataFrame::new(cols).map_err(CollectError::PolarsError).sort_by_schema(schema)
+}
@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+
+use ethers::{
+    abi::{self, ParamType},
+    prelude::*,
+};
+use polars::prelude::*;
+use tokio::sync::mpsc;
+
+use super::logs;
+use crate::{
+    dataframes::SortableDataFrame,
+    types::{
+        conversions::{checked_u64, NarrowU32, ToVecHex},
+        BeaconDeposits, BlockChunk, CollectError, ColumnType, Dataset, Datatype, RowFilter, Source,
+        Table, TransactionChunk,
+    },
+    with_series, with_series_binary, with_series_u32,
+};
+
+/// topic0 of DepositEvent(bytes,bytes,bytes,bytes,bytes)
+const DEPOSIT_EVENT_TOPIC: &str =
+    "0x649bbc62d0e31342afea4e5cd82d4049e7e1ee912fc0889aa790803be39038c5";
+
+/// beacon chain deposit contract of each chain, used when no contract is specified
+fn deposit_contract(chain_id: u64) -> Option<&'static str> {
+    match chain_id {
+        1 => Some("0x00000000219ab540356cBB839Cbe05303d7705Fa"),
+        17000 => Some("0x4242424242424242424242424242424242424242"),
+        11155111 => Some("0x7f02C3E3c98b133055B8B348B2Ac625669Ed295D"),
+        _ => None,
+    }
+}
+
+#[async_trait::async_trait]
+impl Dataset for BeaconDeposits {
+    fn datatype(&self) -> Datatype {
+        Datatype::BeaconDeposits
+    }
+
+    fn name(&self) -> &'static str {
+        "beacon_deposits"
+    }
+
+    fn column_types(&self) -> HashMap<&'static str, ColumnType> {
+        HashMap::from_iter(vec![
+            ("block_number", ColumnType::UInt32),
+            ("transaction_index", ColumnType::UInt32),
+            ("log_index", ColumnType::UInt32),
+            ("transaction_hash", ColumnType::Binary),
+            ("pubkey", ColumnType::Binary),
+            ("withdrawal_credentials", ColumnType::Binary),
+            ("withdrawal_address", ColumnType::Binary),
+            ("amount", ColumnType::UInt64),
+            ("signature", ColumnType::Binary),
+            ("deposit_index", ColumnType::UInt64),
+            ("chain_id", ColumnType::UInt64),
+        ])
+    }
+
+    fn default_columns(&self) -> Vec<&'static str> {
+        vec![
+            "block_number",
+            "transaction_index",
+            "log_index",
+            "transaction_hash",
+            "pubkey",
+            "withdrawal_credentials",
+            "withdrawal_address",
+            "amount",
+            "deposit_index",
+        ]
+    }
+
+    fn default_sort(&self) -> Vec<String> {
+        vec!["deposit_index".to_string()]
+    }
+
+    fn nullable_columns(&self) -> Vec<&'static str> {
+        vec!["withdrawal_address"]
+    }
+
+    fn column_descriptions(&self) -> HashMap<&'static str, &'static str> {
+        HashMap::from_iter(vec![
+            ("amount", "deposit amount, in gwei"),
+            ("signature", "bls signature of the deposit"),
+        ])
+    }
+
+    async fn collect_block_chunk(
+        &self,
+        chunk: &BlockChunk,
+        source: &Source,
+        schema: &Table,
+        filter: Option<&RowFilter>,
+    ) -> Result<DataFrame, CollectError> {
+        let filter = deposit_filter(filter, source.chain_id)?;
+        let rx = logs::fetch_block_logs(chunk, source, Some(&filter)).await;
+        deposits_to_df(rx, schema, source.chain_id).await
+    }
+
+    async fn collect_transaction_chunk(
+        &self,
+        chunk: &TransactionChunk,
+        source: &Source,
+        schema: &Table,
+        filter: Option<&RowFilter>,
+    ) -> Result<DataFrame, CollectError> {
+        let filter = deposit_filter(filter, source.chain_id)?;
+        let rx = logs::fetch_transaction_logs(chunk, source, Some(&filter)).await;
+        deposits_to_df(rx, schema, source.chain_id).await
+    }
+}
+
+fn deposit_filter(filter: Option<&RowFilter>, chain_id: u64) -> Result<RowFilter, CollectError> {
+    let mut filter = logs::event_filter(filter, &[DEPOSIT_EVENT_TOPIC]);
+    if filter.address.is_none() {
+        let address = deposit_contract(chain_id).ok_or_else(|| {
+            CollectError::CollectError(format!(
+                "no known deposit contract for chain {}, specify one with --contract",
+                chain_id
+            ))
+        })?;
+        filter.address =
+            Some(ValueOrArray::Value(address.parse().expect("valid deposit contract address")));
+    }
+    Ok(filter)
+}
+
+struct Deposit {
+    pubkey: Vec<u8>,
+    withdrawal_credentials: Vec<u8>,
+    amount: u64,
+    signature: Vec<u8>,
+    index: u64,
+}
+
+/// decode DepositEvent data, whose amount and index are little-endian bytes
+fn decode_deposit(log: &Log) -> Option<Deposit> {
+    let params =
+        [ParamType::Bytes, ParamType::Bytes, ParamType::Bytes, ParamType::Bytes, ParamType::Bytes];
+    let mut tokens = abi::decode(&params, &log.data).ok()?.into_iter();
+    let mut next = || tokens.next().and_then(|token| token.into_bytes());
+    let pubkey = next()?;
+    let withdrawal_credentials = next()?;
+    let amount = next()?;
+    let signature = next()?;
+    let index = next()?;
+    Some(Deposit {
+        pubkey,
+        withdrawal_credentials,
+        amount: u64::from_le_bytes(amount.try_into().ok()?),
+        signature,
+        index: u64::from_le_bytes(index.try_into().ok()?),
+    })
+}
+
+/// withdrawal address of 0x01 execution layer credentials, None for 0x00 bls credentials
+fn withdrawal_address(credentials: &[u8]) -> Option<Vec<u8>> {
+    match credentials.first() {
+        Some(0x01) | Some(0x02) if credentials.len() == 32 => Some(credentials[12..].to_vec()),
+        _ => None,
+    }
+}
+
+async fn deposits_to_df(
+    mut logs: mpsc::Receiver<Result<Vec<Log>, CollectError>>,
+    schema: &Table,
+    chain_id: u64,
+) -> Result<DataFrame, CollectError> {
+    let topic: H256 = DEPOSIT_EVENT_TOPIC.parse().expect("valid deposit event topic");
+
+    let mut block_number: Vec<u64> = Vec::new();
+    let mut transaction_index: Vec<u64> = Vec::new();
+    let mut log_index: Vec<u64> = Vec::new();
+    let mut transaction_hash: Vec<Vec<u8>> = Vec::new();
+    let mut pubkey: Vec<Vec<u8>> = Vec::new();
+    let mut withdrawal_credentials: Vec<Vec<u8>> = Vec::new();
+    let mut withdrawal_addresses: Vec<Option<Vec<u8>>> = Vec::new();
+    let mut amount: Vec<u64> = Vec::new();
+    let mut signature: Vec<Vec<u8>> = Vec::new();
+    let mut deposit_index: Vec<u64> = Vec::new();
+    while let Some(message) = logs.recv().await {
+        for log in message?.into_iter() {
+            if log.removed == Some(true) || log.topics.first() != Some(&topic) {
+                continue
+            }
+            let deposit = match decode_deposit(&log) {
+                Some(deposit) => deposit,
+                None => continue,
+            };
+            block_number.push(log.block_number.map(|n| n.as_u64()).unwrap_or_default());
+            transaction_index.push(log.transaction_index.map(|i| i.as_u64()).unwrap_or_default());
+            log_index.push(checked_u64(log.log_index.unwrap_or_default(), "log_index")?);
+            transaction_hash.push(log.transaction_hash.unwrap_or_default().as_bytes().to_vec());
+            withdrawal_addresses.push(withdrawal_address(&deposit.withdrawal_credentials));
+            pubkey.push(deposit.pubkey);
+            withdrawal_credentials.push(deposit.withdrawal_credentials);
+            amount.push(deposit.amount);
+            signature.push(deposit.signature);
+            deposit_index.push(deposit.index);
+        }
+    }
+
+    let n_rows = block_number.len();
+    let mut cols = Vec::new();
+    with_series_u32!(cols, "block_number", block_number, schema);
+    with_series_u32!(cols, "transaction_index", transaction_index, schema);
+    with_series_u32!(cols, "log_index", log_index, schema);
+    with_series_binary!(cols, "transaction_hash", transaction_hash, schema);
+    with_series_binary!(cols, "pubkey", pubkey, schema);
+    with_series_binary!(cols, "withdrawal_credentials", withdrawal_credentials, schema);
+    with_series_binary!(cols, "withdrawal_address", withdrawal_addresses, schema);
+    with_series!(cols, "amount", amount, schema);
+    with_series_binary!(cols, "signature", signature, schema);
+    with_series!(cols, "deposit_index", deposit_index, schema);
+    if schema.has_column("chain_id") {
+        cols.push(Series::new("chain_id", vec![chain_id; n_rows]));
+    }
+
+    DataFrame::new(cols).map_err(CollectError::PolarsError).sort_by_schema(schema)
+}
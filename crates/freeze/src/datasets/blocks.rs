@@ -4,16 +4,43 @@ use ethers::prelude::*;
 use polars::prelude::*;
 use tokio::{sync::mpsc, task};
 
+use super::blocks_and_transactions::needs_receipts;
 use crate::{
     dataframes::SortableDataFrame,
     types::{
-        conversions::{ToVecHex, ToVecU8},
-        BlockChunk, Blocks, CollectError, ColumnType, Dataset, Datatype, RowFilter, Source, Table,
+        chains::{other_bytes, other_field},
+        conversions::{checked_u64, NarrowU32, ToVecHex, ToVecU8},
+        BlockChunk, Blocks, ChainExtension, CollectError, ColumnType, Dataset, Datatype,
+        RequestPriority, RowFilter, Source, Table,
     },
-    with_series, with_series_binary,
+    with_series, with_series_binary, with_series_u32,
 };
 
-pub(crate) type BlockTxGasTuple<TX> = Result<(Block<TX>, Option<Vec<u32>>), CollectError>;
+/// block with optional per-transaction receipt fields and recipient lookups
+pub(crate) type BlockTxGasTuple<TX> =
+    Result<(Block<TX>, Option<Vec<ReceiptFields>>, Option<Vec<Recipient>>), CollectError>;
+
+/// fields of a transaction's receipt, fetched when a receipt column is requested
+#[derive(Clone, Debug)]
+pub(crate) struct ReceiptFields {
+    pub(crate) gas_used: u64,
+    /// 1 for success and 0 for failure, None before byzantium
+    pub(crate) status: Option<u64>,
+    /// post-transaction state root, only present before byzantium
+    pub(crate) state_root: Option<Vec<u8>>,
+    /// gas used by the top level call or creation, from traces, only fetched for gas_refund
+    pub(crate) execution_gas: Option<u64>,
+    /// whether the top level call or creation failed, in which case nothing is refunded
+    pub(crate) execution_failed: bool,
+}
+
+/// lookups about a transaction's recipient that take extra requests, None for contract creations
+/// or when not requested
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Recipient {
+    pub(crate) is_contract: Option<bool>,
+    pub(crate) is_verified: Option<bool>,
+}
 
 #[async_trait::async_trait]
 impl Dataset for Blocks {
@@ -36,12 +63,22 @@ impl Dataset for Blocks {
             ("number", ColumnType::UInt32),
             ("gas_used", ColumnType::UInt32),
             ("extra_data", ColumnType::Binary),
+            ("extra_data_utf8", ColumnType::String),
             ("logs_bloom", ColumnType::Binary),
             ("timestamp", ColumnType::UInt32),
             ("total_difficulty", ColumnType::String),
+            ("difficulty", ColumnType::String),
+            ("nonce", ColumnType::Binary),
+            ("mix_hash", ColumnType::Binary),
+            ("prev_randao", ColumnType::Binary),
+            ("is_proof_of_stake", ColumnType::Boolean),
             ("size", ColumnType::UInt32),
             ("base_fee_per_gas", ColumnType::UInt64),
             ("chain_id", ColumnType::UInt64),
+            ("extra_fields", ColumnType::String),
+            ("l1_block_number", ColumnType::UInt64),
+            ("send_count", ColumnType::UInt64),
+            ("send_root", ColumnType::Binary),
             // not including: transactions, seal_fields, epoch_snark_data, randomness
         ])
     }
@@ -54,6 +91,31 @@ impl Dataset for Blocks {
         vec!["number".to_string()]
     }
 
+    fn nullable_columns(&self) -> Vec<&'static str> {
+        vec![
+            "extra_data_utf8",
+            "logs_bloom",
+            "total_difficulty",
+            "nonce",
+            "mix_hash",
+            "prev_randao",
+            "size",
+            "base_fee_per_gas",
+            "extra_fields",
+            "l1_block_number",
+            "send_count",
+            "send_root",
+        ]
+    }
+
+    fn column_descriptions(&self) -> HashMap<&'static str, &'static str> {
+        HashMap::from_iter(vec![
+            ("nonce", "proof of work nonce of the block"),
+            ("gas_used", "gas used by the transactions of the block"),
+            ("gas_limit", "gas limit of the block"),
+        ])
+    }
+
     async fn collect_block_chunk(
         &self,
         chunk: &BlockChunk,
@@ -80,21 +142,14 @@ async fn fetch_blocks(
     for number in block_chunk.numbers() {
         let tx = tx.clone();
         let provider = Arc::clone(&source.provider);
-        let semaphore = source.semaphore.clone();
-        let rate_limiter = source.rate_limiter.as_ref().map(Arc::clone);
+        let source = source.clone();
         task::spawn(async move {
-            let _permit = match semaphore {
-                Some(semaphore) => Some(Arc::clone(&semaphore).acquire_owned().await),
-                _ => None,
-            };
-            if let Some(limiter) = rate_limiter {
-                Arc::clone(&limiter).until_ready().await;
-            }
+            let _permit = source.permit_request(RequestPriority::Bulk).await;
             let block = provider.get_block(number).await;
             let result = match block {
-                Ok(Some(block)) => Ok((block, None)),
+                Ok(Some(block)) => Ok((block, None, None)),
                 Ok(None) => Err(CollectError::CollectError("block not in node".to_string())),
-                Err(e) => Err(CollectError::ProviderError(e)),
+                Err(e) => Err(CollectError::rpc("eth_getBlockByNumber", e)),
             };
             match tx.send(result).await {
                 Ok(_) => {}
@@ -109,18 +164,42 @@ async fn fetch_blocks(
 }
 
 pub(crate) trait ProcessTransactions {
-    fn process(&self, schema: &Table, columns: &mut TransactionColumns, gas_used: Option<u32>);
+    fn process(
+        &self,
+        schema: &Table,
+        columns: &mut TransactionColumns,
+        receipt: Option<&ReceiptFields>,
+        recipient: Recipient,
+        chain: Option<ChainExtension>,
+        forks: Option<GasForks>,
+    ) -> Result<(), CollectError>;
 }
 
 impl ProcessTransactions for TxHash {
-    fn process(&self, _schema: &Table, _columns: &mut TransactionColumns, _gas_used: Option<u32>) {
+    fn process(
+        &self,
+        _schema: &Table,
+        _columns: &mut TransactionColumns,
+        _receipt: Option<&ReceiptFields>,
+        _recipient: Recipient,
+        _chain: Option<ChainExtension>,
+        _forks: Option<GasForks>,
+    ) -> Result<(), CollectError> {
         panic!("transaction data not available to process")
     }
 }
 
 impl ProcessTransactions for Transaction {
-    fn process(&self, schema: &Table, columns: &mut TransactionColumns, gas_used: Option<u32>) {
-        process_transaction(self, schema, columns, gas_used)
+    fn process(
+        &self,
+        schema: &Table,
+        columns: &mut TransactionColumns,
+        receipt: Option<&ReceiptFields>,
+        recipient: Recipient,
+        chain: Option<ChainExtension>,
+        forks: Option<GasForks>,
+    ) -> Result<(), CollectError> {
+        process_transaction(self, schema, columns, receipt, recipient, chain, forks)
     }
 }
 
@@ -140,36 +219,32 @@ pub(crate) async fn blocks_to_dfs<TX: ProcessTransactions>(
     };
 
     // parse stream of blocks
+    let chain = ChainExtension::from_chain_id(chain_id);
+    let forks = GasForks::of_chain(chain_id);
     let mut n_blocks = 0;
     let mut n_txs = 0;
     while let Some(message) = blocks.recv().await {
         match message {
-            Ok((block, gas_used)) => {
+            Ok((block, receipts, recipients)) => {
                 n_blocks += 1;
                 if let Some(schema) = blocks_schema {
-                    process_block(&block, schema, &mut block_columns)
+                    process_block(&block, schema, &mut block_columns, chain)?
                 }
                 if let Some(schema) = transactions_schema {
-                    match gas_used {
-                        Some(gas_used) => {
-                            for (tx, gas_used) in block.transactions.iter().zip(gas_used) {
-                                n_txs += 1;
-                                tx.process(schema, &mut transaction_columns, Some(gas_used))
-                            }
-                        }
-                        None => {
-                            for tx in block.transactions.iter() {
-                                n_txs += 1;
-                                tx.process(schema, &mut transaction_columns, None)
-                            }
-                        }
+                    for (i, tx) in block.transactions.iter().enumerate() {
+                        n_txs += 1;
+                        let receipt = receipts.as_ref().and_then(|receipts| receipts.get(i));
+                        let recipient = recipients
+                            .as_ref()
+                            .and_then(|recipients| recipients.get(i))
+                            .copied()
+                            .unwrap_or_default();
+                        let columns = &mut transaction_columns;
+                        tx.process(schema, columns, receipt, recipient, chain, forks)?
                     }
                 }
             }
-            Err(e) => {
-                println!("{:?}", e);
-                return Err(CollectError::TooManyRequestsError)
-            }
+            Err(e) => return Err(e),
         }
     }
 
@@ -192,14 +267,24 @@ struct BlockColumns {
     state_root: Vec<Vec<u8>>,
     transactions_root: Vec<Vec<u8>>,
     receipts_root: Vec<Vec<u8>>,
-    number: Vec<u32>,
-    gas_used: Vec<u32>,
+    number: Vec<u64>,
+    gas_used: Vec<u64>,
     extra_data: Vec<Vec<u8>>,
+    extra_data_utf8: Vec<Option<String>>,
     logs_bloom: Vec<Option<Vec<u8>>>,
-    timestamp: Vec<u32>,
+    timestamp: Vec<u64>,
     total_difficulty: Vec<Option<Vec<u8>>>,
-    size: Vec<Option<u32>>,
+    difficulty: Vec<String>,
+    nonce: Vec<Option<Vec<u8>>>,
+    mix_hash: Vec<Option<Vec<u8>>>,
+    prev_randao: Vec<Option<Vec<u8>>>,
+    is_proof_of_stake: Vec<bool>,
+    size: Vec<Option<u64>>,
     base_fee_per_gas: Vec<Option<u64>>,
+    extra_fields: Vec<Option<String>>,
+    l1_block_number: Vec<Option<u64>>,
+    send_count: Vec<Option<u64>>,
+    send_root: Vec<Option<Vec<u8>>>,
 }
 
 impl BlockColumns {
@@ -214,11 +299,21 @@ impl BlockColumns {
             number: Vec::with_capacity(n),
             gas_used: Vec::with_capacity(n),
             extra_data: Vec::with_capacity(n),
+            extra_data_utf8: Vec::with_capacity(n),
             logs_bloom: Vec::with_capacity(n),
             timestamp: Vec::with_capacity(n),
             total_difficulty: Vec::with_capacity(n),
+            difficulty: Vec::with_capacity(n),
+            nonce: Vec::with_capacity(n),
+            mix_hash: Vec::with_capacity(n),
+            prev_randao: Vec::with_capacity(n),
+            is_proof_of_stake: Vec::with_capacity(n),
             size: Vec::with_capacity(n),
             base_fee_per_gas: Vec::with_capacity(n),
+            extra_fields: Vec::with_capacity(n),
+            l1_block_number: Vec::with_capacity(n),
+            send_count: Vec::with_capacity(n),
+            send_root: Vec::with_capacity(n),
         }
     }
 
@@ -235,14 +330,24 @@ impl BlockColumns {
         with_series_binary!(cols, "state_root", self.state_root, schema);
         with_series_binary!(cols, "transactions_root", self.transactions_root, schema);
         with_series_binary!(cols, "receipts_root", self.receipts_root, schema);
-        with_series!(cols, "number", self.number, schema);
-        with_series!(cols, "gas_used", self.gas_used, schema);
+        with_series_u32!(cols, "number", self.number, schema);
+        with_series_u32!(cols, "gas_used", self.gas_used, schema);
         with_series_binary!(cols, "extra_data", self.extra_data, schema);
+        with_series!(cols, "extra_data_utf8", self.extra_data_utf8, schema);
         with_series_binary!(cols, "logs_bloom", self.logs_bloom, schema);
-        with_series!(cols, "timestamp", self.timestamp, schema);
+        with_series_u32!(cols, "timestamp", self.timestamp, schema);
         with_series_binary!(cols, "total_difficulty", self.total_difficulty, schema);
-        with_series!(cols, "size", self.size, schema);
+        with_series!(cols, "difficulty", self.difficulty, schema);
+        with_series_binary!(cols, "nonce", self.nonce, schema);
+        with_series_binary!(cols, "mix_hash", self.mix_hash, schema);
+        with_series_binary!(cols, "prev_randao", self.prev_randao, schema);
+        with_series!(cols, "is_proof_of_stake", self.is_proof_of_stake, schema);
+        with_series_u32!(cols, "size", self.size, schema);
         with_series!(cols, "base_fee_per_gas", self.base_fee_per_gas, schema);
+        with_series!(cols, "extra_fields", self.extra_fields, schema);
+        with_series!(cols, "l1_block_number", self.l1_block_number, schema);
+        with_series!(cols, "send_count", self.send_count, schema);
+        with_series_binary!(cols, "send_root", self.send_root, schema);
 
         if schema.has_column("chain_id") {
             cols.push(Series::new("chain_id", vec![chain_id; n_rows as usize]));
@@ -261,12 +366,30 @@ pub(crate) struct TransactionColumns {
     to_address: Vec<Option<Vec<u8>>>,
     value: Vec<String>,
     input: Vec<Vec<u8>>,
-    gas_limit: Vec<u32>,
-    gas_used: Vec<u32>,
+    gas_limit: Vec<u64>,
+    gas_used: Vec<u64>,
+    intrinsic_gas: Vec<Option<u64>>,
+    gas_refund: Vec<Option<u64>>,
+    status: Vec<Option<u64>>,
+    state_root: Vec<Option<Vec<u8>>>,
     gas_price: Vec<Option<u64>>,
-    transaction_type: Vec<Option<u32>>,
+    transaction_type: Vec<Option<u64>>,
     max_priority_fee_per_gas: Vec<Option<u64>>,
     max_fee_per_gas: Vec<Option<u64>>,
+    transaction_type_name: Vec<&'static str>,
+    transaction_kind: Vec<&'static str>,
+    to_kind: Vec<Option<&'static str>>,
+    is_verified: Vec<Option<bool>>,
+    extra_fields: Vec<Option<String>>,
+    fee_currency: Vec<Option<Vec<u8>>>,
+    gateway_fee_recipient: Vec<Option<Vec<u8>>>,
+    gateway_fee: Vec<Option<String>>,
+    is_state_sync: Vec<Option<bool>>,
+    request_id: Vec<Option<Vec<u8>>>,
+    ticket_id: Vec<Option<Vec<u8>>>,
+    refund_to: Vec<Option<Vec<u8>>>,
+    max_refund: Vec<Option<String>>,
+    submission_fee_refund: Vec<Option<String>>,
 }
 
 impl TransactionColumns {
@@ -282,10 +405,28 @@ impl TransactionColumns {
             input: Vec::with_capacity(n),
             gas_limit: Vec::with_capacity(n),
             gas_used: Vec::with_capacity(n),
+            intrinsic_gas: Vec::with_capacity(n),
+            gas_refund: Vec::with_capacity(n),
+            status: Vec::with_capacity(n),
+            state_root: Vec::with_capacity(n),
             gas_price: Vec::with_capacity(n),
             transaction_type: Vec::with_capacity(n),
             max_priority_fee_per_gas: Vec::with_capacity(n),
             max_fee_per_gas: Vec::with_capacity(n),
+            transaction_type_name: Vec::with_capacity(n),
+            transaction_kind: Vec::with_capacity(n),
+            to_kind: Vec::with_capacity(n),
+            is_verified: Vec::with_capacity(n),
+            extra_fields: Vec::with_capacity(n),
+            fee_currency: Vec::with_capacity(n),
+            gateway_fee_recipient: Vec::with_capacity(n),
+            gateway_fee: Vec::with_capacity(n),
+            is_state_sync: Vec::with_capacity(n),
+            request_id: Vec::with_capacity(n),
+            ticket_id: Vec::with_capacity(n),
+            refund_to: Vec::with_capacity(n),
+            max_refund: Vec::with_capacity(n),
+            submission_fee_refund: Vec::with_capacity(n),
         }
     }
 
@@ -304,12 +445,30 @@ impl TransactionColumns {
         with_series_binary!(cols, "to_address", self.to_address, schema);
         with_series!(cols, "value", self.value, schema);
         with_series_binary!(cols, "input", self.input, schema);
-        with_series!(cols, "gas_limit", self.gas_limit, schema);
-        with_series!(cols, "gas_used", self.gas_used, schema);
+        with_series_u32!(cols, "gas_limit", self.gas_limit, schema);
+        with_series_u32!(cols, "gas_used", self.gas_used, schema);
+        with_series!(cols, "intrinsic_gas", self.intrinsic_gas, schema);
+        with_series!(cols, "gas_refund", self.gas_refund, schema);
+        with_series_u32!(cols, "status", self.status, schema);
+        with_series_binary!(cols, "state_root", self.state_root, schema);
         with_series!(cols, "gas_price", self.gas_price, schema);
-        with_series!(cols, "transaction_type", self.transaction_type, schema);
+        with_series_u32!(cols, "transaction_type", self.transaction_type, schema);
         with_series!(cols, "max_priority_fee_per_gas", self.max_priority_fee_per_gas, schema);
         with_series!(cols, "max_fee_per_gas", self.max_fee_per_gas, schema);
+        with_series!(cols, "transaction_type_name", self.transaction_type_name, schema);
+        with_series!(cols, "transaction_kind", self.transaction_kind, schema);
+        with_series!(cols, "to_kind", self.to_kind, schema);
+        with_series!(cols, "is_verified", self.is_verified, schema);
+        with_series!(cols, "extra_fields", self.extra_fields, schema);
+        with_series_binary!(cols, "fee_currency", self.fee_currency, schema);
+        with_series_binary!(cols, "gateway_fee_recipient", self.gateway_fee_recipient, schema);
+        with_series!(cols, "gateway_fee", self.gateway_fee, schema);
+        with_series!(cols, "is_state_sync", self.is_state_sync, schema);
+        with_series_binary!(cols, "request_id", self.request_id, schema);
+        with_series_binary!(cols, "ticket_id", self.ticket_id, schema);
+        with_series_binary!(cols, "refund_to", self.refund_to, schema);
+        with_series!(cols, "max_refund", self.max_refund, schema);
+        with_series!(cols, "submission_fee_refund", self.submission_fee_refund, schema);
 
         if schema.has_column("chain_id") {
             cols.push(Series::new("chain_id", vec![chain_id; n_rows]));
@@ -319,7 +478,12 @@ impl TransactionColumns {
     }
 }
 
-fn process_block<TX>(block: &Block<TX>, schema: &Table, columns: &mut BlockColumns) {
+fn process_block<TX>(
+    block: &Block<TX>,
+    schema: &Table,
+    columns: &mut BlockColumns,
+    chain: Option<ChainExtension>,
+) -> Result<(), CollectError> {
     if schema.has_column("hash") {
         match block.hash {
             Some(h) => columns.hash.push(h.as_bytes().to_vec()),
@@ -346,39 +510,186 @@ fn process_block<TX>(block: &Block<TX>, schema: &Table, columns: &mut BlockColum
     }
     if schema.has_column("number") {
         match block.number {
-            Some(n) => columns.number.push(n.as_u32()),
+            Some(n) => columns.number.push(n.as_u64()),
             _ => panic!("invalid block"),
         }
     }
     if schema.has_column("gas_used") {
-        columns.gas_used.push(block.gas_used.as_u32());
+        columns.gas_used.push(checked_u64(block.gas_used, "gas_used")?);
     }
     if schema.has_column("extra_data") {
         columns.extra_data.push(block.extra_data.to_vec());
     }
+    if schema.has_column("extra_data_utf8") {
+        columns.extra_data_utf8.push(String::from_utf8(block.extra_data.to_vec()).ok());
+    }
     if schema.has_column("logs_bloom") {
         columns.logs_bloom.push(block.logs_bloom.map(|x| x.0.to_vec()));
     }
     if schema.has_column("timestamp") {
-        columns.timestamp.push(block.timestamp.as_u32());
+        columns.timestamp.push(checked_u64(block.timestamp, "timestamp")?);
     }
     if schema.has_column("total_difficulty") {
         columns.total_difficulty.push(block.total_difficulty.map(|x| x.to_vec_u8()));
     }
     if schema.has_column("size") {
-        columns.size.push(block.size.map(|x| x.as_u32()));
+        columns.size.push(block.size.map(|x| checked_u64(x, "size")).transpose()?);
+    }
+
+    // consensus fields, after the merge difficulty is zero and mix_hash holds prevrandao
+    let is_proof_of_stake = block.difficulty.is_zero();
+    if schema.has_column("difficulty") {
+        columns.difficulty.push(block.difficulty.to_string());
+    }
+    if schema.has_column("nonce") {
+        columns.nonce.push(block.nonce.map(|x| x.as_bytes().to_vec()));
+    }
+    if schema.has_column("mix_hash") {
+        columns.mix_hash.push(block.mix_hash.map(|x| x.as_bytes().to_vec()));
+    }
+    if schema.has_column("prev_randao") {
+        let prev_randao = block.mix_hash.filter(|_| is_proof_of_stake);
+        columns.prev_randao.push(prev_randao.map(|x| x.as_bytes().to_vec()));
+    }
+    if schema.has_column("is_proof_of_stake") {
+        columns.is_proof_of_stake.push(is_proof_of_stake);
     }
     if schema.has_column("base_fee_per_gas") {
-        columns.base_fee_per_gas.push(block.base_fee_per_gas.map(|value| value.as_u64()));
+        let value = block.base_fee_per_gas.map(|value| checked_u64(value, "base_fee_per_gas"));
+        columns.base_fee_per_gas.push(value.transpose()?);
+    }
+    if schema.has_column("extra_fields") {
+        columns.extra_fields.push(extra_fields(&block.other));
+    }
+
+    // arbitrum block fields
+    let arbitrum = chain == Some(ChainExtension::Arbitrum);
+    if schema.has_column("l1_block_number") {
+        let value = other_field::<U64>(&block.other, "l1BlockNumber").filter(|_| arbitrum);
+        columns.l1_block_number.push(value.map(|value| value.as_u64()));
+    }
+    if schema.has_column("send_count") {
+        let value = other_field::<U64>(&block.other, "sendCount").filter(|_| arbitrum);
+        columns.send_count.push(value.map(|value| value.as_u64()));
+    }
+    if schema.has_column("send_root") {
+        columns.send_root.push(other_bytes::<H256>(&block.other, "sendRoot").filter(|_| arbitrum));
     }
+    Ok(())
+}
+
+/// nonstandard response fields as a json object, None when the response has none
+fn extra_fields(other: &OtherFields) -> Option<String> {
+    match other.is_empty() {
+        true => None,
+        false => serde_json::to_string(other).ok(),
+    }
+}
+
+/// first blocks of the forks that changed intrinsic gas on a chain
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct GasForks {
+    /// creations cost 53000 gas (EIP-2)
+    homestead: u64,
+    /// nonzero calldata bytes cost 16 gas instead of 68 (EIP-2028)
+    istanbul: u64,
+    /// init code costs 2 gas per word (EIP-3860)
+    shanghai: u64,
+    /// set code authorizations (EIP-7702) and the calldata gas floor (EIP-7623)
+    prague: u64,
+}
+
+impl GasForks {
+    /// forks of chains with a known schedule, None for other chains
+    pub(crate) fn of_chain(chain_id: u64) -> Option<GasForks> {
+        match chain_id {
+            // ethereum mainnet
+            1 => Some(GasForks {
+                homestead: 1_150_000,
+                istanbul: 9_069_000,
+                shanghai: 17_034_870,
+                prague: 22_431_084,
+            }),
+            // sepolia, which launched with all forks through london
+            11155111 => {
+                Some(GasForks { homestead: 0, istanbul: 0, shanghai: 2_990_908, prague: 7_836_331 })
+            }
+            _ => None,
+        }
+    }
+
+    /// whether the prague fork is active at the block of a transaction
+    fn is_prague(&self, tx: &Transaction) -> bool {
+        tx_block_number(tx) >= self.prague
+    }
+}
+
+/// block of a transaction, pending transactions are treated as included in the latest fork
+fn tx_block_number(tx: &Transaction) -> u64 {
+    tx.block_number.map(|number| number.as_u64()).unwrap_or(u64::MAX)
+}
+
+/// gas charged before execution for the base cost, calldata, access list, authorizations, and
+/// init code of a transaction, following the forks of its chain
+fn intrinsic_gas(tx: &Transaction, forks: &GasForks) -> u64 {
+    let block_number = tx_block_number(tx);
+    let is_creation = tx.to.is_none();
+    let mut gas: u64 = match is_creation && block_number >= forks.homestead {
+        true => 53_000,
+        false => 21_000,
+    };
+    let nonzero_byte_gas = if block_number >= forks.istanbul { 16 } else { 68 };
+    for byte in tx.input.iter() {
+        gas += if *byte == 0 { 4 } else { nonzero_byte_gas };
+    }
+    if let Some(access_list) = &tx.access_list {
+        for item in access_list.0.iter() {
+            gas += 2_400 + 1_900 * item.storage_keys.len() as u64;
+        }
+    }
+    if forks.is_prague(tx) {
+        if let Some(authorizations) =
+            other_field::<Vec<serde_json::Value>>(&tx.other, "authorizationList")
+        {
+            gas += 25_000 * authorizations.len() as u64;
+        }
+    }
+    if is_creation && block_number >= forks.shanghai {
+        gas += 2 * tx.input.len().div_ceil(32) as u64;
+    }
+    gas
+}
+
+/// least gas a transaction uses after prague, 10 gas per calldata token (EIP-7623)
+fn calldata_floor_gas(tx: &Transaction) -> u64 {
+    let tokens: u64 = tx.input.iter().map(|byte| if *byte == 0 { 1 } else { 4 }).sum();
+    21_000 + 10 * tokens
+}
+
+/// gas refunded after execution, derived from the gas used by the receipt
+///
+/// gas used is intrinsic gas plus execution gas, minus the refund. after prague gas used is
+/// raised to the calldata floor, which hides the refund, so none is derived when the floor applies
+fn gas_refund(tx: &Transaction, receipt: &ReceiptFields, forks: &GasForks) -> Option<u64> {
+    if receipt.execution_failed {
+        return Some(0)
+    }
+    let execution_gas = receipt.execution_gas?;
+    if forks.is_prague(tx) && receipt.gas_used <= calldata_floor_gas(tx) {
+        return None
+    }
+    Some((intrinsic_gas(tx, forks) + execution_gas).saturating_sub(receipt.gas_used))
 }
 
 fn process_transaction(
     tx: &Transaction,
     schema: &Table,
     columns: &mut TransactionColumns,
-    gas_used: Option<u32>,
-) {
+    receipt: Option<&ReceiptFields>,
+    recipient: Recipient,
+    chain: Option<ChainExtension>,
+    forks: Option<GasForks>,
+) -> Result<(), CollectError> {
     if schema.has_column("block_number") {
         match tx.block_number {
             Some(block_number) => columns.block_number.push(Some(block_number.as_u64())),
@@ -415,23 +726,245 @@ fn process_transaction(
         columns.input.push(tx.input.to_vec());
     }
     if schema.has_column("gas_limit") {
-        columns.gas_limit.push(tx.gas.as_u32());
+        columns.gas_limit.push(checked_u64(tx.gas, "gas_limit")?);
     }
-    if schema.has_column("gas_used") {
-        columns.gas_used.push(gas_used.unwrap())
+    if schema.has_column("intrinsic_gas") {
+        columns.intrinsic_gas.push(forks.map(|forks| intrinsic_gas(tx, &forks)));
+    }
+    if needs_receipts(schema) {
+        let receipt = receipt.ok_or_else(|| {
+            CollectError::CollectError("transaction receipt not available".to_string())
+        })?;
+        if schema.has_column("gas_used") {
+            columns.gas_used.push(receipt.gas_used)
+        }
+        if schema.has_column("gas_refund") {
+            let refund = forks.and_then(|forks| gas_refund(tx, receipt, &forks));
+            columns.gas_refund.push(refund)
+        }
+        if schema.has_column("status") {
+            columns.status.push(receipt.status)
+        }
+        if schema.has_column("state_root") {
+            columns.state_root.push(receipt.state_root.clone())
+        }
     }
     if schema.has_column("gas_price") {
-        columns.gas_price.push(tx.gas_price.map(|gas_price| gas_price.as_u64()));
+        let value = tx.gas_price.map(|gas_price| checked_u64(gas_price, "gas_price"));
+        columns.gas_price.push(value.transpose()?);
     }
     if schema.has_column("transaction_type") {
-        columns.transaction_type.push(tx.transaction_type.map(|value| value.as_u32()));
+        columns.transaction_type.push(tx.transaction_type.map(|value| value.as_u64()));
     }
     if schema.has_column("max_priority_fee_per_gas") {
-        columns
-            .max_priority_fee_per_gas
-            .push(tx.max_priority_fee_per_gas.map(|value| value.as_u64()));
+        let value =
+            tx.max_priority_fee_per_gas.map(|value| checked_u64(value, "max_priority_fee_per_gas"));
+        columns.max_priority_fee_per_gas.push(value.transpose()?);
     }
     if schema.has_column("max_fee_per_gas") {
-        columns.max_fee_per_gas.push(tx.max_fee_per_gas.map(|value| value.as_u64()));
+        let value = tx.max_fee_per_gas.map(|value| checked_u64(value, "max_fee_per_gas"));
+        columns.max_fee_per_gas.push(value.transpose()?);
+    }
+    if schema.has_column("transaction_type_name") {
+        let type_name = match tx.transaction_type.map(|value| value.as_u64()) {
+            None | Some(0) => "legacy",
+            Some(1) => "eip2930",
+            Some(2) => "eip1559",
+            Some(3) => "eip4844",
+            Some(0x7e) => "deposit",
+            Some(_) => "unknown",
+        };
+        columns.transaction_type_name.push(type_name);
+    }
+    if schema.has_column("transaction_kind") {
+        let kind = match tx.to {
+            None => "creation",
+            Some(_) if tx.input.is_empty() => "transfer",
+            Some(_) => "call",
+        };
+        columns.transaction_kind.push(kind);
+    }
+    if schema.has_column("to_kind") {
+        let to_kind = match (tx.to, recipient.is_contract) {
+            (Some(_), Some(true)) => Some("contract"),
+            (Some(_), Some(false)) => Some("eoa"),
+            _ => None,
+        };
+        columns.to_kind.push(to_kind);
+    }
+    if schema.has_column("is_verified") {
+        columns.is_verified.push(recipient.is_verified);
+    }
+    if schema.has_column("extra_fields") {
+        columns.extra_fields.push(extra_fields(&tx.other));
+    }
+
+    // celo transaction fields
+    let celo = chain == Some(ChainExtension::Celo);
+    if schema.has_column("fee_currency") {
+        let value = other_bytes::<Address>(&tx.other, "feeCurrency").filter(|_| celo);
+        columns.fee_currency.push(value);
+    }
+    if schema.has_column("gateway_fee_recipient") {
+        let value = other_bytes::<Address>(&tx.other, "gatewayFeeRecipient").filter(|_| celo);
+        columns.gateway_fee_recipient.push(value);
+    }
+    if schema.has_column("gateway_fee") {
+        let value = other_field::<U256>(&tx.other, "gatewayFee").filter(|_| celo);
+        columns.gateway_fee.push(value.map(|value| value.to_string()));
+    }
+
+    // polygon bor state-sync transactions are sent from and to the zero address
+    if schema.has_column("is_state_sync") {
+        let is_state_sync = match chain {
+            Some(ChainExtension::Polygon) => {
+                Some(tx.from.is_zero() && tx.to.map(|to| to.is_zero()).unwrap_or(false))
+            }
+            _ => None,
+        };
+        columns.is_state_sync.push(is_state_sync);
+    }
+
+    // arbitrum retryable ticket fields
+    let arbitrum = chain == Some(ChainExtension::Arbitrum);
+    if schema.has_column("request_id") {
+        columns.request_id.push(other_bytes::<H256>(&tx.other, "requestId").filter(|_| arbitrum));
+    }
+    if schema.has_column("ticket_id") {
+        columns.ticket_id.push(other_bytes::<H256>(&tx.other, "ticketId").filter(|_| arbitrum));
+    }
+    if schema.has_column("refund_to") {
+        columns.refund_to.push(other_bytes::<Address>(&tx.other, "refundTo").filter(|_| arbitrum));
+    }
+    if schema.has_column("max_refund") {
+        let value = other_field::<U256>(&tx.other, "maxRefund").filter(|_| arbitrum);
+        columns.max_refund.push(value.map(|value| value.to_string()));
+    }
+    if schema.has_column("submission_fee_refund") {
+        let value = other_field::<U256>(&tx.other, "submissionFeeRefund").filter(|_| arbitrum);
+        columns.submission_fee_refund.push(value.map(|value| value.to_string()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ColumnEncoding;
+
+    const CELO_BLOCK: &str = include_str!("../../tests/fixtures/celo_block.json");
+    const POLYGON_BLOCK: &str = include_str!("../../tests/fixtures/polygon_block.json");
+    const ARBITRUM_BLOCK: &str = include_str!("../../tests/fixtures/arbitrum_block.json");
+
+    fn schema(datatype: Datatype, columns: &[&str]) -> Table {
+        let columns = Some(columns.iter().map(|column| column.to_string()).collect());
+        datatype.table_schema(&ColumnEncoding::Hex, &None, &None, &columns, None).unwrap()
+    }
+
+    /// replay a recorded eth_getBlockByNumber response through the blocks and transactions
+    /// transforms of a chain
+    async fn replay(
+        fixture: &str,
+        chain_id: u64,
+        block_columns: &[&str],
+        transaction_columns: &[&str],
+    ) -> (DataFrame, DataFrame) {
+        let block: Block<Transaction> = serde_json::from_str(fixture).unwrap();
+        let (sender, receiver) = mpsc::channel(1);
+        sender.send(Ok((block, None, None))).await.unwrap();
+        drop(sender);
+        let blocks_schema = schema(Datatype::Blocks, block_columns);
+        let transactions_schema = schema(Datatype::Transactions, transaction_columns);
+        let (blocks, transactions) =
+            blocks_to_dfs(receiver, &Some(&blocks_schema), &Some(&transactions_schema), chain_id)
+                .await
+                .unwrap();
+        (blocks.unwrap(), transactions.unwrap())
+    }
+
+    fn utf8_column(df: &DataFrame, name: &str) -> Vec<Option<String>> {
+        df.column(name).unwrap().utf8().unwrap().into_iter().map(|v| v.map(String::from)).collect()
+    }
+
+    fn u64_column(df: &DataFrame, name: &str) -> Vec<Option<u64>> {
+        df.column(name).unwrap().u64().unwrap().into_iter().collect()
+    }
+
+    fn bool_column(df: &DataFrame, name: &str) -> Vec<Option<bool>> {
+        df.column(name).unwrap().bool().unwrap().into_iter().collect()
+    }
+
+    fn some(value: &str) -> Option<String> {
+        Some(value.to_string())
+    }
+
+    #[tokio::test]
+    async fn celo_transaction_columns() {
+        let columns = ChainExtension::Celo.transaction_columns();
+        let (_, txs) = replay(CELO_BLOCK, 42220, &["number"], &columns).await;
+        assert_eq!(
+            utf8_column(&txs, "fee_currency"),
+            vec![some("0x765de816845861e75a25fca122bb6898b8b1282a"), None]
+        );
+        assert_eq!(
+            utf8_column(&txs, "gateway_fee_recipient"),
+            vec![some("0x14e32e6541ec1b69bdfe20ee00b4f180190285e6"), None]
+        );
+        assert_eq!(utf8_column(&txs, "gateway_fee"), vec![some("10000000000000000"), some("0")]);
+    }
+
+    #[tokio::test]
+    async fn polygon_transaction_columns() {
+        let columns = ChainExtension::Polygon.transaction_columns();
+        let (_, txs) = replay(POLYGON_BLOCK, 137, &["number"], &columns).await;
+        assert_eq!(bool_column(&txs, "is_state_sync"), vec![Some(false), Some(true)]);
+    }
+
+    #[tokio::test]
+    async fn arbitrum_block_columns() {
+        let columns = ChainExtension::Arbitrum.block_columns();
+        let (blocks, _) = replay(ARBITRUM_BLOCK, 42161, &columns, &["transaction_hash"]).await;
+        assert_eq!(u64_column(&blocks, "l1_block_number"), vec![Some(19531250)]);
+        assert_eq!(u64_column(&blocks, "send_count"), vec![Some(181713)]);
+        assert_eq!(
+            utf8_column(&blocks, "send_root"),
+            vec![some("0x9ebae3abbdc1af05525e10c5f9b2aa601b26deb0f7cea89908c609a1623cdf2a")]
+        );
+    }
+
+    #[tokio::test]
+    async fn arbitrum_transaction_columns() {
+        let columns = ChainExtension::Arbitrum.transaction_columns();
+        let (_, txs) = replay(ARBITRUM_BLOCK, 42161, &["number"], &columns).await;
+        assert_eq!(
+            utf8_column(&txs, "request_id"),
+            vec![some("0x11830d912d404abc06113a3fb9d158be5fb5c89b9010dc4370f0201e2d39e19a"), None]
+        );
+        assert_eq!(
+            utf8_column(&txs, "ticket_id"),
+            vec![None, some("0x0980fa702b128e3901a87c38ab0724664b2f951859ed055481c062b1bc3837ca")]
+        );
+        assert_eq!(
+            utf8_column(&txs, "refund_to"),
+            vec![None, some("0xad7d9a90be08ec875777cc2c9c0213a9ec5d7a97")]
+        );
+        assert_eq!(utf8_column(&txs, "max_refund"), vec![None, some("1100000000000000")]);
+        assert_eq!(utf8_column(&txs, "submission_fee_refund"), vec![None, some("30000000000000")]);
+    }
+
+    #[tokio::test]
+    async fn extension_columns_are_null_on_other_chains() {
+        let columns = ChainExtension::Arbitrum.block_columns();
+        let (blocks, _) = replay(ARBITRUM_BLOCK, 1, &columns, &["transaction_hash"]).await;
+        assert_eq!(u64_column(&blocks, "l1_block_number"), vec![None]);
+        assert_eq!(utf8_column(&blocks, "send_root"), vec![None]);
+
+        let columns = ChainExtension::Celo.transaction_columns();
+        let (_, txs) = replay(CELO_BLOCK, 1, &["number"], &columns).await;
+        assert_eq!(utf8_column(&txs, "fee_currency"), vec![None, None]);
+
+        let columns = ChainExtension::Polygon.transaction_columns();
+        let (_, txs) = replay(POLYGON_BLOCK, 1, &["number"], &columns).await;
+        assert_eq!(bool_column(&txs, "is_state_sync"), vec![None, None]);
     }
 }
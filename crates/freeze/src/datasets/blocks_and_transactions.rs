@@ -1,13 +1,16 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Mutex,
+};
 
 use ethers::prelude::*;
 use polars::prelude::*;
 use tokio::{sync::mpsc, task};
 
-use super::blocks;
+use super::{blocks, gas_percentiles};
 use crate::types::{
-    BlockChunk, BlocksAndTransactions, CollectError, Datatype, MultiDataset, RowFilter, Source,
-    Table,
+    conversions::checked_u64, BlockChunk, BlocksAndTransactions, CollectError, Datatype,
+    MultiDataset, RequestPriority, RowFilter, Source, Table,
 };
 
 #[async_trait::async_trait]
@@ -17,7 +20,13 @@ impl MultiDataset for BlocksAndTransactions {
     }
 
     fn datatypes(&self) -> HashSet<Datatype> {
-        [Datatype::Blocks, Datatype::Transactions].into_iter().collect()
+        [Datatype::Blocks, Datatype::Transactions, Datatype::GasPercentiles].into_iter().collect()
+    }
+
+    /// any two of blocks, transactions, and gas percentiles are collected from one fetch of
+    /// blocks with their transactions
+    fn collects_together(&self, requested: &HashSet<Datatype>) -> bool {
+        self.datatypes().intersection(requested).count() > 1
     }
 
     async fn collect_block_chunk(
@@ -27,70 +36,119 @@ impl MultiDataset for BlocksAndTransactions {
         schemas: HashMap<Datatype, Table>,
         _filter: HashMap<Datatype, RowFilter>,
     ) -> Result<HashMap<Datatype, DataFrame>, CollectError> {
-        let include_gas_used = match &schemas.get(&Datatype::Transactions) {
-            Some(table) => table.has_column("gas_used"),
-            _ => false,
+        let transactions_schema = schemas.get(&Datatype::Transactions);
+        let (include_receipts, include_traces, lookups) = match transactions_schema {
+            Some(table) => {
+                (needs_receipts(table), needs_traces(table), RecipientLookups::from_schema(table))
+            }
+            _ => (false, false, RecipientLookups::default()),
         };
-        let rx = fetch_blocks_and_transactions(chunk, source, include_gas_used).await;
-        let output = blocks::blocks_to_dfs(
-            rx,
-            &schemas.get(&Datatype::Blocks),
-            &schemas.get(&Datatype::Transactions),
-            source.chain_id,
-        )
-        .await;
-        match output {
-            Ok((Some(blocks_df), Some(txs_df))) => {
-                let mut output: HashMap<Datatype, DataFrame> = HashMap::new();
-                output.insert(Datatype::Blocks, blocks_df);
-                output.insert(Datatype::Transactions, txs_df);
-                Ok(output)
+        let rx =
+            fetch_blocks_and_transactions(chunk, source, include_receipts, include_traces, lookups)
+                .await;
+        let blocks_schema = schemas.get(&Datatype::Blocks);
+        let chain_id = source.chain_id;
+        let ((blocks_df, txs_df), percentiles_df) = match schemas.get(&Datatype::GasPercentiles) {
+            Some(schema) => {
+                let (rx, summarize) = gas_percentiles::tee_gas_percentiles(rx);
+                let dfs = blocks::blocks_to_dfs(rx, &blocks_schema, &transactions_schema, chain_id);
+                let (dfs, columns) = tokio::join!(dfs, summarize);
+                (dfs?, Some(columns?.create_df(schema, chain_id)?))
             }
-            Ok((_, _)) => Err(CollectError::BadSchemaError),
-            Err(e) => Err(e),
+            None => {
+                let dfs = blocks::blocks_to_dfs(rx, &blocks_schema, &transactions_schema, chain_id);
+                (dfs.await?, None)
+            }
+        };
+        let dfs = [
+            (Datatype::Blocks, blocks_df),
+            (Datatype::Transactions, txs_df),
+            (Datatype::GasPercentiles, percentiles_df),
+        ];
+        Ok(dfs.into_iter().filter_map(|(datatype, df)| df.map(|df| (datatype, df))).collect())
+    }
+}
+
+/// whether a transactions schema has columns taken from receipts
+pub(crate) fn needs_receipts(schema: &Table) -> bool {
+    let receipt_columns = ["gas_used", "status", "state_root", "gas_refund"];
+    receipt_columns.iter().any(|column| schema.has_column(column))
+}
+
+/// whether a transactions schema has columns taken from the top level call of traces
+pub(crate) fn needs_traces(schema: &Table) -> bool {
+    schema.has_column("gas_refund")
+}
+
+/// recipient lookups to perform for each transaction
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct RecipientLookups {
+    /// check whether recipient has code, for the to_kind column
+    pub(crate) is_contract: bool,
+    /// check whether recipient is verified on sourcify, for the is_verified column
+    pub(crate) is_verified: bool,
+}
+
+impl RecipientLookups {
+    pub(crate) fn from_schema(schema: &Table) -> RecipientLookups {
+        RecipientLookups {
+            is_contract: schema.has_column("to_kind"),
+            is_verified: schema.has_column("is_verified"),
         }
     }
+
+    fn any(&self) -> bool {
+        self.is_contract || self.is_verified
+    }
 }
 
 pub(crate) async fn fetch_blocks_and_transactions(
     block_chunk: &BlockChunk,
     source: &Source,
-    include_gas_used: bool,
+    include_receipts: bool,
+    include_traces: bool,
+    lookups: RecipientLookups,
 ) -> mpsc::Receiver<blocks::BlockTxGasTuple<Transaction>> {
     let (tx, rx) = mpsc::channel(block_chunk.numbers().len());
     let source = Arc::new(source.clone());
+    let code_cache: Arc<Mutex<HashMap<H160, bool>>> = Arc::new(Mutex::new(HashMap::new()));
 
     for number in block_chunk.numbers() {
         let tx = tx.clone();
         let provider = source.provider.clone();
-        let semaphore = source.semaphore.clone();
-        let rate_limiter = source.rate_limiter.as_ref().map(Arc::clone);
         let source_arc = source.clone();
+        let code_cache = code_cache.clone();
         task::spawn(async move {
-            let permit = match semaphore {
-                Some(semaphore) => Some(Arc::clone(&semaphore).acquire_owned().await),
-                _ => None,
-            };
-            if let Some(limiter) = rate_limiter {
-                Arc::clone(&limiter).until_ready().await;
-            };
+            let permit = source_arc.permit_request(RequestPriority::Bulk).await;
             let block_result = provider.get_block_with_txs(number).await;
             drop(permit);
 
-            // get gas usage
+            // get receipts and recipient lookups
             let result = match block_result {
                 Ok(Some(block)) => {
-                    if include_gas_used {
-                        match get_txs_gas_used(&block, source_arc.clone()).await {
-                            Ok(gas_used) => Ok((block, Some(gas_used))),
-                            Err(e) => Err(e),
+                    let receipts = match include_receipts {
+                        true => get_txs_receipts(&block, source_arc.clone()).await.map(Some),
+                        false => Ok(None),
+                    };
+                    let receipts = match (receipts, include_traces) {
+                        (Ok(Some(receipts)), true) => {
+                            with_execution_gas(receipts, &block, source_arc.clone()).await.map(Some)
                         }
-                    } else {
-                        Ok((block, None))
+                        (receipts, _) => receipts,
+                    };
+                    let recipients = match lookups.any() {
+                        true => get_txs_recipients(&block, source_arc.clone(), code_cache, lookups)
+                            .await
+                            .map(Some),
+                        false => Ok(None),
+                    };
+                    match (receipts, recipients) {
+                        (Ok(receipts), Ok(recipients)) => Ok((block, receipts, recipients)),
+                        (Err(e), _) | (_, Err(e)) => Err(e),
                     }
                 }
                 Ok(None) => Err(CollectError::CollectError("no block found".into())),
-                Err(e) => Err(CollectError::ProviderError(e)),
+                Err(e) => Err(CollectError::rpc("eth_getBlockByNumber", e)),
             };
 
             // send to channel
@@ -106,43 +164,156 @@ pub(crate) async fn fetch_blocks_and_transactions(
     rx
 }
 
-async fn get_txs_gas_used(
+async fn get_txs_receipts(
     block: &Block<Transaction>,
     source: Arc<Source>,
-) -> Result<Vec<u32>, CollectError> {
+) -> Result<Vec<blocks::ReceiptFields>, CollectError> {
     let source = Arc::new(source);
     let mut tasks = Vec::new();
     for tx in &block.transactions {
         let provider = source.provider.clone();
-        let semaphore = source.semaphore.clone();
-        let rate_limiter = source.rate_limiter.as_ref().map(Arc::clone);
+        let source = source.clone();
         let tx_clone = tx.hash;
         let task = task::spawn(async move {
-            let _permit = match semaphore {
-                Some(semaphore) => Some(Arc::clone(&semaphore).acquire_owned().await),
-                _ => None,
-            };
-            if let Some(limiter) = rate_limiter {
-                Arc::clone(&limiter).until_ready().await;
-            };
+            let _permit = source.permit_request(RequestPriority::Bulk).await;
             match provider.get_transaction_receipt(tx_clone).await {
-                Ok(Some(receipt)) => Ok(receipt.gas_used),
+                Ok(Some(receipt)) => Ok(receipt),
                 Ok(None) => {
                     Err(CollectError::CollectError("could not find tx receipt".to_string()))
                 }
-                Err(e) => Err(CollectError::ProviderError(e)),
+                Err(e) => Err(CollectError::rpc("eth_getTransactionReceipt", e)),
+            }
+        });
+        tasks.push(task);
+    }
+
+    let mut receipts = Vec::new();
+    for task in tasks {
+        let receipt = match task.await {
+            Ok(Ok(receipt)) => receipt,
+            Ok(Err(e)) => return Err(e),
+            Err(e) => return Err(CollectError::TaskFailed(e)),
+        };
+        let gas_used = receipt
+            .gas_used
+            .ok_or_else(|| CollectError::CollectError("gas_used not available from node".into()))?;
+        receipts.push(blocks::ReceiptFields {
+            gas_used: checked_u64(gas_used, "gas_used")?,
+            status: receipt.status.map(|status| status.as_u64()),
+            state_root: receipt.root.map(|root| root.as_bytes().to_vec()),
+            execution_gas: None,
+            execution_failed: false,
+        });
+    }
+
+    Ok(receipts)
+}
+
+/// add the gas used by the top level call of each transaction, taken from the block's traces
+///
+/// transactions without a top level trace, e.g. system transactions of some chains, are left as
+/// None
+async fn with_execution_gas(
+    mut receipts: Vec<blocks::ReceiptFields>,
+    block: &Block<Transaction>,
+    source: Arc<Source>,
+) -> Result<Vec<blocks::ReceiptFields>, CollectError> {
+    let number = block
+        .number
+        .ok_or_else(|| CollectError::CollectError("block number not available".to_string()))?;
+    let _permit = source.permit_request(RequestPriority::Bulk).await;
+    let traces = source
+        .provider
+        .trace_block(BlockNumber::Number(number))
+        .await
+        .map_err(|e| CollectError::rpc("trace_block", e))?;
+    for trace in traces.iter().filter(|trace| trace.trace_address.is_empty()) {
+        let receipt = match trace.transaction_position.and_then(|i| receipts.get_mut(i)) {
+            Some(receipt) => receipt,
+            None => continue,
+        };
+        receipt.execution_failed = trace.error.is_some();
+        receipt.execution_gas = match &trace.result {
+            Some(Res::Call(result)) => Some(checked_u64(result.gas_used, "result_gas_used")?),
+            Some(Res::Create(result)) => Some(checked_u64(result.gas_used, "result_gas_used")?),
+            _ => None,
+        };
+    }
+    Ok(receipts)
+}
+
+/// look up each transaction's recipient, leaving contract creations empty
+async fn get_txs_recipients(
+    block: &Block<Transaction>,
+    source: Arc<Source>,
+    code_cache: Arc<Mutex<HashMap<H160, bool>>>,
+    lookups: RecipientLookups,
+) -> Result<Vec<blocks::Recipient>, CollectError> {
+    let to_addresses: Vec<H160> = block.transactions.iter().filter_map(|tx| tx.to).collect();
+    let is_contract = match lookups.is_contract {
+        true => Some(get_is_contract(&to_addresses, block, source.clone(), code_cache).await?),
+        false => None,
+    };
+    let is_verified = match lookups.is_verified {
+        true => Some(source.sourcify.is_verified(&to_addresses, source.chain_id).await?),
+        false => None,
+    };
+    let lookup = |results: &Option<HashMap<H160, bool>>, to: H160| {
+        results.as_ref().and_then(|results| results.get(&to).copied())
+    };
+    Ok(block
+        .transactions
+        .iter()
+        .map(|tx| match tx.to {
+            Some(to) => blocks::Recipient {
+                is_contract: lookup(&is_contract, to),
+                is_verified: lookup(&is_verified, to),
+            },
+            None => blocks::Recipient::default(),
+        })
+        .collect())
+}
+
+/// check whether each address has code
+///
+/// results are cached per chunk, so each address is checked once, at whichever block of the
+/// chunk first requests it. addresses that gain code within a chunk may be labeled either way
+async fn get_is_contract(
+    addresses: &[H160],
+    block: &Block<Transaction>,
+    source: Arc<Source>,
+    code_cache: Arc<Mutex<HashMap<H160, bool>>>,
+) -> Result<HashMap<H160, bool>, CollectError> {
+    let block_number = block.number.map(BlockId::from);
+    let unchecked: HashSet<H160> = {
+        let cache = code_cache.lock().expect("code cache lock");
+        addresses.iter().filter(|address| !cache.contains_key(address)).copied().collect()
+    };
+
+    let mut tasks = Vec::new();
+    for address in unchecked.into_iter() {
+        let provider = source.provider.clone();
+        let source = source.clone();
+        let task = task::spawn(async move {
+            let _permit = source.permit_request(RequestPriority::Bulk).await;
+            match provider.get_code(address, block_number).await {
+                Ok(code) => Ok((address, !code.is_empty())),
+                Err(e) => Err(CollectError::rpc("eth_getCode", e)),
             }
         });
         tasks.push(task);
     }
 
-    let mut gas_used: Vec<u32> = Vec::new();
+    let mut checked = Vec::new();
     for task in tasks {
         match task.await {
-            Ok(Ok(Some(value))) => gas_used.push(value.as_u32()),
-            _ => return Err(CollectError::CollectError("gas_used not available from node".into())),
+            Ok(Ok(result)) => checked.push(result),
+            Ok(Err(e)) => return Err(e),
+            Err(_e) => return Err(CollectError::CollectError("code check task failed".into())),
         }
     }
 
-    Ok(gas_used)
+    let mut cache = code_cache.lock().expect("code cache lock");
+    cache.extend(checked);
+    Ok(addresses.iter().filter_map(|address| cache.get(address).map(|c| (*address, *c))).collect())
 }
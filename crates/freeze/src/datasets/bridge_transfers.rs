@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+
+use ethers::prelude::*;
+use polars::prelude::*;
+use tokio::sync::mpsc;
+
+use super::logs;
+use crate::{
+    dataframes::SortableDataFrame,
+    types::{
+        conversions::{checked_u64, NarrowU32, ToVecHex},
+        BlockChunk, BridgeTransfers, CollectError, ColumnType, Dataset, Datatype, RowFilter,
+        Source, Table, TransactionChunk,
+    },
+    with_series, with_series_binary, with_series_u32,
+};
+
+/// topic0 of ETHDepositInitiated(address,address,uint256,bytes)
+const ETH_DEPOSIT_TOPIC: &str =
+    "0x35d79ab81f2b2017e19afb5c5571778877782d7a8786f5907f93b0f4702f4f23";
+
+/// topic0 of ETHWithdrawalFinalized(address,address,uint256,bytes)
+const ETH_WITHDRAWAL_TOPIC: &str =
+    "0x2ac69ee804d9a7a0984249f508dfab7cb2534b465b6ce1580f99a38ba9c5e631";
+
+/// topic0 of ERC20DepositInitiated(address,address,address,address,uint256,bytes)
+const ERC20_DEPOSIT_TOPIC: &str =
+    "0x718594027abd4eaed59f95162563e0cc6d0e8d5b86b1c7be8b1b0ac3343d0396";
+
+/// topic0 of ERC20WithdrawalFinalized(address,address,address,address,uint256,bytes)
+const ERC20_WITHDRAWAL_TOPIC: &str =
+    "0x3ceee06c1e37648fcbb6ed52e17b3e1f275a1f8c7b22a84b2b84732431e046b3";
+
+/// canonical L1 standard bridges of op stack rollups on ethereum mainnet
+const MAINNET_BRIDGES: [(&str, &str); 2] = [
+    ("optimism", "0x99C9fc46f92E8a1c0deC1b1747d010903E884bE1"),
+    ("base", "0x3154Cf16ccdb4C6d922629664174b904d80F2C35"),
+];
+
+#[async_trait::async_trait]
+impl Dataset for BridgeTransfers {
+    fn datatype(&self) -> Datatype {
+        Datatype::BridgeTransfers
+    }
+
+    fn name(&self) -> &'static str {
+        "bridge_transfers"
+    }
+
+    fn column_types(&self) -> HashMap<&'static str, ColumnType> {
+        HashMap::from_iter(vec![
+            ("block_number", ColumnType::UInt32),
+            ("transaction_index", ColumnType::UInt32),
+            ("log_index", ColumnType::UInt32),
+            ("transaction_hash", ColumnType::Binary),
+            ("bridge", ColumnType::String),
+            ("bridge_address", ColumnType::Binary),
+            ("direction", ColumnType::String),
+            ("l1_token", ColumnType::Binary),
+            ("l2_token", ColumnType::Binary),
+            ("from_address", ColumnType::Binary),
+            ("to_address", ColumnType::Binary),
+            ("amount", ColumnType::Decimal128),
+            ("chain_id", ColumnType::UInt64),
+        ])
+    }
+
+    fn default_columns(&self) -> Vec<&'static str> {
+        vec![
+            "block_number",
+            "transaction_index",
+            "log_index",
+            "transaction_hash",
+            "bridge",
+            "direction",
+            "l1_token",
+            "l2_token",
+            "from_address",
+            "to_address",
+            "amount",
+        ]
+    }
+
+    fn default_sort(&self) -> Vec<String> {
+        vec!["block_number".to_string(), "log_index".to_string()]
+    }
+
+    fn column_descriptions(&self) -> HashMap<&'static str, &'static str> {
+        HashMap::from_iter(vec![
+            ("direction", "deposit or withdrawal"),
+            ("amount", "amount transferred, in wei or token base units"),
+        ])
+    }
+
+    async fn collect_block_chunk(
+        &self,
+        chunk: &BlockChunk,
+        source: &Source,
+        schema: &Table,
+        filter: Option<&RowFilter>,
+    ) -> Result<DataFrame, CollectError> {
+        let filter = bridge_filter(filter, source.chain_id)?;
+        let rx = logs::fetch_block_logs(chunk, source, Some(&filter)).await;
+        bridge_transfers_to_df(rx, &filter, schema, source.chain_id).await
+    }
+
+    async fn collect_transaction_chunk(
+        &self,
+        chunk: &TransactionChunk,
+        source: &Source,
+        schema: &Table,
+        filter: Option<&RowFilter>,
+    ) -> Result<DataFrame, CollectError> {
+        let filter = bridge_filter(filter, source.chain_id)?;
+        let rx = logs::fetch_transaction_logs(chunk, source, Some(&filter)).await;
+        bridge_transfers_to_df(rx, &filter, schema, source.chain_id).await
+    }
+}
+
+/// limit logs to the known bridges of mainnet unless a bridge contract is specified
+fn bridge_filter(filter: Option<&RowFilter>, chain_id: u64) -> Result<RowFilter, CollectError> {
+    let topics =
+        [ETH_DEPOSIT_TOPIC, ETH_WITHDRAWAL_TOPIC, ERC20_DEPOSIT_TOPIC, ERC20_WITHDRAWAL_TOPIC];
+    let mut filter = logs::event_filter(filter, &topics);
+    if filter.address.is_none() {
+        if chain_id != 1 {
+            return Err(CollectError::CollectError(format!(
+                "no known bridge contracts for chain {}, specify one with --contract",
+                chain_id
+            )))
+        }
+        let bridges = MAINNET_BRIDGES
+            .iter()
+            .map(|(_, address)| address.parse().expect("valid bridge address"))
+            .collect();
+        filter.address = Some(ValueOrArray::Array(bridges));
+    }
+    Ok(filter)
+}
+
+/// name of a known bridge contract
+fn bridge_name(address: &H160) -> Option<&'static str> {
+    MAINNET_BRIDGES
+        .iter()
+        .find(|(_, bridge)| bridge.parse::<H160>().ok().as_ref() == Some(address))
+        .map(|(name, _)| *name)
+}
+
+struct BridgeTransfer {
+    direction: &'static str,
+    l1_token: H160,
+    l2_token: H160,
+    from_address: H160,
+    to_address: H160,
+    amount: U256,
+}
+
+/// decode a bridge log, ETH transfers have zero token addresses
+fn decode_bridge_transfer(log: &Log, topics: &[H256; 4]) -> Option<BridgeTransfer> {
+    let address = |word: &[u8]| H160::from_slice(&word[12..32]);
+    let is_eth = log.topics[0] == topics[0] || log.topics[0] == topics[1];
+    let direction = match log.topics[0] {
+        topic if topic == topics[0] || topic == topics[2] => "deposit",
+        topic if topic == topics[1] || topic == topics[3] => "withdrawal",
+        _ => return None,
+    };
+    match is_eth {
+        // topics: from, to; data: amount, extraData
+        true if log.topics.len() == 3 && log.data.len() >= 32 => Some(BridgeTransfer {
+            direction,
+            l1_token: H160::zero(),
+            l2_token: H160::zero(),
+            from_address: address(log.topics[1].as_bytes()),
+            to_address: address(log.topics[2].as_bytes()),
+            amount: U256::from_big_endian(&log.data[..32]),
+        }),
+        // topics: l1Token, l2Token, from; data: to, amount, extraData
+        false if log.topics.len() == 4 && log.data.len() >= 64 => Some(BridgeTransfer {
+            direction,
+            l1_token: address(log.topics[1].as_bytes()),
+            l2_token: address(log.topics[2].as_bytes()),
+            from_address: address(log.topics[3].as_bytes()),
+            to_address: address(&log.data[..32]),
+            amount: U256::from_big_endian(&log.data[32..64]),
+        }),
+        _ => None,
+    }
+}
+
+async fn bridge_transfers_to_df(
+    mut logs: mpsc::Receiver<Result<Vec<Log>, CollectError>>,
+    filter: &RowFilter,
+    schema: &Table,
+    chain_id: u64,
+) -> Result<DataFrame, CollectError> {
+    let topics: [H256; 4] = [
+        ETH_DEPOSIT_TOPIC.parse().expect("valid topic"),
+        ETH_WITHDRAWAL_TOPIC.parse().expect("valid topic"),
+        ERC20_DEPOSIT_TOPIC.parse().expect("valid topic"),
+        ERC20_WITHDRAWAL_TOPIC.parse().expect("valid topic"),
+    ];
+
+    let mut block_number: Vec<u64> = Vec::new();
+    let mut transaction_index: Vec<u64> = Vec::new();
+    let mut log_index: Vec<u64> = Vec::new();
+    let mut transaction_hash: Vec<Vec<u8>> = Vec::new();
+    let mut bridge: Vec<&'static str> = Vec::new();
+    let mut bridge_address: Vec<Vec<u8>> = Vec::new();
+    let mut direction: Vec<&'static str> = Vec::new();
+    let mut l1_token: Vec<Vec<u8>> = Vec::new();
+    let mut l2_token: Vec<Vec<u8>> = Vec::new();
+    let mut from_address: Vec<Vec<u8>> = Vec::new();
+    let mut to_address: Vec<Vec<u8>> = Vec::new();
+    let mut amount: Vec<String> = Vec::new();
+    while let Some(message) = logs.recv().await {
+        for log in message?.into_iter() {
+            if log.removed == Some(true) || filter.excluded_addresses.contains(&log.address) {
+                continue
+            }
+            let transfer = match decode_bridge_transfer(&log, &topics) {
+                Some(transfer) => transfer,
+                None => continue,
+            };
+            block_number.push(log.block_number.map(|n| n.as_u64()).unwrap_or_default());
+            transaction_index.push(log.transaction_index.map(|i| i.as_u64()).unwrap_or_default());
+            log_index.push(checked_u64(log.log_index.unwrap_or_default(), "log_index")?);
+            transaction_hash.push(log.transaction_hash.unwrap_or_default().as_bytes().to_vec());
+            bridge.push(bridge_name(&log.address).unwrap_or("unknown"));
+            bridge_address.push(log.address.as_bytes().to_vec());
+            direction.push(transfer.direction);
+            l1_token.push(transfer.l1_token.as_bytes().to_vec());
+            l2_token.push(transfer.l2_token.as_bytes().to_vec());
+            from_address.push(transfer.from_address.as_bytes().to_vec());
+            to_address.push(transfer.to_address.as_bytes().to_vec());
+            amount.push(transfer.amount.to_string());
+        }
+    }
+
+    let n_rows = block_number.len();
+    let mut cols = Vec::new();
+    with_series_u32!(cols, "block_number", block_number, schema);
+    with_series_u32!(cols, "transaction_index", transaction_index, schema);
+    with_series_u32!(cols, "log_index", log_index, schema);
+    with_series_binary!(cols, "transaction_hash", transaction_hash, schema);
+    with_series!(cols, "bridge", bridge, schema);
+    with_series_binary!(cols, "bridge_address", bridge_address, schema);
+    with_series!(cols, "direction", direction, schema);
+    with_series_binary!(cols, "l1_token", l1_token, schema);
+    with_series_binary!(cols, "l2_token", l2_token, schema);
+    with_series_binary!(cols, "from_address", from_address, schema);
+    with_series_binary!(cols, "to_address", to_address, schema);
+    with_series!(cols, "amount", amount, schema);
+    if schema.has_column("chain_id") {
+        cols.push(Series::new("chain_id", vec![chain_id; n_rows]));
+    }
+
+    DataFrame::new(cols).map_err(CollectError::PolarsError).sort_by_schema(schema)
+}
@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+
+use ethers::{
+    abi::{self, ParamType, Token},
+    prelude::*,
+};
+use polars::prelude::*;
+use tokio::sync::mpsc;
+
+use super::logs;
+use crate::{
+    dataframes::SortableDataFrame,
+    types::{
+        conversions::{checked_u64, NarrowU32, ToVecHex},
+        BlockChunk, CollectError, ColumnType, Dataset, Datatype, Erc1155Transfers, RowFilter,
+        Source, Table, TransactionChunk,
+    },
+    with_series, with_series_binary, with_series_u32,
+};
+
+/// topic0 of TransferSingle(address,address,address,uint256,uint256)
+const TRANSFER_SINGLE_TOPIC: &str =
+    "0xc3d58168c5ae7397731d063d5bbf3d657854427343f4c083240f7aacaa2d0f62";
+
+/// topic0 of TransferBatch(address,address,address,uint256[],uint256[])
+const TRANSFER_BATCH_TOPIC: &str =
+    "0x4a39dc06d4c0dbc64b70af90fd698a233a518aa5d07e595d983b8c0526c8f7fb";
+
+#[async_trait::async_trait]
+impl Dataset for Erc1155Transfers {
+    fn datatype(&self) -> Datatype {
+        Datatype::Erc1155Transfers
+    }
+
+    fn name(&self) -> &'static str {
+        "erc1155_transfers"
+    }
+
+    fn column_types(&self) -> HashMap<&'static str, ColumnType> {
+        HashMap::from_iter(vec![
+            ("block_number", ColumnType::UInt32),
+            ("transaction_index", ColumnType::UInt32),
+            ("log_index", ColumnType::UInt32),
+            ("batch_index", ColumnType::UInt32),
+            ("transaction_hash", ColumnType::Binary),
+            ("erc1155", ColumnType::Binary),
+            ("operator_address", ColumnType::Binary),
+            ("from_address", ColumnType::Binary),
+            ("to_address", ColumnType::Binary),
+            ("token_id", ColumnType::Decimal128),
+            ("value", ColumnType::Decimal128),
+            ("is_batch", ColumnType::Boolean),
+            ("chain_id", ColumnType::UInt64),
+        ])
+    }
+
+    fn default_columns(&self) -> Vec<&'static str> {
+        vec![
+            "block_number",
+            "transaction_index",
+            "log_index",
+            "batch_index",
+            "transaction_hash",
+            "erc1155",
+            "operator_address",
+            "from_address",
+            "to_address",
+            "token_id",
+            "value",
+        ]
+    }
+
+    fn default_sort(&self) -> Vec<String> {
+        vec!["block_number".to_string(), "log_index".to_string(), "batch_index".to_string()]
+    }
+
+    fn column_descriptions(&self) -> HashMap<&'static str, &'static str> {
+        HashMap::from_iter(vec![("value", "number of tokens transferred")])
+    }
+
+    async fn collect_block_chunk(
+        &self,
+        chunk: &BlockChunk,
+        source: &Source,
+        schema: &Table,
+        filter: Option<&RowFilter>,
+    ) -> Result<DataFrame, CollectError> {
+        let filter = logs::event_filter(filter, &[TRANSFER_SINGLE_TOPIC, TRANSFER_BATCH_TOPIC]);
+        let rx = logs::fetch_block_logs(chunk, source, Some(&filter)).await;
+        transfers_to_df(rx, &filter, schema, source.chain_id).await
+    }
+
+    async fn collect_transaction_chunk(
+        &self,
+        chunk: &TransactionChunk,
+        source: &Source,
+        schema: &Table,
+        filter: Option<&RowFilter>,
+    ) -> Result<DataFrame, CollectError> {
+        let filter = logs::event_filter(filter, &[TRANSFER_SINGLE_TOPIC, TRANSFER_BATCH_TOPIC]);
+        let rx = logs::fetch_transaction_logs(chunk, source, Some(&filter)).await;
+        transfers_to_df(rx, &filter, schema, source.chain_id).await
+    }
+}
+
+/// decode the (id, value) pairs of a TransferSingle or TransferBatch log
+///
+/// returns None for logs whose data does not decode, e.g. batches with mismatched array lengths
+fn decode_transfers(log: &Log, is_batch: bool) -> Option<Vec<(U256, U256)>> {
+    if !is_batch {
+        return match log.data.len() {
+            64 => Some(vec![(
+                U256::from_big_endian(&log.data[..32]),
+                U256::from_big_endian(&log.data[32..]),
+            )]),
+            _ => None,
+        }
+    }
+    let array = ParamType::Array(Box::new(ParamType::Uint(256)));
+    let tokens = abi::decode(&[array.clone(), array], &log.data).ok()?;
+    match (&tokens[0], &tokens[1]) {
+        (Token::Array(ids), Token::Array(values)) if ids.len() == values.len() => ids
+            .iter()
+            .zip(values.iter())
+            .map(|(id, value)| Some((id.clone().into_uint()?, value.clone().into_uint()?)))
+            .collect(),
+        _ => None,
+    }
+}
+
+/// decode erc1155 transfer logs, exploding each TransferBatch into one row per token id
+async fn transfers_to_df(
+    mut logs: mpsc::Receiver<Result<Vec<Log>, CollectError>>,
+    filter: &RowFilter,
+    schema: &Table,
+    chain_id: u64,
+) -> Result<DataFrame, CollectError> {
+    let single_topic: H256 = TRANSFER_SINGLE_TOPIC.parse().expect("valid transfer single topic");
+    let batch_topic: H256 = TRANSFER_BATCH_TOPIC.parse().expect("valid transfer batch topic");
+
+    let mut block_number: Vec<u64> = Vec::new();
+    let mut transaction_index: Vec<u64> = Vec::new();
+    let mut log_index: Vec<u64> = Vec::new();
+    let mut batch_index: Vec<u32> = Vec::new();
+    let mut transaction_hash: Vec<Vec<u8>> = Vec::new();
+    let mut erc1155: Vec<Vec<u8>> = Vec::new();
+    let mut operator_address: Vec<Vec<u8>> = Vec::new();
+    let mut from_address: Vec<Vec<u8>> = Vec::new();
+    let mut to_address: Vec<Vec<u8>> = Vec::new();
+    let mut token_id: Vec<String> = Vec::new();
+    let mut value: Vec<String> = Vec::new();
+    let mut is_batch: Vec<bool> = Vec::new();
+    while let Some(message) = logs.recv().await {
+        for log in message?.into_iter() {
+            if log.removed == Some(true) ||
+                filter.excluded_addresses.contains(&log.address) ||
+                log.topics.len() != 4
+            {
+                continue
+            }
+            let batch = match log.topics[0] {
+                topic if topic == single_topic => false,
+                topic if topic == batch_topic => true,
+                _ => continue,
+            };
+            let transfers = match decode_transfers(&log, batch) {
+                Some(transfers) => transfers,
+                None => continue,
+            };
+            for (i, (id, amount)) in transfers.into_iter().enumerate() {
+                block_number.push(log.block_number.map(|n| n.as_u64()).unwrap_or_default());
+                transaction_index
+                    .push(log.transaction_index.map(|i| i.as_u64()).unwrap_or_default());
+                log_index.push(checked_u64(log.log_index.unwrap_or_default(), "log_index")?);
+                batch_index.push(i as u32);
+                transaction_hash.push(log.transaction_hash.unwrap_or_default().as_bytes().to_vec());
+                erc1155.push(log.address.as_bytes().to_vec());
+                operator_address.push(log.topics[1].as_bytes()[12..].to_vec());
+                from_address.push(log.topics[2].as_bytes()[12..].to_vec());
+                to_address.push(log.topics[3].as_bytes()[12..].to_vec());
+                token_id.push(id.to_string());
+                value.push(amount.to_string());
+                is_batch.push(batch);
+            }
+        }
+    }
+
+    let n_rows = block_number.len();
+    let mut cols = Vec::new();
+    with_series_u32!(cols, "block_number", block_number, schema);
+    with_series_u32!(cols, "transaction_index", transaction_index, schema);
+    with_series_u32!(cols, "log_index", log_index, schema);
+    with_series!(cols, "batch_index", batch_index, schema);
+    with_series_binary!(cols, "transaction_hash", transaction_hash, schema);
+    with_series_binary!(cols, "erc1155", erc1155, schema);
+    with_series_binary!(cols, "operator_address", operator_address, schema);
+    with_series_binary!(cols, "from_address", from_address, schema);
+    with_series_binary!(cols, "to_address", to_address, schema);
+    with_series!(cols, "token_id", token_id, schema);
+    with_series!(cols, "value", value, schema);
+    with_series!(cols, "is_batch", is_batch, schema);
+    if schema.has_column("chain_id") {
+        cols.push(Series::new("chain_id", vec![chain_id; n_rows]));
+    }
+
+    DataFrame::new(cols).map_err(CollectError::PolarsError).sort_by_schema(schema)
+}
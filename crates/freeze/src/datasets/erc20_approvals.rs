@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+
+use ethers::prelude::*;
+use polars::prelude::*;
+use tokio::sync::mpsc;
+
+use super::logs;
+use crate::{
+    dataframes::SortableDataFrame,
+    types::{
+        conversions::{checked_u64, NarrowU32, ToVecHex},
+        tokens::format_decimal,
+        BlockChunk, CollectError, ColumnType, Dataset, Datatype, Erc20Approvals, RowFilter, Source,
+        Table, TransactionChunk,
+    },
+    with_series, with_series_binary, with_series_u32,
+};
+
+/// topic0 of Approval(address,address,uint256)
+const APPROVAL_TOPIC: &str = "0x8c5be1e5ebec7d5bd14f71427d1e84f3dd0314c0f7b2291e5b200ac8c7c3b925";
+
+#[async_trait::async_trait]
+impl Dataset for Erc20Approvals {
+    fn datatype(&self) -> Datatype {
+        Datatype::Erc20Approvals
+    }
+
+    fn name(&self) -> &'static str {
+        "erc20_approvals"
+    }
+
+    fn column_types(&self) -> HashMap<&'static str, ColumnType> {
+        HashMap::from_iter(vec![
+            ("block_number", ColumnType::UInt32),
+            ("transaction_index", ColumnType::UInt32),
+            ("log_index", ColumnType::UInt32),
+            ("transaction_hash", ColumnType::Binary),
+            ("erc20", ColumnType::Binary),
+            ("owner_address", ColumnType::Binary),
+            ("spender_address", ColumnType::Binary),
+            ("value", ColumnType::Decimal128),
+            ("is_unlimited", ColumnType::Boolean),
+            ("decimals", ColumnType::UInt32),
+            ("amount", ColumnType::String),
+            ("chain_id", ColumnType::UInt64),
+        ])
+    }
+
+    fn default_columns(&self) -> Vec<&'static str> {
+        vec![
+            "block_number",
+            "transaction_index",
+            "log_index",
+            "transaction_hash",
+            "erc20",
+            "owner_address",
+            "spender_address",
+            "value",
+        ]
+    }
+
+    fn default_sort(&self) -> Vec<String> {
+        vec!["block_number".to_string(), "log_index".to_string()]
+    }
+
+    fn nullable_columns(&self) -> Vec<&'static str> {
+        vec!["decimals", "amount"]
+    }
+
+    fn column_descriptions(&self) -> HashMap<&'static str, &'static str> {
+        HashMap::from_iter(vec![("value", "allowance, in token base units")])
+    }
+
+    async fn collect_block_chunk(
+        &self,
+        chunk: &BlockChunk,
+        source: &Source,
+        schema: &Table,
+        filter: Option<&RowFilter>,
+    ) -> Result<DataFrame, CollectError> {
+        let filter = logs::event_filter(filter, &[APPROVAL_TOPIC]);
+        let rx = logs::fetch_block_logs(chunk, source, Some(&filter)).await;
+        approvals_to_df(rx, &filter, source, schema).await
+    }
+
+    async fn collect_transaction_chunk(
+        &self,
+        chunk: &TransactionChunk,
+        source: &Source,
+        schema: &Table,
+        filter: Option<&RowFilter>,
+    ) -> Result<DataFrame, CollectError> {
+        let filter = logs::event_filter(filter, &[APPROVAL_TOPIC]);
+        let rx = logs::fetch_transaction_logs(chunk, source, Some(&filter)).await;
+        approvals_to_df(rx, &filter, source, schema).await
+    }
+}
+
+/// decode erc20 Approval logs, skipping erc721 Approvals which have a fourth topic
+async fn approvals_to_df(
+    mut logs: mpsc::Receiver<Result<Vec<Log>, CollectError>>,
+    filter: &RowFilter,
+    source: &Source,
+    schema: &Table,
+) -> Result<DataFrame, CollectError> {
+    let topic: H256 = APPROVAL_TOPIC.parse().expect("valid approval topic");
+    let mut approvals: Vec<Log> = Vec::new();
+    while let Some(message) = logs.recv().await {
+        approvals.extend(message?.into_iter().filter(|log| {
+            log.removed != Some(true) &&
+                !filter.excluded_addresses.contains(&log.address) &&
+                log.topics.len() == 3 &&
+                log.topics[0] == topic &&
+                log.data.len() == 32
+        }));
+    }
+
+    // decimals require an eth_call per token, so they are only fetched when requested
+    let decimals = match schema.has_column("decimals") || schema.has_column("amount") {
+        true => {
+            let tokens: Vec<H160> = approvals.iter().map(|log| log.address).collect();
+            source.token_decimals.get(&tokens, source).await?
+        }
+        false => HashMap::new(),
+    };
+
+    let n_rows = approvals.len();
+    let mut block_number: Vec<u64> = Vec::with_capacity(n_rows);
+    let mut transaction_index: Vec<u64> = Vec::with_capacity(n_rows);
+    let mut log_index: Vec<u64> = Vec::with_capacity(n_rows);
+    let mut transaction_hash: Vec<Vec<u8>> = Vec::with_capacity(n_rows);
+    let mut erc20: Vec<Vec<u8>> = Vec::with_capacity(n_rows);
+    let mut owner_address: Vec<Vec<u8>> = Vec::with_capacity(n_rows);
+    let mut spender_address: Vec<Vec<u8>> = Vec::with_capacity(n_rows);
+    let mut value: Vec<String> = Vec::with_capacity(n_rows);
+    let mut is_unlimited: Vec<bool> = Vec::with_capacity(n_rows);
+    let mut token_decimals: Vec<Option<u32>> = Vec::with_capacity(n_rows);
+    let mut amount: Vec<Option<String>> = Vec::with_capacity(n_rows);
+    for log in approvals.iter() {
+        let raw_value = U256::from_big_endian(&log.data);
+        let log_decimals = decimals.get(&log.address).copied().flatten();
+        block_number.push(log.block_number.map(|n| n.as_u64()).unwrap_or_default());
+        transaction_index.push(log.transaction_index.map(|i| i.as_u64()).unwrap_or_default());
+        log_index.push(checked_u64(log.log_index.unwrap_or_default(), "log_index")?);
+        transaction_hash.push(log.transaction_hash.unwrap_or_default().as_bytes().to_vec());
+        erc20.push(log.address.as_bytes().to_vec());
+        owner_address.push(log.topics[1].as_bytes()[12..].to_vec());
+        spender_address.push(log.topics[2].as_bytes()[12..].to_vec());
+        value.push(raw_value.to_string());
+        is_unlimited.push(raw_value == U256::MAX);
+        token_decimals.push(log_decimals.map(|d| d as u32));
+        amount.push(log_decimals.map(|d| format_decimal(raw_value, d)));
+    }
+
+    let mut cols = Vec::new();
+    with_series_u32!(cols, "block_number", block_number, schema);
+    with_series_u32!(cols, "transaction_index", transaction_index, schema);
+    with_series_u32!(cols, "log_index", log_index, schema);
+    with_series_binary!(cols, "transaction_hash", transaction_hash, schema);
+    with_series_binary!(cols, "erc20", erc20, schema);
+    with_series_binary!(cols, "owner_address", owner_address, schema);
+    with_series_binary!(cols, "spender_address", spender_address, schema);
+    with_series!(cols, "value", value, schema);
+    with_series!(cols, "is_unlimited", is_unlimited, schema);
+    with_series!(cols, "decimals", token_decimals, schema);
+    with_series!(cols, "amount", amount, schema);
+    if schema.has_column("chain_id") {
+        cols.push(Series::new("chain_id", vec![source.chain_id; n_rows]));
+    }
+
+    DataFrame::new(cols).map_err(CollectError::PolarsError).sort_by_schema(schema)
+}
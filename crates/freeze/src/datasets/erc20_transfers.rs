@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+
+use ethers::prelude::*;
+use polars::prelude::*;
+use tokio::sync::mpsc;
+
+use super::logs;
+use crate::{
+    dataframes::SortableDataFrame,
+    types::{
+        conversions::{checked_u64, NarrowU32, ToVecHex},
+        tokens::format_decimal,
+        topics::TRANSFER_TOPIC,
+        BlockChunk, CollectError, ColumnType, Dataset, Datatype, Erc20Transfers, RowFilter, Source,
+        Table, TransactionChunk,
+    },
+    with_series, with_series_binary, with_series_u32,
+};
+
+#[async_trait::async_trait]
+impl Dataset for Erc20Transfers {
+    fn datatype(&self) -> Datatype {
+        Datatype::Erc20Transfers
+    }
+
+    fn name(&self) -> &'static str {
+        "erc20_transfers"
+    }
+
+    fn column_types(&self) -> HashMap<&'static str, ColumnType> {
+        HashMap::from_iter(vec![
+            ("block_number", ColumnType::UInt32),
+            ("transaction_index", ColumnType::UInt32),
+            ("log_index", ColumnType::UInt32),
+            ("transaction_hash", ColumnType::Binary),
+            ("erc20", ColumnType::Binary),
+            ("from_address", ColumnType::Binary),
+            ("to_address", ColumnType::Binary),
+            ("value", ColumnType::Decimal128),
+            ("decimals", ColumnType::UInt32),
+            ("amount", ColumnType::String),
+            ("chain_id", ColumnType::UInt64),
+        ])
+    }
+
+    fn default_columns(&self) -> Vec<&'static str> {
+        vec![
+            "block_number",
+            "transaction_index",
+            "log_index",
+            "transaction_hash",
+            "erc20",
+            "from_address",
+            "to_address",
+            "value",
+        ]
+    }
+
+    fn default_sort(&self) -> Vec<String> {
+        vec!["block_number".to_string(), "log_index".to_string()]
+    }
+
+    fn nullable_columns(&self) -> Vec<&'static str> {
+        vec!["decimals", "amount"]
+    }
+
+    fn column_descriptions(&self) -> HashMap<&'static str, &'static str> {
+        HashMap::from_iter(vec![("value", "amount transferred, in token base units")])
+    }
+
+    async fn collect_block_chunk(
+        &self,
+        chunk: &BlockChunk,
+        source: &Source,
+        schema: &Table,
+        filter: Option<&RowFilter>,
+    ) -> Result<DataFrame, CollectError> {
+        let filter = transfer_filter(filter);
+        let rx = logs::fetch_block_logs(chunk, source, Some(&filter)).await;
+        transfers_to_df(rx, &filter, source, schema).await
+    }
+
+    async fn collect_transaction_chunk(
+        &self,
+        chunk: &TransactionChunk,
+        source: &Source,
+        schema: &Table,
+        filter: Option<&RowFilter>,
+    ) -> Result<DataFrame, CollectError> {
+        let filter = transfer_filter(filter);
+        let rx = logs::fetch_transaction_logs(chunk, source, Some(&filter)).await;
+        transfers_to_df(rx, &filter, source, schema).await
+    }
+}
+
+/// filter for Transfer logs, keeping the address and spam filters of the query
+fn transfer_filter(filter: Option<&RowFilter>) -> RowFilter {
+    logs::event_filter(filter, &[&format!("{:?}", TRANSFER_TOPIC)])
+}
+
+/// decode erc20 Transfer logs, skipping erc721 Transfers which have a fourth topic
+async fn transfers_to_df(
+    logs: mpsc::Receiver<Result<Vec<Log>, CollectError>>,
+    filter: &RowFilter,
+    source: &Source,
+    schema: &Table,
+) -> Result<DataFrame, CollectError> {
+    let mut logs = logs::filter_spam_logs(logs, Some(filter)).await;
+    let mut transfers: Vec<Log> = Vec::new();
+    while let Some(message) = logs.recv().await {
+        transfers.extend(message?.into_iter().filter(|log| {
+            log.removed != Some(true) &&
+                !filter.excluded_addresses.contains(&log.address) &&
+                log.topics.len() == 3 &&
+                log.topics[0] == TRANSFER_TOPIC &&
+                log.data.len() == 32
+        }));
+    }
+
+    // decimals require an eth_call per token, so they are only fetched when requested
+    let decimals = match schema.has_column("decimals") || schema.has_column("amount") {
+        true => {
+            let tokens: Vec<H160> = transfers.iter().map(|log| log.address).collect();
+            source.token_decimals.get(&tokens, source).await?
+        }
+        false => HashMap::new(),
+    };
+
+    let n_rows = transfers.len();
+    let mut block_number: Vec<u64> = Vec::with_capacity(n_rows);
+    let mut transaction_index: Vec<u64> = Vec::with_capacity(n_rows);
+    let mut log_index: Vec<u64> = Vec::with_capacity(n_rows);
+    let mut transaction_hash: Vec<Vec<u8>> = Vec::with_capacity(n_rows);
+    let mut erc20: Vec<Vec<u8>> = Vec::with_capacity(n_rows);
+    let mut from_address: Vec<Vec<u8>> = Vec::with_capacity(n_rows);
+    let mut to_address: Vec<Vec<u8>> = Vec::with_capacity(n_rows);
+    let mut value: Vec<String> = Vec::with_capacity(n_rows);
+    let mut token_decimals: Vec<Option<u32>> = Vec::with_capacity(n_rows);
+    let mut amount: Vec<Option<String>> = Vec::with_capacity(n_rows);
+    for log in transfers.iter() {
+        let raw_value = U256::from_big_endian(&log.data);
+        let log_decimals = decimals.get(&log.address).copied().flatten();
+        block_number.push(log.block_number.map(|n| n.as_u64()).unwrap_or_default());
+        transaction_index.push(log.transaction_index.map(|i| i.as_u64()).unwrap_or_default());
+        log_index.push(checked_u64(log.log_index.unwrap_or_default(), "log_index")?);
+        transaction_hash.push(log.transaction_hash.unwrap_or_default().as_bytes().to_vec());
+        erc20.push(log.address.as_bytes().to_vec());
+        from_address.push(log.topics[1].as_bytes()[12..].to_vec());
+        to_address.push(log.topics[2].as_bytes()[12..].to_vec());
+        value.push(raw_value.to_string());
+        token_decimals.push(log_decimals.map(|d| d as u32));
+        amount.push(log_decimals.map(|d| format_decimal(raw_value, d)));
+    }
+
+    let mut cols = Vec::new();
+    with_series_u32!(cols, "block_number", block_number, schema);
+    with_series_u32!(cols, "transaction_index", transaction_index, schema);
+    with_series_u32!(cols, "log_index", log_index, schema);
+    with_series_binary!(cols, "transaction_hash", transaction_hash, schema);
+    with_series_binary!(cols, "erc20", erc20, schema);
+    with_series_binary!(cols, "from_address", from_address, schema);
+    with_series_binary!(cols, "to_address", to_address, schema);
+    with_series!(cols, "value", value, schema);
+    with_series!(cols, "decimals", token_decimals, schema);
+    with_series!(cols, "amount", amount, schema);
+    if schema.has_column("chain_id") {
+        cols.push(Series::new("chain_id", vec![source.chain_id; n_rows]));
+    }
+
+    DataFrame::new(cols).map_err(CollectError::PolarsError).sort_by_schema(schema)
+}
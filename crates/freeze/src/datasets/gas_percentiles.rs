@@ -0,0 +1,223 @@
+use std::{collections::HashMap, future::Future};
+
+use ethers::prelude::*;
+use polars::prelude::*;
+use tokio::sync::mpsc;
+
+use super::{blocks, blocks_and_transactions};
+use crate::{
+    dataframes::SortableDataFrame,
+    types::{
+        conversions::{checked_u64, NarrowU32},
+        BlockChunk, CollectError, ColumnType, Dataset, Datatype, GasPercentiles, RowFilter, Source,
+        Table,
+    },
+    with_series, with_series_u32,
+};
+
+/// percentiles computed for each block
+const PERCENTILES: [u64; 5] = [10, 25, 50, 75, 90];
+
+const GAS_PRICE_COLUMNS: [&str; 5] =
+    ["gas_price_p10", "gas_price_p25", "gas_price_p50", "gas_price_p75", "gas_price_p90"];
+
+const PRIORITY_FEE_COLUMNS: [&str; 5] = [
+    "priority_fee_p10",
+    "priority_fee_p25",
+    "priority_fee_p50",
+    "priority_fee_p75",
+    "priority_fee_p90",
+];
+
+#[async_trait::async_trait]
+impl Dataset for GasPercentiles {
+    fn datatype(&self) -> Datatype {
+        Datatype::GasPercentiles
+    }
+
+    fn name(&self) -> &'static str {
+        "gas_percentiles"
+    }
+
+    fn column_types(&self) -> HashMap<&'static str, ColumnType> {
+        let mut column_types = HashMap::from_iter(vec![
+            ("block_number", ColumnType::UInt32),
+            ("n_transactions", ColumnType::UInt32),
+            ("base_fee_per_gas", ColumnType::UInt64),
+            ("chain_id", ColumnType::UInt64),
+        ]);
+        for column in GAS_PRICE_COLUMNS.iter().chain(PRIORITY_FEE_COLUMNS.iter()) {
+            column_types.insert(*column, ColumnType::UInt64);
+        }
+        column_types
+    }
+
+    fn default_columns(&self) -> Vec<&'static str> {
+        let mut columns = vec!["block_number", "n_transactions", "base_fee_per_gas"];
+        columns.extend(GAS_PRICE_COLUMNS);
+        columns.extend(PRIORITY_FEE_COLUMNS);
+        columns
+    }
+
+    fn default_sort(&self) -> Vec<String> {
+        vec!["block_number".to_string()]
+    }
+
+    fn nullable_columns(&self) -> Vec<&'static str> {
+        let mut columns = vec!["base_fee_per_gas"];
+        columns.extend(GAS_PRICE_COLUMNS);
+        columns.extend(PRIORITY_FEE_COLUMNS);
+        columns
+    }
+
+    fn column_descriptions(&self) -> HashMap<&'static str, &'static str> {
+        let mut descriptions = HashMap::new();
+        for column in GAS_PRICE_COLUMNS.iter() {
+            descriptions.insert(*column, "percentile of effective gas prices, in wei");
+        }
+        for column in PRIORITY_FEE_COLUMNS.iter() {
+            descriptions.insert(*column, "percentile of effective priority fees, in wei");
+        }
+        descriptions
+    }
+
+    async fn collect_block_chunk(
+        &self,
+        chunk: &BlockChunk,
+        source: &Source,
+        schema: &Table,
+        _filter: Option<&RowFilter>,
+    ) -> Result<DataFrame, CollectError> {
+        let rx = blocks_and_transactions::fetch_blocks_and_transactions(
+            chunk,
+            source,
+            false,
+            false,
+            blocks_and_transactions::RecipientLookups::default(),
+        )
+        .await;
+        gas_percentiles_to_df(rx, schema, source.chain_id).await
+    }
+}
+
+/// summarize the gas prices of each block's transactions, without keeping the transactions
+async fn gas_percentiles_to_df(
+    mut blocks: mpsc::Receiver<blocks::BlockTxGasTuple<Transaction>>,
+    schema: &Table,
+    chain_id: u64,
+) -> Result<DataFrame, CollectError> {
+    let mut columns = GasPercentileColumns::new();
+    while let Some(message) = blocks.recv().await {
+        let (block, _, _) = message?;
+        columns.process_block(&block)?;
+    }
+    columns.create_df(schema, chain_id)
+}
+
+/// pass a stream of blocks on while summarizing their gas prices, so that gas percentiles
+/// collected with other datasets of the same blocks reuse their fetch
+pub(crate) fn tee_gas_percentiles(
+    mut blocks: mpsc::Receiver<blocks::BlockTxGasTuple<Transaction>>,
+) -> (
+    mpsc::Receiver<blocks::BlockTxGasTuple<Transaction>>,
+    impl Future<Output = Result<GasPercentileColumns, CollectError>>,
+) {
+    let (tx, rx) = mpsc::channel(100);
+    let summarize = async move {
+        let mut columns = GasPercentileColumns::new();
+        while let Some(message) = blocks.recv().await {
+            if let Ok((block, _, _)) = &message {
+                columns.process_block(block)?;
+            }
+            if tx.send(message).await.is_err() {
+                break
+            }
+        }
+        Ok(columns)
+    };
+    (rx, summarize)
+}
+
+pub(crate) struct GasPercentileColumns {
+    block_number: Vec<u64>,
+    n_transactions: Vec<u32>,
+    base_fee_per_gas: Vec<Option<u64>>,
+    gas_prices: Vec<Vec<Option<u64>>>,
+    priority_fees: Vec<Vec<Option<u64>>>,
+}
+
+impl GasPercentileColumns {
+    fn new() -> Self {
+        Self {
+            block_number: Vec::new(),
+            n_transactions: Vec::new(),
+            base_fee_per_gas: Vec::new(),
+            gas_prices: vec![Vec::new(); PERCENTILES.len()],
+            priority_fees: vec![Vec::new(); PERCENTILES.len()],
+        }
+    }
+
+    fn process_block(&mut self, block: &Block<Transaction>) -> Result<(), CollectError> {
+        let number = match block.number {
+            Some(number) => number.as_u64(),
+            None => return Err(CollectError::CollectError("block number missing".to_string())),
+        };
+        let base_fee =
+            block.base_fee_per_gas.map(|fee| checked_u64(fee, "base_fee_per_gas")).transpose()?;
+
+        // gas price of mined transactions is the effective gas price
+        let mut block_gas_prices = block
+            .transactions
+            .iter()
+            .filter_map(|tx| tx.gas_price)
+            .map(|price| checked_u64(price, "gas_price"))
+            .collect::<Result<Vec<u64>, CollectError>>()?;
+        let mut block_priority_fees: Vec<u64> = block_gas_prices
+            .iter()
+            .map(|price| price.saturating_sub(base_fee.unwrap_or(0)))
+            .collect();
+        block_gas_prices.sort_unstable();
+        block_priority_fees.sort_unstable();
+
+        self.block_number.push(number);
+        self.n_transactions.push(block.transactions.len() as u32);
+        self.base_fee_per_gas.push(base_fee);
+        for (i, percentile) in PERCENTILES.iter().enumerate() {
+            self.gas_prices[i].push(nearest_rank(&block_gas_prices, *percentile));
+            self.priority_fees[i].push(nearest_rank(&block_priority_fees, *percentile));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn create_df(
+        self,
+        schema: &Table,
+        chain_id: u64,
+    ) -> Result<DataFrame, CollectError> {
+        let n_rows = self.block_number.len();
+        let mut cols = Vec::new();
+        with_series_u32!(cols, "block_number", self.block_number, schema);
+        with_series!(cols, "n_transactions", self.n_transactions, schema);
+        with_series!(cols, "base_fee_per_gas", self.base_fee_per_gas, schema);
+        for (column, values) in GAS_PRICE_COLUMNS.iter().zip(self.gas_prices) {
+            with_series!(cols, *column, values, schema);
+        }
+        for (column, values) in PRIORITY_FEE_COLUMNS.iter().zip(self.priority_fees) {
+            with_series!(cols, *column, values, schema);
+        }
+        if schema.has_column("chain_id") {
+            cols.push(Series::new("chain_id", vec![chain_id; n_rows]));
+        }
+
+        DataFrame::new(cols).map_err(CollectError::PolarsError).sort_by_schema(schema)
+    }
+}
+
+/// nearest-rank percentile of sorted values
+fn nearest_rank(sorted: &[u64], percentile: u64) -> Option<u64> {
+    if sorted.is_empty() {
+        return None
+    }
+    let rank = (percentile as usize * sorted.len()).div_ceil(100);
+    sorted.get(rank.max(1) - 1).copied()
+}
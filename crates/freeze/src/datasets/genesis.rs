@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+
+use ethers::prelude::*;
+use polars::prelude::*;
+
+use crate::{
+    dataframes::SortableDataFrame,
+    lookup,
+    types::{
+        conversions::ToVecHex, BlockChunk, ChunkData, CollectError, ColumnType, Dataset, Datatype,
+        Genesis, GenesisAccount, GenesisAlloc, RequestPriority, RowFilter, Source, Table,
+    },
+    with_series, with_series_binary,
+};
+
+#[async_trait::async_trait]
+impl Dataset for Genesis {
+    fn datatype(&self) -> Datatype {
+        Datatype::Genesis
+    }
+
+    fn name(&self) -> &'static str {
+        "genesis"
+    }
+
+    fn column_types(&self) -> HashMap<&'static str, ColumnType> {
+        HashMap::from_iter(vec![
+            ("address", ColumnType::Binary),
+            ("balance", ColumnType::Decimal128),
+            ("nonce", ColumnType::UInt64),
+            ("code", ColumnType::Binary),
+            ("n_storage_slots", ColumnType::UInt64),
+            ("chain_id", ColumnType::UInt64),
+        ])
+    }
+
+    fn default_columns(&self) -> Vec<&'static str> {
+        vec!["address", "balance", "nonce", "code"]
+    }
+
+    fn default_sort(&self) -> Vec<String> {
+        vec!["address".to_string()]
+    }
+
+    /// accounts allocated at genesis, collected only by the chunk containing block 0
+    ///
+    /// accounts are read from --genesis-file if given, from the state of --addresses at block 0
+    /// if given, and otherwise from debug_dumpBlock of block 0, which requires a geth-like node
+    async fn collect_block_chunk(
+        &self,
+        chunk: &BlockChunk,
+        source: &Source,
+        schema: &Table,
+        filter: Option<&RowFilter>,
+    ) -> Result<DataFrame, CollectError> {
+        let accounts = if chunk.min_value() != Some(0) {
+            Vec::new()
+        } else if let Some(genesis) = &source.genesis {
+            genesis.accounts.clone()
+        } else {
+            match filter.and_then(|filter| filter.address.as_ref()) {
+                Some(ValueOrArray::Value(address)) => query_accounts(&[*address], source).await?,
+                Some(ValueOrArray::Array(addresses)) => query_accounts(addresses, source).await?,
+                None => dump_accounts(source).await?,
+            }
+        };
+
+        let n_rows = accounts.len();
+        let mut address: Vec<Vec<u8>> = Vec::with_capacity(n_rows);
+        let mut balance: Vec<String> = Vec::with_capacity(n_rows);
+        let mut nonce: Vec<u64> = Vec::with_capacity(n_rows);
+        let mut code: Vec<Vec<u8>> = Vec::with_capacity(n_rows);
+        let mut n_storage_slots: Vec<u64> = Vec::with_capacity(n_rows);
+        for account in accounts.into_iter() {
+            address.push(account.address);
+            balance.push(account.balance.to_string());
+            nonce.push(account.nonce);
+            code.push(account.code);
+            n_storage_slots.push(account.n_storage_slots);
+        }
+
+        let mut cols = Vec::new();
+        with_series_binary!(cols, "address", address, schema);
+        with_series!(cols, "balance", balance, schema);
+        with_series!(cols, "nonce", nonce, schema);
+        with_series_binary!(cols, "code", code, schema);
+        with_series!(cols, "n_storage_slots", n_storage_slots, schema);
+        if schema.has_column("chain_id") {
+            cols.push(Series::new("chain_id", vec![source.chain_id; n_rows]));
+        }
+
+        DataFrame::new(cols).map_err(CollectError::PolarsError).sort_by_schema(schema)
+    }
+}
+
+/// all accounts of the state dump of block 0
+async fn dump_accounts(source: &Source) -> Result<Vec<GenesisAccount>, CollectError> {
+    let _permit = source.permit_request(RequestPriority::Bulk).await;
+    let dump: serde_json::Value = source
+        .provider
+        .request("debug_dumpBlock", ["0x0"])
+        .await
+        .map_err(|e| CollectError::rpc("debug_dumpBlock", e))?;
+    Ok(GenesisAlloc::from_dump(&dump)?.accounts)
+}
+
+/// state of each address at block 0
+///
+/// storage slots cannot be counted through standard rpc methods, so they are reported as 0
+async fn query_accounts(
+    addresses: &[H160],
+    source: &Source,
+) -> Result<Vec<GenesisAccount>, CollectError> {
+    let mut addresses = addresses.to_vec();
+    addresses.sort();
+    addresses.dedup();
+    let accounts = addresses.into_iter().map(|address| query_account(address, source));
+    futures::future::try_join_all(accounts).await
+}
+
+async fn query_account(address: H160, source: &Source) -> Result<GenesisAccount, CollectError> {
+    let block = Some(BlockId::from(0u64));
+    let balance = {
+        let _permit = source.permit_request(RequestPriority::Bulk).await;
+        source
+            .provider
+            .get_balance(address, block)
+            .await
+            .map_err(|e| CollectError::rpc("eth_getBalance", e))?
+    };
+    let nonce = {
+        let _permit = source.permit_request(RequestPriority::Bulk).await;
+        lookup::nonce_at(address, 0, &source.provider).await?
+    };
+    let code = {
+        let _permit = source.permit_request(RequestPriority::Bulk).await;
+        source
+            .provider
+            .get_code(address, block)
+            .await
+            .map_err(|e| CollectError::rpc("eth_getCode", e))?
+    };
+    Ok(GenesisAccount {
+        address: address.as_bytes().to_vec(),
+        balance,
+        nonce,
+        code: code.to_vec(),
+        n_storage_slots: 0,
+    })
+}
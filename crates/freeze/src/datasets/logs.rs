@@ -1,4 +1,7 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::Arc,
+};
 
 use ethers::prelude::*;
 use polars::prelude::*;
@@ -7,10 +10,11 @@ use tokio::{sync::mpsc, task};
 use crate::{
     dataframes::SortableDataFrame,
     types::{
-        conversions::ToVecHex, BlockChunk, CollectError, ColumnType, Dataset, Datatype, Logs,
+        conversions::{checked_u64, NarrowU32, ToVecHex},
+        local_logs, BlockChunk, CollectError, ColumnType, Dataset, Datatype, Logs, RequestPriority,
         RowFilter, Source, Table, TransactionChunk,
     },
-    with_series, with_series_binary,
+    with_series, with_series_binary, with_series_u32,
 };
 
 #[async_trait::async_trait]
@@ -36,6 +40,8 @@ impl Dataset for Logs {
             ("topic3", ColumnType::Binary),
             ("data", ColumnType::Binary),
             ("chain_id", ColumnType::UInt64),
+            ("transaction_from", ColumnType::Binary),
+            ("trace_address", ColumnType::String),
         ])
     }
 
@@ -58,6 +64,10 @@ impl Dataset for Logs {
         vec!["block_number".to_string(), "log_index".to_string()]
     }
 
+    fn nullable_columns(&self) -> Vec<&'static str> {
+        vec!["topic0", "topic1", "topic2", "topic3", "transaction_from", "trace_address"]
+    }
+
     async fn collect_block_chunk(
         &self,
         chunk: &BlockChunk,
@@ -66,7 +76,9 @@ impl Dataset for Logs {
         filter: Option<&RowFilter>,
     ) -> Result<DataFrame, CollectError> {
         let rx = fetch_block_logs(chunk, source, filter).await;
-        logs_to_df(rx, schema, source.chain_id).await
+        let rx = filter_spam_logs(rx, filter).await;
+        let (rx, context) = fetch_log_context(rx, source, schema).await;
+        logs_to_df(rx, schema, source.chain_id, &context).await
     }
 
     async fn collect_transaction_chunk(
@@ -82,23 +94,37 @@ impl Dataset for Logs {
         //     ));
         // };
         let rx = fetch_transaction_logs(chunk, source, filter).await;
-        logs_to_df(rx, schema, source.chain_id).await
+        let rx = filter_spam_logs(rx, filter).await;
+        let (rx, context) = fetch_log_context(rx, source, schema).await;
+        logs_to_df(rx, schema, source.chain_id, &context).await
     }
 }
 
-async fn fetch_block_logs(
+pub(crate) async fn fetch_block_logs(
     block_chunk: &BlockChunk,
     source: &Source,
     filter: Option<&RowFilter>,
 ) -> mpsc::Receiver<Result<Vec<Log>, CollectError>> {
+    if let Some(local_logs) = &source.local_logs {
+        let (tx, rx) = mpsc::channel(1);
+        let local_logs = Arc::clone(local_logs);
+        let block_chunk = block_chunk.clone();
+        let filter = filter.cloned();
+        task::spawn_blocking(move || {
+            let result = local_logs.read(&block_chunk, filter.as_ref());
+            // a dropped receiver means the chunk was abandoned
+            let _ = tx.blocking_send(result);
+        });
+        return rx
+    }
+
     // todo: need to modify these functions so they turn a result
     let request_chunks = block_chunk.to_log_filter_options(&source.inner_request_size);
     let (tx, rx) = mpsc::channel(request_chunks.len());
     for request_chunk in request_chunks.iter() {
         let tx = tx.clone();
         let provider = source.provider.clone();
-        let semaphore = source.semaphore.clone();
-        let rate_limiter = source.rate_limiter.as_ref().map(Arc::clone);
+        let source = source.clone();
         let log_filter = match filter {
             Some(filter) => Filter {
                 block_option: *request_chunk,
@@ -112,14 +138,11 @@ async fn fetch_block_logs(
             },
         };
         task::spawn(async move {
-            let _permit = match semaphore {
-                Some(semaphore) => Some(Arc::clone(&semaphore).acquire_owned().await),
-                _ => None,
-            };
-            if let Some(limiter) = rate_limiter {
-                Arc::clone(&limiter).until_ready().await;
-            }
-            let result = provider.get_logs(&log_filter).await.map_err(CollectError::ProviderError);
+            let _permit = source.permit_request(RequestPriority::Bulk).await;
+            let result = provider
+                .get_logs(&log_filter)
+                .await
+                .map_err(|e| CollectError::rpc("eth_getLogs", e));
             match tx.send(result).await {
                 Ok(_) => {}
                 Err(tokio::sync::mpsc::error::SendError(_e)) => {
@@ -132,11 +155,19 @@ async fn fetch_block_logs(
     rx
 }
 
-async fn fetch_transaction_logs(
+pub(crate) async fn fetch_transaction_logs(
     transaction_chunk: &TransactionChunk,
     source: &Source,
-    _filter: Option<&RowFilter>,
+    filter: Option<&RowFilter>,
 ) -> mpsc::Receiver<Result<Vec<Log>, CollectError>> {
+    if source.local_logs.is_some() {
+        let (tx, rx) = mpsc::channel(1);
+        let message = "--logs-dir only supports block ranges, not --txs".to_string();
+        let _ = tx.send(Err(CollectError::CollectError(message))).await;
+        return rx
+    }
+    // receipts hold every log of their transaction, so unrelated logs are dropped on request
+    let prune_filter = filter.filter(|filter| filter.prune_receipt_logs).cloned();
     match transaction_chunk {
         TransactionChunk::Values(tx_hashes) => {
             let (tx, rx) = mpsc::channel(tx_hashes.len() * 200);
@@ -144,23 +175,25 @@ async fn fetch_transaction_logs(
                 let tx_hash = tx_hash.clone();
                 let tx = tx.clone();
                 let provider = source.provider.clone();
-                let semaphore = source.semaphore.clone();
-                let rate_limiter = source.rate_limiter.as_ref().map(Arc::clone);
+                let source = source.clone();
+                let prune_filter = prune_filter.clone();
                 task::spawn(async move {
-                    let _permit = match semaphore {
-                        Some(semaphore) => Some(Arc::clone(&semaphore).acquire_owned().await),
-                        _ => None,
-                    };
-                    if let Some(limiter) = rate_limiter {
-                        Arc::clone(&limiter).until_ready().await;
-                    }
+                    let _permit = source.permit_request(RequestPriority::Bulk).await;
                     let receipt = provider
                         .get_transaction_receipt(H256::from_slice(&tx_hash))
                         .await
-                        .map_err(CollectError::ProviderError);
-                    let logs = match receipt {
-                        Ok(Some(receipt)) => Ok(receipt.logs),
-                        _ => Err(CollectError::CollectError("".to_string())),
+                        .map_err(|e| CollectError::rpc("eth_getTransactionReceipt", e));
+                    let logs = match (receipt, &prune_filter) {
+                        (Ok(Some(receipt)), Some(filter)) => Ok(receipt
+                            .logs
+                            .into_iter()
+                            .filter(|log| local_logs::matches_filter(log, filter))
+                            .collect()),
+                        (Ok(Some(receipt)), None) => Ok(receipt.logs),
+                        (Ok(None), _) => {
+                            Err(CollectError::CollectError("could not find tx receipt".to_string()))
+                        }
+                        (Err(e), _) => Err(e),
                     };
                     match tx.send(logs).await {
                         Ok(_) => {}
@@ -190,14 +223,203 @@ async fn fetch_transaction_logs(
     }
 }
 
+/// restrict log filter to events with any of topic0s, keeping any contract and token filters
+pub(crate) fn event_filter(filter: Option<&RowFilter>, topic0s: &[&str]) -> RowFilter {
+    let topics: Vec<Option<H256>> =
+        topic0s.iter().map(|topic| Some(topic.parse().expect("valid event topic"))).collect();
+    let topic0 = match topics.len() {
+        1 => ValueOrArray::Value(topics[0]),
+        _ => ValueOrArray::Array(topics),
+    };
+    RowFilter {
+        topics: [Some(topic0), None, None, None],
+        address: filter.and_then(|filter| filter.address.clone()),
+        spam_filter: filter.and_then(|filter| filter.spam_filter.clone()),
+        excluded_addresses: filter
+            .map(|filter| filter.excluded_addresses.clone())
+            .unwrap_or_default(),
+        collapse_delegatecalls: false,
+        prune_receipt_logs: filter.map(|filter| filter.prune_receipt_logs).unwrap_or(false),
+    }
+}
+
+/// drop spam logs, which requires gathering all logs of the chunk first
+pub(crate) async fn filter_spam_logs(
+    mut logs: mpsc::Receiver<Result<Vec<Log>, CollectError>>,
+    filter: Option<&RowFilter>,
+) -> mpsc::Receiver<Result<Vec<Log>, CollectError>> {
+    let spam_filter = match filter.and_then(|filter| filter.spam_filter.as_ref()) {
+        Some(spam_filter) => spam_filter,
+        None => return logs,
+    };
+    let mut all_logs = Vec::new();
+    let mut result = Ok(());
+    while let Some(message) = logs.recv().await {
+        match message {
+            Ok(logs) => all_logs.extend(logs),
+            Err(e) => {
+                result = Err(e);
+                break
+            }
+        }
+    }
+    let (tx, rx) = mpsc::channel(1);
+    let message = result.map(|()| spam_filter.filter_logs(all_logs));
+    // receiver is held locally, so sending into the fresh channel cannot fail
+    let _ = tx.send(message).await;
+    rx
+}
+
+/// per-transaction data joined into logs, fetched only for the columns that need it
+#[derive(Default)]
+struct LogContext {
+    /// sender of each transaction, for transaction_from
+    senders: HashMap<H256, H160>,
+    /// call frame of each log, by block number and log index
+    trace_addresses: HashMap<(u64, u64), String>,
+}
+
+/// gather the logs of a chunk and fetch the data of the blocks holding them
+///
+/// senders come from fetching each block once with its transactions, far cheaper than a receipt
+/// per log. trace addresses come from replaying each block with call and vm traces
+async fn fetch_log_context(
+    mut logs: mpsc::Receiver<Result<Vec<Log>, CollectError>>,
+    source: &Source,
+    schema: &Table,
+) -> (mpsc::Receiver<Result<Vec<Log>, CollectError>>, LogContext) {
+    let mut context = LogContext::default();
+    let include_senders = schema.has_column("transaction_from");
+    let include_trace_addresses = schema.has_column("trace_address");
+    if !include_senders && !include_trace_addresses {
+        return (logs, context)
+    }
+    let mut all_logs = Vec::new();
+    let mut result = Ok(());
+    while let Some(message) = logs.recv().await {
+        match message {
+            Ok(logs) => all_logs.extend(logs),
+            Err(e) => {
+                result = Err(e);
+                break
+            }
+        }
+    }
+    let numbers: BTreeSet<u64> =
+        all_logs.iter().filter_map(|log| log.block_number.map(|n| n.as_u64())).collect();
+
+    if result.is_ok() && include_senders {
+        let fetches = numbers.iter().map(|number| async move {
+            let _permit = source.permit_request(RequestPriority::Bulk).await;
+            match source.provider.get_block_with_txs(*number).await {
+                Ok(Some(block)) => Ok(block),
+                Ok(None) => Err(CollectError::CollectError(format!("block {} not found", number))),
+                Err(e) => Err(CollectError::rpc("eth_getBlockByNumber", e)),
+            }
+        });
+        for block in futures::future::join_all(fetches).await.into_iter() {
+            match block {
+                Ok(block) => {
+                    context.senders.extend(block.transactions.iter().map(|tx| (tx.hash, tx.from)))
+                }
+                Err(e) => {
+                    result = Err(e);
+                    break
+                }
+            }
+        }
+    }
+
+    if result.is_ok() && include_trace_addresses {
+        let fetches = numbers.iter().map(|number| async move {
+            let _permit = source.permit_request(RequestPriority::Bulk).await;
+            source
+                .provider
+                .trace_replay_block_transactions(
+                    BlockNumber::Number((*number).into()),
+                    vec![TraceType::Trace, TraceType::VmTrace],
+                )
+                .await
+                .map_err(|e| CollectError::rpc("trace_replayBlockTransactions", e))
+        });
+        let replays = futures::future::join_all(fetches).await;
+        for (number, block_traces) in numbers.iter().zip(replays) {
+            match block_traces {
+                Ok(block_traces) => {
+                    // log indices count the logs kept by the chain across the whole block
+                    let frames = block_traces.iter().flat_map(log_frames).enumerate();
+                    context.trace_addresses.extend(
+                        frames.map(|(log_index, frame)| ((*number, log_index as u64), frame)),
+                    );
+                }
+                Err(e) => {
+                    result = Err(e);
+                    break
+                }
+            }
+        }
+    }
+
+    let (tx, rx) = mpsc::channel(1);
+    // receiver is held locally, so sending into the fresh channel cannot fail
+    let _ = tx.send(result.map(|()| all_logs)).await;
+    (rx, context)
+}
+
+/// trace address of the call frame emitting each log of a transaction, in log order
+///
+/// LOG opcodes are found in the vm trace, whose nested frames are numbered like trace
+/// addresses. logs of failed frames are discarded by the chain, so those frames are skipped
+fn log_frames(block_trace: &BlockTrace) -> Vec<String> {
+    let failed: Vec<&Vec<usize>> = block_trace
+        .trace
+        .iter()
+        .flatten()
+        .filter(|trace| trace.error.is_some())
+        .map(|trace| &trace.trace_address)
+        .collect();
+    let mut frames = Vec::new();
+    if let Some(vm_trace) = &block_trace.vm_trace {
+        add_log_frames(vm_trace, &mut vec![], &failed, &mut frames);
+    }
+    frames
+}
+
+fn add_log_frames(
+    vm_trace: &VMTrace,
+    trace_address: &mut Vec<usize>,
+    failed: &[&Vec<usize>],
+    frames: &mut Vec<String>,
+) {
+    if failed.iter().any(|failed| trace_address.starts_with(failed)) {
+        return
+    }
+    let mut n_calls = 0;
+    for op in vm_trace.ops.iter() {
+        let opcode = vm_trace.code.get(op.pc).copied().unwrap_or_default();
+        // LOG0 to LOG4
+        if (0xa0..=0xa4).contains(&opcode) && op.ex.is_some() {
+            let address: Vec<String> = trace_address.iter().map(|n| n.to_string()).collect();
+            frames.push(address.join("_"));
+        }
+        if let Some(sub) = &op.sub {
+            trace_address.push(n_calls);
+            add_log_frames(sub, trace_address, failed, frames);
+            trace_address.pop();
+            n_calls += 1;
+        }
+    }
+}
+
 async fn logs_to_df(
     mut logs: mpsc::Receiver<Result<Vec<Log>, CollectError>>,
     schema: &Table,
     chain_id: u64,
+    context: &LogContext,
 ) -> Result<DataFrame, CollectError> {
-    let mut block_number: Vec<u32> = Vec::new();
-    let mut transaction_index: Vec<u32> = Vec::new();
-    let mut log_index: Vec<u32> = Vec::new();
+    let mut block_number: Vec<u64> = Vec::new();
+    let mut transaction_index: Vec<u64> = Vec::new();
+    let mut log_index: Vec<u64> = Vec::new();
     let mut transaction_hash: Vec<Vec<u8>> = Vec::new();
     let mut address: Vec<Vec<u8>> = Vec::new();
     let mut topic0: Vec<Option<Vec<u8>>> = Vec::new();
@@ -205,6 +427,8 @@ async fn logs_to_df(
     let mut topic2: Vec<Option<Vec<u8>>> = Vec::new();
     let mut topic3: Vec<Option<Vec<u8>>> = Vec::new();
     let mut data: Vec<Vec<u8>> = Vec::new();
+    let mut transaction_from: Vec<Option<Vec<u8>>> = Vec::new();
+    let mut trace_address: Vec<Option<String>> = Vec::new();
 
     let mut n_rows = 0;
     // while let Some(Ok(logs)) = logs.recv().await {
@@ -221,6 +445,7 @@ async fn logs_to_df(
                         log.transaction_index,
                         log.log_index,
                     ) {
+                        let li = checked_u64(li, "log_index")?;
                         n_rows += 1;
                         address.push(log.address.as_bytes().to_vec());
                         match log.topics.len() {
@@ -257,21 +482,30 @@ async fn logs_to_df(
                             _ => return Err(CollectError::InvalidNumberOfTopics),
                         }
                         data.push(log.data.clone().to_vec());
-                        block_number.push(bn.as_u32());
+                        block_number.push(bn.as_u64());
                         transaction_hash.push(tx.as_bytes().to_vec());
-                        transaction_index.push(ti.as_u32());
-                        log_index.push(li.as_u32());
+                        if schema.has_column("transaction_from") {
+                            let from =
+                                context.senders.get(&tx).map(|from| from.as_bytes().to_vec());
+                            transaction_from.push(from);
+                        }
+                        if schema.has_column("trace_address") {
+                            let key = (bn.as_u64(), li);
+                            trace_address.push(context.trace_addresses.get(&key).cloned());
+                        }
+                        transaction_index.push(ti.as_u64());
+                        log_index.push(li);
                     }
                 }
             }
-            _ => return Err(CollectError::TooManyRequestsError),
+            Err(e) => return Err(e),
         }
     }
 
     let mut cols = Vec::new();
-    with_series!(cols, "block_number", block_number, schema);
-    with_series!(cols, "transaction_index", transaction_index, schema);
-    with_series!(cols, "log_index", log_index, schema);
+    with_series_u32!(cols, "block_number", block_number, schema);
+    with_series_u32!(cols, "transaction_index", transaction_index, schema);
+    with_series_u32!(cols, "log_index", log_index, schema);
     with_series_binary!(cols, "transaction_hash", transaction_hash, schema);
     with_series_binary!(cols, "contract_address", address, schema);
     with_series_binary!(cols, "topic0", topic0, schema);
@@ -279,6 +513,8 @@ async fn logs_to_df(
     with_series_binary!(cols, "topic2", topic2, schema);
     with_series_binary!(cols, "topic3", topic3, schema);
     with_series_binary!(cols, "data", data, schema);
+    with_series_binary!(cols, "transaction_from", transaction_from, schema);
+    with_series!(cols, "trace_address", trace_address, schema);
 
     if schema.has_column("chain_id") {
         cols.push(Series::new("chain_id", vec![chain_id; n_rows]));
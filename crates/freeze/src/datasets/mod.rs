@@ -1,11 +1,29 @@
+mod address_activity;
+mod address_lifespans;
+mod approvals_for_all;
 mod balance_diffs;
+mod beacon_deposits;
 mod blocks;
 mod blocks_and_transactions;
+mod bridge_transfers;
 mod code_diffs;
+mod erc1155_transfers;
+mod erc20_approvals;
+mod erc20_transfers;
+mod gas_percentiles;
+mod genesis;
 mod logs;
+mod multicall_calls;
 mod nonce_diffs;
+mod raw_rpc;
+mod safe_transactions;
+mod starknet_blocks;
+mod starknet_transactions;
 mod state_diffs;
 mod storage_diffs;
 mod traces;
 mod transactions;
+mod validators;
 mod vm_traces;
+mod weth_wraps;
+mod zksync_transactions;
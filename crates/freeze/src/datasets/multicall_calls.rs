@@ -0,0 +1,344 @@
+use std::collections::HashMap;
+
+use ethers::{
+    abi::{self, ParamType, Token},
+    prelude::*,
+};
+use polars::prelude::*;
+use tokio::sync::mpsc;
+
+use super::{blocks, blocks_and_transactions};
+use crate::{
+    dataframes::SortableDataFrame,
+    types::{
+        conversions::{NarrowU32, ToVecHex},
+        BlockChunk, CollectError, ColumnType, Dataset, Datatype, MulticallCalls, RowFilter, Source,
+        Table,
+    },
+    with_series, with_series_binary, with_series_u32,
+};
+
+/// multicalls nested deeper than this are kept as opaque child calls
+const MAX_DEPTH: usize = 8;
+
+/// calldata schemes that batch several child calls into one call
+#[derive(Clone, Copy)]
+enum Scheme {
+    /// aggregate((address,bytes)[])
+    Aggregate,
+    /// tryAggregate(bool,(address,bytes)[])
+    TryAggregate,
+    /// blockAndAggregate((address,bytes)[])
+    BlockAndAggregate,
+    /// tryBlockAndAggregate(bool,(address,bytes)[])
+    TryBlockAndAggregate,
+    /// aggregate3((address,bool,bytes)[])
+    Aggregate3,
+    /// aggregate3Value((address,bool,uint256,bytes)[])
+    Aggregate3Value,
+    /// multicall(bytes[]), child calls are delegatecalls to the multicall contract itself
+    Multicall,
+    /// multicall(uint256,bytes[]), with a deadline
+    MulticallDeadline,
+    /// multicall(bytes32,bytes[]), with a previous block hash
+    MulticallBlockHash,
+}
+
+impl Scheme {
+    fn from_selector(selector: &[u8]) -> Option<Scheme> {
+        match selector {
+            [0x25, 0x2d, 0xba, 0x42] => Some(Scheme::Aggregate),
+            [0xbc, 0xe3, 0x8b, 0xd7] => Some(Scheme::TryAggregate),
+            [0xc3, 0x07, 0x7f, 0xa9] => Some(Scheme::BlockAndAggregate),
+            [0x39, 0x95, 0x42, 0xe9] => Some(Scheme::TryBlockAndAggregate),
+            [0x82, 0xad, 0x56, 0xcb] => Some(Scheme::Aggregate3),
+            [0x17, 0x4d, 0xea, 0x71] => Some(Scheme::Aggregate3Value),
+            [0xac, 0x96, 0x50, 0xd8] => Some(Scheme::Multicall),
+            [0x5a, 0xe4, 0x01, 0xdc] => Some(Scheme::MulticallDeadline),
+            [0x1f, 0x04, 0x64, 0xd1] => Some(Scheme::MulticallBlockHash),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Scheme::Aggregate => "aggregate",
+            Scheme::TryAggregate => "try_aggregate",
+            Scheme::BlockAndAggregate => "block_and_aggregate",
+            Scheme::TryBlockAndAggregate => "try_block_and_aggregate",
+            Scheme::Aggregate3 => "aggregate3",
+            Scheme::Aggregate3Value => "aggregate3_value",
+            Scheme::Multicall => "multicall",
+            Scheme::MulticallDeadline => "multicall_deadline",
+            Scheme::MulticallBlockHash => "multicall_block_hash",
+        }
+    }
+
+    /// parameters of the call, the array of child calls is always last
+    fn params(&self) -> Vec<ParamType> {
+        let call = ParamType::Tuple(vec![ParamType::Address, ParamType::Bytes]);
+        let call3 = ParamType::Tuple(vec![ParamType::Address, ParamType::Bool, ParamType::Bytes]);
+        let call3_value = ParamType::Tuple(vec![
+            ParamType::Address,
+            ParamType::Bool,
+            ParamType::Uint(256),
+            ParamType::Bytes,
+        ]);
+        let array = |param| ParamType::Array(Box::new(param));
+        match self {
+            Scheme::Aggregate | Scheme::BlockAndAggregate => vec![array(call)],
+            Scheme::TryAggregate | Scheme::TryBlockAndAggregate => {
+                vec![ParamType::Bool, array(call)]
+            }
+            Scheme::Aggregate3 => vec![array(call3)],
+            Scheme::Aggregate3Value => vec![array(call3_value)],
+            Scheme::Multicall => vec![array(ParamType::Bytes)],
+            Scheme::MulticallDeadline => vec![ParamType::Uint(256), array(ParamType::Bytes)],
+            Scheme::MulticallBlockHash => {
+                vec![ParamType::FixedBytes(32), array(ParamType::Bytes)]
+            }
+        }
+    }
+}
+
+/// one child call of a multicall
+struct ChildCall {
+    multicall_address: H160,
+    scheme: &'static str,
+    call_path: String,
+    depth: u32,
+    target: H160,
+    value: Option<U256>,
+    allow_failure: Option<bool>,
+    input: Vec<u8>,
+}
+
+/// target, value, allow_failure, and input of a child call
+type Child = (H160, Option<U256>, Option<bool>, Vec<u8>);
+
+/// decode the child calls of a multicall to an address, none if the calldata is not a multicall
+fn decode_multicall(address: H160, input: &[u8]) -> Option<(Scheme, Vec<Child>)> {
+    let scheme = Scheme::from_selector(input.get(..4)?)?;
+    let mut tokens = abi::decode(&scheme.params(), &input[4..]).ok()?;
+    let calls = tokens.pop()?.into_array()?;
+    let require_success = match scheme {
+        Scheme::TryAggregate | Scheme::TryBlockAndAggregate => {
+            tokens.first().cloned().and_then(Token::into_bool)
+        }
+        _ => None,
+    };
+    let mut children = Vec::with_capacity(calls.len());
+    for call in calls.into_iter() {
+        let child = match (scheme, call) {
+            (Scheme::Multicall | Scheme::MulticallDeadline | Scheme::MulticallBlockHash, call) => {
+                (address, None, Some(false), call.into_bytes()?)
+            }
+            (Scheme::Aggregate3, Token::Tuple(fields)) => {
+                let mut fields = fields.into_iter();
+                let target = fields.next()?.into_address()?;
+                let allow_failure = fields.next()?.into_bool()?;
+                (target, None, Some(allow_failure), fields.next()?.into_bytes()?)
+            }
+            (Scheme::Aggregate3Value, Token::Tuple(fields)) => {
+                let mut fields = fields.into_iter();
+                let target = fields.next()?.into_address()?;
+                let allow_failure = fields.next()?.into_bool()?;
+                let value = fields.next()?.into_uint()?;
+                (target, Some(value), Some(allow_failure), fields.next()?.into_bytes()?)
+            }
+            (_, Token::Tuple(fields)) => {
+                let mut fields = fields.into_iter();
+                let target = fields.next()?.into_address()?;
+                let allow_failure = require_success.map(|require| !require).or(Some(false));
+                (target, None, allow_failure, fields.next()?.into_bytes()?)
+            }
+            _ => return None,
+        };
+        children.push(child);
+    }
+    Some((scheme, children))
+}
+
+/// expand the child calls of a multicall in call order, each child call followed by its own
+/// child calls if it is itself a multicall
+fn expand_multicall(
+    address: H160,
+    input: &[u8],
+    path: &str,
+    depth: usize,
+    calls: &mut Vec<ChildCall>,
+) {
+    let (scheme, children) = match decode_multicall(address, input) {
+        Some(decoded) => decoded,
+        None => return,
+    };
+    for (i, (target, value, allow_failure, child_input)) in children.into_iter().enumerate() {
+        let call_path = match path.is_empty() {
+            true => i.to_string(),
+            false => format!("{}_{}", path, i),
+        };
+        let index = calls.len();
+        calls.push(ChildCall {
+            multicall_address: address,
+            scheme: scheme.name(),
+            call_path: call_path.clone(),
+            depth: depth as u32,
+            target,
+            value,
+            allow_failure,
+            input: Vec::new(),
+        });
+        if depth < MAX_DEPTH {
+            expand_multicall(target, &child_input, &call_path, depth + 1, calls);
+        }
+        calls[index].input = child_input;
+    }
+}
+
+#[async_trait::async_trait]
+impl Dataset for MulticallCalls {
+    fn datatype(&self) -> Datatype {
+        Datatype::MulticallCalls
+    }
+
+    fn name(&self) -> &'static str {
+        "multicall_calls"
+    }
+
+    fn column_types(&self) -> HashMap<&'static str, ColumnType> {
+        HashMap::from_iter(vec![
+            ("block_number", ColumnType::UInt32),
+            ("transaction_index", ColumnType::UInt32),
+            ("transaction_hash", ColumnType::Binary),
+            ("from_address", ColumnType::Binary),
+            ("multicall_address", ColumnType::Binary),
+            ("scheme", ColumnType::String),
+            ("call_index", ColumnType::UInt32),
+            ("call_path", ColumnType::String),
+            ("depth", ColumnType::UInt32),
+            ("target", ColumnType::Binary),
+            ("value", ColumnType::Decimal128),
+            ("allow_failure", ColumnType::Boolean),
+            ("selector", ColumnType::Binary),
+            ("input", ColumnType::Binary),
+            ("chain_id", ColumnType::UInt64),
+        ])
+    }
+
+    fn default_columns(&self) -> Vec<&'static str> {
+        vec![
+            "block_number",
+            "transaction_index",
+            "transaction_hash",
+            "call_index",
+            "multicall_address",
+            "scheme",
+            "call_path",
+            "target",
+            "value",
+            "allow_failure",
+            "selector",
+        ]
+    }
+
+    fn default_sort(&self) -> Vec<String> {
+        vec!["block_number".to_string(), "transaction_index".to_string(), "call_index".to_string()]
+    }
+
+    fn nullable_columns(&self) -> Vec<&'static str> {
+        vec!["value", "allow_failure", "selector"]
+    }
+
+    fn column_descriptions(&self) -> HashMap<&'static str, &'static str> {
+        HashMap::from_iter(vec![("value", "wei sent with the call, null if not given")])
+    }
+
+    async fn collect_block_chunk(
+        &self,
+        chunk: &BlockChunk,
+        source: &Source,
+        schema: &Table,
+        _filter: Option<&RowFilter>,
+    ) -> Result<DataFrame, CollectError> {
+        let rx = blocks_and_transactions::fetch_blocks_and_transactions(
+            chunk,
+            source,
+            false,
+            false,
+            blocks_and_transactions::RecipientLookups::default(),
+        )
+        .await;
+        multicall_calls_to_df(rx, source, schema).await
+    }
+}
+
+async fn multicall_calls_to_df(
+    mut blocks: mpsc::Receiver<blocks::BlockTxGasTuple<Transaction>>,
+    source: &Source,
+    schema: &Table,
+) -> Result<DataFrame, CollectError> {
+    let mut block_number: Vec<u64> = Vec::new();
+    let mut transaction_index: Vec<u64> = Vec::new();
+    let mut transaction_hash: Vec<Vec<u8>> = Vec::new();
+    let mut from_address: Vec<Vec<u8>> = Vec::new();
+    let mut multicall_address: Vec<Vec<u8>> = Vec::new();
+    let mut scheme: Vec<String> = Vec::new();
+    let mut call_index: Vec<u32> = Vec::new();
+    let mut call_path: Vec<String> = Vec::new();
+    let mut depth: Vec<u32> = Vec::new();
+    let mut target: Vec<Vec<u8>> = Vec::new();
+    let mut value: Vec<Option<String>> = Vec::new();
+    let mut allow_failure: Vec<Option<bool>> = Vec::new();
+    let mut selector: Vec<Option<Vec<u8>>> = Vec::new();
+    let mut input: Vec<Vec<u8>> = Vec::new();
+    while let Some(message) = blocks.recv().await {
+        let (block, _, _) = message?;
+        for tx in block.transactions.iter() {
+            let to = match tx.to {
+                Some(to) => to,
+                None => continue,
+            };
+            let mut calls = Vec::new();
+            expand_multicall(to, &tx.input, "", 0, &mut calls);
+            for (i, call) in calls.into_iter().enumerate() {
+                block_number.push(tx.block_number.map(|n| n.as_u64()).unwrap_or_default());
+                transaction_index
+                    .push(tx.transaction_index.map(|i| i.as_u64()).unwrap_or_default());
+                transaction_hash.push(tx.hash.as_bytes().to_vec());
+                from_address.push(tx.from.as_bytes().to_vec());
+                multicall_address.push(call.multicall_address.as_bytes().to_vec());
+                scheme.push(call.scheme.to_string());
+                call_index.push(i as u32);
+                call_path.push(call.call_path);
+                depth.push(call.depth);
+                target.push(call.target.as_bytes().to_vec());
+                value.push(call.value.map(|value| value.to_string()));
+                allow_failure.push(call.allow_failure);
+                selector.push(call.input.get(..4).map(|selector| selector.to_vec()));
+                input.push(call.input);
+            }
+        }
+    }
+
+    let n_rows = block_number.len();
+    let mut cols = Vec::new();
+    with_series_u32!(cols, "block_number", block_number, schema);
+    with_series_u32!(cols, "transaction_index", transaction_index, schema);
+    with_series_binary!(cols, "transaction_hash", transaction_hash, schema);
+    with_series_binary!(cols, "from_address", from_address, schema);
+    with_series_binary!(cols, "multicall_address", multicall_address, schema);
+    with_series!(cols, "scheme", scheme, schema);
+    with_series!(cols, "call_index", call_index, schema);
+    with_series!(cols, "call_path", call_path, schema);
+    with_series!(cols, "depth", depth, schema);
+    with_series_binary!(cols, "target", target, schema);
+    with_series!(cols, "value", value, schema);
+    with_series!(cols, "allow_failure", allow_failure, schema);
+    with_series_binary!(cols, "selector", selector, schema);
+    with_series_binary!(cols, "input", input, schema);
+    if schema.has_column("chain_id") {
+        cols.push(Series::new("chain_id", vec![source.chain_id; n_rows]));
+    }
+
+    DataFrame::new(cols).map_err(CollectError::PolarsError).sort_by_schema(schema)
+}
@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+
+use polars::prelude::*;
+use tokio::{sync::mpsc, task};
+
+use crate::{
+    dataframes::SortableDataFrame,
+    types::{
+        BlockChunk, CollectError, ColumnType, Dataset, Datatype, RawRpc, RequestPriority,
+        RowFilter, Source, Table,
+    },
+    with_series,
+};
+
+#[async_trait::async_trait]
+impl Dataset for RawRpc {
+    fn datatype(&self) -> Datatype {
+        Datatype::RawRpc
+    }
+
+    fn name(&self) -> &'static str {
+        "raw_rpc"
+    }
+
+    fn column_types(&self) -> HashMap<&'static str, ColumnType> {
+        HashMap::from_iter(vec![
+            ("block_number", ColumnType::UInt32),
+            ("method", ColumnType::String),
+            ("params", ColumnType::String),
+            ("response", ColumnType::String),
+            ("chain_id", ColumnType::UInt64),
+        ])
+    }
+
+    fn default_columns(&self) -> Vec<&'static str> {
+        vec!["block_number", "method", "response"]
+    }
+
+    fn default_sort(&self) -> Vec<String> {
+        vec!["block_number".to_string()]
+    }
+
+    async fn collect_block_chunk(
+        &self,
+        chunk: &BlockChunk,
+        source: &Source,
+        schema: &Table,
+        _filter: Option<&RowFilter>,
+    ) -> Result<DataFrame, CollectError> {
+        let rx = fetch_raw_responses(chunk, source).await?;
+        raw_responses_to_df(rx, schema, source).await
+    }
+}
+
+/// response of a request for a block, with the rendered parameters
+type RawResponse = (u64, String, serde_json::Value);
+
+/// send the request of --rpc-method once per block, through the limits of the source
+async fn fetch_raw_responses(
+    block_chunk: &BlockChunk,
+    source: &Source,
+) -> Result<mpsc::Receiver<Result<RawResponse, CollectError>>, CollectError> {
+    let request = source.raw_rpc.as_ref().ok_or_else(|| {
+        CollectError::CollectError("raw_rpc dataset requires --rpc-method".to_string())
+    })?;
+    let numbers = block_chunk.numbers();
+    let (tx, rx) = mpsc::channel(numbers.len().max(1));
+    for number in numbers {
+        let tx = tx.clone();
+        let source = source.clone();
+        let method = request.method.clone();
+        let params = request.params(number)?;
+        task::spawn(async move {
+            let _permit = source.permit_request(RequestPriority::Bulk).await;
+            let result = source
+                .provider
+                .request::<_, serde_json::Value>(&method, &params)
+                .await
+                .map_err(|e| CollectError::rpc("raw_rpc", e))
+                .map(|response| (number, params.to_string(), response));
+            match tx.send(result).await {
+                Ok(_) => {}
+                Err(tokio::sync::mpsc::error::SendError(_e)) => {
+                    eprintln!("send error, try using a rate limit with --requests-per-second or limiting max concurrency with --max-concurrent-requests");
+                    std::process::exit(1)
+                }
+            }
+        });
+    }
+    Ok(rx)
+}
+
+async fn raw_responses_to_df(
+    mut responses: mpsc::Receiver<Result<RawResponse, CollectError>>,
+    schema: &Table,
+    source: &Source,
+) -> Result<DataFrame, CollectError> {
+    let method_name = source.raw_rpc.as_ref().map(|request| request.method.clone());
+    let mut block_number: Vec<u32> = Vec::new();
+    let mut params: Vec<String> = Vec::new();
+    let mut response: Vec<String> = Vec::new();
+    while let Some(message) = responses.recv().await {
+        let (number, rendered, value) = message?;
+        block_number.push(number as u32);
+        params.push(rendered);
+        response.push(value.to_string());
+    }
+
+    let n_rows = block_number.len();
+    let mut cols = Vec::new();
+    with_series!(cols, "block_number", block_number, schema);
+    if schema.has_column("method") {
+        cols.push(Series::new("method", vec![method_name.unwrap_or_default(); n_rows]));
+    }
+    with_series!(cols, "params", params, schema);
+    with_series!(cols, "response", response, schema);
+    if schema.has_column("chain_id") {
+        cols.push(Series::new("chain_id", vec![source.chain_id; n_rows]));
+    }
+
+    DataFrame::new(cols).map_err(CollectError::PolarsError).sort_by_schema(schema)
+}
@@ -0,0 +1,325 @@
+use std::collections::HashMap;
+
+use ethers::{
+    abi::{self, ParamType, Token},
+    prelude::*,
+};
+use polars::prelude::*;
+use tokio::{sync::mpsc, task};
+
+use super::logs;
+use crate::{
+    dataframes::SortableDataFrame,
+    types::{
+        conversions::{checked_u64, NarrowU32, ToVecHex},
+        BlockChunk, CollectError, ColumnType, Dataset, Datatype, RequestPriority, RowFilter,
+        SafeTransactions, Source, Table, TransactionChunk,
+    },
+    with_series, with_series_binary, with_series_u32,
+};
+
+/// topic0 of ExecutionSuccess(bytes32,uint256)
+const EXECUTION_SUCCESS_TOPIC: &str =
+    "0x442e715f626346e8c54381002da614f62bee8d27386535b2521ec8540898556e";
+
+/// topic0 of ExecutionFailure(bytes32,uint256)
+const EXECUTION_FAILURE_TOPIC: &str =
+    "0x23428b18acfb3ea64b08dc0c1d296ea9c09702c09083ca5272e64d115b687d23";
+
+/// selector of
+/// execTransaction(address,uint256,bytes,uint8,uint256,uint256,uint256,address,address,bytes)
+const EXEC_TRANSACTION_SELECTOR: [u8; 4] = [0x6a, 0x76, 0x12, 0x02];
+
+/// columns decoded from execTransaction calldata, which requires fetching each transaction
+const CALLDATA_COLUMNS: [&str; 8] = [
+    "to_address",
+    "value",
+    "data",
+    "operation",
+    "safe_tx_gas",
+    "gas_token",
+    "refund_receiver",
+    "n_signatures",
+];
+
+#[async_trait::async_trait]
+impl Dataset for SafeTransactions {
+    fn datatype(&self) -> Datatype {
+        Datatype::SafeTransactions
+    }
+
+    fn name(&self) -> &'static str {
+        "safe_transactions"
+    }
+
+    fn column_types(&self) -> HashMap<&'static str, ColumnType> {
+        HashMap::from_iter(vec![
+            ("block_number", ColumnType::UInt32),
+            ("transaction_index", ColumnType::UInt32),
+            ("log_index", ColumnType::UInt32),
+            ("transaction_hash", ColumnType::Binary),
+            ("safe_address", ColumnType::Binary),
+            ("safe_tx_hash", ColumnType::Binary),
+            ("success", ColumnType::Boolean),
+            ("payment", ColumnType::Decimal128),
+            ("to_address", ColumnType::Binary),
+            ("value", ColumnType::Decimal128),
+            ("data", ColumnType::Binary),
+            ("operation", ColumnType::UInt32),
+            ("safe_tx_gas", ColumnType::UInt64),
+            ("gas_token", ColumnType::Binary),
+            ("refund_receiver", ColumnType::Binary),
+            ("n_signatures", ColumnType::UInt32),
+            ("chain_id", ColumnType::UInt64),
+        ])
+    }
+
+    fn default_columns(&self) -> Vec<&'static str> {
+        vec![
+            "block_number",
+            "transaction_index",
+            "log_index",
+            "transaction_hash",
+            "safe_address",
+            "safe_tx_hash",
+            "success",
+            "to_address",
+            "value",
+            "operation",
+        ]
+    }
+
+    fn default_sort(&self) -> Vec<String> {
+        vec!["block_number".to_string(), "log_index".to_string()]
+    }
+
+    fn nullable_columns(&self) -> Vec<&'static str> {
+        vec![
+            "to_address",
+            "value",
+            "data",
+            "operation",
+            "safe_tx_gas",
+            "gas_token",
+            "refund_receiver",
+            "n_signatures",
+        ]
+    }
+
+    fn column_descriptions(&self) -> HashMap<&'static str, &'static str> {
+        HashMap::from_iter(vec![
+            ("to_address", "target of the safe transaction"),
+            ("value", "wei sent by the safe transaction"),
+            ("data", "calldata of the safe transaction"),
+        ])
+    }
+
+    async fn collect_block_chunk(
+        &self,
+        chunk: &BlockChunk,
+        source: &Source,
+        schema: &Table,
+        filter: Option<&RowFilter>,
+    ) -> Result<DataFrame, CollectError> {
+        let filter =
+            logs::event_filter(filter, &[EXECUTION_SUCCESS_TOPIC, EXECUTION_FAILURE_TOPIC]);
+        let rx = logs::fetch_block_logs(chunk, source, Some(&filter)).await;
+        safe_transactions_to_df(rx, source, schema).await
+    }
+
+    async fn collect_transaction_chunk(
+        &self,
+        chunk: &TransactionChunk,
+        source: &Source,
+        schema: &Table,
+        filter: Option<&RowFilter>,
+    ) -> Result<DataFrame, CollectError> {
+        let filter =
+            logs::event_filter(filter, &[EXECUTION_SUCCESS_TOPIC, EXECUTION_FAILURE_TOPIC]);
+        let rx = logs::fetch_transaction_logs(chunk, source, Some(&filter)).await;
+        safe_transactions_to_df(rx, source, schema).await
+    }
+}
+
+/// arguments of an execTransaction call
+struct ExecTransaction {
+    to: H160,
+    value: U256,
+    data: Vec<u8>,
+    operation: u32,
+    safe_tx_gas: u64,
+    gas_token: H160,
+    refund_receiver: H160,
+    n_signatures: u32,
+}
+
+/// decode execTransaction calldata of a transaction sent directly to a safe
+fn decode_exec_transaction(tx: &Transaction, safe: H160) -> Option<ExecTransaction> {
+    if tx.to != Some(safe) || tx.input.len() < 4 || tx.input[..4] != EXEC_TRANSACTION_SELECTOR {
+        return None
+    }
+    let params = [
+        ParamType::Address,
+        ParamType::Uint(256),
+        ParamType::Bytes,
+        ParamType::Uint(8),
+        ParamType::Uint(256),
+        ParamType::Uint(256),
+        ParamType::Uint(256),
+        ParamType::Address,
+        ParamType::Address,
+        ParamType::Bytes,
+    ];
+    let mut tokens = abi::decode(&params, &tx.input[4..]).ok()?.into_iter();
+    let mut next = || tokens.next();
+    let to = next()?.into_address()?;
+    let value = next()?.into_uint()?;
+    let data = next()?.into_bytes()?;
+    let operation = next()?.into_uint()?.low_u32();
+    let safe_tx_gas = next()?.into_uint()?.low_u64();
+    let (_base_gas, _gas_price) = (next()?, next()?);
+    let gas_token = next()?.into_address()?;
+    let refund_receiver = next()?.into_address()?;
+    let signatures = match next()? {
+        Token::Bytes(signatures) => signatures,
+        _ => return None,
+    };
+    Some(ExecTransaction {
+        to,
+        value,
+        data,
+        operation,
+        safe_tx_gas,
+        gas_token,
+        refund_receiver,
+        // each signature is 65 bytes, contract signatures append dynamic data after them
+        n_signatures: (signatures.len() / 65) as u32,
+    })
+}
+
+/// fetch each transaction that emitted a safe execution event
+async fn get_transactions(
+    tx_hashes: Vec<H256>,
+    source: &Source,
+) -> Result<HashMap<H256, Transaction>, CollectError> {
+    let mut tasks = Vec::new();
+    for tx_hash in tx_hashes.into_iter() {
+        let provider = source.provider.clone();
+        let source = source.clone();
+        let task = task::spawn(async move {
+            let _permit = source.permit_request(RequestPriority::Bulk).await;
+            provider
+                .get_transaction(tx_hash)
+                .await
+                .map_err(|e| CollectError::rpc("eth_getTransactionByHash", e))
+        });
+        tasks.push(task);
+    }
+
+    let mut transactions = HashMap::new();
+    for task in tasks {
+        match task.await {
+            Ok(Ok(Some(tx))) => {
+                transactions.insert(tx.hash, tx);
+            }
+            Ok(Ok(None)) => {}
+            Ok(Err(e)) => return Err(e),
+            Err(_e) => return Err(CollectError::CollectError("transaction task failed".into())),
+        }
+    }
+    Ok(transactions)
+}
+
+async fn safe_transactions_to_df(
+    mut logs: mpsc::Receiver<Result<Vec<Log>, CollectError>>,
+    source: &Source,
+    schema: &Table,
+) -> Result<DataFrame, CollectError> {
+    let success_topic: H256 = EXECUTION_SUCCESS_TOPIC.parse().expect("valid success topic");
+    let failure_topic: H256 = EXECUTION_FAILURE_TOPIC.parse().expect("valid failure topic");
+    let mut executions: Vec<Log> = Vec::new();
+    while let Some(message) = logs.recv().await {
+        executions.extend(message?.into_iter().filter(|log| {
+            log.removed != Some(true) &&
+                log.topics.len() == 1 &&
+                (log.topics[0] == success_topic || log.topics[0] == failure_topic) &&
+                log.data.len() == 64
+        }));
+    }
+
+    let transactions = match CALLDATA_COLUMNS.iter().any(|column| schema.has_column(column)) {
+        true => {
+            let mut tx_hashes: Vec<H256> =
+                executions.iter().filter_map(|log| log.transaction_hash).collect();
+            tx_hashes.sort();
+            tx_hashes.dedup();
+            get_transactions(tx_hashes, source).await?
+        }
+        false => HashMap::new(),
+    };
+
+    let n_rows = executions.len();
+    let mut block_number: Vec<u64> = Vec::with_capacity(n_rows);
+    let mut transaction_index: Vec<u64> = Vec::with_capacity(n_rows);
+    let mut log_index: Vec<u64> = Vec::with_capacity(n_rows);
+    let mut transaction_hash: Vec<Vec<u8>> = Vec::with_capacity(n_rows);
+    let mut safe_address: Vec<Vec<u8>> = Vec::with_capacity(n_rows);
+    let mut safe_tx_hash: Vec<Vec<u8>> = Vec::with_capacity(n_rows);
+    let mut success: Vec<bool> = Vec::with_capacity(n_rows);
+    let mut payment: Vec<String> = Vec::with_capacity(n_rows);
+    let mut to_address: Vec<Option<Vec<u8>>> = Vec::with_capacity(n_rows);
+    let mut value: Vec<Option<String>> = Vec::with_capacity(n_rows);
+    let mut data: Vec<Option<Vec<u8>>> = Vec::with_capacity(n_rows);
+    let mut operation: Vec<Option<u32>> = Vec::with_capacity(n_rows);
+    let mut safe_tx_gas: Vec<Option<u64>> = Vec::with_capacity(n_rows);
+    let mut gas_token: Vec<Option<Vec<u8>>> = Vec::with_capacity(n_rows);
+    let mut refund_receiver: Vec<Option<Vec<u8>>> = Vec::with_capacity(n_rows);
+    let mut n_signatures: Vec<Option<u32>> = Vec::with_capacity(n_rows);
+    for log in executions.iter() {
+        block_number.push(log.block_number.map(|n| n.as_u64()).unwrap_or_default());
+        transaction_index.push(log.transaction_index.map(|i| i.as_u64()).unwrap_or_default());
+        log_index.push(checked_u64(log.log_index.unwrap_or_default(), "log_index")?);
+        transaction_hash.push(log.transaction_hash.unwrap_or_default().as_bytes().to_vec());
+        safe_address.push(log.address.as_bytes().to_vec());
+        safe_tx_hash.push(log.data[..32].to_vec());
+        success.push(log.topics[0] == success_topic);
+        payment.push(U256::from_big_endian(&log.data[32..]).to_string());
+
+        // calldata is only decodable when the safe was called directly, not via a module
+        let exec = log
+            .transaction_hash
+            .and_then(|tx_hash| transactions.get(&tx_hash))
+            .and_then(|tx| decode_exec_transaction(tx, log.address));
+        to_address.push(exec.as_ref().map(|exec| exec.to.as_bytes().to_vec()));
+        value.push(exec.as_ref().map(|exec| exec.value.to_string()));
+        data.push(exec.as_ref().map(|exec| exec.data.clone()));
+        operation.push(exec.as_ref().map(|exec| exec.operation));
+        safe_tx_gas.push(exec.as_ref().map(|exec| exec.safe_tx_gas));
+        gas_token.push(exec.as_ref().map(|exec| exec.gas_token.as_bytes().to_vec()));
+        refund_receiver.push(exec.as_ref().map(|exec| exec.refund_receiver.as_bytes().to_vec()));
+        n_signatures.push(exec.as_ref().map(|exec| exec.n_signatures));
+    }
+
+    let mut cols = Vec::new();
+    with_series_u32!(cols, "block_number", block_number, schema);
+    with_series_u32!(cols, "transaction_index", transaction_index, schema);
+    with_series_u32!(cols, "log_index", log_index, schema);
+    with_series_binary!(cols, "transaction_hash", transaction_hash, schema);
+    with_series_binary!(cols, "safe_address", safe_address, schema);
+    with_series_binary!(cols, "safe_tx_hash", safe_tx_hash, schema);
+    with_series!(cols, "success", success, schema);
+    with_series!(cols, "payment", payment, schema);
+    with_series_binary!(cols, "to_address", to_address, schema);
+    with_series!(cols, "value", value, schema);
+    with_series_binary!(cols, "data", data, schema);
+    with_series!(cols, "operation", operation, schema);
+    with_series!(cols, "safe_tx_gas", safe_tx_gas, schema);
+    with_series_binary!(cols, "gas_token", gas_token, schema);
+    with_series_binary!(cols, "refund_receiver", refund_receiver, schema);
+    with_series!(cols, "n_signatures", n_signatures, schema);
+    if schema.has_column("chain_id") {
+        cols.push(Series::new("chain_id", vec![source.chain_id; n_rows]));
+    }
+
+    DataFrame::new(cols).map_err(CollectError::PolarsError).sort_by_schema(schema)
+}
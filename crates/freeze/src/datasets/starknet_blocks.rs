@@ -0,0 +1,160 @@
+use std::{collections::HashMap, sync::Arc};
+
+use polars::prelude::*;
+use tokio::{sync::mpsc, task};
+
+use crate::{
+    dataframes::SortableDataFrame,
+    types::{
+        conversions::ToVecHex,
+        starknet::{felt_to_bytes, get_starknet_block},
+        BlockChunk, CollectError, ColumnType, Dataset, Datatype, RequestPriority, RowFilter,
+        Source, StarknetBlock, StarknetBlocks, Table, Vm,
+    },
+    with_series, with_series_binary,
+};
+
+#[async_trait::async_trait]
+impl Dataset for StarknetBlocks {
+    fn datatype(&self) -> Datatype {
+        Datatype::StarknetBlocks
+    }
+
+    fn name(&self) -> &'static str {
+        "starknet_blocks"
+    }
+
+    fn column_types(&self) -> HashMap<&'static str, ColumnType> {
+        HashMap::from_iter(vec![
+            ("block_number", ColumnType::UInt64),
+            ("block_hash", ColumnType::Binary),
+            ("parent_hash", ColumnType::Binary),
+            ("new_root", ColumnType::Binary),
+            ("timestamp", ColumnType::UInt64),
+            ("sequencer_address", ColumnType::Binary),
+            ("status", ColumnType::String),
+            ("starknet_version", ColumnType::String),
+            ("l1_gas_price_wei", ColumnType::String),
+            ("l1_gas_price_fri", ColumnType::String),
+            ("n_transactions", ColumnType::UInt32),
+            ("chain_id", ColumnType::UInt64),
+        ])
+    }
+
+    fn default_columns(&self) -> Vec<&'static str> {
+        vec![
+            "block_number",
+            "block_hash",
+            "timestamp",
+            "sequencer_address",
+            "status",
+            "starknet_version",
+            "n_transactions",
+        ]
+    }
+
+    fn default_sort(&self) -> Vec<String> {
+        vec!["block_number".to_string()]
+    }
+
+    fn nullable_columns(&self) -> Vec<&'static str> {
+        vec!["status", "starknet_version", "l1_gas_price_wei", "l1_gas_price_fri"]
+    }
+
+    fn column_descriptions(&self) -> HashMap<&'static str, &'static str> {
+        HashMap::from_iter(vec![("status", "acceptance status of the block, e.g. ACCEPTED_ON_L1")])
+    }
+
+    fn vm(&self) -> Vm {
+        Vm::Starknet
+    }
+
+    async fn collect_block_chunk(
+        &self,
+        chunk: &BlockChunk,
+        source: &Source,
+        schema: &Table,
+        _filter: Option<&RowFilter>,
+    ) -> Result<DataFrame, CollectError> {
+        let rx = fetch_starknet_blocks(chunk, source).await;
+        starknet_blocks_to_df(rx, schema, source.chain_id).await
+    }
+}
+
+pub(crate) async fn fetch_starknet_blocks(
+    block_chunk: &BlockChunk,
+    source: &Source,
+) -> mpsc::Receiver<Result<StarknetBlock, CollectError>> {
+    let (tx, rx) = mpsc::channel(block_chunk.numbers().len());
+
+    for number in block_chunk.numbers() {
+        let tx = tx.clone();
+        let provider = Arc::clone(&source.provider);
+        let source = source.clone();
+        task::spawn(async move {
+            let _permit = source.permit_request(RequestPriority::Bulk).await;
+            let result = get_starknet_block(&provider, number).await;
+            match tx.send(result).await {
+                Ok(_) => {}
+                Err(tokio::sync::mpsc::error::SendError(_e)) => {
+                    eprintln!("send error, try using a rate limit with --requests-per-second or limiting max concurrency with --max-concurrent-requests");
+                    std::process::exit(1)
+                }
+            }
+        });
+    }
+    rx
+}
+
+async fn starknet_blocks_to_df(
+    mut blocks: mpsc::Receiver<Result<StarknetBlock, CollectError>>,
+    schema: &Table,
+    chain_id: u64,
+) -> Result<DataFrame, CollectError> {
+    let mut block_number: Vec<u64> = Vec::new();
+    let mut block_hash: Vec<Vec<u8>> = Vec::new();
+    let mut parent_hash: Vec<Vec<u8>> = Vec::new();
+    let mut new_root: Vec<Vec<u8>> = Vec::new();
+    let mut timestamp: Vec<u64> = Vec::new();
+    let mut sequencer_address: Vec<Vec<u8>> = Vec::new();
+    let mut status: Vec<Option<String>> = Vec::new();
+    let mut starknet_version: Vec<Option<String>> = Vec::new();
+    let mut l1_gas_price_wei: Vec<Option<String>> = Vec::new();
+    let mut l1_gas_price_fri: Vec<Option<String>> = Vec::new();
+    let mut n_transactions: Vec<u32> = Vec::new();
+
+    while let Some(message) = blocks.recv().await {
+        let block = message?;
+        let gas_price = block.l1_gas_price.as_ref();
+        block_number.push(block.block_number);
+        block_hash.push(felt_to_bytes(&block.block_hash));
+        parent_hash.push(felt_to_bytes(&block.parent_hash));
+        new_root.push(felt_to_bytes(&block.new_root));
+        timestamp.push(block.timestamp);
+        sequencer_address.push(felt_to_bytes(&block.sequencer_address));
+        l1_gas_price_wei.push(gas_price.and_then(|p| p.price_in_wei).map(|p| p.to_string()));
+        l1_gas_price_fri.push(gas_price.and_then(|p| p.price_in_fri).map(|p| p.to_string()));
+        n_transactions.push(block.transactions.len() as u32);
+        status.push(block.status);
+        starknet_version.push(block.starknet_version);
+    }
+
+    let n_rows = block_number.len();
+    let mut cols = Vec::new();
+    with_series!(cols, "block_number", block_number, schema);
+    with_series_binary!(cols, "block_hash", block_hash, schema);
+    with_series_binary!(cols, "parent_hash", parent_hash, schema);
+    with_series_binary!(cols, "new_root", new_root, schema);
+    with_series!(cols, "timestamp", timestamp, schema);
+    with_series_binary!(cols, "sequencer_address", sequencer_address, schema);
+    with_series!(cols, "status", status, schema);
+    with_series!(cols, "starknet_version", starknet_version, schema);
+    with_series!(cols, "l1_gas_price_wei", l1_gas_price_wei, schema);
+    with_series!(cols, "l1_gas_price_fri", l1_gas_price_fri, schema);
+    with_series!(cols, "n_transactions", n_transactions, schema);
+    if schema.has_column("chain_id") {
+        cols.push(Series::new("chain_id", vec![chain_id; n_rows]));
+    }
+
+    DataFrame::new(cols).map_err(CollectError::PolarsError).sort_by_schema(schema)
+}
@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+
+use polars::prelude::*;
+use tokio::sync::mpsc;
+
+use super::starknet_blocks;
+use crate::{
+    dataframes::SortableDataFrame,
+    types::{
+        conversions::ToVecHex, starknet::felt_to_bytes, BlockChunk, CollectError, ColumnType,
+        Dataset, Datatype, RowFilter, Source, StarknetBlock, StarknetTransactions, Table, Vm,
+    },
+    with_series, with_series_binary,
+};
+
+#[async_trait::async_trait]
+impl Dataset for StarknetTransactions {
+    fn datatype(&self) -> Datatype {
+        Datatype::StarknetTransactions
+    }
+
+    fn name(&self) -> &'static str {
+        "starknet_transactions"
+    }
+
+    fn column_types(&self) -> HashMap<&'static str, ColumnType> {
+        HashMap::from_iter(vec![
+            ("block_number", ColumnType::UInt64),
+            ("transaction_index", ColumnType::UInt32),
+            ("transaction_hash", ColumnType::Binary),
+            ("transaction_type", ColumnType::String),
+            ("version", ColumnType::UInt64),
+            ("sender_address", ColumnType::Binary),
+            ("nonce", ColumnType::UInt64),
+            ("max_fee", ColumnType::String),
+            ("entry_point_selector", ColumnType::Binary),
+            ("class_hash", ColumnType::Binary),
+            ("calldata", ColumnType::Binary),
+            ("n_calldata", ColumnType::UInt32),
+            ("signature", ColumnType::Binary),
+            ("chain_id", ColumnType::UInt64),
+        ])
+    }
+
+    fn default_columns(&self) -> Vec<&'static str> {
+        vec![
+            "block_number",
+            "transaction_index",
+            "transaction_hash",
+            "transaction_type",
+            "version",
+            "sender_address",
+            "nonce",
+            "max_fee",
+            "n_calldata",
+        ]
+    }
+
+    fn default_sort(&self) -> Vec<String> {
+        vec!["block_number".to_string(), "transaction_index".to_string()]
+    }
+
+    fn nullable_columns(&self) -> Vec<&'static str> {
+        vec!["version", "sender_address", "nonce", "max_fee", "entry_point_selector", "class_hash"]
+    }
+
+    fn column_descriptions(&self) -> HashMap<&'static str, &'static str> {
+        HashMap::from_iter(vec![
+            ("transaction_type", "starknet transaction type, e.g. INVOKE"),
+            ("signature", "signature felts, as 32 byte words"),
+            ("nonce", "nonce of the sender"),
+        ])
+    }
+
+    fn vm(&self) -> Vm {
+        Vm::Starknet
+    }
+
+    async fn collect_block_chunk(
+        &self,
+        chunk: &BlockChunk,
+        source: &Source,
+        schema: &Table,
+        _filter: Option<&RowFilter>,
+    ) -> Result<DataFrame, CollectError> {
+        let rx = starknet_blocks::fetch_starknet_blocks(chunk, source).await;
+        starknet_transactions_to_df(rx, schema, source.chain_id).await
+    }
+}
+
+/// flatten starknet blocks into transactions
+///
+/// calldata and signature felts are concatenated as 32 byte words, sender_address is the
+/// contract address for transactions without a sender, e.g. l1 handler transactions
+async fn starknet_transactions_to_df(
+    mut blocks: mpsc::Receiver<Result<StarknetBlock, CollectError>>,
+    schema: &Table,
+    chain_id: u64,
+) -> Result<DataFrame, CollectError> {
+    let mut block_number: Vec<u64> = Vec::new();
+    let mut transaction_index: Vec<u32> = Vec::new();
+    let mut transaction_hash: Vec<Vec<u8>> = Vec::new();
+    let mut transaction_type: Vec<String> = Vec::new();
+    let mut version: Vec<Option<u64>> = Vec::new();
+    let mut sender_address: Vec<Option<Vec<u8>>> = Vec::new();
+    let mut nonce: Vec<Option<u64>> = Vec::new();
+    let mut max_fee: Vec<Option<String>> = Vec::new();
+    let mut entry_point_selector: Vec<Option<Vec<u8>>> = Vec::new();
+    let mut class_hash: Vec<Option<Vec<u8>>> = Vec::new();
+    let mut calldata: Vec<Vec<u8>> = Vec::new();
+    let mut n_calldata: Vec<u32> = Vec::new();
+    let mut signature: Vec<Vec<u8>> = Vec::new();
+
+    while let Some(message) = blocks.recv().await {
+        let block = message?;
+        for (i, tx) in block.transactions.into_iter().enumerate() {
+            block_number.push(block.block_number);
+            transaction_index.push(i as u32);
+            transaction_hash.push(felt_to_bytes(&tx.transaction_hash));
+            transaction_type.push(tx.transaction_type);
+            version.push(tx.version.map(|value| value.low_u64()));
+            sender_address
+                .push(tx.sender_address.or(tx.contract_address).map(|a| felt_to_bytes(&a)));
+            nonce.push(tx.nonce.map(|value| value.low_u64()));
+            max_fee.push(tx.max_fee.map(|value| value.to_string()));
+            entry_point_selector.push(tx.entry_point_selector.map(|s| felt_to_bytes(&s)));
+            class_hash.push(tx.class_hash.map(|h| felt_to_bytes(&h)));
+            n_calldata.push(tx.calldata.len() as u32);
+            calldata.push(tx.calldata.iter().flat_map(felt_to_bytes).collect());
+            signature.push(tx.signature.iter().flat_map(felt_to_bytes).collect());
+        }
+    }
+
+    let n_rows = transaction_hash.len();
+    let mut cols = Vec::new();
+    with_series!(cols, "block_number", block_number, schema);
+    with_series!(cols, "transaction_index", transaction_index, schema);
+    with_series_binary!(cols, "transaction_hash", transaction_hash, schema);
+    with_series!(cols, "transaction_type", transaction_type, schema);
+    with_series!(cols, "version", version, schema);
+    with_series_binary!(cols, "sender_address", sender_address, schema);
+    with_series!(cols, "nonce", nonce, schema);
+    with_series!(cols, "max_fee", max_fee, schema);
+    with_series_binary!(cols, "entry_point_selector", entry_point_selector, schema);
+    with_series_binary!(cols, "class_hash", class_hash, schema);
+    with_series_binary!(cols, "calldata", calldata, schema);
+    with_series!(cols, "n_calldata", n_calldata, schema);
+    with_series_binary!(cols, "signature", signature, schema);
+    if schema.has_column("chain_id") {
+        cols.push(Series::new("chain_id", vec![chain_id; n_rows]));
+    }
+
+    DataFrame::new(cols).map_err(CollectError::PolarsError).sort_by_schema(schema)
+}
@@ -8,7 +8,7 @@ use crate::{
     dataframes::SortableDataFrame,
     types::{
         conversions::ToVecHex, BlockChunk, ChunkData, CollectError, ColumnType, Datatype,
-        MultiDataset, RowFilter, Source, StateDiffs, Table,
+        MultiDataset, RequestPriority, RowFilter, Source, StateDiffs, Table,
     },
     with_series, with_series_binary,
 };
@@ -30,10 +30,10 @@ impl MultiDataset for StateDiffs {
         chunk: &BlockChunk,
         source: &Source,
         schemas: HashMap<Datatype, Table>,
-        _filter: HashMap<Datatype, RowFilter>,
+        filter: HashMap<Datatype, RowFilter>,
     ) -> Result<HashMap<Datatype, DataFrame>, CollectError> {
         let rx = fetch_state_diffs(chunk, source).await;
-        state_diffs_to_df(rx, &schemas, source.chain_id).await
+        state_diffs_to_df(rx, &schemas, &filter, source.chain_id).await
     }
 }
 
@@ -42,12 +42,16 @@ pub(crate) async fn collect_single(
     chunk: &BlockChunk,
     source: &Source,
     schema: &Table,
-    _filter: Option<&RowFilter>,
+    filter: Option<&RowFilter>,
 ) -> Result<DataFrame, CollectError> {
     let rx = fetch_state_diffs(chunk, source).await;
     let mut schemas: HashMap<Datatype, Table> = HashMap::new();
     schemas.insert(*datatype, schema.clone());
-    let dfs = state_diffs_to_df(rx, &schemas, source.chain_id).await;
+    let mut filters: HashMap<Datatype, RowFilter> = HashMap::new();
+    if let Some(filter) = filter {
+        filters.insert(*datatype, filter.clone());
+    }
+    let dfs = state_diffs_to_df(rx, &schemas, &filters, source.chain_id).await;
 
     // get single df out of result
     let df = match dfs {
@@ -70,21 +74,14 @@ pub(crate) async fn fetch_block_traces(
     for number in block_chunk.numbers() {
         let tx = tx.clone();
         let provider = source.provider.clone();
-        let semaphore = source.semaphore.clone();
-        let rate_limiter = source.rate_limiter.as_ref().map(Arc::clone);
+        let source = source.clone();
         let trace_types = trace_types.to_vec();
         tokio::spawn(async move {
-            let _permit = match semaphore {
-                Some(semaphore) => Some(Arc::clone(&semaphore).acquire_owned().await),
-                _ => None,
-            };
-            if let Some(limiter) = rate_limiter {
-                Arc::clone(&limiter).until_ready().await;
-            }
+            let _permit = source.permit_request(RequestPriority::Bulk).await;
             let result = provider
                 .trace_replay_block_transactions(BlockNumber::Number(number.into()), trace_types)
                 .await
-                .map_err(CollectError::ProviderError);
+                .map_err(|e| CollectError::rpc("trace_replayBlockTransactions", e));
             match tx.send((number as u32, result)).await {
                 Ok(_) => {}
                 Err(tokio::sync::mpsc::error::SendError(_e)) => {
@@ -108,6 +105,7 @@ pub(crate) async fn fetch_state_diffs(
 async fn state_diffs_to_df(
     mut rx: mpsc::Receiver<(u32, Result<Vec<BlockTrace>, CollectError>)>,
     schemas: &HashMap<Datatype, Table>,
+    filters: &HashMap<Datatype, RowFilter>,
     chain_id: u64,
 ) -> Result<HashMap<Datatype, DataFrame>, CollectError> {
     let include_storage = schemas.contains_key(&Datatype::StorageDiffs);
@@ -193,9 +191,10 @@ async fn state_diffs_to_df(
                     {
                         for (addr, addr_diff) in state_diff.iter() {
                             n_rows += n_rows;
+                            let kept = |datatype| includes_address(filters, datatype, addr);
 
                             // storage
-                            if include_storage {
+                            if include_storage && kept(Datatype::StorageDiffs) {
                                 for (s, diff) in addr_diff.storage.iter() {
                                     let (from, to) = match diff {
                                         Diff::Same => (H256::zero(), H256::zero()),
@@ -228,7 +227,7 @@ async fn state_diffs_to_df(
                             }
 
                             // balance
-                            if include_balance {
+                            if include_balance && kept(Datatype::BalanceDiffs) {
                                 let (from, to) = match addr_diff.balance {
                                     Diff::Same => ("0".to_string(), "0".to_string()),
                                     Diff::Born(value) => ("0".to_string(), value.to_string()),
@@ -258,7 +257,7 @@ async fn state_diffs_to_df(
                             }
 
                             // nonce
-                            if include_nonce {
+                            if include_nonce && kept(Datatype::NonceDiffs) {
                                 let (from, to) = match addr_diff.nonce {
                                     Diff::Same => (0u64, 0u64),
                                     Diff::Born(value) => (0u64, value.as_u64()),
@@ -288,7 +287,7 @@ async fn state_diffs_to_df(
                             }
 
                             // code
-                            if include_code {
+                            if include_code && kept(Datatype::CodeDiffs) {
                                 let (from, to) = match &addr_diff.code {
                                     Diff::Same => (
                                         H256::zero().as_bytes().to_vec(),
@@ -327,7 +326,7 @@ async fn state_diffs_to_df(
                     }
                 }
             }
-            _ => return Err(CollectError::TooManyRequestsError),
+            (_, Err(e)) => return Err(e),
         }
     }
 
@@ -416,3 +415,12 @@ fn included(
         false
     }
 }
+
+/// whether diffs of an address are kept by the row filter of a datatype
+fn includes_address(
+    filters: &HashMap<Datatype, RowFilter>,
+    datatype: Datatype,
+    address: &H160,
+) -> bool {
+    filters.get(&datatype).map(|filter| filter.includes_address(address)).unwrap_or(true)
+}
@@ -1,4 +1,4 @@
-use std::{collections::HashMap, sync::Arc};
+use std::collections::{HashMap, HashSet};
 
 use ethers::prelude::*;
 use polars::prelude::*;
@@ -7,10 +7,11 @@ use tokio::{sync::mpsc, task};
 use crate::{
     dataframes::SortableDataFrame,
     types::{
-        conversions::ToVecHex, BlockChunk, CollectError, ColumnType, Dataset, Datatype, RowFilter,
+        conversions::{checked_u64, NarrowU32, ToVecHex},
+        BlockChunk, CollectError, ColumnType, Dataset, Datatype, RequestPriority, RowFilter,
         Source, Table, Traces,
     },
-    with_series, with_series_binary,
+    with_series, with_series_binary, with_series_u32,
 };
 
 #[async_trait::async_trait]
@@ -39,12 +40,20 @@ impl Dataset for Traces {
             ("result_code", ColumnType::Binary),
             ("result_address", ColumnType::Binary),
             ("trace_address", ColumnType::String),
+            ("parent_trace_address", ColumnType::String),
+            ("depth", ColumnType::UInt32),
+            ("n_children", ColumnType::UInt32),
+            ("is_root", ColumnType::Boolean),
             ("subtraces", ColumnType::UInt32),
             ("transaction_position", ColumnType::UInt32),
             ("transaction_hash", ColumnType::Binary),
             ("block_number", ColumnType::UInt32),
             ("block_hash", ColumnType::Binary),
             ("error", ColumnType::String),
+            ("reverted", ColumnType::Boolean),
+            ("error_class", ColumnType::String),
+            ("revert_reason", ColumnType::String),
+            ("delegate_to", ColumnType::Binary),
             ("chain_id", ColumnType::UInt64),
         ])
     }
@@ -78,16 +87,99 @@ impl Dataset for Traces {
         vec!["block_number".to_string(), "transaction_position".to_string()]
     }
 
+    fn nullable_columns(&self) -> Vec<&'static str> {
+        vec![
+            "action_from",
+            "action_to",
+            "action_gas",
+            "action_input",
+            "action_call_type",
+            "action_init",
+            "action_reward_type",
+            "result_gas_used",
+            "result_output",
+            "result_code",
+            "result_address",
+            "parent_trace_address",
+            "error",
+            "error_class",
+            "revert_reason",
+            "delegate_to",
+        ]
+    }
+
     async fn collect_block_chunk(
         &self,
         chunk: &BlockChunk,
         source: &Source,
         schema: &Table,
-        _filter: Option<&RowFilter>,
+        filter: Option<&RowFilter>,
     ) -> Result<DataFrame, CollectError> {
-        let rx = fetch_traces(chunk, source).await;
-        traces_to_df(rx, schema, source.chain_id).await
+        let addresses = filter.filter(|filter| !filter.collapse_delegatecalls).and_then(|filter| {
+            filter.address.as_ref().map(|address| match address {
+                ValueOrArray::Value(address) => vec![*address],
+                ValueOrArray::Array(addresses) => addresses.clone(),
+            })
+        });
+        let rx = match addresses {
+            Some(addresses) => fetch_address_traces(chunk, source, addresses).await,
+            None => fetch_traces(chunk, source).await,
+        };
+        traces_to_df(rx, schema, source.chain_id, filter).await
+    }
+}
+
+/// fetch the traces sent by or to addresses with trace_filter, instead of every trace of a block
+///
+/// trace_filter combines fromAddress and toAddress differently across clients, so traces sent by
+/// and sent to the addresses are requested separately and merged. collapsing delegatecalls needs
+/// every trace of a transaction, so it still fetches whole blocks
+async fn fetch_address_traces(
+    block_chunk: &BlockChunk,
+    source: &Source,
+    addresses: Vec<H160>,
+) -> mpsc::Receiver<Result<Vec<Trace>, CollectError>> {
+    let request_chunks = block_chunk.to_log_filter_options(&source.inner_request_size);
+    let (tx, rx) = mpsc::channel(request_chunks.len());
+
+    for block_option in request_chunks.into_iter() {
+        let (from_block, to_block) = match block_option {
+            FilterBlockOption::Range { from_block: Some(from), to_block: Some(to) } => (from, to),
+            _ => continue,
+        };
+        let tx = tx.clone();
+        let provider = source.provider.clone();
+        let source = source.clone();
+        let addresses = addresses.clone();
+        task::spawn(async move {
+            let _permit = source.permit_request(RequestPriority::Bulk).await;
+            let filter = TraceFilter::default().from_block(from_block).to_block(to_block);
+            let sent_by = provider.trace_filter(filter.clone().from_address(addresses.clone()));
+            let sent_to = provider.trace_filter(filter.to_address(addresses));
+            let result = match futures::future::try_join(sent_by, sent_to).await {
+                Ok((sent_by, sent_to)) => Ok(merge_traces(sent_by, sent_to)),
+                Err(e) => Err(CollectError::rpc("trace_filter", e)),
+            };
+            match tx.send(result).await {
+                Ok(_) => {}
+                Err(tokio::sync::mpsc::error::SendError(_e)) => {
+                    eprintln!("send error, try using a rate limit with --requests-per-second or limiting max concurrency with --max-concurrent-requests");
+                    std::process::exit(1)
+                }
+            }
+        });
     }
+    rx
+}
+
+/// merge traces of two trace_filter responses, dropping traces present in both
+fn merge_traces(mut traces: Vec<Trace>, other: Vec<Trace>) -> Vec<Trace> {
+    let key = |trace: &Trace| {
+        (trace.block_number, trace.transaction_position, trace.trace_address.clone())
+    };
+    let seen: HashSet<_> = traces.iter().map(key).collect();
+    traces.extend(other.into_iter().filter(|trace| !seen.contains(&key(trace))));
+    traces
 }
 
 async fn fetch_traces(
@@ -99,20 +191,13 @@ async fn fetch_traces(
     for number in block_chunk.numbers() {
         let tx = tx.clone();
         let provider = source.provider.clone();
-        let semaphore = source.semaphore.clone();
-        let rate_limiter = source.rate_limiter.as_ref().map(Arc::clone);
+        let source = source.clone();
         task::spawn(async move {
-            let _permit = match semaphore {
-                Some(semaphore) => Some(Arc::clone(&semaphore).acquire_owned().await),
-                _ => None,
-            };
-            if let Some(limiter) = rate_limiter {
-                Arc::clone(&limiter).until_ready().await;
-            }
+            let _permit = source.permit_request(RequestPriority::Bulk).await;
             let result = provider
                 .trace_block(BlockNumber::Number(number.into()))
                 .await
-                .map_err(CollectError::ProviderError);
+                .map_err(|e| CollectError::rpc("trace_block", e));
             match tx.send(result).await {
                 Ok(_) => {}
                 Err(tokio::sync::mpsc::error::SendError(_e)) => {
@@ -153,11 +238,156 @@ fn action_call_type_to_string(action_call_type: &CallType) -> String {
     }
 }
 
+/// trace address as indices joined by underscores, empty for the root call
+fn join_trace_address(trace_address: &[usize]) -> String {
+    trace_address.iter().map(|n| n.to_string()).collect::<Vec<String>>().join("_")
+}
+
+/// collapse proxy calls whose only subcall delegatecalls an implementation with the same input
+/// and output, returning each trace with the implementation its call was delegated to
+///
+/// the delegatecall is removed and its subcalls move up one level, so trace addresses, depths,
+/// and subtraces describe the collapsed call tree. chains of proxies collapse into one call
+/// annotated with the last implementation
+fn collapse_delegatecalls(traces: Vec<Trace>) -> Vec<(Trace, Option<H160>)> {
+    let mut traces: Vec<(Trace, Option<H160>)> =
+        traces.into_iter().map(|trace| (trace, None)).collect();
+    let mut index = 0;
+    while index < traces.len() {
+        let delegate = traces
+            .get(index + 1)
+            .filter(|(child, _)| is_delegate_wrapper(&traces[index].0, child))
+            .map(|(child, _)| (child.subtraces, child.trace_address.clone(), child.action.clone()));
+        let (subtraces, delegate_address, implementation) = match delegate {
+            Some((subtraces, address, Action::Call(call))) => (subtraces, address, call.to),
+            _ => {
+                index += 1;
+                continue
+            }
+        };
+        traces.remove(index + 1);
+
+        // subcalls of the delegatecall directly follow it, within the same transaction
+        let level = traces[index].0.trace_address.len();
+        let position = traces[index].0.transaction_position;
+        for (trace, _) in traces.iter_mut().skip(index + 1) {
+            if trace.transaction_position != position ||
+                !trace.trace_address.starts_with(&delegate_address)
+            {
+                break
+            }
+            trace.trace_address.remove(level);
+        }
+        traces[index].0.subtraces = subtraces;
+        traces[index].1 = Some(implementation);
+    }
+    traces
+}
+
+/// whether a call only delegatecalls its implementation, passing its input and output through
+fn is_delegate_wrapper(parent: &Trace, child: &Trace) -> bool {
+    let (call, delegate) = match (&parent.action, &child.action) {
+        (Action::Call(call), Action::Call(delegate)) => (call, delegate),
+        _ => return false,
+    };
+    let same_output = match (&parent.result, &child.result) {
+        (Some(Res::Call(a)), Some(Res::Call(b))) => a.output == b.output,
+        (None, None) => true,
+        _ => false,
+    };
+    parent.subtraces == 1 &&
+        parent.transaction_position == child.transaction_position &&
+        child.trace_address.len() == parent.trace_address.len() + 1 &&
+        child.trace_address.starts_with(&parent.trace_address) &&
+        delegate.call_type == CallType::DelegateCall &&
+        delegate.from == call.to &&
+        delegate.input == call.input &&
+        parent.error == child.error &&
+        same_output
+}
+
+/// whether a trace calls, is called by, creates, or destroys an address of the filter
+fn involves_address(trace: &Trace, filter: &RowFilter) -> bool {
+    if filter.address.is_none() {
+        return true
+    }
+    let addresses = match (&trace.action, &trace.result) {
+        (Action::Call(call), _) => vec![call.from, call.to],
+        (Action::Create(create), Some(Res::Create(result))) => vec![create.from, result.address],
+        (Action::Create(create), _) => vec![create.from],
+        (Action::Suicide(suicide), _) => vec![suicide.address, suicide.refund_address],
+        (Action::Reward(reward), _) => vec![reward.author],
+    };
+    addresses.iter().any(|address| filter.includes_address(address))
+}
+
+/// normalized class of a trace error, whose wording differs between clients
+pub(crate) fn classify_error(error: &str) -> &'static str {
+    let error = error.to_lowercase();
+    let class = [
+        ("revert", "revert"),
+        ("out of gas", "out_of_gas"),
+        ("outofgas", "out_of_gas"),
+        ("gas uint64 overflow", "out_of_gas"),
+        ("jump", "invalid_jump"),
+        ("instruction", "invalid_opcode"),
+        ("opcode", "invalid_opcode"),
+        ("stack", "stack_error"),
+        ("static", "static_call_violation"),
+        ("write protection", "static_call_violation"),
+        ("collision", "create_collision"),
+        ("insufficient balance", "insufficient_balance"),
+        ("call depth", "call_depth"),
+        ("built-in", "precompile_failure"),
+        ("precompile", "precompile_failure"),
+        ("code size", "code_size_exceeded"),
+        ("invalid code", "invalid_code"),
+    ]
+    .into_iter()
+    .find(|(pattern, _)| error.contains(pattern));
+    class.map(|(_, class)| class).unwrap_or("other")
+}
+
+/// decode revert output of Error(string) or Panic(uint256), as emitted by solidity
+pub(crate) fn decode_revert(output: &[u8]) -> Option<String> {
+    if output.len() < 4 {
+        return None
+    }
+    let (selector, data) = output.split_at(4);
+    match selector {
+        [0x08, 0xc3, 0x79, 0xa0] => {
+            match ethers::abi::decode(&[ethers::abi::ParamType::String], data).ok()?.pop()? {
+                ethers::abi::Token::String(reason) => Some(reason),
+                _ => None,
+            }
+        }
+        [0x4e, 0x48, 0x7b, 0x71] if data.len() == 32 => {
+            let code = U256::from_big_endian(data).low_u64();
+            let description = match code {
+                0x01 => "assertion failed",
+                0x11 => "arithmetic overflow",
+                0x12 => "division by zero",
+                0x21 => "invalid enum value",
+                0x22 => "invalid storage encoding",
+                0x31 => "pop of empty array",
+                0x32 => "array index out of bounds",
+                0x41 => "out of memory",
+                0x51 => "uninitialized function",
+                _ => "unknown panic",
+            };
+            Some(format!("panic 0x{:02x}: {}", code, description))
+        }
+        _ => None,
+    }
+}
+
 async fn traces_to_df(
     mut rx: mpsc::Receiver<Result<Vec<Trace>, CollectError>>,
     schema: &Table,
     chain_id: u64,
+    filter: Option<&RowFilter>,
 ) -> Result<DataFrame, CollectError> {
+    let collapse = filter.map(|filter| filter.collapse_delegatecalls).unwrap_or(false);
     let include_action_from = schema.has_column("action_from");
     let include_action_to = schema.has_column("action_to");
     let include_action_value = schema.has_column("action_value");
@@ -172,40 +402,63 @@ async fn traces_to_df(
     let include_result_code = schema.has_column("result_code");
     let include_result_address = schema.has_column("result_address");
     let include_trace_address = schema.has_column("trace_address");
+    let include_parent_trace_address = schema.has_column("parent_trace_address");
+    let include_depth = schema.has_column("depth");
+    let include_n_children = schema.has_column("n_children");
+    let include_is_root = schema.has_column("is_root");
     let include_subtraces = schema.has_column("subtraces");
     let include_transaction_position = schema.has_column("transaction_position");
     let include_transaction_hash = schema.has_column("transaction_hash");
     let include_block_number = schema.has_column("block_number");
     let include_block_hash = schema.has_column("block_hash");
     let include_error = schema.has_column("error");
+    let include_reverted = schema.has_column("reverted");
+    let include_error_class = schema.has_column("error_class");
+    let include_revert_reason = schema.has_column("revert_reason");
+    let include_delegate_to = schema.has_column("delegate_to");
 
     let capacity = 0;
     let mut action_from: Vec<Option<Vec<u8>>> = Vec::with_capacity(capacity);
     let mut action_to: Vec<Option<Vec<u8>>> = Vec::with_capacity(capacity);
     let mut action_value: Vec<String> = Vec::with_capacity(capacity);
-    let mut action_gas: Vec<Option<u32>> = Vec::with_capacity(capacity);
+    let mut action_gas: Vec<Option<u64>> = Vec::with_capacity(capacity);
     let mut action_input: Vec<Option<Vec<u8>>> = Vec::with_capacity(capacity);
     let mut action_call_type: Vec<Option<String>> = Vec::with_capacity(capacity);
     let mut action_init: Vec<Option<Vec<u8>>> = Vec::with_capacity(capacity);
     let mut action_reward_type: Vec<Option<String>> = Vec::with_capacity(capacity);
     let mut action_type: Vec<String> = Vec::with_capacity(capacity);
-    let mut result_gas_used: Vec<Option<u32>> = Vec::with_capacity(capacity);
+    let mut result_gas_used: Vec<Option<u64>> = Vec::with_capacity(capacity);
     let mut result_output: Vec<Option<Vec<u8>>> = Vec::with_capacity(capacity);
     let mut result_code: Vec<Option<Vec<u8>>> = Vec::with_capacity(capacity);
     let mut result_address: Vec<Option<Vec<u8>>> = Vec::with_capacity(capacity);
     let mut trace_address: Vec<String> = Vec::with_capacity(capacity);
+    let mut parent_trace_address: Vec<Option<String>> = Vec::with_capacity(capacity);
+    let mut depth: Vec<u32> = Vec::with_capacity(capacity);
+    let mut n_children: Vec<u32> = Vec::with_capacity(capacity);
+    let mut is_root: Vec<bool> = Vec::with_capacity(capacity);
     let mut subtraces: Vec<u32> = Vec::with_capacity(capacity);
     let mut transaction_position: Vec<u32> = Vec::with_capacity(capacity);
     let mut transaction_hash: Vec<Vec<u8>> = Vec::with_capacity(capacity);
-    let mut block_number: Vec<u32> = Vec::with_capacity(capacity);
+    let mut block_number: Vec<u64> = Vec::with_capacity(capacity);
     let mut block_hash: Vec<Vec<u8>> = Vec::with_capacity(capacity);
     let mut error: Vec<Option<String>> = Vec::with_capacity(capacity);
+    let mut reverted: Vec<bool> = Vec::with_capacity(capacity);
+    let mut error_class: Vec<Option<&'static str>> = Vec::with_capacity(capacity);
+    let mut revert_reason: Vec<Option<String>> = Vec::with_capacity(capacity);
+    let mut delegate_to: Vec<Option<Vec<u8>>> = Vec::with_capacity(capacity);
 
     let mut n_rows = 0;
     while let Some(message) = rx.recv().await {
         match message {
             Ok(traces) => {
-                for trace in traces.iter() {
+                let traces = match collapse {
+                    true => collapse_delegatecalls(traces),
+                    false => traces.into_iter().map(|trace| (trace, None)).collect(),
+                };
+                for (trace, implementation) in traces.iter() {
+                    if !filter.map(|filter| involves_address(trace, filter)).unwrap_or(true) {
+                        continue
+                    }
                     if let (Some(tx_hash), Some(tx_pos)) =
                         (trace.transaction_hash, trace.transaction_position)
                     {
@@ -248,7 +501,7 @@ async fn traces_to_df(
                                     action_value.push(a.value.to_string());
                                 }
                                 if include_action_gas {
-                                    action_gas.push(Some(a.gas.as_u32()));
+                                    action_gas.push(Some(checked_u64(a.gas, "action_gas")?));
                                 }
                                 if include_action_input {
                                     action_input.push(Some(a.input.to_vec()));
@@ -273,7 +526,7 @@ async fn traces_to_df(
                                     action_value.push(action.value.to_string());
                                 }
                                 if include_action_gas {
-                                    action_gas.push(Some(action.gas.as_u32()));
+                                    action_gas.push(Some(checked_u64(action.gas, "action_gas")?));
                                 }
                                 if include_action_init {
                                     action_init.push(Some(action.init.to_vec()));
@@ -355,7 +608,8 @@ async fn traces_to_df(
                         match &trace.result {
                             Some(Res::Call(result)) => {
                                 if include_result_gas_used {
-                                    result_gas_used.push(Some(result.gas_used.as_u32()));
+                                    let gas_used = checked_u64(result.gas_used, "result_gas_used")?;
+                                    result_gas_used.push(Some(gas_used));
                                 }
                                 if include_result_output {
                                     result_output.push(Some(result.output.to_vec()));
@@ -370,7 +624,8 @@ async fn traces_to_df(
                             }
                             Some(Res::Create(result)) => {
                                 if include_result_gas_used {
-                                    result_gas_used.push(Some(result.gas_used.as_u32()));
+                                    let gas_used = checked_u64(result.gas_used, "result_gas_used")?;
+                                    result_gas_used.push(Some(gas_used));
                                 }
                                 if include_result_code {
                                     result_code.push(Some(result.code.to_vec()));
@@ -399,15 +654,26 @@ async fn traces_to_df(
                             }
                         }
                         if include_trace_address {
-                            trace_address.push(
+                            trace_address.push(join_trace_address(&trace.trace_address));
+                        }
+                        // the parent of a call is its trace address without the last index
+                        if include_parent_trace_address {
+                            parent_trace_address.push(
                                 trace
                                     .trace_address
-                                    .iter()
-                                    .map(|n| n.to_string())
-                                    .collect::<Vec<String>>()
-                                    .join("_"),
+                                    .split_last()
+                                    .map(|(_, parent)| join_trace_address(parent)),
                             );
                         }
+                        if include_depth {
+                            depth.push(trace.trace_address.len() as u32);
+                        }
+                        if include_n_children {
+                            n_children.push(trace.subtraces as u32);
+                        }
+                        if include_is_root {
+                            is_root.push(trace.trace_address.is_empty());
+                        }
                         if include_subtraces {
                             subtraces.push(trace.subtraces as u32);
                         }
@@ -418,7 +684,7 @@ async fn traces_to_df(
                             transaction_hash.push(tx_hash.as_bytes().to_vec());
                         }
                         if include_block_number {
-                            block_number.push(trace.block_number as u32);
+                            block_number.push(trace.block_number);
                         }
                         if include_block_hash {
                             block_hash.push(trace.block_hash.as_bytes().to_vec());
@@ -426,10 +692,26 @@ async fn traces_to_df(
                         if include_error {
                             error.push(trace.error.clone());
                         }
+                        if include_reverted {
+                            reverted.push(trace.error.is_some());
+                        }
+                        if include_error_class {
+                            error_class.push(trace.error.as_deref().map(classify_error));
+                        }
+                        if include_revert_reason {
+                            let reason = match (&trace.error, &trace.result) {
+                                (Some(_), Some(Res::Call(result))) => decode_revert(&result.output),
+                                _ => None,
+                            };
+                            revert_reason.push(reason);
+                        }
+                        if include_delegate_to {
+                            delegate_to.push(implementation.map(|a| a.as_bytes().to_vec()));
+                        }
                     }
                 }
             }
-            Err(e) => return Err(CollectError::RPCError(e.to_string())),
+            Err(e) => return Err(e),
         }
     }
 
@@ -438,23 +720,31 @@ async fn traces_to_df(
     with_series_binary!(cols, "action_from", action_from, schema);
     with_series_binary!(cols, "action_to", action_to, schema);
     with_series!(cols, "action_value", action_value, schema);
-    with_series!(cols, "action_gas", action_gas, schema);
+    with_series_u32!(cols, "action_gas", action_gas, schema);
     with_series_binary!(cols, "action_input", action_input, schema);
     with_series!(cols, "action_call_type", action_call_type, schema);
     with_series_binary!(cols, "action_init", action_init, schema);
     with_series!(cols, "action_reward_type", action_reward_type, schema);
     with_series!(cols, "action_type", action_type, schema);
-    with_series!(cols, "result_gas_used", result_gas_used, schema);
+    with_series_u32!(cols, "result_gas_used", result_gas_used, schema);
     with_series_binary!(cols, "result_output", result_output, schema);
     with_series_binary!(cols, "result_code", result_code, schema);
     with_series_binary!(cols, "result_address", result_address, schema);
     with_series!(cols, "trace_address", trace_address, schema);
+    with_series!(cols, "parent_trace_address", parent_trace_address, schema);
+    with_series!(cols, "depth", depth, schema);
+    with_series!(cols, "n_children", n_children, schema);
+    with_series!(cols, "is_root", is_root, schema);
     with_series!(cols, "subtraces", subtraces, schema);
     with_series!(cols, "transaction_position", transaction_position, schema);
     with_series_binary!(cols, "transaction_hash", transaction_hash, schema);
-    with_series!(cols, "block_number", block_number, schema);
+    with_series_u32!(cols, "block_number", block_number, schema);
     with_series_binary!(cols, "block_hash", block_hash, schema);
     with_series!(cols, "error", error, schema);
+    with_series!(cols, "reverted", reverted, schema);
+    with_series!(cols, "error_class", error_class, schema);
+    with_series!(cols, "revert_reason", revert_reason, schema);
+    with_series_binary!(cols, "delegate_to", delegate_to, schema);
 
     if schema.has_column("chain_id") {
         cols.push(Series::new("chain_id", vec![chain_id; n_rows]));
@@ -31,11 +31,29 @@ impl Dataset for Transactions {
             ("input", ColumnType::Binary),
             ("gas_limit", ColumnType::UInt32),
             ("gas_used", ColumnType::UInt32),
+            ("intrinsic_gas", ColumnType::UInt64),
+            ("gas_refund", ColumnType::UInt64),
+            ("status", ColumnType::UInt32),
+            ("state_root", ColumnType::Binary),
             ("gas_price", ColumnType::UInt64),
             ("transaction_type", ColumnType::UInt32),
             ("max_priority_fee_per_gas", ColumnType::UInt64),
             ("max_fee_per_gas", ColumnType::UInt64),
             ("chain_id", ColumnType::UInt64),
+            ("transaction_type_name", ColumnType::String),
+            ("transaction_kind", ColumnType::String),
+            ("to_kind", ColumnType::String),
+            ("is_verified", ColumnType::Boolean),
+            ("extra_fields", ColumnType::String),
+            ("fee_currency", ColumnType::Binary),
+            ("gateway_fee_recipient", ColumnType::Binary),
+            ("gateway_fee", ColumnType::String),
+            ("is_state_sync", ColumnType::Boolean),
+            ("request_id", ColumnType::Binary),
+            ("ticket_id", ColumnType::Binary),
+            ("refund_to", ColumnType::Binary),
+            ("max_refund", ColumnType::String),
+            ("submission_fee_refund", ColumnType::String),
         ])
     }
 
@@ -62,6 +80,53 @@ impl Dataset for Transactions {
         vec!["block_number".to_string(), "transaction_index".to_string()]
     }
 
+    fn nullable_columns(&self) -> Vec<&'static str> {
+        vec![
+            "block_number",
+            "transaction_index",
+            "to_address",
+            "gas_refund",
+            "status",
+            "state_root",
+            "gas_price",
+            "transaction_type",
+            "max_priority_fee_per_gas",
+            "max_fee_per_gas",
+            "to_kind",
+            "is_verified",
+            "extra_fields",
+            "fee_currency",
+            "gateway_fee_recipient",
+            "gateway_fee",
+            "is_state_sync",
+            "request_id",
+            "ticket_id",
+            "refund_to",
+            "max_refund",
+            "submission_fee_refund",
+        ]
+    }
+
+    fn column_descriptions(&self) -> HashMap<&'static str, &'static str> {
+        HashMap::from_iter(vec![
+            ("value", "wei sent with the transaction"),
+            ("status", "1 if the transaction succeeded, 0 if it reverted"),
+            ("nonce", "nonce of the sender"),
+            ("gas_used", "gas used by the transaction"),
+            (
+                "intrinsic_gas",
+                "gas charged before execution, null on chains without a known fork schedule",
+            ),
+            (
+                "gas_refund",
+                "gas refunded after execution, from trace_block, null without a top level trace, \
+                on chains without a known fork schedule, or when the EIP-7623 calldata floor \
+                sets gas used",
+            ),
+            ("state_root", "state root after the transaction, before byzantium"),
+        ])
+    }
+
     async fn collect_block_chunk(
         &self,
         chunk: &BlockChunk,
@@ -69,10 +134,17 @@ impl Dataset for Transactions {
         schema: &Table,
         _filter: Option<&RowFilter>,
     ) -> Result<DataFrame, CollectError> {
-        let include_gas_used = schema.has_column("gas_used");
-        let rx =
-            blocks_and_transactions::fetch_blocks_and_transactions(chunk, source, include_gas_used)
-                .await;
+        let include_receipts = blocks_and_transactions::needs_receipts(schema);
+        let include_traces = blocks_and_transactions::needs_traces(schema);
+        let lookups = blocks_and_transactions::RecipientLookups::from_schema(schema);
+        let rx = blocks_and_transactions::fetch_blocks_and_transactions(
+            chunk,
+            source,
+            include_receipts,
+            include_traces,
+            lookups,
+        )
+        .await;
         let output = blocks::blocks_to_dfs(rx, &None, &Some(schema), source.chain_id).await;
         match output {
             Ok((_, Some(txs_df))) => Ok(txs_df),
@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+
+use ethers::prelude::*;
+use polars::prelude::*;
+
+use crate::{
+    dataframes::SortableDataFrame,
+    types::{
+        conversions::ToVecHex, BlockChunk, ChunkData, CollectError, ColumnType, Dataset, Datatype,
+        RequestPriority, RowFilter, Source, Table, Validators,
+    },
+    with_series, with_series_binary,
+};
+
+#[async_trait::async_trait]
+impl Dataset for Validators {
+    fn datatype(&self) -> Datatype {
+        Datatype::Validators
+    }
+
+    fn name(&self) -> &'static str {
+        "validators"
+    }
+
+    fn column_types(&self) -> HashMap<&'static str, ColumnType> {
+        HashMap::from_iter(vec![
+            ("block_number", ColumnType::UInt32),
+            ("slot", ColumnType::UInt64),
+            ("validator_index", ColumnType::UInt64),
+            ("pubkey", ColumnType::Binary),
+            ("withdrawal_credentials", ColumnType::Binary),
+            ("withdrawal_address", ColumnType::Binary),
+            ("status", ColumnType::String),
+            ("chain_id", ColumnType::UInt64),
+        ])
+    }
+
+    fn default_columns(&self) -> Vec<&'static str> {
+        vec![
+            "block_number",
+            "slot",
+            "validator_index",
+            "pubkey",
+            "withdrawal_credentials",
+            "withdrawal_address",
+            "status",
+        ]
+    }
+
+    fn default_sort(&self) -> Vec<String> {
+        vec!["validator_index".to_string()]
+    }
+
+    fn nullable_columns(&self) -> Vec<&'static str> {
+        vec!["withdrawal_address"]
+    }
+
+    fn column_descriptions(&self) -> HashMap<&'static str, &'static str> {
+        HashMap::from_iter(vec![
+            ("status", "beacon chain status of the validator, e.g. active_ongoing"),
+            ("slot", "beacon chain slot of the block"),
+        ])
+    }
+
+    /// snapshot of all validators at the last block of the chunk
+    async fn collect_block_chunk(
+        &self,
+        chunk: &BlockChunk,
+        source: &Source,
+        schema: &Table,
+        _filter: Option<&RowFilter>,
+    ) -> Result<DataFrame, CollectError> {
+        let beacon = source.beacon.as_ref().ok_or_else(|| {
+            CollectError::CollectError("validators dataset requires --beacon-url".to_string())
+        })?;
+        let block_number = chunk
+            .max_value()
+            .ok_or_else(|| CollectError::CollectError("empty block chunk".to_string()))?;
+        let permit = source.permit_request(RequestPriority::Bulk).await;
+        let block = source
+            .provider
+            .get_block(block_number)
+            .await
+            .map_err(|e| CollectError::rpc("eth_getBlockByNumber", e))?
+            .ok_or_else(|| CollectError::CollectError("block not found".to_string()))?;
+        drop(permit);
+        let slot = beacon.slot_at(block.timestamp.as_u64()).await?;
+        let validators = beacon.validators(slot).await?;
+
+        let n_rows = validators.len();
+        let mut validator_index: Vec<u64> = Vec::with_capacity(n_rows);
+        let mut pubkey: Vec<Vec<u8>> = Vec::with_capacity(n_rows);
+        let mut withdrawal_credentials: Vec<Vec<u8>> = Vec::with_capacity(n_rows);
+        let mut withdrawal_address: Vec<Option<Vec<u8>>> = Vec::with_capacity(n_rows);
+        let mut status: Vec<String> = Vec::with_capacity(n_rows);
+        for validator in validators.into_iter() {
+            let credentials = &validator.withdrawal_credentials;
+            validator_index.push(validator.index);
+            withdrawal_address.push(match credentials.first() {
+                Some(0x01) | Some(0x02) if credentials.len() == 32 => {
+                    Some(credentials[12..].to_vec())
+                }
+                _ => None,
+            });
+            pubkey.push(validator.pubkey);
+            withdrawal_credentials.push(validator.withdrawal_credentials);
+            status.push(validator.status);
+        }
+
+        let mut cols = Vec::new();
+        if schema.has_column("block_number") {
+            cols.push(Series::new("block_number", vec![block_number as u32; n_rows]));
+        }
+        if schema.has_column("slot") {
+            cols.push(Series::new("slot", vec![slot; n_rows]));
+        }
+        with_series!(cols, "validator_index", validator_index, schema);
+        with_series_binary!(cols, "pubkey", pubkey, schema);
+        with_series_binary!(cols, "withdrawal_credentials", withdrawal_credentials, schema);
+        with_series_binary!(cols, "withdrawal_address", withdrawal_address, schema);
+        with_series!(cols, "status", status, schema);
+        if schema.has_column("chain_id") {
+            cols.push(Series::new("chain_id", vec![source.chain_id; n_rows]));
+        }
+
+        DataFrame::new(cols).map_err(CollectError::PolarsError).sort_by_schema(schema)
+    }
+}
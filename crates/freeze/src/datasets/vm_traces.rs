@@ -49,6 +49,10 @@ impl Dataset for VmTraces {
         vec!["block_number".to_string(), "transaction_position".to_string(), "used".to_string()]
     }
 
+    fn nullable_columns(&self) -> Vec<&'static str> {
+        vec!["used", "push", "mem_off", "mem_data", "storage_key", "storage_val"]
+    }
+
     async fn collect_block_chunk(
         &self,
         chunk: &BlockChunk,
@@ -113,7 +117,7 @@ async fn vm_traces_to_df(
                     }
                 }
             }
-            _ => return Err(CollectError::TooManyRequestsError),
+            (_, Err(e)) => return Err(e),
         }
     }
 
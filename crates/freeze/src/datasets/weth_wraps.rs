@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+
+use ethers::prelude::*;
+use polars::prelude::*;
+use tokio::sync::mpsc;
+
+use super::logs;
+use crate::{
+    dataframes::SortableDataFrame,
+    types::{
+        conversions::{checked_u64, NarrowU32, ToVecHex},
+        BlockChunk, CollectError, ColumnType, Dataset, Datatype, RowFilter, Source, Table,
+        TransactionChunk, WethWraps,
+    },
+    with_series, with_series_binary, with_series_u32,
+};
+
+/// topic0 of Deposit(address,uint256)
+const DEPOSIT_TOPIC: &str = "0xe1fffcc4923d04b559f4d29a8bfc6cda04eb5b0d3c460751c2402c5c5cc9109c";
+
+/// topic0 of Withdrawal(address,uint256)
+const WITHDRAWAL_TOPIC: &str = "0x7fcf532c15f0a6db0bd6d0e038bea71d30d808c7d98cb3bf7268a95bf5081b65";
+
+/// canonical weth contract of each chain, used when no contract is specified
+fn weth_address(chain_id: u64) -> Option<&'static str> {
+    match chain_id {
+        1 => Some("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"),
+        10 | 8453 => Some("0x4200000000000000000000000000000000000006"),
+        42161 => Some("0x82aF49447D8a07e3bd95BD0d56f35241523fBab1"),
+        _ => None,
+    }
+}
+
+#[async_trait::async_trait]
+impl Dataset for WethWraps {
+    fn datatype(&self) -> Datatype {
+        Datatype::WethWraps
+    }
+
+    fn name(&self) -> &'static str {
+        "weth_wraps"
+    }
+
+    fn column_types(&self) -> HashMap<&'static str, ColumnType> {
+        HashMap::from_iter(vec![
+            ("block_number", ColumnType::UInt32),
+            ("transaction_index", ColumnType::UInt32),
+            ("log_index", ColumnType::UInt32),
+            ("transaction_hash", ColumnType::Binary),
+            ("weth", ColumnType::Binary),
+            ("address", ColumnType::Binary),
+            ("direction", ColumnType::String),
+            ("value", ColumnType::Decimal128),
+            ("chain_id", ColumnType::UInt64),
+        ])
+    }
+
+    fn default_columns(&self) -> Vec<&'static str> {
+        vec![
+            "block_number",
+            "transaction_index",
+            "log_index",
+            "transaction_hash",
+            "weth",
+            "address",
+            "direction",
+            "value",
+        ]
+    }
+
+    fn default_sort(&self) -> Vec<String> {
+        vec!["block_number".to_string(), "log_index".to_string()]
+    }
+
+    fn column_descriptions(&self) -> HashMap<&'static str, &'static str> {
+        HashMap::from_iter(vec![
+            ("value", "wei wrapped or unwrapped"),
+            ("direction", "deposit or withdrawal"),
+            ("address", "account that wrapped or unwrapped"),
+        ])
+    }
+
+    async fn collect_block_chunk(
+        &self,
+        chunk: &BlockChunk,
+        source: &Source,
+        schema: &Table,
+        filter: Option<&RowFilter>,
+    ) -> Result<DataFrame, CollectError> {
+        let filter = weth_filter(filter, source.chain_id)?;
+        let rx = logs::fetch_block_logs(chunk, source, Some(&filter)).await;
+        wraps_to_df(rx, &filter, schema, source.chain_id).await
+    }
+
+    async fn collect_transaction_chunk(
+        &self,
+        chunk: &TransactionChunk,
+        source: &Source,
+        schema: &Table,
+        filter: Option<&RowFilter>,
+    ) -> Result<DataFrame, CollectError> {
+        let filter = weth_filter(filter, source.chain_id)?;
+        let rx = logs::fetch_transaction_logs(chunk, source, Some(&filter)).await;
+        wraps_to_df(rx, &filter, schema, source.chain_id).await
+    }
+}
+
+/// Deposit and Withdrawal are common event signatures, so logs are limited to a weth contract
+fn weth_filter(filter: Option<&RowFilter>, chain_id: u64) -> Result<RowFilter, CollectError> {
+    let mut filter = logs::event_filter(filter, &[DEPOSIT_TOPIC, WITHDRAWAL_TOPIC]);
+    if filter.address.is_none() {
+        let address = weth_address(chain_id).ok_or_else(|| {
+            CollectError::CollectError(format!(
+                "no known weth contract for chain {}, specify one with --contract",
+                chain_id
+            ))
+        })?;
+        filter.address = Some(ValueOrArray::Value(address.parse().expect("valid weth address")));
+    }
+    Ok(filter)
+}
+
+async fn wraps_to_df(
+    mut logs: mpsc::Receiver<Result<Vec<Log>, CollectError>>,
+    filter: &RowFilter,
+    schema: &Table,
+    chain_id: u64,
+) -> Result<DataFrame, CollectError> {
+    let deposit_topic: H256 = DEPOSIT_TOPIC.parse().expect("valid deposit topic");
+    let withdrawal_topic: H256 = WITHDRAWAL_TOPIC.parse().expect("valid withdrawal topic");
+    let mut wraps: Vec<Log> = Vec::new();
+    while let Some(message) = logs.recv().await {
+        wraps.extend(message?.into_iter().filter(|log| {
+            log.removed != Some(true) &&
+                !filter.excluded_addresses.contains(&log.address) &&
+                log.topics.len() == 2 &&
+                (log.topics[0] == deposit_topic || log.topics[0] == withdrawal_topic) &&
+                log.data.len() == 32
+        }));
+    }
+
+    let n_rows = wraps.len();
+    let mut block_number: Vec<u64> = Vec::with_capacity(n_rows);
+    let mut transaction_index: Vec<u64> = Vec::with_capacity(n_rows);
+    let mut log_index: Vec<u64> = Vec::with_capacity(n_rows);
+    let mut transaction_hash: Vec<Vec<u8>> = Vec::with_capacity(n_rows);
+    let mut weth: Vec<Vec<u8>> = Vec::with_capacity(n_rows);
+    let mut address: Vec<Vec<u8>> = Vec::with_capacity(n_rows);
+    let mut direction: Vec<&'static str> = Vec::with_capacity(n_rows);
+    let mut value: Vec<String> = Vec::with_capacity(n_rows);
+    for log in wraps.iter() {
+        block_number.push(log.block_number.map(|n| n.as_u64()).unwrap_or_default());
+        transaction_index.push(log.transaction_index.map(|i| i.as_u64()).unwrap_or_default());
+        log_index.push(checked_u64(log.log_index.unwrap_or_default(), "log_index")?);
+        transaction_hash.push(log.transaction_hash.unwrap_or_default().as_bytes().to_vec());
+        weth.push(log.address.as_bytes().to_vec());
+        address.push(log.topics[1].as_bytes()[12..].to_vec());
+        direction.push(if log.topics[0] == deposit_topic { "wrap" } else { "unwrap" });
+        value.push(U256::from_big_endian(&log.data).to_string());
+    }
+
+    let mut cols = Vec::new();
+    with_series_u32!(cols, "block_number", block_number, schema);
+    with_series_u32!(cols, "transaction_index", transaction_index, schema);
+    with_series_u32!(cols, "log_index", log_index, schema);
+    with_series_binary!(cols, "transaction_hash", transaction_hash, schema);
+    with_series_binary!(cols, "weth", weth, schema);
+    with_series_binary!(cols, "address", address, schema);
+    with_series!(cols, "direction", direction, schema);
+    with_series!(cols, "value", value, schema);
+    if schema.has_column("chain_id") {
+        cols.push(Series::new("chain_id", vec![chain_id; n_rows]));
+    }
+
+    DataFrame::new(cols).map_err(CollectError::PolarsError).sort_by_schema(schema)
+}
@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+
+use ethers::prelude::*;
+use polars::prelude::*;
+use tokio::sync::mpsc;
+
+use super::{blocks, blocks_and_transactions};
+use crate::{
+    dataframes::SortableDataFrame,
+    types::{
+        chains::other_field,
+        conversions::{checked_u64, NarrowU32, ToVecHex},
+        BlockChunk, CollectError, ColumnType, Dataset, Datatype, RowFilter, Source, Table, Vm,
+        ZkSyncTransactions,
+    },
+    with_series, with_series_binary, with_series_u32,
+};
+
+/// transaction type of l1 -> l2 priority operations
+const PRIORITY_OPERATION_TYPE: u64 = 0xff;
+
+#[async_trait::async_trait]
+impl Dataset for ZkSyncTransactions {
+    fn datatype(&self) -> Datatype {
+        Datatype::ZkSyncTransactions
+    }
+
+    fn name(&self) -> &'static str {
+        "zksync_transactions"
+    }
+
+    fn column_types(&self) -> HashMap<&'static str, ColumnType> {
+        HashMap::from_iter(vec![
+            ("block_number", ColumnType::UInt32),
+            ("transaction_index", ColumnType::UInt32),
+            ("transaction_hash", ColumnType::Binary),
+            ("nonce", ColumnType::UInt64),
+            ("from_address", ColumnType::Binary),
+            ("to_address", ColumnType::Binary),
+            ("value", ColumnType::String),
+            ("input", ColumnType::Binary),
+            ("gas_limit", ColumnType::UInt64),
+            ("gas_price", ColumnType::UInt64),
+            ("transaction_type", ColumnType::UInt32),
+            ("l1_batch_number", ColumnType::UInt64),
+            ("l1_batch_tx_index", ColumnType::UInt64),
+            ("l1_batch_timestamp", ColumnType::UInt64),
+            ("is_priority_operation", ColumnType::Boolean),
+            ("chain_id", ColumnType::UInt64),
+        ])
+    }
+
+    fn default_columns(&self) -> Vec<&'static str> {
+        vec![
+            "block_number",
+            "transaction_index",
+            "transaction_hash",
+            "from_address",
+            "to_address",
+            "value",
+            "transaction_type",
+            "l1_batch_number",
+            "l1_batch_tx_index",
+            "is_priority_operation",
+        ]
+    }
+
+    fn default_sort(&self) -> Vec<String> {
+        vec!["block_number".to_string(), "transaction_index".to_string()]
+    }
+
+    fn nullable_columns(&self) -> Vec<&'static str> {
+        vec![
+            "block_number",
+            "transaction_index",
+            "to_address",
+            "gas_price",
+            "transaction_type",
+            "l1_batch_number",
+            "l1_batch_tx_index",
+            "l1_batch_timestamp",
+        ]
+    }
+
+    fn column_descriptions(&self) -> HashMap<&'static str, &'static str> {
+        HashMap::from_iter(vec![
+            ("value", "wei sent with the transaction"),
+            ("nonce", "nonce of the sender"),
+        ])
+    }
+
+    fn vm(&self) -> Vm {
+        Vm::ZkSync
+    }
+
+    async fn collect_block_chunk(
+        &self,
+        chunk: &BlockChunk,
+        source: &Source,
+        schema: &Table,
+        _filter: Option<&RowFilter>,
+    ) -> Result<DataFrame, CollectError> {
+        let rx = blocks_and_transactions::fetch_blocks_and_transactions(
+            chunk,
+            source,
+            false,
+            false,
+            blocks_and_transactions::RecipientLookups::default(),
+        )
+        .await;
+        zksync_transactions_to_df(rx, schema, source.chain_id).await
+    }
+}
+
+/// flatten blocks into transactions, reading the l1 batch fields zksync adds to each response
+async fn zksync_transactions_to_df(
+    mut blocks: mpsc::Receiver<blocks::BlockTxGasTuple<Transaction>>,
+    schema: &Table,
+    chain_id: u64,
+) -> Result<DataFrame, CollectError> {
+    let mut block_number: Vec<Option<u64>> = Vec::new();
+    let mut transaction_index: Vec<Option<u64>> = Vec::new();
+    let mut transaction_hash: Vec<Vec<u8>> = Vec::new();
+    let mut nonce: Vec<u64> = Vec::new();
+    let mut from_address: Vec<Vec<u8>> = Vec::new();
+    let mut to_address: Vec<Option<Vec<u8>>> = Vec::new();
+    let mut value: Vec<String> = Vec::new();
+    let mut input: Vec<Vec<u8>> = Vec::new();
+    let mut gas_limit: Vec<u64> = Vec::new();
+    let mut gas_price: Vec<Option<u64>> = Vec::new();
+    let mut transaction_type: Vec<Option<u64>> = Vec::new();
+    let mut l1_batch_number: Vec<Option<u64>> = Vec::new();
+    let mut l1_batch_tx_index: Vec<Option<u64>> = Vec::new();
+    let mut l1_batch_timestamp: Vec<Option<u64>> = Vec::new();
+    let mut is_priority_operation: Vec<bool> = Vec::new();
+
+    while let Some(message) = blocks.recv().await {
+        let (block, _, _) = message?;
+        let batch_timestamp = other_field::<U64>(&block.other, "l1BatchTimestamp");
+        for tx in block.transactions.iter() {
+            let tx_type = tx.transaction_type.map(|value| value.as_u64());
+            block_number.push(tx.block_number.map(|value| value.as_u64()));
+            transaction_index.push(tx.transaction_index.map(|value| value.as_u64()));
+            transaction_hash.push(tx.hash.as_bytes().to_vec());
+            nonce.push(tx.nonce.as_u64());
+            from_address.push(tx.from.as_bytes().to_vec());
+            to_address.push(tx.to.map(|to| to.as_bytes().to_vec()));
+            value.push(tx.value.to_string());
+            input.push(tx.input.to_vec());
+            gas_limit.push(checked_u64(tx.gas, "gas_limit")?);
+            gas_price.push(tx.gas_price.map(|price| checked_u64(price, "gas_price")).transpose()?);
+            transaction_type.push(tx_type);
+            l1_batch_number
+                .push(other_field::<U64>(&tx.other, "l1BatchNumber").map(|value| value.as_u64()));
+            l1_batch_tx_index
+                .push(other_field::<U64>(&tx.other, "l1BatchTxIndex").map(|value| value.as_u64()));
+            l1_batch_timestamp.push(batch_timestamp.map(|value| value.as_u64()));
+            is_priority_operation.push(tx_type == Some(PRIORITY_OPERATION_TYPE));
+        }
+    }
+
+    let n_rows = transaction_hash.len();
+    let mut cols = Vec::new();
+    with_series_u32!(cols, "block_number", block_number, schema);
+    with_series_u32!(cols, "transaction_index", transaction_index, schema);
+    with_series_binary!(cols, "transaction_hash", transaction_hash, schema);
+    with_series!(cols, "nonce", nonce, schema);
+    with_series_binary!(cols, "from_address", from_address, schema);
+    with_series_binary!(cols, "to_address", to_address, schema);
+    with_series!(cols, "value", value, schema);
+    with_series_binary!(cols, "input", input, schema);
+    with_series!(cols, "gas_limit", gas_limit, schema);
+    with_series!(cols, "gas_price", gas_price, schema);
+    with_series_u32!(cols, "transaction_type", transaction_type, schema);
+    with_series!(cols, "l1_batch_number", l1_batch_number, schema);
+    with_series!(cols, "l1_batch_tx_index", l1_batch_tx_index, schema);
+    with_series!(cols, "l1_batch_timestamp", l1_batch_timestamp, schema);
+    with_series!(cols, "is_priority_operation", is_priority_operation, schema);
+    if schema.has_column("chain_id") {
+        cols.push(Series::new("chain_id", vec![chain_id; n_rows]));
+    }
+
+    DataFrame::new(cols).map_err(CollectError::PolarsError).sort_by_schema(schema)
+}
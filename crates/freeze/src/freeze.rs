@@ -1,174 +1,1818 @@
-use std::{collections::HashMap, path::Path, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    io::Write,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Instant,
+};
 
-use futures::future::join_all;
+use ethers::{
+    prelude::{BlockNumber, H256},
+    providers::{Middleware, Provider, ProviderError},
+};
+use futures::{future::join_all, stream::FuturesUnordered, Future, StreamExt};
 use indicatif::ProgressBar;
-use tokio::sync::Semaphore;
+use polars::prelude::DataFrame;
+use tokio::{
+    runtime::Handle,
+    sync::{OwnedRwLockReadGuard, RwLock, Semaphore},
+};
+use tokio_util::sync::CancellationToken;
 
 use crate::types::{
-    dataframes, Chunk, Datatype, FileOutput, FreezeChunkSummary, FreezeError, FreezeSummary,
-    FreezeSummaryAgg, MultiDatatype, MultiQuery, Source,
+    chunks::ChunkSizer,
+    dataframes::{self, EpochRollup, Rollups},
+    encode_hex, BlockChunk, Chunk, ChunkData, CollectError, Datatype, FailedChunkLog, FileError,
+    FileOutput, Follow, FreezeChunkSummary, FreezeError, FreezeSummary, FreezeSummaryAgg,
+    HeadSubscription, Manifest, ManifestEntry, MultiDatatype, MultiQuery, OutputLock, PartitionBy,
+    Reorg, ReorgLog, RequestPriority, RowFilter, RpcClient, Signer, Source, Table, REORGS_NAME,
 };
+#[cfg(feature = "sqlite")]
+use crate::types::{dataframes::SqliteSink, FileFormat};
 
 /// perform a bulk data extraction of multiple datatypes over multiple block chunks
+///
+/// dropping the returned future cancels chunks that have not been committed yet. chunks already
+/// writing are still committed, and the output directory stays locked until they are
 pub async fn freeze(
     query: &MultiQuery,
     source: &Source,
     sink: &FileOutput,
 ) -> Result<FreezeSummary, FreezeError> {
-    // create progress bar
-    let bar = Arc::new(ProgressBar::new(query.chunks.len() as u64));
+    freeze_until_cancelled(query, source, sink, &CancellationToken::new()).await
+}
+
+/// freeze until done or until a token is cancelled
+///
+/// cancelling stops chunks that are waiting or collecting, which are reported as errored with
+/// FreezeError::Cancelled. chunks already writing are committed first, so every committed chunk
+/// is kept and the manifest only lists complete chunks. a later run resumes the remaining chunks
+pub async fn freeze_until_cancelled(
+    query: &MultiQuery,
+    source: &Source,
+    sink: &FileOutput,
+    cancel: &CancellationToken,
+) -> Result<FreezeSummary, FreezeError> {
+    // chunk tasks outlive this future if it is dropped, so dropping it cancels them
+    let cancel = cancel.child_token();
+    let _cancel_on_drop = cancel.clone().drop_guard();
+
+    // lock output directory until the last chunk task finishes
+    let bar = progress_bar(query.chunks.len() as u64)?;
+    let run = RunLock::acquire(sink)?;
+    Manifest::clear_staging(&sink.output_dir);
+    let env = Arc::new(FreezeEnv::open(query, source, sink, bar, cancel, &run)?);
+
+    // freeze chunks concurrently
+    let summaries = Mutex::new(Vec::new());
+    freeze_chunks(&env, &summaries).await;
+    let chunk_summaries = summaries.into_inner().expect("summaries lock");
+
+    // sign manifest while still holding the output directory lock
+    run.drain().await;
+    run.sign()?;
+    Ok(chunk_summaries.aggregate())
+}
+
+/// freeze on the runtime of a handle, returning a future that any executor can await
+///
+/// chunks are spawned as tasks of the handle's runtime, so the caller's runtime is used instead
+/// of requiring cryo to be awaited from within a tokio runtime
+pub fn freeze_on(
+    handle: &Handle,
+    query: MultiQuery,
+    source: Source,
+    sink: FileOutput,
+) -> impl Future<Output = Result<FreezeSummary, FreezeError>> + Send + 'static {
+    let task = handle.spawn(async move { freeze(&query, &source, &sink).await });
+    async move { task.await.map_err(FreezeError::TaskFailed)? }
+}
+
+/// write a zero-row file of each datatype with the exact schema that freezing would write
+///
+/// files are named like chunk files with `schema` in place of the chunk, e.g.
+/// `ethereum__logs__schema.parquet`, and are not recorded in the manifest. sqlite output creates
+/// the empty tables instead. returns the paths written
+pub fn freeze_empty_schemas(
+    query: &MultiQuery,
+    sink: &FileOutput,
+) -> Result<Vec<String>, FreezeError> {
+    let mut dfs = HashMap::new();
+    for (datatype, schema) in query.schemas.iter() {
+        dfs.insert(*datatype, dataframes::empty_dataframe(schema)?);
+    }
+    let mut dfs: Vec<(Datatype, DataFrame)> =
+        prepare_dfs(dfs, &query.schemas, sink)?.into_iter().collect();
+    dfs.sort_by_key(|(datatype, _)| datatype.dataset().name());
+
+    let _lock = OutputLock::acquire(&sink.output_dir)?;
+    #[cfg(feature = "sqlite")]
+    if sink.format == FileFormat::Sqlite {
+        let sqlite = SqliteSink::open(sink)?;
+        sqlite.create_tables(&dfs)?;
+        return Ok(vec![sqlite.path().to_string()])
+    }
+    let mut paths = Vec::new();
+    for (datatype, mut df) in dfs.into_iter() {
+        let mut pieces = vec![sink.prefix.clone(), datatype.dataset().name().to_string()];
+        pieces.push("schema".to_string());
+        pieces.extend(sink.suffix.clone());
+        let filename = format!("{}.{}", pieces.join("__"), sink.extension());
+        let path = std::path::Path::new(&sink.output_dir).join(filename);
+        let path = path.to_string_lossy().into_owned();
+        dataframes::df_to_file(&mut df, &path, sink)?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+/// backfill the chunks of a query while following the chain head, until interrupted
+///
+/// backfill and follow share the output directory lock and manifest but not their request
+/// budgets, so new blocks are collected promptly however large the backfill is. a followed chunk
+/// that fails is retried at the next poll, so followed output has no gaps
+pub async fn freeze_and_follow(
+    query: &MultiQuery,
+    source: &Source,
+    sink: &FileOutput,
+    follow: &Follow,
+) -> Result<FreezeSummary, FreezeError> {
+    let cancel = CancellationToken::new();
+    let _cancel_on_drop = cancel.clone().drop_guard();
+    let bar = progress_bar(query.chunks.len() as u64)?;
+    let run_lock = RunLock::acquire(sink)?;
+    Manifest::clear_staging(&sink.output_dir);
+    let backfill_env =
+        Arc::new(FreezeEnv::open(query, source, sink, bar, cancel.clone(), &run_lock)?);
+    let follow_query = MultiQuery { chunks: vec![], ..query.clone() };
+    let hidden = Arc::new(ProgressBar::hidden());
+    let follow_env = Arc::new(FreezeEnv::open(
+        &follow_query,
+        &follow.source,
+        sink,
+        hidden.clone(),
+        cancel.clone(),
+        &run_lock,
+    )?);
+    // preconfirmed blocks that changed before confirmation are recollected over their files
+    let reconcile_env = match follow.preconfirmed {
+        true => {
+            let sink = FileOutput { overwrite: true, ..sink.clone() };
+            let (query, source) = (&follow_query, &follow.source);
+            let env = FreezeEnv::open(query, source, &sink, hidden, cancel.clone(), &run_lock)?;
+            Some(Arc::new(env))
+        }
+        false => None,
+    };
+
+    let summaries = Mutex::new(Vec::new());
+    let backfill = freeze_chunks(&backfill_env, &summaries);
+    let following = follow_head(follow, &follow_env, reconcile_env.as_ref(), &summaries);
+    // an alert with --exit-on-alert ends the run without waiting for the backfill
+    let run = async {
+        tokio::pin!(backfill, following);
+        tokio::select! {
+            _ = &mut backfill => following.await,
+            alert = &mut following => match alert {
+                Some(alert) => Some(alert),
+                None => {
+                    backfill.await;
+                    None
+                }
+            },
+        }
+    };
+    let alert = tokio::select! {
+        alert = run => alert,
+        _ = tokio::signal::ctrl_c() => None,
+    };
+    cancel.cancel();
+
+    // chunk tasks of the dropped futures may still be writing, so wait for them before signing
+    run_lock.drain().await;
+    run_lock.sign()?;
+    if let Some(alert) = alert {
+        return Err(FreezeError::FollowUnhealthy(alert))
+    }
+    Ok(summaries.into_inner().expect("summaries lock").aggregate())
+}
+
+/// collect blocks as they reach the required confirmations, until output limits are exceeded
+///
+/// in tip mode each block is its own chunk, so its files are written as soon as it is collected.
+/// preconfirmed blocks are collected at the head and recollected if their hash changed by the
+/// time they are confirmed
+///
+/// returns the first health check alert if following should stop at alerts
+async fn follow_head(
+    follow: &Follow,
+    env: &Arc<FreezeEnv>,
+    reconcile_env: Option<&Arc<FreezeEnv>>,
+    summaries: &Mutex<Vec<FreezeChunkSummary>>,
+) -> Option<String> {
+    let mut next_block = follow.start_block;
+    let mut health = FollowHealth::new();
+    let mut unconfirmed = VecDeque::new();
+    let mut reorgs = match follow.record_reorgs {
+        true => Some(ReorgTracker::new(&env.sink.output_dir)),
+        false => None,
+    };
+    let mut subscription = match &follow.ws_url {
+        Some(url) => match HeadSubscription::connect(url).await {
+            Ok(subscription) => Some(subscription),
+            Err(e) => {
+                env.report(&format!("could not subscribe to new heads, polling: {}", e));
+                None
+            }
+        },
+        None => None,
+    };
+    while env.guard.check().is_ok() && env.prompt.check().is_ok() && !env.cancel.is_cancelled() {
+        let head = match subscription.as_ref().and_then(HeadSubscription::latest) {
+            Some(head) => Ok(head),
+            None => env.prompt.source(&env.source).latest_block_number().await,
+        };
+        let polled_head = head.as_ref().ok().copied();
+        for alert in health.check(follow, polled_head, next_block) {
+            env.report(&format!("alert: {}", alert));
+            if let Some(url) = &follow.alert_webhook {
+                if let Err(e) = post_alert(url, &alert, &env.sink.prefix).await {
+                    env.report(&format!("could not post alert to webhook: {}", e));
+                }
+            }
+            if follow.exit_on_alert {
+                return Some(alert)
+            }
+        }
+        let head = match head {
+            Ok(head) => head,
+            Err(e) => {
+                env.report(&format!("could not get chain head: {}", e));
+                tokio::time::sleep(follow.poll_interval).await;
+                continue
+            }
+        };
+        if let Some(tracker) = reorgs.as_mut() {
+            match tracker.poll(&env.prompt.source(&env.source).provider).await {
+                Ok(Some(reorg)) => {
+                    env.report(&format!(
+                        "reorg: head {} replaced by {}, depth {}",
+                        reorg.old_head_number, reorg.new_head_number, reorg.depth
+                    ));
+                    if tracker.log.record(&reorg).is_err() {
+                        env.report(&format!("could not record reorg in {}", REORGS_NAME));
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => env.report(&format!("could not check chain head for reorgs: {}", e)),
+            }
+        }
+        if let Some(reconcile_env) = reconcile_env {
+            reconcile_blocks(follow, head, &mut unconfirmed, reconcile_env, summaries).await;
+        }
+        let confirmed = match follow.preconfirmed {
+            true => head,
+            false => head.saturating_sub(follow.confirmations),
+        };
+        if confirmed < next_block {
+            wait_for_head(&mut subscription, follow.poll_interval, env).await;
+            continue
+        }
+
+        let end_block = confirmed.min(next_block + follow.chunk_size.max(1) - 1);
+        let chunks = match follow.tip {
+            true => (next_block..=end_block)
+                .map(|block| Chunk::Block(BlockChunk::Range(block, block)))
+                .collect(),
+            false => vec![Chunk::Block(BlockChunk::Range(next_block, end_block))],
+        };
+        let hashes = match reconcile_env {
+            Some(_) => block_hashes(next_block, end_block, env).await,
+            None => Vec::new(),
+        };
+        let chunk_summaries: Vec<FreezeChunkSummary> =
+            join_all(spawn_chunks(chunks, env)).await.into_iter().filter_map(Result::ok).collect();
+        let errored = chunk_summaries.iter().any(|summary| summary.errored);
+        summaries.lock().expect("summaries lock").extend(chunk_summaries);
+        match errored {
+            true => tokio::time::sleep(follow.poll_interval).await,
+            false => {
+                unconfirmed.extend(hashes);
+                next_block = end_block + 1;
+            }
+        }
+    }
+    None
+}
+
+/// wait for a new head from the subscription, or for the poll interval without one
+///
+/// the wait is bounded by the poll interval even when subscribed, so that health checks keep
+/// running while the head stalls
+async fn wait_for_head(
+    subscription: &mut Option<HeadSubscription>,
+    poll_interval: std::time::Duration,
+    env: &FreezeEnv,
+) {
+    match subscription {
+        Some(active) => {
+            if !active.wait(poll_interval).await {
+                env.report("new heads subscription ended, polling");
+                *subscription = None;
+            }
+        }
+        None => tokio::time::sleep(poll_interval).await,
+    }
+}
+
+/// hashes of blocks at the time they are collected, none where a block could not be fetched
+async fn block_hashes(
+    start_block: u64,
+    end_block: u64,
+    env: &FreezeEnv,
+) -> Vec<(u64, Option<H256>)> {
+    let provider = env.prompt.source(&env.source).provider;
+    let blocks = join_all((start_block..=end_block).map(|block| provider.get_block(block))).await;
+    (start_block..=end_block)
+        .zip(blocks)
+        .map(|(number, block)| (number, block.ok().flatten().and_then(|block| block.hash)))
+        .collect()
+}
+
+/// recollect preconfirmed blocks that reached their confirmations with a different hash
+async fn reconcile_blocks(
+    follow: &Follow,
+    head: u64,
+    unconfirmed: &mut VecDeque<(u64, Option<H256>)>,
+    env: &Arc<FreezeEnv>,
+    summaries: &Mutex<Vec<FreezeChunkSummary>>,
+) {
+    while let Some((number, hash)) = unconfirmed.front().cloned() {
+        if number + follow.confirmations > head {
+            break
+        }
+        let provider = env.prompt.source(&env.source).provider;
+        let canonical = match provider.get_block(number).await {
+            Ok(Some(block)) => block.hash,
+            // retry at the next poll
+            _ => break,
+        };
+        if hash.is_none() || canonical != hash {
+            env.report(&format!("block {} changed after collection, recollecting", number));
+            let chunk = Chunk::Block(BlockChunk::Range(number, number));
+            let chunk_summaries: Vec<FreezeChunkSummary> = join_all(spawn_chunks(vec![chunk], env))
+                .await
+                .into_iter()
+                .filter_map(Result::ok)
+                .collect();
+            let errored = chunk_summaries.iter().any(|summary| summary.errored);
+            summaries.lock().expect("summaries lock").extend(chunk_summaries);
+            if errored {
+                break
+            }
+        }
+        unconfirmed.pop_front();
+    }
+}
+
+/// health of following, alerting once each time following falls behind or the head stalls
+struct FollowHealth {
+    head: u64,
+    head_changed: Instant,
+    lagging: bool,
+    stalled: bool,
+}
+
+impl FollowHealth {
+    fn new() -> FollowHealth {
+        FollowHealth { head: 0, head_changed: Instant::now(), lagging: false, stalled: false }
+    }
+
+    /// alerts raised by a poll of the chain head, which may have failed
+    fn check(&mut self, follow: &Follow, head: Option<u64>, next_block: u64) -> Vec<String> {
+        if let Some(head) = head.filter(|head| *head > self.head) {
+            self.head = head;
+            self.head_changed = Instant::now();
+        }
+
+        let mut alerts = Vec::new();
+        if let Some(max_block_lag) = follow.max_block_lag {
+            // blocks are only followed up to the confirmed head, so lag is measured from there
+            let confirmed = match follow.preconfirmed {
+                true => self.head,
+                false => self.head.saturating_sub(follow.confirmations),
+            };
+            let lag = confirmed.saturating_sub(next_block.saturating_sub(1));
+            let lagging = lag > max_block_lag;
+            if lagging && !self.lagging {
+                alerts.push(format!(
+                    "followed blocks are {} blocks behind confirmed head {}, more than \
+                    --max-block-lag {}",
+                    lag, confirmed, max_block_lag
+                ));
+            }
+            self.lagging = lagging;
+        }
+        if let Some(max_head_stall) = follow.max_head_stall {
+            let stall = self.head_changed.elapsed();
+            let stalled = stall > max_head_stall;
+            if stalled && !self.stalled {
+                alerts.push(format!(
+                    "chain head has not advanced past {} for {} seconds, more than --max-head-stall",
+                    self.head,
+                    stall.as_secs()
+                ));
+            }
+            self.stalled = stalled;
+        }
+        alerts
+    }
+}
+
+/// blocks walked back from a replaced head when searching for the common ancestor of a reorg
+const MAX_REORG_DEPTH: u64 = 128;
+
+/// chain head across polls, recording reorgs that replace a polled head
+struct ReorgTracker {
+    head: Option<(u64, H256)>,
+    log: ReorgLog,
+}
+
+impl ReorgTracker {
+    fn new(output_dir: &str) -> ReorgTracker {
+        ReorgTracker { head: None, log: ReorgLog::new(output_dir) }
+    }
+
+    /// poll the chain head, returning a reorg if the previously polled head is no longer canonical
+    async fn poll(
+        &mut self,
+        provider: &Provider<RpcClient>,
+    ) -> Result<Option<Reorg>, ProviderError> {
+        let block = match provider.get_block(BlockNumber::Latest).await? {
+            Some(block) => block,
+            None => return Ok(None),
+        };
+        let (number, hash) = match (block.number, block.hash) {
+            (Some(number), Some(hash)) => (number.as_u64(), hash),
+            _ => return Ok(None),
+        };
+        let (old_number, old_hash) = match self.head {
+            Some(head) => head,
+            None => {
+                self.head = Some((number, hash));
+                return Ok(None)
+            }
+        };
+
+        // hash of the block now canonical at the height of the previous head
+        let canonical = if number == old_number {
+            Some(hash)
+        } else if number == old_number + 1 {
+            Some(block.parent_hash)
+        } else {
+            provider.get_block(old_number).await?.and_then(|block| block.hash)
+        };
+        let canonical = match canonical {
+            Some(canonical) => canonical,
+            // a load balanced provider can briefly return a head behind the previous head
+            None => return Ok(None),
+        };
+        if canonical == old_hash {
+            if number > old_number {
+                self.head = Some((number, hash));
+            }
+            return Ok(None)
+        }
+
+        // walk back both chains until they share a parent
+        let (mut old_parent, mut new_parent) = (old_hash, canonical);
+        let mut depth = 0;
+        let mut common_ancestor = None;
+        while depth < MAX_REORG_DEPTH {
+            let old_block = provider.get_block(old_parent).await?;
+            let new_block = provider.get_block(new_parent).await?;
+            let (old_block, new_block) = match (old_block, new_block) {
+                (Some(old_block), Some(new_block)) => (old_block, new_block),
+                // nodes may prune blocks of the replaced chain
+                _ => break,
+            };
+            depth += 1;
+            (old_parent, new_parent) = (old_block.parent_hash, new_block.parent_hash);
+            if old_parent == new_parent {
+                common_ancestor = Some(old_number.saturating_sub(depth));
+                break
+            }
+        }
+
+        self.head = Some((number, hash));
+        Ok(Some(Reorg {
+            old_head_number: old_number,
+            old_head_hash: encode_hex(old_hash.as_bytes()),
+            new_head_number: number,
+            new_head_hash: encode_hex(hash.as_bytes()),
+            common_ancestor,
+            depth: depth.max(1),
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+        }))
+    }
+}
+
+/// post an alert to a webhook, as json with a `text` field as accepted by chat webhooks
+async fn post_alert(url: &str, alert: &str, network: &str) -> Result<(), reqwest::Error> {
+    let body = serde_json::json!({
+        "text": format!("cryo {}: {}", network, alert),
+        "network": network,
+        "alert": alert,
+    });
+    reqwest::Client::new()
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(body.to_string())
+        .timeout(std::time::Duration::from_secs(10))
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map(|_| ())
+}
+
+/// print a note of a run above its progress bar, unless output is not verbose
+fn report(bar: &ProgressBar, verbose: bool, message: &str) {
+    if verbose {
+        bar.suspend(|| println!("{}", message))
+    }
+}
+
+fn progress_bar(n_chunks: u64) -> Result<Arc<ProgressBar>, FreezeError> {
+    let bar = Arc::new(ProgressBar::new(n_chunks));
     bar.set_style(
         indicatif::ProgressStyle::default_bar()
             .template("{wide_bar:.green} {human_pos} / {human_len}   ETA={eta_precise} ")
             .map_err(FreezeError::ProgressBarError)?,
     );
+    Ok(bar)
+}
 
-    // freeze chunks concurrently
-    let (datatypes, multi_datatypes) = cluster_datatypes(query.schemas.keys().collect());
-    let sem = Arc::new(Semaphore::new(source.max_concurrent_chunks as usize));
-    let query = Arc::new(query.clone());
-    let source = Arc::new(source.clone());
-    let sink = Arc::new(sink.clone());
+/// freeze the chunks of a query, adding the summary of each chunk as it finishes
+///
+/// with adaptive chunks, block chunks are taken one at a time as earlier chunks finish, keeping
+/// max_concurrent_chunks chunks in flight, so that each chunk is sized by the rows of the chunks
+/// finished before it
+async fn freeze_chunks(env: &Arc<FreezeEnv>, summaries: &Mutex<Vec<FreezeChunkSummary>>) {
+    let sizer = match &env.sizer {
+        Some(sizer) => sizer,
+        None => {
+            for task in spawn_chunks(env.query.chunks.clone(), env) {
+                if let Ok(summary) = task.await {
+                    summaries.lock().expect("summaries lock").push(summary);
+                }
+            }
+            return
+        }
+    };
+    let mut in_flight = FuturesUnordered::new();
+    loop {
+        while in_flight.len() < env.source.max_concurrent_chunks.max(1) as usize {
+            let next = sizer.lock().expect("sizer lock").next_chunk();
+            let (chunk, delta) = match next {
+                Some(next) => next,
+                None => break,
+            };
+            // progress is counted in chunks, which resizing merges and splits
+            let length = env.bar.length().unwrap_or(0) as i64;
+            env.bar.set_length((length + delta).max(0) as u64);
+            in_flight.push(join_all(spawn_chunks(vec![Chunk::Block(chunk)], env)));
+        }
+        match in_flight.next().await {
+            Some(tasks) => {
+                let mut summaries = summaries.lock().expect("summaries lock");
+                summaries.extend(tasks.into_iter().filter_map(Result::ok));
+            }
+            None => break,
+        }
+    }
+}
+
+/// spawn a task freezing all datatypes of each chunk
+fn spawn_chunks(
+    chunks: Vec<Chunk>,
+    env: &Arc<FreezeEnv>,
+) -> Vec<tokio::task::JoinHandle<FreezeChunkSummary>> {
+    let (datatypes, multi_datatypes) = cluster_datatypes(env.query.schemas.keys().collect());
+    let all_datatypes: Vec<Datatype> = env.query.schemas.keys().cloned().collect();
     let mut tasks: Vec<_> = vec![];
-    for chunk in query.chunks.clone().into_iter() {
-        // datatypes
-        for datatype in &datatypes {
-            let task = tokio::spawn(freeze_datatype_chunk(
-                chunk.clone(),
-                *datatype,
-                Arc::clone(&sem),
-                Arc::clone(&query),
-                Arc::clone(&source),
-                Arc::clone(&sink),
-                Arc::clone(&bar),
-            ));
-            tasks.push(task)
-        }
-
-        // multi datatypes
-        for multi_datatype in &multi_datatypes {
-            let bar = Arc::clone(&bar);
-            let task = tokio::spawn(freeze_multi_datatype_chunk(
-                chunk.clone(),
-                *multi_datatype,
-                Arc::clone(&sem),
-                Arc::clone(&query),
-                Arc::clone(&source),
-                Arc::clone(&sink),
-                Arc::clone(&bar),
-            ));
-            tasks.push(task)
-        }
-    }
-    let chunk_summaries: Vec<FreezeChunkSummary> =
-        join_all(tasks).await.into_iter().filter_map(Result::ok).collect();
-    Ok(chunk_summaries.aggregate())
+    for chunk in chunks.into_iter() {
+        let (datatypes, multi_datatypes) = (datatypes.clone(), multi_datatypes.clone());
+        let (all_datatypes, env) = (all_datatypes.clone(), env.clone());
+        let running = env.run.running();
+        let task = tokio::spawn(async move {
+            let _running = running;
+            let summary =
+                freeze_chunk(chunk.clone(), datatypes, multi_datatypes, env.clone()).await;
+            finish_rollups(&chunk, &all_datatypes, &env);
+            summary
+        });
+        tasks.push(task)
+    }
+    tasks
+}
+
+/// count a finished chunk towards the rollups of its epochs, writing rollups of complete epochs
+fn finish_rollups(chunk: &Chunk, datatypes: &[Datatype], env: &FreezeEnv) {
+    if let Some(rollups) = &env.rollups {
+        if let Err(e) = rollups.finish(chunk, datatypes, &env.sink) {
+            env.report(&format!("could not write rollups: {}", e));
+        }
+    }
+}
+
+/// summarize the rows of a chunk by epoch, if rollups are enabled
+fn summarize_rollups(
+    chunk: &Chunk,
+    dfs: &HashMap<Datatype, DataFrame>,
+    env: &FreezeEnv,
+) -> Result<HashMap<Datatype, HashMap<u64, EpochRollup>>, FileError> {
+    let mut summaries = HashMap::new();
+    if let Some(rollups) = &env.rollups {
+        for (datatype, df) in dfs.iter() {
+            if let Some(schema) = env.query.schemas.get(datatype) {
+                summaries.insert(*datatype, rollups.summarize(chunk, df, schema)?);
+            }
+        }
+    }
+    Ok(summaries)
+}
+
+/// output directory lock of a run, shared by the chunk tasks of the run
+///
+/// chunk tasks keep running when the future awaiting them is dropped, and may still commit files
+/// to the manifest. the lock is released only after the last task finishes, and the manifest is
+/// signed then unless the run already signed it
+struct RunLock {
+    output_dir: String,
+    verbose: bool,
+    signer: Mutex<Option<Signer>>,
+    running: Arc<RwLock<()>>,
+    _lock: OutputLock,
+}
+
+impl RunLock {
+    fn acquire(sink: &FileOutput) -> Result<Arc<RunLock>, FreezeError> {
+        Ok(Arc::new(RunLock {
+            output_dir: sink.output_dir.clone(),
+            verbose: sink.verbose,
+            signer: Mutex::new(sink.signer.clone()),
+            running: Arc::new(RwLock::new(())),
+            _lock: OutputLock::acquire(&sink.output_dir)?,
+        }))
+    }
+
+    /// guard held by a chunk task while it runs, none once the run is draining
+    fn running(&self) -> Option<OwnedRwLockReadGuard<()>> {
+        self.running.clone().try_read_owned().ok()
+    }
+
+    /// wait for the chunk tasks that are still running
+    async fn drain(&self) {
+        let _drained = self.running.write().await;
+    }
+
+    /// sign the manifest, which must only be called once no chunk task is running
+    fn sign(&self) -> Result<(), FileError> {
+        if let Some(signer) = self.signer.lock().expect("signer lock").take() {
+            signer.sign_manifest(&self.output_dir)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for RunLock {
+    fn drop(&mut self) {
+        if let Err(e) = self.sign() {
+            let message = format!("could not sign manifest: {}", e);
+            report(&ProgressBar::hidden(), self.verbose, &message);
+        }
+    }
+}
+
+/// state shared by the chunks of a freeze
+struct FreezeEnv {
+    sem: Semaphore,
+    query: MultiQuery,
+    source: Source,
+    sink: FileOutput,
+    guard: OutputGuard,
+    disk: DiskMonitor,
+    manifest: Manifest,
+    bar: Arc<ProgressBar>,
+    rollups: Option<Rollups>,
+    prompt: FailurePrompt,
+    #[cfg(feature = "sqlite")]
+    sqlite: Option<SqliteSink>,
+    failed: FailedChunkLog,
+    sizer: Option<Mutex<ChunkSizer>>,
+    cancel: CancellationToken,
+    run: Arc<RunLock>,
+}
+
+impl FreezeEnv {
+    /// open the manifest of the output directory, which must already be locked
+    fn open(
+        query: &MultiQuery,
+        source: &Source,
+        sink: &FileOutput,
+        bar: Arc<ProgressBar>,
+        cancel: CancellationToken,
+        run: &Arc<RunLock>,
+    ) -> Result<FreezeEnv, FreezeError> {
+        let manifest = Manifest::open(&sink.output_dir)?;
+        let mut sink = sink.clone();
+        if let Some(lineage) = sink.lineage.as_mut() {
+            lineage.parent_run_ids = manifest.head_run_ids()?;
+        }
+
+        // check disk space before starting
+        let disk = DiskMonitor::new(&sink, query);
+        disk.preflight(estimate_output_bytes(query), &bar)?;
+        let all_datatypes: Vec<Datatype> = query.schemas.keys().cloned().collect();
+        let rollups = sink
+            .rollup_epoch
+            .filter(|_| !sink.count_only)
+            .map(|epoch_size| Rollups::new(epoch_size, &query.chunks, &all_datatypes));
+        #[cfg(feature = "sqlite")]
+        let sqlite = match sink.format {
+            FileFormat::Sqlite if !sink.count_only => Some(SqliteSink::open(&sink)?),
+            _ => None,
+        };
+
+        // only queries of block chunks are resized
+        let block_chunks: Option<Vec<BlockChunk>> = query
+            .chunks
+            .iter()
+            .map(|chunk| match chunk {
+                Chunk::Block(block_chunk) => Some(block_chunk.clone()),
+                _ => None,
+            })
+            .collect();
+        let sizer = match (&query.adaptive, block_chunks) {
+            (Some(adaptive), Some(block_chunks)) => {
+                let entries = Manifest::read(&sink.output_dir)?;
+                Some(Mutex::new(ChunkSizer::new(adaptive, block_chunks, &entries)))
+            }
+            _ => None,
+        };
+
+        Ok(FreezeEnv {
+            sem: Semaphore::new(source.max_concurrent_chunks as usize),
+            query: query.clone(),
+            source: source.clone(),
+            guard: OutputGuard::new(&sink),
+            prompt: FailurePrompt::new(&sink),
+            failed: FailedChunkLog::new(&sink.output_dir),
+            sink,
+            disk,
+            manifest,
+            bar,
+            rollups,
+            #[cfg(feature = "sqlite")]
+            sqlite,
+            sizer,
+            cancel,
+            run: run.clone(),
+        })
+    }
+
+    /// count the rows of a collected chunk towards the size of later adaptive chunks
+    fn record_rows(&self, chunk: &Chunk, dfs: &HashMap<Datatype, DataFrame>) {
+        if let (Some(sizer), Chunk::Block(block_chunk)) = (&self.sizer, chunk) {
+            let mut sizer = sizer.lock().expect("sizer lock");
+            for (datatype, df) in dfs.iter() {
+                sizer.record(*datatype, block_chunk.size(), df.height() as u64);
+            }
+        }
+    }
+
+    /// print a note of the run above the progress bar, unless output is not verbose
+    fn report(&self, message: &str) {
+        report(&self.bar, self.sink.verbose, message)
+    }
 }
 
 fn cluster_datatypes(dts: Vec<&Datatype>) -> (Vec<Datatype>, Vec<MultiDatatype>) {
+    let requested: HashSet<Datatype> = dts.iter().map(|dt| **dt).collect();
     let mdts: Vec<MultiDatatype> = MultiDatatype::variants()
         .iter()
-        .filter(|mdt| mdt.multi_dataset().datatypes().iter().all(|x| dts.contains(&x)))
+        .filter(|mdt| mdt.multi_dataset().collects_together(&requested))
         .cloned()
         .collect();
     let mdt_dts: Vec<Datatype> =
-        mdts.iter().flat_map(|mdt| mdt.multi_dataset().datatypes()).collect();
+        mdts.iter().flat_map(|mdt| mdt.requested_datatypes(&requested)).collect();
     let other_dts = dts.iter().filter(|dt| !mdt_dts.contains(dt)).map(|x| **x).collect();
     (other_dts, mdts)
 }
 
-async fn freeze_datatype_chunk(
+/// freeze all datatypes of a chunk, committing their files together
+///
+/// datatypes are collected concurrently and only written once all of them are collected, then
+/// staged and committed as one manifest entry, so a chunk is never committed for only some of
+/// its datatypes
+async fn freeze_chunk(
     chunk: Chunk,
-    datatype: Datatype,
-    sem: Arc<Semaphore>,
-    query: Arc<MultiQuery>,
-    source: Arc<Source>,
-    sink: Arc<FileOutput>,
-    bar: Arc<ProgressBar>,
+    datatypes: Vec<Datatype>,
+    multi_datatypes: Vec<MultiDatatype>,
+    env: Arc<FreezeEnv>,
 ) -> FreezeChunkSummary {
-    let _permit = sem.acquire().await.expect("Semaphore acquire");
+    let _permit = env.sem.acquire().await.expect("Semaphore acquire");
 
-    let ds = datatype.dataset();
+    // create paths
+    let mut paths: HashMap<Datatype, String> = HashMap::new();
+    let requested: HashSet<Datatype> = env.query.schemas.keys().cloned().collect();
+    let multi_dts = multi_datatypes.iter().flat_map(|mdt| mdt.requested_datatypes(&requested));
+    for datatype in datatypes.iter().cloned().chain(multi_dts) {
+        match chunk.filepath(datatype.dataset().name(), &env.sink) {
+            Err(e) => return FreezeChunkSummary::error(paths, e.into()),
+            Ok(path) => paths.insert(datatype, path),
+        };
+    }
 
-    // create path
-    let path = match chunk.filepath(ds.name(), &sink) {
-        Err(_e) => return FreezeChunkSummary::error(HashMap::new()),
-        Ok(path) => path,
+    // skip datatypes whose files already exist
+    let pending = |dts: HashSet<Datatype>| {
+        env.sink.overwrite ||
+            dts.iter().any(|dt| {
+                paths.get(dt).map(|path| !is_committed(&chunk, *dt, path, &env)).unwrap_or(true)
+            })
     };
-    let paths = HashMap::from([(datatype, path.clone())]);
-
-    // skip path if file already exists
-    if Path::new(&path).exists() && !sink.overwrite {
+    let datatypes: Vec<Datatype> =
+        datatypes.into_iter().filter(|dt| pending([*dt].into())).collect();
+    let multi_datatypes: Vec<MultiDatatype> = multi_datatypes
+        .into_iter()
+        .filter(|mdt| pending(mdt.requested_datatypes(&requested)))
+        .collect();
+    if datatypes.is_empty() && multi_datatypes.is_empty() {
         return FreezeChunkSummary::skip(paths)
     }
+    let multi_dts = multi_datatypes.iter().flat_map(|mdt| mdt.requested_datatypes(&requested));
+    let pending_datatypes: Vec<Datatype> = datatypes.iter().cloned().chain(multi_dts).collect();
+    paths.retain(|datatype, _| pending_datatypes.contains(datatype));
+
+    // abort if output limits exceeded or run was aborted at a prompt
+    if let Err(e) = env.guard.check() {
+        return FreezeChunkSummary::error(paths, e)
+    }
+    if let Err(e) = env.prompt.check() {
+        return FreezeChunkSummary::error(paths, e)
+    }
+    if env.cancel.is_cancelled() {
+        return FreezeChunkSummary::error(paths, cancelled(&chunk))
+    }
+
+    // collect data of every datatype before writing any of it
+    let (collected, multi_collected) = futures::join!(
+        join_all(datatypes.iter().map(|datatype| collect_datatype(&chunk, *datatype, &env))),
+        join_all(multi_datatypes.iter().map(|mdt| collect_multi_datatype(&chunk, *mdt, &env))),
+    );
+    let mut dfs = HashMap::new();
+    let mut decisions = Vec::new();
+    let mut audit: Option<Vec<String>> = None;
+    let mut error = None;
+    for collected in collected.into_iter().chain(multi_collected) {
+        decisions.extend(collected.decisions);
+        if let Some(mismatches) = collected.audit {
+            audit.get_or_insert_with(Vec::new).extend(mismatches);
+        }
+        match collected.dfs {
+            Ok(collected_dfs) => dfs.extend(collected_dfs),
+            Err(e) => error = error.or(Some(e)),
+        }
+    }
+    if let Some(e) = error {
+        return FreezeChunkSummary::error(paths, e).with_decisions(decisions)
+    }
+
+    // write data
+    write_chunk(&chunk, dfs, paths, &env).await.with_decisions(decisions).with_audit(audit)
+}
 
-    // collect data
-    let schema = match query.schemas.get(&datatype) {
+/// dataframes collected for some of the datatypes of a chunk
+struct Collected {
+    dfs: Result<HashMap<Datatype, DataFrame>, FreezeError>,
+    decisions: Vec<String>,
+    audit: Option<Vec<String>>,
+}
+
+async fn collect_datatype(chunk: &Chunk, datatype: Datatype, env: &FreezeEnv) -> Collected {
+    let ds = datatype.dataset();
+    let mut decisions = Vec::new();
+    let schema = match env.query.schemas.get(&datatype) {
         Some(schema) => schema,
-        _ => return FreezeChunkSummary::error(paths),
-    };
-    let collect_output =
-        ds.collect_chunk(&chunk, &source, schema, query.row_filters.get(&datatype)).await;
-    let mut df = match collect_output {
-        Err(_e) => {
-            println!("chunk failed: {:?}", _e);
-            return FreezeChunkSummary::error(paths)
+        _ => {
+            let e = CollectError::BadSchemaError.in_chunk(ds.name(), chunk);
+            return Collected { dfs: Err(e.into()), decisions, audit: None }
         }
+    };
+    let filter = env.query.row_filters.get(&datatype);
+    let ds = &ds;
+    let collect =
+        |source: Source| async move { ds.collect_chunk(chunk, &source, schema, filter).await };
+    let collect_output = collect_or_prompt(ds.name(), chunk, env, &mut decisions, collect).await;
+    let df = match collect_output {
+        Err(e) => return Collected { dfs: Err(e), decisions, audit: None },
         Ok(df) => df,
     };
+    let dfs = HashMap::from([(datatype, df)]);
+    let audit = audit_chunk(chunk, &dfs, env, |source| async move {
+        collect(source).await.map(|df| HashMap::from([(datatype, df)]))
+    })
+    .await;
+    Collected { dfs: Ok(dfs), decisions, audit }
+}
 
-    // write data
-    if let Err(_e) = dataframes::df_to_file(&mut df, &path, &sink) {
-        return FreezeChunkSummary::error(paths)
+async fn collect_multi_datatype(chunk: &Chunk, mdt: MultiDatatype, env: &FreezeEnv) -> Collected {
+    let schemas = &env.query.schemas;
+    let filters: HashMap<Datatype, RowFilter> = mdt
+        .multi_dataset()
+        .datatypes()
+        .into_iter()
+        .filter_map(|datatype| {
+            env.query.row_filters.get(&datatype).map(|filter| (datatype, filter.clone()))
+        })
+        .collect();
+    let filters = &filters;
+    let collect = |source: Source| async move {
+        let multi_dataset = mdt.multi_dataset();
+        multi_dataset.collect_chunk(chunk, &source, schemas.clone(), filters.clone()).await
+    };
+    let mut decisions = Vec::new();
+    let name = mdt.multi_dataset().name();
+    let dfs = match collect_or_prompt(name, chunk, env, &mut decisions, collect).await {
+        Err(e) => return Collected { dfs: Err(e), decisions, audit: None },
+        Ok(dfs) => dfs,
+    };
+    let audit = audit_chunk(chunk, &dfs, env, collect).await;
+    Collected { dfs: Ok(dfs), decisions, audit }
+}
+
+/// collect a chunk, asking what to do when it fails if prompting is enabled
+///
+/// the decision at each prompt is added to decisions, for the summary of the run
+async fn collect_or_prompt<T, F, Fut>(
+    name: &'static str,
+    chunk: &Chunk,
+    env: &FreezeEnv,
+    decisions: &mut Vec<String>,
+    collect: F,
+) -> Result<T, FreezeError>
+where
+    F: Fn(Source) -> Fut,
+    Fut: Future<Output = Result<T, CollectError>>,
+{
+    loop {
+        let source = env.prompt.source(&env.source);
+        let collected = async {
+            match source.chunk_timeout {
+                Some(timeout) => tokio::time::timeout(timeout, collect(source))
+                    .await
+                    .unwrap_or(Err(CollectError::ChunkTimeout(timeout))),
+                None => collect(source).await,
+            }
+        };
+        let result = tokio::select! {
+            result = collected => result,
+            _ = env.cancel.cancelled() => return Err(cancelled(chunk)),
+        };
+        let e = match result {
+            Ok(output) => return Ok(output),
+            Err(e) => e.in_chunk(name, chunk),
+        };
+        env.report(&format!("chunk failed: {}", e));
+        if let Err(log_error) = env.failed.record(name, &chunk.stub().unwrap_or_default(), &e) {
+            env.report(&format!("could not log failed chunk: {}", log_error));
+        }
+        let decision = match env.prompt.decide(&env.source, &env.bar).await {
+            Some(decision) => decision,
+            None => return Err(e.into()),
+        };
+        let stub = chunk.stub().unwrap_or_default();
+        decisions.push(format!("{} {}: {}", name, stub, decision.as_str()));
+        match decision {
+            FailureDecision::Retry | FailureDecision::SwitchProvider => continue,
+            FailureDecision::Skip | FailureDecision::Abort => return Err(e.into()),
+        }
     }
+}
 
-    bar.inc(1);
-    FreezeChunkSummary::success(paths)
+/// collect a chunk a second time if it is sampled for the determinism audit, describing each
+/// datatype whose second response differs from the first
+///
+/// load-balanced providers can serve chunks from nodes on different forks or with different
+/// state, which only shows when the same request is answered twice
+async fn audit_chunk<F, Fut>(
+    chunk: &Chunk,
+    dfs: &HashMap<Datatype, DataFrame>,
+    env: &FreezeEnv,
+    collect: F,
+) -> Option<Vec<String>>
+where
+    F: Fn(Source) -> Fut,
+    Fut: Future<Output = Result<HashMap<Datatype, DataFrame>, CollectError>>,
+{
+    let stub = chunk.stub().unwrap_or_default();
+    if !audit_sampled(&stub, env.source.audit_fraction?) {
+        return None
+    }
+    let second = match collect(env.source.clone()).await {
+        Ok(second) => second,
+        Err(e) => {
+            env.report(&format!("could not audit chunk {}: {}", stub, e));
+            return None
+        }
+    };
+    let mut mismatches = Vec::new();
+    for (datatype, df) in dfs.iter() {
+        let difference = match second.get(datatype) {
+            Some(other) if df.frame_equal_missing(other) => continue,
+            Some(other) if df.height() != other.height() => {
+                format!("{} rows, then {}", df.height(), other.height())
+            }
+            Some(_) => format!("{} rows with different values", df.height()),
+            None => "missing from second response".to_string(),
+        };
+        mismatches.push(format!("{} {}: {}", datatype.dataset().name(), stub, difference));
+    }
+    Some(mismatches)
 }
 
-async fn freeze_multi_datatype_chunk(
-    chunk: Chunk,
-    mdt: MultiDatatype,
-    sem: Arc<Semaphore>,
-    query: Arc<MultiQuery>,
-    source: Arc<Source>,
-    sink: Arc<FileOutput>,
-    bar: Arc<ProgressBar>,
+/// whether a chunk is among the fraction of chunks audited, the same chunks in every run
+///
+/// chunks are picked by keccak of their stub, which unlike std hashers is stable across releases
+fn audit_sampled(stub: &str, fraction: f64) -> bool {
+    let hash = ethers::utils::keccak256(stub.as_bytes());
+    let mut prefix = [0u8; 8];
+    prefix.copy_from_slice(&hash[..8]);
+    (u64::from_be_bytes(prefix) % 10_000) < (fraction * 10_000.0) as u64
+}
+
+/// error of a chunk stopped by cancellation, which is not logged as a failed chunk
+fn cancelled(chunk: &Chunk) -> FreezeError {
+    FreezeError::Cancelled(format!("chunk {} not collected", chunk.stub().unwrap_or_default()))
+}
+
+/// commit all files of a chunk, once disk space and output limits allow, or count its rows
+///
+/// the chunk keeps its collection permit until it is written, so a sink slower than collection,
+/// e.g. sqlite, which serializes writes, holds back the collection of further chunks instead of
+/// piling up collected chunks in memory
+async fn write_chunk(
+    chunk: &Chunk,
+    dfs: HashMap<Datatype, DataFrame>,
+    paths: HashMap<Datatype, String>,
+    env: &FreezeEnv,
 ) -> FreezeChunkSummary {
-    let _permit = sem.acquire().await.expect("Semaphore acquire");
+    env.record_rows(chunk, &dfs);
+    if env.sink.count_only {
+        let stub = chunk.stub().unwrap_or_default();
+        let row_counts = dfs
+            .iter()
+            .map(|(datatype, df)| (*datatype, stub.clone(), df.height() as u64))
+            .collect();
+        env.bar.inc(1);
+        return FreezeChunkSummary::success(paths).with_row_counts(row_counts)
+    }
+    env.disk.wait_for_space(&env.bar).await;
+    if let Err(e) = env.guard.check() {
+        return FreezeChunkSummary::error(paths, e)
+    }
+    let stub = match chunk.stub() {
+        Ok(stub) => stub,
+        Err(e) => return FreezeChunkSummary::error(paths, FileError::from(e).into()),
+    };
+    let summaries = match summarize_rollups(chunk, &dfs, env) {
+        Ok(summaries) => summaries,
+        Err(e) => return FreezeChunkSummary::error(paths, e.into()),
+    };
+    let dfs = match prepare_dfs(dfs, &env.query.schemas, &env.sink) {
+        Ok(dfs) => dfs,
+        Err(e) => return FreezeChunkSummary::error(paths, e.into()),
+    };
+    let entry = match commit_dfs(chunk, dfs, &paths, &stub, env) {
+        Ok(entry) => entry,
+        Err(e) => return FreezeChunkSummary::error(paths, e.into()),
+    };
+    let n_rows = entry.files.iter().map(|f| f.n_rows).sum();
+    let n_bytes = entry.files.iter().map(|f| f.n_bytes).sum();
+    env.guard.record(n_rows, n_bytes, &env.bar).await;
+    env.disk.record(n_bytes, &env.bar);
+    if let Some(rollups) = &env.rollups {
+        if let Err(e) = rollups.add(chunk, summaries) {
+            env.report(&format!("could not add rollups: {}", e));
+        }
+    }
 
-    // create paths
-    let mut paths: HashMap<Datatype, String> = HashMap::new();
-    for ds in mdt.multi_dataset().datasets().values() {
-        match chunk.filepath(ds.name(), &sink) {
-            Err(_e) => return FreezeChunkSummary::error(paths),
-            Ok(path) => paths.insert(ds.datatype(), path),
+    env.bar.inc(1);
+    FreezeChunkSummary::success(paths)
+}
+
+/// commit the dataframes of a chunk to the sqlite database of the run, or else to files
+fn commit_dfs(
+    chunk: &Chunk,
+    dfs: HashMap<Datatype, DataFrame>,
+    paths: &HashMap<Datatype, String>,
+    stub: &str,
+    env: &FreezeEnv,
+) -> Result<ManifestEntry, FileError> {
+    #[cfg(feature = "sqlite")]
+    if let Some(sqlite) = &env.sqlite {
+        return sqlite.commit_chunk(dfs.into_iter().collect(), stub, &env.sink, &env.manifest)
+    }
+    chunk_files(chunk, dfs, paths, &env.sink).and_then(|files| {
+        let datatypes: Vec<Datatype> = paths.keys().cloned().collect();
+        dataframes::commit_chunk(&datatypes, files, stub, &env.sink, &env.manifest)
+    })
+}
+
+/// apply output settings that depend on schemas, row order before decimal conversion and nesting
+fn prepare_dfs(
+    dfs: HashMap<Datatype, DataFrame>,
+    schemas: &HashMap<Datatype, Table>,
+    sink: &FileOutput,
+) -> Result<HashMap<Datatype, DataFrame>, FileError> {
+    let mut prepared = HashMap::new();
+    for (datatype, mut df) in dfs.into_iter() {
+        let schema = schemas.get(&datatype);
+        if sink.deterministic {
+            df = dataframes::sort_deterministic(df, schema)?;
+        }
+        if let (Some(scale), Some(schema)) = (sink.decimal_scale, schema) {
+            df = dataframes::cast_decimal_columns(df, schema, scale)?;
+        }
+        if datatype == Datatype::Traces && sink.nested_traces {
+            df = dataframes::nest_traces(df)?;
+        }
+        prepared.insert(datatype, df);
+    }
+    Ok(prepared)
+}
+
+/// partitioning applied to a datatype, if any
+fn partition_by(datatype: Datatype, sink: &FileOutput) -> Option<&PartitionBy> {
+    sink.partition_by
+        .as_ref()
+        .filter(|partition_by| dataframes::partition_column(datatype, partition_by).is_some())
+}
+
+/// whether a datatype's chunk was already committed, to one file, a set of partitions, or sqlite
+///
+/// counting rows recounts every chunk, since counts are not stored
+fn is_committed(chunk: &Chunk, datatype: Datatype, path: &str, env: &FreezeEnv) -> bool {
+    if env.sink.count_only {
+        return false
+    }
+    #[cfg(feature = "sqlite")]
+    if let Some(sqlite) = &env.sqlite {
+        let stub = chunk.stub().unwrap_or_default();
+        return sqlite.is_committed(datatype.dataset().name(), &stub)
+    }
+    match partition_by(datatype, &env.sink) {
+        Some(_) => chunk
+            .stub()
+            .map(|stub| env.manifest.is_chunk_committed(datatype.dataset().name(), &stub))
+            .unwrap_or(false),
+        None => env.manifest.is_committed(path),
+    }
+}
+
+/// assign each dataframe of a chunk to its output file, splitting partitioned datatypes
+fn chunk_files(
+    chunk: &Chunk,
+    dfs: HashMap<Datatype, DataFrame>,
+    paths: &HashMap<Datatype, String>,
+    sink: &FileOutput,
+) -> Result<Vec<(Datatype, String, DataFrame)>, FileError> {
+    let mut files = Vec::new();
+    for (datatype, df) in dfs.into_iter() {
+        let partition_by = partition_by(datatype, sink);
+        let column = partition_by.and_then(|p| dataframes::partition_column(datatype, p));
+        match (partition_by, column) {
+            (Some(partition_by), Some(column)) => {
+                for (label, part) in dataframes::partition_df(&df, column, partition_by)? {
+                    let name = format!("{}__{}", datatype.dataset().name(), label);
+                    files.push((datatype, chunk.filepath(&name, sink)?, part));
+                }
+            }
+            _ => {
+                let path = paths
+                    .get(&datatype)
+                    .ok_or(FileError::NoFilePathError("no path given for dataframe".to_string()))?;
+                files.push((datatype, path.clone(), df));
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// tracks output written across chunks, aborting or prompting when a limit is exceeded
+struct OutputGuard {
+    max_rows: Option<u64>,
+    max_bytes: Option<u64>,
+    prompt: bool,
+    verbose: bool,
+    rows: AtomicU64,
+    bytes: AtomicU64,
+    state: Mutex<GuardState>,
+    prompting: tokio::sync::Mutex<()>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum GuardState {
+    Running,
+    Lifted,
+    Aborted,
+}
+
+impl OutputGuard {
+    fn new(sink: &FileOutput) -> OutputGuard {
+        OutputGuard {
+            max_rows: sink.max_output_rows,
+            max_bytes: sink.max_output_bytes,
+            prompt: sink.prompt_on_output_limit,
+            verbose: sink.verbose,
+            rows: AtomicU64::new(0),
+            bytes: AtomicU64::new(0),
+            state: Mutex::new(GuardState::Running),
+            prompting: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    fn state(&self) -> GuardState {
+        *self.state.lock().expect("output guard lock")
+    }
+
+    fn check(&self) -> Result<(), FreezeError> {
+        match self.state() {
+            GuardState::Aborted => {
+                Err(FreezeError::OutputLimitExceeded("chunk not collected".to_string()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    async fn record(&self, rows: u64, bytes: u64, bar: &ProgressBar) {
+        let rows = self.rows.fetch_add(rows, Ordering::SeqCst) + rows;
+        let bytes = self.bytes.fetch_add(bytes, Ordering::SeqCst) + bytes;
+        let message = match (self.max_rows, self.max_bytes) {
+            (Some(max_rows), _) if rows > max_rows => {
+                format!("wrote {} rows, exceeding --max-output-rows {}", rows, max_rows)
+            }
+            (_, Some(max_bytes)) if bytes > max_bytes => {
+                format!("wrote {} bytes, exceeding --max-output-bytes {}", bytes, max_bytes)
+            }
+            _ => return,
         };
+
+        let _prompting = self.prompting.lock().await;
+        if self.state() == GuardState::Running {
+            let proceed = self.prompt && prompt_continue(&message, bar).await;
+            let state = match proceed {
+                true => GuardState::Lifted,
+                false => {
+                    let message = format!("{}, aborting remaining chunks", message);
+                    report(bar, self.verbose, &message);
+                    GuardState::Aborted
+                }
+            };
+            *self.state.lock().expect("output guard lock") = state;
+        }
     }
+}
 
-    // skip path if file already exists
-    if paths.values().all(|path| Path::new(&path).exists()) && !sink.overwrite {
-        return FreezeChunkSummary::skip(paths)
+/// choice given at a prompt about a failed chunk
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum FailureDecision {
+    Retry,
+    Skip,
+    Abort,
+    SwitchProvider,
+}
+
+impl FailureDecision {
+    fn as_str(&self) -> &'static str {
+        match self {
+            FailureDecision::Retry => "retry",
+            FailureDecision::Skip => "skip",
+            FailureDecision::Abort => "abort",
+            FailureDecision::SwitchProvider => "switch provider",
+        }
     }
+}
 
-    // collect data
-    let collect_result = mdt
-        .multi_dataset()
-        .collect_chunk(&chunk, &source, query.schemas.clone(), HashMap::new())
-        .await;
-    let mut dfs = match collect_result {
-        Err(_e) => {
-            println!("chunk failed: {:?}", _e);
-            return FreezeChunkSummary::error(paths)
+/// asks what to do with failed chunks, one prompt at a time
+struct FailurePrompt {
+    enabled: bool,
+    prompting: tokio::sync::Mutex<()>,
+    aborted: AtomicBool,
+    /// source using the rpc url given at a switch provider prompt, used by all later requests
+    switched: Mutex<Option<Source>>,
+}
+
+impl FailurePrompt {
+    fn new(sink: &FileOutput) -> FailurePrompt {
+        FailurePrompt {
+            enabled: sink.prompt_on_failure,
+            prompting: tokio::sync::Mutex::new(()),
+            aborted: AtomicBool::new(false),
+            switched: Mutex::new(None),
         }
-        Ok(dfs) => dfs,
+    }
+
+    fn check(&self) -> Result<(), FreezeError> {
+        match self.aborted.load(Ordering::SeqCst) {
+            true => Err(FreezeError::Aborted("chunk not collected".to_string())),
+            false => Ok(()),
+        }
+    }
+
+    /// source to collect chunks with, the original one unless the provider was switched
+    fn source(&self, original: &Source) -> Source {
+        match &*self.switched.lock().expect("switched source lock") {
+            Some(source) => source.clone(),
+            None => original.clone(),
+        }
+    }
+
+    /// ask what to do with a failed chunk, or None if prompting is disabled
+    async fn decide(&self, original: &Source, bar: &ProgressBar) -> Option<FailureDecision> {
+        if !self.enabled {
+            return None
+        }
+        let _prompting = self.prompting.lock().await;
+        if self.aborted.load(Ordering::SeqCst) {
+            return Some(FailureDecision::Abort)
+        }
+        loop {
+            let message = "[r]etry chunk, [s]kip chunk, [a]bort run, or s[w]itch provider? [r] ";
+            // stdin was closed, so there is no one left to ask about this or later chunks
+            let answer = match prompt_line(message, bar).await {
+                Some(answer) => answer.to_lowercase(),
+                None => {
+                    self.aborted.store(true, Ordering::SeqCst);
+                    return Some(FailureDecision::Abort)
+                }
+            };
+            let decision = match answer.as_str() {
+                "" | "r" | "retry" => FailureDecision::Retry,
+                "s" | "skip" => FailureDecision::Skip,
+                "a" | "abort" => FailureDecision::Abort,
+                "w" | "switch" => FailureDecision::SwitchProvider,
+                _ => continue,
+            };
+            match decision {
+                FailureDecision::Abort => self.aborted.store(true, Ordering::SeqCst),
+                FailureDecision::SwitchProvider => {
+                    let url = match prompt_line("rpc url: ", bar).await {
+                        Some(url) => url,
+                        None => {
+                            self.aborted.store(true, Ordering::SeqCst);
+                            return Some(FailureDecision::Abort)
+                        }
+                    };
+                    match switch_provider(&url, &self.source(original)).await {
+                        Ok(source) => {
+                            *self.switched.lock().expect("switched source lock") = Some(source)
+                        }
+                        Err(e) => {
+                            bar.suspend(|| println!("could not switch provider: {}", e));
+                            continue
+                        }
+                    }
+                }
+                FailureDecision::Retry | FailureDecision::Skip => {}
+            }
+            return Some(decision)
+        }
+    }
+}
+
+/// copy of a source that sends requests to another rpc url of the same chain
+async fn switch_provider(url: &str, source: &Source) -> Result<Source, String> {
+    let client = RpcClient::new(url).map_err(|e| e.to_string())?;
+    let provider = Provider::new(client);
+    let _permit = source.permit_request(RequestPriority::Metadata).await;
+    let chain_id = provider.get_chainid().await.map_err(|e| e.to_string())?.as_u64();
+    if chain_id != source.chain_id {
+        return Err(format!("rpc url has chain id {}, expected {}", chain_id, source.chain_id))
+    }
+    Ok(Source { provider: Arc::new(provider), ..source.clone() })
+}
+
+/// seconds between disk space checks while paused
+const DISK_POLL_SECONDS: u64 = 10;
+
+/// monitors free disk space, pausing writes while it is below a threshold
+struct DiskMonitor {
+    output_dir: String,
+    verbose: bool,
+    min_free_space: Option<u64>,
+    n_chunks: u64,
+    n_written: AtomicU64,
+    bytes_written: AtomicU64,
+    warned: AtomicBool,
+    paused: tokio::sync::Mutex<()>,
+}
+
+impl DiskMonitor {
+    /// monitor of a query's output, recorded once per chunk with the files of all its datatypes
+    fn new(sink: &FileOutput, query: &MultiQuery) -> DiskMonitor {
+        DiskMonitor {
+            output_dir: sink.output_dir.clone(),
+            verbose: sink.verbose,
+            min_free_space: sink.min_free_space,
+            n_chunks: query.chunks.len() as u64,
+            n_written: AtomicU64::new(0),
+            bytes_written: AtomicU64::new(0),
+            warned: AtomicBool::new(false),
+            paused: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    fn free_space(&self) -> Option<u64> {
+        available_space(&self.output_dir)
+    }
+
+    /// refuse to start when free space is already below --min-free-space, and warn when the
+    /// estimated output of the run does not fit in free space
+    fn preflight(&self, estimated_bytes: u64, bar: &ProgressBar) -> Result<(), FreezeError> {
+        let free = match self.free_space() {
+            Some(free) => free,
+            None => return Ok(()),
+        };
+        if let Some(min_free_space) = self.min_free_space {
+            if free < min_free_space {
+                return Err(FreezeError::InsufficientDiskSpace(format!(
+                    "{} free in {}, need at least {} (--min-free-space)",
+                    format_bytes(free),
+                    self.output_dir,
+                    format_bytes(min_free_space)
+                )))
+            }
+        }
+        if estimated_bytes > free {
+            let message = format!(
+                "warning: output is roughly estimated at {} but only {} is free in {}",
+                format_bytes(estimated_bytes),
+                format_bytes(free),
+                self.output_dir
+            );
+            report(bar, self.verbose, &message);
+        }
+        Ok(())
+    }
+
+    /// wait until free space is above threshold, so that files are never partially written
+    async fn wait_for_space(&self, bar: &ProgressBar) {
+        let min_free_space = match self.min_free_space {
+            Some(min_free_space) => min_free_space,
+            None => return,
+        };
+        let _paused = self.paused.lock().await;
+        let mut notified = false;
+        while let Some(free) = self.free_space() {
+            if free >= min_free_space {
+                if notified {
+                    report(bar, self.verbose, "disk space available again, resuming");
+                }
+                return
+            }
+            if !notified {
+                let message = format!(
+                    "only {} free in {}, below --min-free-space {}. pausing until space is \
+                    freed. completed chunks are kept, so it is safe to stop and rerun the same \
+                    command later to resume",
+                    format_bytes(free),
+                    self.output_dir,
+                    format_bytes(min_free_space)
+                );
+                report(bar, self.verbose, &message);
+                notified = true;
+            }
+            tokio::time::sleep(std::time::Duration::from_secs(DISK_POLL_SECONDS)).await;
+        }
+    }
+
+    /// warn once if estimated remaining output exceeds free space
+    fn record(&self, bytes: u64, bar: &ProgressBar) {
+        self.n_written.fetch_add(1, Ordering::SeqCst);
+        self.bytes_written.fetch_add(bytes, Ordering::SeqCst);
+        let remaining = self.remaining_bytes();
+        if let Some(free) = self.free_space() {
+            if remaining > free && !self.warned.swap(true, Ordering::SeqCst) {
+                let message = format!(
+                    "warning: remaining output is estimated at {} but only {} is free in {}",
+                    format_bytes(remaining),
+                    format_bytes(free),
+                    self.output_dir
+                );
+                report(bar, self.verbose, &message);
+            }
+        }
+    }
+
+    /// output of the chunks not written yet, at the average size of the chunks written so far
+    fn remaining_bytes(&self) -> u64 {
+        let n_written = self.n_written.load(Ordering::SeqCst);
+        let bytes_written = self.bytes_written.load(Ordering::SeqCst);
+        match n_written {
+            0 => 0,
+            n_written => (bytes_written / n_written) * self.n_chunks.saturating_sub(n_written),
+        }
+    }
+}
+
+/// rough output size of a query, from the number of blocks or transactions of its chunks and
+/// typical parquet sizes of each dataset on mainnet. address chunks are not estimated
+fn estimate_output_bytes(query: &MultiQuery) -> u64 {
+    let (n_blocks, n_transactions) =
+        query.chunks.iter().fold((0, 0), |(n_blocks, n_transactions), chunk| match chunk {
+            Chunk::Block(chunk) => (n_blocks + chunk.size(), n_transactions),
+            Chunk::Transaction(chunk) => (n_blocks, n_transactions + chunk.size()),
+            Chunk::Address(_) => (n_blocks, n_transactions),
+        });
+    query
+        .schemas
+        .keys()
+        .map(|datatype| {
+            let per_block = estimated_bytes_per_block(*datatype);
+            n_blocks * per_block + n_transactions * per_block / TRANSACTIONS_PER_BLOCK
+        })
+        .sum()
+}
+
+/// typical number of transactions of a mainnet block
+const TRANSACTIONS_PER_BLOCK: u64 = 150;
+
+/// typical parquet bytes per mainnet block of each dataset
+fn estimated_bytes_per_block(datatype: Datatype) -> u64 {
+    match datatype {
+        Datatype::Blocks | Datatype::GasPercentiles | Datatype::Genesis => 500,
+        Datatype::Transactions | Datatype::ZkSyncTransactions => 40_000,
+        Datatype::Logs | Datatype::Erc20Transfers | Datatype::Erc20Approvals => 30_000,
+        Datatype::Traces => 150_000,
+        Datatype::VmTraces => 2_000_000,
+        Datatype::StorageDiffs => 40_000,
+        Datatype::BalanceDiffs | Datatype::NonceDiffs | Datatype::CodeDiffs => 10_000,
+        _ => 5_000,
+    }
+}
+
+/// free space of the filesystem holding a directory, none if it cannot be determined
+pub fn available_space(dir: &str) -> Option<u64> {
+    fs2::available_space(dir).ok()
+}
+
+fn format_bytes(bytes: u64) -> String {
+    match bytes {
+        b if b >= 1_000_000_000 => format!("{:.1}GB", b as f64 / 1e9),
+        b if b >= 1_000_000 => format!("{:.1}MB", b as f64 / 1e6),
+        b if b >= 1_000 => format!("{:.1}KB", b as f64 / 1e3),
+        b => format!("{}B", b),
+    }
+}
+
+/// ask for a line on stdin, reading it on a blocking thread so runtime workers keep running
+///
+/// returns None when stdin is closed or unreadable, e.g. after ctrl-d, so that callers do not
+/// mistake it for an empty answer
+async fn prompt_line(message: &str, bar: &ProgressBar) -> Option<String> {
+    let message = message.to_string();
+    let bar = bar.clone();
+    let answer = tokio::task::spawn_blocking(move || {
+        bar.suspend(|| {
+            print!("{}", message);
+            let _ = std::io::stdout().flush();
+            let mut answer = String::new();
+            match std::io::stdin().read_line(&mut answer) {
+                Ok(0) | Err(_) => None,
+                Ok(_) => Some(answer.trim().to_string()),
+            }
+        })
+    });
+    answer.await.ok().flatten()
+}
+
+async fn prompt_continue(message: &str, bar: &ProgressBar) -> bool {
+    let answer = prompt_line(&format!("{}, continue anyway? [y/N] ", message), bar).await;
+    matches!(answer.map(|answer| answer.to_lowercase()).as_deref(), Some("y" | "yes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use ethers::prelude::U64;
+
+    use super::*;
+    use crate::types::{
+        testing, ColumnEncoding, FileFormat, MockFetcher, RpcClientError, RpcSource,
     };
 
-    // write data
-    if let Err(_e) = dataframes::dfs_to_files(&mut dfs, &paths, &sink) {
-        return FreezeChunkSummary::error(paths)
+    #[test]
+    fn disk_estimate_counts_each_chunk_once() {
+        let schemas = [Datatype::Blocks, Datatype::Transactions, Datatype::Logs]
+            .into_iter()
+            .map(|datatype| {
+                let schema = datatype.table_schema(&ColumnEncoding::Hex, &None, &None, &None, None);
+                (datatype, schema.unwrap())
+            })
+            .collect();
+        let chunks = (0..4).map(|i| Chunk::Block(BlockChunk::Range(i * 10, i * 10 + 9))).collect();
+        let query = MultiQuery { schemas, chunks, row_filters: HashMap::new(), adaptive: None };
+        let dir = std::env::temp_dir().to_string_lossy().into_owned();
+        let disk = DiskMonitor::new(&testing::file_output(&dir, &FileFormat::Parquet), &query);
+
+        // each chunk is recorded once, with the bytes of the files of all of its datatypes
+        disk.record(3_000, &ProgressBar::hidden());
+        assert_eq!(disk.remaining_bytes(), 9_000);
+        disk.record(3_000, &ProgressBar::hidden());
+        assert_eq!(disk.remaining_bytes(), 6_000);
     }
 
-    bar.inc(1);
-    FreezeChunkSummary::success(paths)
+    #[test]
+    fn audit_samples_a_fraction_of_chunks() {
+        let stubs: Vec<String> =
+            (0..1000).map(|i| format!("{:08}_to_{:08}", i * 1000, i * 1000 + 999)).collect();
+        let n_sampled = |fraction| stubs.iter().filter(|s| audit_sampled(s, fraction)).count();
+        assert_eq!(n_sampled(0.0), 0);
+        assert_eq!(n_sampled(1.0), 1000);
+        assert!((400..600).contains(&n_sampled(0.5)));
+    }
+
+    fn follow(source: Source) -> Follow {
+        Follow {
+            start_block: 0,
+            confirmations: 0,
+            chunk_size: 1,
+            poll_interval: std::time::Duration::from_millis(10),
+            ws_url: None,
+            source,
+            max_block_lag: None,
+            max_head_stall: None,
+            alert_webhook: None,
+            exit_on_alert: false,
+            record_reorgs: false,
+            tip: false,
+            preconfirmed: false,
+        }
+    }
+
+    #[test]
+    fn follow_lag_is_measured_from_confirmed_head() {
+        let source = testing::offline_source(MockFetcher::default(), 1);
+        let follow = Follow { confirmations: 64, max_block_lag: Some(10), ..follow(source) };
+
+        // caught up to the confirmed head, even though it is far behind the chain head
+        let mut health = FollowHealth::new();
+        assert!(health.check(&follow, Some(1_000), 937).is_empty());
+
+        // lagging the confirmed head alerts once, until following catches up again
+        let alerts = health.check(&follow, Some(1_020), 937);
+        assert_eq!(alerts.len(), 1);
+        assert!(alerts[0].contains("20 blocks behind confirmed head 956"), "{}", alerts[0]);
+        assert!(health.check(&follow, Some(1_021), 937).is_empty());
+        assert!(health.check(&follow, Some(1_021), 957).is_empty());
+        assert!(!health.lagging);
+
+        // preconfirmed following is measured from the chain head itself
+        let follow = Follow { preconfirmed: true, ..follow };
+        let mut health = FollowHealth::new();
+        assert_eq!(health.check(&follow, Some(1_000), 937).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn follow_collects_confirmed_blocks() {
+        let dir = std::env::temp_dir().join(format!("cryo_follow_{}", std::process::id()));
+        let dir = dir.to_string_lossy().into_owned();
+        std::fs::create_dir_all(&dir).unwrap();
+        let schema = Datatype::Blocks.table_schema(&ColumnEncoding::Hex, &None, &None, &None, None);
+        let query = MultiQuery {
+            schemas: HashMap::from([(Datatype::Blocks, schema.unwrap())]),
+            chunks: vec![Chunk::Block(BlockChunk::Range(0, 9))],
+            row_filters: HashMap::new(),
+            adaptive: None,
+        };
+        let rpc = MockFetcher { latest_block: 30, ..MockFetcher::default() };
+        let source = testing::offline_source(rpc, 1);
+        // the head never advances, so the stall alert ends the run once following has caught up
+        let follow = Follow {
+            start_block: 10,
+            confirmations: 5,
+            chunk_size: 4,
+            max_head_stall: Some(std::time::Duration::from_millis(500)),
+            exit_on_alert: true,
+            ..follow(source.clone())
+        };
+        let sink = testing::file_output(&dir, &FileFormat::Parquet);
+
+        let result = freeze_and_follow(&query, &source, &sink, &follow).await;
+        assert!(matches!(result, Err(FreezeError::FollowUnhealthy(_))));
+        let mut stubs: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+            .filter_map(|name| Some(name.strip_suffix(".parquet")?.split("__").last()?.to_string()))
+            .collect();
+        stubs.sort();
+        let expected = [(0, 9), (10, 13), (14, 17), (18, 21), (22, 25)]
+            .map(|(start, end)| format!("{:08}_to_{:08}", start, end));
+        assert_eq!(stubs, expected);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    /// offline chain whose blocks from a fork height are replaced once it forks
+    #[derive(Debug, Default, Clone)]
+    struct ForkingChain {
+        head: Arc<AtomicU64>,
+        fork: Arc<Mutex<Option<u64>>>,
+    }
+
+    impl ForkingChain {
+        fn hash(number: u64, branch: u8) -> H256 {
+            let mut hash = H256::from_low_u64_be(number);
+            hash.0[0] = branch;
+            hash
+        }
+
+        fn branch(&self, number: u64) -> u8 {
+            match *self.fork.lock().unwrap() {
+                Some(fork) if number >= fork => 1,
+                _ => 0,
+            }
+        }
+
+        fn block(&self, number: u64, branch: u8) -> serde_json::Value {
+            let params = serde_json::json!([U64::from(number), false]);
+            let mut block =
+                MockFetcher::default().respond("eth_getBlockByNumber", &params).unwrap();
+            let parent_branch = match self.branch(number.saturating_sub(1)) {
+                0 => 0,
+                _ => branch,
+            };
+            block["hash"] = serde_json::json!(Self::hash(number, branch));
+            block["parentHash"] =
+                serde_json::json!(Self::hash(number.saturating_sub(1), parent_branch));
+            block
+        }
+    }
+
+    impl RpcSource for ForkingChain {
+        fn respond(
+            &self,
+            method: &str,
+            params: &serde_json::Value,
+        ) -> Result<serde_json::Value, RpcClientError> {
+            let head = self.head.load(Ordering::SeqCst);
+            match method {
+                "eth_getBlockByNumber" => {
+                    let number = match params[0].as_str() {
+                        Some("latest") => head,
+                        _ => serde_json::from_value::<U64>(params[0].clone()).unwrap().as_u64(),
+                    };
+                    Ok(self.block(number, self.branch(number)))
+                }
+                "eth_getBlockByHash" => {
+                    let hash: H256 = serde_json::from_value(params[0].clone()).unwrap();
+                    Ok(self.block(hash.to_low_u64_be(), hash.0[0]))
+                }
+                _ => Err(RpcClientError::UnsupportedMethod(method.to_string())),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn reorg_tracker_finds_common_ancestor() {
+        let chain = ForkingChain::default();
+        let provider = Provider::new(RpcClient::offline(chain.clone()));
+        let dir = std::env::temp_dir().join(format!("cryo_reorgs_{}", std::process::id()));
+        let mut tracker = ReorgTracker::new(&dir.to_string_lossy());
+
+        chain.head.store(100, Ordering::SeqCst);
+        assert!(tracker.poll(&provider).await.unwrap().is_none());
+        chain.head.store(101, Ordering::SeqCst);
+        assert!(tracker.poll(&provider).await.unwrap().is_none());
+
+        // blocks 99 to 101 are replaced, and the new chain is one block longer
+        *chain.fork.lock().unwrap() = Some(99);
+        chain.head.store(102, Ordering::SeqCst);
+        let reorg = tracker.poll(&provider).await.unwrap().expect("reorg");
+        assert_eq!(reorg.old_head_number, 101);
+        assert_eq!(reorg.new_head_number, 102);
+        assert_eq!(reorg.common_ancestor, Some(98));
+        assert_eq!(reorg.depth, 3);
+        assert_eq!(reorg.old_head_hash, encode_hex(ForkingChain::hash(101, 0).as_bytes()));
+
+        // the new chain is extended without another reorg
+        chain.head.store(103, Ordering::SeqCst);
+        assert!(tracker.poll(&provider).await.unwrap().is_none());
+    }
 }
@@ -10,8 +10,17 @@
 mod collect;
 mod datasets;
 mod freeze;
+pub mod lookup;
 mod types;
 
-pub use collect::{collect, collect_multiple};
-pub use freeze::freeze;
+pub use collect::{collect, collect_multiple, collect_on};
+pub use freeze::{
+    available_space, freeze, freeze_and_follow, freeze_empty_schemas, freeze_on,
+    freeze_until_cancelled,
+};
+pub use tokio_util::sync::CancellationToken;
 pub use types::*;
+
+// used in benches but not lib.rs
+#[cfg(test)]
+use criterion as _;
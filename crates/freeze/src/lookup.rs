@@ -0,0 +1,126 @@
+//! binary searches over chain history, e.g. the block of a timestamp
+
+use ethers::prelude::*;
+
+use crate::types::{CollectError, RpcClient};
+
+/// get timestamp of a block
+pub async fn block_timestamp(
+    block_number: u64,
+    provider: &Provider<RpcClient>,
+) -> Result<u64, CollectError> {
+    match provider.get_block(block_number).await {
+        Ok(Some(block)) => Ok(block.timestamp.as_u64()),
+        Ok(None) => Err(CollectError::CollectError(format!("block {} not found", block_number))),
+        Err(e) => Err(CollectError::rpc("eth_getBlockByNumber", e)),
+    }
+}
+
+/// binary search for first block in [lower, upper] with timestamp at or after given timestamp
+///
+/// block upper must have a timestamp at or after the given timestamp
+pub async fn first_block_after(
+    timestamp: u64,
+    mut lower: u64,
+    mut upper: u64,
+    provider: &Provider<RpcClient>,
+) -> Result<u64, CollectError> {
+    while lower < upper {
+        let mid = lower + (upper - lower) / 2;
+        if block_timestamp(mid, provider).await? >= timestamp {
+            upper = mid;
+        } else {
+            lower = mid + 1;
+        }
+    }
+    Ok(lower)
+}
+
+/// first block with timestamp at or after given timestamp, None if no such block exists yet
+pub async fn block_at_timestamp(
+    timestamp: u64,
+    provider: &Provider<RpcClient>,
+) -> Result<Option<u64>, CollectError> {
+    let latest_block = provider
+        .get_block_number()
+        .await
+        .map_err(|e| CollectError::rpc("eth_blockNumber", e))?
+        .as_u64();
+    if block_timestamp(latest_block, provider).await? < timestamp {
+        return Ok(None)
+    }
+    first_block_after(timestamp, 0, latest_block, provider).await.map(Some)
+}
+
+/// binary search for the block in [lower, upper] of the first transaction sent by an address
+///
+/// searches for the first block after which the nonce of the address is nonzero, which requires
+/// an archive node. None if the address sent no transaction by block upper
+pub async fn first_transaction_block(
+    address: H160,
+    mut lower: u64,
+    mut upper: u64,
+    provider: &Provider<RpcClient>,
+) -> Result<Option<u64>, CollectError> {
+    if nonce_at(address, upper, provider).await? == 0 {
+        return Ok(None)
+    }
+    while lower < upper {
+        let mid = lower + (upper - lower) / 2;
+        if nonce_at(address, mid, provider).await? > 0 {
+            upper = mid;
+        } else {
+            lower = mid + 1;
+        }
+    }
+    Ok(Some(lower))
+}
+
+/// get nonce of an address after a block
+pub async fn nonce_at(
+    address: H160,
+    block_number: u64,
+    provider: &Provider<RpcClient>,
+) -> Result<u64, CollectError> {
+    provider
+        .get_transaction_count(address, Some(block_number.into()))
+        .await
+        .map(|nonce| nonce.as_u64())
+        .map_err(|e| CollectError::rpc("eth_getTransactionCount", e))
+}
+
+/// get code of an address after a block, empty if the address has no code
+pub async fn code_at(
+    address: H160,
+    block_number: u64,
+    provider: &Provider<RpcClient>,
+) -> Result<Bytes, CollectError> {
+    provider
+        .get_code(address, Some(block_number.into()))
+        .await
+        .map_err(|e| CollectError::rpc("eth_getCode", e))
+}
+
+/// binary search for the block in [lower, upper] that deployed the code of an address
+///
+/// searches for the first block after which the address has code, which requires an archive
+/// node. None if the address has no code after block upper
+pub async fn deployment_block(
+    address: H160,
+    mut lower: u64,
+    mut upper: u64,
+    provider: &Provider<RpcClient>,
+) -> Result<Option<u64>, CollectError> {
+    if code_at(address, upper, provider).await?.is_empty() {
+        return Ok(None)
+    }
+    while lower < upper {
+        let mid = lower + (upper - lower) / 2;
+        if !code_at(address, mid, provider).await?.is_empty() {
+            upper = mid;
+        } else {
+            lower = mid + 1;
+        }
+    }
+    Ok(Some(lower))
+}
@@ -0,0 +1,142 @@
+use std::sync::Mutex;
+
+use ethers::prelude::*;
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::types::CollectError;
+
+/// client for the standard beacon node api
+///
+/// historical validator states generally require a beacon node that retains archive states
+#[derive(Debug)]
+pub struct BeaconClient {
+    url: String,
+    client: reqwest::Client,
+    timing: Mutex<Option<SlotTiming>>,
+}
+
+/// validator of the beacon chain at a given state
+#[derive(Debug)]
+pub struct Validator {
+    /// validator index
+    pub index: u64,
+    /// bls public key
+    pub pubkey: Vec<u8>,
+    /// withdrawal credentials, 0x00 for bls credentials and 0x01 for an execution address
+    pub withdrawal_credentials: Vec<u8>,
+    /// validator status, e.g. active_ongoing
+    pub status: String,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct SlotTiming {
+    genesis_time: u64,
+    seconds_per_slot: u64,
+}
+
+#[derive(Deserialize)]
+struct Response<T> {
+    data: T,
+}
+
+#[derive(Deserialize)]
+struct Genesis {
+    genesis_time: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+struct Spec {
+    seconds_per_slot: String,
+}
+
+#[derive(Deserialize)]
+struct ValidatorEntry {
+    index: String,
+    status: String,
+    validator: ValidatorData,
+}
+
+#[derive(Deserialize)]
+struct ValidatorData {
+    pubkey: Bytes,
+    withdrawal_credentials: Bytes,
+}
+
+impl BeaconClient {
+    /// create client for beacon node at url
+    pub fn new(url: &str) -> BeaconClient {
+        BeaconClient {
+            url: url.trim_end_matches('/').to_string(),
+            client: reqwest::Client::new(),
+            timing: Mutex::new(None),
+        }
+    }
+
+    /// slot whose timestamp is the given execution block timestamp
+    pub async fn slot_at(&self, timestamp: u64) -> Result<u64, CollectError> {
+        let cached = *self.timing.lock().expect("beacon timing lock");
+        let timing = match cached {
+            Some(timing) => timing,
+            None => {
+                let genesis: Genesis = self.get("/eth/v1/beacon/genesis").await?;
+                let spec: Spec = self.get("/eth/v1/config/spec").await?;
+                let timing = SlotTiming {
+                    genesis_time: parse_number(&genesis.genesis_time)?,
+                    seconds_per_slot: parse_number(&spec.seconds_per_slot)?,
+                };
+                *self.timing.lock().expect("beacon timing lock") = Some(timing);
+                timing
+            }
+        };
+        match timestamp.checked_sub(timing.genesis_time) {
+            Some(elapsed) if timing.seconds_per_slot > 0 => Ok(elapsed / timing.seconds_per_slot),
+            _ => Err(CollectError::CollectError(format!(
+                "timestamp {} is before beacon chain genesis",
+                timestamp
+            ))),
+        }
+    }
+
+    /// all validators of the beacon state at slot
+    pub async fn validators(&self, slot: u64) -> Result<Vec<Validator>, CollectError> {
+        let entries: Vec<ValidatorEntry> =
+            self.get(&format!("/eth/v1/beacon/states/{}/validators", slot)).await?;
+        entries
+            .into_iter()
+            .map(|entry| {
+                Ok(Validator {
+                    index: parse_number(&entry.index)?,
+                    pubkey: entry.validator.pubkey.to_vec(),
+                    withdrawal_credentials: entry.validator.withdrawal_credentials.to_vec(),
+                    status: entry.status,
+                })
+            })
+            .collect()
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, CollectError> {
+        let request_error =
+            |e: reqwest::Error| CollectError::CollectError(format!("beacon request failed: {}", e));
+        let body = self
+            .client
+            .get(format!("{}{}", self.url, path))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(request_error)?
+            .text()
+            .await
+            .map_err(request_error)?;
+        let response: Response<T> = serde_json::from_str(&body)
+            .map_err(|e| CollectError::CollectError(format!("invalid beacon response: {}", e)))?;
+        Ok(response.data)
+    }
+}
+
+/// beacon api encodes integers as decimal strings
+fn parse_number(value: &str) -> Result<u64, CollectError> {
+    value
+        .parse()
+        .map_err(|_e| CollectError::CollectError(format!("invalid beacon api number: {}", value)))
+}
@@ -0,0 +1,100 @@
+use ethers::prelude::*;
+use serde::de::DeserializeOwned;
+
+/// chain whose rpc responses carry prominent nonstandard fields, extracted into their own columns
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ChainExtension {
+    /// celo gateway fee and fee currency transaction fields
+    Celo,
+    /// polygon bor state-sync transactions
+    Polygon,
+    /// arbitrum l1 block fields and retryable ticket transaction fields
+    Arbitrum,
+}
+
+impl ChainExtension {
+    /// get schema extension of a chain, None for chains without one
+    pub fn from_chain_id(chain_id: u64) -> Option<ChainExtension> {
+        match chain_id {
+            42220 | 44787 | 62320 => Some(ChainExtension::Celo),
+            137 | 80001 | 80002 => Some(ChainExtension::Polygon),
+            42161 | 42170 | 421613 | 421614 => Some(ChainExtension::Arbitrum),
+            _ => None,
+        }
+    }
+
+    /// block columns populated on this chain
+    pub fn block_columns(&self) -> Vec<&'static str> {
+        match self {
+            ChainExtension::Arbitrum => vec!["l1_block_number", "send_count", "send_root"],
+            ChainExtension::Celo | ChainExtension::Polygon => vec![],
+        }
+    }
+
+    /// transaction columns populated on this chain
+    pub fn transaction_columns(&self) -> Vec<&'static str> {
+        match self {
+            ChainExtension::Celo => vec!["fee_currency", "gateway_fee_recipient", "gateway_fee"],
+            ChainExtension::Polygon => vec!["is_state_sync"],
+            ChainExtension::Arbitrum => {
+                vec!["request_id", "ticket_id", "refund_to", "max_refund", "submission_fee_refund"]
+            }
+        }
+    }
+}
+
+/// deserialize a nonstandard response field, None when absent or malformed
+pub(crate) fn other_field<T: DeserializeOwned>(other: &OtherFields, key: &str) -> Option<T> {
+    other.get_deserialized(key).and_then(|value| value.ok())
+}
+
+/// nonstandard address or hash field as bytes
+pub(crate) fn other_bytes<T: DeserializeOwned + AsRef<[u8]>>(
+    other: &OtherFields,
+    key: &str,
+) -> Option<Vec<u8>> {
+    other_field::<T>(other, key).map(|value| value.as_ref().to_vec())
+}
+
+/// rpc dialect of a chain, a capability of the source that gates which datasets can be collected
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Vm {
+    /// standard ethereum json-rpc
+    #[default]
+    Evm,
+    /// zksync era, standard json-rpc with additional l1 batch fields
+    ZkSync,
+    /// starknet json-rpc
+    Starknet,
+}
+
+impl Vm {
+    /// parse vm from its name
+    pub fn from_name(name: &str) -> Option<Vm> {
+        match name {
+            "evm" => Some(Vm::Evm),
+            "zksync" => Some(Vm::ZkSync),
+            "starknet" => Some(Vm::Starknet),
+            _ => None,
+        }
+    }
+
+    /// name of vm
+    pub fn name(&self) -> &'static str {
+        match self {
+            Vm::Evm => "evm",
+            Vm::ZkSync => "zksync",
+            Vm::Starknet => "starknet",
+        }
+    }
+
+    /// whether a source of this vm can collect datasets that require `vm`
+    ///
+    /// zksync serves the standard ethereum methods, starknet serves none of them
+    pub fn supports(&self, vm: Vm) -> bool {
+        matches!(
+            (self, vm),
+            (Vm::Evm, Vm::Evm) | (Vm::ZkSync, Vm::Evm | Vm::ZkSync) | (Vm::Starknet, Vm::Starknet)
+        )
+    }
+}
@@ -0,0 +1,109 @@
+use std::collections::{HashMap, VecDeque};
+
+use super::{chunk::BlockChunk, strategy::MAX_STRATEGY_CHUNK_BLOCKS};
+use crate::types::{Datatype, ManifestEntry};
+
+/// weight of the latest chunk in the rows per block estimate of a datatype
+const DENSITY_SMOOTHING: f64 = 0.5;
+
+/// resizing of block chunks while freezing, so that each chunk holds about a target number of rows
+#[derive(Debug, Clone)]
+pub struct AdaptiveChunks {
+    /// target number of rows per chunk of the densest datatype
+    pub target_rows: u64,
+}
+
+/// queue of block chunks resized by the rows per block of the chunks collected so far
+///
+/// contiguous block ranges are merged where blocks hold few rows and split where they hold many.
+/// chunks are taken unchanged until a chunk has been collected, and chunks committed by an
+/// earlier run keep their bounds so that resumed runs skip them
+pub(crate) struct ChunkSizer {
+    target_rows: u64,
+    pending: VecDeque<BlockChunk>,
+    committed: HashMap<u64, u64>,
+    rows_per_block: HashMap<Datatype, f64>,
+}
+
+impl ChunkSizer {
+    pub(crate) fn new(
+        adaptive: &AdaptiveChunks,
+        chunks: Vec<BlockChunk>,
+        entries: &[ManifestEntry],
+    ) -> ChunkSizer {
+        let committed = entries.iter().filter_map(|entry| parse_stub(&entry.chunk)).collect();
+        ChunkSizer {
+            target_rows: adaptive.target_rows.max(1),
+            pending: chunks.into(),
+            committed,
+            rows_per_block: HashMap::new(),
+        }
+    }
+
+    /// record the number of rows a datatype collected over a number of blocks
+    pub(crate) fn record(&mut self, datatype: Datatype, n_blocks: u64, n_rows: u64) {
+        if n_blocks == 0 {
+            return
+        }
+        let observed = n_rows as f64 / n_blocks as f64;
+        let estimate = self.rows_per_block.entry(datatype).or_insert(observed);
+        *estimate = DENSITY_SMOOTHING * observed + (1.0 - DENSITY_SMOOTHING) * *estimate;
+    }
+
+    /// number of blocks the next chunk should span, none before any chunk is collected
+    fn chunk_blocks(&self) -> Option<u64> {
+        let rows_per_block = self.rows_per_block.values().cloned().reduce(f64::max)?;
+        let n_blocks = match rows_per_block > 0.0 {
+            true => (self.target_rows as f64 / rows_per_block).round() as u64,
+            false => MAX_STRATEGY_CHUNK_BLOCKS,
+        };
+        Some(n_blocks.clamp(1, MAX_STRATEGY_CHUNK_BLOCKS))
+    }
+
+    /// take the next chunk, with the change in the number of chunks left caused by resizing it
+    pub(crate) fn next_chunk(&mut self) -> Option<(BlockChunk, i64)> {
+        let (start, end) = match self.pending.pop_front()? {
+            BlockChunk::Range(start, end) => (start, end),
+            chunk => return Some((chunk, 0)),
+        };
+        let limit = match (self.committed.get(&start), self.chunk_blocks()) {
+            (Some(committed_end), _) => *committed_end,
+            (None, Some(n_blocks)) => start + n_blocks - 1,
+            (None, None) => return Some((BlockChunk::Range(start, end), 0)),
+        };
+        // never merge into a committed chunk, which would be collected again under new bounds
+        let limit = match self.committed.keys().filter(|committed| **committed > start).min() {
+            Some(next_committed) => limit.min(next_committed - 1),
+            None => limit,
+        };
+
+        // split chunks that extend past limit, merge following ranges that start before it
+        let mut end = end;
+        let mut delta = 0;
+        if end > limit {
+            self.pending.push_front(BlockChunk::Range(limit + 1, end));
+            end = limit;
+            delta += 1;
+        }
+        while let Some(BlockChunk::Range(next_start, next_end)) = self.pending.front().cloned() {
+            if end >= limit || next_start != end + 1 {
+                break
+            }
+            self.pending.pop_front();
+            delta -= 1;
+            end = next_end;
+            if end > limit {
+                self.pending.push_front(BlockChunk::Range(limit + 1, end));
+                end = limit;
+                delta += 1;
+            }
+        }
+        Some((BlockChunk::Range(start, end), delta))
+    }
+}
+
+/// parse the bounds of a block chunk stub, e.g. 00001000_to_00001999
+fn parse_stub(stub: &str) -> Option<(u64, u64)> {
+    let (start, end) = stub.split_once("_to_")?;
+    Some((start.parse().ok()?, end.parse().ok()?))
+}
@@ -1,4 +1,4 @@
-use crate::types::{FileError, FileOutput};
+use crate::types::{ChunkError, FileError, FileOutput};
 
 use super::{binary_chunk::BinaryChunk, chunk_ops::ChunkData, number_chunk::NumberChunk};
 
@@ -34,6 +34,15 @@ impl Chunk {
             Chunk::Address(chunk) => chunk.filepath(name, file_output),
         }
     }
+
+    /// get string representation of chunk range
+    pub fn stub(&self) -> Result<String, ChunkError> {
+        match self {
+            Chunk::Block(chunk) => chunk.stub(),
+            Chunk::Transaction(chunk) => chunk.stub(),
+            Chunk::Address(chunk) => chunk.stub(),
+        }
+    }
 }
 
 impl From<Vec<Chunk>> for Chunk {
@@ -34,7 +34,7 @@ pub trait ChunkData: Sized {
             Some(suffix) => vec![network_name, name.to_string(), self.stub()?, suffix.clone()],
             None => vec![network_name, name.to_string(), self.stub()?],
         };
-        let filename = format!("{}.{}", pieces.join("__"), file_output.format.as_str());
+        let filename = format!("{}.{}", pieces.join("__"), file_output.extension());
         match file_output.output_dir.as_str() {
             "." => Ok(filename),
             output_dir => Ok(output_dir.to_string() + "/" + filename.as_str()),
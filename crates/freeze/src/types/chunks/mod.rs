@@ -1,9 +1,17 @@
+pub(crate) mod adaptive;
 pub(crate) mod binary_chunk;
 pub(crate) mod chunk;
 pub(crate) mod chunk_ops;
 pub(crate) mod number_chunk;
+pub(crate) mod strategy;
 pub(crate) mod subchunks;
 
+pub use adaptive::AdaptiveChunks;
+pub(crate) use adaptive::ChunkSizer;
 pub use chunk::{AddressChunk, BlockChunk, Chunk, TransactionChunk};
 pub use chunk_ops::ChunkData;
+pub use strategy::{
+    ByteTarget, ChunkStrategy, FixedBlocks, TimestampAligned, TxCountTarget,
+    MAX_STRATEGY_CHUNK_BLOCKS,
+};
 pub use subchunks::Subchunk;
@@ -73,6 +73,39 @@ impl NumberChunk {
         }
     }
 
+    /// split chunk so that each given block number starts a new chunk
+    pub fn split_before(self, boundaries: &[u64]) -> Vec<NumberChunk> {
+        let mut boundaries = boundaries.to_vec();
+        boundaries.sort();
+        boundaries.dedup();
+        match self {
+            NumberChunk::Numbers(numbers) => {
+                let mut groups: Vec<Vec<u64>> = vec![Vec::new(); boundaries.len() + 1];
+                for number in numbers {
+                    let index = boundaries.partition_point(|boundary| *boundary <= number);
+                    groups[index].push(number);
+                }
+                groups
+                    .into_iter()
+                    .filter(|group| !group.is_empty())
+                    .map(NumberChunk::Numbers)
+                    .collect()
+            }
+            NumberChunk::Range(start, end) => {
+                let mut chunks = Vec::new();
+                let mut chunk_start = start;
+                for boundary in boundaries {
+                    if boundary > chunk_start && boundary <= end {
+                        chunks.push(NumberChunk::Range(chunk_start, boundary - 1));
+                        chunk_start = boundary;
+                    }
+                }
+                chunks.push(NumberChunk::Range(chunk_start, end));
+                chunks
+            }
+        }
+    }
+
     /// align boundaries of chunk to clean boundaries
     pub fn align(self, chunk_size: u64) -> Option<NumberChunk> {
         match self {
@@ -0,0 +1,276 @@
+use async_trait::async_trait;
+use ethers::prelude::*;
+
+use super::{chunk::BlockChunk, chunk_ops::ChunkData, subchunks::Subchunk};
+use crate::{
+    lookup::{block_timestamp, first_block_after},
+    types::{CollectError, RpcClient},
+};
+
+/// number of blocks sampled per block range to estimate how much data its blocks hold
+const DENSITY_SAMPLES: u64 = 64;
+
+/// maximum number of blocks per chunk of target-based strategies, bounding chunks of empty blocks
+pub const MAX_STRATEGY_CHUNK_BLOCKS: u64 = 100_000;
+
+/// strategy for splitting requested blocks into the chunks that are collected and written
+///
+/// ends of block ranges passed to split are exclusive, as parsed from block inputs, while ends
+/// of the returned ranges are inclusive
+#[async_trait]
+pub trait ChunkStrategy: Send + Sync {
+    /// description of strategy, e.g. for summaries
+    fn describe(&self) -> String;
+
+    /// split block chunks into chunks to collect
+    async fn split(
+        &self,
+        block_chunks: Vec<BlockChunk>,
+        provider: &Provider<RpcClient>,
+    ) -> Result<Vec<BlockChunk>, CollectError>;
+}
+
+/// chunks of a fixed number of blocks, or a fixed number of chunks
+#[derive(Debug, Clone)]
+pub struct FixedBlocks {
+    /// number of blocks per chunk
+    pub chunk_size: u64,
+    /// number of chunks, used instead of chunk size if given
+    pub n_chunks: Option<u64>,
+    /// align chunk boundaries to multiples of chunk size
+    pub align: bool,
+}
+
+#[async_trait]
+impl ChunkStrategy for FixedBlocks {
+    fn describe(&self) -> String {
+        match (self.n_chunks, self.align) {
+            (Some(n_chunks), _) => format!("{} chunks", n_chunks),
+            (None, true) => format!("{} blocks per chunk, aligned", self.chunk_size),
+            (None, false) => format!("{} blocks per chunk", self.chunk_size),
+        }
+    }
+
+    async fn split(
+        &self,
+        block_chunks: Vec<BlockChunk>,
+        _provider: &Provider<RpcClient>,
+    ) -> Result<Vec<BlockChunk>, CollectError> {
+        let chunks = match self.n_chunks {
+            _ if self.align => block_chunks.subchunk_by_aligned_size(&self.chunk_size),
+            Some(n_chunks) => block_chunks.subchunk_by_count(&n_chunks),
+            None => block_chunks.subchunk_by_size(&self.chunk_size),
+        };
+        Ok(chunks)
+    }
+}
+
+/// chunks of about a target number of bytes of block data, estimated from sampled block sizes
+///
+/// block sizes are those of the encoded blocks reported by the node, so the size of output
+/// files scales with the target rather than matching it
+#[derive(Debug, Clone)]
+pub struct ByteTarget {
+    /// target number of bytes of block data per chunk
+    pub target_bytes: u64,
+}
+
+#[async_trait]
+impl ChunkStrategy for ByteTarget {
+    fn describe(&self) -> String {
+        format!("about {} bytes of block data per chunk", self.target_bytes)
+    }
+
+    async fn split(
+        &self,
+        block_chunks: Vec<BlockChunk>,
+        provider: &Provider<RpcClient>,
+    ) -> Result<Vec<BlockChunk>, CollectError> {
+        let measure = |block: &Block<H256>| block.size.map(|size| size.as_u64()).unwrap_or(0);
+        split_by_density(block_chunks, self.target_bytes, measure, provider).await
+    }
+}
+
+/// chunks of about a target number of transactions, estimated from sampled blocks
+#[derive(Debug, Clone)]
+pub struct TxCountTarget {
+    /// target number of transactions per chunk
+    pub target_txs: u64,
+}
+
+#[async_trait]
+impl ChunkStrategy for TxCountTarget {
+    fn describe(&self) -> String {
+        format!("about {} transactions per chunk", self.target_txs)
+    }
+
+    async fn split(
+        &self,
+        block_chunks: Vec<BlockChunk>,
+        provider: &Provider<RpcClient>,
+    ) -> Result<Vec<BlockChunk>, CollectError> {
+        let measure = |block: &Block<H256>| block.transactions.len() as u64;
+        split_by_density(block_chunks, self.target_txs, measure, provider).await
+    }
+}
+
+/// chunks split where block timestamps cross multiples of an interval, e.g. each utc day
+#[derive(Debug, Clone)]
+pub struct TimestampAligned {
+    /// interval between boundaries, in seconds
+    pub interval: u64,
+}
+
+#[async_trait]
+impl ChunkStrategy for TimestampAligned {
+    fn describe(&self) -> String {
+        format!("chunks aligned to {}s of block timestamps", self.interval)
+    }
+
+    async fn split(
+        &self,
+        block_chunks: Vec<BlockChunk>,
+        provider: &Provider<RpcClient>,
+    ) -> Result<Vec<BlockChunk>, CollectError> {
+        let mut chunks = Vec::new();
+        for block_chunk in block_chunks.into_iter() {
+            let block_chunk = to_inclusive(block_chunk);
+            let bounds = (block_chunk.min_value(), block_chunk.max_value());
+            let (first_block, last_block) = match bounds {
+                (Some(first_block), Some(last_block)) => (first_block, last_block),
+                _ => continue,
+            };
+            let last_timestamp = block_timestamp(last_block, provider).await?;
+            let first_timestamp = block_timestamp(first_block, provider).await?;
+            let mut period = first_timestamp / self.interval + 1;
+            let mut boundaries = Vec::new();
+            let mut lower = first_block;
+            while period * self.interval <= last_timestamp {
+                let boundary =
+                    first_block_after(period * self.interval, lower, last_block, provider).await?;
+                boundaries.push(boundary);
+                lower = boundary;
+                period += 1;
+            }
+            chunks.extend(block_chunk.split_before(&boundaries));
+        }
+        Ok(chunks)
+    }
+}
+
+/// range ends are exclusive until split
+fn to_inclusive(block_chunk: BlockChunk) -> BlockChunk {
+    match block_chunk {
+        BlockChunk::Range(start, end) if end > start => BlockChunk::Range(start, end - 1),
+        block_chunk => block_chunk,
+    }
+}
+
+/// split chunks so that each holds about target of a measure of blocks, sampling blocks to
+/// estimate the measure of the blocks around them
+async fn split_by_density<F>(
+    block_chunks: Vec<BlockChunk>,
+    target: u64,
+    measure: F,
+    provider: &Provider<RpcClient>,
+) -> Result<Vec<BlockChunk>, CollectError>
+where
+    F: Fn(&Block<H256>) -> u64 + Send + Sync,
+{
+    let target = target.max(1);
+    let mut chunks = Vec::new();
+    for block_chunk in block_chunks.into_iter() {
+        let block_chunk = to_inclusive(block_chunk);
+        let mut numbers = match &block_chunk {
+            BlockChunk::Numbers(numbers) => numbers.clone(),
+            BlockChunk::Range(_, _) => Vec::new(),
+        };
+        numbers.sort();
+        numbers.dedup();
+        let (first_block, last_block) = match (block_chunk.min_value(), block_chunk.max_value()) {
+            (Some(first_block), Some(last_block)) => (first_block, last_block),
+            _ => continue,
+        };
+
+        // sample blocks evenly, by position among numbers or by height within ranges
+        let n_blocks = match numbers.is_empty() {
+            true => last_block - first_block + 1,
+            false => numbers.len() as u64,
+        };
+        let step = (n_blocks / DENSITY_SAMPLES).max(1);
+        let mut samples = Vec::new();
+        for index in (0..n_blocks).step_by(step as usize) {
+            let block_number = match numbers.is_empty() {
+                true => first_block + index,
+                false => numbers[index as usize],
+            };
+            let block = provider
+                .get_block(block_number)
+                .await
+                .map_err(|e| CollectError::rpc("eth_getBlockByNumber", e))?
+                .ok_or_else(|| {
+                    CollectError::CollectError(format!("block {} not found", block_number))
+                })?;
+            samples.push((block_number, measure(&block)));
+        }
+
+        let boundaries = match numbers.is_empty() {
+            true => range_boundaries(last_block, &samples, target),
+            false => number_boundaries(&numbers, &samples, target),
+        };
+        chunks.extend(block_chunk.split_before(&boundaries));
+    }
+    Ok(chunks)
+}
+
+/// boundaries of a block range, each sample standing for the blocks up to the next sample
+fn range_boundaries(last_block: u64, samples: &[(u64, u64)], target: u64) -> Vec<u64> {
+    let mut boundaries = Vec::new();
+    let mut chunk_start = match samples.first() {
+        Some((block_number, _)) => *block_number,
+        None => return boundaries,
+    };
+    let mut filled = 0u64;
+    for (i, (segment_start, density)) in samples.iter().enumerate() {
+        let segment_end = samples.get(i + 1).map(|(next, _)| *next).unwrap_or(last_block + 1);
+        let mut block = *segment_start;
+        while block < segment_end {
+            let needed = match density {
+                0 => u64::MAX,
+                density => (target - filled).div_ceil(*density),
+            };
+            let n_blocks = needed
+                .min(segment_end - block)
+                .min(chunk_start + MAX_STRATEGY_CHUNK_BLOCKS - block);
+            block += n_blocks;
+            filled = filled.saturating_add(n_blocks.saturating_mul(*density));
+            if filled >= target || block - chunk_start >= MAX_STRATEGY_CHUNK_BLOCKS {
+                if block <= last_block {
+                    boundaries.push(block);
+                }
+                chunk_start = block;
+                filled = 0;
+            }
+        }
+    }
+    boundaries
+}
+
+/// boundaries of a list of blocks, each sample standing for the listed blocks up to the next
+fn number_boundaries(numbers: &[u64], samples: &[(u64, u64)], target: u64) -> Vec<u64> {
+    let mut boundaries = Vec::new();
+    let mut filled = 0u64;
+    let mut n_blocks = 0u64;
+    for number in numbers.iter() {
+        if filled >= target || n_blocks >= MAX_STRATEGY_CHUNK_BLOCKS {
+            boundaries.push(*number);
+            filled = 0;
+            n_blocks = 0;
+        }
+        let sample = samples.partition_point(|(sampled, _)| sampled <= number);
+        let density = samples.get(sample.saturating_sub(1)).map(|(_, d)| *d).unwrap_or(0);
+        filled = filled.saturating_add(density);
+        n_blocks += 1;
+    }
+    boundaries
+}
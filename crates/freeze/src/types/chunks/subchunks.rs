@@ -7,6 +7,12 @@ pub trait Subchunk {
 
     /// divide into number of subchunks
     fn subchunk_by_count(&self, n_chunks: &u64) -> Vec<BlockChunk>;
+
+    /// divide into subchunks whose boundaries are multiples of chunk size
+    ///
+    /// partial chunks at the edges of ranges are dropped, so that every range chunk spans a
+    /// full interval and has the same file name regardless of the requested range
+    fn subchunk_by_aligned_size(&self, chunk_size: &u64) -> Vec<BlockChunk>;
 }
 
 impl Subchunk for BlockChunk {
@@ -30,6 +36,18 @@ impl Subchunk for BlockChunk {
         let chunk_size = (total_blocks + n_chunks - 1) / n_chunks;
         self.subchunk_by_size(&chunk_size)
     }
+
+    fn subchunk_by_aligned_size(&self, chunk_size: &u64) -> Vec<BlockChunk> {
+        match self.clone().align(*chunk_size) {
+            Some(BlockChunk::Numbers(numbers)) => {
+                let boundaries: Vec<u64> =
+                    numbers.iter().map(|number| (number / chunk_size) * chunk_size).collect();
+                BlockChunk::Numbers(numbers).split_before(&boundaries)
+            }
+            Some(chunk) => chunk.subchunk_by_size(chunk_size),
+            None => vec![],
+        }
+    }
 }
 
 impl Subchunk for Vec<BlockChunk> {
@@ -40,6 +58,10 @@ impl Subchunk for Vec<BlockChunk> {
     fn subchunk_by_count(&self, n_chunks: &u64) -> Vec<BlockChunk> {
         to_single_chunk(self).subchunk_by_count(n_chunks)
     }
+
+    fn subchunk_by_aligned_size(&self, chunk_size: &u64) -> Vec<BlockChunk> {
+        self.iter().flat_map(|chunk| chunk.subchunk_by_aligned_size(chunk_size)).collect()
+    }
 }
 
 fn to_single_chunk(chunks: &Vec<BlockChunk>) -> BlockChunk {
@@ -1,6 +1,7 @@
 /// conversion operations
 use ethers::prelude::*;
-use prefix_hex;
+
+use crate::types::CollectError;
 
 /// Converts data to Vec<u8>
 pub trait ToVecU8 {
@@ -53,7 +54,7 @@ impl ToVecHex for Vec<Vec<u8>> {
     type Output = Vec<String>;
 
     fn to_vec_hex(&self) -> Self::Output {
-        self.iter().map(|v| prefix_hex::encode(v.clone())).collect()
+        self.iter().map(|v| encode_hex(v)).collect()
     }
 }
 
@@ -61,6 +62,76 @@ impl ToVecHex for Vec<Option<Vec<u8>>> {
     type Output = Vec<Option<String>>;
 
     fn to_vec_hex(&self) -> Self::Output {
-        self.iter().map(|opt| opt.as_ref().map(|v| prefix_hex::encode(v.clone()))).collect()
+        self.iter().map(|opt| opt.as_deref().map(encode_hex)).collect()
+    }
+}
+
+/// encode bytes as 0x-prefixed lowercase hex, using simd instructions where available
+pub fn encode_hex(bytes: &[u8]) -> String {
+    let mut buffer = vec![0; 2 + 2 * bytes.len()];
+    buffer[..2].copy_from_slice(b"0x");
+    faster_hex::hex_encode(bytes, &mut buffer[2..]).expect("hex buffer has twice the bytes");
+    String::from_utf8(buffer).expect("hex is valid utf8")
+}
+
+/// decode hex with or without a 0x prefix, using simd instructions where available
+pub fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    let hex = hex.strip_prefix("0x").unwrap_or(hex).as_bytes();
+    if !hex.len().is_multiple_of(2) {
+        return None
     }
+    let mut bytes = vec![0; hex.len() / 2];
+    faster_hex::hex_decode(hex, &mut bytes).ok()?;
+    Some(bytes)
+}
+
+/// Narrows integers collected as u64 to the u32 of a column, erroring instead of wrapping
+pub trait NarrowU32 {
+    /// Output type
+    type Output;
+
+    /// Convert to u32, erroring if a value exceeds the uint32 range
+    fn narrow_u32(self, column: &str) -> Result<Self::Output, CollectError>;
+}
+
+impl NarrowU32 for Vec<u64> {
+    type Output = Vec<u32>;
+
+    fn narrow_u32(self, column: &str) -> Result<Self::Output, CollectError> {
+        self.into_iter()
+            .map(|value| u32::try_from(value).map_err(|_e| uint32_overflow(column, value)))
+            .collect()
+    }
+}
+
+impl NarrowU32 for Vec<Option<u64>> {
+    type Output = Vec<Option<u32>>;
+
+    fn narrow_u32(self, column: &str) -> Result<Self::Output, CollectError> {
+        self.into_iter()
+            .map(|value| match value {
+                Some(value) => {
+                    u32::try_from(value).map(Some).map_err(|_e| uint32_overflow(column, value))
+                }
+                None => Ok(None),
+            })
+            .collect()
+    }
+}
+
+fn uint32_overflow(column: &str, value: u64) -> CollectError {
+    CollectError::CollectError(format!(
+        "{} value {} exceeds the uint32 range, rerun with --wide-integers",
+        column, value
+    ))
+}
+
+/// convert an rpc integer to u64, erroring instead of panicking or wrapping if it does not fit
+pub(crate) fn checked_u64<T>(value: T, column: &str) -> Result<u64, CollectError>
+where
+    T: TryInto<u64> + std::fmt::Display + Copy,
+{
+    value.try_into().map_err(|_e| {
+        CollectError::CollectError(format!("{} value {} exceeds the uint64 range", column, value))
+    })
 }
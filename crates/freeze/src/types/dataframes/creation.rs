@@ -21,3 +21,18 @@ macro_rules! with_series_binary {
         }
     };
 }
+
+/// convert a Vec of u64 to Series, as uint32 unless the schema widens the column, and add to
+/// Vec<Series>, returning an error if a value exceeds the uint32 range
+#[macro_export]
+macro_rules! with_series_u32 {
+    ($all_series:expr, $name:expr, $value:expr, $schema:expr) => {
+        if $schema.has_column($name) {
+            if let Some(ColumnType::UInt64) = $schema.column_type($name) {
+                $all_series.push(Series::new($name, $value));
+            } else {
+                $all_series.push(Series::new($name, $value.narrow_u32($name)?));
+            }
+        }
+    };
+}
@@ -0,0 +1,46 @@
+use polars::prelude::*;
+
+use crate::types::{ColumnType, FileError, Table};
+
+/// digits held by an arrow decimal128
+const DECIMAL128_PRECISION: usize = 38;
+
+/// convert decimal128 columns, collected as integer strings, to arrow decimal128 with a scale
+///
+/// integers are kept as is and the scale only places the decimal point, e.g. wei with scale 18
+/// read as ether. values of more than 38 digits, such as unlimited approvals, are written as
+/// null. polars cannot hold decimal256, so such values are only exact in the default string form
+pub(crate) fn cast_decimal_columns(
+    mut df: DataFrame,
+    schema: &Table,
+    scale: u32,
+) -> Result<DataFrame, FileError> {
+    for name in schema.columns() {
+        if schema.column_type(name) != Some(ColumnType::Decimal128) {
+            continue
+        }
+        let strings = match df.column(name) {
+            Ok(series) => series.cast(&DataType::Utf8).map_err(|_e| FileError::FileWriteError)?,
+            Err(_) => continue,
+        };
+        let values: Int128Chunked = strings
+            .utf8()
+            .map_err(|_e| FileError::FileWriteError)?
+            .into_iter()
+            .map(|value| value.and_then(parse_decimal128))
+            .collect();
+        let mut decimals =
+            values.into_decimal_unchecked(Some(DECIMAL128_PRECISION), scale as usize).into_series();
+        decimals.rename(name);
+        df.replace(name, decimals).map_err(|_e| FileError::FileWriteError)?;
+    }
+    Ok(df)
+}
+
+/// parse an integer string that fits the precision of decimal128
+fn parse_decimal128(value: &str) -> Option<i128> {
+    match value.trim_start_matches('-').len() <= DECIMAL128_PRECISION {
+        true => value.parse().ok(),
+        false => None,
+    }
+}
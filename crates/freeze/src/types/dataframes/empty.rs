@@ -0,0 +1,26 @@
+use polars::prelude::*;
+
+use crate::types::{ColumnType, FileError, Table};
+
+/// zero-row dataframe with the columns and dtypes that collecting a schema produces
+pub(crate) fn empty_dataframe(schema: &Table) -> Result<DataFrame, FileError> {
+    let mut cols = Vec::new();
+    for name in schema.columns() {
+        let dtype = match schema.column_type(name) {
+            Some(ColumnType::UInt32) => DataType::UInt32,
+            Some(ColumnType::UInt64) => DataType::UInt64,
+            Some(ColumnType::Int32) => DataType::Int32,
+            Some(ColumnType::Int64) => DataType::Int64,
+            Some(ColumnType::Float64) => DataType::Float64,
+            Some(ColumnType::Boolean) => DataType::Boolean,
+            // decimal128 columns are collected as integer strings, cast later for --decimal
+            Some(ColumnType::Decimal128) | Some(ColumnType::String) | Some(ColumnType::Hex) => {
+                DataType::Utf8
+            }
+            Some(ColumnType::Binary) => DataType::Binary,
+            None => return Err(FileError::FileWriteError),
+        };
+        cols.push(Series::new_empty(name, &dtype));
+    }
+    DataFrame::new(cols).map_err(|_e| FileError::FileWriteError)
+}
@@ -1,28 +1,109 @@
-use std::collections::HashMap;
+use std::io::Write;
 
+use flate2::{write::GzEncoder, Compression};
 use polars::prelude::*;
+#[cfg(feature = "xlsx")]
+use rust_xlsxwriter::{Format, Workbook, XlsxError};
 
-use crate::types::{FileError, FileOutput};
+use super::{write_stats, STATS_DIR};
+use crate::types::{
+    sha256_file, CommitStatus, CsvDialect, CsvQuoteStyle, Datatype, FileError, FileOutput,
+    Manifest, ManifestEntry, ManifestFile, STAGING_DIR_NAME,
+};
 
-pub(crate) fn dfs_to_files<T>(
-    dfs: &mut HashMap<T, DataFrame>,
-    filenames: &HashMap<T, String>,
+/// write all dataframes of a chunk, committing them together
+///
+/// files are written to a staging directory and then moved into place between a pending and a
+/// committed manifest entry, so a crash never leaves a chunk committed for only some datasets.
+/// statistics sidecars are moved into output_dir/stats along with their files
+pub(crate) fn commit_chunk(
+    datatypes: &[Datatype],
+    mut dfs: Vec<(Datatype, String, DataFrame)>,
+    chunk_stub: &str,
     file_output: &FileOutput,
-) -> Result<(), FileError>
-where
-    T: std::cmp::Eq,
-    T: std::hash::Hash,
-{
-    for (name, df) in dfs.iter_mut() {
-        let filename = match filenames.get(name) {
-            Some(filename) => filename,
-            None => {
-                return Err(FileError::NoFilePathError("no path given for dataframe".to_string()))
-            }
-        };
-        df_to_file(df, filename, file_output)?
+    manifest: &Manifest,
+) -> Result<ManifestEntry, FileError> {
+    let staging_dir = std::path::Path::new(&file_output.output_dir).join(STAGING_DIR_NAME);
+    std::fs::create_dir_all(&staging_dir).map_err(|_e| FileError::FileWriteError)?;
+    let stats_dir = std::path::Path::new(&file_output.output_dir).join(STATS_DIR);
+    if file_output.stats_sidecars {
+        std::fs::create_dir_all(&stats_dir).map_err(|_e| FileError::FileWriteError)?;
     }
-    Ok(())
+
+    // stage files, listed in the manifest in a stable order
+    let mut datasets: Vec<String> =
+        datatypes.iter().map(|datatype| datatype.dataset().name().to_string()).collect();
+    datasets.sort();
+    dfs.sort_by(|a, b| a.1.cmp(&b.1));
+    let mut staged = Vec::new();
+    let mut files = Vec::new();
+    for (datatype, path, mut df) in dfs.into_iter() {
+        let filename = std::path::Path::new(&path)
+            .file_name()
+            .ok_or(FileError::NoFilePathError(path.clone()))?
+            .to_string_lossy()
+            .into_owned();
+        let staged_path = staging_dir.join(&filename).to_string_lossy().into_owned();
+        df_to_file(&mut df, &staged_path, file_output)?;
+        if file_output.deterministic {
+            set_modified(&staged_path, source_date_epoch())?;
+        }
+        let n_bytes = std::fs::metadata(&staged_path).map(|m| m.len()).unwrap_or_default();
+        files.push(ManifestFile {
+            dataset: datatype.dataset().name().to_string(),
+            filename: filename.clone(),
+            n_rows: df.height() as u64,
+            n_bytes,
+            sha256: Some(sha256_file(&staged_path)?),
+        });
+        if file_output.stats_sidecars {
+            let stats_name = format!("{}.json", filename);
+            let staged_stats = staging_dir.join(&stats_name).to_string_lossy().into_owned();
+            write_stats(&df, &filename, &staged_stats)?;
+            staged.push((staged_stats, stats_dir.join(&stats_name).to_string_lossy().into_owned()));
+        }
+        staged.push((staged_path, path));
+    }
+
+    // move files into place, between a pending entry and a committed entry
+    let timestamp = || match file_output.deterministic {
+        true => source_date_epoch(),
+        false => std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default(),
+    };
+    let mut entry = ManifestEntry {
+        status: CommitStatus::Pending,
+        chunk: chunk_stub.to_string(),
+        datasets,
+        files,
+        timestamp: timestamp(),
+        lineage: file_output.lineage.clone(),
+    };
+    manifest.append(&entry)?;
+    for (staged_path, path) in staged.iter() {
+        std::fs::rename(staged_path, path).map_err(|_e| FileError::FileWriteError)?;
+    }
+    entry.status = CommitStatus::Committed;
+    entry.timestamp = timestamp();
+    manifest.append(&entry)?;
+    Ok(entry)
+}
+
+/// fixed time of deterministic output, SOURCE_DATE_EPOCH if set, as in reproducible builds
+pub(crate) fn source_date_epoch() -> u64 {
+    std::env::var("SOURCE_DATE_EPOCH").ok().and_then(|value| value.parse().ok()).unwrap_or(0)
+}
+
+/// set the modification time of a file, which is kept when the file is moved into place
+fn set_modified(path: &str, timestamp: u64) -> Result<(), FileError> {
+    let time = std::time::UNIX_EPOCH + std::time::Duration::from_secs(timestamp);
+    std::fs::File::options()
+        .write(true)
+        .open(path)
+        .and_then(|file| file.set_modified(time))
+        .map_err(|_e| FileError::FileWriteError)
 }
 
 /// write polars dataframe to file
@@ -35,12 +116,17 @@ pub(crate) fn df_to_file(
     let tmp_filename = binding.as_str();
     let result = match filename {
         _ if filename.ends_with(".parquet") => df_to_parquet(df, tmp_filename, file_output),
-        _ if filename.ends_with(".csv") => df_to_csv(df, tmp_filename),
+        _ if filename.ends_with(".csv") || filename.ends_with(".csv.gz") => {
+            df_to_csv(df, tmp_filename, &file_output.csv)
+        }
         _ if filename.ends_with(".json") => df_to_json(df, tmp_filename),
+        #[cfg(feature = "xlsx")]
+        _ if filename.ends_with(".xlsx") => df_to_xlsx(df, tmp_filename, file_output),
         _ => return Err(FileError::FileWriteError),
     };
     match result {
         Ok(()) => std::fs::rename(tmp_filename, filename).map_err(|_e| FileError::FileWriteError),
+        Err(e @ FileError::TooManyRows(..)) => Err(e),
         Err(_e) => Err(FileError::FileWriteError),
     }
 }
@@ -51,9 +137,16 @@ fn df_to_parquet(
     filename: &str,
     file_output: &FileOutput,
 ) -> Result<(), FileError> {
+    if file_output.parquet_dictionary_encoding {
+        dictionary_encode_columns(df)?;
+    }
+    // parquet2 panics computing statistics of decimal columns wider than 18 digits, which are
+    // written as fixed length byte arrays
+    let has_decimals =
+        df.get_columns().iter().any(|column| matches!(column.dtype(), DataType::Decimal(..)));
     let file = std::fs::File::create(filename).map_err(|_e| FileError::FileWriteError)?;
     let result = ParquetWriter::new(file)
-        .with_statistics(file_output.parquet_statistics)
+        .with_statistics(file_output.parquet_statistics && !has_decimals)
         .with_compression(file_output.parquet_compression)
         .with_row_group_size(file_output.row_group_size)
         .finish(df);
@@ -63,14 +156,111 @@ fn df_to_parquet(
     }
 }
 
+/// cast hex address and topic columns to categorical, which parquet stores dictionary encoded
+///
+/// addresses and topics repeat heavily within a chunk, e.g. a few popular contracts emit most
+/// logs. the parquet writer of polars writes every column plain except dictionary arrays and
+/// does not expose per-column encodings, so this is the only way to request dictionary encoding
+/// and delta encodings are not available. it changes the arrow type of these columns in the
+/// written file from string to dictionary<int32, string>, so it is opt-in. binary columns cannot
+/// be cast to categorical, so only hex columns are encoded
+fn dictionary_encode_columns(df: &mut DataFrame) -> Result<(), FileError> {
+    let names: Vec<String> = df
+        .get_columns()
+        .iter()
+        .filter(|column| column.dtype() == &DataType::Utf8 && is_dictionary_column(column.name()))
+        .map(|column| column.name().to_string())
+        .collect();
+    for name in names {
+        let column = df
+            .column(&name)
+            .and_then(|column| column.cast(&DataType::Categorical(None)))
+            .map_err(|_e| FileError::FileWriteError)?;
+        df.with_column(column).map_err(|_e| FileError::FileWriteError)?;
+    }
+    Ok(())
+}
+
+fn is_dictionary_column(name: &str) -> bool {
+    name == "address" || name.ends_with("_address") || name.starts_with("topic")
+}
+
 /// write polars dataframe to csv file
-fn df_to_csv(df: &mut DataFrame, filename: &str) -> Result<(), FileError> {
-    let file = std::fs::File::create(filename).map_err(|_e| FileError::FileWriteError)?;
-    let result = CsvWriter::new(file).finish(df);
-    match result {
-        Err(_e) => Err(FileError::FileWriteError),
-        _ => Ok(()),
+fn df_to_csv(df: &mut DataFrame, filename: &str, dialect: &CsvDialect) -> Result<(), FileError> {
+    let mut contents = Vec::new();
+    CsvWriter::new(&mut contents)
+        .has_header(dialect.header)
+        .with_delimiter(dialect.delimiter)
+        .finish(df)
+        .map_err(|_e| FileError::FileWriteError)?;
+    if dialect.quote_style == CsvQuoteStyle::Always {
+        contents = quote_all_fields(&contents, dialect.delimiter);
+    }
+    if dialect.rfc4180 {
+        contents = crlf_line_endings(&contents);
+    }
+
+    let mut file = std::fs::File::create(filename).map_err(|_e| FileError::FileWriteError)?;
+    let result = match dialect.gzip {
+        true => {
+            let mut encoder = GzEncoder::new(file, Compression::default());
+            encoder.write_all(&contents).and_then(|_| encoder.finish().map(|_| ()))
+        }
+        false => file.write_all(&contents),
+    };
+    result.map_err(|_e| FileError::FileWriteError)
+}
+
+/// replace the LF row endings of csv contents with CRLF, leaving line breaks inside quoted fields
+fn crlf_line_endings(contents: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(contents.len() + contents.len() / 64);
+    let mut quoted = false;
+    for byte in contents.iter() {
+        match byte {
+            b'"' => quoted = !quoted,
+            b'\n' if !quoted => output.push(b'\r'),
+            _ => {}
+        }
+        output.push(*byte);
+    }
+    output
+}
+
+/// quote every field of csv contents that polars left unquoted
+///
+/// the csv writer of polars only quotes fields when necessary, so fields it left unquoted
+/// contain no quotes, delimiters, or line breaks and can be wrapped in quotes as they are
+fn quote_all_fields(contents: &[u8], delimiter: u8) -> Vec<u8> {
+    let mut output = Vec::with_capacity(contents.len() * 2);
+    let mut quoted = false;
+    let mut field_start = true;
+    let mut wrapped = false;
+    for byte in contents.iter() {
+        if field_start {
+            field_start = false;
+            if *byte != b'"' {
+                output.push(b'"');
+                wrapped = true;
+            }
+        }
+        if !quoted && (*byte == delimiter || *byte == b'\n') {
+            if wrapped {
+                output.push(b'"');
+                wrapped = false;
+            }
+            output.push(*byte);
+            field_start = true;
+            continue
+        }
+        if *byte == b'"' {
+            quoted = !quoted;
+        }
+        output.push(*byte);
+    }
+    if wrapped {
+        output.push(b'"');
     }
+    output
 }
 
 /// write polars dataframe to json file
@@ -82,3 +272,46 @@ fn df_to_json(df: &mut DataFrame, filename: &str) -> Result<(), FileError> {
         _ => Ok(()),
     }
 }
+
+/// write polars dataframe to xlsx file, as a single worksheet with a bold, frozen header row
+///
+/// binary columns are already hex encoded for non-parquet output. integers too large for the f64
+/// numbers of spreadsheets are written as text, and nested values as their display strings
+#[cfg(feature = "xlsx")]
+fn df_to_xlsx(df: &DataFrame, filename: &str, file_output: &FileOutput) -> Result<(), FileError> {
+    if df.height() > file_output.xlsx_max_rows {
+        return Err(FileError::TooManyRows(df.height(), file_output.xlsx_max_rows))
+    }
+    let write_error = |_e: XlsxError| FileError::FileWriteError;
+    let mut workbook = Workbook::new();
+    let worksheet = workbook.add_worksheet();
+    let bold = Format::new().set_bold();
+    for (col, series) in df.get_columns().iter().enumerate() {
+        let col = u16::try_from(col).map_err(|_e| FileError::FileWriteError)?;
+        worksheet.write_string_with_format(0, col, series.name(), &bold).map_err(write_error)?;
+        for index in 0..series.len() {
+            let row = (index + 1) as u32;
+            let value = series.get(index).map_err(|_e| FileError::FileWriteError)?;
+            let result = match value {
+                AnyValue::Null => continue,
+                AnyValue::Boolean(value) => worksheet.write_boolean(row, col, value),
+                AnyValue::Utf8(value) => worksheet.write_string(row, col, value),
+                AnyValue::Float32(value) => worksheet.write_number(row, col, f64::from(value)),
+                AnyValue::Float64(value) => worksheet.write_number(row, col, value),
+                value => match value.extract::<i64>() {
+                    Some(number) if number.unsigned_abs() <= MAX_SAFE_INTEGER => {
+                        worksheet.write_number(row, col, number as f64)
+                    }
+                    _ => worksheet.write_string(row, col, value.to_string()),
+                },
+            };
+            result.map_err(write_error)?;
+        }
+    }
+    worksheet.set_freeze_panes(1, 0).map_err(write_error)?;
+    workbook.save(filename).map_err(write_error)
+}
+
+/// largest integer that spreadsheets store exactly
+#[cfg(feature = "xlsx")]
+const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
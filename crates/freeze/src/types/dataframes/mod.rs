@@ -1,8 +1,26 @@
+mod decimal;
+mod empty;
 mod export;
+mod nested;
+mod partition;
+mod rollup;
 mod sort;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod stats;
 
 #[macro_use]
 mod creation;
 
+pub(crate) use decimal::*;
+pub(crate) use empty::empty_dataframe;
 pub(crate) use export::*;
-pub(crate) use sort::SortableDataFrame;
+pub(crate) use nested::*;
+pub(crate) use partition::*;
+pub(crate) use rollup::{EpochRollup, Rollups};
+pub(crate) use sort::{sort_deterministic, SortableDataFrame};
+#[cfg(feature = "sqlite")]
+pub(crate) use sqlite::SqliteSink;
+#[cfg(feature = "sqlite")]
+pub use sqlite::{sqlite_mapping, SqlColumnMapping};
+pub(crate) use stats::{write_stats, STATS_DIR};
@@ -0,0 +1,30 @@
+use polars::prelude::*;
+
+use crate::types::FileError;
+
+/// columns identifying the transaction of a trace, kept as top level columns when nesting
+const TRANSACTION_COLUMNS: [&str; 5] =
+    ["block_number", "block_hash", "transaction_position", "transaction_hash", "chain_id"];
+
+/// nest traces into one row per transaction, holding a list of call structs in trace order
+///
+/// calls keep their trace_address, so the call hierarchy can be walked within a row instead of
+/// joining traces with themselves
+pub(crate) fn nest_traces(df: DataFrame) -> Result<DataFrame, FileError> {
+    let names: Vec<String> = df.get_column_names().iter().map(|name| name.to_string()).collect();
+    let keys: Vec<Expr> = names
+        .iter()
+        .filter(|name| TRANSACTION_COLUMNS.contains(&name.as_str()))
+        .map(|name| col(name))
+        .collect();
+    let fields: Vec<Expr> = names
+        .iter()
+        .filter(|name| !TRANSACTION_COLUMNS.contains(&name.as_str()))
+        .map(|name| col(name))
+        .collect();
+    df.lazy()
+        .groupby_stable(keys)
+        .agg([as_struct(&fields).alias("calls")])
+        .collect()
+        .map_err(|_e| FileError::FileWriteError)
+}
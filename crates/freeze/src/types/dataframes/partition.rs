@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+
+use polars::prelude::*;
+
+use crate::types::{encode_hex, Datatype, FileError, PartitionBy};
+
+/// label of partition that collects rows of contracts beyond the partition cap
+pub(crate) const OTHER_PARTITION: &str = "other";
+
+/// column used to partition a datatype, if the datatype supports the partitioning
+pub(crate) fn partition_column(datatype: Datatype, partition_by: &PartitionBy) -> Option<&str> {
+    match (partition_by, datatype) {
+        (PartitionBy::Contract { .. }, Datatype::Logs) => Some("contract_address"),
+        (PartitionBy::Contract { .. }, Datatype::Traces) => Some("action_to"),
+        _ => None,
+    }
+}
+
+/// split dataframe into labeled partitions
+pub(crate) fn partition_df(
+    df: &DataFrame,
+    column: &str,
+    partition_by: &PartitionBy,
+) -> Result<Vec<(String, DataFrame)>, FileError> {
+    match partition_by {
+        PartitionBy::Contract { max_contracts } => {
+            partition_by_contract(df, column, *max_contracts)
+        }
+    }
+}
+
+/// one partition per contract for the contracts with the most rows, the rest in "other"
+fn partition_by_contract(
+    df: &DataFrame,
+    column: &str,
+    max_contracts: usize,
+) -> Result<Vec<(String, DataFrame)>, FileError> {
+    let series = df.column(column).map_err(|_e| FileError::FileWriteError)?;
+    let labels: Vec<Option<String>> = match series.dtype() {
+        DataType::Binary => series
+            .binary()
+            .map_err(|_e| FileError::FileWriteError)?
+            .into_iter()
+            .map(|value| value.map(encode_hex))
+            .collect(),
+        DataType::Utf8 => series
+            .utf8()
+            .map_err(|_e| FileError::FileWriteError)?
+            .into_iter()
+            .map(|value| value.map(|v| v.to_string()))
+            .collect(),
+        _ => return Err(FileError::FileWriteError),
+    };
+
+    // keep contracts with the most rows, ties broken by address for determinism
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for label in labels.iter().flatten() {
+        *counts.entry(label.as_str()).or_default() += 1;
+    }
+    let mut ranked: Vec<(&str, usize)> = counts.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    let kept: Vec<&str> = ranked.into_iter().take(max_contracts).map(|(label, _)| label).collect();
+
+    let mut partitions = Vec::new();
+    for label in kept.iter() {
+        let mask: BooleanChunked =
+            labels.iter().map(|value| value.as_deref() == Some(*label)).collect();
+        let part = df.filter(&mask).map_err(|_e| FileError::FileWriteError)?;
+        partitions.push((label.to_string(), part));
+    }
+    let mask: BooleanChunked = labels
+        .iter()
+        .map(|value| match value {
+            Some(value) => !kept.contains(&value.as_str()),
+            None => true,
+        })
+        .collect();
+    let other = df.filter(&mask).map_err(|_e| FileError::FileWriteError)?;
+    if other.height() > 0 {
+        partitions.push((OTHER_PARTITION.to_string(), other));
+    }
+    Ok(partitions)
+}
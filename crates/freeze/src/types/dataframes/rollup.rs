@@ -0,0 +1,232 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Mutex,
+};
+
+use ethers::prelude::I256;
+use polars::prelude::*;
+
+use crate::types::{
+    decode_hex, BlockChunk, Chunk, ChunkData, ColumnType, Datatype, FileError, FileOutput, Table,
+};
+
+/// directory of output_dir holding rollup files
+pub(crate) const ROLLUP_DIR: &str = "rollups";
+
+/// summaries of each epoch of blocks, written as soon as every chunk of an epoch is done
+///
+/// rollups give coarse analytics of a long collection before its detailed files are complete.
+/// an epoch collected only in part, because chunks were skipped as already committed or failed,
+/// is still written but marked incomplete, since its counts only cover the collected chunks
+pub(crate) struct Rollups {
+    epoch_size: u64,
+    /// number of chunks of each epoch that have not finished yet
+    pending: Mutex<HashMap<(Datatype, u64), (u64, EpochRollup)>>,
+}
+
+/// summary of the rows of one datatype within one epoch
+#[derive(Default)]
+pub(crate) struct EpochRollup {
+    n_chunks: u64,
+    n_chunks_collected: u64,
+    n_rows: u64,
+    sums: BTreeMap<String, I256>,
+    addresses: HashSet<Vec<u8>>,
+}
+
+impl Rollups {
+    /// track the epochs overlapping the block chunks of a query
+    pub(crate) fn new(epoch_size: u64, chunks: &[Chunk], datatypes: &[Datatype]) -> Rollups {
+        let mut pending: HashMap<(Datatype, u64), (u64, EpochRollup)> = HashMap::new();
+        for chunk in chunks.iter() {
+            for epoch in chunk_epochs(chunk, epoch_size) {
+                for datatype in datatypes.iter() {
+                    let (remaining, rollup) = pending.entry((*datatype, epoch)).or_default();
+                    *remaining += 1;
+                    rollup.n_chunks += 1;
+                }
+            }
+        }
+        Rollups { epoch_size, pending: Mutex::new(pending) }
+    }
+
+    /// summarize the rows of a collected chunk by epoch, before they are written
+    pub(crate) fn summarize(
+        &self,
+        chunk: &Chunk,
+        df: &DataFrame,
+        schema: &Table,
+    ) -> Result<HashMap<u64, EpochRollup>, FileError> {
+        let epochs: Vec<u64> = match df.column("block_number") {
+            Ok(series) => series
+                .cast(&DataType::UInt64)
+                .map_err(|_e| FileError::FileWriteError)?
+                .u64()
+                .map_err(|_e| FileError::FileWriteError)?
+                .into_iter()
+                .map(|block| block.unwrap_or_default() / self.epoch_size)
+                .collect(),
+            Err(_) => match chunk_epochs(chunk, self.epoch_size).first() {
+                Some(epoch) => vec![*epoch; df.height()],
+                None => return Ok(HashMap::new()),
+            },
+        };
+
+        let mut rollups: HashMap<u64, EpochRollup> = HashMap::new();
+        for epoch in epochs.iter() {
+            rollups.entry(*epoch).or_default().n_rows += 1;
+        }
+        for name in schema.columns() {
+            let series = match df.column(name) {
+                Ok(series) => series,
+                Err(_) => continue,
+            };
+            if schema.column_type(name) == Some(ColumnType::Decimal128) {
+                let values =
+                    series.cast(&DataType::Utf8).map_err(|_e| FileError::FileWriteError)?;
+                let values = values.utf8().map_err(|_e| FileError::FileWriteError)?;
+                for (epoch, value) in epochs.iter().zip(values) {
+                    if let Some(value) = value.and_then(|v| I256::from_dec_str(v).ok()) {
+                        let sum = rollups.entry(*epoch).or_default().sums.entry(name.to_string());
+                        let sum = sum.or_default();
+                        *sum = sum.saturating_add(value);
+                    }
+                }
+            } else if name == "address" || name.ends_with("_address") {
+                for (epoch, address) in epochs.iter().zip(address_values(series)?) {
+                    if let Some(address) = address {
+                        rollups.entry(*epoch).or_default().addresses.insert(address);
+                    }
+                }
+            }
+        }
+        Ok(rollups)
+    }
+
+    /// add the summaries of a committed chunk to the rollups of its epochs
+    pub(crate) fn add(
+        &self,
+        chunk: &Chunk,
+        summaries: HashMap<Datatype, HashMap<u64, EpochRollup>>,
+    ) -> Result<(), FileError> {
+        let mut pending = self.pending.lock().map_err(|_e| FileError::FileWriteError)?;
+        for (datatype, summary) in summaries.into_iter() {
+            for epoch in chunk_epochs(chunk, self.epoch_size) {
+                if let Some((_, rollup)) = pending.get_mut(&(datatype, epoch)) {
+                    rollup.n_chunks_collected += 1;
+                    if let Some(partial) = summary.get(&epoch) {
+                        rollup.merge(partial);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// mark a chunk of some datatypes as done, whether or not it was collected, and write the
+    /// rollups of epochs that have no chunks left
+    pub(crate) fn finish(
+        &self,
+        chunk: &Chunk,
+        datatypes: &[Datatype],
+        sink: &FileOutput,
+    ) -> Result<(), FileError> {
+        let mut complete = Vec::new();
+        {
+            let mut pending = self.pending.lock().map_err(|_e| FileError::FileWriteError)?;
+            for datatype in datatypes.iter() {
+                for epoch in chunk_epochs(chunk, self.epoch_size) {
+                    let key = (*datatype, epoch);
+                    let remaining = match pending.get_mut(&key) {
+                        Some((remaining, _)) => remaining,
+                        None => continue,
+                    };
+                    *remaining -= 1;
+                    if *remaining == 0 {
+                        if let Some((_, rollup)) = pending.remove(&key) {
+                            complete.push((*datatype, epoch, rollup));
+                        }
+                    }
+                }
+            }
+        }
+        for (datatype, epoch, rollup) in complete.into_iter() {
+            self.write(datatype, epoch, &rollup, sink)?;
+        }
+        Ok(())
+    }
+
+    fn write(
+        &self,
+        datatype: Datatype,
+        epoch: u64,
+        rollup: &EpochRollup,
+        sink: &FileOutput,
+    ) -> Result<(), FileError> {
+        let start_block = epoch * self.epoch_size;
+        let end_block = start_block + self.epoch_size - 1;
+        let stub = BlockChunk::Range(start_block, end_block).stub()?;
+        let name = datatype.dataset().name();
+        let dir = format!("{}/{}", sink.output_dir, ROLLUP_DIR);
+        std::fs::create_dir_all(&dir).map_err(|_e| FileError::FileWriteError)?;
+        let path = format!("{}/{}__{}__rollup__{}.json", dir, sink.prefix, name, stub);
+
+        let sums: BTreeMap<&str, String> =
+            rollup.sums.iter().map(|(name, sum)| (name.as_str(), sum.to_string())).collect();
+        let contents = serde_json::json!({
+            "datatype": name,
+            "start_block": start_block,
+            "end_block": end_block,
+            "complete": rollup.n_chunks_collected == rollup.n_chunks,
+            "n_chunks": rollup.n_chunks,
+            "n_chunks_collected": rollup.n_chunks_collected,
+            "n_rows": rollup.n_rows,
+            "n_active_addresses": rollup.addresses.len(),
+            "sums": sums,
+        });
+        let contents =
+            serde_json::to_string_pretty(&contents).map_err(|_e| FileError::FileWriteError)?;
+        std::fs::write(path, contents).map_err(|_e| FileError::FileWriteError)
+    }
+}
+
+impl EpochRollup {
+    fn merge(&mut self, other: &EpochRollup) {
+        self.n_rows += other.n_rows;
+        for (name, value) in other.sums.iter() {
+            let sum = self.sums.entry(name.clone()).or_default();
+            *sum = sum.saturating_add(*value);
+        }
+        self.addresses.extend(other.addresses.iter().cloned());
+    }
+}
+
+/// epochs overlapping a block chunk, none for other chunk types
+fn chunk_epochs(chunk: &Chunk, epoch_size: u64) -> Vec<u64> {
+    match chunk {
+        Chunk::Block(block_chunk) => match (block_chunk.min_value(), block_chunk.max_value()) {
+            (Some(min), Some(max)) => (min / epoch_size..=max / epoch_size).collect(),
+            _ => vec![],
+        },
+        _ => vec![],
+    }
+}
+
+/// addresses of a binary or hex column
+fn address_values(series: &Series) -> Result<Vec<Option<Vec<u8>>>, FileError> {
+    match series.dtype() {
+        DataType::Binary => Ok(series
+            .binary()
+            .map_err(|_e| FileError::FileWriteError)?
+            .into_iter()
+            .map(|value| value.map(|v| v.to_vec()))
+            .collect()),
+        DataType::Utf8 => Ok(series
+            .utf8()
+            .map_err(|_e| FileError::FileWriteError)?
+            .into_iter()
+            .map(|value| value.and_then(decode_hex))
+            .collect()),
+        _ => Ok(vec![None; series.len()]),
+    }
+}
@@ -1,14 +1,15 @@
 use polars::prelude::*;
 
-use crate::types::{CollectError, Table};
+use crate::types::{CollectError, ColumnType, FileError, Table};
 
 pub(crate) trait SortableDataFrame {
     fn sort_by_schema(self, schema: &Table) -> Self;
 }
 
 impl SortableDataFrame for Result<DataFrame, CollectError> {
+    /// sort rows by schema sort columns, after widening uint32 columns the schema widened
     fn sort_by_schema(self, schema: &Table) -> Self {
-        match (self, &schema.sort_columns) {
+        match (self.and_then(|df| widen_integer_columns(df, schema)), &schema.sort_columns) {
             (Ok(df), Some(sort_columns)) => {
                 df.sort(sort_columns, false).map_err(CollectError::PolarsError)
             }
@@ -16,3 +17,62 @@ impl SortableDataFrame for Result<DataFrame, CollectError> {
         }
     }
 }
+
+/// cast uint32 series to uint64 where the schema widened their column
+fn widen_integer_columns(mut df: DataFrame, schema: &Table) -> Result<DataFrame, CollectError> {
+    for name in schema.columns() {
+        if schema.column_type(name) != Some(ColumnType::UInt64) {
+            continue
+        }
+        let widened = match df.column(name) {
+            Ok(series) if series.dtype() == &DataType::UInt32 => series.cast(&DataType::UInt64)?,
+            _ => continue,
+        };
+        df.replace(name, widened)?;
+    }
+    Ok(df)
+}
+
+/// sort rows by schema sort columns, then by every other sortable column, so that rows come out
+/// in the same order however they were collected
+///
+/// list and struct columns cannot be sorted on, rows equal in every other column are identical
+/// in practice. polars cannot sort on several columns when one is boolean, so boolean columns are
+/// sorted on as uint32 and cast back afterwards
+pub(crate) fn sort_deterministic(
+    mut df: DataFrame,
+    schema: Option<&Table>,
+) -> Result<DataFrame, FileError> {
+    let mut by: Vec<String> =
+        schema.and_then(|schema| schema.sort_columns.clone()).unwrap_or_default();
+    for column in df.get_columns().iter() {
+        let sortable = !matches!(column.dtype(), DataType::List(_) | DataType::Struct(_));
+        if sortable && !by.iter().any(|name| name == column.name()) {
+            by.push(column.name().to_string());
+        }
+    }
+    by.retain(|name| df.column(name).is_ok());
+    if by.is_empty() {
+        return Ok(df)
+    }
+    let booleans: Vec<String> = df
+        .get_columns()
+        .iter()
+        .filter(|column| column.dtype() == &DataType::Boolean)
+        .map(|column| column.name().to_string())
+        .collect();
+    for name in booleans.iter() {
+        let column = df.column(name).and_then(|column| column.cast(&DataType::UInt32));
+        column
+            .and_then(|column| df.replace(name, column).map(|_| ()))
+            .map_err(|_e| FileError::FileWriteError)?;
+    }
+    let mut df = df.sort(by, false).map_err(|_e| FileError::FileWriteError)?;
+    for name in booleans.iter() {
+        let column = df.column(name).and_then(|column| column.cast(&DataType::Boolean));
+        column
+            .and_then(|column| df.replace(name, column).map(|_| ()))
+            .map_err(|_e| FileError::FileWriteError)?;
+    }
+    Ok(df)
+}
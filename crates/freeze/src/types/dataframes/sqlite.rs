@@ -0,0 +1,285 @@
+use std::{path::Path, sync::Mutex, time::Duration};
+
+use polars::prelude::*;
+use rusqlite::{params, types::Value, Connection, Transaction};
+
+use super::source_date_epoch;
+use crate::types::{
+    ColumnType, CommitStatus, Datatype, FileError, FileOutput, Manifest, ManifestEntry,
+    ManifestFile, Table,
+};
+
+/// table of the database recording the rows of each committed chunk
+const CHUNKS_TABLE: &str = "cryo_chunks";
+
+/// sqlite database holding every chunk of a run, with one table per dataset
+///
+/// each chunk is inserted in a single transaction, together with the range of rowids it
+/// occupies, so a chunk is either fully present or absent and can be replaced when overwriting
+pub(crate) struct SqliteSink {
+    path: String,
+    filename: String,
+    connection: Mutex<Connection>,
+    allow_lossy: bool,
+}
+
+/// sqlite column type of a column of a dataset
+#[derive(Clone, Debug)]
+pub struct SqlColumnMapping {
+    /// name of column
+    pub column: String,
+    /// cryo type of column
+    pub column_type: ColumnType,
+    /// sqlite type of column
+    pub sql_type: &'static str,
+    /// how values of the column can lose precision in sqlite, if they can
+    pub lossy: Option<&'static str>,
+}
+
+/// sqlite column types of the columns of a schema, flagging lossy mappings
+pub fn sqlite_mapping(schema: &Table) -> Vec<SqlColumnMapping> {
+    schema
+        .columns()
+        .into_iter()
+        .filter_map(|column| {
+            let column_type = schema.column_type(column)?;
+            let (sql_type, lossy) = match column_type {
+                ColumnType::UInt32 |
+                ColumnType::Int32 |
+                ColumnType::Int64 |
+                ColumnType::Boolean => ("INTEGER", None),
+                ColumnType::UInt64 => ("INTEGER", Some("values above 2^63-1 are stored as REAL")),
+                ColumnType::Float64 => ("REAL", None),
+                // decimal128 columns are integer strings unless converted with --decimal
+                ColumnType::Decimal128 | ColumnType::String | ColumnType::Hex => ("TEXT", None),
+                ColumnType::Binary => ("BLOB", None),
+            };
+            Some(SqlColumnMapping { column: column.to_string(), column_type, sql_type, lossy })
+        })
+        .collect()
+}
+
+impl SqliteSink {
+    /// open or create the database of an output directory
+    pub(crate) fn open(sink: &FileOutput) -> Result<SqliteSink, FileError> {
+        let filename = match &sink.suffix {
+            Some(suffix) => format!("{}__{}.sqlite", sink.prefix, suffix),
+            None => format!("{}.sqlite", sink.prefix),
+        };
+        let path = Path::new(&sink.output_dir).join(&filename).to_string_lossy().into_owned();
+        let connection = Connection::open(&path).map_err(|_e| FileError::FileWriteError)?;
+        // backfill and follow write through separate connections
+        connection.busy_timeout(Duration::from_secs(60)).map_err(|_e| FileError::FileWriteError)?;
+        let sql = format!(
+            "CREATE TABLE IF NOT EXISTS {} (dataset TEXT, chunk TEXT, first_rowid INTEGER, \
+             last_rowid INTEGER, PRIMARY KEY (dataset, chunk))",
+            CHUNKS_TABLE
+        );
+        connection.execute(&sql, []).map_err(|_e| FileError::FileWriteError)?;
+        Ok(SqliteSink {
+            path,
+            filename,
+            connection: Mutex::new(connection),
+            allow_lossy: sink.allow_lossy,
+        })
+    }
+
+    /// whether a chunk of a dataset is already in the database
+    pub(crate) fn is_committed(&self, dataset: &str, chunk: &str) -> bool {
+        let connection = self.connection.lock().expect("sqlite lock");
+        let sql = format!("SELECT 1 FROM {} WHERE dataset = ?1 AND chunk = ?2", CHUNKS_TABLE);
+        connection.query_row(&sql, params![dataset, chunk], |_row| Ok(())).is_ok()
+    }
+
+    /// insert all dataframes of a chunk in one transaction, replacing any earlier copy of it
+    ///
+    /// the growth of the database file is attributed to the first dataset of the chunk
+    pub(crate) fn commit_chunk(
+        &self,
+        mut dfs: Vec<(Datatype, DataFrame)>,
+        chunk_stub: &str,
+        file_output: &FileOutput,
+        manifest: &Manifest,
+    ) -> Result<ManifestEntry, FileError> {
+        dfs.sort_by_key(|(datatype, _)| datatype.dataset().name());
+        if !self.allow_lossy {
+            for (datatype, df) in dfs.iter() {
+                check_lossless(datatype.dataset().name(), df)?;
+            }
+        }
+        let mut connection = self.connection.lock().map_err(|_e| FileError::FileWriteError)?;
+        let n_bytes_before = self.n_bytes();
+        let tx = connection.transaction().map_err(|_e| FileError::FileWriteError)?;
+        let mut files = Vec::new();
+        for (datatype, df) in dfs.iter() {
+            let table = datatype.dataset().name();
+            insert_chunk(&tx, table, chunk_stub, df).map_err(|_e| FileError::FileWriteError)?;
+            files.push(ManifestFile {
+                dataset: table.to_string(),
+                filename: self.filename.clone(),
+                n_rows: df.height() as u64,
+                n_bytes: 0,
+                sha256: None,
+            });
+        }
+        tx.commit().map_err(|_e| FileError::FileWriteError)?;
+        if let Some(file) = files.first_mut() {
+            file.n_bytes = self.n_bytes().saturating_sub(n_bytes_before);
+        }
+
+        let entry = ManifestEntry {
+            status: CommitStatus::Committed,
+            chunk: chunk_stub.to_string(),
+            datasets: files.iter().map(|file| file.dataset.clone()).collect(),
+            files,
+            timestamp: match file_output.deterministic {
+                true => source_date_epoch(),
+                false => std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or_default(),
+            },
+            lineage: file_output.lineage.clone(),
+        };
+        manifest.append(&entry)?;
+        Ok(entry)
+    }
+
+    /// create empty tables for dataframes, without recording any chunk
+    pub(crate) fn create_tables(&self, dfs: &[(Datatype, DataFrame)]) -> Result<(), FileError> {
+        let connection = self.connection.lock().map_err(|_e| FileError::FileWriteError)?;
+        for (datatype, df) in dfs.iter() {
+            create_table(&connection, datatype.dataset().name(), df)
+                .map_err(|_e| FileError::FileWriteError)?;
+        }
+        Ok(())
+    }
+
+    /// path of the database file
+    pub(crate) fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn n_bytes(&self) -> u64 {
+        std::fs::metadata(&self.path).map(|m| m.len()).unwrap_or_default()
+    }
+}
+
+/// insert the rows of a chunk into the table of its dataset, creating the table if needed
+fn insert_chunk(
+    tx: &Transaction<'_>,
+    table: &str,
+    chunk_stub: &str,
+    df: &DataFrame,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let columns = df.get_columns();
+    create_table(tx, table, df)?;
+
+    // remove rows of an earlier commit of the chunk, when overwriting
+    let previous = tx.query_row(
+        &format!(
+            "SELECT first_rowid, last_rowid FROM {} WHERE dataset = ?1 AND chunk = ?2",
+            CHUNKS_TABLE
+        ),
+        params![table, chunk_stub],
+        |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?)),
+    );
+    if let Ok((first, last)) = previous {
+        let sql = format!("DELETE FROM \"{}\" WHERE rowid BETWEEN ?1 AND ?2", table);
+        tx.execute(&sql, params![first, last])?;
+    }
+
+    // rows inserted by a single transaction receive consecutive rowids
+    let first_rowid: i64 = tx.query_row(
+        &format!("SELECT COALESCE(MAX(rowid), 0) + 1 FROM \"{}\"", table),
+        [],
+        |row| row.get(0),
+    )?;
+    let names: Vec<String> =
+        columns.iter().map(|column| format!("\"{}\"", column.name())).collect();
+    let placeholders: Vec<String> = (1..=columns.len()).map(|i| format!("?{}", i)).collect();
+    let sql = format!(
+        "INSERT INTO \"{}\" ({}) VALUES ({})",
+        table,
+        names.join(", "),
+        placeholders.join(", ")
+    );
+    let mut statement = tx.prepare_cached(&sql)?;
+    for index in 0..df.height() {
+        let mut values = Vec::with_capacity(columns.len());
+        for column in columns.iter() {
+            values.push(sql_value(column.get(index)?));
+        }
+        statement.execute(rusqlite::params_from_iter(values))?;
+    }
+    let last_rowid = first_rowid + df.height() as i64 - 1;
+    tx.execute(
+        &format!("INSERT OR REPLACE INTO {} VALUES (?1, ?2, ?3, ?4)", CHUNKS_TABLE),
+        params![table, chunk_stub, first_rowid, last_rowid],
+    )?;
+    Ok(())
+}
+
+/// fail if any value of a dataframe would lose precision in sqlite
+fn check_lossless(table: &str, df: &DataFrame) -> Result<(), FileError> {
+    for column in df.get_columns().iter() {
+        if column.dtype() == &DataType::UInt64 &&
+            column.max::<u64>().map(|max| max > i64::MAX as u64).unwrap_or(false)
+        {
+            return Err(FileError::LossyValue(format!(
+                "column {}.{} has values above 2^63-1",
+                table,
+                column.name()
+            )))
+        }
+    }
+    Ok(())
+}
+
+/// create the table of a dataset with the columns of a dataframe, if it does not exist
+fn create_table(connection: &Connection, table: &str, df: &DataFrame) -> rusqlite::Result<()> {
+    let definitions: Vec<String> = df
+        .get_columns()
+        .iter()
+        .map(|column| format!("\"{}\" {}", column.name(), sql_type(column.dtype())))
+        .collect();
+    let sql = format!("CREATE TABLE IF NOT EXISTS \"{}\" ({})", table, definitions.join(", "));
+    connection.execute(&sql, []).map(|_| ())
+}
+
+/// sqlite column type of a polars dtype
+fn sql_type(dtype: &DataType) -> &'static str {
+    match dtype {
+        DataType::Boolean |
+        DataType::Int8 |
+        DataType::Int16 |
+        DataType::Int32 |
+        DataType::Int64 |
+        DataType::UInt8 |
+        DataType::UInt16 |
+        DataType::UInt32 |
+        DataType::UInt64 => "INTEGER",
+        DataType::Float32 | DataType::Float64 => "REAL",
+        DataType::Binary => "BLOB",
+        _ => "TEXT",
+    }
+}
+
+/// sqlite value of a polars value, nested values are stored as text
+///
+/// uint64 values beyond i64 are stored as reals, which is only reached with --allow-lossy
+fn sql_value(value: AnyValue<'_>) -> Value {
+    match value {
+        AnyValue::Null => Value::Null,
+        AnyValue::UInt64(value) if value > i64::MAX as u64 => Value::Real(value as f64),
+        AnyValue::Boolean(value) => Value::Integer(value as i64),
+        AnyValue::Utf8(value) => Value::Text(value.to_string()),
+        AnyValue::Binary(value) => Value::Blob(value.to_vec()),
+        AnyValue::Float32(value) => Value::Real(f64::from(value)),
+        AnyValue::Float64(value) => Value::Real(value),
+        value => match value.extract::<i64>() {
+            Some(number) => Value::Integer(number),
+            None => Value::Text(value.to_string()),
+        },
+    }
+}
@@ -0,0 +1,121 @@
+use std::hash::{Hash, Hasher};
+
+use polars::prelude::*;
+use serde::Serialize;
+
+use crate::types::FileError;
+
+/// directory of output_dir holding statistics sidecars
+pub(crate) const STATS_DIR: &str = "stats";
+
+/// registers of distinct count estimates are indexed by this many bits of each hash
+const HLL_PRECISION: u32 = 12;
+
+/// statistics of an output file, written as json next to the data so that catalogs can assess
+/// a file without opening it
+#[derive(Serialize)]
+struct FileStats {
+    file: String,
+    n_rows: u64,
+    min_block: Option<u64>,
+    max_block: Option<u64>,
+    columns: Vec<ColumnStats>,
+}
+
+/// statistics of one column of an output file
+#[derive(Serialize)]
+struct ColumnStats {
+    name: String,
+    dtype: String,
+    null_count: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    min: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max: Option<serde_json::Value>,
+    /// estimate of distinct values of binary and string columns, e.g. addresses
+    #[serde(skip_serializing_if = "Option::is_none")]
+    distinct_estimate: Option<u64>,
+}
+
+/// write the statistics sidecar of a dataframe written to a file
+pub(crate) fn write_stats(df: &DataFrame, filename: &str, path: &str) -> Result<(), FileError> {
+    let block_numbers = df.column("block_number").ok();
+    let stats = FileStats {
+        file: filename.to_string(),
+        n_rows: df.height() as u64,
+        min_block: block_numbers.and_then(|series| series.min::<u64>()),
+        max_block: block_numbers.and_then(|series| series.max::<u64>()),
+        columns: df.get_columns().iter().map(column_stats).collect::<Result<_, _>>()?,
+    };
+    let json = serde_json::to_string_pretty(&stats).map_err(|_e| FileError::FileWriteError)?;
+    std::fs::write(path, json).map_err(|_e| FileError::FileWriteError)
+}
+
+fn column_stats(series: &Series) -> Result<ColumnStats, FileError> {
+    let (min, max, distinct_estimate) = match series.dtype() {
+        DataType::UInt8 | DataType::UInt16 | DataType::UInt32 | DataType::UInt64 => {
+            (series.min::<u64>().map(Into::into), series.max::<u64>().map(Into::into), None)
+        }
+        DataType::Int8 | DataType::Int16 | DataType::Int32 | DataType::Int64 => {
+            (series.min::<i64>().map(Into::into), series.max::<i64>().map(Into::into), None)
+        }
+        DataType::Float32 | DataType::Float64 => {
+            (series.min::<f64>().map(Into::into), series.max::<f64>().map(Into::into), None)
+        }
+        DataType::Binary => {
+            let mut hll = HyperLogLog::new();
+            let values = series.binary().map_err(|_e| FileError::FileWriteError)?;
+            values.into_iter().flatten().for_each(|value| hll.insert(value));
+            (None, None, Some(hll.estimate()))
+        }
+        DataType::Utf8 => {
+            let mut hll = HyperLogLog::new();
+            let values = series.utf8().map_err(|_e| FileError::FileWriteError)?;
+            values.into_iter().flatten().for_each(|value| hll.insert(value.as_bytes()));
+            (None, None, Some(hll.estimate()))
+        }
+        _ => (None, None, None),
+    };
+    Ok(ColumnStats {
+        name: series.name().to_string(),
+        dtype: series.dtype().to_string(),
+        null_count: series.null_count() as u64,
+        min,
+        max,
+        distinct_estimate,
+    })
+}
+
+/// hyperloglog estimate of the number of distinct values, within about 2% using 4KB
+struct HyperLogLog {
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    fn new() -> HyperLogLog {
+        HyperLogLog { registers: vec![0; 1 << HLL_PRECISION] }
+    }
+
+    fn insert(&mut self, value: &[u8]) {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        value.hash(&mut hasher);
+        let hash = hasher.finish();
+        let index = (hash >> (64 - HLL_PRECISION)) as usize;
+        let rank = ((hash << HLL_PRECISION) | (1 << (HLL_PRECISION - 1))).leading_zeros() + 1;
+        self.registers[index] = self.registers[index].max(rank as u8);
+    }
+
+    fn estimate(&self) -> u64 {
+        let m = self.registers.len() as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self.registers.iter().map(|register| 2f64.powi(-(*register as i32))).sum();
+        let raw = alpha * m * m / sum;
+        let n_zeros = self.registers.iter().filter(|register| **register == 0).count();
+        // linear counting is more accurate for small cardinalities
+        let estimate = match raw <= 2.5 * m && n_zeros > 0 {
+            true => m * (m / n_zeros as f64).ln(),
+            false => raw,
+        };
+        estimate.round() as u64
+    }
+}
@@ -0,0 +1,190 @@
+use super::Dataset;
+
+/// descriptions of columns by name, for columns that mean the same in every dataset having them
+///
+/// datasets override descriptions of columns whose meaning is specific to them with
+/// `Dataset::column_descriptions`
+const COLUMN_DESCRIPTIONS: [(&str, &str); 171] = [
+    ("action_call_type", "call, delegatecall, staticcall, or callcode"),
+    ("action_from", "sender of the call or creation"),
+    ("action_gas", "gas given to the call or creation"),
+    ("action_init", "init code of a creation"),
+    ("action_input", "calldata of the call"),
+    ("action_reward_type", "block or uncle, for reward traces"),
+    ("action_to", "recipient of the call"),
+    ("action_type", "call, create, suicide, or reward"),
+    ("action_value", "wei sent with the call or creation"),
+    ("address", "account address"),
+    ("allow_failure", "whether the multicall tolerates failure of the call"),
+    ("amount", "value divided by 10^decimals of the token"),
+    ("approved", "whether the operator is approved"),
+    ("author", "fee recipient of the block"),
+    ("balance", "balance in wei"),
+    ("base_fee_per_gas", "base fee of the block, in wei, after london"),
+    ("batch_index", "position of the token id within a TransferBatch"),
+    ("block_hash", "hash of the block"),
+    ("block_number", "number of the block"),
+    ("bridge", "name of the bridge"),
+    ("bridge_address", "contract address of the bridge"),
+    ("call_index", "position of the call among the calls of its multicall"),
+    ("call_path", "positions of the call within nested multicalls, e.g. 0.2"),
+    ("calldata", "calldata felts, as 32 byte words"),
+    ("chain_id", "chain id of the network"),
+    ("class_hash", "hash of the declared contract class"),
+    ("code", "contract code"),
+    ("contract_address", "address of the contract that emitted the log"),
+    ("cost", "gas cost of the opcode"),
+    ("creation_block", "block in which the address was created, if it is a contract"),
+    ("data", "non-indexed data of the log"),
+    ("decimals", "decimals() of the token, null if it does not implement decimals()"),
+    ("delegate_to", "implementation called by a collapsed proxy delegatecall"),
+    ("deposit_index", "index of the deposit in the deposit contract"),
+    ("depth", "number of parent calls"),
+    ("difficulty", "proof of work difficulty of the block"),
+    ("direction", "direction of the transfer"),
+    ("entry_point_selector", "selector of the entry point of an invoke"),
+    ("erc1155", "address of the erc1155 contract"),
+    ("erc20", "address of the erc20 token"),
+    ("error", "error message of the failed call"),
+    ("error_class", "normalized class of the error, e.g. out_of_gas or reverted"),
+    ("extra_data", "extra data set by the block producer"),
+    ("extra_data_utf8", "extra data decoded as utf8, null if not valid utf8"),
+    ("extra_fields", "json of fields returned by the rpc that have no column"),
+    ("fee_currency", "celo token in which gas is paid"),
+    ("first_block", "first block of the chunk in which the address was active"),
+    ("from_address", "sender"),
+    ("from_value", "value before the transaction"),
+    ("gas_limit", "gas limit"),
+    ("gas_price", "gas price, in wei"),
+    ("gas_spent", "gas used times effective gas price, in wei"),
+    ("gas_token", "token in which the safe refunds gas, zero address for ETH"),
+    ("gas_used", "gas used"),
+    ("gateway_fee", "celo gateway fee"),
+    ("gateway_fee_recipient", "celo recipient of the gateway fee"),
+    ("hash", "hash of the block"),
+    ("input", "calldata"),
+    ("is_batch", "whether the transfer is part of a TransferBatch"),
+    ("is_priority_operation", "whether the transaction is an l1 priority operation"),
+    ("is_proof_of_stake", "whether the block is after the merge"),
+    ("is_root", "whether the trace is the top level call of its transaction"),
+    ("is_state_sync", "whether the transaction is a polygon state sync"),
+    ("is_unlimited", "whether the approval is the maximum uint256"),
+    ("is_verified", "whether the recipient has verified source code on sourcify"),
+    ("l1_batch_number", "l1 batch containing the transaction"),
+    ("l1_batch_timestamp", "timestamp of the l1 batch"),
+    ("l1_batch_tx_index", "position of the transaction within its l1 batch"),
+    ("l1_block_number", "arbitrum l1 block number of the block"),
+    ("l1_gas_price_fri", "l1 gas price, in fri"),
+    ("l1_gas_price_wei", "l1 gas price, in wei"),
+    ("l1_token", "token on l1"),
+    ("l2_token", "token on l2"),
+    ("last_block", "last block of the chunk in which the address was active"),
+    ("log_index", "position of the log within the block"),
+    ("logs_bloom", "bloom filter of the logs of the block"),
+    ("max_fee", "maximum fee of the transaction"),
+    ("max_fee_per_gas", "maximum fee per gas of eip-1559 transactions, in wei"),
+    ("max_priority_fee_per_gas", "maximum priority fee per gas of eip-1559 transactions, in wei"),
+    ("max_refund", "arbitrum maximum refund of a retryable ticket"),
+    ("mem_data", "memory written by the opcode"),
+    ("mem_off", "offset of the memory written by the opcode"),
+    ("method", "json-rpc method of the request"),
+    ("mix_hash", "mix hash of the block"),
+    ("multicall_address", "address of the multicall contract"),
+    ("n_calldata", "number of calldata felts"),
+    ("n_children", "number of direct child calls"),
+    ("n_received", "number of transactions received"),
+    ("n_sent", "number of transactions sent"),
+    ("n_signatures", "number of owner signatures of the safe transaction"),
+    ("n_storage_slots", "number of storage slots allocated"),
+    ("n_transactions", "number of transactions in the block"),
+    ("new_root", "state root after the block"),
+    ("nonce", "nonce"),
+    ("number", "number of the block"),
+    ("op", "opcode name"),
+    ("operation", "0 for a call, 1 for a delegatecall"),
+    ("operator_address", "account that performed or may perform transfers"),
+    ("owner_address", "owner of the tokens"),
+    ("params", "json params of the request"),
+    ("parent_hash", "hash of the parent block"),
+    ("parent_trace_address", "trace address of the parent call"),
+    ("payment", "gas refund paid by the safe"),
+    ("pc", "program counter of the opcode"),
+    ("prev_randao", "prevrandao of the block, after the merge"),
+    ("pubkey", "validator public key"),
+    ("push", "values pushed to the stack by the opcode"),
+    ("receipts_root", "receipts trie root of the block"),
+    ("refund_receiver", "recipient of the gas refund of the safe transaction"),
+    ("refund_to", "arbitrum recipient of the refund of a retryable ticket"),
+    ("request_id", "arbitrum id of the l1 request"),
+    ("response", "json result of the request"),
+    ("result_address", "address of the created contract"),
+    ("result_code", "code of the created contract"),
+    ("result_gas_used", "gas used by the call or creation"),
+    ("result_output", "return data of the call"),
+    ("revert_reason", "decoded Error(string) or Panic(uint256) revert reason"),
+    ("reverted", "whether the call reverted"),
+    ("safe_address", "address of the safe"),
+    ("safe_tx_gas", "gas given to the safe transaction"),
+    ("safe_tx_hash", "hash of the safe transaction signed by owners"),
+    ("scheme", "multicall variant of the call"),
+    ("selector", "first 4 bytes of the calldata"),
+    ("send_count", "arbitrum number of l2 to l1 messages"),
+    ("send_root", "arbitrum root of l2 to l1 messages"),
+    ("sender_address", "account that sent the transaction"),
+    ("sequencer_address", "sequencer that produced the block"),
+    ("signature", "signature"),
+    ("size", "size of the block, in bytes"),
+    ("slot", "storage slot"),
+    ("spender_address", "account allowed to spend the tokens"),
+    ("starknet_version", "starknet version of the block"),
+    ("state_root", "state trie root"),
+    ("status", "status"),
+    ("storage_key", "storage slot written by the opcode"),
+    ("storage_val", "storage value written by the opcode"),
+    ("submission_fee_refund", "arbitrum refund of the submission fee of a retryable ticket"),
+    ("subtraces", "number of direct child calls"),
+    ("success", "whether the safe transaction succeeded"),
+    ("target", "contract called"),
+    ("ticket_id", "arbitrum id of the retryable ticket"),
+    ("timestamp", "timestamp of the block, in unix seconds"),
+    ("to_address", "recipient"),
+    ("to_kind", "eoa or contract, for the recipient"),
+    ("to_value", "value after the transaction"),
+    ("token_id", "id of the token"),
+    ("topic0", "event signature hash, null for anonymous events"),
+    ("topic1", "first indexed argument"),
+    ("topic2", "second indexed argument"),
+    ("topic3", "third indexed argument"),
+    ("total_difficulty", "cumulative difficulty of the chain up to the block"),
+    ("trace_address", "position of the call in the call tree, e.g. 0_2"),
+    ("transaction_from", "sender of the transaction"),
+    ("transaction_hash", "hash of the transaction"),
+    ("transaction_index", "position of the transaction within the block"),
+    ("transaction_kind", "transfer, call, or create"),
+    ("transaction_position", "position of the transaction within the block"),
+    ("transaction_type", "type of the transaction"),
+    ("transaction_type_name", "name of the transaction type, e.g. eip1559"),
+    ("transactions_root", "transactions trie root of the block"),
+    ("used", "gas used by the opcode"),
+    ("validator_index", "index of the validator"),
+    ("value", "value"),
+    ("value_float", "value in wei as a float"),
+    ("value_received", "wei received"),
+    ("value_sent", "wei sent"),
+    ("value_str", "value in wei as a decimal string"),
+    ("version", "transaction version"),
+    ("weth", "address of the weth contract"),
+    ("withdrawal_address", "execution address of 0x01 and 0x02 withdrawal credentials"),
+    ("withdrawal_credentials", "withdrawal credentials of the validator"),
+];
+
+/// description of a column of a dataset
+pub fn column_description(dataset: &dyn Dataset, column: &str) -> Option<&'static str> {
+    if let Some(description) = dataset.column_descriptions().get(column) {
+        return Some(description)
+    }
+    COLUMN_DESCRIPTIONS
+        .iter()
+        .find(|(name, _)| *name == column)
+        .map(|(_, description)| *description)
+}
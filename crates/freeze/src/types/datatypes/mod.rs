@@ -1,5 +1,7 @@
+mod descriptions;
 mod multi;
 mod scalar;
 
+pub use descriptions::column_description;
 pub use multi::*;
 pub use scalar::*;
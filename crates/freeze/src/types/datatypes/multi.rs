@@ -37,6 +37,11 @@ impl MultiDatatype {
             MultiDatatype::StateDiffs => Box::new(StateDiffs),
         }
     }
+
+    /// return Datatypes of MultiDatatype that are requested
+    pub fn requested_datatypes(&self, requested: &HashSet<Datatype>) -> HashSet<Datatype> {
+        self.multi_dataset().datatypes().intersection(requested).cloned().collect()
+    }
 }
 
 /// MultiDataset manages multiple datasets that get collected together
@@ -48,6 +53,11 @@ pub trait MultiDataset: Sync + Send {
     /// return Datatypes associated with MultiDataset
     fn datatypes(&self) -> HashSet<Datatype>;
 
+    /// whether requested Datatypes are collected together, by default when all are requested
+    fn collects_together(&self, requested: &HashSet<Datatype>) -> bool {
+        self.datatypes().is_subset(requested)
+    }
+
     /// return Datasets associated with MultiDataset
     fn datasets(&self) -> HashMap<Datatype, Box<dyn Dataset>> {
         self.datatypes().iter().map(|dt| (*dt, dt.dataset())).collect()
@@ -5,64 +5,187 @@ use polars::prelude::*;
 
 use crate::types::{
     AddressChunk, BlockChunk, Chunk, CollectError, ColumnType, RowFilter, Source, Table,
-    TransactionChunk,
+    TransactionChunk, Vm,
 };
 
+/// Address Activity Dataset
+pub struct AddressActivity;
+/// Address Lifespans Dataset
+pub struct AddressLifespans;
+/// Approvals For All Dataset
+pub struct ApprovalsForAll;
 /// Balance Diffs Dataset
 pub struct BalanceDiffs;
+/// Beacon Deposits Dataset
+pub struct BeaconDeposits;
 /// Blocks Dataset
 pub struct Blocks;
+/// Bridge Transfers Dataset
+pub struct BridgeTransfers;
 /// Code Diffs Dataset
 pub struct CodeDiffs;
+/// Erc1155 Transfers Dataset
+pub struct Erc1155Transfers;
+/// Erc20 Approvals Dataset
+pub struct Erc20Approvals;
+/// Erc20 Transfers Dataset
+pub struct Erc20Transfers;
+/// Gas Percentiles Dataset
+pub struct GasPercentiles;
+/// Genesis Dataset
+pub struct Genesis;
 /// Logs Dataset
 pub struct Logs;
+/// Multicall Calls Dataset
+pub struct MulticallCalls;
 /// Nonce Diffs Dataset
 pub struct NonceDiffs;
+/// Raw Rpc Dataset
+pub struct RawRpc;
+/// Safe Transactions Dataset
+pub struct SafeTransactions;
+/// Starknet Blocks Dataset
+pub struct StarknetBlocks;
+/// Starknet Transactions Dataset
+pub struct StarknetTransactions;
 /// Storage Diffs Dataset
 pub struct StorageDiffs;
 /// Traces Dataset
 pub struct Traces;
 /// Transactions Dataset
 pub struct Transactions;
+/// Validators Dataset
+pub struct Validators;
 /// VmTraces Dataset
 pub struct VmTraces;
+/// Weth Wraps Dataset
+pub struct WethWraps;
+/// ZkSync Transactions Dataset
+pub struct ZkSyncTransactions;
 
 /// enum of possible datatypes that cryo can collect
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Datatype {
+    /// Address Activity
+    AddressActivity,
+    /// Address Lifespans
+    AddressLifespans,
+    /// Approvals For All
+    ApprovalsForAll,
     /// Balance Diffs
     BalanceDiffs,
+    /// Beacon Deposits
+    BeaconDeposits,
     /// Blocks
     Blocks,
+    /// Bridge Transfers
+    BridgeTransfers,
     /// Code Diffs
     CodeDiffs,
+    /// Erc1155 Transfers
+    Erc1155Transfers,
+    /// Erc20 Approvals
+    Erc20Approvals,
+    /// Erc20 Transfers
+    Erc20Transfers,
+    /// Gas Percentiles
+    GasPercentiles,
+    /// Genesis
+    Genesis,
     /// Logs
     Logs,
+    /// Multicall Calls
+    MulticallCalls,
     /// Nonce Diffs
     NonceDiffs,
+    /// Raw Rpc
+    RawRpc,
     /// Transactions
     Transactions,
     /// Traces
     Traces,
+    /// Safe Transactions
+    SafeTransactions,
+    /// Starknet Blocks
+    StarknetBlocks,
+    /// Starknet Transactions
+    StarknetTransactions,
     /// Storage Diffs
     StorageDiffs,
+    /// Validators
+    Validators,
     /// VmTraces
     VmTraces,
+    /// Weth Wraps
+    WethWraps,
+    /// ZkSync Transactions
+    ZkSyncTransactions,
 }
 
 impl Datatype {
+    /// return all variants of datatype
+    pub fn variants() -> Vec<Datatype> {
+        vec![
+            Datatype::AddressActivity,
+            Datatype::AddressLifespans,
+            Datatype::ApprovalsForAll,
+            Datatype::BalanceDiffs,
+            Datatype::BeaconDeposits,
+            Datatype::Blocks,
+            Datatype::BridgeTransfers,
+            Datatype::CodeDiffs,
+            Datatype::Erc1155Transfers,
+            Datatype::Erc20Approvals,
+            Datatype::Erc20Transfers,
+            Datatype::GasPercentiles,
+            Datatype::Genesis,
+            Datatype::Logs,
+            Datatype::MulticallCalls,
+            Datatype::NonceDiffs,
+            Datatype::RawRpc,
+            Datatype::Transactions,
+            Datatype::Traces,
+            Datatype::SafeTransactions,
+            Datatype::StarknetBlocks,
+            Datatype::StarknetTransactions,
+            Datatype::StorageDiffs,
+            Datatype::Validators,
+            Datatype::VmTraces,
+            Datatype::WethWraps,
+            Datatype::ZkSyncTransactions,
+        ]
+    }
+
     /// get the Dataset struct corresponding to Datatype
     pub fn dataset(&self) -> Box<dyn Dataset> {
         match *self {
+            Datatype::AddressActivity => Box::new(AddressActivity),
+            Datatype::AddressLifespans => Box::new(AddressLifespans),
+            Datatype::ApprovalsForAll => Box::new(ApprovalsForAll),
             Datatype::BalanceDiffs => Box::new(BalanceDiffs),
+            Datatype::BeaconDeposits => Box::new(BeaconDeposits),
             Datatype::Blocks => Box::new(Blocks),
+            Datatype::BridgeTransfers => Box::new(BridgeTransfers),
             Datatype::CodeDiffs => Box::new(CodeDiffs),
+            Datatype::Erc1155Transfers => Box::new(Erc1155Transfers),
+            Datatype::Erc20Approvals => Box::new(Erc20Approvals),
+            Datatype::Erc20Transfers => Box::new(Erc20Transfers),
+            Datatype::GasPercentiles => Box::new(GasPercentiles),
+            Datatype::Genesis => Box::new(Genesis),
             Datatype::Logs => Box::new(Logs),
+            Datatype::MulticallCalls => Box::new(MulticallCalls),
             Datatype::NonceDiffs => Box::new(NonceDiffs),
+            Datatype::RawRpc => Box::new(RawRpc),
             Datatype::Transactions => Box::new(Transactions),
             Datatype::Traces => Box::new(Traces),
+            Datatype::SafeTransactions => Box::new(SafeTransactions),
+            Datatype::StarknetBlocks => Box::new(StarknetBlocks),
+            Datatype::StarknetTransactions => Box::new(StarknetTransactions),
             Datatype::StorageDiffs => Box::new(StorageDiffs),
+            Datatype::Validators => Box::new(Validators),
             Datatype::VmTraces => Box::new(VmTraces),
+            Datatype::WethWraps => Box::new(WethWraps),
+            Datatype::ZkSyncTransactions => Box::new(ZkSyncTransactions),
         }
     }
 }
@@ -87,6 +210,21 @@ pub trait Dataset: Sync + Send {
     /// default sort order for dataset
     fn default_sort(&self) -> Vec<String>;
 
+    /// columns that can be null, e.g. fields absent before a fork or on some chains
+    fn nullable_columns(&self) -> Vec<&'static str> {
+        vec![]
+    }
+
+    /// descriptions of columns whose meaning is specific to dataset, see `column_description`
+    fn column_descriptions(&self) -> HashMap<&'static str, &'static str> {
+        HashMap::new()
+    }
+
+    /// rpc dialect required of the source
+    fn vm(&self) -> Vm {
+        Vm::Evm
+    }
+
     /// collect dataset for a particular chunk
     async fn collect_chunk(
         &self,
@@ -3,6 +3,8 @@ use ethers::prelude::*;
 use polars::prelude::*;
 use thiserror::Error;
 
+use crate::types::{Chunk, RpcClientError};
+
 /// Error related to running freeze function
 #[derive(Error, Debug)]
 pub enum FreezeError {
@@ -23,8 +25,32 @@ pub enum FreezeError {
     ProgressBarError(#[from] indicatif::style::TemplateError),
 
     /// Parse error
-    #[error("Parsing error")]
+    #[error(transparent)]
     ParseError(#[from] ParseError),
+
+    /// Output limit exceeded
+    #[error("Output limit exceeded: {0}")]
+    OutputLimitExceeded(String),
+
+    /// Output directory locked by another run
+    #[error("Output directory locked: {0}")]
+    OutputLocked(String),
+
+    /// Not enough free disk space to start
+    #[error("Insufficient disk space: {0}")]
+    InsufficientDiskSpace(String),
+
+    /// Run aborted at a prompt about a failed chunk
+    #[error("Run aborted: {0}")]
+    Aborted(String),
+
+    /// Following stopped by a health check alert
+    #[error("Following unhealthy: {0}")]
+    FollowUnhealthy(String),
+
+    /// Chunk not collected because the run was cancelled
+    #[error("Cancelled: {0}")]
+    Cancelled(String),
 }
 
 /// Error related to data collection
@@ -34,9 +60,41 @@ pub enum CollectError {
     #[error("Collect failed: {0}")]
     CollectError(String),
 
+    /// Error collecting a particular chunk of a particular dataset
+    #[error("Failed to collect {dataset} for chunk {}: {source}", .chunk.stub().unwrap_or_default())]
+    ChunkFailed {
+        /// name of dataset being collected
+        dataset: &'static str,
+        /// chunk being collected
+        chunk: Chunk,
+        /// underlying error
+        #[source]
+        source: Box<CollectError>,
+    },
+
     /// Error related to provider operations
-    #[error("Failed to get block: {0}")]
-    ProviderError(#[source] ProviderError),
+    #[error("RPC call {method} failed after {retries} retries: {source}")]
+    ProviderError {
+        /// JSON-RPC method that failed
+        method: &'static str,
+        /// number of retries attempted before failing
+        retries: u32,
+        /// underlying provider error
+        #[source]
+        source: ProviderError,
+    },
+
+    /// Error related to provider rate limits
+    #[error("RPC call {method} was rate limited after {retries} retries, try using a rate limit with --requests-per-second or limiting max concurrency with --max-concurrent-requests")]
+    RateLimitError {
+        /// JSON-RPC method that was rate limited
+        method: &'static str,
+        /// number of retries attempted before failing
+        retries: u32,
+        /// underlying provider error
+        #[source]
+        source: ProviderError,
+    },
 
     /// Error related to tokio task
     #[error("Task failed: {0}")]
@@ -54,24 +112,216 @@ pub enum CollectError {
     #[error("Bad schema specified")]
     BadSchemaError,
 
-    /// Error related to too many requests
-    #[error("try using a rate limit with --requests-per-second or limiting max concurrency with --max-concurrent-requests")]
-    TooManyRequestsError,
+    /// Chunk abandoned after exceeding --chunk-timeout
+    #[error("Chunk timed out after {} seconds", .0.as_secs_f64())]
+    ChunkTimeout(std::time::Duration),
+}
+
+impl CollectError {
+    /// create error from a failed RPC call, classifying rate limit errors
+    pub fn rpc(method: &'static str, source: ProviderError) -> CollectError {
+        let retries = rpc_retries(&source);
+        if is_rate_limit_error(&source) {
+            CollectError::RateLimitError { method, retries, source }
+        } else {
+            CollectError::ProviderError { method, retries, source }
+        }
+    }
+
+    /// add dataset and chunk context to error
+    pub fn in_chunk(self, dataset: &'static str, chunk: &Chunk) -> CollectError {
+        match self {
+            CollectError::ChunkFailed { .. } => self,
+            e => CollectError::ChunkFailed { dataset, chunk: chunk.clone(), source: Box::new(e) },
+        }
+    }
 
-    /// Generic RPC Error
-    #[error("RPC call error")]
-    RPCError(String),
+    /// get underlying error, without chunk context
+    pub fn inner(&self) -> &CollectError {
+        match self {
+            CollectError::ChunkFailed { source, .. } => source.inner(),
+            e => e,
+        }
+    }
+
+    /// whether error was caused by provider rate limits
+    pub fn is_rate_limit(&self) -> bool {
+        matches!(self.inner(), CollectError::RateLimitError { .. })
+    }
+
+    /// whether error was caused by an invalid schema
+    pub fn is_schema_error(&self) -> bool {
+        matches!(
+            self.inner(),
+            CollectError::BadSchemaError |
+                CollectError::PolarsError(_) |
+                CollectError::InvalidNumberOfTopics
+        )
+    }
+
+    /// JSON-RPC method that caused error, if any
+    pub fn method(&self) -> Option<&'static str> {
+        match self.inner() {
+            CollectError::ProviderError { method, .. } |
+            CollectError::RateLimitError { method, .. } => Some(method),
+            _ => None,
+        }
+    }
+
+    /// number of retries attempted before error
+    pub fn retries(&self) -> u32 {
+        match self.inner() {
+            CollectError::ProviderError { retries, .. } |
+            CollectError::RateLimitError { retries, .. } => *retries,
+            _ => 0,
+        }
+    }
+
+    /// cause of error, normalized across the error messages of different providers
+    pub fn kind(&self) -> ErrorKind {
+        match self.inner() {
+            CollectError::RateLimitError { .. } => ErrorKind::RateLimited,
+            CollectError::ProviderError { source, .. } => provider_error_kind(source),
+            CollectError::ChunkTimeout(_) => ErrorKind::Connection,
+            _ => ErrorKind::Local,
+        }
+    }
+}
+
+impl FreezeError {
+    /// cause of error, normalized across the error messages of different providers
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            FreezeError::CollectError(e) => e.kind(),
+            _ => ErrorKind::Local,
+        }
+    }
+}
+
+/// cause of a failed chunk, telling apart failures of the provider from failures of cryo
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ErrorKind {
+    /// provider rejected requests for exceeding its rate limits
+    RateLimited,
+    /// provider has pruned the historical state needed by a request
+    PrunedState,
+    /// provider does not support a method, e.g. trace_block without a trace module
+    MissingMethod,
+    /// provider returned a response that could not be parsed
+    MalformedResponse,
+    /// provider could not be reached or timed out
+    Connection,
+    /// provider returned some other error
+    OtherRpc,
+    /// error unrelated to the provider, e.g. writing files
+    Local,
+}
+
+impl ErrorKind {
+    /// short name of error kind
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorKind::RateLimited => "rate limited",
+            ErrorKind::PrunedState => "pruned state",
+            ErrorKind::MissingMethod => "missing trace module",
+            ErrorKind::MalformedResponse => "malformed response",
+            ErrorKind::Connection => "connection failed",
+            ErrorKind::OtherRpc => "other rpc error",
+            ErrorKind::Local => "local error",
+        }
+    }
+
+    /// whether errors of this kind are caused by the provider rather than by cryo
+    pub fn is_provider_fault(&self) -> bool {
+        !matches!(self, ErrorKind::Local)
+    }
+
+    /// suggested remedy for errors of this kind
+    pub fn hint(&self) -> &'static str {
+        match self {
+            ErrorKind::RateLimited => "lower --requests-per-second or --max-concurrent-requests",
+            ErrorKind::PrunedState => "use an archive node for historical state",
+            ErrorKind::MissingMethod => "use a node with the trace or debug api enabled",
+            ErrorKind::MalformedResponse => "retry with --lenient",
+            ErrorKind::Connection => "check the rpc url and network, or raise --chunk-timeout",
+            ErrorKind::OtherRpc => "see the error messages above",
+            ErrorKind::Local => "see the error messages above",
+        }
+    }
+}
+
+fn provider_error_kind(error: &ProviderError) -> ErrorKind {
+    if is_rate_limit_error(error) {
+        return ErrorKind::RateLimited
+    }
+    if let Some(JsonRpcError { code: -32601, .. }) = RpcError::as_error_response(error) {
+        return ErrorKind::MissingMethod
+    }
+    if RpcError::as_serde_error(error).is_some() {
+        return ErrorKind::MalformedResponse
+    }
+    let message = error.to_string().to_lowercase();
+    let matches = |patterns: &[&str]| patterns.iter().any(|s| message.contains(s));
+    if matches(&[
+        "missing trie node",
+        "pruned",
+        "historical state",
+        "state is not available",
+        "state not available",
+        "header not found",
+        "old data not available",
+    ]) {
+        ErrorKind::PrunedState
+    } else if matches(&[
+        "method not found",
+        "does not exist/is not available",
+        "not supported",
+        "unsupported method",
+        "unsupported rpc method",
+        "namespace is disabled",
+        "module not enabled",
+    ]) {
+        ErrorKind::MissingMethod
+    } else if matches(&["deserializ", "invalid type", "missing field", "expected value"]) {
+        ErrorKind::MalformedResponse
+    } else if matches(&["connection", "timed out", "timeout", "dns error", "error sending request"])
+    {
+        ErrorKind::Connection
+    } else {
+        ErrorKind::OtherRpc
+    }
+}
+
+/// number of retries the rpc client attempted before a provider error
+fn rpc_retries(error: &ProviderError) -> u32 {
+    match error {
+        ProviderError::JsonRpcClientError(e) => {
+            let e: &(dyn std::error::Error + 'static) = e.as_ref();
+            e.downcast_ref::<RpcClientError>().map(RpcClientError::retries).unwrap_or(0)
+        }
+        _ => 0,
+    }
+}
+
+fn is_rate_limit_error(error: &ProviderError) -> bool {
+    if let Some(JsonRpcError { code, .. }) = RpcError::as_error_response(error) {
+        if matches!(*code, 429 | -32005) {
+            return true
+        }
+    }
+    let message = error.to_string().to_lowercase();
+    ["rate limit", "too many requests", "429"].iter().any(|s| message.contains(s))
 }
 
 /// Error related to parsing
 #[derive(Error, Debug)]
 pub enum ParseError {
     /// Error related to parsing
-    #[error("Parsing error")]
+    #[error("{0}")]
     ParseError(String),
 
     /// Parse int error
-    #[error("Parsing error")]
+    #[error("invalid integer: {0}")]
     ParseIntError(#[from] std::num::ParseIntError),
 }
 
@@ -101,4 +351,59 @@ pub enum FileError {
     /// Error in writing file
     #[error("Error writing file")]
     FileWriteError,
+
+    /// Error reading file
+    #[error("Error reading file: {0}")]
+    FileReadError(String),
+
+    /// Error reading or writing manifest
+    #[error("Error accessing manifest")]
+    ManifestError,
+
+    /// Error signing or verifying manifest
+    #[error("Signature error: {0}")]
+    SignatureError(String),
+
+    /// Value that would lose precision in the output format
+    #[error("{0}, which sqlite cannot store exactly, use --allow-lossy to store them as REAL")]
+    LossyValue(String),
+
+    /// Too many rows for the output format
+    #[error("{0} rows exceed the limit of {1} rows per xlsx file, use --xlsx-max-rows or a smaller --chunk-size")]
+    TooManyRows(usize, usize),
+}
+
+/// Error round-tripping data through an output format
+#[derive(Error, Debug)]
+pub enum RoundTripError {
+    /// Error building schema
+    #[error("Invalid schema: {0}")]
+    SchemaError(String),
+
+    /// Error writing file
+    #[error("Failed to write file: {0}")]
+    FileError(#[from] FileError),
+
+    /// Error reading file back
+    #[error("Failed to read file: {0}")]
+    ReadError(String),
+
+    /// Error related to polars operations
+    #[error("Polars error: {0}")]
+    PolarsError(#[from] PolarsError),
+
+    /// Error converting random values to columns
+    #[error("Failed to build column: {0}")]
+    CollectError(#[from] CollectError),
+
+    /// Column changed after round trip
+    #[error("Column {column} of {dataset} did not survive round trip through {format}")]
+    Mismatch {
+        /// name of dataset
+        dataset: String,
+        /// file format
+        format: &'static str,
+        /// name of column
+        column: String,
+    },
 }
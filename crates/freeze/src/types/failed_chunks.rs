@@ -0,0 +1,94 @@
+use std::{
+    io::{BufRead, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::{CollectError, FileError};
+
+/// name of file listing chunks that failed, placed in output directory
+pub const FAILED_CHUNKS_NAME: &str = "cryo_failed_chunks.jsonl";
+
+/// record of a chunk that failed and was not written
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FailedChunk {
+    /// name of dataset, or multi dataset
+    pub dataset: String,
+    /// stub of chunk
+    pub chunk: String,
+    /// normalized cause of failure, e.g. `rate limited`
+    pub kind: String,
+    /// error message
+    pub error: String,
+    /// unix time of failure
+    pub timestamp: u64,
+}
+
+/// append-only log of failed chunks, stored as json lines
+///
+/// chunks are logged whenever they fail, so a chunk that later succeeds still has its failures
+/// listed. committed chunks are recorded by the manifest instead
+#[derive(Debug)]
+pub struct FailedChunkLog {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl FailedChunkLog {
+    /// log of failed chunks of output directory
+    pub fn new(output_dir: &str) -> FailedChunkLog {
+        FailedChunkLog {
+            path: Path::new(output_dir).join(FAILED_CHUNKS_NAME),
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// append failure of a chunk to log
+    pub fn record(
+        &self,
+        dataset: &str,
+        chunk: &str,
+        error: &CollectError,
+    ) -> Result<(), FileError> {
+        let entry = FailedChunk {
+            dataset: dataset.to_string(),
+            chunk: chunk.to_string(),
+            kind: error.kind().as_str().to_string(),
+            error: error.to_string(),
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+        };
+        let line = serde_json::to_string(&entry).map_err(|_e| FileError::FileWriteError)?;
+        let _lock = self.lock.lock().map_err(|_e| FileError::FileWriteError)?;
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|_e| FileError::FileWriteError)?;
+        file.write_all(format!("{}\n", line).as_bytes()).map_err(|_e| FileError::FileWriteError)
+    }
+
+    /// read failed chunks logged in output directory
+    pub fn read(output_dir: &str) -> Result<Vec<FailedChunk>, FileError> {
+        let path = Path::new(output_dir).join(FAILED_CHUNKS_NAME);
+        let file = match std::fs::File::open(path) {
+            Ok(file) => file,
+            Err(_) => return Ok(Vec::new()),
+        };
+        let mut entries = Vec::new();
+        for line in std::io::BufReader::new(file).lines() {
+            let line = line.map_err(|e| FileError::FileReadError(e.to_string()))?;
+            if line.trim().is_empty() {
+                continue
+            }
+            let entry = serde_json::from_str(&line)
+                .map_err(|e| FileError::FileReadError(format!("invalid failed chunk: {}", e)))?;
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+}
@@ -1,5 +1,7 @@
 use polars::prelude::*;
 
+use crate::types::{Lineage, Signer};
+
 /// Options for file output
 #[derive(Clone)]
 pub struct FileOutput {
@@ -19,6 +21,116 @@ pub struct FileOutput {
     pub parquet_statistics: bool,
     /// Parquet compression options
     pub parquet_compression: ParquetCompression,
+    /// Dictionary encode hex address and topic columns in parquet files, changing their type
+    /// from string to dictionary<int32, string>
+    pub parquet_dictionary_encoding: bool,
+    /// Abort after writing this many rows
+    pub max_output_rows: Option<u64>,
+    /// Abort after writing this many bytes
+    pub max_output_bytes: Option<u64>,
+    /// Prompt whether to continue when an output limit is exceeded, instead of aborting
+    pub prompt_on_output_limit: bool,
+    /// Prompt whether to retry, skip, abort, or switch provider when a chunk fails
+    pub prompt_on_failure: bool,
+    /// Pause writing while free disk space in output_dir is below this many bytes
+    pub min_free_space: Option<u64>,
+    /// Print notes of the run above the progress bar, e.g. failed chunks and disk space warnings
+    pub verbose: bool,
+    /// Split each chunk's output into multiple files
+    pub partition_by: Option<PartitionBy>,
+    /// Provenance recorded in the manifest with each committed chunk
+    pub lineage: Option<Lineage>,
+    /// Sign the manifest after the run, covering the checksums of all committed files
+    pub signer: Option<Signer>,
+    /// Write decimal128 columns as arrow decimals with this scale, instead of integer strings
+    pub decimal_scale: Option<u32>,
+    /// Write traces as one row per transaction with a list of call structs
+    pub nested_traces: bool,
+    /// Write byte-identical files for identical inputs, with stable row order and fixed times
+    pub deterministic: bool,
+    /// Write a summary of each epoch of this many blocks to output_dir/rollups
+    pub rollup_epoch: Option<u64>,
+    /// Dialect of csv output files
+    pub csv: CsvDialect,
+    /// Fail chunks with more rows than this instead of writing large xlsx files
+    pub xlsx_max_rows: usize,
+    /// Collect chunks but only record their row counts, writing no files
+    pub count_only: bool,
+    /// Store values that a database sink cannot hold exactly with a lossy conversion, instead
+    /// of failing their chunk
+    pub allow_lossy: bool,
+    /// Write a json sidecar of row counts, null counts, ranges, and distinct estimates of each
+    /// file to output_dir/stats
+    pub stats_sidecars: bool,
+}
+
+impl FileOutput {
+    /// extension of output files, including the compression of gzipped csv files
+    pub fn extension(&self) -> &'static str {
+        match self.format {
+            FileFormat::Csv if self.csv.gzip => "csv.gz",
+            _ => self.format.as_str(),
+        }
+    }
+}
+
+/// Largest number of data rows of an xlsx worksheet, below its header row
+pub const XLSX_MAX_ROWS: usize = 1_048_575;
+
+/// Dialect of csv output files
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CsvDialect {
+    /// Byte separating fields
+    pub delimiter: u8,
+    /// When to quote fields
+    pub quote_style: CsvQuoteStyle,
+    /// Write a header row of column names
+    pub header: bool,
+    /// End rows with CRLF as required by RFC 4180, instead of LF
+    pub rfc4180: bool,
+    /// Compress files with gzip
+    pub gzip: bool,
+}
+
+impl Default for CsvDialect {
+    fn default() -> CsvDialect {
+        CsvDialect {
+            delimiter: b',',
+            quote_style: CsvQuoteStyle::Necessary,
+            header: true,
+            rfc4180: false,
+            gzip: false,
+        }
+    }
+}
+
+/// When to quote fields of csv output files
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CsvQuoteStyle {
+    /// Quote fields containing the delimiter, quotes, or line breaks
+    Necessary,
+    /// Quote every field
+    Always,
+}
+
+impl CsvQuoteStyle {
+    /// convert CsvQuoteStyle to str
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            CsvQuoteStyle::Necessary => "necessary",
+            CsvQuoteStyle::Always => "always",
+        }
+    }
+}
+
+/// Partitioning of a chunk's output into multiple files
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PartitionBy {
+    /// One file per contract address, for logs and traces
+    Contract {
+        /// contracts with the most rows get their own file, the rest share an "other" file
+        max_contracts: usize,
+    },
 }
 
 /// File format
@@ -30,6 +142,12 @@ pub enum FileFormat {
     Csv,
     /// Json file format
     Json,
+    /// Excel file format, for small extracts
+    #[cfg(feature = "xlsx")]
+    Xlsx,
+    /// Sqlite database, one per output directory with one table per dataset
+    #[cfg(feature = "sqlite")]
+    Sqlite,
 }
 
 impl FileFormat {
@@ -39,6 +157,10 @@ impl FileFormat {
             FileFormat::Parquet => "parquet",
             FileFormat::Csv => "csv",
             FileFormat::Json => "json",
+            #[cfg(feature = "xlsx")]
+            FileFormat::Xlsx => "xlsx",
+            #[cfg(feature = "sqlite")]
+            FileFormat::Sqlite => "sqlite",
         }
     }
 }
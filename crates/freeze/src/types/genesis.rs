@@ -0,0 +1,105 @@
+use std::collections::BTreeMap;
+
+use ethers::prelude::*;
+use serde_json::Value;
+
+use crate::types::{decode_hex, CollectError, ParseError};
+
+/// account of the genesis allocation
+#[derive(Debug, Clone)]
+pub struct GenesisAccount {
+    /// address of account
+    pub address: Vec<u8>,
+    /// balance of account in wei
+    pub balance: U256,
+    /// nonce of account
+    pub nonce: u64,
+    /// code of account, empty for externally owned accounts
+    pub code: Vec<u8>,
+    /// number of storage slots set at genesis
+    pub n_storage_slots: u64,
+}
+
+/// accounts allocated at block 0, read from a genesis.json or a state dump of block 0
+#[derive(Debug, Clone, Default)]
+pub struct GenesisAlloc {
+    /// accounts sorted by address
+    pub accounts: Vec<GenesisAccount>,
+}
+
+impl GenesisAlloc {
+    /// read the alloc of a genesis.json file, as used by geth init
+    pub fn open(path: &str) -> Result<GenesisAlloc, ParseError> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|_e| ParseError::ParseError(format!("could not read {}", path)))?;
+        let genesis: Value = serde_json::from_str(&contents)
+            .map_err(|e| ParseError::ParseError(format!("invalid genesis file {}: {}", path, e)))?;
+        let alloc = genesis
+            .get("alloc")
+            .ok_or_else(|| ParseError::ParseError(format!("no alloc in genesis file {}", path)))?;
+        GenesisAlloc::from_accounts(alloc).map_err(ParseError::ParseError)
+    }
+
+    /// read the accounts of a debug_dumpBlock response
+    pub fn from_dump(dump: &Value) -> Result<GenesisAlloc, CollectError> {
+        let accounts = dump.get("accounts").ok_or_else(|| {
+            CollectError::CollectError("no accounts in debug_dumpBlock response".to_string())
+        })?;
+        GenesisAlloc::from_accounts(accounts).map_err(CollectError::CollectError)
+    }
+
+    /// parse a map of address to account, with hex or decimal numbers
+    fn from_accounts(accounts: &Value) -> Result<GenesisAlloc, String> {
+        let accounts = accounts.as_object().ok_or("accounts must be a json object")?;
+        let mut sorted = BTreeMap::new();
+        for (key, account) in accounts.iter() {
+            // state dumps of newer geth versions key accounts by address or by hashed address
+            let address = match account.get("address").and_then(Value::as_str) {
+                Some(address) => address,
+                None => key.as_str(),
+            };
+            let address = decode_hex(address)
+                .filter(|address| address.len() == 20)
+                .ok_or_else(|| format!("invalid genesis address: {}", address))?;
+            let balance = match account.get("balance") {
+                Some(balance) => parse_u256(balance)
+                    .ok_or_else(|| format!("invalid genesis balance: {}", balance))?,
+                None => U256::zero(),
+            };
+            let nonce = match account.get("nonce") {
+                Some(nonce) => parse_u256(nonce)
+                    .filter(|nonce| *nonce <= U256::from(u64::MAX))
+                    .ok_or_else(|| format!("invalid genesis nonce: {}", nonce))?
+                    .as_u64(),
+                None => 0,
+            };
+            let code = match account.get("code").and_then(Value::as_str) {
+                Some(code) => {
+                    decode_hex(code).ok_or_else(|| format!("invalid genesis code: {}", code))?
+                }
+                None => Vec::new(),
+            };
+            let n_storage_slots = account
+                .get("storage")
+                .and_then(Value::as_object)
+                .map(|storage| storage.len() as u64)
+                .unwrap_or_default();
+            let account = GenesisAccount { address, balance, nonce, code, n_storage_slots };
+            sorted.insert(account.address.clone(), account);
+        }
+        Ok(GenesisAlloc { accounts: sorted.into_values().collect() })
+    }
+}
+
+/// parse a json number, a decimal string, or a 0x-prefixed hex string
+fn parse_u256(value: &Value) -> Option<U256> {
+    match value {
+        Value::Number(number) => number.as_u64().map(U256::from),
+        Value::String(string) => match string.strip_prefix("0x") {
+            Some("") => Some(U256::zero()),
+            Some(hex) => U256::from_str_radix(hex, 16).ok(),
+            None => U256::from_dec_str(string).ok(),
+        },
+        _ => None,
+    }
+}
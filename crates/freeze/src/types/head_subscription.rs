@@ -0,0 +1,74 @@
+use std::time::Duration;
+
+use ethers::providers::{Middleware, Provider, Ws};
+use futures::StreamExt;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::types::CollectError;
+
+/// chain heads pushed by an eth_subscribe("newHeads") subscription over websockets
+///
+/// heads arrive as soon as the node sees them, so following does not wait for the next poll and
+/// does not request the block number on every poll. the subscription ends when the websocket
+/// closes, after which callers fall back to polling
+pub struct HeadSubscription {
+    heads: mpsc::Receiver<u64>,
+    latest: Option<u64>,
+}
+
+impl HeadSubscription {
+    /// connect to a websocket rpc and subscribe to new heads
+    pub async fn connect(url: &str) -> Result<HeadSubscription, CollectError> {
+        let provider = Provider::<Ws>::connect(url).await.map_err(|e| {
+            CollectError::CollectError(format!("could not connect to {}: {}", url, e))
+        })?;
+
+        // the subscription stream borrows its provider, so both are kept by one task
+        let (heads_tx, heads_rx) = mpsc::channel(64);
+        let (subscribed_tx, subscribed_rx) = oneshot::channel();
+        tokio::spawn(async move {
+            let mut stream = match provider.subscribe_blocks().await {
+                Ok(stream) => {
+                    let _ = subscribed_tx.send(Ok(()));
+                    stream
+                }
+                Err(e) => {
+                    let _ = subscribed_tx.send(Err(e.to_string()));
+                    return
+                }
+            };
+            while let Some(block) = stream.next().await {
+                if let Some(number) = block.number {
+                    if heads_tx.send(number.as_u64()).await.is_err() {
+                        break
+                    }
+                }
+            }
+        });
+        match subscribed_rx.await {
+            Ok(Ok(())) => Ok(HeadSubscription { heads: heads_rx, latest: None }),
+            Ok(Err(e)) => Err(CollectError::CollectError(format!("could not subscribe: {}", e))),
+            Err(_) => Err(CollectError::CollectError("subscription task failed".to_string())),
+        }
+    }
+
+    /// highest head received so far, none before the first head arrives
+    pub fn latest(&self) -> Option<u64> {
+        self.latest
+    }
+
+    /// wait up to a timeout for new heads, returning false once the subscription has ended
+    pub async fn wait(&mut self, timeout: Duration) -> bool {
+        match tokio::time::timeout(timeout, self.heads.recv()).await {
+            Ok(Some(head)) => {
+                self.latest = self.latest.max(Some(head));
+                while let Ok(head) = self.heads.try_recv() {
+                    self.latest = self.latest.max(Some(head));
+                }
+                true
+            }
+            Ok(None) => false,
+            Err(_) => true,
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use serde_json::{Map, Value};
+
+/// bytes of a logsBloom
+const BLOOM_LEN: usize = 256;
+
+/// fields holding integer quantities, for which an empty `0x` means zero
+const QUANTITY_FIELDS: [&str; 24] = [
+    "baseFeePerGas",
+    "blockNumber",
+    "chainId",
+    "cumulativeGasUsed",
+    "difficulty",
+    "effectiveGasPrice",
+    "gas",
+    "gasLimit",
+    "gasPrice",
+    "gasUsed",
+    "logIndex",
+    "maxFeePerGas",
+    "maxPriorityFeePerGas",
+    "number",
+    "r",
+    "s",
+    "size",
+    "status",
+    "timestamp",
+    "totalDifficulty",
+    "transactionIndex",
+    "type",
+    "v",
+    "value",
+];
+
+/// rewrite a response of a nonstandard evm node into the shape ethers expects
+///
+/// applies to block, transaction, receipt, and log responses, whose quantities are all hex
+/// strings in the standard. fixes `0X` prefixes, empty `0x` quantities, and integers given as json
+/// numbers, fills null or missing logsBloom with an empty bloom, and fills missing signature and
+/// uncle fields with zeros. other methods, e.g. traces with json number fields, are left as is
+pub fn normalize_response(method: &str, value: &mut Value) {
+    match method {
+        "eth_getBlockByNumber" | "eth_getBlockByHash" => {
+            if let Value::Object(block) = value {
+                normalize_block(block)
+            }
+        }
+        "eth_getTransactionByHash" => {
+            if let Value::Object(tx) = value {
+                normalize_transaction(tx)
+            }
+        }
+        "eth_getTransactionReceipt" => {
+            if let Value::Object(receipt) = value {
+                normalize_receipt(receipt)
+            }
+        }
+        "eth_getBlockReceipts" | "eth_getLogs" => {
+            if let Value::Array(items) = value {
+                for item in items.iter_mut() {
+                    if let Value::Object(item) = item {
+                        match method {
+                            "eth_getLogs" => normalize_object(item),
+                            _ => normalize_receipt(item),
+                        }
+                    }
+                }
+            }
+        }
+        "eth_blockNumber" | "eth_chainId" | "eth_getBalance" | "eth_getTransactionCount" => {
+            normalize_value(value, true)
+        }
+        _ => {}
+    }
+}
+
+fn normalize_block(block: &mut Map<String, Value>) {
+    normalize_object(block);
+    fill_missing(block, "sha3Uncles", &format!("0x{}", "0".repeat(64)));
+    fill_missing(block, "difficulty", "0x0");
+    fill_missing(block, "extraData", "0x");
+    if let Some(Value::Null) = block.get("logsBloom") {
+        block.remove("logsBloom");
+    }
+    if let Some(Value::Array(transactions)) = block.get_mut("transactions") {
+        for tx in transactions.iter_mut() {
+            if let Value::Object(tx) = tx {
+                normalize_transaction(tx)
+            }
+        }
+    }
+}
+
+fn normalize_transaction(tx: &mut Map<String, Value>) {
+    normalize_object(tx);
+    if let Some(nonce) = tx.get_mut("nonce") {
+        normalize_value(nonce, true)
+    }
+    for key in ["v", "r", "s"] {
+        fill_missing(tx, key, "0x0");
+    }
+}
+
+fn normalize_receipt(receipt: &mut Map<String, Value>) {
+    normalize_object(receipt);
+    fill_missing(receipt, "logsBloom", &format!("0x{}", "0".repeat(BLOOM_LEN * 2)));
+    if let Some(Value::Array(logs)) = receipt.get_mut("logs") {
+        for log in logs.iter_mut() {
+            if let Value::Object(log) = log {
+                normalize_object(log)
+            }
+        }
+    }
+}
+
+/// normalize every scalar field of an object, nested objects and arrays are handled by callers
+fn normalize_object(object: &mut Map<String, Value>) {
+    for (key, value) in object.iter_mut() {
+        let is_quantity = QUANTITY_FIELDS.contains(&key.as_str());
+        match value {
+            Value::Array(items) => items.iter_mut().for_each(|item| normalize_value(item, false)),
+            value => normalize_value(value, is_quantity),
+        }
+    }
+}
+
+/// empty `0x` is valid for byte fields, so only quantities have it replaced with zero
+fn normalize_value(value: &mut Value, is_quantity: bool) {
+    match value {
+        Value::Number(number) => {
+            if let Some(number) = number.as_u64() {
+                *value = Value::String(format!("0x{:x}", number))
+            }
+        }
+        Value::String(s) => {
+            if let Some(digits) = s.strip_prefix("0X") {
+                *s = format!("0x{}", digits);
+            }
+            if is_quantity && s.as_str() == "0x" {
+                *s = "0x0".to_string();
+            }
+        }
+        _ => {}
+    }
+}
+
+/// insert a default value for a field that is missing or null
+fn fill_missing(object: &mut Map<String, Value>, key: &str, default: &str) {
+    match object.get(key) {
+        None | Some(Value::Null) => {
+            object.insert(key.to_string(), Value::String(default.to_string()));
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,472 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Condvar, Mutex,
+    },
+};
+
+use ethers::prelude::*;
+use polars::{
+    export::arrow::{
+        array::{Array, BinaryArray, PrimitiveArray, Utf8Array},
+        io::parquet::read as parquet_read,
+    },
+    prelude::*,
+};
+
+use crate::types::{decode_hex, BlockChunk, ChunkData, CollectError, ParseError, RowFilter};
+
+/// columns of logs files needed to rebuild logs
+const LOG_COLUMNS: [&str; 10] = [
+    "block_number",
+    "transaction_index",
+    "log_index",
+    "transaction_hash",
+    "contract_address",
+    "topic0",
+    "topic1",
+    "topic2",
+    "topic3",
+    "data",
+];
+
+/// logs read from the parquet files of an earlier logs collection instead of rpc
+///
+/// reads are pushed down to parquet: files outside a chunk are skipped by name, row groups
+/// are skipped by the block_number, contract_address, and topic statistics of their footer when
+/// they cannot hold logs of the chunk and filter, and only log columns are read, so deriving
+/// datasets from a large logs collection reads a small part of it. files of a chunk are read by
+/// parallel threads, within a cap on the bytes of files being read at once across chunks.
+/// bloom filters are not used, since the parquet writer of cryo does not write them
+#[derive(Debug)]
+pub struct LocalLogs {
+    /// logs files with the block range in their name, if any
+    files: Vec<(PathBuf, Option<(u64, u64)>)>,
+    /// threads reading the files of each chunk
+    threads: usize,
+    /// on-disk bytes of files being read at once
+    memory: Option<MemoryBudget>,
+    /// files and row groups read or skipped by statistics
+    pruning: PruningCounters,
+}
+
+/// files and row groups skipped by statistics, across the chunks of a run
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LocalPruning {
+    /// files whose statistics were checked
+    pub n_files: u64,
+    /// files skipped because none of their row groups can match
+    pub n_files_pruned: u64,
+    /// row groups whose statistics were checked
+    pub n_row_groups: u64,
+    /// row groups skipped because their statistics exclude the chunk or filter
+    pub n_row_groups_pruned: u64,
+}
+
+#[derive(Debug, Default)]
+struct PruningCounters {
+    n_files: AtomicU64,
+    n_files_pruned: AtomicU64,
+    n_row_groups: AtomicU64,
+    n_row_groups_pruned: AtomicU64,
+}
+
+impl LocalLogs {
+    /// index the logs parquet files of a directory
+    pub fn open(dir: &str) -> Result<LocalLogs, ParseError> {
+        let read_dir = std::fs::read_dir(dir)
+            .map_err(|_e| ParseError::ParseError(format!("could not read directory {}", dir)))?;
+        let mut files = Vec::new();
+        for entry in read_dir.flatten() {
+            let path = entry.path();
+            let filename = path.file_name().map(|name| name.to_string_lossy().to_string());
+            let stem = match filename.as_deref().and_then(|f| f.strip_suffix(".parquet")) {
+                Some(stem) => stem.to_string(),
+                None => continue,
+            };
+            let pieces: Vec<&str> = stem.split("__").collect();
+            if pieces.len() < 3 || pieces[1] != "logs" {
+                continue
+            }
+            let range = pieces[2..].iter().find_map(|piece| {
+                let (start, end) = piece.split_once("_to_")?;
+                Some((start.parse().ok()?, end.parse().ok()?))
+            });
+            files.push((path, range));
+        }
+        if files.is_empty() {
+            return Err(ParseError::ParseError(format!("no parquet logs files in {}", dir)))
+        }
+        files.sort();
+        let threads = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+        Ok(LocalLogs { files, threads, memory: None, pruning: PruningCounters::default() })
+    }
+
+    /// read the files of each chunk with this many threads
+    pub fn with_threads(mut self, threads: usize) -> LocalLogs {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// limit the on-disk bytes of files being read at once, a larger file is read alone
+    pub fn with_max_memory(mut self, max_bytes: u64) -> LocalLogs {
+        self.memory = Some(MemoryBudget::new(max_bytes));
+        self
+    }
+
+    /// files and row groups skipped by statistics so far
+    pub fn pruning(&self) -> LocalPruning {
+        LocalPruning {
+            n_files: self.pruning.n_files.load(Ordering::Relaxed),
+            n_files_pruned: self.pruning.n_files_pruned.load(Ordering::Relaxed),
+            n_row_groups: self.pruning.n_row_groups.load(Ordering::Relaxed),
+            n_row_groups_pruned: self.pruning.n_row_groups_pruned.load(Ordering::Relaxed),
+        }
+    }
+
+    /// read logs of a block chunk that match a filter
+    pub(crate) fn read(
+        &self,
+        block_chunk: &BlockChunk,
+        filter: Option<&RowFilter>,
+    ) -> Result<Vec<Log>, CollectError> {
+        let (start, end) = match (block_chunk.min_value(), block_chunk.max_value()) {
+            (Some(start), Some(end)) => (start, end),
+            _ => return Ok(vec![]),
+        };
+        let numbers: Option<HashSet<u64>> = match block_chunk {
+            BlockChunk::Numbers(numbers) => Some(numbers.iter().copied().collect()),
+            BlockChunk::Range(_, _) => None,
+        };
+        let files: Vec<&PathBuf> = self
+            .files
+            .iter()
+            .filter(|(_, range)| match range {
+                Some((file_start, file_end)) => *file_end >= start && *file_start <= end,
+                None => true,
+            })
+            .map(|(path, _)| path)
+            .collect();
+
+        let keep = |log: &Log| {
+            let number = log.block_number.map(|n| n.as_u64()).unwrap_or_default();
+            number >= start &&
+                number <= end &&
+                numbers.as_ref().map(|n| n.contains(&number)).unwrap_or(true) &&
+                filter.map(|f| matches_filter(log, f)).unwrap_or(true)
+        };
+
+        // threads take files in order, results are reassembled in file order
+        let next = AtomicUsize::new(0);
+        let results = Mutex::new(Vec::with_capacity(files.len()));
+        std::thread::scope(|scope| {
+            for _ in 0..self.threads.min(files.len()) {
+                scope.spawn(|| loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let path = match files.get(i) {
+                        Some(path) => path,
+                        None => break,
+                    };
+                    let n_bytes = std::fs::metadata(path).map(|m| m.len()).unwrap_or_default();
+                    let _permit = self.memory.as_ref().map(|memory| memory.acquire(n_bytes));
+                    let result = read_file(path, start, end, filter, &self.pruning)
+                        .map(|logs| logs.into_iter().filter(|log| keep(log)).collect::<Vec<_>>());
+                    results.lock().expect("results lock").push((i, result));
+                });
+            }
+        });
+        let mut results = results.into_inner().expect("results lock");
+        results.sort_by_key(|(i, _)| *i);
+        let mut logs = Vec::new();
+        for (_, result) in results.into_iter() {
+            logs.extend(result?);
+        }
+        Ok(logs)
+    }
+}
+
+/// cap on the bytes of files being read at once, shared by all chunks
+#[derive(Debug)]
+struct MemoryBudget {
+    max_bytes: u64,
+    available: Mutex<u64>,
+    released: Condvar,
+}
+
+/// bytes taken from a memory budget, returned when dropped
+struct MemoryPermit<'a> {
+    budget: &'a MemoryBudget,
+    n_bytes: u64,
+}
+
+impl MemoryBudget {
+    fn new(max_bytes: u64) -> MemoryBudget {
+        MemoryBudget { max_bytes, available: Mutex::new(max_bytes), released: Condvar::new() }
+    }
+
+    /// wait until n_bytes are available, requests above the cap wait for the whole budget
+    fn acquire(&self, n_bytes: u64) -> MemoryPermit<'_> {
+        let n_bytes = n_bytes.min(self.max_bytes);
+        let mut available = self.available.lock().expect("memory budget lock");
+        while *available < n_bytes {
+            available = self.released.wait(available).expect("memory budget lock");
+        }
+        *available -= n_bytes;
+        MemoryPermit { budget: self, n_bytes }
+    }
+}
+
+impl Drop for MemoryPermit<'_> {
+    fn drop(&mut self) {
+        let mut available = self.budget.available.lock().expect("memory budget lock");
+        *available += self.n_bytes;
+        self.budget.released.notify_all();
+    }
+}
+
+/// read logs of a block range from a parquet file, reading only the log columns of row groups
+/// whose statistics allow logs of the range and filter
+fn read_file(
+    path: &Path,
+    start: u64,
+    end: u64,
+    filter: Option<&RowFilter>,
+    pruning: &PruningCounters,
+) -> Result<Vec<Log>, CollectError> {
+    let file_error =
+        |e: String| CollectError::CollectError(format!("could not read {}: {}", path.display(), e));
+    let mut file = std::fs::File::open(path)
+        .map_err(|e| CollectError::CollectError(format!("could not open {:?}: {}", path, e)))?;
+    let metadata = parquet_read::read_metadata(&mut file).map_err(|e| file_error(e.to_string()))?;
+    let schema = parquet_read::infer_schema(&metadata).map_err(|e| file_error(e.to_string()))?;
+    for column in ["block_number", "contract_address", "topic0"] {
+        if !schema.fields.iter().any(|field| field.name == column) {
+            return Err(CollectError::CollectError(format!(
+                "{} lacks column {}, collect logs with it to derive datasets",
+                path.display(),
+                column
+            )))
+        }
+    }
+
+    // skip row groups whose statistics exclude the block range or the filter
+    let mut bounds = vec![RowGroupBounds::Integer(start, end)];
+    if let Some(filter) = filter {
+        if let Some(values) = filter.address.as_ref().map(address_values) {
+            bounds.push(RowGroupBounds::Bytes("contract_address", values));
+        }
+        for (i, topic) in filter.topics.iter().enumerate() {
+            if let Some(values) = topic.as_ref().and_then(topic_values) {
+                bounds.push(RowGroupBounds::Bytes(TOPIC_COLUMNS[i], values));
+            }
+        }
+    }
+    let n_row_groups = metadata.row_groups.len();
+    let mut keep = vec![true; n_row_groups];
+    for bound in bounds.iter() {
+        let field = match schema.fields.iter().find(|field| field.name == bound.column()) {
+            Some(field) => field,
+            None => continue,
+        };
+        let statistics = match parquet_read::statistics::deserialize(field, &metadata.row_groups) {
+            Ok(statistics) => statistics,
+            Err(_) => continue,
+        };
+        for (i, keep) in keep.iter_mut().enumerate() {
+            *keep = *keep &&
+                bound.may_match(statistics.min_value.as_ref(), statistics.max_value.as_ref(), i);
+        }
+    }
+    let row_groups: Vec<_> = metadata
+        .row_groups
+        .into_iter()
+        .zip(keep)
+        .filter_map(|(row_group, keep)| keep.then_some(row_group))
+        .collect();
+    pruning.n_files.fetch_add(1, Ordering::Relaxed);
+    pruning.n_row_groups.fetch_add(n_row_groups as u64, Ordering::Relaxed);
+    pruning
+        .n_row_groups_pruned
+        .fetch_add((n_row_groups - row_groups.len()) as u64, Ordering::Relaxed);
+    if row_groups.is_empty() {
+        pruning.n_files_pruned.fetch_add(1, Ordering::Relaxed);
+        return Ok(Vec::new())
+    }
+
+    let schema = schema.filter(|_, field| LOG_COLUMNS.contains(&field.name.as_str()));
+    let reader = parquet_read::FileReader::new(file, row_groups, schema.clone(), None, None, None);
+    let mut logs = Vec::new();
+    for chunk in reader {
+        let chunk = chunk.map_err(|e| file_error(e.to_string()))?;
+        let df = DataFrame::try_from((chunk, schema.fields.as_slice()))
+            .map_err(|e| file_error(e.to_string()))?;
+        logs.extend(dataframe_to_logs(&df).map_err(|e| file_error(e.to_string()))?);
+    }
+    Ok(logs)
+}
+
+const TOPIC_COLUMNS: [&str; 4] = ["topic0", "topic1", "topic2", "topic3"];
+
+/// values a row group must be able to hold to be read
+enum RowGroupBounds {
+    /// block numbers within an inclusive range
+    Integer(u64, u64),
+    /// any of these values of a binary or hex column
+    Bytes(&'static str, Vec<Vec<u8>>),
+}
+
+impl RowGroupBounds {
+    fn column(&self) -> &'static str {
+        match self {
+            RowGroupBounds::Integer(..) => "block_number",
+            RowGroupBounds::Bytes(column, _) => column,
+        }
+    }
+
+    /// whether a row group can hold a value, true when its statistics are missing
+    fn may_match(&self, min: &dyn Array, max: &dyn Array, row_group: usize) -> bool {
+        match self {
+            RowGroupBounds::Integer(start, end) => {
+                match (integer_bound(min, row_group), integer_bound(max, row_group)) {
+                    (Some(min), Some(max)) => min <= *end && max >= *start,
+                    _ => true,
+                }
+            }
+            RowGroupBounds::Bytes(_, values) => {
+                match (bytes_bound(min, row_group), bytes_bound(max, row_group)) {
+                    (Some(min), Some(max)) => {
+                        values.iter().any(|value| *value >= min && *value <= max)
+                    }
+                    _ => true,
+                }
+            }
+        }
+    }
+}
+
+/// addresses allowed by an address filter
+fn address_values(address: &ValueOrArray<H160>) -> Vec<Vec<u8>> {
+    match address {
+        ValueOrArray::Value(address) => vec![address.as_bytes().to_vec()],
+        ValueOrArray::Array(addresses) => {
+            addresses.iter().map(|address| address.as_bytes().to_vec()).collect()
+        }
+    }
+}
+
+/// values allowed by a topic filter, none if it allows any value
+fn topic_values(topic: &ValueOrArray<Option<H256>>) -> Option<Vec<Vec<u8>>> {
+    match topic {
+        ValueOrArray::Value(value) => value.map(|value| vec![value.as_bytes().to_vec()]),
+        ValueOrArray::Array(values) => {
+            values.iter().map(|value| value.map(|value| value.as_bytes().to_vec())).collect()
+        }
+    }
+}
+
+/// statistic of a row group of an integer column
+fn integer_bound(array: &dyn Array, row_group: usize) -> Option<u64> {
+    if array.is_null(row_group) {
+        return None
+    }
+    let any = array.as_any();
+    if let Some(array) = any.downcast_ref::<PrimitiveArray<u32>>() {
+        return Some(array.value(row_group) as u64)
+    }
+    if let Some(array) = any.downcast_ref::<PrimitiveArray<u64>>() {
+        return Some(array.value(row_group))
+    }
+    None
+}
+
+/// statistic of a row group of a binary column, or of a hex column as bytes
+fn bytes_bound(array: &dyn Array, row_group: usize) -> Option<Vec<u8>> {
+    if array.is_null(row_group) {
+        return None
+    }
+    let any = array.as_any();
+    if let Some(array) = any.downcast_ref::<BinaryArray<i64>>() {
+        return Some(array.value(row_group).to_vec())
+    }
+    if let Some(array) = any.downcast_ref::<BinaryArray<i32>>() {
+        return Some(array.value(row_group).to_vec())
+    }
+    // lowercase hex strings of equal length sort as their bytes do
+    if let Some(array) = any.downcast_ref::<Utf8Array<i64>>() {
+        return decode_hex(&array.value(row_group).to_lowercase())
+    }
+    if let Some(array) = any.downcast_ref::<Utf8Array<i32>>() {
+        return decode_hex(&array.value(row_group).to_lowercase())
+    }
+    None
+}
+
+fn dataframe_to_logs(df: &DataFrame) -> PolarsResult<Vec<Log>> {
+    let block_number = integer_column(df, "block_number")?;
+    let transaction_index = integer_column(df, "transaction_index")?;
+    let log_index = integer_column(df, "log_index")?;
+    let transaction_hash = bytes_column(df, "transaction_hash")?;
+    let address = bytes_column(df, "contract_address")?;
+    let topics: Vec<Vec<Option<Vec<u8>>>> = ["topic0", "topic1", "topic2", "topic3"]
+        .iter()
+        .map(|name| bytes_column(df, name))
+        .collect::<PolarsResult<_>>()?;
+    let data = bytes_column(df, "data")?;
+
+    let mut logs = Vec::with_capacity(df.height());
+    for i in 0..df.height() {
+        let log = Log {
+            address: address[i].as_deref().map(H160::from_slice).unwrap_or_default(),
+            topics: topics
+                .iter()
+                .map_while(|topic| topic[i].as_deref().map(H256::from_slice))
+                .collect(),
+            data: data[i].clone().unwrap_or_default().into(),
+            block_number: block_number[i].map(U64::from),
+            transaction_hash: transaction_hash[i].as_deref().map(H256::from_slice),
+            transaction_index: transaction_index[i].map(U64::from),
+            log_index: log_index[i].map(U256::from),
+            removed: Some(false),
+            ..Default::default()
+        };
+        logs.push(log);
+    }
+    Ok(logs)
+}
+
+/// values of an integer column, all null if the column was not collected
+fn integer_column(df: &DataFrame, name: &str) -> PolarsResult<Vec<Option<u64>>> {
+    match df.column(name) {
+        Ok(series) => Ok(series.cast(&DataType::UInt64)?.u64()?.into_iter().collect()),
+        Err(_) => Ok(vec![None; df.height()]),
+    }
+}
+
+/// values of a binary or hex column, all null if the column was not collected
+fn bytes_column(df: &DataFrame, name: &str) -> PolarsResult<Vec<Option<Vec<u8>>>> {
+    let series = match df.column(name) {
+        Ok(series) => series,
+        Err(_) => return Ok(vec![None; df.height()]),
+    };
+    match series.dtype() {
+        DataType::Binary => {
+            Ok(series.binary()?.into_iter().map(|value| value.map(|v| v.to_vec())).collect())
+        }
+        _ => Ok(series.utf8()?.into_iter().map(|value| value.and_then(decode_hex)).collect()),
+    }
+}
+
+/// whether a log matches the address and topics of a filter, as eth_getLogs would
+pub(crate) fn matches_filter(log: &Log, filter: &RowFilter) -> bool {
+    filter.includes_address(&log.address) &&
+        filter.topics.iter().enumerate().all(|(i, topic)| match topic {
+            None => true,
+            Some(ValueOrArray::Value(None)) => true,
+            Some(ValueOrArray::Value(Some(value))) => log.topics.get(i) == Some(value),
+            Some(ValueOrArray::Array(values)) => {
+                values.iter().any(|value| value.is_none() || log.topics.get(i) == value.as_ref())
+            }
+        })
+}
@@ -0,0 +1,81 @@
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use fs2::FileExt;
+
+use crate::types::FreezeError;
+
+/// name of lockfile placed in output directory
+pub const LOCKFILE_NAME: &str = ".cryo.lock";
+
+/// advisory lock on an output directory, released when dropped
+///
+/// prevents concurrent cryo runs from writing interleaved files into the same directory. the
+/// lockfile is locked with an os file lock, so the os releases it when the run exits, including
+/// when it is interrupted or crashes. the lockfile itself is left in place and only records the
+/// current holder
+#[derive(Debug)]
+pub struct OutputLock {
+    file: File,
+}
+
+impl OutputLock {
+    /// acquire lock on output directory, failing if another live run holds it
+    pub fn acquire(output_dir: &str) -> Result<OutputLock, FreezeError> {
+        let path = PathBuf::from(output_dir).join(LOCKFILE_NAME);
+        let mut file = open_lockfile(&path).map_err(|e| {
+            FreezeError::OutputLocked(format!(
+                "could not create lockfile {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        if file.try_lock_exclusive().is_err() {
+            return Err(FreezeError::OutputLocked(format!(
+                "{} is locked by another cryo run ({}). wait for it to finish or stop it, or \
+                write to a different output directory",
+                output_dir,
+                read_holder(&mut file)
+            )))
+        }
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let _ = file.set_len(0);
+        let _ = writeln!(file, "pid={} started={}", std::process::id(), timestamp);
+        Ok(OutputLock { file })
+    }
+}
+
+impl Drop for OutputLock {
+    fn drop(&mut self) {
+        let _ = self.file.set_len(0);
+        let _ = self.file.unlock();
+    }
+}
+
+/// holder of the lock on an output directory, if another run currently holds it
+pub fn lock_holder(output_dir: &str) -> Option<String> {
+    let mut file = File::open(Path::new(output_dir).join(LOCKFILE_NAME)).ok()?;
+    match file.try_lock_shared() {
+        Ok(()) => {
+            let _ = file.unlock();
+            None
+        }
+        Err(_) => Some(read_holder(&mut file)),
+    }
+}
+
+fn open_lockfile(path: &Path) -> std::io::Result<File> {
+    std::fs::OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path)
+}
+
+fn read_holder(file: &mut File) -> String {
+    let mut holder = String::new();
+    let _ = file.seek(SeekFrom::Start(0)).and_then(|_| file.read_to_string(&mut holder));
+    holder.trim().to_string()
+}
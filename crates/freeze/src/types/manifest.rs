@@ -0,0 +1,242 @@
+use std::{
+    collections::HashSet,
+    io::{BufRead, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::FileError;
+
+/// name of manifest file placed in output directory
+pub const MANIFEST_NAME: &str = "cryo_manifest.jsonl";
+
+/// name of directory where chunk files are staged before being committed
+pub const STAGING_DIR_NAME: &str = ".cryo_staging";
+
+/// record of a chunk's files being committed to the output directory
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ManifestEntry {
+    /// whether files are about to be moved into place or have all been moved into place
+    pub status: CommitStatus,
+    /// block or transaction range of chunk
+    pub chunk: String,
+    /// datasets collected for chunk, including datasets whose chunk produced no files
+    #[serde(default)]
+    pub datasets: Vec<String>,
+    /// files committed for chunk, one per dataset
+    pub files: Vec<ManifestFile>,
+    /// unix timestamp of entry
+    pub timestamp: u64,
+    /// provenance of the run that committed the chunk, absent for entries of older versions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lineage: Option<Lineage>,
+}
+
+/// provenance of a run, recorded with each chunk the run commits
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Lineage {
+    /// random id of the run
+    pub run_id: String,
+    /// ids of the runs that last wrote to the output directory before this run
+    #[serde(default)]
+    pub parent_run_ids: Vec<String>,
+    /// version of cryo that performed the run
+    pub cryo_version: String,
+    /// command line of the run, with rpc urls redacted
+    pub command: Vec<String>,
+    /// host of the rpc endpoint and a hash of its full url, so api keys are never stored
+    pub endpoint: String,
+    /// chain id reported by the rpc endpoint
+    pub chain_id: u64,
+}
+
+impl Lineage {
+    /// create lineage of a new run, whose parents are filled in when the output dir is opened
+    pub fn new(endpoint: String, chain_id: u64, command: Vec<String>) -> Lineage {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        let seed = format!("{}:{}:{}", nanos, std::process::id(), command.join(" "));
+        Lineage {
+            run_id: short_hash(seed.as_bytes(), 8),
+            parent_run_ids: vec![],
+            cryo_version: env!("CARGO_PKG_VERSION").to_string(),
+            command,
+            endpoint,
+            chain_id,
+        }
+    }
+}
+
+/// fingerprint of an rpc url, its host followed by a short hash of the full url
+pub fn endpoint_fingerprint(url: &str) -> String {
+    let without_scheme = url.split_once("://").map(|(_, rest)| rest).unwrap_or(url);
+    let authority = without_scheme.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = authority.rsplit('@').next().unwrap_or_default();
+    format!("{}#{}", host, short_hash(url.as_bytes(), 4))
+}
+
+/// unprefixed hex of the first n_bytes of the keccak hash of data
+fn short_hash(data: &[u8], n_bytes: usize) -> String {
+    let hash = ethers::utils::keccak256(data);
+    prefix_hex::encode(hash[..n_bytes].to_vec()).trim_start_matches("0x").to_string()
+}
+
+/// stage of a chunk commit
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CommitStatus {
+    /// files are staged and about to be moved into place
+    Pending,
+    /// all files are in place
+    Committed,
+}
+
+/// record of a single committed file
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ManifestFile {
+    /// name of dataset
+    pub dataset: String,
+    /// file name, relative to output directory
+    pub filename: String,
+    /// number of rows in file
+    pub n_rows: u64,
+    /// size of file in bytes
+    pub n_bytes: u64,
+    /// hex sha-256 checksum of file, absent for entries of older versions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+}
+
+/// append-only manifest of chunk commits, stored as json lines
+///
+/// a pending entry is appended before a chunk's files are moved into place and a committed
+/// entry after, so files of a pending entry without a committed entry belong to a chunk that
+/// was interrupted mid-commit. files never mentioned, e.g. written by older versions, are
+/// considered committed
+#[derive(Debug)]
+pub struct Manifest {
+    path: PathBuf,
+    incomplete: Mutex<HashSet<String>>,
+    committed_chunks: Mutex<HashSet<(String, String)>>,
+}
+
+impl Manifest {
+    /// open manifest of output directory, loading files of interrupted commits
+    pub fn open(output_dir: &str) -> Result<Manifest, FileError> {
+        let path = Path::new(output_dir).join(MANIFEST_NAME);
+        let mut incomplete = HashSet::new();
+        let mut committed_chunks = HashSet::new();
+        for entry in read_entries(&path)? {
+            if entry.status == CommitStatus::Committed {
+                for dataset in entry.datasets.iter() {
+                    committed_chunks.insert((dataset.clone(), entry.chunk.clone()));
+                }
+            }
+            for file in entry.files {
+                match entry.status {
+                    CommitStatus::Pending => incomplete.insert(file.filename),
+                    CommitStatus::Committed => incomplete.remove(&file.filename),
+                };
+            }
+        }
+        Ok(Manifest {
+            path,
+            incomplete: Mutex::new(incomplete),
+            committed_chunks: Mutex::new(committed_chunks),
+        })
+    }
+
+    /// remove files staged by an interrupted run
+    ///
+    /// must only be called while holding the output directory lock
+    pub fn clear_staging(output_dir: &str) {
+        let _ = std::fs::remove_dir_all(Path::new(output_dir).join(STAGING_DIR_NAME));
+    }
+
+    /// ids of the runs that committed the most recent chunks of the output directory
+    ///
+    /// a run is the parent of the next run writing to the same directory, like git commits
+    pub fn head_run_ids(&self) -> Result<Vec<String>, FileError> {
+        let entries = read_entries(&self.path)?;
+        let head = entries
+            .iter()
+            .rev()
+            .filter(|entry| entry.status == CommitStatus::Committed)
+            .find_map(|entry| entry.lineage.as_ref());
+        Ok(head.map(|lineage| vec![lineage.run_id.clone()]).unwrap_or_default())
+    }
+
+    /// read committed entries of manifest in output directory
+    pub fn read(output_dir: &str) -> Result<Vec<ManifestEntry>, FileError> {
+        let entries = read_entries(&Path::new(output_dir).join(MANIFEST_NAME))?;
+        Ok(entries.into_iter().filter(|entry| entry.status == CommitStatus::Committed).collect())
+    }
+
+    /// whether file at path exists and was not left behind by an interrupted commit
+    pub fn is_committed(&self, path: &str) -> bool {
+        let path = Path::new(path);
+        match path.file_name() {
+            Some(filename) if path.exists() => {
+                let incomplete = self.incomplete.lock().expect("manifest lock");
+                !incomplete.contains(filename.to_string_lossy().as_ref())
+            }
+            _ => false,
+        }
+    }
+
+    /// whether a chunk of a dataset was committed, e.g. to a partitioned set of files
+    pub fn is_chunk_committed(&self, dataset: &str, chunk: &str) -> bool {
+        let committed_chunks = self.committed_chunks.lock().expect("manifest lock");
+        committed_chunks.contains(&(dataset.to_string(), chunk.to_string()))
+    }
+
+    /// append entry to manifest
+    pub fn append(&self, entry: &ManifestEntry) -> Result<(), FileError> {
+        let line = serde_json::to_string(entry).map_err(|_e| FileError::ManifestError)?;
+        let mut incomplete = self.incomplete.lock().expect("manifest lock");
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|_e| FileError::ManifestError)?;
+        file.write_all(format!("{}\n", line).as_bytes()).map_err(|_e| FileError::ManifestError)?;
+        file.sync_all().map_err(|_e| FileError::ManifestError)?;
+        if entry.status == CommitStatus::Committed {
+            let mut committed_chunks = self.committed_chunks.lock().expect("manifest lock");
+            for dataset in entry.datasets.iter() {
+                committed_chunks.insert((dataset.clone(), entry.chunk.clone()));
+            }
+        }
+        for file in entry.files.iter() {
+            match entry.status {
+                CommitStatus::Pending => incomplete.insert(file.filename.clone()),
+                CommitStatus::Committed => incomplete.remove(&file.filename),
+            };
+        }
+        Ok(())
+    }
+}
+
+fn read_entries(path: &Path) -> Result<Vec<ManifestEntry>, FileError> {
+    let file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(_e) => return Err(FileError::ManifestError),
+    };
+    let mut entries = Vec::new();
+    for line in std::io::BufReader::new(file).lines() {
+        let line = line.map_err(|_e| FileError::ManifestError)?;
+        if line.trim().is_empty() {
+            continue
+        }
+        // a crash during append can leave a truncated final line, which is ignored
+        if let Ok(entry) = serde_json::from_str(&line) {
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
@@ -0,0 +1,351 @@
+use ethers::prelude::*;
+use serde_json::{json, Value};
+
+use crate::types::{RpcClientError, RpcSource};
+
+/// offline source of synthetic, deterministic chain data
+///
+/// every block, transaction, log, and trace is derived from its block number and index, so
+/// repeated runs produce identical output. use with `RpcClient::offline(MockFetcher::default())`
+#[derive(Debug, Clone)]
+pub struct MockFetcher {
+    /// chain id reported by eth_chainId
+    pub chain_id: u64,
+    /// block number reported by eth_blockNumber
+    pub latest_block: u64,
+    /// number of transactions in each block
+    pub transactions_per_block: u64,
+    /// number of logs emitted by each transaction
+    pub logs_per_transaction: u64,
+}
+
+impl Default for MockFetcher {
+    fn default() -> MockFetcher {
+        MockFetcher {
+            chain_id: 1,
+            latest_block: 1_000_000,
+            transactions_per_block: 4,
+            logs_per_transaction: 2,
+        }
+    }
+}
+
+const GENESIS_TIMESTAMP: u64 = 1_600_000_000;
+const BLOCK_TIME: u64 = 12;
+
+impl RpcSource for MockFetcher {
+    fn respond(&self, method: &str, params: &Value) -> Result<Value, RpcClientError> {
+        match method {
+            "eth_chainId" => Ok(json!(U64::from(self.chain_id))),
+            "eth_blockNumber" => Ok(json!(U64::from(self.latest_block))),
+            "eth_getBlockByNumber" => {
+                let number = self.block_number(&params[0])?;
+                let full = params[1].as_bool().unwrap_or(false);
+                Ok(self.block(number, full))
+            }
+            "eth_getTransactionReceipt" => {
+                let hash: H256 = parse_param(&params[0])?;
+                let (number, index) = decode_hash(hash);
+                Ok(self.receipt(number, index))
+            }
+            "eth_getLogs" => self.logs(&params[0]),
+            "eth_getCode" => {
+                // odd addresses have code, so recipients are a mix of contracts and eoas
+                let address: H160 = parse_param(&params[0])?;
+                match address.to_low_u64_be() % 2 {
+                    1 => Ok(json!(Bytes::from(vec![0x60, 0x80, 0x60, 0x40]))),
+                    _ => Ok(json!(Bytes::default())),
+                }
+            }
+            "trace_block" => {
+                let number = self.block_number(&params[0])?;
+                let traces: Vec<Value> = (0..self.n_transactions(number))
+                    .map(|index| self.call_trace(number, index))
+                    .collect();
+                Ok(json!(traces))
+            }
+            "trace_replayBlockTransactions" => {
+                let number = self.block_number(&params[0])?;
+                let trace_types: Vec<String> = parse_param(&params[1])?;
+                let traces: Vec<Value> = (0..self.n_transactions(number))
+                    .map(|index| self.replay_trace(number, index, &trace_types))
+                    .collect();
+                Ok(json!(traces))
+            }
+            _ => Err(RpcClientError::UnsupportedMethod(method.to_string())),
+        }
+    }
+}
+
+impl MockFetcher {
+    fn block_number(&self, param: &Value) -> Result<u64, RpcClientError> {
+        match param.as_str() {
+            Some("latest") | Some("pending") | Some("safe") | Some("finalized") => {
+                Ok(self.latest_block)
+            }
+            Some("earliest") => Ok(0),
+            _ => Ok(parse_param::<U64>(param)?.as_u64()),
+        }
+    }
+
+    fn n_transactions(&self, number: u64) -> u64 {
+        if number > self.latest_block {
+            0
+        } else {
+            self.transactions_per_block
+        }
+    }
+
+    fn block(&self, number: u64, full: bool) -> Value {
+        if number > self.latest_block {
+            return Value::Null
+        }
+        let transactions: Vec<Value> = (0..self.n_transactions(number))
+            .map(|index| match full {
+                true => self.transaction(number, index),
+                false => json!(transaction_hash(number, index)),
+            })
+            .collect();
+        let gas_used = U256::from(21_000 * self.n_transactions(number));
+        json!({
+            "hash": block_hash(number),
+            "parentHash": block_hash(number.saturating_sub(1)),
+            "sha3Uncles": H256::zero(),
+            "miner": address(number % 16),
+            "stateRoot": H256::from_low_u64_be(number),
+            "transactionsRoot": H256::from_low_u64_be(number),
+            "receiptsRoot": H256::from_low_u64_be(number),
+            "number": U64::from(number),
+            "gasUsed": gas_used,
+            "gasLimit": U256::from(30_000_000u64),
+            "extraData": Bytes::from(b"cryo mock".to_vec()),
+            "logsBloom": Bloom::zero(),
+            "timestamp": U256::from(GENESIS_TIMESTAMP + BLOCK_TIME * number),
+            "difficulty": U256::zero(),
+            "totalDifficulty": U256::zero(),
+            "uncles": [],
+            "transactions": transactions,
+            "size": U256::from(1_000 + 100 * self.n_transactions(number)),
+            "mixHash": H256::zero(),
+            "nonce": H64::zero(),
+            "baseFeePerGas": U256::from(1_000_000_000u64 + number % 1_000),
+        })
+    }
+
+    fn transaction(&self, number: u64, index: u64) -> Value {
+        json!({
+            "hash": transaction_hash(number, index),
+            "nonce": U256::from(number),
+            "blockHash": block_hash(number),
+            "blockNumber": U64::from(number),
+            "transactionIndex": U64::from(index),
+            "from": address(100 + index),
+            "to": address(200 + index),
+            "value": U256::from(number * 1_000 + index),
+            "gasPrice": U256::from(2_000_000_000u64),
+            "gas": U256::from(50_000u64),
+            "input": Bytes::from(index.to_be_bytes().to_vec()),
+            "v": U64::from(37u64),
+            "r": U256::from(number + 1),
+            "s": U256::from(index + 1),
+            "type": U64::zero(),
+            "chainId": U256::from(self.chain_id),
+        })
+    }
+
+    fn receipt(&self, number: u64, index: u64) -> Value {
+        if number > self.latest_block || index >= self.n_transactions(number) {
+            return Value::Null
+        }
+        let logs: Vec<Value> =
+            (0..self.logs_per_transaction).map(|log| self.log(number, index, log)).collect();
+        json!({
+            "transactionHash": transaction_hash(number, index),
+            "transactionIndex": U64::from(index),
+            "blockHash": block_hash(number),
+            "blockNumber": U64::from(number),
+            "from": address(100 + index),
+            "to": address(200 + index),
+            "cumulativeGasUsed": U256::from(21_000 * (index + 1)),
+            "gasUsed": U256::from(21_000u64),
+            "contractAddress": null,
+            "logs": logs,
+            "status": U64::one(),
+            "logsBloom": Bloom::zero(),
+            "type": U64::zero(),
+            "effectiveGasPrice": U256::from(2_000_000_000u64),
+        })
+    }
+
+    fn log(&self, number: u64, index: u64, log: u64) -> Value {
+        json!({
+            "address": address(200 + index),
+            "topics": [H256::from_low_u64_be(log), H256::from_low_u64_be(number)],
+            "data": Bytes::from(H256::from_low_u64_be(index).as_bytes().to_vec()),
+            "blockHash": block_hash(number),
+            "blockNumber": U64::from(number),
+            "transactionHash": transaction_hash(number, index),
+            "transactionIndex": U64::from(index),
+            "logIndex": U256::from(index * self.logs_per_transaction + log),
+            "transactionLogIndex": U256::from(log),
+            "removed": false,
+        })
+    }
+
+    fn logs(&self, filter: &Value) -> Result<Value, RpcClientError> {
+        let from_block = self.block_number(&filter["fromBlock"])?;
+        let to_block = self.block_number(&filter["toBlock"])?.min(self.latest_block);
+        let addresses: Vec<H160> = match &filter["address"] {
+            Value::Null => vec![],
+            Value::Array(_) => parse_param(&filter["address"])?,
+            value => vec![parse_param(value)?],
+        };
+        let topic0: Vec<H256> = match &filter["topics"][0] {
+            Value::Null => vec![],
+            Value::Array(_) => parse_param(&filter["topics"][0])?,
+            value => vec![parse_param(value)?],
+        };
+
+        let mut logs = Vec::new();
+        for number in from_block..=to_block {
+            for index in 0..self.n_transactions(number) {
+                if !addresses.is_empty() && !addresses.contains(&address(200 + index)) {
+                    continue
+                }
+                for log in 0..self.logs_per_transaction {
+                    if !topic0.is_empty() && !topic0.contains(&H256::from_low_u64_be(log)) {
+                        continue
+                    }
+                    logs.push(self.log(number, index, log));
+                }
+            }
+        }
+        Ok(json!(logs))
+    }
+
+    fn call_trace(&self, number: u64, index: u64) -> Value {
+        json!({
+            "action": {
+                "callType": "call",
+                "from": address(100 + index),
+                "to": address(200 + index),
+                "gas": U256::from(50_000u64),
+                "input": Bytes::from(index.to_be_bytes().to_vec()),
+                "value": U256::from(number * 1_000 + index),
+            },
+            "result": {
+                "gasUsed": U256::from(21_000u64),
+                "output": Bytes::default(),
+            },
+            "traceAddress": [],
+            "subtraces": 0,
+            "transactionPosition": index,
+            "transactionHash": transaction_hash(number, index),
+            "blockNumber": number,
+            "blockHash": block_hash(number),
+            "type": "call",
+        })
+    }
+
+    fn replay_trace(&self, number: u64, index: u64, trace_types: &[String]) -> Value {
+        let has = |trace_type: &str| trace_types.iter().any(|t| t == trace_type);
+        let trace = match has("trace") {
+            true => {
+                let mut trace = self.call_trace(number, index);
+                if let Value::Object(map) = &mut trace {
+                    for key in
+                        ["transactionPosition", "transactionHash", "blockNumber", "blockHash"]
+                    {
+                        map.remove(key);
+                    }
+                }
+                json!([trace])
+            }
+            false => Value::Null,
+        };
+        let vm_trace = match has("vmTrace") {
+            true => json!({
+                "code": Bytes::from(vec![0x60, 0x00, 0x00]),
+                "ops": [{
+                    "cost": 3,
+                    "ex": {"mem": null, "push": [U256::zero()], "store": null, "used": 49_997},
+                    "pc": 0,
+                    "sub": null,
+                    "op": "PUSH1",
+                    "idx": "0",
+                }],
+            }),
+            false => Value::Null,
+        };
+        let state_diff = match has("stateDiff") {
+            true => {
+                let value = U256::from(number * 1_000 + index);
+                let balance = U256::from(10u64).pow(U256::from(18u64));
+                json!({
+                    format!("{:?}", address(100 + index)): {
+                        "balance": {"*": {"from": balance, "to": balance - value}},
+                        "nonce": {"*": {"from": U256::from(number), "to": U256::from(number + 1)}},
+                        "code": "=",
+                        "storage": {},
+                    },
+                    format!("{:?}", address(200 + index)): {
+                        "balance": {"*": {"from": balance, "to": balance + value}},
+                        "nonce": "=",
+                        "code": "=",
+                        "storage": {
+                            format!("{:?}", H256::from_low_u64_be(index)): {
+                                "*": {
+                                    "from": H256::from_low_u64_be(number),
+                                    "to": H256::from_low_u64_be(number + 1),
+                                },
+                            },
+                        },
+                    },
+                })
+            }
+            false => Value::Null,
+        };
+        json!({
+            "output": Bytes::default(),
+            "trace": trace,
+            "vmTrace": vm_trace,
+            "stateDiff": state_diff,
+            "transactionHash": transaction_hash(number, index),
+        })
+    }
+}
+
+fn parse_param<T: serde::de::DeserializeOwned>(param: &Value) -> Result<T, RpcClientError> {
+    serde_json::from_value(param.clone()).map_err(|e| RpcClientError::InvalidParams(e.to_string()))
+}
+
+fn address(i: u64) -> H160 {
+    H160::from_low_u64_be(i + 1)
+}
+
+/// block hashes encode the block number so they can be decoded by later requests
+fn block_hash(number: u64) -> H256 {
+    encode_hash(0xb1, number, 0)
+}
+
+/// transaction hashes encode block number and index so receipts can be served by hash
+fn transaction_hash(number: u64, index: u64) -> H256 {
+    encode_hash(0x7c, number, index)
+}
+
+fn encode_hash(prefix: u8, number: u64, index: u64) -> H256 {
+    let mut bytes = [0u8; 32];
+    bytes[0] = prefix;
+    bytes[8..16].copy_from_slice(&number.to_be_bytes());
+    bytes[16..24].copy_from_slice(&index.to_be_bytes());
+    H256::from(bytes)
+}
+
+fn decode_hash(hash: H256) -> (u64, u64) {
+    let bytes = hash.as_bytes();
+    let mut number = [0u8; 8];
+    let mut index = [0u8; 8];
+    number.copy_from_slice(&bytes[8..16]);
+    index.copy_from_slice(&bytes[16..24]);
+    (u64::from_be_bytes(number), u64::from_be_bytes(index))
+}
@@ -1,13 +1,25 @@
 /// type specifications for cryo_freeze crate
 
+/// beacon node api client
+pub mod beacon;
+/// chain-specific schema extensions
+pub mod chains;
 /// type specifications for chunk types
 pub mod chunks;
 /// conversion operations
 pub mod conversions;
 /// type specifications for collectable types
 pub mod datatypes;
+/// synthetic offline data source
+pub mod mock_fetcher;
+/// JSON-RPC client
+pub mod rpc_client;
 /// type specifications for data sources
 pub mod sources;
+/// contract verification lookups
+pub mod sourcify;
+/// starknet json-rpc types
+pub mod starknet;
 
 /// type specifications for dataframes
 #[macro_use]
@@ -15,23 +27,87 @@ pub mod dataframes;
 
 /// error specifications
 pub mod errors;
+/// log of failed chunks
+pub mod failed_chunks;
 /// type specifications for output data formats
 pub mod files;
+/// genesis allocations
+pub mod genesis;
+/// chain heads pushed by websocket subscriptions
+pub mod head_subscription;
+/// normalization of nonstandard rpc responses
+pub mod lenient;
+/// logs read from local parquet files
+pub mod local_logs;
+/// output directory locks
+pub mod locks;
+/// manifests of committed output files
+pub mod manifest;
 /// quries
 pub mod queries;
+/// json-rpc requests of the raw_rpc dataset
+pub mod raw_rpc;
+/// reorgs observed while following
+pub mod reorgs;
 /// type specifications for data schemas
 pub mod schemas;
+/// rate limits shared across processes
+pub mod shared_limits;
+/// checksums and manifest signatures
+pub mod signatures;
+/// spam log filtering
+pub mod spam;
 /// types related to summaries
 pub mod summaries;
+/// schema round-trip testing harness
+pub mod testing;
+/// erc20 token metadata
+pub mod tokens;
+/// event topics shared by datasets and log filters
+pub mod topics;
+/// rpc usage ledger across runs
+pub mod usage;
 
-pub use chunks::{AddressChunk, BlockChunk, Chunk, ChunkData, Subchunk, TransactionChunk};
-pub use conversions::{ToVecHex, ToVecU8};
+pub use beacon::{BeaconClient, Validator};
+pub use chains::{ChainExtension, Vm};
+pub use chunks::{
+    AdaptiveChunks, AddressChunk, BlockChunk, ByteTarget, Chunk, ChunkData, ChunkStrategy,
+    FixedBlocks, Subchunk, TimestampAligned, TransactionChunk, TxCountTarget,
+    MAX_STRATEGY_CHUNK_BLOCKS,
+};
+pub use conversions::{decode_hex, encode_hex, NarrowU32, ToVecHex, ToVecU8};
 pub use datatypes::*;
-pub use files::{ColumnEncoding, FileFormat, FileOutput};
-pub use queries::{MultiQuery, RowFilter, SingleQuery};
+pub use failed_chunks::{FailedChunk, FailedChunkLog, FAILED_CHUNKS_NAME};
+pub use files::{
+    ColumnEncoding, CsvDialect, CsvQuoteStyle, FileFormat, FileOutput, PartitionBy, XLSX_MAX_ROWS,
+};
+pub use genesis::{GenesisAccount, GenesisAlloc};
+pub use head_subscription::HeadSubscription;
+pub use local_logs::{LocalLogs, LocalPruning};
+pub use locks::{lock_holder, OutputLock, LOCKFILE_NAME};
+pub use manifest::{
+    endpoint_fingerprint, CommitStatus, Lineage, Manifest, ManifestEntry, ManifestFile,
+    MANIFEST_NAME, STAGING_DIR_NAME,
+};
+pub use mock_fetcher::MockFetcher;
+pub use queries::{Follow, MultiQuery, RowFilter, SingleQuery};
+pub use raw_rpc::{RawRpcRequest, BLOCK_PLACEHOLDER};
+pub use reorgs::{Reorg, ReorgLog, REORGS_NAME};
+pub use rpc_client::{
+    MethodStats, RpcClient, RpcClientError, RpcFixture, RpcLogger, RpcRecorder, RpcSource,
+};
 pub use schemas::{ColumnType, Table};
-pub use sources::{RateLimiter, Source};
+pub use shared_limits::SharedRateLimiter;
+pub use signatures::{sha256_file, verify_manifest, SignatureScheme, Signer};
+pub use sources::{RateLimiter, RequestPriority, Source};
+pub use sourcify::{SourcifyClient, DEFAULT_SOURCIFY_URL};
+pub use spam::SpamFilter;
+pub use starknet::{StarknetBlock, StarknetTransaction};
 pub(crate) use summaries::FreezeSummaryAgg;
 pub use summaries::{FreezeChunkSummary, FreezeSummary};
+pub use tokens::TokenDecimals;
+pub use usage::{MethodUsage, UsageEntry, UsageLedger, USAGE_LEDGER_ENV};
 
-pub use errors::{ChunkError, CollectError, FileError, FreezeError, ParseError};
+pub use errors::{
+    ChunkError, CollectError, ErrorKind, FileError, FreezeError, ParseError, RoundTripError,
+};
@@ -1,8 +1,8 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use ethers::prelude::*;
 
-use crate::types::{Chunk, Datatype, Table};
+use crate::types::{AdaptiveChunks, Chunk, Datatype, Source, SpamFilter, Table};
 
 /// Query multiple data types
 #[derive(Clone)]
@@ -26,15 +26,68 @@ pub struct MultiQuery {
     pub chunks: Vec<Chunk>,
     /// Row filter
     pub row_filters: HashMap<Datatype, RowFilter>,
+    /// Resize block chunks while collecting to hold about a target number of rows
+    pub adaptive: Option<AdaptiveChunks>,
 }
 
-/// Options for fetching logs
+/// Following of the chain head alongside a backfill
 #[derive(Clone)]
+pub struct Follow {
+    /// first block to follow, after the backfilled blocks
+    pub start_block: u64,
+    /// blocks that followed blocks must be behind the chain head, so they are unlikely to reorg
+    pub confirmations: u64,
+    /// maximum blocks per followed chunk, or per poll in tip mode
+    pub chunk_size: u64,
+    /// time between polls of the chain head
+    pub poll_interval: std::time::Duration,
+    /// websocket url subscribed to for new chain heads instead of polling
+    pub ws_url: Option<String>,
+    /// source of followed blocks, with its own concurrency budget
+    pub source: Source,
+    /// alert when the last followed block is more than this many blocks behind the confirmed head
+    pub max_block_lag: Option<u64>,
+    /// alert when the chain head reported by the provider stops advancing for this long
+    pub max_head_stall: Option<std::time::Duration>,
+    /// url receiving alerts as json posts
+    pub alert_webhook: Option<String>,
+    /// stop following with an error at the first alert
+    pub exit_on_alert: bool,
+    /// record reorgs of the chain head in output_dir/cryo_reorgs.jsonl
+    pub record_reorgs: bool,
+    /// collect each followed block as its own chunk, writing its files as soon as it arrives
+    pub tip: bool,
+    /// collect blocks at the chain head, recollecting blocks whose hash changed once they have
+    /// the required confirmations
+    pub preconfirmed: bool,
+}
+
+/// Options for fetching logs
+#[derive(Clone, Default)]
 pub struct RowFilter {
     /// topics to filter for
     pub topics: [Option<ValueOrArray<Option<H256>>>; 4],
     /// address to filter for
     pub address: Option<ValueOrArray<H160>>,
+    /// spam Transfer logs to drop
+    pub spam_filter: Option<SpamFilter>,
+    /// token contracts whose rows are dropped from token datasets
+    pub excluded_addresses: HashSet<H160>,
+    /// collapse proxy calls that only delegatecall their implementation into one trace
+    pub collapse_delegatecalls: bool,
+    /// drop logs of fetched receipts that do not match the address and topics of the filter
+    pub prune_receipt_logs: bool,
+}
+
+impl RowFilter {
+    /// whether an address is among the addresses of the filter, true if it has no addresses
+    pub fn includes_address(&self, address: &H160) -> bool {
+        match &self.address {
+            None => true,
+            Some(ValueOrArray::Value(value)) => value == address,
+            Some(ValueOrArray::Array(values)) => values.contains(address),
+        }
+    }
 }
 
 impl From<MultiQuery> for SingleQuery {
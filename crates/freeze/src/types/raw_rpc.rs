@@ -0,0 +1,36 @@
+use crate::types::{CollectError, ParseError};
+
+/// placeholder of a request template replaced by the hex block number of each request
+pub const BLOCK_PLACEHOLDER: &str = "{block}";
+
+/// json-rpc method and parameter template of the raw_rpc dataset
+#[derive(Debug, Clone)]
+pub struct RawRpcRequest {
+    /// json-rpc method, e.g. eth_feeHistory
+    pub method: String,
+    /// json array of parameters, in which {block} is replaced by the block number, e.g.
+    /// `["{block}", false]`
+    pub params: String,
+}
+
+impl RawRpcRequest {
+    /// create request, checking that the template renders a json array
+    pub fn new(method: &str, params: &str) -> Result<RawRpcRequest, ParseError> {
+        let request = RawRpcRequest { method: method.to_string(), params: params.to_string() };
+        match request.render(0) {
+            Ok(serde_json::Value::Array(_)) => Ok(request),
+            Ok(_) => Err(ParseError::ParseError("rpc params must be a json array".to_string())),
+            Err(e) => Err(ParseError::ParseError(format!("invalid rpc params: {}", e))),
+        }
+    }
+
+    /// parameters of the request for a block
+    pub fn params(&self, block_number: u64) -> Result<serde_json::Value, CollectError> {
+        self.render(block_number).map_err(|e| CollectError::CollectError(e.to_string()))
+    }
+
+    fn render(&self, block_number: u64) -> Result<serde_json::Value, serde_json::Error> {
+        let block = format!("0x{:x}", block_number);
+        serde_json::from_str(&self.params.replace(BLOCK_PLACEHOLDER, &block))
+    }
+}
@@ -0,0 +1,79 @@
+use std::{
+    io::{BufRead, Write},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::FileError;
+
+/// name of file listing reorgs observed while following, placed in output directory
+pub const REORGS_NAME: &str = "cryo_reorgs.jsonl";
+
+/// record of a reorg of the chain head, observed between two polls of the head
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Reorg {
+    /// number of the head before the reorg
+    pub old_head_number: u64,
+    /// hash of the head before the reorg
+    pub old_head_hash: String,
+    /// number of the head after the reorg
+    pub new_head_number: u64,
+    /// hash of the head after the reorg
+    pub new_head_hash: String,
+    /// last block shared by the old and new chains, none if it was not found, e.g. because the
+    /// node pruned the replaced blocks
+    pub common_ancestor: Option<u64>,
+    /// number of blocks of the old chain that were replaced, a lower bound if the common
+    /// ancestor was not found
+    pub depth: u64,
+    /// unix time the reorg was observed
+    pub timestamp: u64,
+}
+
+/// append-only log of reorgs observed while following, stored as json lines
+///
+/// reorgs are only observed when they replace a head seen by a poll, so reorgs that start and
+/// resolve between two polls are missed
+#[derive(Debug)]
+pub struct ReorgLog {
+    path: PathBuf,
+}
+
+impl ReorgLog {
+    /// log of reorgs of output directory
+    pub fn new(output_dir: &str) -> ReorgLog {
+        ReorgLog { path: Path::new(output_dir).join(REORGS_NAME) }
+    }
+
+    /// append a reorg to log
+    pub fn record(&self, reorg: &Reorg) -> Result<(), FileError> {
+        let line = serde_json::to_string(reorg).map_err(|_e| FileError::FileWriteError)?;
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|_e| FileError::FileWriteError)?;
+        file.write_all(format!("{}\n", line).as_bytes()).map_err(|_e| FileError::FileWriteError)
+    }
+
+    /// read reorgs logged in output directory
+    pub fn read(output_dir: &str) -> Result<Vec<Reorg>, FileError> {
+        let path = Path::new(output_dir).join(REORGS_NAME);
+        let file = match std::fs::File::open(path) {
+            Ok(file) => file,
+            Err(_) => return Ok(Vec::new()),
+        };
+        let mut entries = Vec::new();
+        for line in std::io::BufReader::new(file).lines() {
+            let line = line.map_err(|e| FileError::FileReadError(e.to_string()))?;
+            if line.trim().is_empty() {
+                continue
+            }
+            let entry = serde_json::from_str(&line)
+                .map_err(|e| FileError::FileReadError(format!("invalid reorg: {}", e)))?;
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+}
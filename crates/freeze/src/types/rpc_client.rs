@@ -0,0 +1,1135 @@
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    io::{BufRead, Write},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use ethers::prelude::*;
+use governor::Jitter;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use thiserror::Error;
+use tokio::sync::watch;
+
+use crate::types::{lenient::normalize_response, RateLimiter};
+
+/// JSON-RPC client used by cryo
+///
+/// wraps an http transport or an offline `RpcSource`, with optional request logging and recording
+#[derive(Debug)]
+pub struct RpcClient {
+    transport: Transport,
+    logger: Option<RpcLogger>,
+    recorder: Option<RpcRecorder>,
+    lenient: bool,
+    in_flight: Option<InFlight>,
+    breaker: Option<CircuitBreaker>,
+    method_limits: Option<MethodRateLimits>,
+    retries: Option<Retries>,
+    stats: Option<RpcStats>,
+    credits: Option<Credits>,
+    unsupported_methods: Vec<String>,
+}
+
+#[derive(Debug)]
+enum Transport {
+    Http(Http),
+    Offline(Box<dyn RpcSource>),
+}
+
+/// source of JSON-RPC responses that does not require a network connection
+pub trait RpcSource: Debug + Send + Sync {
+    /// respond to a JSON-RPC request
+    fn respond(
+        &self,
+        method: &str,
+        params: &serde_json::Value,
+    ) -> Result<serde_json::Value, RpcClientError>;
+}
+
+impl RpcClient {
+    /// create client for an rpc url, accepting gzip and brotli compressed responses
+    pub fn new(url: &str) -> Result<RpcClient, RpcClientError> {
+        let url = reqwest::Url::parse(url).map_err(|_e| RpcClientError::InvalidUrl)?;
+        let transport = Http::new_with_client(url, http_client()?);
+        Ok(RpcClient {
+            transport: Transport::Http(transport),
+            logger: None,
+            recorder: None,
+            lenient: false,
+            in_flight: None,
+            breaker: None,
+            method_limits: None,
+            retries: None,
+            stats: None,
+            credits: None,
+            unsupported_methods: vec![],
+        })
+    }
+
+    /// create client that serves responses from an offline source, e.g. an `RpcFixture`
+    pub fn offline<S: RpcSource + 'static>(source: S) -> RpcClient {
+        RpcClient {
+            transport: Transport::Offline(Box::new(source)),
+            logger: None,
+            recorder: None,
+            lenient: false,
+            in_flight: None,
+            breaker: None,
+            method_limits: None,
+            retries: None,
+            stats: None,
+            credits: None,
+            unsupported_methods: vec![],
+        }
+    }
+
+    /// log every request and truncated response
+    pub fn with_logger(mut self, logger: RpcLogger) -> RpcClient {
+        self.logger = Some(logger);
+        self
+    }
+
+    /// record every request and response into a fixture file
+    pub fn with_recorder(mut self, recorder: RpcRecorder) -> RpcClient {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /// tolerate nonstandard responses of exotic evm chains, see `lenient::normalize_response`
+    pub fn with_lenient(mut self) -> RpcClient {
+        self.lenient = true;
+        self
+    }
+
+    /// share one call between identical concurrent requests, e.g. the same block requested by two
+    /// datasets
+    pub fn with_coalescing(mut self) -> RpcClient {
+        self.in_flight = Some(InFlight::default());
+        self
+    }
+
+    /// pause requests for cooldown after max_failures consecutive transport or rate limit
+    /// failures, then probe the endpoint with one request per cooldown until it recovers
+    pub fn with_circuit_breaker(mut self, max_failures: u32, cooldown: Duration) -> RpcClient {
+        self.breaker = Some(CircuitBreaker {
+            max_failures: max_failures.max(1),
+            cooldown,
+            state: Mutex::new(CircuitState::default()),
+            n_pauses: AtomicU64::new(0),
+        });
+        self
+    }
+
+    /// number of times the circuit breaker paused requests, if it is enabled
+    pub fn circuit_pauses(&self) -> Option<u64> {
+        self.breaker.as_ref().map(|breaker| breaker.n_pauses.load(Ordering::Relaxed))
+    }
+
+    /// rate limit methods matching patterns, e.g. `trace` for trace_* methods, `debug_trace*`, or
+    /// `eth_getLogs`, requests use the limit of the longest matching pattern, or `default`
+    pub fn with_method_rate_limits(
+        mut self,
+        limits: Vec<(String, RateLimiter)>,
+        jitter: Jitter,
+    ) -> RpcClient {
+        let (defaults, limits): (Vec<_>, Vec<_>) =
+            limits.into_iter().partition(|(pattern, _)| pattern == "default");
+        self.method_limits = Some(MethodRateLimits {
+            limits,
+            default: defaults.into_iter().next().map(|(_, limiter)| limiter),
+            jitter,
+        });
+        self
+    }
+
+    /// fail requests of methods matching patterns without sending them, for methods a provider
+    /// is known not to serve, patterns are those of `with_method_rate_limits`
+    pub fn with_unsupported_methods(mut self, patterns: Vec<String>) -> RpcClient {
+        self.unsupported_methods = patterns;
+        self
+    }
+
+    /// retry rate limited http requests up to max_retries times
+    ///
+    /// retries wait for the Retry-After header of a response, in seconds, or for an exponential
+    /// backoff if there is none. requests told to wait longer than max_retry_after fail instead,
+    /// and a max_retry_after of zero ignores the header
+    pub fn with_retries(
+        mut self,
+        max_retries: u32,
+        max_retry_after: Duration,
+    ) -> Result<RpcClient, RpcClientError> {
+        if let Transport::Http(http) = &self.transport {
+            self.retries = Some(Retries {
+                client: http_client()?,
+                url: http.url().clone(),
+                max_retries,
+                max_retry_after,
+                next_id: AtomicU64::new(1),
+            });
+        }
+        Ok(self)
+    }
+
+    /// count requests and response bytes of each method, see `RpcClient::stats`
+    pub fn with_stats(mut self) -> RpcClient {
+        self.stats = Some(RpcStats::default());
+        self
+    }
+
+    /// charge each request the credits of its method, as priced by providers that bill in
+    /// compute units or credits, and fail requests that would exceed a budget without sending them
+    ///
+    /// costs are keyed by the patterns of `with_method_rate_limits`, methods matching no pattern
+    /// cost the `default` pattern's credits, or nothing. requests coalesced into one http call
+    /// are charged once, and each retry of a rate limited request is charged again
+    pub fn with_credits(mut self, costs: Vec<(String, u64)>, budget: Option<u64>) -> RpcClient {
+        let (defaults, costs): (Vec<_>, Vec<_>) =
+            costs.into_iter().partition(|(pattern, _)| pattern == "default");
+        self.credits = Some(Credits {
+            costs,
+            default: defaults.into_iter().next().map(|(_, cost)| cost).unwrap_or(0),
+            budget,
+            total: AtomicU64::new(0),
+            methods: Mutex::new(HashMap::new()),
+        });
+        self
+    }
+
+    /// credits charged for one request of a method, if credits are tracked
+    pub fn credit_cost(&self, method: &str) -> Option<u64> {
+        self.credits.as_ref().map(|credits| credits.cost(method))
+    }
+
+    /// credit budget of the run, if any
+    pub fn credit_budget(&self) -> Option<u64> {
+        self.credits.as_ref().and_then(|credits| credits.budget)
+    }
+
+    /// credits charged for each method, most credits first
+    pub fn credits_spent(&self) -> Vec<(String, u64)> {
+        let mut spent: Vec<(String, u64)> = match &self.credits {
+            Some(credits) => credits
+                .methods
+                .lock()
+                .map(|methods| methods.iter().map(|(k, v)| (k.clone(), *v)).collect())
+                .unwrap_or_default(),
+            None => vec![],
+        };
+        spent.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        spent
+    }
+
+    /// requests and response bytes of each method, largest response bytes first
+    pub fn stats(&self) -> Vec<(String, MethodStats)> {
+        let mut stats: Vec<(String, MethodStats)> = match &self.stats {
+            Some(stats) => stats
+                .methods
+                .lock()
+                .map(|methods| methods.iter().map(|(k, v)| (k.clone(), *v)).collect())
+                .unwrap_or_default(),
+            None => vec![],
+        };
+        stats.sort_by(|a, b| b.1.n_bytes.cmp(&a.1.n_bytes).then_with(|| a.0.cmp(&b.0)));
+        stats
+    }
+
+    /// send a request, sharing one call between identical concurrent requests when coalescing
+    async fn request_coalesced(
+        &self,
+        method: &str,
+        params: &serde_json::Value,
+    ) -> Result<serde_json::Value, RpcClientError> {
+        let in_flight = match &self.in_flight {
+            Some(in_flight) => in_flight,
+            None => return self.send(method, params).await,
+        };
+        let key = fixture_key(method, params);
+        let call = {
+            let mut calls = in_flight.calls.lock().expect("in-flight lock");
+            match calls.get(&key) {
+                Some(receiver) => Err(receiver.clone()),
+                None => {
+                    let (sender, receiver) = watch::channel(None);
+                    calls.insert(key.clone(), receiver);
+                    Ok(sender)
+                }
+            }
+        };
+        match call {
+            // first caller performs the request and shares its result
+            Ok(sender) => {
+                let _guard = InFlightGuard { in_flight, key };
+                let result = self.send(method, params).await;
+                let shared = match &result {
+                    Ok(value) => Ok(value.clone()),
+                    Err(e) => Err((e.to_string(), e.as_error_response().cloned())),
+                };
+                let _ = sender.send(Some(shared));
+                result
+            }
+            // later callers wait for the shared result
+            Err(mut receiver) => {
+                loop {
+                    let shared = receiver.borrow().clone();
+                    if let Some(shared) = shared {
+                        return shared.map_err(|(message, response)| RpcClientError::Coalesced {
+                            message,
+                            response,
+                        })
+                    }
+                    if receiver.changed().await.is_err() {
+                        break
+                    }
+                }
+                // first caller was cancelled before responding
+                self.send(method, params).await
+            }
+        }
+    }
+
+    async fn send(
+        &self,
+        method: &str,
+        params: &serde_json::Value,
+    ) -> Result<serde_json::Value, RpcClientError> {
+        let http = match &self.transport {
+            Transport::Http(http) => http,
+            Transport::Offline(source) => {
+                if let Some(credits) = &self.credits {
+                    credits.charge(method)?;
+                }
+                return source.respond(method, params)
+            }
+        };
+        match &self.retries {
+            Some(retries) => retries.request(method, params, self.credits.as_ref()).await,
+            None => {
+                if let Some(credits) = &self.credits {
+                    credits.charge(method)?;
+                }
+                JsonRpcClient::request(http, method, params).await.map_err(Into::into)
+            }
+        }
+    }
+}
+
+/// http client accepting gzip and brotli compressed responses
+fn http_client() -> Result<reqwest::Client, RpcClientError> {
+    reqwest::Client::builder()
+        .gzip(true)
+        .brotli(true)
+        .build()
+        .map_err(|_e| RpcClientError::InvalidUrl)
+}
+
+/// first wait of exponential backoff between retries of rate limited requests
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+
+/// longest wait of exponential backoff between retries of rate limited requests
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// retries of rate limited http requests
+///
+/// requests are sent with their own http client, since the ethers transport does not expose the
+/// status and headers of responses
+#[derive(Debug)]
+struct Retries {
+    client: reqwest::Client,
+    url: reqwest::Url,
+    max_retries: u32,
+    max_retry_after: Duration,
+    next_id: AtomicU64,
+}
+
+/// outcome of one attempt of a request
+enum Attempt {
+    Done(Result<serde_json::Value, RpcClientError>),
+    /// rate limited, with the wait requested by the Retry-After header, if any
+    RateLimited(RpcClientError, Option<Duration>),
+}
+
+impl Retries {
+    async fn request(
+        &self,
+        method: &str,
+        params: &serde_json::Value,
+        credits: Option<&Credits>,
+    ) -> Result<serde_json::Value, RpcClientError> {
+        let mut backoff = INITIAL_BACKOFF;
+        let mut retries = 0;
+        loop {
+            let (error, retry_after) = match self.attempt(method, params, credits).await {
+                Attempt::Done(result) => return result.map_err(|e| e.after_retries(retries)),
+                Attempt::RateLimited(error, retry_after) => (error, retry_after),
+            };
+            if retries >= self.max_retries {
+                return Err(error.after_retries(retries))
+            }
+            let wait = match retry_after {
+                Some(_) if self.max_retry_after.is_zero() => backoff,
+                Some(retry_after) if retry_after > self.max_retry_after => {
+                    return Err(error.after_retries(retries))
+                }
+                Some(retry_after) => retry_after,
+                None => backoff,
+            };
+            tokio::time::sleep(wait).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+            retries += 1;
+        }
+    }
+
+    /// send one attempt of a request, charging its credits, since providers bill every attempt
+    async fn attempt(
+        &self,
+        method: &str,
+        params: &serde_json::Value,
+        credits: Option<&Credits>,
+    ) -> Attempt {
+        if let Some(Err(e)) = credits.map(|credits| credits.charge(method)) {
+            return Attempt::Done(Err(e))
+        }
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let body =
+            serde_json::json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params});
+        let response = match self.client.post(self.url.clone()).json(&body).send().await {
+            Ok(response) => response,
+            Err(e) => return Attempt::Done(Err(HttpClientError::ReqwestError(e).into())),
+        };
+        let status = response.status();
+        if [429, 503].contains(&status.as_u16()) {
+            let retry_after = response
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse::<u64>().ok())
+                .map(Duration::from_secs);
+            let error = RpcClientError::RateLimited { status: status.as_u16(), retry_after };
+            return Attempt::RateLimited(error, retry_after)
+        }
+        let bytes = match response.bytes().await {
+            Ok(bytes) => bytes,
+            Err(e) => return Attempt::Done(Err(HttpClientError::ReqwestError(e).into())),
+        };
+
+        // json-rpc rate limit errors carry no header, and are retried with backoff
+        let mut response: serde_json::Value = match serde_json::from_slice(&bytes) {
+            Ok(response) => response,
+            Err(err) => {
+                let text = String::from_utf8_lossy(&bytes).to_string();
+                return Attempt::Done(Err(HttpClientError::SerdeJson { err, text }.into()))
+            }
+        };
+        let error = response.get_mut("error").map(serde_json::Value::take);
+        match error.filter(|error| !error.is_null()).map(serde_json::from_value::<JsonRpcError>) {
+            Some(Ok(error)) if [429, -32005].contains(&error.code) => {
+                Attempt::RateLimited(HttpClientError::JsonRpcError(error).into(), None)
+            }
+            Some(Ok(error)) => Attempt::Done(Err(HttpClientError::JsonRpcError(error).into())),
+            Some(Err(e)) => Attempt::Done(Err(e.into())),
+            None => match response.get_mut("result") {
+                Some(result) => Attempt::Done(Ok(result.take())),
+                None => {
+                    let text = String::from_utf8_lossy(&bytes).to_string();
+                    let err = <serde_json::Error as serde::de::Error>::custom("no result or error");
+                    Attempt::Done(Err(HttpClientError::SerdeJson { err, text }.into()))
+                }
+            },
+        }
+    }
+}
+
+/// result of an in-flight request, errors are shared as their message and json-rpc response
+type SharedResult = Result<serde_json::Value, (String, Option<JsonRpcError>)>;
+
+/// requests being performed, keyed by method and params
+#[derive(Debug, Default)]
+struct InFlight {
+    calls: Mutex<HashMap<String, watch::Receiver<Option<SharedResult>>>>,
+}
+
+/// removes a request from in-flight requests once it completes or is cancelled
+struct InFlightGuard<'a> {
+    in_flight: &'a InFlight,
+    key: String,
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut calls) = self.in_flight.calls.lock() {
+            calls.remove(&self.key);
+        }
+    }
+}
+
+/// requests and response bytes of each method
+#[derive(Debug, Default)]
+struct RpcStats {
+    methods: Mutex<HashMap<String, MethodStats>>,
+}
+
+/// requests and response bytes of a method
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MethodStats {
+    /// number of requests, including failed requests
+    pub n_requests: u64,
+    /// bytes of json responses, after decompression
+    pub n_bytes: u64,
+}
+
+impl RpcStats {
+    fn record(&self, method: &str, result: &Result<serde_json::Value, RpcClientError>) {
+        let n_bytes = match result {
+            Ok(value) => serde_json::to_vec(value).map(|bytes| bytes.len() as u64).unwrap_or(0),
+            Err(_) => 0,
+        };
+        if let Ok(mut methods) = self.methods.lock() {
+            let stats = methods.entry(method.to_string()).or_default();
+            stats.n_requests += 1;
+            stats.n_bytes += n_bytes;
+        }
+    }
+}
+
+/// credits charged for requests, see `RpcClient::with_credits`
+#[derive(Debug)]
+struct Credits {
+    costs: Vec<(String, u64)>,
+    default: u64,
+    budget: Option<u64>,
+    total: AtomicU64,
+    methods: Mutex<HashMap<String, u64>>,
+}
+
+impl Credits {
+    /// cost of the longest pattern matching a method
+    fn cost(&self, method: &str) -> u64 {
+        self.costs
+            .iter()
+            .filter(|(pattern, _)| method_matches(pattern, method))
+            .max_by_key(|(pattern, _)| pattern.len())
+            .map(|(_, cost)| *cost)
+            .unwrap_or(self.default)
+    }
+
+    /// charge a request before it is sent, unless it would exceed the budget
+    fn charge(&self, method: &str) -> Result<(), RpcClientError> {
+        let cost = self.cost(method);
+        let total = self.total.fetch_add(cost, Ordering::Relaxed) + cost;
+        if let Some(budget) = self.budget.filter(|budget| total > *budget) {
+            self.total.fetch_sub(cost, Ordering::Relaxed);
+            return Err(RpcClientError::CreditBudgetExceeded(budget))
+        }
+        if let Ok(mut methods) = self.methods.lock() {
+            *methods.entry(method.to_string()).or_default() += cost;
+        }
+        Ok(())
+    }
+}
+
+/// rate limiters of methods
+struct MethodRateLimits {
+    limits: Vec<(String, RateLimiter)>,
+    default: Option<RateLimiter>,
+    jitter: Jitter,
+}
+
+impl Debug for MethodRateLimits {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let patterns: Vec<&String> = self.limits.iter().map(|(pattern, _)| pattern).collect();
+        f.debug_struct("MethodRateLimits")
+            .field("patterns", &patterns)
+            .field("default", &self.default.is_some())
+            .finish()
+    }
+}
+
+impl MethodRateLimits {
+    /// wait until the rate limit of a method allows a request
+    async fn until_ready(&self, method: &str) {
+        let limiter = self
+            .limits
+            .iter()
+            .filter(|(pattern, _)| method_matches(pattern, method))
+            .max_by_key(|(pattern, _)| pattern.len())
+            .map(|(_, limiter)| limiter)
+            .or(self.default.as_ref());
+        if let Some(limiter) = limiter {
+            limiter.until_ready_with_jitter(self.jitter).await;
+        }
+    }
+}
+
+/// whether a method matches a pattern: a method name, a namespace such as `trace`, or a prefix
+/// ending in `*`
+fn method_matches(pattern: &str, method: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => method.starts_with(prefix),
+        None => match method.strip_prefix(pattern) {
+            Some("") => true,
+            Some(rest) => !pattern.contains('_') && rest.starts_with('_'),
+            None => false,
+        },
+    }
+}
+
+/// interval at which requests waiting on a probe of an open circuit check its outcome
+const PROBE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// stops sending requests to a failing endpoint, so retries do not hammer it
+#[derive(Debug)]
+struct CircuitBreaker {
+    max_failures: u32,
+    cooldown: Duration,
+    state: Mutex<CircuitState>,
+    /// times the circuit opened after being closed
+    n_pauses: AtomicU64,
+}
+
+#[derive(Debug, Default)]
+struct CircuitState {
+    /// consecutive failed requests
+    failures: u32,
+    /// requests are paused until this time while the circuit is open
+    open_until: Option<Instant>,
+    /// start of the request probing an open circuit
+    probe_started: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    /// wait until a request may be sent
+    ///
+    /// once the cooldown of an open circuit elapses, one request probes the endpoint while others
+    /// keep waiting, a probe that never completes is replaced after another cooldown
+    async fn admit(&self) {
+        loop {
+            let wait = {
+                let mut state = self.state.lock().expect("circuit breaker lock");
+                let now = Instant::now();
+                match (state.open_until, state.probe_started) {
+                    (None, _) => return,
+                    (Some(open_until), _) if open_until > now => open_until - now,
+                    (Some(_), Some(started)) if now.duration_since(started) < self.cooldown => {
+                        PROBE_POLL_INTERVAL
+                    }
+                    (Some(_), _) => {
+                        state.probe_started = Some(now);
+                        return
+                    }
+                }
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// close the circuit after a success, or count a failure and open it at max_failures
+    fn record(&self, result: &Result<serde_json::Value, RpcClientError>) {
+        let mut state = self.state.lock().expect("circuit breaker lock");
+        let failed = match result {
+            Ok(_) => false,
+            Err(e) => is_endpoint_failure(e),
+        };
+        if !failed {
+            *state = CircuitState::default();
+            return
+        }
+        state.failures += 1;
+        if state.failures >= self.max_failures {
+            if state.open_until.is_none() {
+                self.n_pauses.fetch_add(1, Ordering::Relaxed);
+            }
+            state.open_until = Some(Instant::now() + self.cooldown);
+            state.probe_started = None;
+        }
+    }
+}
+
+/// whether an error indicates an unavailable or throttling endpoint, rather than a bad request
+fn is_endpoint_failure(error: &RpcClientError) -> bool {
+    match error.as_error_response() {
+        Some(JsonRpcError { code, .. }) => [429, -32005].contains(code),
+        None => !matches!(error.last_attempt(), RpcClientError::SerdeError(_)),
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for RpcClient {
+    type Error = RpcClientError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        if self.unsupported_methods.iter().any(|pattern| method_matches(pattern, method)) {
+            return Err(RpcClientError::UnsupportedMethod(method.to_string()))
+        }
+
+        let plain = self.logger.is_none() &&
+            self.recorder.is_none() &&
+            !self.lenient &&
+            self.in_flight.is_none() &&
+            self.breaker.is_none() &&
+            self.method_limits.is_none() &&
+            self.retries.is_none() &&
+            self.stats.is_none() &&
+            self.credits.is_none();
+        if let (Transport::Http(http), true) = (&self.transport, plain) {
+            return JsonRpcClient::request(http, method, params).await.map_err(Into::into);
+        }
+
+        let params = serde_json::to_value(params)?;
+        let start = Instant::now();
+        let result: Result<serde_json::Value, RpcClientError> = match &self.transport {
+            Transport::Http(_) => {
+                if let Some(method_limits) = &self.method_limits {
+                    method_limits.until_ready(method).await;
+                }
+                if let Some(breaker) = &self.breaker {
+                    breaker.admit().await;
+                }
+                let result = self.request_coalesced(method, &params).await;
+                if let Some(breaker) = &self.breaker {
+                    breaker.record(&result);
+                }
+                result
+            }
+            Transport::Offline(_) => self.request_coalesced(method, &params).await,
+        };
+        if let Some(logger) = &self.logger {
+            logger.log(method, &params, &result, start);
+        }
+        if let Some(recorder) = &self.recorder {
+            recorder.record(method, &params, &result);
+        }
+        if let Some(stats) = &self.stats {
+            stats.record(method, &result);
+        }
+        let mut result = result?;
+        if self.lenient {
+            normalize_response(method, &mut result);
+        }
+        Ok(serde_json::from_value(result)?)
+    }
+}
+
+/// logs JSON-RPC requests and responses
+pub struct RpcLogger {
+    output: Mutex<Box<dyn Write + Send>>,
+    max_response_length: usize,
+    n_requests: AtomicU64,
+}
+
+impl Debug for RpcLogger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RpcLogger").field("max_response_length", &self.max_response_length).finish()
+    }
+}
+
+impl RpcLogger {
+    /// create logger that writes to stderr
+    pub fn stderr(max_response_length: usize) -> RpcLogger {
+        RpcLogger::new(Box::new(std::io::stderr()), max_response_length)
+    }
+
+    /// create logger that writes to a file
+    pub fn file(path: &str, max_response_length: usize) -> Result<RpcLogger, RpcClientError> {
+        let file = std::fs::File::create(path).map_err(RpcClientError::LogFileError)?;
+        Ok(RpcLogger::new(Box::new(std::io::BufWriter::new(file)), max_response_length))
+    }
+
+    fn new(output: Box<dyn Write + Send>, max_response_length: usize) -> RpcLogger {
+        RpcLogger { output: Mutex::new(output), max_response_length, n_requests: AtomicU64::new(0) }
+    }
+
+    fn log(
+        &self,
+        method: &str,
+        params: &serde_json::Value,
+        result: &Result<serde_json::Value, RpcClientError>,
+        start: Instant,
+    ) {
+        let id = self.n_requests.fetch_add(1, Ordering::Relaxed);
+        let elapsed = start.elapsed().as_secs_f64() * 1000.0;
+        let response = match result {
+            Ok(value) => format!("ok {}", truncate(value.to_string(), self.max_response_length)),
+            Err(e) => format!("error {}", truncate(e.to_string(), self.max_response_length)),
+        };
+        let line = format!("[rpc #{}] {} {} {:.1}ms {}\n", id, method, params, elapsed, response);
+        if let Ok(mut output) = self.output.lock() {
+            let _ = output.write_all(line.as_bytes());
+            let _ = output.flush();
+        }
+    }
+}
+
+/// entry of a fixture file, one per line
+#[derive(Serialize, Deserialize)]
+struct FixtureEntry {
+    method: String,
+    params: serde_json::Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    result: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<FixtureError>,
+}
+
+/// error response of a fixture entry, mirrors `JsonRpcError` which is not serializable
+#[derive(Serialize, Deserialize)]
+struct FixtureError {
+    code: i64,
+    message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    data: Option<serde_json::Value>,
+}
+
+impl From<&JsonRpcError> for FixtureError {
+    fn from(error: &JsonRpcError) -> FixtureError {
+        FixtureError { code: error.code, message: error.message.clone(), data: error.data.clone() }
+    }
+}
+
+impl From<FixtureError> for JsonRpcError {
+    fn from(error: FixtureError) -> JsonRpcError {
+        JsonRpcError { code: error.code, message: error.message, data: error.data }
+    }
+}
+
+fn fixture_key(method: &str, params: &serde_json::Value) -> String {
+    format!("{} {}", method, params)
+}
+
+/// records JSON-RPC requests and responses into a fixture file
+pub struct RpcRecorder {
+    output: Mutex<Box<dyn Write + Send>>,
+}
+
+impl Debug for RpcRecorder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RpcRecorder").finish()
+    }
+}
+
+impl RpcRecorder {
+    /// create recorder that writes to a fixture file
+    pub fn file(path: &str) -> Result<RpcRecorder, RpcClientError> {
+        let file = std::fs::File::create(path).map_err(RpcClientError::FixtureFileError)?;
+        Ok(RpcRecorder { output: Mutex::new(Box::new(std::io::BufWriter::new(file))) })
+    }
+
+    fn record(
+        &self,
+        method: &str,
+        params: &serde_json::Value,
+        result: &Result<serde_json::Value, RpcClientError>,
+    ) {
+        let (result, error) = match result {
+            Ok(value) => (Some(value.clone()), None),
+            Err(e) => match e.as_error_response() {
+                Some(error) => (None, Some(error.into())),
+                // transport failures are not part of the chain data, do not record them
+                None => return,
+            },
+        };
+        let entry =
+            FixtureEntry { method: method.to_string(), params: params.clone(), result, error };
+        if let (Ok(line), Ok(mut output)) = (serde_json::to_string(&entry), self.output.lock()) {
+            let _ = output.write_all(line.as_bytes());
+            let _ = output.write_all(b"\n");
+            let _ = output.flush();
+        }
+    }
+}
+
+/// JSON-RPC responses recorded by `RpcRecorder`
+#[derive(Debug, Default)]
+pub struct RpcFixture {
+    responses: HashMap<String, Result<serde_json::Value, JsonRpcError>>,
+}
+
+impl RpcFixture {
+    /// load fixture from file
+    pub fn load(path: &str) -> Result<RpcFixture, RpcClientError> {
+        let file = std::fs::File::open(path).map_err(RpcClientError::FixtureFileError)?;
+        let mut responses = HashMap::new();
+        for line in std::io::BufReader::new(file).lines() {
+            let line = line.map_err(RpcClientError::FixtureFileError)?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry: FixtureEntry = serde_json::from_str(&line)?;
+            let response = match (entry.result, entry.error) {
+                (_, Some(error)) => Err(error.into()),
+                (result, None) => Ok(result.unwrap_or(serde_json::Value::Null)),
+            };
+            responses.insert(fixture_key(&entry.method, &entry.params), response);
+        }
+        Ok(RpcFixture { responses })
+    }
+}
+
+impl RpcSource for RpcFixture {
+    fn respond(
+        &self,
+        method: &str,
+        params: &serde_json::Value,
+    ) -> Result<serde_json::Value, RpcClientError> {
+        match self.responses.get(&fixture_key(method, params)) {
+            Some(Ok(value)) => Ok(value.clone()),
+            Some(Err(error)) => Err(RpcClientError::ReplayedError(error.clone())),
+            None => Err(RpcClientError::MissingFixture(fixture_key(method, params))),
+        }
+    }
+}
+
+fn truncate(mut s: String, max_length: usize) -> String {
+    if s.len() > max_length {
+        let mut end = max_length;
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        s.truncate(end);
+        s.push_str("...");
+    }
+    s
+}
+
+/// Error related to cryo's JSON-RPC client
+#[derive(Error, Debug)]
+pub enum RpcClientError {
+    /// Error from http transport
+    #[error(transparent)]
+    HttpError(#[from] HttpClientError),
+
+    /// Error serializing or deserializing JSON
+    #[error("Failed to (de)serialize JSON: {0}")]
+    SerdeError(#[from] serde_json::Error),
+
+    /// Invalid rpc url
+    #[error("Invalid rpc url")]
+    InvalidUrl,
+
+    /// Error opening log file
+    #[error("Failed to open rpc log file: {0}")]
+    LogFileError(#[source] std::io::Error),
+
+    /// Error reading or writing fixture file
+    #[error("Failed to access rpc fixture file: {0}")]
+    FixtureFileError(#[source] std::io::Error),
+
+    /// Request not present in replay fixture
+    #[error("No recorded response for request: {0}")]
+    MissingFixture(String),
+
+    /// Method not supported by offline source
+    #[error("Unsupported rpc method: {0}")]
+    UnsupportedMethod(String),
+
+    /// Request not sent because its credits would exceed the budget of the run
+    #[error("Credit budget of {0} exhausted")]
+    CreditBudgetExceeded(u64),
+
+    /// Invalid request parameters
+    #[error("Invalid rpc params: {0}")]
+    InvalidParams(String),
+
+    /// Error response recorded in replay fixture
+    #[error("Replayed rpc error: {0}")]
+    ReplayedError(JsonRpcError),
+
+    /// Http response rate limiting the request
+    #[error("Rate limited with http status {status}, retry after {retry_after:?}")]
+    RateLimited {
+        /// http status of the response
+        status: u16,
+        /// wait requested by the Retry-After header of the response
+        retry_after: Option<Duration>,
+    },
+
+    /// Error of a request shared with an identical concurrent request
+    #[error("{message}")]
+    Coalesced {
+        /// message of the original error
+        message: String,
+        /// json-rpc error response of the original error, if any
+        response: Option<JsonRpcError>,
+    },
+
+    /// Error of the last attempt of a request that was retried after being rate limited
+    #[error("{error}")]
+    Retried {
+        /// number of retries attempted before failing
+        retries: u32,
+        /// error of the last attempt
+        error: Box<RpcClientError>,
+    },
+}
+
+impl RpcClientError {
+    /// number of retries attempted before error
+    pub fn retries(&self) -> u32 {
+        match self {
+            RpcClientError::Retried { retries, .. } => *retries,
+            _ => 0,
+        }
+    }
+
+    /// error of the last attempt, without the retries that preceded it
+    pub fn last_attempt(&self) -> &RpcClientError {
+        match self {
+            RpcClientError::Retried { error, .. } => error,
+            e => e,
+        }
+    }
+
+    fn after_retries(self, retries: u32) -> RpcClientError {
+        match retries {
+            0 => self,
+            retries => RpcClientError::Retried { retries, error: Box::new(self) },
+        }
+    }
+}
+
+impl RpcError for RpcClientError {
+    fn as_error_response(&self) -> Option<&JsonRpcError> {
+        match self {
+            RpcClientError::HttpError(e) => e.as_error_response(),
+            RpcClientError::ReplayedError(e) => Some(e),
+            RpcClientError::Coalesced { response, .. } => response.as_ref(),
+            RpcClientError::Retried { error, .. } => error.as_error_response(),
+            _ => None,
+        }
+    }
+
+    fn as_serde_error(&self) -> Option<&serde_json::Error> {
+        match self {
+            RpcClientError::HttpError(e) => e.as_serde_error(),
+            RpcClientError::SerdeError(e) => Some(e),
+            RpcClientError::Retried { error, .. } => error.as_serde_error(),
+            _ => None,
+        }
+    }
+}
+
+impl From<RpcClientError> for ProviderError {
+    fn from(e: RpcClientError) -> Self {
+        ProviderError::JsonRpcClientError(Box::new(e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufReader, Read},
+        sync::Arc,
+    };
+
+    use super::*;
+
+    /// serve one http response per connection, in order, returning the url of the server
+    fn serve(responses: Vec<String>) -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if let Some(value) = line.to_lowercase().strip_prefix("content-length:") {
+                        length = value.trim().parse().unwrap();
+                    }
+                    if line == "\r\n" {
+                        break
+                    }
+                }
+                reader.read_exact(&mut vec![0; length]).unwrap();
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        url
+    }
+
+    fn response(status: &str, headers: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 {}\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            headers,
+            body.len(),
+            body
+        )
+    }
+
+    #[tokio::test]
+    async fn credits_charged_per_attempt() {
+        let url = serve(vec![
+            response("429 Too Many Requests", "Retry-After: 0\r\n", ""),
+            response("200 OK", "", r#"{"jsonrpc":"2.0","id":1,"result":"0x1"}"#),
+        ]);
+        let client = RpcClient::new(&url)
+            .unwrap()
+            .with_retries(3, Duration::from_secs(1))
+            .unwrap()
+            .with_credits(vec![("eth_blockNumber".to_string(), 10)], None);
+        let number: String = JsonRpcClient::request(&client, "eth_blockNumber", ()).await.unwrap();
+        assert_eq!(number, "0x1");
+        assert_eq!(client.credits_spent(), vec![("eth_blockNumber".to_string(), 20)]);
+    }
+
+    /// offline source counting its calls, slow enough that concurrent requests overlap
+    #[derive(Debug, Clone, Default)]
+    struct CountingSource {
+        calls: Arc<AtomicU64>,
+    }
+
+    impl RpcSource for CountingSource {
+        fn respond(
+            &self,
+            _method: &str,
+            _params: &serde_json::Value,
+        ) -> Result<serde_json::Value, RpcClientError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(200));
+            Ok(serde_json::json!("0x1"))
+        }
+    }
+
+    /// send two identical requests, the second while the first is being answered
+    async fn request_twice(client: RpcClient, source: &CountingSource) -> u64 {
+        let client = Arc::new(client);
+        let request = |client: Arc<RpcClient>| {
+            tokio::spawn(async move {
+                let params = ("0x1", false);
+                JsonRpcClient::request::<_, String>(&*client, "eth_getBlockByNumber", params).await
+            })
+        };
+        let first = request(Arc::clone(&client));
+        while source.calls.load(Ordering::SeqCst) == 0 {
+            tokio::task::yield_now().await;
+        }
+        let second = request(client);
+        assert_eq!(first.await.unwrap().unwrap(), "0x1");
+        assert_eq!(second.await.unwrap().unwrap(), "0x1");
+        source.calls.load(Ordering::SeqCst)
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn identical_concurrent_requests_coalesce() {
+        let source = CountingSource::default();
+        let client = RpcClient::offline(source.clone()).with_coalescing();
+        assert_eq!(request_twice(client, &source).await, 1);
+
+        let source = CountingSource::default();
+        let client = RpcClient::offline(source.clone());
+        assert_eq!(request_twice(client, &source).await, 2);
+    }
+}
@@ -32,6 +32,27 @@ impl Table {
     pub fn columns(&self) -> Vec<&str> {
         self.columns.keys().map(|x| x.as_str()).collect()
     }
+
+    /// store uint32 columns as uint64, for chains whose numbers exceed the uint32 range
+    pub fn widen_integers(&mut self) {
+        for ctype in self.columns.values_mut() {
+            if *ctype == ColumnType::UInt32 {
+                *ctype = ColumnType::UInt64;
+            }
+        }
+    }
+
+    /// remove lossy float columns, returning their names
+    pub fn drop_float_columns(&mut self) -> Vec<String> {
+        let floats = self
+            .columns
+            .iter()
+            .filter(|(_, ctype)| **ctype == ColumnType::Float64)
+            .map(|(name, _)| name.clone())
+            .collect();
+        self.columns.retain(|_, ctype| *ctype != ColumnType::Float64);
+        floats
+    }
 }
 
 /// datatype of column
@@ -47,6 +68,8 @@ pub enum ColumnType {
     Int64,
     /// Float64 column type
     Float64,
+    /// Boolean column type
+    Boolean,
     /// Decimal128 column type
     Decimal128,
     /// String column type
@@ -66,6 +89,7 @@ impl ColumnType {
             ColumnType::Int32 => "int32",
             ColumnType::Int64 => "int64",
             ColumnType::Float64 => "float64",
+            ColumnType::Boolean => "boolean",
             ColumnType::Decimal128 => "decimal128",
             ColumnType::String => "string",
             ColumnType::Binary => "binary",
@@ -0,0 +1,120 @@
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use fs2::FileExt;
+
+use crate::types::ParseError;
+
+/// requests per second limit shared by the cryo processes of a machine through a file
+///
+/// the file holds the time at which the next request may be sent. each request locks the file,
+/// reserves the next slot, and sleeps until its slot, so processes sharing a file together stay
+/// within one provider quota instead of each assuming the full quota. processes sharing a file
+/// should use the same limit
+#[derive(Debug)]
+pub struct SharedRateLimiter {
+    path: PathBuf,
+    /// time between requests
+    interval: Duration,
+    /// how far the next slot may lag behind the present, allowing bursts of requests
+    tolerance: Duration,
+}
+
+impl SharedRateLimiter {
+    /// open or create the file of a shared limit of requests_per_second, with bursts of burst
+    pub fn open(
+        path: &Path,
+        requests_per_second: u32,
+        burst: u32,
+    ) -> Result<SharedRateLimiter, ParseError> {
+        if requests_per_second == 0 || burst == 0 {
+            return Err(ParseError::ParseError("shared rate limit must be positive".to_string()))
+        }
+        std::fs::OpenOptions::new().create(true).append(true).open(path).map_err(|e| {
+            ParseError::ParseError(format!("could not open {}: {}", path.display(), e))
+        })?;
+        let interval = Duration::from_secs_f64(1.0 / requests_per_second as f64);
+        let tolerance = interval * (burst - 1);
+        Ok(SharedRateLimiter { path: path.to_path_buf(), interval, tolerance })
+    }
+
+    /// wait until the next request may be sent
+    ///
+    /// if the file cannot be used, e.g. after it was deleted, requests proceed without waiting
+    pub async fn until_ready(&self) {
+        let path = self.path.clone();
+        let (interval, tolerance) = (self.interval, self.tolerance);
+        let wait = tokio::task::spawn_blocking(move || reserve(&path, interval, tolerance)).await;
+        if let Ok(Ok(wait)) = wait {
+            if !wait.is_zero() {
+                tokio::time::sleep(wait).await;
+            }
+        }
+    }
+}
+
+/// reserve the next slot of a shared limit, returning how long to wait for it
+fn reserve(path: &Path, interval: Duration, tolerance: Duration) -> std::io::Result<Duration> {
+    let mut file = std::fs::OpenOptions::new().read(true).write(true).open(path)?;
+    file.lock_exclusive()?;
+    let result = reserve_locked(&mut file, interval, tolerance);
+    let _ = file.unlock();
+    result
+}
+
+fn reserve_locked(
+    file: &mut File,
+    interval: Duration,
+    tolerance: Duration,
+) -> std::io::Result<Duration> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos() as u64;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    let next_slot = contents.trim().parse::<u64>().unwrap_or_default().max(now);
+
+    // requests within the tolerance go out at once, later ones wait for their slot
+    let tolerance = tolerance.as_nanos() as u64;
+    let wait = next_slot.saturating_sub(now).saturating_sub(tolerance);
+    let next_slot = next_slot + interval.as_nanos() as u64;
+
+    file.set_len(0)?;
+    file.seek(SeekFrom::Start(0))?;
+    write!(file, "{}", next_slot)?;
+    Ok(Duration::from_nanos(wait))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn limiters_sharing_a_file_share_the_rate() {
+        let path = std::env::temp_dir().join(format!("cryo_shared_limit_{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let first = SharedRateLimiter::open(&path, 20, 1).unwrap();
+        let second = SharedRateLimiter::open(&path, 20, 1).unwrap();
+
+        // 20 requests at 20 per second take at least 19 intervals, however they are split
+        let start = Instant::now();
+        let requests = (0..10).flat_map(|_| [first.until_ready(), second.until_ready()]);
+        futures::future::join_all(requests).await;
+        assert!(start.elapsed() >= Duration::from_millis(900), "{:?}", start.elapsed());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn invalid_limits_are_reported() {
+        let path =
+            std::env::temp_dir().join(format!("cryo_shared_limit_zero_{}", std::process::id()));
+        let error = SharedRateLimiter::open(&path, 0, 1).unwrap_err();
+        assert_eq!(error.to_string(), "shared rate limit must be positive");
+        let error = SharedRateLimiter::open(Path::new("/nonexistent/limit"), 20, 1).unwrap_err();
+        assert!(error.to_string().contains("could not open /nonexistent/limit"), "{}", error);
+    }
+}
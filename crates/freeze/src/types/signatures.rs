@@ -0,0 +1,250 @@
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::types::{FileError, MANIFEST_NAME};
+
+/// tool used to sign manifests
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignatureScheme {
+    /// minisign, signature written to cryo_manifest.jsonl.minisig
+    Minisign,
+    /// gpg detached armored signature, written to cryo_manifest.jsonl.asc
+    Gpg,
+}
+
+impl SignatureScheme {
+    /// parse scheme from its name
+    pub fn from_name(name: &str) -> Option<SignatureScheme> {
+        match name {
+            "minisign" => Some(SignatureScheme::Minisign),
+            "gpg" => Some(SignatureScheme::Gpg),
+            _ => None,
+        }
+    }
+
+    /// extension appended to the manifest name for its signature
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SignatureScheme::Minisign => "minisig",
+            SignatureScheme::Gpg => "asc",
+        }
+    }
+}
+
+/// signer of the manifest of an output directory
+///
+/// the manifest records the checksum of every file, so its signature covers all committed files
+#[derive(Clone, Debug)]
+pub struct Signer {
+    /// tool used to sign
+    pub scheme: SignatureScheme,
+    /// secret key file for minisign, secret key file or key id for gpg
+    pub key: String,
+}
+
+impl Signer {
+    /// sign the manifest of output directory, replacing any previous signature
+    ///
+    /// must only be called while holding the output directory lock
+    pub fn sign_manifest(&self, output_dir: &str) -> Result<PathBuf, FileError> {
+        let manifest = Path::new(output_dir).join(MANIFEST_NAME);
+        let signature = signature_path(output_dir, self.scheme);
+        let status = match self.scheme {
+            SignatureScheme::Minisign => Command::new("minisign")
+                .arg("-S")
+                .arg("-s")
+                .arg(&self.key)
+                .arg("-m")
+                .arg(&manifest)
+                .arg("-x")
+                .arg(&signature)
+                .status(),
+            SignatureScheme::Gpg => {
+                let mut command = Command::new("gpg");
+                command.args(["--batch", "--yes", "--armor", "--detach-sign"]);
+                let keyring = match Path::new(&self.key).is_file() {
+                    true => Some(GpgKeyring::import(&self.key)?),
+                    false => {
+                        command.arg("--local-user").arg(&self.key);
+                        None
+                    }
+                };
+                if let Some(keyring) = &keyring {
+                    command.arg("--homedir").arg(&keyring.home);
+                }
+                command.arg("--output").arg(&signature).arg(&manifest).status()
+            }
+        };
+        match status {
+            Ok(status) if status.success() => Ok(signature),
+            Ok(status) => Err(sign_error(format!("signing exited with {}", status))),
+            Err(e) => Err(sign_error(format!("could not run {:?}: {}", self.scheme, e))),
+        }
+    }
+}
+
+/// path of the manifest signature of output directory
+pub fn signature_path(output_dir: &str, scheme: SignatureScheme) -> PathBuf {
+    Path::new(output_dir).join(format!("{}.{}", MANIFEST_NAME, scheme.extension()))
+}
+
+/// verify the manifest signature of output directory with a public key
+///
+/// for gpg, the public key is a key file, or a key id or fingerprint of the local keyring. the
+/// signature must be made by that key, a valid signature of another key of the keyring fails
+pub fn verify_manifest(
+    output_dir: &str,
+    scheme: SignatureScheme,
+    public_key: &str,
+) -> Result<(), FileError> {
+    let manifest = Path::new(output_dir).join(MANIFEST_NAME);
+    let signature = signature_path(output_dir, scheme);
+    match scheme {
+        SignatureScheme::Minisign => run_tool(
+            Command::new("minisign")
+                .arg("-V")
+                .arg("-p")
+                .arg(public_key)
+                .arg("-m")
+                .arg(&manifest)
+                .arg("-x")
+                .arg(&signature),
+        ),
+        SignatureScheme::Gpg => {
+            let mut command = Command::new("gpg");
+            command.args(["--batch", "--status-fd", "1"]);
+            // a keyring holding only the key file can only validate signatures of that key
+            let (keyring, key_id) = match Path::new(public_key).is_file() {
+                true => (Some(GpgKeyring::import(public_key)?), None),
+                false => (None, Some(parse_gpg_key_id(public_key)?)),
+            };
+            if let Some(keyring) = &keyring {
+                command.arg("--homedir").arg(&keyring.home);
+            }
+            let output = command
+                .arg("--verify")
+                .arg(&signature)
+                .arg(&manifest)
+                .output()
+                .map_err(|e| sign_error(format!("could not run {:?}: {}", command, e)))?;
+            if !output.status.success() {
+                return Err(sign_error(format!("{:?} exited with {}", command, output.status)))
+            }
+            let status = String::from_utf8_lossy(&output.stdout);
+            let fingerprints = valid_signature_fingerprints(&status);
+            let signed_by_key = match &key_id {
+                Some(key_id) => fingerprints.iter().any(|fpr| fpr.ends_with(key_id.as_str())),
+                None => !fingerprints.is_empty(),
+            };
+            match signed_by_key {
+                true => Ok(()),
+                false => Err(sign_error(format!(
+                    "{} is not signed by {}",
+                    signature.display(),
+                    public_key
+                ))),
+            }
+        }
+    }
+}
+
+/// normalize a gpg key id or fingerprint to uppercase hex, e.g. 0xdeadbeef to DEADBEEF
+fn parse_gpg_key_id(key: &str) -> Result<String, FileError> {
+    let hex: String = key.trim_start_matches("0x").chars().filter(|c| *c != ' ').collect();
+    match hex.len() >= 8 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        true => Ok(hex.to_uppercase()),
+        false => Err(sign_error(format!(
+            "gpg public key must be a key file, key id, or fingerprint: {}",
+            key
+        ))),
+    }
+}
+
+/// fingerprints of the signing keys and their primary keys in VALIDSIG lines of gpg status output
+fn valid_signature_fingerprints(status: &str) -> Vec<String> {
+    let mut fingerprints = Vec::new();
+    for line in status.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if let ["[GNUPG:]", "VALIDSIG", fingerprint, rest @ ..] = fields.as_slice() {
+            fingerprints.push(fingerprint.to_uppercase());
+            if let Some(primary) = rest.last() {
+                fingerprints.push(primary.to_uppercase());
+            }
+        }
+    }
+    fingerprints
+}
+
+/// throwaway gpg home directory holding an imported key file, removed when dropped
+struct GpgKeyring {
+    home: PathBuf,
+}
+
+impl GpgKeyring {
+    fn import(key_file: &str) -> Result<GpgKeyring, FileError> {
+        let home = unique_temp_dir("cryo_gpg")?;
+        let keyring = GpgKeyring { home };
+        run_tool(
+            Command::new("gpg")
+                .arg("--homedir")
+                .arg(&keyring.home)
+                .args(["--batch", "--import"])
+                .arg(key_file),
+        )?;
+        Ok(keyring)
+    }
+}
+
+/// create a new directory in the temp directory, unique across the calls of every process
+fn unique_temp_dir(prefix: &str) -> Result<PathBuf, FileError> {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    loop {
+        let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let dir = std::env::temp_dir().join(format!("{}_{}_{}", prefix, std::process::id(), n));
+        match std::fs::create_dir(&dir) {
+            Ok(()) => return Ok(dir),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(sign_error(e.to_string())),
+        }
+    }
+}
+
+impl Drop for GpgKeyring {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.home);
+    }
+}
+
+/// hex sha-256 checksum of a file
+pub fn sha256_file(path: &str) -> Result<String, FileError> {
+    let read_error = |_e: std::io::Error| FileError::FileReadError(path.to_string());
+    let mut file = std::fs::File::open(path).map_err(read_error)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 1 << 16];
+    loop {
+        let n = file.read(&mut buffer).map_err(read_error)?;
+        if n == 0 {
+            break
+        }
+        hasher.update(&buffer[..n]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn run_tool(command: &mut Command) -> Result<(), FileError> {
+    match command.status() {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(sign_error(format!("{:?} exited with {}", command, status))),
+        Err(e) => Err(sign_error(format!("could not run {:?}: {}", command, e))),
+    }
+}
+
+fn sign_error(message: String) -> FileError {
+    FileError::SignatureError(message)
+}
@@ -5,8 +5,14 @@ use governor::{
     clock::DefaultClock,
     middleware::NoOpMiddleware,
     state::{direct::NotKeyed, InMemoryState},
+    Jitter,
+};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::types::{
+    BeaconClient, GenesisAlloc, LocalLogs, RawRpcRequest, RpcClient, SharedRateLimiter,
+    SourcifyClient, TokenDecimals, Vm,
 };
-use tokio::sync::Semaphore;
 
 /// RateLimiter based on governor crate
 pub type RateLimiter = governor::RateLimiter<NotKeyed, InMemoryState, DefaultClock, NoOpMiddleware>;
@@ -15,17 +21,100 @@ pub type RateLimiter = governor::RateLimiter<NotKeyed, InMemoryState, DefaultClo
 #[derive(Clone)]
 pub struct Source {
     /// provider data source
-    pub provider: Arc<Provider<Http>>,
+    pub provider: Arc<Provider<RpcClient>>,
     /// semaphore for controlling concurrency
     pub semaphore: Option<Arc<Semaphore>>,
+    /// semaphore reserved for metadata requests, so they do not queue behind bulk requests
+    pub metadata_semaphore: Option<Arc<Semaphore>>,
     /// rate limiter for controlling request rate
     pub rate_limiter: Option<Arc<RateLimiter>>,
+    /// rate limit shared with other cryo processes, applied after rate_limiter
+    pub shared_rate_limiter: Option<Arc<SharedRateLimiter>>,
+    /// random delay added to rate limited requests, so they do not synchronize into bursts
+    pub rate_jitter: Jitter,
     /// chain_id of network
     pub chain_id: u64,
     /// number of blocks per log request
     pub inner_request_size: u64,
     /// Maximum chunks collected concurrently
     pub max_concurrent_chunks: u64,
+    /// contract verification lookups, shared across chunks
+    pub sourcify: Arc<SourcifyClient>,
+    /// erc20 decimals, shared across chunks
+    pub token_decimals: Arc<TokenDecimals>,
+    /// beacon node for consensus layer datasets
+    pub beacon: Option<Arc<BeaconClient>>,
+    /// rpc dialect of the source
+    pub vm: Vm,
+    /// parquet logs read instead of eth_getLogs, for datasets derived from logs
+    pub local_logs: Option<Arc<LocalLogs>>,
+    /// genesis allocation read from a genesis.json instead of a state dump of block 0
+    pub genesis: Option<Arc<GenesisAlloc>>,
+    /// json-rpc request sent per block by the raw_rpc dataset
+    pub raw_rpc: Option<RawRpcRequest>,
+    /// abandon a chunk whose collection takes longer than this
+    pub chunk_timeout: Option<std::time::Duration>,
+    /// fraction of chunks collected a second time to check that responses are deterministic
+    pub audit_fraction: Option<f64>,
+}
+
+/// priority class of an rpc request
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RequestPriority {
+    /// data requests issued per block, transaction, or log range, e.g. trace_block
+    Bulk,
+    /// small requests such as eth_blockNumber, eth_chainId, or token decimals
+    Metadata,
+}
+
+impl Source {
+    /// wait until a request of a priority class may be sent
+    ///
+    /// bulk requests queue on the main semaphore, which admits waiters in order. metadata
+    /// requests queue on a small reserved semaphore instead, so they are not stuck behind
+    /// thousands of queued bulk requests. both priorities then wait on the rate limiters, so
+    /// metadata requests only jump the queue and never exceed the configured request rate
+    pub async fn permit_request(&self, priority: RequestPriority) -> Option<OwnedSemaphorePermit> {
+        let semaphore = match priority {
+            RequestPriority::Bulk => &self.semaphore,
+            RequestPriority::Metadata => &self.metadata_semaphore,
+        };
+        let permit = match semaphore {
+            Some(semaphore) => Arc::clone(semaphore).acquire_owned().await.ok(),
+            None => None,
+        };
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.until_ready_with_jitter(self.rate_jitter).await;
+        }
+        if let Some(limiter) = &self.shared_rate_limiter {
+            limiter.until_ready().await;
+        }
+        permit
+    }
+
+    /// number of the latest block, requested with metadata priority
+    pub async fn latest_block_number(&self) -> Result<u64, ProviderError> {
+        let _permit = self.permit_request(RequestPriority::Metadata).await;
+        self.provider.get_block_number().await.map(|n| n.as_u64())
+    }
+
+    /// limit the concurrency of bulk requests with a semaphore owned by the caller
+    ///
+    /// sources given the same semaphore share one concurrency limit, which the caller can also
+    /// acquire permits of for its own rpc requests
+    pub fn with_semaphore(mut self, semaphore: Arc<Semaphore>) -> Source {
+        self.semaphore = Some(semaphore);
+        self
+    }
+
+    /// limit the rate of bulk requests with a rate limiter owned by the caller
+    ///
+    /// sources given the same rate limiter share one request rate, which the caller's own rpc
+    /// requests can also wait on with `until_ready`
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> Source {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
 }
 
 // impl Source {
@@ -0,0 +1,94 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Mutex,
+};
+
+use ethers::prelude::*;
+use serde::Deserialize;
+
+use crate::types::CollectError;
+
+/// default url of sourcify server
+pub const DEFAULT_SOURCIFY_URL: &str = "https://sourcify.dev/server";
+
+/// maximum number of addresses checked per sourcify request
+const SOURCIFY_BATCH_SIZE: usize = 50;
+
+/// client for looking up whether contracts are verified on sourcify
+///
+/// lookups are cached for the lifetime of the client, so each address is requested once per run
+#[derive(Debug)]
+pub struct SourcifyClient {
+    url: String,
+    client: reqwest::Client,
+    cache: Mutex<HashMap<H160, bool>>,
+}
+
+#[derive(Deserialize)]
+struct CheckResult {
+    address: H160,
+    status: String,
+}
+
+impl SourcifyClient {
+    /// create client for sourcify server at url
+    pub fn new(url: &str) -> SourcifyClient {
+        SourcifyClient {
+            url: url.trim_end_matches('/').to_string(),
+            client: reqwest::Client::new(),
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// whether each address has a fully or partially verified contract on chain
+    pub async fn is_verified(
+        &self,
+        addresses: &[H160],
+        chain_id: u64,
+    ) -> Result<HashMap<H160, bool>, CollectError> {
+        let unchecked: Vec<H160> = {
+            let cache = self.cache.lock().expect("sourcify cache lock");
+            let unique: HashSet<H160> = addresses.iter().copied().collect();
+            unique.into_iter().filter(|address| !cache.contains_key(address)).collect()
+        };
+        for batch in unchecked.chunks(SOURCIFY_BATCH_SIZE) {
+            let verified = self.check_batch(batch, chain_id).await?;
+            let mut cache = self.cache.lock().expect("sourcify cache lock");
+            for address in batch.iter() {
+                cache.insert(*address, verified.contains(address));
+            }
+        }
+        let cache = self.cache.lock().expect("sourcify cache lock");
+        Ok(addresses.iter().map(|address| (*address, cache[address])).collect())
+    }
+
+    async fn check_batch(
+        &self,
+        addresses: &[H160],
+        chain_id: u64,
+    ) -> Result<HashSet<H160>, CollectError> {
+        let addresses: Vec<String> =
+            addresses.iter().map(|address| format!("{:?}", address)).collect();
+        let request_error = |e: reqwest::Error| {
+            CollectError::CollectError(format!("sourcify request failed: {}", e))
+        };
+        let body = self
+            .client
+            .get(format!("{}/check-by-addresses", self.url))
+            .query(&[("addresses", addresses.join(",")), ("chainIds", chain_id.to_string())])
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(request_error)?
+            .text()
+            .await
+            .map_err(request_error)?;
+        let results: Vec<CheckResult> = serde_json::from_str(&body)
+            .map_err(|e| CollectError::CollectError(format!("invalid sourcify response: {}", e)))?;
+        Ok(results
+            .into_iter()
+            .filter(|result| result.status == "perfect" || result.status == "partial")
+            .map(|result| result.address)
+            .collect())
+    }
+}
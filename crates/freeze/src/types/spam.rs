@@ -0,0 +1,70 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use ethers::prelude::*;
+
+use crate::types::topics::TRANSFER_TOPIC;
+
+/// Heuristics for dropping spam token Transfer logs during collection
+///
+/// drops Transfer logs of listed spam contracts, and zero-value erc20 Transfer logs of
+/// contracts that send zero-value transfers to at least `min_recipients` distinct recipients
+/// within a chunk, a pattern of address poisoning and airdrop spam
+#[derive(Clone, Debug)]
+pub struct SpamFilter {
+    /// contracts whose Transfer logs are always dropped
+    pub contracts: HashSet<H160>,
+    /// distinct recipients of zero-value transfers within a chunk above which a contract is spam
+    pub min_recipients: usize,
+    n_dropped: Arc<AtomicU64>,
+}
+
+impl SpamFilter {
+    /// create spam filter
+    pub fn new(contracts: HashSet<H160>, min_recipients: usize) -> SpamFilter {
+        SpamFilter { contracts, min_recipients, n_dropped: Arc::new(AtomicU64::new(0)) }
+    }
+
+    /// number of logs dropped so far, across all chunks
+    pub fn n_dropped(&self) -> u64 {
+        self.n_dropped.load(Ordering::Relaxed)
+    }
+
+    /// drop spam Transfer logs from the logs of a chunk
+    pub(crate) fn filter_logs(&self, logs: Vec<Log>) -> Vec<Log> {
+        let mut recipients: HashMap<H160, HashSet<H256>> = HashMap::new();
+        for log in logs.iter().filter(|log| is_zero_value_transfer(log)) {
+            recipients.entry(log.address).or_default().insert(log.topics[2]);
+        }
+        let spammers: HashSet<H160> = recipients
+            .into_iter()
+            .filter(|(_, recipients)| recipients.len() >= self.min_recipients)
+            .map(|(contract, _)| contract)
+            .collect();
+
+        let n_logs = logs.len();
+        let logs: Vec<Log> = logs
+            .into_iter()
+            .filter(|log| {
+                let is_transfer = log.topics.first() == Some(&TRANSFER_TOPIC);
+                let is_listed = is_transfer && self.contracts.contains(&log.address);
+                let is_spammed = spammers.contains(&log.address) && is_zero_value_transfer(log);
+                !(is_listed || is_spammed)
+            })
+            .collect();
+        self.n_dropped.fetch_add((n_logs - logs.len()) as u64, Ordering::Relaxed);
+        logs
+    }
+}
+
+/// erc20 Transfer with a value of zero, erc721 Transfers have a fourth topic and no value
+fn is_zero_value_transfer(log: &Log) -> bool {
+    log.topics.len() == 3 &&
+        log.topics[0] == TRANSFER_TOPIC &&
+        log.data.iter().all(|byte| *byte == 0)
+}
@@ -0,0 +1,94 @@
+use ethers::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::types::{CollectError, RpcClient};
+
+/// block returned by starknet_getBlockWithTxs
+#[derive(Debug, Deserialize, Serialize)]
+pub struct StarknetBlock {
+    /// block status, e.g. ACCEPTED_ON_L1
+    pub status: Option<String>,
+    /// block hash
+    pub block_hash: U256,
+    /// parent block hash
+    pub parent_hash: U256,
+    /// block number
+    pub block_number: u64,
+    /// state root after the block
+    pub new_root: U256,
+    /// block timestamp
+    pub timestamp: u64,
+    /// address of the sequencer that produced the block
+    pub sequencer_address: U256,
+    /// l1 gas price used to charge fees
+    pub l1_gas_price: Option<StarknetGasPrice>,
+    /// starknet protocol version of the block
+    pub starknet_version: Option<String>,
+    /// transactions of the block
+    pub transactions: Vec<StarknetTransaction>,
+}
+
+/// l1 gas price of a starknet block
+#[derive(Debug, Deserialize, Serialize)]
+pub struct StarknetGasPrice {
+    /// price in wei
+    pub price_in_wei: Option<U256>,
+    /// price in fri
+    pub price_in_fri: Option<U256>,
+}
+
+/// transaction of a starknet block, fields vary by transaction type and version
+#[derive(Debug, Deserialize, Serialize)]
+pub struct StarknetTransaction {
+    /// transaction hash
+    pub transaction_hash: U256,
+    /// transaction type, e.g. INVOKE or L1_HANDLER
+    #[serde(rename = "type")]
+    pub transaction_type: String,
+    /// transaction version
+    pub version: Option<U256>,
+    /// account sending the transaction
+    pub sender_address: Option<U256>,
+    /// contract called, for l1 handler and v0 invoke transactions
+    pub contract_address: Option<U256>,
+    /// account nonce
+    pub nonce: Option<U256>,
+    /// maximum fee, for transactions before v3
+    pub max_fee: Option<U256>,
+    /// entry point called, for l1 handler and v0 invoke transactions
+    pub entry_point_selector: Option<U256>,
+    /// class hash, for declare and deploy account transactions
+    pub class_hash: Option<U256>,
+    /// calldata felts
+    #[serde(default)]
+    pub calldata: Vec<U256>,
+    /// signature felts
+    #[serde(default)]
+    pub signature: Vec<U256>,
+}
+
+/// fetch a starknet block with its transactions
+pub async fn get_starknet_block(
+    provider: &Provider<RpcClient>,
+    block_number: u64,
+) -> Result<StarknetBlock, CollectError> {
+    let params = [serde_json::json!({ "block_number": block_number })];
+    provider
+        .request("starknet_getBlockWithTxs", params)
+        .await
+        .map_err(|e| CollectError::rpc("starknet_getBlockWithTxs", e))
+}
+
+/// fetch the chain id of a starknet node, e.g. SN_MAIN encoded as an integer
+pub async fn get_starknet_chain_id(provider: &Provider<RpcClient>) -> Result<u64, ProviderError> {
+    let params: [u8; 0] = [];
+    let chain_id: U256 = provider.request("starknet_chainId", params).await?;
+    Ok(chain_id.low_u64())
+}
+
+/// felt as 32 big-endian bytes
+pub fn felt_to_bytes(felt: &U256) -> Vec<u8> {
+    let mut bytes = vec![0u8; 32];
+    felt.to_big_endian(&mut bytes);
+    bytes
+}
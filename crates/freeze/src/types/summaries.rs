@@ -1,5 +1,5 @@
-use crate::types::Datatype;
-use std::collections::HashMap;
+use crate::types::{Datatype, FreezeError};
+use std::collections::{BTreeMap, HashMap};
 
 /// Summary of freeze operation
 pub struct FreezeSummary {
@@ -11,6 +11,16 @@ pub struct FreezeSummary {
     pub n_errored: u64,
     /// paths
     pub paths: HashMap<Datatype, Vec<String>>,
+    /// errors encountered by chunks
+    pub errors: Vec<FreezeError>,
+    /// decisions given at prompts about failed chunks, e.g. `logs 00000000_to_00000999: retry`
+    pub decisions: Vec<String>,
+    /// number of chunks collected a second time to audit the determinism of the provider
+    pub n_audited: u64,
+    /// differences found in audited chunks, e.g. `logs 00000000_to_00000999: 120 rows, then 118`
+    pub audit_mismatches: Vec<String>,
+    /// rows of each chunk of each datatype, recorded with --count-only
+    pub row_counts: HashMap<Datatype, BTreeMap<String, u64>>,
 }
 
 pub(crate) trait FreezeSummaryAgg {
@@ -24,6 +34,11 @@ impl FreezeSummaryAgg for Vec<FreezeChunkSummary> {
         let mut n_errored: u64 = 0;
 
         let mut paths = HashMap::new();
+        let mut errors = Vec::new();
+        let mut decisions = Vec::new();
+        let mut n_audited = 0;
+        let mut audit_mismatches = Vec::new();
+        let mut row_counts: HashMap<Datatype, BTreeMap<String, u64>> = HashMap::new();
         for chunk_summary in self {
             if chunk_summary.skipped {
                 n_skipped += 1;
@@ -35,9 +50,30 @@ impl FreezeSummaryAgg for Vec<FreezeChunkSummary> {
             for (datatype, path) in chunk_summary.paths {
                 paths.entry(datatype).or_insert_with(Vec::new).push(path);
             }
+            if let Some(error) = chunk_summary.error {
+                errors.push(error);
+            }
+            decisions.extend(chunk_summary.decisions);
+            if let Some(mismatches) = chunk_summary.audit {
+                n_audited += 1;
+                audit_mismatches.extend(mismatches);
+            }
+            for (datatype, chunk, n_rows) in chunk_summary.row_counts {
+                row_counts.entry(datatype).or_default().insert(chunk, n_rows);
+            }
         }
 
-        FreezeSummary { n_completed, n_skipped, n_errored, paths }
+        FreezeSummary {
+            n_completed,
+            n_skipped,
+            n_errored,
+            paths,
+            errors,
+            decisions,
+            n_audited,
+            audit_mismatches,
+            row_counts,
+        }
     }
 }
 
@@ -49,18 +85,71 @@ pub struct FreezeChunkSummary {
     pub errored: bool,
     /// output paths
     pub paths: HashMap<Datatype, String>,
+    /// error encountered by chunk
+    pub error: Option<FreezeError>,
+    /// decisions given at prompts about failures of chunk
+    pub decisions: Vec<String>,
+    /// differences found when collecting the chunk a second time, if the chunk was audited
+    pub audit: Option<Vec<String>>,
+    /// datatype, chunk stub, and number of rows of each dataframe counted with --count-only
+    pub row_counts: Vec<(Datatype, String, u64)>,
 }
 
 impl FreezeChunkSummary {
     pub(crate) fn success(paths: HashMap<Datatype, String>) -> FreezeChunkSummary {
-        FreezeChunkSummary { skipped: false, errored: false, paths }
+        FreezeChunkSummary {
+            skipped: false,
+            errored: false,
+            paths,
+            error: None,
+            decisions: vec![],
+            audit: None,
+            row_counts: vec![],
+        }
     }
 
-    pub(crate) fn error(paths: HashMap<Datatype, String>) -> FreezeChunkSummary {
-        FreezeChunkSummary { skipped: false, errored: true, paths }
+    pub(crate) fn error(
+        paths: HashMap<Datatype, String>,
+        error: FreezeError,
+    ) -> FreezeChunkSummary {
+        FreezeChunkSummary {
+            skipped: false,
+            errored: true,
+            paths,
+            error: Some(error),
+            decisions: vec![],
+            audit: None,
+            row_counts: vec![],
+        }
     }
 
     pub(crate) fn skip(paths: HashMap<Datatype, String>) -> FreezeChunkSummary {
-        FreezeChunkSummary { skipped: true, errored: false, paths }
+        FreezeChunkSummary {
+            skipped: true,
+            errored: false,
+            paths,
+            error: None,
+            decisions: vec![],
+            audit: None,
+            row_counts: vec![],
+        }
+    }
+
+    pub(crate) fn with_decisions(mut self, decisions: Vec<String>) -> FreezeChunkSummary {
+        self.decisions = decisions;
+        self
+    }
+
+    pub(crate) fn with_audit(mut self, audit: Option<Vec<String>>) -> FreezeChunkSummary {
+        self.audit = audit;
+        self
+    }
+
+    pub(crate) fn with_row_counts(
+        mut self,
+        row_counts: Vec<(Datatype, String, u64)>,
+    ) -> FreezeChunkSummary {
+        self.row_counts = row_counts;
+        self
     }
 }
@@ -0,0 +1,429 @@
+use std::sync::Arc;
+
+use ethers::prelude::Provider;
+use governor::Jitter;
+use polars::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    types::{
+        conversions::{NarrowU32, ToVecHex},
+        dataframes::df_to_file,
+        ColumnEncoding, ColumnType, CsvDialect, Datatype, FileFormat, FileOutput, RoundTripError,
+        RpcClient, RpcSource, Source, SourcifyClient, Table, TokenDecimals, Vm,
+        DEFAULT_SOURCIFY_URL, XLSX_MAX_ROWS,
+    },
+};
+
+/// datatypes checked by `check_all_datasets`
+const DATATYPES: [Datatype; 27] = [
+    Datatype::AddressActivity,
+    Datatype::AddressLifespans,
+    Datatype::ApprovalsForAll,
+    Datatype::BalanceDiffs,
+    Datatype::BeaconDeposits,
+    Datatype::Blocks,
+    Datatype::BridgeTransfers,
+    Datatype::CodeDiffs,
+    Datatype::Erc1155Transfers,
+    Datatype::Erc20Approvals,
+    Datatype::Erc20Transfers,
+    Datatype::GasPercentiles,
+    Datatype::Genesis,
+    Datatype::Logs,
+    Datatype::MulticallCalls,
+    Datatype::NonceDiffs,
+    Datatype::RawRpc,
+    Datatype::SafeTransactions,
+    Datatype::StarknetBlocks,
+    Datatype::StarknetTransactions,
+    Datatype::Transactions,
+    Datatype::Traces,
+    Datatype::StorageDiffs,
+    Datatype::Validators,
+    Datatype::VmTraces,
+    Datatype::WethWraps,
+    Datatype::ZkSyncTransactions,
+];
+
+/// file formats checked by `check_schema_roundtrip`
+const FORMATS: [FileFormat; 3] = [FileFormat::Parquet, FileFormat::Csv, FileFormat::Json];
+
+/// check that random rows of every column of every built-in dataset survive every output format
+pub fn check_all_datasets(n_rows: usize, seed: u64, dir: &str) -> Result<(), RoundTripError> {
+    for datatype in DATATYPES.iter() {
+        for format in FORMATS.iter() {
+            let encoding = match format {
+                FileFormat::Parquet => ColumnEncoding::Binary,
+                _ => ColumnEncoding::Hex,
+            };
+            let all = Some(vec!["all".to_string()]);
+            let schema = datatype
+                .table_schema(&encoding, &None, &None, &all, None)
+                .map_err(|_e| RoundTripError::SchemaError(datatype.dataset().name().to_string()))?;
+            let df = random_dataframe(&schema, n_rows, seed)?;
+            roundtrip_dataframe(&df, datatype.dataset().name(), format, dir)?;
+        }
+    }
+    Ok(())
+}
+
+/// check that random rows of a schema survive every output format
+///
+/// binary columns should use hex encoding for csv and json schemas, as cryo does when writing
+pub fn check_schema_roundtrip(
+    schema: &Table,
+    n_rows: usize,
+    seed: u64,
+    dir: &str,
+) -> Result<(), RoundTripError> {
+    let df = random_dataframe(schema, n_rows, seed)?;
+    for format in FORMATS.iter() {
+        roundtrip_dataframe(&df, schema.datatype.dataset().name(), format, dir)?;
+    }
+    Ok(())
+}
+
+/// generate dataframe of random rows matching schema, including edge values and nulls
+///
+/// uint32, binary, and hex columns are built with the conversions used by datasets
+pub fn random_dataframe(
+    schema: &Table,
+    n_rows: usize,
+    seed: u64,
+) -> Result<DataFrame, RoundTripError> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut cols = Vec::new();
+    for name in schema.columns() {
+        let ctype =
+            schema.column_type(name).ok_or(RoundTripError::SchemaError(name.to_string()))?;
+        push_random_series(&mut cols, schema, name, ctype, n_rows, &mut rng)?;
+    }
+    Ok(DataFrame::new(cols)?)
+}
+
+/// output options for files written by the harness, which overwrite earlier files
+pub fn file_output(dir: &str, format: &FileFormat) -> FileOutput {
+    FileOutput {
+        output_dir: dir.to_string(),
+        prefix: "roundtrip".to_string(),
+        suffix: None,
+        overwrite: true,
+        format: format.clone(),
+        row_group_size: None,
+        parquet_statistics: true,
+        parquet_compression: ParquetCompression::Lz4Raw,
+        parquet_dictionary_encoding: false,
+        max_output_rows: None,
+        max_output_bytes: None,
+        prompt_on_output_limit: false,
+        prompt_on_failure: false,
+        min_free_space: None,
+        verbose: false,
+        partition_by: None,
+        lineage: None,
+        signer: None,
+        decimal_scale: None,
+        nested_traces: false,
+        deterministic: false,
+        rollup_epoch: None,
+        csv: CsvDialect::default(),
+        xlsx_max_rows: XLSX_MAX_ROWS,
+        count_only: false,
+        allow_lossy: false,
+        stats_sidecars: false,
+    }
+}
+
+/// source serving rpc responses from an offline source, without concurrency or rate limits
+pub fn offline_source<S: RpcSource + 'static>(rpc: S, chain_id: u64) -> Source {
+    Source {
+        provider: Arc::new(Provider::new(RpcClient::offline(rpc))),
+        semaphore: None,
+        metadata_semaphore: None,
+        rate_limiter: None,
+        shared_rate_limiter: None,
+        rate_jitter: Jitter::default(),
+        chain_id,
+        inner_request_size: 1,
+        max_concurrent_chunks: 1,
+        sourcify: Arc::new(SourcifyClient::new(DEFAULT_SOURCIFY_URL)),
+        token_decimals: Arc::new(TokenDecimals::default()),
+        beacon: None,
+        vm: Vm::default(),
+        local_logs: None,
+        genesis: None,
+        raw_rpc: None,
+        chunk_timeout: None,
+        audit_fraction: None,
+    }
+}
+
+/// write dataframe to a file of the given format, read it back, and compare
+pub fn roundtrip_dataframe(
+    df: &DataFrame,
+    name: &str,
+    format: &FileFormat,
+    dir: &str,
+) -> Result<(), RoundTripError> {
+    std::fs::create_dir_all(dir).map_err(|_e| RoundTripError::ReadError(dir.to_string()))?;
+    let path = format!("{}/roundtrip__{}.{}", dir, name, format.as_str());
+    let file_output = file_output(dir, format);
+    df_to_file(&mut df.clone(), &path, &file_output)?;
+
+    let read_error = |_e: PolarsError| RoundTripError::ReadError(path.clone());
+    let file = std::fs::File::open(&path).map_err(|_e| RoundTripError::ReadError(path.clone()))?;
+    let read = match format {
+        FileFormat::Parquet => ParquetReader::new(file).finish().map_err(read_error)?,
+        FileFormat::Csv => CsvReader::new(file)
+            .has_header(true)
+            .with_dtypes(Some(Arc::new(df.schema())))
+            .finish()
+            .map_err(read_error)?,
+        FileFormat::Json => {
+            let read = JsonReader::new(file)
+                .with_json_format(JsonFormat::Json)
+                .finish()
+                .map_err(read_error)?;
+            read_json_uint64_columns(read, df, &path)?
+        }
+        // xlsx and sqlite outputs are not read back
+        #[cfg(feature = "xlsx")]
+        FileFormat::Xlsx => return Err(RoundTripError::ReadError(path.clone())),
+        #[cfg(feature = "sqlite")]
+        FileFormat::Sqlite => return Err(RoundTripError::ReadError(path.clone())),
+    };
+    let _ = std::fs::remove_file(&path);
+
+    for original in df.get_columns() {
+        let mismatch = || RoundTripError::Mismatch {
+            dataset: name.to_string(),
+            format: format.as_str(),
+            column: original.name().to_string(),
+        };
+        let column = read.column(original.name()).map_err(|_e| mismatch())?;
+        let column = column.cast(original.dtype()).map_err(|_e| mismatch())?;
+        if !series_equal(original, &column) {
+            return Err(mismatch())
+        }
+    }
+    Ok(())
+}
+
+/// replace uint64 columns read by polars with values parsed by serde_json
+///
+/// the json reader of polars infers every integer as int64, so uint64 values above i64::MAX do
+/// not survive it even though the written file holds them exactly
+fn read_json_uint64_columns(
+    mut read: DataFrame,
+    df: &DataFrame,
+    path: &str,
+) -> Result<DataFrame, RoundTripError> {
+    let names: Vec<&str> = df
+        .get_columns()
+        .iter()
+        .filter(|column| column.dtype() == &DataType::UInt64)
+        .map(|column| column.name())
+        .collect();
+    if names.is_empty() {
+        return Ok(read)
+    }
+    let read_error = || RoundTripError::ReadError(path.to_string());
+    let contents = std::fs::read(path).map_err(|_e| read_error())?;
+    let rows: Vec<serde_json::Map<String, serde_json::Value>> =
+        serde_json::from_slice(&contents).map_err(|_e| read_error())?;
+    for name in names {
+        let values: Vec<Option<u64>> =
+            rows.iter().map(|row| row.get(name).and_then(|value| value.as_u64())).collect();
+        read.with_column(Series::new(name, values)).map_err(|_e| read_error())?;
+    }
+    Ok(read)
+}
+
+/// compare series including nulls, polars does not implement equality of decimal series
+fn series_equal(left: &Series, right: &Series) -> bool {
+    match (left.decimal(), right.decimal()) {
+        (Ok(left), Ok(right)) if left.dtype() == right.dtype() => {
+            let (left, right): (&Int128Chunked, &Int128Chunked) = (left, right);
+            left.into_iter().eq(right)
+        }
+        (Ok(_), Ok(_)) => false,
+        _ => left.series_equal_missing(right),
+    }
+}
+
+fn push_random_series(
+    cols: &mut Vec<Series>,
+    schema: &Table,
+    name: &str,
+    ctype: ColumnType,
+    n_rows: usize,
+    rng: &mut StdRng,
+) -> Result<(), RoundTripError> {
+    let series = match ctype {
+        ColumnType::UInt32 => {
+            let edges = [0, 1, u32::MAX as u64];
+            let values = random_values(n_rows, rng, &edges, |rng| rng.gen::<u32>() as u64);
+            with_series_u32!(cols, name, values, schema);
+            return Ok(())
+        }
+        ColumnType::UInt64 => {
+            let edges = [0, 1, u32::MAX as u64 + 1, i64::MAX as u64 + 1, u64::MAX];
+            Series::new(name, random_values(n_rows, rng, &edges, |rng| rng.gen::<u64>()))
+        }
+        ColumnType::Int32 => {
+            let edges = [0, -1, i32::MIN, i32::MAX];
+            Series::new(name, random_values(n_rows, rng, &edges, |rng| rng.gen::<i32>()))
+        }
+        ColumnType::Int64 => {
+            let edges = [0, -1, i64::MIN, i64::MAX];
+            Series::new(name, random_values(n_rows, rng, &edges, |rng| rng.gen::<i64>()))
+        }
+        ColumnType::Float64 => {
+            // 1.157920892373162e77 is the largest u256, e.g. a float conversion of a wei value
+            let edges = [0.0, -0.0, 1e-18, 1e18, 1.157920892373162e77, f64::MAX];
+            Series::new(name, random_values(n_rows, rng, &edges, |rng| rng.gen::<f64>() * 1e24))
+        }
+        ColumnType::Boolean => {
+            Series::new(name, random_values(n_rows, rng, &[false, true], |rng| rng.gen::<bool>()))
+        }
+        ColumnType::Decimal128 => {
+            let edges = [
+                "0".to_string(),
+                u64::MAX.to_string(),
+                u128::MAX.to_string(),
+                ethers::types::U256::MAX.to_string(),
+            ];
+            let values = random_values(n_rows, rng, &edges, |rng| {
+                let mut bytes = [0u8; 32];
+                let start = rng.gen_range(0..32);
+                rng.fill(&mut bytes[start..]);
+                ethers::types::U256::from_big_endian(&bytes).to_string()
+            });
+            Series::new(name, values)
+        }
+        ColumnType::String => {
+            let edges = [
+                String::new(),
+                "a,b".to_string(),
+                "\"quoted\"".to_string(),
+                "multi\nline".to_string(),
+                "unicode ❄️".to_string(),
+            ];
+            let values = random_values(n_rows, rng, &edges, |rng| {
+                let length = rng.gen_range(0..32);
+                (0..length).map(|_| rng.gen_range(' '..='~')).collect::<String>()
+            });
+            Series::new(name, values)
+        }
+        ColumnType::Binary | ColumnType::Hex => {
+            let edges = [vec![], vec![0u8; 20], vec![255u8; 32]];
+            let values = random_values(n_rows, rng, &edges, random_bytes);
+            with_series_binary!(cols, name, values, schema);
+            return Ok(())
+        }
+    };
+    cols.push(series);
+    Ok(())
+}
+
+/// random values with edge values at the start and roughly one tenth nulls
+fn random_values<T: Clone>(
+    n_rows: usize,
+    rng: &mut StdRng,
+    edges: &[T],
+    mut random: impl FnMut(&mut StdRng) -> T,
+) -> Vec<Option<T>> {
+    (0..n_rows)
+        .map(|i| match i {
+            i if i < edges.len() => Some(edges[i].clone()),
+            _ if rng.gen_ratio(1, 10) => None,
+            _ => Some(random(rng)),
+        })
+        .collect()
+}
+
+fn random_bytes(rng: &mut StdRng) -> Vec<u8> {
+    let length = [0, 4, 20, 32, 64][rng.gen_range(0..5)];
+    (0..length).map(|_| rng.gen()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::dataframes::{cast_decimal_columns, nest_traces, sort_deterministic};
+
+    fn test_dir(name: &str) -> String {
+        let dir =
+            std::env::temp_dir().join(format!("cryo_testing_{}_{}", name, std::process::id()));
+        dir.to_string_lossy().into_owned()
+    }
+
+    fn all_columns(datatype: Datatype, encoding: ColumnEncoding) -> Table {
+        let all = Some(vec!["all".to_string()]);
+        let sort = Some(datatype.dataset().default_sort());
+        datatype.table_schema(&encoding, &None, &None, &all, sort).unwrap()
+    }
+
+    #[test]
+    fn all_datasets_roundtrip() {
+        let dir = test_dir("all");
+        check_all_datasets(64, 0, &dir).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn uint32_columns_are_narrowed() {
+        let schema = all_columns(Datatype::Blocks, ColumnEncoding::Binary);
+        let df = random_dataframe(&schema, 16, 0).unwrap();
+        assert_eq!(df.column("number").unwrap().dtype(), &DataType::UInt32);
+        assert_eq!(df.column("hash").unwrap().dtype(), &DataType::Binary);
+
+        let schema = all_columns(Datatype::Blocks, ColumnEncoding::Hex);
+        let df = random_dataframe(&schema, 16, 0).unwrap();
+        let hashes = df.column("hash").unwrap().utf8().unwrap();
+        assert!(hashes.into_iter().flatten().all(|hash| hash.starts_with("0x")));
+    }
+
+    #[test]
+    fn deterministic_sort_ignores_collection_order() {
+        let dir = test_dir("sort");
+        for datatype in DATATYPES.iter() {
+            let schema = all_columns(*datatype, ColumnEncoding::Binary);
+            let df = random_dataframe(&schema, 64, 1).unwrap();
+            let sorted = sort_deterministic(df.clone(), Some(&schema)).unwrap();
+            let reversed = sort_deterministic(df.reverse(), Some(&schema)).unwrap();
+            assert!(sorted.frame_equal_missing(&reversed), "{}", datatype.dataset().name());
+            let name = datatype.dataset().name();
+            roundtrip_dataframe(&sorted, name, &FileFormat::Parquet, &dir).unwrap();
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn decimal_columns_roundtrip() {
+        let dir = test_dir("decimal");
+        // with the python feature of polars, unified in workspace builds, decimals are read back
+        // as float64 unless beta decimal types are activated
+        std::env::set_var("POLARS_ACTIVATE_DECIMAL", "1");
+        for datatype in [Datatype::Erc20Transfers, Datatype::Erc1155Transfers, Datatype::Genesis] {
+            let schema = all_columns(datatype, ColumnEncoding::Binary);
+            let df = random_dataframe(&schema, 64, 2).unwrap();
+            let df = cast_decimal_columns(df, &schema, 18).unwrap();
+            let name = datatype.dataset().name();
+            roundtrip_dataframe(&df, name, &FileFormat::Parquet, &dir).unwrap();
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn nested_traces_roundtrip() {
+        let dir = test_dir("nested");
+        let schema = all_columns(Datatype::Traces, ColumnEncoding::Binary);
+        let df = random_dataframe(&schema, 64, 3).unwrap();
+        let nested = nest_traces(df).unwrap();
+        assert!(nested.column("calls").is_ok());
+        roundtrip_dataframe(&nested, "traces", &FileFormat::Parquet, &dir).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,72 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use ethers::prelude::*;
+
+use crate::types::{CollectError, RequestPriority, Source};
+
+/// selector of erc20 decimals()
+const DECIMALS_SELECTOR: [u8; 4] = [0x31, 0x3c, 0xe5, 0x67];
+
+/// cache of erc20 token decimals, fetched once per token per run
+///
+/// tokens without a valid decimals() are cached as None
+#[derive(Debug, Default)]
+pub struct TokenDecimals {
+    cache: Mutex<HashMap<H160, Option<u8>>>,
+}
+
+impl TokenDecimals {
+    /// get decimals of each token, calling decimals() on tokens not yet cached
+    pub async fn get(
+        &self,
+        tokens: &[H160],
+        source: &Source,
+    ) -> Result<HashMap<H160, Option<u8>>, CollectError> {
+        let unchecked: Vec<H160> = {
+            let cache = self.cache.lock().expect("token decimals lock");
+            let mut unchecked: Vec<H160> =
+                tokens.iter().filter(|token| !cache.contains_key(token)).copied().collect();
+            unchecked.sort();
+            unchecked.dedup();
+            unchecked
+        };
+        let fetched = futures::future::try_join_all(
+            unchecked.iter().map(|token| fetch_decimals(*token, source)),
+        )
+        .await?;
+        self.cache.lock().expect("token decimals lock").extend(unchecked.into_iter().zip(fetched));
+        let cache = self.cache.lock().expect("token decimals lock");
+        Ok(tokens.iter().map(|token| (*token, cache.get(token).copied().flatten())).collect())
+    }
+}
+
+async fn fetch_decimals(token: H160, source: &Source) -> Result<Option<u8>, CollectError> {
+    let _permit = source.permit_request(RequestPriority::Metadata).await;
+    let request = TransactionRequest::new().to(token).data(Bytes::from(DECIMALS_SELECTOR.to_vec()));
+    match source.provider.call(&request.into(), None).await {
+        // tokens commonly revert or return nothing when decimals() is not implemented
+        Ok(output) if output.len() == 32 => {
+            let decimals = U256::from_big_endian(&output);
+            Ok(if decimals <= U256::from(77) { Some(decimals.as_u32() as u8) } else { None })
+        }
+        Ok(_) => Ok(None),
+        Err(e) if RpcError::as_error_response(&e).is_some() => Ok(None),
+        Err(e) => Err(CollectError::rpc("eth_call", e)),
+    }
+}
+
+/// format integer amount as a decimal string with the given number of decimals, e.g. 1.5
+pub fn format_decimal(value: U256, decimals: u8) -> String {
+    let digits = value.to_string();
+    let decimals = decimals as usize;
+    if decimals == 0 {
+        return digits
+    }
+    let digits = format!("{:0>width$}", digits, width = decimals + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - decimals);
+    let fraction = fraction.trim_end_matches('0');
+    match fraction.is_empty() {
+        true => whole.to_string(),
+        false => format!("{}.{}", whole, fraction),
+    }
+}
@@ -0,0 +1,158 @@
+use std::{
+    collections::BTreeMap,
+    io::{BufRead, Write},
+    path::{Path, PathBuf},
+};
+
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+
+use crate::types::{FileError, RpcClient};
+
+/// environment variable overriding the path of the usage ledger
+pub const USAGE_LEDGER_ENV: &str = "CRYO_USAGE_LEDGER";
+
+/// requests and credits of one run against one provider
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct UsageEntry {
+    /// fingerprint of the rpc endpoint, see `endpoint_fingerprint`
+    pub provider: String,
+    /// unix time at the end of the run
+    pub timestamp: u64,
+    /// number of requests, including failed requests
+    pub n_requests: u64,
+    /// credits charged, zero if credits were not tracked
+    pub credits: u64,
+    /// usage of each method
+    pub methods: BTreeMap<String, MethodUsage>,
+}
+
+/// requests and credits of one method
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default)]
+pub struct MethodUsage {
+    /// number of requests
+    pub n_requests: u64,
+    /// credits charged
+    pub credits: u64,
+}
+
+impl UsageEntry {
+    /// usage of a client over a run, none if it sent no requests
+    pub fn from_client(provider: &str, client: &RpcClient) -> Option<UsageEntry> {
+        let mut methods: BTreeMap<String, MethodUsage> = BTreeMap::new();
+        for (method, stats) in client.stats() {
+            methods.entry(method).or_default().n_requests = stats.n_requests;
+        }
+        for (method, credits) in client.credits_spent() {
+            methods.entry(method).or_default().credits = credits;
+        }
+        if methods.is_empty() {
+            return None
+        }
+        Some(UsageEntry {
+            provider: provider.to_string(),
+            timestamp: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            n_requests: methods.values().map(|usage| usage.n_requests).sum(),
+            credits: methods.values().map(|usage| usage.credits).sum(),
+            methods,
+        })
+    }
+}
+
+/// append-only ledger of the usage of each run, stored as json lines and shared by all runs of
+/// a user, so usage can be summed across jobs sharing a provider's quota
+#[derive(Debug)]
+pub struct UsageLedger {
+    path: PathBuf,
+}
+
+impl UsageLedger {
+    /// ledger at a path
+    pub fn new<P: AsRef<Path>>(path: P) -> UsageLedger {
+        UsageLedger { path: path.as_ref().to_path_buf() }
+    }
+
+    /// ledger at the path of CRYO_USAGE_LEDGER, or at ~/.cryo/usage.jsonl
+    pub fn default_ledger() -> Option<UsageLedger> {
+        if let Some(path) = std::env::var_os(USAGE_LEDGER_ENV) {
+            return Some(UsageLedger::new(path))
+        }
+        let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+        Some(UsageLedger::new(Path::new(&home).join(".cryo").join("usage.jsonl")))
+    }
+
+    /// path of the ledger file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// append the usage of a run, locking the file against concurrent runs
+    pub fn record(&self, entry: &UsageEntry) -> Result<(), FileError> {
+        if let Some(parent) = self.path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).map_err(|_e| FileError::FileWriteError)?;
+        }
+        let line = serde_json::to_string(entry).map_err(|_e| FileError::FileWriteError)?;
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .map_err(|_e| FileError::FileWriteError)?;
+        file.lock_exclusive().map_err(|_e| FileError::FileWriteError)?;
+        let result = file.write_all(format!("{}\n", line).as_bytes());
+        let _ = file.unlock();
+        result.map_err(|_e| FileError::FileWriteError)
+    }
+
+    /// read the usage of all recorded runs
+    ///
+    /// malformed lines are skipped, e.g. the truncated last line of a run killed while appending
+    pub fn read(&self) -> Result<Vec<UsageEntry>, FileError> {
+        let file = match std::fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(_) => return Ok(Vec::new()),
+        };
+        let mut entries = Vec::new();
+        for line in std::io::BufReader::new(file).lines() {
+            let line = line.map_err(|e| FileError::FileReadError(e.to_string()))?;
+            if line.trim().is_empty() {
+                continue
+            }
+            if let Ok(entry) = serde_json::from_str(&line) {
+                entries.push(entry);
+            }
+        }
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_skips_malformed_lines() {
+        let path = std::env::temp_dir().join(format!("cryo_usage_{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let ledger = UsageLedger::new(&path);
+        let entry = UsageEntry {
+            provider: "abc".to_string(),
+            timestamp: 1,
+            n_requests: 2,
+            credits: 3,
+            methods: BTreeMap::new(),
+        };
+        ledger.record(&entry).unwrap();
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .and_then(|mut file| file.write_all(b"{\"provider\": \"ab"))
+            .unwrap();
+        let entries = ledger.read().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].n_requests, 2);
+    }
+}
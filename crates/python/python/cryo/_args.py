@@ -32,6 +32,10 @@ def parse_cli_args(
         kwargs['json'] = True
     elif file_format == 'csv':
         kwargs['csv'] = True
+    elif file_format == 'xlsx':
+        kwargs['xlsx'] = True
+    elif file_format == 'sqlite':
+        kwargs['sqlite'] = True
     # elif file_format == 'avro':
     #     kwargs['avro'] = True
     else:
@@ -11,6 +11,8 @@ if typing.TYPE_CHECKING:
         Literal['parquet'],
         Literal['csv'],
         Literal['json'],
+        Literal['xlsx'],
+        Literal['sqlite'],
         Literal['avro'],
     ]
     PythonOutput = Union[
@@ -29,20 +31,59 @@ if typing.TYPE_CHECKING:
         exclude_columns: typing.Sequence[str] | None
         columns: typing.Sequence[str] | None
         hex: bool
+        exact: bool
+        wide_integers: bool
         sort: typing.Sequence[str] | None
         rpc: str | None
         network_name: str | None
         requests_per_second: int | None
+        rps: list[str] | None
+        rate_burst: int | None
+        rate_jitter: int | None
+        shared_rate_limit: str | None
+        credit_costs: list[str] | None
+        max_credits: int | None
         max_concurrent_requests: int | None
         max_concurrent_chunks: int | None
+        circuit_breaker: int | None
+        circuit_cooldown: float | None
+        max_retries: int | None
+        max_retry_after: float | None
+        chunk_timeout: float | None
+        audit_determinism: float | None
+        prompt_on_failure: bool
+        follow: bool
+        follow_interval: float | None
+        follow_max_concurrent_requests: int | None
+        follow_ws_url: str | None
+        max_block_lag: int | None
+        max_head_stall: float | None
+        alert_webhook: str | None
+        exit_on_alert: bool
+        record_reorgs: bool
+        tip: bool
+        preconfirmed: bool
         dry: bool
+        count_only: bool
+        emit_empty_schema: bool
         chunk_size: int | None
         n_chunks: int | None
+        chunk_strategy: str | None
+        adaptive_chunks: int | None
         output_dir: str | None
         file_suffix: str | None
         overwrite: bool
         csv: bool
         json: bool
+        xlsx: bool
+        sqlite: bool
+        allow_lossy: bool
+        xlsx_max_rows: int
+        csv_delimiter: str
+        csv_quote_style: str
+        no_csv_header: bool
+        csv_rfc4180: bool
+        csv_gzip: bool
         row_group_size: int | None
         n_row_groups: int | None
         no_stats: bool
@@ -53,5 +94,46 @@ if typing.TYPE_CHECKING:
         topic2: str | bytes | None
         topic3: str | bytes | None
         inner_request_size: int | None
+        debug_rpc: str | None
+        record_rpc: str | None
+        replay_rpc: str | None
+        mock_rpc: bool
+        max_output_rows: int | None
+        max_output_bytes: str | None
+        min_free_space: str | None
+        allow_mixed_chains: bool
+        deterministic: bool
+        rollups: Optional[int]
+        stats_sidecars: bool
+        sign_key: str | None
+        sign_with: str
+        dictionary_encoding: bool
+        decimal: int | None
+        nested_traces: bool
+        collapse_delegatecalls: bool
+        partition_by: Optional[str]
+        max_partitions: int
+        chunks: Optional[str]
+        last: Optional[str]
+        sourcify_url: Optional[str]
+        filter_spam: bool
+        spam_contracts: Optional[str]
+        spam_min_recipients: int
+        prune_receipt_logs: bool
+        tokens: Optional[str]
+        exclude_tokens: Optional[str]
+        addresses: Optional[str]
+        rpc_method: Optional[str]
+        rpc_params: Optional[str]
+        beacon_url: Optional[str]
+        genesis_file: Optional[str]
+        logs_dir: Optional[str]
+        local_threads: Optional[int]
+        local_max_memory: Optional[str]
+        vm: Optional[str]
+        lenient: bool
+        provider_profile: Optional[str]
         no_verbose: bool
+        config: Optional[str]
+        profile: Optional[str]
 
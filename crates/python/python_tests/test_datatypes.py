@@ -17,6 +17,17 @@ datatypes = [
     'storage_diffs',
     'code_diffs',
     'vm_traces',
+    'gas_percentiles',
+    'multicall_calls',
+    'address_activity',
+    'erc20_transfers',
+    'erc20_approvals',
+    'approvals_for_all',
+    'erc1155_transfers',
+    'weth_wraps',
+    'bridge_transfers',
+    'safe_transactions',
+    'beacon_deposits',
 ]
 
 block_ranges = [['17_000_000:17_000_010']]
@@ -36,7 +47,7 @@ def test_datatype(datatype, blocks):
 @pytest.mark.parametrize('datatype', datatypes)
 @pytest.mark.parametrize('blocks', block_ranges)
 def test_datatype_hex(datatype, blocks):
-    if datatype == 'vm_traces':
+    if datatype in ['vm_traces', 'gas_percentiles']:
         pytest.skip('')
         return
     df = cryo.collect(datatype, blocks=blocks, hex=False)
@@ -18,20 +18,59 @@ use cryo_cli::{run, Args};
         exclude_columns = None,
         columns = None,
         hex = false,
+        exact = false,
+        wide_integers = false,
         sort = None,
         rpc = None,
         network_name = None,
         requests_per_second = None,
+        rps = None,
+        rate_burst = None,
+        rate_jitter = None,
+        shared_rate_limit = None,
+        credit_costs = None,
+        max_credits = None,
         max_concurrent_requests = None,
         max_concurrent_chunks = None,
+        circuit_breaker = None,
+        circuit_cooldown = None,
+        max_retries = None,
+        max_retry_after = None,
+        chunk_timeout = None,
+        audit_determinism = None,
+        prompt_on_failure = false,
+        follow = false,
+        follow_interval = None,
+        follow_max_concurrent_requests = None,
+        follow_ws_url = None,
+        max_block_lag = None,
+        max_head_stall = None,
+        alert_webhook = None,
+        exit_on_alert = false,
+        record_reorgs = false,
+        tip = false,
+        preconfirmed = false,
         dry = false,
+        count_only = false,
+        emit_empty_schema = false,
         chunk_size = 1000,
         n_chunks = None,
+        chunk_strategy = None,
+        adaptive_chunks = None,
         output_dir = ".".to_string(),
         file_suffix = None,
         overwrite = false,
         csv = false,
         json = false,
+        xlsx = false,
+        sqlite = false,
+        allow_lossy = false,
+        xlsx_max_rows = 100_000,
+        csv_delimiter = ",".to_string(),
+        csv_quote_style = "necessary".to_string(),
+        no_csv_header = false,
+        csv_rfc4180 = false,
+        csv_gzip = false,
         row_group_size = None,
         n_row_groups = None,
         no_stats = false,
@@ -42,7 +81,48 @@ use cryo_cli::{run, Args};
         topic2 = None,
         topic3 = None,
         inner_request_size = 1,
+        debug_rpc = None,
+        record_rpc = None,
+        replay_rpc = None,
+        mock_rpc = false,
+        max_output_rows = None,
+        max_output_bytes = None,
+        min_free_space = None,
+        allow_mixed_chains = false,
+        deterministic = false,
+        rollups = None,
+        stats_sidecars = false,
+        sign_key = None,
+        sign_with = "minisign".to_string(),
+        dictionary_encoding = false,
+        decimal = None,
+        nested_traces = false,
+        collapse_delegatecalls = false,
+        partition_by = None,
+        max_partitions = 100,
+        chunks = None,
+        last = None,
+        sourcify_url = None,
+        filter_spam = false,
+        spam_contracts = None,
+        spam_min_recipients = 1000,
+        prune_receipt_logs = false,
+        tokens = None,
+        exclude_tokens = None,
+        addresses = None,
+        rpc_method = None,
+        rpc_params = None,
+        beacon_url = None,
+        genesis_file = None,
+        logs_dir = None,
+        local_threads = None,
+        local_max_memory = None,
+        vm = None,
+        lenient = false,
+        provider_profile = None,
         no_verbose = false,
+        config = None,
+        profile = None,
     )
 )]
 #[allow(clippy::too_many_arguments)]
@@ -57,20 +137,59 @@ pub fn _freeze(
     exclude_columns: Option<Vec<String>>,
     columns: Option<Vec<String>>,
     hex: bool,
+    exact: bool,
+    wide_integers: bool,
     sort: Option<Vec<String>>,
     rpc: Option<String>,
     network_name: Option<String>,
     requests_per_second: Option<u32>,
+    rps: Option<Vec<String>>,
+    rate_burst: Option<u32>,
+    rate_jitter: Option<u64>,
+    shared_rate_limit: Option<String>,
+    credit_costs: Option<Vec<String>>,
+    max_credits: Option<u64>,
     max_concurrent_requests: Option<u64>,
     max_concurrent_chunks: Option<u64>,
+    circuit_breaker: Option<u32>,
+    circuit_cooldown: Option<f64>,
+    max_retries: Option<u32>,
+    max_retry_after: Option<f64>,
+    chunk_timeout: Option<f64>,
+    audit_determinism: Option<f64>,
+    prompt_on_failure: bool,
+    follow: bool,
+    follow_interval: Option<f64>,
+    follow_max_concurrent_requests: Option<u64>,
+    follow_ws_url: Option<String>,
+    max_block_lag: Option<u64>,
+    max_head_stall: Option<f64>,
+    alert_webhook: Option<String>,
+    exit_on_alert: bool,
+    record_reorgs: bool,
+    tip: bool,
+    preconfirmed: bool,
     dry: bool,
+    count_only: bool,
+    emit_empty_schema: bool,
     chunk_size: u64,
     n_chunks: Option<u64>,
+    chunk_strategy: Option<String>,
+    adaptive_chunks: Option<u64>,
     output_dir: String,
     file_suffix: Option<String>,
     overwrite: bool,
     csv: bool,
     json: bool,
+    xlsx: bool,
+    sqlite: bool,
+    allow_lossy: bool,
+    xlsx_max_rows: usize,
+    csv_delimiter: String,
+    csv_quote_style: String,
+    no_csv_header: bool,
+    csv_rfc4180: bool,
+    csv_gzip: bool,
     row_group_size: Option<usize>,
     n_row_groups: Option<usize>,
     no_stats: bool,
@@ -81,7 +200,48 @@ pub fn _freeze(
     topic2: Option<String>,
     topic3: Option<String>,
     inner_request_size: u64,
+    debug_rpc: Option<String>,
+    record_rpc: Option<String>,
+    replay_rpc: Option<String>,
+    mock_rpc: bool,
+    max_output_rows: Option<u64>,
+    max_output_bytes: Option<String>,
+    min_free_space: Option<String>,
+    allow_mixed_chains: bool,
+    deterministic: bool,
+    rollups: Option<u64>,
+    stats_sidecars: bool,
+    sign_key: Option<String>,
+    sign_with: String,
+    dictionary_encoding: bool,
+    decimal: Option<u32>,
+    nested_traces: bool,
+    collapse_delegatecalls: bool,
+    partition_by: Option<String>,
+    max_partitions: usize,
+    chunks: Option<String>,
+    last: Option<String>,
+    sourcify_url: Option<String>,
+    filter_spam: bool,
+    spam_contracts: Option<String>,
+    spam_min_recipients: usize,
+    prune_receipt_logs: bool,
+    tokens: Option<String>,
+    exclude_tokens: Option<String>,
+    addresses: Option<String>,
+    rpc_method: Option<String>,
+    rpc_params: Option<String>,
+    beacon_url: Option<String>,
+    genesis_file: Option<String>,
+    logs_dir: Option<String>,
+    local_threads: Option<usize>,
+    local_max_memory: Option<String>,
+    vm: Option<String>,
+    lenient: bool,
+    provider_profile: Option<String>,
     no_verbose: bool,
+    config: Option<String>,
+    profile: Option<String>,
 ) -> PyResult<&PyAny> {
     let args = Args {
         datatype,
@@ -93,20 +253,61 @@ pub fn _freeze(
         exclude_columns,
         columns,
         hex,
+        exact,
+        wide_integers,
         sort,
         rpc,
         network_name,
         requests_per_second,
+        rps,
+        rate_burst,
+        rate_jitter,
+        shared_rate_limit,
+        credit_costs,
+        max_credits,
+        no_usage_ledger: true,
+        record_usage: false,
         max_concurrent_requests,
         max_concurrent_chunks,
+        circuit_breaker,
+        circuit_cooldown,
+        max_retries,
+        max_retry_after,
+        chunk_timeout,
+        audit_determinism,
+        prompt_on_failure,
+        follow,
+        follow_interval,
+        follow_max_concurrent_requests,
+        follow_ws_url,
+        max_block_lag,
+        max_head_stall,
+        alert_webhook,
+        exit_on_alert,
+        record_reorgs,
+        tip,
+        preconfirmed,
         dry,
+        count_only,
+        emit_empty_schema,
         chunk_size,
         n_chunks,
+        chunk_strategy,
+        adaptive_chunks,
         output_dir,
         file_suffix,
         overwrite,
         csv,
         json,
+        xlsx,
+        sqlite,
+        allow_lossy,
+        xlsx_max_rows,
+        csv_delimiter,
+        csv_quote_style,
+        no_csv_header,
+        csv_rfc4180,
+        csv_gzip,
         row_group_size,
         n_row_groups,
         no_stats,
@@ -117,7 +318,49 @@ pub fn _freeze(
         topic2,
         topic3,
         inner_request_size,
+        debug_rpc,
+        record_rpc,
+        replay_rpc,
+        mock_rpc,
+        max_output_rows,
+        max_output_bytes,
+        min_free_space,
+        allow_mixed_chains,
+        deterministic,
+        rollups,
+        stats_sidecars,
+        sign_key,
+        sign_with,
+        dictionary_encoding,
+        decimal,
+        nested_traces,
+        collapse_delegatecalls,
+        partition_by,
+        max_partitions,
+        chunks,
+        last,
+        sourcify_url,
+        filter_spam,
+        spam_contracts,
+        spam_min_recipients,
+        prune_receipt_logs,
+        tokens,
+        exclude_tokens,
+        addresses,
+        rpc_method,
+        rpc_params,
+        beacon_url,
+        genesis_file,
+        logs_dir,
+        local_threads,
+        local_max_memory,
+        vm,
+        lenient,
+        provider_profile,
         no_verbose,
+        config,
+        profile,
+        markdown: false,
     };
 
     pyo3_asyncio::tokio::future_into_py(py, async move {
@@ -130,12 +373,20 @@ pub fn _freeze(
                     paths.set_item(key, values).unwrap();
                 }
                 let paths = paths.to_object(py);
+                let row_counts = PyDict::new(py);
+                for (key, counts) in &result.row_counts {
+                    let counts: Vec<(&str, u64)> =
+                        counts.iter().map(|(chunk, n_rows)| (chunk.as_str(), *n_rows)).collect();
+                    row_counts.set_item(key.dataset().name(), counts.into_py_dict(py)).unwrap();
+                }
+                let row_counts = row_counts.to_object(py);
 
                 let dict = [
                     ("n_completed".to_string(), result.n_completed.into_py(py)),
                     ("n_skipped".to_string(), result.n_skipped.into_py(py)),
                     ("n_errored".to_string(), result.n_errored.into_py(py)),
                     ("paths".to_string(), paths),
+                    ("row_counts".to_string(), row_counts),
                 ]
                 .into_py_dict(py);
                 Ok(dict.to_object(py))